@@ -0,0 +1,102 @@
+//! `cda`'s cross-reference on a synthetic analysis: 10k modules in 100
+//! directories, each with a few public exports and imports of earlier
+//! modules, some of them re-exported, plus external imports and a few
+//! cycles.
+//!
+//! Run with `cargo bench --bench cross_reference`.
+
+use codebase_deep_analyzer::core::Analysis;
+use serde_json::json;
+use std::time::{Duration, Instant};
+
+const MODULES: usize = 10_000;
+const EXPORTS_PER_MODULE: usize = 5;
+const IMPORTS_PER_MODULE: usize = 8;
+const RUNS: u32 = 5;
+
+fn synthetic_analysis() -> Analysis {
+    let mut rng = fastrand::Rng::with_seed(7);
+    let mut analysis = Analysis::default();
+    analysis.modules = (0..MODULES)
+        .map(|i| {
+            let mut exports: Vec<_> = (0..EXPORTS_PER_MODULE)
+                .map(|k| {
+                    json!({
+                        "name": format!("item_{}_{}", i, k),
+                        "kind": "function",
+                        "signature": null,
+                        "description": if k == 0 { "" } else { "Does things" },
+                        "line_number": k * 10 + 1,
+                    })
+                })
+                .collect();
+            let mut imports = Vec::new();
+            for n in 0..IMPORTS_PER_MODULE {
+                // Mostly earlier modules; every 500th module closes a cycle
+                let target = if i % 500 == 499 && n == 0 {
+                    i - 498
+                } else if i == 0 {
+                    continue;
+                } else {
+                    rng.usize(0..i)
+                };
+                let item = format!("item_{}_{}", target, rng.usize(0..EXPORTS_PER_MODULE));
+                if n == 1 {
+                    exports.push(json!({
+                        "name": item,
+                        "kind": "function",
+                        "signature": null,
+                        "description": "",
+                        "line_number": 100,
+                    }));
+                }
+                imports.push(json!({
+                    "source": format!("d{}/m{}", target / 100, target),
+                    "items": [item],
+                    "is_external": false,
+                }));
+            }
+            imports.push(json!({
+                "source": format!("pkg{}/sub", i % 50),
+                "items": ["thing"],
+                "is_external": true,
+            }));
+            serde_json::from_value(json!({
+                "path": format!("d{}/m{}.rs", i / 100, i),
+                "language": "rust",
+                "exports": exports,
+                "imports": imports,
+                "summary": "",
+                "has_deep_analysis": false,
+                "is_test_module": i % 100 == 99,
+            }))
+            .unwrap()
+        })
+        .collect();
+    analysis
+}
+
+fn main() {
+    let analysis = synthetic_analysis();
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+
+    let mut total = Duration::ZERO;
+    let mut best = Duration::MAX;
+    for _ in 0..RUNS {
+        let started = Instant::now();
+        let crossref = runtime
+            .block_on(codebase_deep_analyzer::cross_reference(&analysis))
+            .unwrap();
+        let elapsed = started.elapsed();
+        std::hint::black_box(crossref);
+        total += elapsed;
+        best = best.min(elapsed);
+    }
+    println!(
+        "cross_reference, {} modules: best {:.1?}, mean {:.1?} over {} runs",
+        MODULES,
+        best,
+        total / RUNS,
+        RUNS
+    );
+}
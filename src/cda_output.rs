@@ -0,0 +1,596 @@
+//! Typed `analysis.json`, for tools that read cda's output
+//!
+//! These types mirror the JSON that `cda analyze --format json` writes, and
+//! nothing else: with `default-features = false, features = ["cda-output"]`
+//! they build on serde alone. They change in lockstep with
+//! [`SCHEMA_VERSION`], which is the version the generator writes.
+//!
+//! Files from an older 1.x schema load with the fields they lack left empty.
+//! The parts of the schema that few tools read (timings, CI pipelines,
+//! Kubernetes manifests, Helm charts and API specs) are kept as JSON values.
+//!
+//! ```no_run
+//! use codebase_deep_analyzer::cda_output::{OutputDocument, Severity};
+//!
+//! let doc = OutputDocument::from_path("docs/analysis.json")?;
+//! if let Some(module) = doc.module("src/main.rs") {
+//!     println!("{}", module.summary);
+//! }
+//! for (module, export) in doc.exports_of_kind("function") {
+//!     println!("{}: {}", module.path, export.name);
+//! }
+//! let errors = doc.gaps_with_severity(Severity::Error).count();
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Version of the analysis.json layout these types describe. The major
+/// version changes when a change would stop older readers from loading it.
+pub const SCHEMA_VERSION: &str = "1.37";
+
+/// A whole `analysis.json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct OutputDocument {
+    /// Schema version the file was written with
+    pub version: String,
+    #[serde(default)]
+    pub run: Run,
+    pub architecture_overview: Option<String>,
+    pub modules: Vec<Module>,
+    #[serde(default)]
+    pub config_files: Vec<ConfigFile>,
+    #[serde(default)]
+    pub doc_files: Vec<DocFile>,
+    #[serde(default)]
+    pub commands: Vec<RunCommand>,
+    #[serde(default)]
+    pub ci: Vec<Value>,
+    #[serde(default)]
+    pub kubernetes: Vec<Value>,
+    #[serde(default)]
+    pub helm_charts: Vec<Value>,
+    #[serde(default)]
+    pub api_specs: Vec<Value>,
+    pub cross_reference: CrossReference,
+    #[serde(default)]
+    pub statistics: Statistics,
+}
+
+impl OutputDocument {
+    /// Read an `analysis.json`. Files of another major schema version are
+    /// refused with both versions named.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        content
+            .parse()
+            .with_context(|| format!("Failed to load {}", path.display()))
+    }
+
+    /// The module at `path`, relative to the analysis root
+    pub fn module(&self, path: &str) -> Option<&Module> {
+        let path = path.trim_start_matches("./");
+        self.modules.iter().find(|m| m.path == path)
+    }
+
+    /// Exports of `kind` (`function`, `struct`, ...) in every module, with
+    /// the module each is in
+    pub fn exports_of_kind<'a>(
+        &'a self,
+        kind: &'a str,
+    ) -> impl Iterator<Item = (&'a Module, &'a Export)> + 'a {
+        self.modules
+            .iter()
+            .flat_map(|m| m.exports.iter().map(move |e| (m, e)))
+            .filter(move |(_, e)| e.kind == kind)
+    }
+
+    /// Gaps of one severity, in the order they are listed
+    pub fn gaps_with_severity(&self, severity: Severity) -> impl Iterator<Item = &Gap> {
+        self.cross_reference
+            .gaps
+            .iter()
+            .filter(move |g| g.severity == Some(severity))
+    }
+
+    /// Gaps grouped by severity, most severe first. Gaps from before schema
+    /// 1.21 have no severity and are left out.
+    pub fn gaps_by_severity(&self) -> Vec<(Severity, Vec<&Gap>)> {
+        [Severity::Error, Severity::Warning, Severity::Info]
+            .into_iter()
+            .map(|severity| (severity, self.gaps_with_severity(severity).collect()))
+            .filter(|(_, gaps): &(_, Vec<_>)| !gaps.is_empty())
+            .collect()
+    }
+}
+
+impl FromStr for OutputDocument {
+    type Err = anyhow::Error;
+
+    fn from_str(content: &str) -> Result<Self> {
+        let value: Value = serde_json::from_str(content).context("Not valid JSON")?;
+        let version = value.get("version").and_then(Value::as_str).context(
+            "No schema version; is it an analysis.json from `cda analyze --format json`?",
+        )?;
+        anyhow::ensure!(
+            major(version) == major(SCHEMA_VERSION),
+            "Schema version {} can't be read by these types, which read version {}.x",
+            version,
+            major(SCHEMA_VERSION)
+        );
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+fn major(version: &str) -> &str {
+    version.split('.').next().unwrap_or("")
+}
+
+/// How the analysis was scoped
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Run {
+    /// Languages analyzed; empty means all
+    pub language_filter: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc_language: Option<String>,
+    /// Only a sample of the source files was analyzed
+    #[serde(default)]
+    pub partial: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample: Option<Sample>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timings: Option<Value>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped_files: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub renames: Vec<Rename>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<RunWarning>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Sample {
+    pub strategy: String,
+    pub sampled_files: usize,
+    pub total_files: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Rename {
+    pub from: String,
+    pub to: String,
+    /// The content didn't change
+    pub exact: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct RunWarning {
+    /// e.g. `overview-missing` or `parse-timeout`
+    pub kind: String,
+    pub message: String,
+}
+
+/// A source file, or several documented together
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Module {
+    pub path: String,
+    pub language: String,
+    pub summary: String,
+    pub has_deep_analysis: bool,
+    #[serde(default)]
+    pub is_test_module: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding_warning: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ownership: Option<Ownership>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skip_reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_version: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_template: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub modified_during_analysis: bool,
+    /// Every file of a module made of several, `path` first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+    /// `full`, `truncated`, `static-only`, ...; missing before schema 1.14
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completeness: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub behaviors: Vec<Behavior>,
+    /// The LLM prompt had the summaries of neighboring modules; missing
+    /// before schema 1.33
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub neighbor_context: bool,
+    /// Flags the module's conditions check; missing before schema 1.34
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub feature_flags: Vec<Flag>,
+    /// Why a deep run chose or skipped the module, when `--deep-select` or
+    /// `--max-llm-calls` chose; missing before schema 1.36
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selection: Option<Selection>,
+    /// What its LLM analysis took; missing before schema 1.37
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub llm_usage: Option<LlmUsage>,
+    pub exports: Vec<Export>,
+    pub imports: Vec<Import>,
+}
+
+impl Module {
+    /// The export named `name`
+    pub fn export(&self, name: &str) -> Option<&Export> {
+        self.exports.iter().find(|e| e.name == name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Ownership {
+    pub contributors: Vec<Contributor>,
+    pub last_modified: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub owners: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Contributor {
+    pub name: String,
+    pub email: String,
+    pub commits: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Selection {
+    pub score: u32,
+    /// E.g. `entry point (+40)`
+    pub factors: Vec<String>,
+    pub selected: bool,
+    /// `selected`, `resumed`, `over budget`, `barrel file`, `generated`,
+    /// `empty` or `not selected`
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct LlmUsage {
+    pub duration_ms: u64,
+    /// Requests sent, retries and chunks included
+    pub calls: usize,
+    /// `None` when the provider didn't report them
+    pub input_tokens: Option<u64>,
+    pub output_tokens: Option<u64>,
+    pub model: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct ModuleCost {
+    pub path: String,
+    #[serde(flatten)]
+    pub usage: LlmUsage,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Behavior {
+    pub test: String,
+    pub path: String,
+    pub line: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub statement: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Export {
+    pub name: String,
+    /// `function`, `class`, `type`, `const`, `enum`, `trait`, `struct`, ...
+    pub kind: String,
+    pub signature: Option<String>,
+    pub description: String,
+    pub line: usize,
+    /// `private` for items collected with `--include-private`; absent for
+    /// public items
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub visibility: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+    /// File of a multi-file module the item is in, when it isn't the
+    /// module's `path`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(default)]
+    pub function_signature: Option<FunctionSignature>,
+    /// Condition the item is compiled or run under
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cfg: Option<String>,
+    /// Uses of the item elsewhere in the codebase
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub examples: Vec<Example>,
+}
+
+impl Export {
+    pub fn is_public(&self) -> bool {
+        self.visibility.as_deref().is_none_or(|v| v == "public")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Example {
+    pub path: String,
+    pub line: usize,
+    pub snippet: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct FunctionSignature {
+    pub params: Vec<Param>,
+    pub return_type: Option<String>,
+    pub generics: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Param {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Import {
+    pub source: String,
+    pub items: Vec<String>,
+    pub external: bool,
+    /// `type-only`, `re-export` or `side-effect`; absent for ordinary imports
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct ConfigFile {
+    pub path: String,
+    /// `build`, `ci`, `container` or `lint`
+    pub kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct DocFile {
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct RunCommand {
+    pub command: String,
+    /// `cargo-bin`, `npm-script`, `make-target`, ...
+    pub kind: String,
+    pub source: String,
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub inferred: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub in_ci: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct CrossReference {
+    pub dependencies: Vec<Dependency>,
+    #[serde(default)]
+    pub edges: Vec<Edge>,
+    pub external_deps: Vec<String>,
+    /// Missing before schema 1.32
+    #[serde(default)]
+    pub external_dependencies: Vec<ExternalDependency>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub integrations: Vec<Integration>,
+    /// Missing before schema 1.34
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<FeatureFlag>,
+    pub gaps: Vec<Gap>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub gaps_by_owner: Vec<OwnerGaps>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Dependency {
+    pub module: String,
+    pub depends_on: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Edge {
+    pub from_module: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_export: Option<String>,
+    pub to_module: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to_export: Option<String>,
+    /// `import`, `call`, `re-export`, `type-only` or `side-effect`
+    pub kind: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct ExternalDependency {
+    pub package: String,
+    /// Modules importing the package
+    pub used_by: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Integration {
+    pub package: String,
+    pub category: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service: Option<String>,
+    pub modules: Vec<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub inferred: bool,
+}
+
+/// A flag some code checks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Flag {
+    /// `cargo` or `env`
+    pub kind: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct FeatureFlag {
+    /// `cargo` or `env`
+    pub kind: String,
+    pub name: String,
+    /// The Cargo.toml declaring a Cargo feature
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub declared_in: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub enables: Vec<String>,
+    #[serde(default)]
+    pub modules: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exports: Vec<GatedExport>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct GatedExport {
+    pub module: String,
+    pub export: String,
+    pub cfg: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Gap {
+    pub kind: String,
+    /// Missing before schema 1.21
+    #[serde(default)]
+    pub severity: Option<Severity>,
+    pub description: String,
+    pub location: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+}
+
+/// How much a gap matters, least first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct OwnerGaps {
+    pub owner: String,
+    pub gaps: usize,
+    pub by_severity: BTreeMap<String, usize>,
+    pub by_kind: BTreeMap<String, usize>,
+}
+
+/// Missing fields of older files are 0
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct Statistics {
+    pub total_modules: usize,
+    pub total_exports: usize,
+    pub test_modules: usize,
+    pub external_dependencies: usize,
+    pub potential_gaps: usize,
+    pub llm_analyzed_modules: usize,
+    /// Up to 10 modules, most tokens first, then calls and time; missing
+    /// before schema 1.37
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub most_expensive_modules: Vec<ModuleCost>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn golden(name: &str) -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/golden")
+            .join(name)
+            .join("analysis.json")
+    }
+
+    #[test]
+    fn test_older_schemas_load() {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/outputs");
+        for version in ["1.0", "1.13"] {
+            let doc =
+                OutputDocument::from_path(fixtures.join(version).join("analysis.json")).unwrap();
+            assert_eq!(doc.version, version);
+            assert!(doc.cross_reference.external_dependencies.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_accessors() {
+        let doc = OutputDocument::from_path(golden("deep")).unwrap();
+        let parser = doc.module("./src/parser.rs").unwrap();
+        assert_eq!(parser.path, "src/parser.rs");
+        assert!(doc.module("src/missing.rs").is_none());
+
+        let functions: Vec<_> = doc.exports_of_kind("function").collect();
+        assert!(!functions.is_empty());
+        assert!(functions.iter().all(|(_, e)| e.kind == "function"));
+        let (module, export) = functions[0];
+        assert_eq!(module.export(&export.name), Some(export));
+
+        let by_severity = doc.gaps_by_severity();
+        let grouped: usize = by_severity.iter().map(|(_, gaps)| gaps.len()).sum();
+        assert_eq!(grouped, doc.cross_reference.gaps.len());
+        assert!(by_severity.windows(2).all(|w| w[0].0 > w[1].0));
+    }
+
+    #[test]
+    fn test_other_major_version_is_refused() {
+        let err = r#"{"version": "2.0", "modules": []}"#.parse::<OutputDocument>().unwrap_err();
+        assert!(err.to_string().contains("Schema version 2.0"), "{}", err);
+        assert!("{}".parse::<OutputDocument>().is_err());
+    }
+}
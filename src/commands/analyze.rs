@@ -1,9 +1,24 @@
 use anyhow::Result;
-use indicatif::{ProgressBar, ProgressStyle};
-use std::path::Path;
-use tracing::{debug, info};
+use clap::ValueEnum;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{debug, info, warn};
 
-use crate::core::{analyzer, discovery};
+use crate::commands::clean;
+use crate::core::analyzer::{self, ParseOptions, ResumeMode, RunWarning, SampleInfo, WarningKind};
+use crate::core::discovery::{self, Detection, DiscoveryOptions, Language};
+use crate::core::exit::{CompletedWithWarnings, UsageError};
+use crate::core::gate::{self, Baseline, GapThresholds, GapsExceeded};
+use crate::core::renames::{self, Rename};
+use crate::core::runs::{self, RunEntry, RunStats};
+use crate::core::selection::{self, DeepSelect};
+use crate::core::timings::Timings;
+use crate::core::{behaviors, integrations, orphans, pairing, run_commands};
+use crate::core::{git, ownership, Analysis, FileInventory, SourceFile};
+use crate::events::{self, Event};
+use crate::logging::{self, Phase};
+use crate::output::manifest::{self, ArtifactKind, Manifest};
 use crate::output::{self, Format};
 
 pub struct AnalyzeArgs {
@@ -12,106 +27,847 @@ pub struct AnalyzeArgs {
     pub module: Option<String>,
     pub provider: String,
     pub model: Option<String>,
-    pub parallelism: usize,
-    pub deep: bool, // Per-file LLM analysis (slow)
+    /// Files parsed concurrently
+    pub parse_jobs: usize,
+    /// LLM requests in flight at once
+    pub llm_concurrency: usize,
+    /// Record contributors and CODEOWNERS owners per module
+    pub ownership: bool,
+    /// `[owners]` team of each path pattern, used with CODEOWNERS to
+    /// attribute gaps
+    pub owners: BTreeMap<String, String>,
+    /// Private items and snippets to collect along with the exports
+    pub parse_options: ParseOptions,
+    pub mode: AnalysisMode,
+    pub check_model: bool,
+    /// Start the deep loop without the provider's pre-flight request
+    pub skip_preflight: bool,
+    pub resume: ResumeMode,
+    /// Keep resumed files made with an older prompt version
+    pub keep_stale_prompts: bool,
+    /// Resume state to adopt before the run, see [`analyzer::import_state`]
+    pub import_state: Option<String>,
+    /// Deep-analyze only files changed since this git ref
+    pub changed_only: Option<String>,
+    /// Deep-analyze only the files listed in this file, or on stdin for `-`
+    pub files_from: Option<String>,
+    /// Also refresh importers of changed or listed files
+    pub dependents: bool,
+    /// Gap limits checked after cross-referencing
+    pub fail_on_gaps: Option<GapThresholds>,
+    /// Only count gaps missing from this analysis.json
+    pub baseline: Option<String>,
     pub format: Format,
+    pub discovery: DiscoveryOptions,
+    /// Only analyze this many source files
+    pub max_files: Option<usize>,
+    /// How to pick them; `None` picks by fan-in when a previous
+    /// analysis.json exists, otherwise the largest files
+    pub sample: Option<SampleStrategy>,
+    /// Order the deep loop works through the files in
+    pub order: FileOrder,
+    /// Which files the deep loop analyzes, see [`selection::select`]
+    pub deep_select: DeepSelect,
+    /// Most files the deep loop sends to the LLM
+    pub max_llm_calls: Option<usize>,
+    /// Seed for `--sample random` and `--order random`
+    pub seed: Option<u64>,
+    /// Write into `<output>/runs/<timestamp>-<tag>/` and record the run in
+    /// the runs index
+    pub tag: Option<String>,
+    /// Print the timing report at the end
+    pub timings: bool,
+    /// Write progress events as NDJSON to this file, or stdout for `-`
+    pub progress_json: Option<String>,
+    /// Remove stale artifacts of earlier runs instead of flagging them
+    pub prune: bool,
+    /// Keep the output of source files that no longer exist
+    pub keep_stale: bool,
+    /// Document header/source pairs and Rust directory modules file by file
+    pub no_pairing: bool,
+    /// Only generate the architecture overview again, from the output's
+    /// analysis.json
+    pub only_overview: bool,
+    /// What a `--module` run does with the output of a broader earlier run
+    pub module_output: ModuleOutput,
+}
+
+/// What a `--module` run does with output an earlier run left for files
+/// outside the module
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ModuleOutput {
+    /// Write the module's output over it and warn that the result is mixed
+    #[default]
+    Overlay,
+    /// Update the module's part of the earlier analysis.json and write the
+    /// output for the whole of it
+    Merge,
+    /// Remove the rest, so the output covers the module alone
+    Replace,
+}
+
+/// How much of the analysis is done by the LLM
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum AnalysisMode {
+    /// Tree-sitter only; no LLM calls and no API key needed
+    Static,
+    /// Static analysis plus one LLM call for the architecture overview
+    #[default]
+    Standard,
+    /// An LLM call per source file, plus the overview (slow)
+    Deep,
+}
+
+impl AnalysisMode {
+    /// What the mode will call the LLM for, shown at startup
+    fn describe(self, provider: &str) -> String {
+        match self {
+            AnalysisMode::Static => "static (no LLM calls)".to_string(),
+            AnalysisMode::Standard => format!(
+                "standard (1 {} call for the architecture overview; --mode static for none)",
+                provider
+            ),
+            AnalysisMode::Deep => format!(
+                "deep (1 {} call per source file, plus the architecture overview and \
+                 third-party integrations)",
+                provider
+            ),
+        }
+    }
+}
+
+/// How `--max-files` picks the files to analyze
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SampleStrategy {
+    /// Largest files first
+    Largest,
+    /// Most imported modules first
+    FanIn,
+    /// Uniformly at random (see --seed)
+    Random,
+}
+
+impl SampleStrategy {
+    pub fn name(self) -> &'static str {
+        match self {
+            SampleStrategy::Largest => "largest",
+            SampleStrategy::FanIn => "fan-in",
+            SampleStrategy::Random => "random",
+        }
+    }
+
+    /// The sample is the first `--max-files` files in this order
+    fn order(self) -> FileOrder {
+        match self {
+            SampleStrategy::Largest => FileOrder::Size,
+            SampleStrategy::FanIn => FileOrder::FanIn,
+            SampleStrategy::Random => FileOrder::Random,
+        }
+    }
+}
+
+/// Order `--mode deep` and `--changed-only` send files to the LLM in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum FileOrder {
+    /// Modules before those importing them, from a static pre-pass, so
+    /// their summaries are ready for the importers' prompts
+    #[default]
+    Dependencies,
+    /// Largest files first
+    Size,
+    /// Most imported modules first, ranked by a static pre-pass
+    FanIn,
+    /// By path, as discovered
+    Path,
+    /// Shuffled (see --seed)
+    Random,
+}
+
+impl FileOrder {
+    pub fn name(self) -> &'static str {
+        match self {
+            FileOrder::Dependencies => "dependencies",
+            FileOrder::Size => "size",
+            FileOrder::FanIn => "fan-in",
+            FileOrder::Path => "path",
+            FileOrder::Random => "random",
+        }
+    }
+
+    /// The order needs the import graph before the deep loop
+    fn needs_prepass(self) -> bool {
+        matches!(self, FileOrder::Dependencies | FileOrder::FanIn)
+    }
 }
 
 pub async fn run(args: AnalyzeArgs) -> Result<()> {
+    if let Some(target) = &args.progress_json {
+        events::open(target)?;
+        events::emit(Event::RunStarted {
+            command: "analyze",
+            version: env!("CARGO_PKG_VERSION"),
+            path: &args.path,
+            output: &args.output,
+        });
+    }
     let path = Path::new(&args.path).canonicalize()?;
-    let output_path = Path::new(&args.output);
+    // A file is analyzed on its own, with its directory as the root
+    let single_file = path.is_file();
+    let root = match path.parent() {
+        Some(parent) if single_file => parent.to_path_buf(),
+        _ => path.clone(),
+    };
+    if single_file && args.module.is_some() {
+        return Err(
+            UsageError::new("--module selects a directory, so it needs a directory path").into(),
+        );
+    }
+    let module = args
+        .module
+        .as_deref()
+        .map(|m| discovery::resolve_module(&path, m))
+        .transpose()?
+        .filter(|m| !m.is_empty());
+    let started_at = std::time::SystemTime::now();
+    let output_dir = match &args.tag {
+        Some(tag) => runs::run_dir(
+            Path::new(&args.output),
+            tag,
+            started_at,
+            args.resume == ResumeMode::Resume,
+        )?,
+        None => PathBuf::from(&args.output),
+    };
+    let output_path = output_dir.as_path();
 
-    info!("Analyzing codebase at: {}", path.display());
+    if single_file {
+        info!("Analyzing file: {}", path.display());
+    } else {
+        info!("Analyzing codebase at: {}", path.display());
+    }
     info!("Output directory: {}", output_path.display());
+    info!("Mode: {}", args.mode.describe(&args.provider));
+    if args.mode == AnalysisMode::Deep && !analyzer::deep_mode().sends_source() {
+        info!("Deep mode: signatures only; no source is sent to the LLM");
+    }
+    info!(
+        "Parse jobs: {}, LLM concurrency: {}",
+        args.parse_jobs, args.llm_concurrency
+    );
+
+    if args.mode == AnalysisMode::Static {
+        if args.changed_only.is_some() {
+            return Err(UsageError::new(
+                "--changed-only re-runs LLM analysis on changed files, so it can't be used with --mode static",
+            )
+            .into());
+        }
+        if args.files_from.is_some() {
+            return Err(UsageError::new(
+                "--files-from re-runs LLM analysis on the listed files, so it can't be used with --mode static",
+            )
+            .into());
+        }
+        if args.check_model {
+            return Err(
+                UsageError::new("--check-model has nothing to check with --mode static").into(),
+            );
+        }
+    }
+
+    let incremental = args.changed_only.is_some() || args.files_from.is_some();
+    if args.import_state.is_some() && args.mode != AnalysisMode::Deep && !incremental {
+        return Err(UsageError::new(
+            "--import-state only applies to --mode deep, --changed-only and --files-from",
+        )
+        .into());
+    }
+    if args.dependents && !incremental {
+        return Err(UsageError::new(
+            "--dependents only applies to --changed-only and --files-from",
+        )
+        .into());
+    }
+    // Read before the long phases, so a missing list fails right away
+    let listed = args.files_from.as_deref().map(read_file_list).transpose()?;
+
+    // The output of another cda is only built on if this one can read it
+    if args.resume != ResumeMode::Force {
+        output::compat::check(output_path)?;
+    }
+
+    if args.only_overview {
+        if args.mode == AnalysisMode::Static {
+            return Err(UsageError::new(
+                "--only-overview asks the LLM for the overview, so it can't be used with --mode static",
+            )
+            .into());
+        }
+        return only_overview(&args, output_path).await;
+    }
+
+    // Without credentials, every file of a deep run would fail in turn after
+    // discovery, and a standard run would fail at the overview
+    if args.mode != AnalysisMode::Static {
+        crate::llm::check_credentials(&args.provider)?;
+    }
+
+    if args.check_model {
+        let provider = crate::llm::get_provider(&args.provider, args.model.as_deref())?;
+        crate::llm::check_model(provider.as_ref()).await?;
+        info!(
+            "Model {} is available from {}",
+            provider.model(),
+            provider.name()
+        );
+    }
+
+    // Read the baseline before the output (which may be the same file) is rewritten
+    let baseline = args
+        .baseline
+        .as_deref()
+        .map(|p| Baseline::from_path(Path::new(p)))
+        .transpose()?;
+    let merge_into = match &module {
+        Some(module) if args.module_output == ModuleOutput::Merge => {
+            let json = analysis_json(output_path)?;
+            if !json.is_file() {
+                return Err(UsageError::new(format!(
+                    "--merge needs the analysis.json of an earlier run in {}; \
+                     run `cda analyze -f json` over the whole project first",
+                    output_path.display()
+                ))
+                .into());
+            }
+            let (prior, _) = output::load(&json)?;
+            Some((module.clone(), prior))
+        }
+        _ => None,
+    };
 
     // Create output directory
     std::fs::create_dir_all(output_path)?;
 
-    // Set up progress bar
-    let spinner_style = ProgressStyle::default_spinner()
-        .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈ ")
-        .template("{prefix:.bold.dim} {spinner} {wide_msg}")?;
+    if args.resume == ResumeMode::Force {
+        let pages = clean::generated_pages(output_path, Manifest::load(output_path)?.as_ref());
+        analyzer::remove_state(output_path)?;
+        for page in &pages {
+            std::fs::remove_file(page)?;
+        }
+        let modules_dir = output_path.join("modules");
+        if modules_dir.is_dir() {
+            clean::remove_empty_dirs(&modules_dir)?;
+        }
+        info!(
+            "--force: removed progress and {} module pages from {}",
+            pages.len(),
+            output_path.display()
+        );
+    }
+
+    let started = std::time::Instant::now();
+    let timings = Timings::default();
 
     // Phase 1: Discovery
-    let discovery_pb = ProgressBar::new_spinner();
-    discovery_pb.set_style(spinner_style.clone());
-    discovery_pb.set_prefix("[1/4]");
-    discovery_pb.set_message("Discovering files...");
-    discovery_pb.enable_steady_tick(std::time::Duration::from_millis(100));
-
-    let inventory = discovery::discover(&path, args.module.as_deref()).await?;
-
-    discovery_pb.finish_with_message(format!(
-        "Found {} files ({} source, {} config, {} docs)",
-        inventory.total_files(),
-        inventory.source_files.len(),
-        inventory.config_files.len(),
-        inventory.doc_files.len()
-    ));
+    let phase = Phase::start("discovery", "[1/4]", "Discovering files...");
 
-    // Phase 2: Module Analysis
-    let analysis_pb = ProgressBar::new_spinner();
-    analysis_pb.set_style(spinner_style.clone());
-    analysis_pb.set_prefix("[2/4]");
+    // Don't analyze what earlier runs wrote into the analyzed path
+    let discovery_options = args
+        .discovery
+        .clone()
+        .excluding_output(Path::new(&args.output), &output_dir);
+    let mut inventory = if single_file {
+        discovery::discover_file(&path)?
+    } else {
+        discovery::discover(&path, module.as_deref(), &discovery_options).await?
+    };
 
-    // Default: fast static analysis. --deep enables slow per-file LLM analysis
-    let analysis = if args.deep {
-        analysis_pb.set_message(format!(
-            "Deep analysis with {} LLM (streaming to disk)...",
-            args.provider
-        ));
-        analysis_pb.enable_steady_tick(std::time::Duration::from_millis(100));
+    let filtered = if inventory.filtered_files > 0 {
+        format!(
+            ", {} filtered by --language {}",
+            inventory.filtered_files,
+            language_names(&args.discovery.languages)
+        )
+    } else {
+        String::new()
+    };
+    let set_aside = inventory.generated_files.len() + inventory.vendored_files.len();
+    let filtered = if set_aside > 0 {
+        format!("{}, {} generated or vendored", filtered, set_aside)
+    } else {
+        filtered
+    };
+    timings.phase(
+        "discovery",
+        phase.finish(format!(
+            "Found {} files ({} source, {} config, {} manifests, {} docs{})",
+            inventory.total_files(),
+            inventory.source_files.len(),
+            inventory.config_files.len(),
+            inventory.manifest_files(),
+            inventory.doc_files.len(),
+            filtered
+        )),
+    );
+    let sniffed = inventory
+        .source_files
+        .iter()
+        .filter(|f| matches!(f.detection, Detection::Shebang | Detection::Content))
+        .count();
+    if sniffed > 0 {
+        info!(
+            "Recognized {} file(s) without a known extension as source by their content",
+            sniffed
+        );
+    }
+    if !args.no_pairing {
+        let files = inventory.source_files.len();
+        inventory.source_files = pairing::pair(std::mem::take(&mut inventory.source_files));
+        let paired = files - inventory.source_files.len();
+        if paired > 0 {
+            info!(
+                "Paired {} files into the modules of their headers or mod.rs (--no-pairing to keep them apart)",
+                paired
+            );
+        }
+    }
+    if let Some(dir) = &args.import_state {
+        analyzer::import_state(Path::new(dir), output_path, &inventory)?;
+    }
+    // The other files of an earlier run aren't gone when one file is analyzed
+    let renames = if single_file {
+        Vec::new()
+    } else {
+        analyzer::migrate_progress(output_path, &inventory.root)?;
+        detect_renames(&inventory, output_path)
+    };
+    let mut prune = args.prune;
+    if let Some(module) = &module {
+        let outside = outside_module(output_path, module, &inventory)?;
+        if !outside.is_empty() {
+            match args.module_output {
+                ModuleOutput::Overlay => warn!(
+                    "{} holds the output of an earlier run beyond --module {} ({}); this run \
+                     rewrites the index and statistics for {} alone. Pass --merge to update \
+                     that module within the earlier analysis, or --replace to remove the rest",
+                    output_path.display(),
+                    module,
+                    outside,
+                    module
+                ),
+                ModuleOutput::Merge => info!(
+                    "Merging --module {} into the earlier analysis ({})",
+                    module, outside
+                ),
+                ModuleOutput::Replace => {
+                    info!(
+                        "--replace: removing the output of the earlier run beyond --module {} ({})",
+                        module, outside
+                    );
+                    prune = true;
+                }
+            }
+        }
+    }
+
+    let llm_loop = args.mode == AnalysisMode::Deep || incremental;
+    if args.order != FileOrder::default() && !llm_loop {
+        return Err(UsageError::new(
+            "--order only applies to --mode deep, --changed-only and --files-from",
+        )
+        .into());
+    }
+    if args.mode != AnalysisMode::Deep
+        && (args.deep_select != DeepSelect::All || args.max_llm_calls.is_some())
+    {
+        return Err(
+            UsageError::new("--deep-select and --max-llm-calls only apply to --mode deep").into(),
+        );
+    }
+    let sample_strategy = args.max_files.map(|_| match args.sample {
+        Some(strategy) => strategy,
+        None if output_path.join("analysis.json").exists() => SampleStrategy::FanIn,
+        None => SampleStrategy::Largest,
+    });
+    let seed = if sample_strategy == Some(SampleStrategy::Random) || args.order == FileOrder::Random
+    {
+        args.seed.unwrap_or_else(|| {
+            let seed = fastrand::u64(..);
+            info!(
+                "Shuffling with --seed {} (pass it to repeat this run)",
+                seed
+            );
+            seed
+        })
+    } else if args.seed.is_some() {
+        return Err(
+            UsageError::new("--seed only applies to --sample random and --order random").into(),
+        );
+    } else {
+        0
+    };
+
+    let (mut inventory, sample) = match (args.max_files, sample_strategy) {
+        (Some(max), Some(strategy)) => {
+            let dependencies = if strategy == SampleStrategy::FanIn {
+                Some(dependency_map(&inventory, output_path, args.parse_jobs).await?)
+            } else {
+                None
+            };
+            sample_inventory(inventory, max, strategy, dependencies.as_ref(), seed)
+        }
+        _ => (inventory, None),
+    };
+    if let Some(sample) = &sample {
+        info!(
+            "Partial run: analyzing {} of {} source files (--sample {})",
+            sample.sampled, sample.total, sample.strategy
+        );
+    }
+
+    // Ordering by imports needs the import graph before the deep loop; the
+    // static pass and its cross-reference are kept for phases 2 and 3
+    let mut prepass = None;
+    let mut prepass_crossref = None;
+    if llm_loop && args.order.needs_prepass() {
+        let message = match args.order {
+            FileOrder::FanIn => "Ranking files by fan-in...",
+            _ => "Ordering files by their imports...",
+        };
+        let phase = Phase::start("prepass", "[2/4]", message);
+        let result =
+            analyzer::analyze_static(&inventory, args.parse_jobs, args.parse_options, &timings)
+                .await?;
+        let crossref = analyzer::cross_reference(&result).await?;
+        timings.phase(
+            "prepass",
+            phase.finish(format!(
+                "Mapped {} dependencies before the deep loop",
+                crossref.dependencies.len()
+            )),
+        );
+        prepass = Some(result);
+        prepass_crossref = Some(crossref);
+    }
+    let prepass_ran = prepass_crossref.is_some();
 
+    // Modules are put back in discovery order afterwards, so the output
+    // doesn't depend on --order
+    let discovery_order: HashMap<String, usize> = inventory
+        .source_files
+        .iter()
+        .enumerate()
+        .map(|(i, f)| (f.path.clone(), i))
+        .collect();
+    if args.order != FileOrder::Path {
+        order_files(
+            &mut inventory.source_files,
+            args.order,
+            prepass_crossref.as_ref().map(|c| &c.dependencies),
+            seed,
+        );
+        debug!("Analyzing files in --order {}", args.order.name());
+    }
+    let neighbors = if llm_loop {
+        neighbors(
+            output_path,
+            prepass_crossref.as_ref().map(|c| &c.dependencies),
+        )
+        .map(Arc::new)
+    } else {
+        None
+    };
+
+    // A model that was never pulled, or a server without the memory for the
+    // context size, would otherwise fail the first file after all of the above
+    if llm_loop && !args.skip_preflight {
         let provider = crate::llm::get_provider(&args.provider, args.model.as_deref())?;
+        if provider.wants_preflight() {
+            crate::llm::preflight(provider.as_ref()).await?;
+            info!(
+                "Pre-flight request to {} with model {} succeeded",
+                provider.name(),
+                provider.model()
+            );
+        }
+    }
+
+    // Phase 2: Module Analysis
+    // Default: fast static analysis. --deep enables slow per-file LLM analysis
+    let mut deep_time = None;
+    let mut analysis = if incremental {
+        let phase = Phase::start(
+            "analysis",
+            "[2/4]",
+            match (&args.changed_only, &args.files_from) {
+                (Some(git_ref), _) => {
+                    format!("Deep analysis of files changed since {}...", git_ref)
+                }
+                _ => "Deep analysis of the listed files...".to_string(),
+            },
+        );
 
-        // Use streaming analysis - writes each module to disk immediately
-        let result = analyzer::analyze_streaming(
+        let mut prior = output::load_prior(output_path)?;
+        for rename in renames.iter().filter(|r| r.exact) {
+            if let Some(module) = prior.remove(&rename.from) {
+                prior.insert(rename.to.clone(), module);
+            }
+        }
+        let result = match prepass.take() {
+            Some(result) => result,
+            None => {
+                analyzer::analyze_static(&inventory, args.parse_jobs, args.parse_options, &timings)
+                    .await?
+            }
+        };
+        let mut targets = match (&args.changed_only, &listed) {
+            (Some(git_ref), _) => changed_targets(&root, git_ref, &inventory)?,
+            (None, Some(listed)) => {
+                let source = args.files_from.as_deref().unwrap_or("-");
+                listed_targets(&root, source, listed, &inventory)
+            }
+            (None, None) => unreachable!("incremental runs have a list of files"),
+        };
+        if args.dependents && !targets.is_empty() {
+            let crossref = analyzer::cross_reference(&result).await?;
+            let extra = analyzer::dependents(&crossref.dependencies, &targets);
+            info!("Refreshing {} dependent modules", extra.len());
+            targets.extend(extra);
+        }
+        // An unchanged file keeps its analysis under its new path
+        targets.retain(|t| !renames.iter().any(|r| r.exact && r.to == *t));
+
+        let provider = crate::llm::get_provider(&args.provider, args.model.as_deref())?;
+        let result = analyzer::analyze_incremental(
+            result,
             &inventory,
-            provider.as_ref(),
+            &targets,
+            provider.into(),
             output_path,
-            args.parallelism,
+            args.parse_jobs,
+            args.llm_concurrency,
+            args.parse_options,
+            &prior,
+            &timings,
+            neighbors,
         )
         .await?;
 
-        let llm_count = result
-            .modules
-            .iter()
-            .filter(|m| m.has_deep_analysis)
-            .count();
-        analysis_pb.finish_with_message(format!(
+        timings.phase(
+            "analysis",
+            phase.finish(format!(
+                "Re-analyzed {} modules, reused {} from the previous run",
+                targets.len(),
+                result
+                    .modules
+                    .iter()
+                    .filter(|m| !targets.contains(&m.path) && prior.contains_key(&m.path))
+                    .count()
+            )),
+        );
+
+        result
+    } else if args.mode == AnalysisMode::Deep {
+        let phase = Phase::start(
+            "analysis",
+            "[2/4]",
+            format!(
+                "Deep analysis with {} LLM (streaming to disk)...",
+                args.provider
+            ),
+        );
+
+        let provider = crate::llm::get_provider(&args.provider, args.model.as_deref())?;
+
+        let result = if args.deep_select == DeepSelect::All && args.max_llm_calls.is_none() {
+            // Use streaming analysis - writes each module to disk immediately
+            analyzer::analyze_streaming(
+                &inventory,
+                provider.into(),
+                output_path,
+                args.parse_jobs,
+                args.llm_concurrency,
+                args.parse_options,
+                args.resume,
+                args.keep_stale_prompts,
+                &timings,
+                neighbors,
+            )
+            .await?
+        } else {
+            let mut result = match prepass.take() {
+                Some(result) => result,
+                None => {
+                    analyzer::analyze_static(
+                        &inventory,
+                        args.parse_jobs,
+                        args.parse_options,
+                        &timings,
+                    )
+                    .await?
+                }
+            };
+            if prepass_crossref.is_none() {
+                prepass_crossref = Some(analyzer::cross_reference(&result).await?);
+            }
+            let dependencies = prepass_crossref
+                .as_ref()
+                .map(|c| &c.dependencies)
+                .expect("cross-reference computed above");
+            // Files an earlier run finished are kept, not analyzed again
+            let resumed: HashSet<String> = match args.resume {
+                ResumeMode::Resume => analyzer::progress_files(output_path).into_keys().collect(),
+                _ => HashSet::new(),
+            };
+            let targets = selection::select(
+                &mut result.modules,
+                &inventory.source_files,
+                dependencies,
+                args.deep_select,
+                args.max_llm_calls,
+                |size| analyzer::expected_calls(provider.as_ref(), size),
+                &resumed,
+            );
+            info!(
+                "Deep-analyzing {} of {} modules (--deep-select {}{})",
+                targets.len(),
+                result.modules.len(),
+                args.deep_select.name(),
+                args.max_llm_calls
+                    .map(|max| format!(", --max-llm-calls {}", max))
+                    .unwrap_or_default()
+            );
+            if targets.is_empty() {
+                result
+            } else {
+                analyzer::analyze_selected(
+                    result,
+                    &inventory,
+                    &targets,
+                    provider.into(),
+                    output_path,
+                    args.parse_jobs,
+                    args.llm_concurrency,
+                    args.parse_options,
+                    args.resume,
+                    args.keep_stale_prompts,
+                    &timings,
+                    neighbors,
+                )
+                .await?
+            }
+        };
+
+        let llm_count = result.llm_modules();
+        deep_time = Some(phase.finish(format!(
             "Analyzed {} modules ({} with LLM), found {} exports",
             result.modules.len(),
             llm_count,
             result.total_exports()
-        ));
+        )));
+        timings.phase("analysis", deep_time.unwrap_or_default());
 
         result
     } else {
-        analysis_pb.set_message("Analyzing modules (fast static analysis)...");
-        analysis_pb.enable_steady_tick(std::time::Duration::from_millis(100));
+        let phase = Phase::start(
+            "analysis",
+            "[2/4]",
+            "Analyzing modules (fast static analysis)...",
+        );
 
         debug!("Running fast static analysis (use --deep for per-file LLM)");
-        let result = analyzer::analyze_static(&inventory).await?;
+        let result =
+            analyzer::analyze_static(&inventory, args.parse_jobs, args.parse_options, &timings)
+                .await?;
 
-        analysis_pb.finish_with_message(format!(
-            "Analyzed {} modules, found {} exports",
-            result.modules.len(),
-            result.total_exports()
-        ));
+        timings.phase(
+            "analysis",
+            phase.finish(format!(
+                "Analyzed {} modules, found {} exports",
+                result.modules.len(),
+                result.total_exports()
+            )),
+        );
 
         result
     };
 
+    if args.order != FileOrder::Path {
+        analysis
+            .modules
+            .sort_by_key(|m| discovery_order.get(&m.path).copied().unwrap_or(usize::MAX));
+    }
+    analysis.language_filter = args.discovery.languages.clone();
+    analysis.doc_language = crate::llm::doc_language().map(String::from);
+    analysis.sample = sample;
+    analysis.renames = renames;
+    if !analysis.skipped_files.is_empty() {
+        info!(
+            "Skipped {} file(s) with binary content",
+            analysis.skipped_files.len()
+        );
+    }
+
+    let minified = analysis
+        .modules
+        .iter()
+        .filter(|m| m.skip_reason.is_some())
+        .count();
+    if minified > 0 {
+        info!(
+            "Summarized {} minified file(s) instead of analyzing them",
+            minified
+        );
+    }
+
+    let placeholders: Vec<_> = analysis.placeholders().map(|m| m.path.as_str()).collect();
+    if !placeholders.is_empty() {
+        warn!(
+            "{} resumed module(s) could not be loaded again and are left out of the statistics: {}",
+            placeholders.len(),
+            placeholders.join(", ")
+        );
+    }
+
+    if args.ownership {
+        let started = std::time::Instant::now();
+        add_ownership(&mut analysis, &root, output_path);
+        timings.phase("ownership", started.elapsed());
+    }
+
+    let merged = merge_into.is_some();
+    if let Some((module, prior)) = merge_into {
+        analysis = merge_module_run(analysis, prior, &module);
+    }
+
     // Phase 3: Cross-reference
-    let crossref_pb = ProgressBar::new_spinner();
-    crossref_pb.set_style(spinner_style.clone());
-    crossref_pb.set_prefix("[3/4]");
-    crossref_pb.set_message("Cross-referencing...");
-    crossref_pb.enable_steady_tick(std::time::Duration::from_millis(100));
+    let phase = Phase::start("cross_reference", "[3/4]", "Cross-referencing...");
 
-    // Always generate architecture overview with LLM (one quick call)
-    let provider = crate::llm::get_provider(&args.provider, args.model.as_deref())?;
-    let crossref = analyzer::cross_reference_with_llm(&analysis, provider.as_ref()).await?;
+    // The fan-in pre-pass parsed the same files, so its cross-reference holds
+    // unless other modules were merged in, or a file changed after it was
+    // parsed and its module was analyzed again with new imports
+    let changed = changed_since_discovery(&analysis, &inventory);
+    let crossref = match prepass_crossref.filter(|_| !merged && !changed) {
+        Some(crossref) => crossref,
+        None => analyzer::cross_reference(&analysis).await?,
+    };
+    // Outside static mode, one quick LLM call writes the architecture overview;
+    // deep mode also has the LLM classify packages the built-in table doesn't
+    // know, describe the project's commands and restate its tests as behavior
+    let crossref = if args.mode == AnalysisMode::Static {
+        crossref
+    } else {
+        let provider = crate::llm::get_provider(&args.provider, args.model.as_deref())?;
+        let mut crossref = crossref;
+        if args.mode == AnalysisMode::Deep {
+            integrations::add_inferred(&analysis, &mut crossref.integrations, provider.as_ref())
+                .await;
+            run_commands::describe(&mut analysis.commands, provider.as_ref()).await;
+            behaviors::describe(&mut analysis.modules, provider.as_ref()).await;
+        }
+        analyzer::add_architecture_overview(&mut analysis, crossref, provider.as_ref(), output_path)
+            .await
+    };
 
     let arch_status = if crossref.architecture_overview.is_some() {
         " + architecture overview"
@@ -119,24 +875,1154 @@ pub async fn run(args: AnalyzeArgs) -> Result<()> {
         ""
     };
 
-    crossref_pb.finish_with_message(format!(
-        "Mapped {} dependencies, found {} potential gaps{}",
-        crossref.dependencies.len(),
-        crossref.gaps.len(),
-        arch_status
-    ));
+    timings.phase(
+        "cross_reference",
+        phase.finish(format!(
+            "Mapped {} dependencies, found {} potential gaps{}",
+            crossref.dependencies.len(),
+            crossref.gaps.len(),
+            arch_status
+        )),
+    );
+
+    let mut crossref = crossref;
+    if let Some(owners) = ownership::PathOwners::load(&root, &args.owners) {
+        owners.attribute(&mut crossref.gaps);
+        let unassigned = crossref
+            .gaps
+            .iter()
+            .filter(|g| g.owner.as_deref() == Some(ownership::UNASSIGNED))
+            .count();
+        if unassigned > 0 {
+            info!(
+                "{} of {} gaps are in code no CODEOWNERS rule or [owners] mapping covers",
+                unassigned,
+                crossref.gaps.len()
+            );
+        }
+    }
 
     // Phase 4: Output (README + gaps, modules already written)
-    let output_pb = ProgressBar::new_spinner();
-    output_pb.set_style(spinner_style);
-    output_pb.set_prefix("[4/4]");
-    output_pb.set_message("Generating index and gaps...");
-    output_pb.enable_steady_tick(std::time::Duration::from_millis(100));
+    let phase = Phase::start("output", "[4/4]", "Generating index and gaps...");
 
-    output::generate(&analysis, &crossref, output_path, args.format)?;
+    // Deep analysis wrote the page already; a single file gets one anyway
+    if single_file && !llm_loop {
+        let modules_dir = output_path.join("modules");
+        for module in &analysis.modules {
+            if let Err(e) = analyzer::write_static_page(&modules_dir, module) {
+                warn!("Failed to write the page for {}: {:#}", module.path, e);
+            }
+        }
+    }
 
-    output_pb.finish_with_message(format!("Output written to {}", output_path.display()));
+    let report = timings.report();
+    for slow in &report.slow_parses {
+        if let Some(reason) = &slow.fallback {
+            analysis.warnings.push(RunWarning::new(
+                WarningKind::ParseTimeout,
+                format!("{}: {}", slow.path, reason),
+            ));
+        }
+    }
+    analysis.timings = Some(report);
+    // Scripts analyzing a single file get analysis.json with any format, and
+    // so does a merge, for the next one to build on
+    let formats = if (single_file || merged) && args.format == Format::Markdown {
+        vec![Format::Json, Format::Markdown]
+    } else {
+        vec![args.format]
+    };
+    for &format in &formats {
+        output::generate(&analysis, &crossref, output_path, format)?;
+    }
+
+    let run_id = args.tag.as_ref().map(|_| {
+        output_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    });
+    if !args.keep_stale && !single_file {
+        remove_orphans(&path, output_path, &analysis);
+    }
+    let mut produced: Vec<_> = formats
+        .iter()
+        .flat_map(|&format| manifest::outputs(&analysis, output_path, format))
+        .collect();
+    if args.mode != AnalysisMode::Static {
+        produced.push((
+            analyzer::OVERVIEW_PROMPT_FILE.to_string(),
+            ArtifactKind::OverviewPrompt,
+        ));
+    }
+    if llm_loop {
+        produced.push((analyzer::PROGRESS_FILE.to_string(), ArtifactKind::Progress));
+        produced.push((analyzer::LLM_LOG_FILE.to_string(), ArtifactKind::LlmLog));
+    }
+    manifest::write(output_path, run_id.clone(), Some(&root), &produced, prune)?;
+
+    timings.phase(
+        "output",
+        phase.finish(format!("Output written to {}", output_path.display())),
+    );
+
+    let llm_modules = analysis.llm_modules();
+    let elapsed = started.elapsed();
+    let over_budget = analysis
+        .timings
+        .as_ref()
+        .map(|t| t.over_budget.as_slice())
+        .unwrap_or_default();
+    logging::summary(
+        &format!(
+            "Analyzed {} modules ({} with LLM), {} exports, {} gaps in {:.1}s{}",
+            analysis.modules.len(),
+            llm_modules,
+            analysis.total_exports(),
+            crossref.gaps.len(),
+            elapsed.as_secs_f64(),
+            if over_budget.is_empty() {
+                String::new()
+            } else {
+                format!("; {} file(s) over the per-file budget", over_budget.len())
+            }
+        ),
+        serde_json::json!({
+            "modules": analysis.modules.len(),
+            "llm_modules": llm_modules,
+            "exports": analysis.total_exports(),
+            "gaps": crossref.gaps.len(),
+            "over_budget": over_budget.len(),
+            "duration_ms": elapsed.as_millis() as u64,
+        }),
+    );
+    for file in over_budget {
+        info!(
+            "Over the per-file budget: {} ({:.1}s)",
+            file.path,
+            file.ms as f64 / 1000.0
+        );
+    }
+
+    if args.timings {
+        timings.report().print();
+    }
+
+    if let (Some(tag), Some(id)) = (&args.tag, run_id) {
+        runs::record(
+            Path::new(&args.output),
+            RunEntry {
+                id,
+                tag: tag.clone(),
+                started_at: runs::iso_timestamp(started_at),
+                commit: git::head_commit(&root),
+                provider: args.provider.clone(),
+                model: args.model.clone(),
+                deep: args.mode == AnalysisMode::Deep,
+                partial: analysis.sample.is_some(),
+                order: llm_loop.then(|| args.order.name().to_string()),
+                prepass: prepass_ran,
+                doc_language: analysis.doc_language.clone(),
+                stats: RunStats {
+                    modules: analysis.modules.len(),
+                    exports: analysis.total_exports(),
+                    gaps: crossref.gaps.len(),
+                    llm_modules,
+                },
+            },
+        )?;
+        info!(
+            "Recorded run `{}` in {}",
+            tag,
+            Path::new(&args.output).join(runs::RUNS_DIR).display()
+        );
+    }
+
+    if let (Some(sample), Some(deep_time)) = (&analysis.sample, deep_time) {
+        let estimate = deep_time.as_secs_f64() * sample.total as f64 / sample.sampled as f64;
+        info!(
+            "A full --deep run over {} files would take about {:.0}s ({} LLM calls) at this rate",
+            sample.total, estimate, sample.total
+        );
+    }
+
+    if let Some(thresholds) = &args.fail_on_gaps {
+        let gaps = match &baseline {
+            Some(baseline) => {
+                let root = gate::legacy_root(analysis.modules.iter().map(|m| m.path.as_str()));
+                baseline.new_gaps(&crossref.gaps, &root)
+            }
+            None => crossref.gaps.iter().collect(),
+        };
+        let violations = gate::evaluate(&gaps, thresholds);
+        if !violations.is_empty() {
+            gate::print_violations(&violations, baseline.is_some());
+            return Err(GapsExceeded::new(&violations).into());
+        }
+        info!("Gap check passed");
+    }
+
+    let warnings = logging::warnings();
+    if warnings > 0 {
+        return Err(CompletedWithWarnings { count: warnings }.into());
+    }
 
     info!("✅ Analysis complete!");
     Ok(())
 }
+
+/// `--only-overview`: generate the architecture overview again for the
+/// analysis.json in `output_path`, without analyzing any module. The JSON is
+/// rewritten with it, and so is `CODEBASE.md` for `--format markdown`.
+async fn only_overview(args: &AnalyzeArgs, output_path: &Path) -> Result<()> {
+    let json = analysis_json(output_path)?;
+    if !json.is_file() {
+        anyhow::bail!(
+            "No analysis.json in {}; --only-overview needs the output of an earlier \
+             `cda analyze --format json` run",
+            output_path.display()
+        );
+    }
+    let (mut analysis, crossref) = output::load(&json)?;
+    info!(
+        "Generating the architecture overview for the {} modules in {}",
+        analysis.modules.len(),
+        json.display()
+    );
+
+    let provider = crate::llm::get_provider(&args.provider, args.model.as_deref())?;
+    let crossref = analyzer::add_architecture_overview(
+        &mut analysis,
+        crossref,
+        provider.as_ref(),
+        output_path,
+    )
+    .await;
+
+    let mut produced = vec![(
+        analyzer::OVERVIEW_PROMPT_FILE.to_string(),
+        ArtifactKind::OverviewPrompt,
+    )];
+    let mut formats = vec![Format::Json];
+    if args.format == Format::Markdown {
+        formats.push(Format::Markdown);
+    }
+    for format in formats {
+        output::generate(&analysis, &crossref, output_path, format)?;
+        produced.extend(manifest::outputs(&analysis, output_path, format));
+    }
+    manifest::update(output_path, &produced)?;
+
+    let warnings = logging::warnings();
+    if warnings > 0 {
+        return Err(CompletedWithWarnings { count: warnings }.into());
+    }
+    info!(
+        "✅ Architecture overview written to {}",
+        output_path.display()
+    );
+    Ok(())
+}
+
+/// The analysis.json `output_path`'s manifest lists, or the default one
+fn analysis_json(output_path: &Path) -> Result<PathBuf> {
+    Ok(Manifest::load(output_path)?
+        .and_then(|m| {
+            m.find(ArtifactKind::Analysis)
+                .map(|a| output_path.join(&a.path))
+        })
+        .unwrap_or_else(|| output_path.join("analysis.json")))
+}
+
+/// Output of an earlier run for files outside a `--module`
+#[derive(Debug, Default, PartialEq, Eq)]
+struct OutsideModule {
+    /// Module and directory pages in the manifest
+    pages: usize,
+    /// Modules in analysis.json
+    modules: usize,
+}
+
+impl OutsideModule {
+    fn is_empty(&self) -> bool {
+        self.pages == 0 && self.modules == 0
+    }
+}
+
+impl std::fmt::Display for OutsideModule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} page(s) and {} module(s) in analysis.json",
+            self.pages, self.modules
+        )
+    }
+}
+
+/// What the output in `output_path` holds beyond the files of a `--module`
+/// run: pages its manifest lists that the run can't write, and modules of
+/// its analysis.json outside the module's directory
+fn outside_module(
+    output_path: &Path,
+    module: &str,
+    inventory: &FileInventory,
+) -> Result<OutsideModule> {
+    let module = discovery::normalize_module(module);
+    let mut own: HashSet<String> = HashSet::from([analyzer::directory_page_name("")]);
+    for file in &inventory.source_files {
+        own.insert(analyzer::module_page_name(&file.path));
+        let mut path = file.path.as_str();
+        while let Some(i) = path.rfind(['/', '\\']) {
+            path = &path[..i];
+            own.insert(analyzer::directory_page_name(path));
+        }
+    }
+
+    let mut outside = OutsideModule::default();
+    if let Some(manifest) = Manifest::load(output_path)? {
+        outside.pages = manifest
+            .current()
+            .filter(|a| a.kind.is_page())
+            .filter(|a| {
+                let page = a.path.strip_prefix("modules/").unwrap_or(&a.path);
+                !own.contains(page)
+            })
+            .count();
+    }
+    let json = analysis_json(output_path)?;
+    if json.is_file() {
+        if let Ok((prior, _)) = output::load(&json) {
+            outside.modules = prior
+                .modules
+                .iter()
+                .filter(|m| !in_module(&m.path, &module))
+                .count();
+        }
+    }
+    Ok(outside)
+}
+
+fn in_module(path: &str, module: &str) -> bool {
+    let path = path.replace('\\', "/");
+    module.is_empty()
+        || path == module
+        || path
+            .strip_prefix(module)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// The modules `analysis` found under `module` in place of those `prior`
+/// had there. Project-level findings (config files, commands, CI, ...)
+/// are kept from `prior`, which saw the whole tree.
+fn merge_module_run(mut analysis: Analysis, prior: Analysis, module: &str) -> Analysis {
+    let modules = std::mem::take(&mut analysis.modules);
+    analysis.modules = prior
+        .modules
+        .into_iter()
+        .filter(|m| !in_module(&m.path, module))
+        .chain(modules)
+        .collect();
+    analysis.modules.sort_by(|a, b| a.path.cmp(&b.path));
+    let skipped = std::mem::take(&mut analysis.skipped_files);
+    analysis.skipped_files = prior
+        .skipped_files
+        .into_iter()
+        .filter(|f| !in_module(f, module))
+        .chain(skipped)
+        .collect();
+    analysis.skipped_files.sort();
+
+    analysis.config_files = prior.config_files;
+    analysis.doc_files = prior.doc_files;
+    analysis.commands = prior.commands;
+    analysis.declared_features = prior.declared_features;
+    analysis.ci = prior.ci;
+    analysis.kubernetes = prior.kubernetes;
+    analysis.helm_charts = prior.helm_charts;
+    analysis.api_specs = prior.api_specs;
+    analysis
+}
+
+/// Files renamed since the run whose output is in `output_path`. The module
+/// page and progress entry of an exact rename move to its new path; those
+/// of the others are dropped, so they are analyzed again.
+fn detect_renames(inventory: &FileInventory, output_path: &Path) -> Vec<Rename> {
+    let prior = output::load_prior(output_path).unwrap_or_else(|e| {
+        debug!("No earlier analysis.json to detect renames with: {}", e);
+        HashMap::new()
+    });
+    let mut previous: HashMap<String, Option<String>> = prior
+        .into_iter()
+        .map(|(path, module)| (path, module.content_hash))
+        .collect();
+    for (path, hash) in analyzer::progress_files(output_path) {
+        let known = previous.entry(path).or_default();
+        if known.is_none() {
+            *known = hash;
+        }
+    }
+    if previous.is_empty() {
+        return Vec::new();
+    }
+
+    let renames = renames::detect(&previous, inventory);
+    for rename in &renames {
+        info!(
+            "Renamed: {} → {}{}",
+            rename.from,
+            rename.to,
+            if rename.exact {
+                " (unchanged, keeping its analysis)"
+            } else {
+                ""
+            }
+        );
+        if let Err(e) = analyzer::carry_forward(output_path, rename) {
+            warn!("Failed to move the analysis of {}: {}", rename.from, e);
+        }
+    }
+    renames
+}
+
+/// Source files changed since `git_ref`
+fn changed_targets(
+    root: &Path,
+    git_ref: &str,
+    inventory: &FileInventory,
+) -> Result<HashSet<String>> {
+    let changed = git::changed_files(root, git_ref)?;
+    let targets: HashSet<String> = inventory
+        .source_files
+        .iter()
+        .filter(|f| {
+            changed.contains(&f.abs_path)
+                || f.companions.iter().any(|c| changed.contains(&c.abs_path))
+        })
+        .map(|f| f.path.clone())
+        .collect();
+    info!(
+        "{} files changed since {}, {} of them in the inventory",
+        changed.len(),
+        git_ref,
+        targets.len()
+    );
+    Ok(targets)
+}
+
+/// Listed paths shown when some match no source file
+const MAX_UNMATCHED_SHOWN: usize = 10;
+
+/// The paths of `--files-from`: the lines of the file `source`, or of stdin
+/// for `-`, without blank ones
+fn read_file_list(source: &str) -> Result<Vec<String>> {
+    let text = if source == "-" {
+        let mut text = String::new();
+        std::io::Read::read_to_string(&mut std::io::stdin(), &mut text)
+            .map_err(|e| anyhow::anyhow!("Cannot read --files-from from stdin: {}", e))?;
+        text
+    } else {
+        std::fs::read_to_string(source)
+            .map_err(|e| anyhow::anyhow!("Cannot read --files-from {}: {}", source, e))?
+    };
+    Ok(text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+/// Source files named in the `--files-from` list read from `source`, with
+/// the listed paths that name none logged
+fn listed_targets(
+    root: &Path,
+    source: &str,
+    listed: &[String],
+    inventory: &FileInventory,
+) -> HashSet<String> {
+    let (targets, unmatched) = match_listed(root, listed, inventory);
+    let source = if source == "-" { "stdin" } else { source };
+    info!(
+        "{} paths read from {}, {} of them in the inventory",
+        listed.len(),
+        source,
+        targets.len()
+    );
+    if !unmatched.is_empty() {
+        let mut shown: Vec<String> = unmatched
+            .iter()
+            .take(MAX_UNMATCHED_SHOWN)
+            .map(|path| match root.join(path).exists() {
+                true => path.clone(),
+                false => format!("{} (not found)", path),
+            })
+            .collect();
+        if unmatched.len() > shown.len() {
+            shown.push(format!("and {} more", unmatched.len() - shown.len()));
+        }
+        info!(
+            "{} listed path(s) match no discovered source file, so they are not analyzed: {}",
+            unmatched.len(),
+            shown.join(", ")
+        );
+    }
+    if targets.is_empty() && !listed.is_empty() {
+        warn!(
+            "None of the paths read from {} is a source file; paths are relative to {}",
+            source,
+            root.display()
+        );
+    }
+    targets
+}
+
+/// The modules whose file, or one of whose paired files, is in `listed`, and
+/// the listed paths that are none of them. Paths are relative to `root`, or
+/// absolute under it.
+fn match_listed(
+    root: &Path,
+    listed: &[String],
+    inventory: &FileInventory,
+) -> (HashSet<String>, Vec<String>) {
+    let mut modules: HashMap<&str, &str> = HashMap::new();
+    for file in &inventory.source_files {
+        modules.insert(&file.path, &file.path);
+        for companion in &file.companions {
+            modules.insert(&companion.path, &file.path);
+        }
+    }
+
+    let mut targets = HashSet::new();
+    let mut unmatched = Vec::new();
+    for path in listed {
+        let relative = Path::new(path)
+            .strip_prefix(root)
+            .map(|p| p.to_string_lossy().into_owned())
+            .unwrap_or_else(|_| path.replace('\\', "/"));
+        let relative = relative.trim_start_matches("./");
+        match modules.get(relative) {
+            Some(module) => {
+                targets.insert(module.to_string());
+            }
+            None => unmatched.push(relative.to_string()),
+        }
+    }
+    (targets, unmatched)
+}
+
+/// Remove the module pages and progress entries of source files that are
+/// gone from `root`, and list them in cda-changes.md
+fn remove_orphans(root: &Path, output_path: &Path, analysis: &Analysis) {
+    let modules = analysis.modules.iter().map(|m| m.path.as_str());
+    let result = Manifest::load(output_path)
+        .map(|m| m.as_ref().map(Manifest::pages).unwrap_or_default())
+        .and_then(|listed| orphans::find(output_path, root, modules, &listed))
+        .and_then(|found| {
+            if !found.is_empty() {
+                orphans::remove(output_path, &found)?;
+                orphans::record(output_path, &found)?;
+                info!(
+                    "Removed the output of {} file(s) that no longer exist ({} page(s)); \
+                 see {}, or pass --keep-stale to keep it",
+                    found.files.len(),
+                    found.pages.len(),
+                    orphans::CHANGES_FILE
+                );
+            }
+            Ok(())
+        });
+    if let Err(e) = result {
+        warn!("Failed to remove the output of deleted files: {}", e);
+    }
+}
+
+/// Attach git and CODEOWNERS ownership to every module, and to the module
+/// pages that --deep has already written
+fn add_ownership(analysis: &mut Analysis, root: &Path, output_path: &Path) {
+    let paths: Vec<&str> = analysis.modules.iter().map(|m| m.path.as_str()).collect();
+    let mut ownership = ownership::collect(root, &paths);
+
+    let modules_dir = output_path.join("modules");
+    for module in &mut analysis.modules {
+        module.ownership = ownership.remove(&module.path);
+        let Some(owners) = module
+            .ownership
+            .as_ref()
+            .filter(|_| module.has_deep_analysis)
+        else {
+            continue;
+        };
+        if let Err(e) = analyzer::write_page_ownership(&modules_dir, &module.path, owners) {
+            warn!(
+                "Failed to add ownership to the page for {}: {}",
+                module.path, e
+            );
+        }
+    }
+}
+
+/// How many modules import each source file, from the previous
+/// analysis.json when there is one this cda can read, otherwise from a fresh
+/// static pass
+async fn dependency_map(
+    inventory: &FileInventory,
+    output_path: &Path,
+    parse_jobs: usize,
+) -> Result<BTreeMap<String, Vec<String>>> {
+    let prior = output::load_prior_dependencies(output_path).unwrap_or_else(|e| {
+        warn!("Mapping dependencies again: {:#}", e);
+        None
+    });
+    match prior {
+        Some(dependencies) => Ok(dependencies),
+        None => {
+            let analysis = analyzer::analyze_static(
+                inventory,
+                parse_jobs,
+                ParseOptions::default(),
+                &Timings::default(),
+            )
+            .await?;
+            Ok(analyzer::cross_reference(&analysis).await?.dependencies)
+        }
+    }
+}
+
+/// Neighbors for the deep prompts: the pre-pass's dependency map, or else
+/// the previous run's, with the summaries the previous run got from the LLM.
+/// `None` without a dependency map; a previous analysis.json that can't be
+/// read only costs the neighbors.
+fn neighbors(
+    output_path: &Path,
+    dependencies: Option<&BTreeMap<String, Vec<String>>>,
+) -> Option<analyzer::Neighbors> {
+    let prior_dependencies = match dependencies {
+        Some(_) => None,
+        None => output::load_prior_dependencies(output_path)
+            .inspect_err(|e| debug!("No neighbors from the previous run: {:#}", e))
+            .ok()
+            .flatten(),
+    };
+    let dependencies = dependencies.or(prior_dependencies.as_ref())?;
+    let summaries = output::load_prior(output_path)
+        .inspect_err(|e| debug!("No neighbor summaries from the previous run: {:#}", e))
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(_, module)| module.has_deep_analysis)
+        .map(|(path, module)| (path, module.summary))
+        .collect();
+    Some(analyzer::Neighbors::new(dependencies, summaries))
+}
+
+/// How many modules import each module in a dependency map
+fn fan_in_counts(dependencies: &BTreeMap<String, Vec<String>>) -> HashMap<String, usize> {
+    let mut counts = HashMap::new();
+    for deps in dependencies.values() {
+        for dep in deps {
+            *counts.entry(dep.clone()).or_default() += 1;
+        }
+    }
+    counts
+}
+
+/// Keep at most `max` source files, chosen by `strategy`. Ties are broken by
+/// path so the sample is stable between runs.
+fn sample_inventory(
+    mut inventory: FileInventory,
+    max: usize,
+    strategy: SampleStrategy,
+    dependencies: Option<&BTreeMap<String, Vec<String>>>,
+    seed: u64,
+) -> (FileInventory, Option<SampleInfo>) {
+    let total = inventory.source_files.len();
+    if total <= max {
+        return (inventory, None);
+    }
+
+    order_files(
+        &mut inventory.source_files,
+        strategy.order(),
+        dependencies,
+        seed,
+    );
+    inventory.source_files.truncate(max);
+
+    let sample = SampleInfo {
+        strategy: strategy.name().to_string(),
+        sampled: max,
+        total,
+    };
+    (inventory, Some(sample))
+}
+
+/// Sort `files` into `order`, using `dependencies` for the orders that
+/// follow imports. Ties are broken by path, so the order is stable between
+/// runs.
+fn order_files(
+    files: &mut [SourceFile],
+    order: FileOrder,
+    dependencies: Option<&BTreeMap<String, Vec<String>>>,
+    seed: u64,
+) {
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    match order {
+        FileOrder::Dependencies => {
+            if let Some(dependencies) = dependencies {
+                let rank = dependencies_first(files, dependencies);
+                files.sort_by_key(|f| rank[&f.path]);
+            }
+        }
+        FileOrder::Size => files.sort_by_key(|f| std::cmp::Reverse(f.size)),
+        FileOrder::FanIn => {
+            let fan_in = dependencies.map(fan_in_counts).unwrap_or_default();
+            let count = |path: &str| fan_in.get(path).copied().unwrap_or(0);
+            files.sort_by_key(|f| std::cmp::Reverse(count(&f.path)));
+        }
+        FileOrder::Path => {}
+        FileOrder::Random => fastrand::Rng::with_seed(seed).shuffle(files),
+    }
+}
+
+/// Position of each of `files` (sorted by path) when every module comes
+/// after the modules it imports. An import cycle is broken at the first
+/// path still waiting.
+fn dependencies_first(
+    files: &[SourceFile],
+    dependencies: &BTreeMap<String, Vec<String>>,
+) -> HashMap<String, usize> {
+    let mut waiting: BTreeMap<&str, usize> = files.iter().map(|f| (f.path.as_str(), 0)).collect();
+    let mut importers: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (module, deps) in dependencies {
+        if !waiting.contains_key(module.as_str()) {
+            continue;
+        }
+        let deps: BTreeSet<&str> = deps
+            .iter()
+            .map(String::as_str)
+            .filter(|d| *d != module && waiting.contains_key(d))
+            .collect();
+        for dep in deps {
+            *waiting.get_mut(module.as_str()).unwrap() += 1;
+            importers.entry(dep).or_default().push(module);
+        }
+    }
+
+    let mut ready: BTreeSet<&str> = waiting
+        .iter()
+        .filter(|(_, count)| **count == 0)
+        .map(|(path, _)| *path)
+        .collect();
+    let mut rank = HashMap::new();
+    while let Some(&first) = waiting.keys().next() {
+        let next = ready.pop_first().unwrap_or(first);
+        waiting.remove(next);
+        rank.insert(next.to_string(), rank.len());
+        for importer in importers.get(next).into_iter().flatten() {
+            if let Some(count) = waiting.get_mut(importer) {
+                *count -= 1;
+                if *count == 0 {
+                    ready.insert(importer);
+                }
+            }
+        }
+    }
+    rank
+}
+
+/// Whether any source file changed after discovery, so a parse from before
+/// the deep loop may no longer match the analyzed modules
+fn changed_since_discovery(analysis: &Analysis, inventory: &FileInventory) -> bool {
+    analysis.modules.iter().any(|m| m.modified_during_analysis)
+        || inventory
+            .source_files
+            .iter()
+            .any(|f| f.changed_since_discovery())
+}
+
+fn language_names(languages: &[Language]) -> String {
+    languages
+        .iter()
+        .map(|l| l.name())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::SourceFile;
+
+    fn inventory() -> FileInventory {
+        let mut inventory = FileInventory::default();
+        for (path, size) in [("a.rs", 10), ("b.rs", 30), ("c.rs", 20), ("d.rs", 5)] {
+            inventory.source_files.push(SourceFile {
+                path: path.into(),
+                abs_path: path.into(),
+                language: Language::Rust,
+                size,
+                is_test: false,
+                modified: None,
+                companions: Vec::new(),
+                detection: Default::default(),
+            });
+        }
+        inventory
+    }
+
+    fn paths(inventory: &FileInventory) -> Vec<&str> {
+        inventory
+            .source_files
+            .iter()
+            .map(|f| f.path.as_str())
+            .collect()
+    }
+
+    fn dependency_map(edges: &[(&str, &[&str])]) -> BTreeMap<String, Vec<String>> {
+        edges
+            .iter()
+            .map(|(module, deps)| {
+                let deps = deps.iter().map(|d| d.to_string()).collect();
+                (module.to_string(), deps)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_changed_since_discovery() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.rs");
+        std::fs::write(&path, "use crate::b;\n").unwrap();
+        let metadata = path.metadata().unwrap();
+        let mut inventory = inventory();
+        inventory.source_files[0].abs_path = path.clone();
+        inventory.source_files[0].size = metadata.len();
+        inventory.source_files[0].modified = metadata.modified().ok();
+        let analysis = Analysis::default();
+        assert!(!changed_since_discovery(&analysis, &inventory));
+
+        std::fs::write(&path, "use crate::b;\nuse crate::c;\n").unwrap();
+        assert!(changed_since_discovery(&analysis, &inventory));
+    }
+
+    #[test]
+    fn test_sample_inventory() {
+        let (sampled, info) = sample_inventory(inventory(), 2, SampleStrategy::Largest, None, 0);
+        assert_eq!(paths(&sampled), vec!["b.rs", "c.rs"]);
+        let info = info.unwrap();
+        assert_eq!(
+            (info.strategy.as_str(), info.sampled, info.total),
+            ("largest", 2, 4)
+        );
+
+        // d.rs is imported three times, a.rs once
+        let dependencies = dependency_map(&[
+            ("a.rs", &["d.rs"]),
+            ("b.rs", &["d.rs", "a.rs"]),
+            ("c.rs", &["d.rs"]),
+        ]);
+        let (sampled, _) = sample_inventory(
+            inventory(),
+            2,
+            SampleStrategy::FanIn,
+            Some(&dependencies),
+            0,
+        );
+        assert_eq!(paths(&sampled), vec!["d.rs", "a.rs"]);
+
+        let random = |seed| {
+            let (sampled, _) = sample_inventory(inventory(), 3, SampleStrategy::Random, None, seed);
+            paths(&sampled)
+                .into_iter()
+                .map(String::from)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(random(7), random(7));
+        assert_eq!(random(7).len(), 3);
+
+        let (all, info) = sample_inventory(inventory(), 4, SampleStrategy::Largest, None, 0);
+        assert_eq!(all.source_files.len(), 4);
+        assert!(info.is_none());
+    }
+
+    #[test]
+    fn test_order_files() {
+        let ordered = |order, dependencies: Option<&BTreeMap<String, Vec<String>>>, seed| {
+            let mut files = inventory().source_files;
+            files.reverse();
+            order_files(&mut files, order, dependencies, seed);
+            files.into_iter().map(|f| f.path).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            ordered(FileOrder::Path, None, 0),
+            vec!["a.rs", "b.rs", "c.rs", "d.rs"]
+        );
+        assert_eq!(
+            ordered(FileOrder::Size, None, 0),
+            vec!["b.rs", "c.rs", "a.rs", "d.rs"]
+        );
+
+        // Unranked files keep path order behind the ranked ones; d.rs is
+        // imported three times, c.rs once
+        let dependencies = dependency_map(&[
+            ("a.rs", &["d.rs", "c.rs"]),
+            ("b.rs", &["d.rs"]),
+            ("c.rs", &["d.rs"]),
+        ]);
+        assert_eq!(
+            ordered(FileOrder::FanIn, Some(&dependencies), 0),
+            vec!["d.rs", "c.rs", "a.rs", "b.rs"]
+        );
+
+        // Imported modules first, then path order among the ready ones
+        assert_eq!(
+            ordered(FileOrder::Dependencies, Some(&dependencies), 0),
+            vec!["d.rs", "b.rs", "c.rs", "a.rs"]
+        );
+        // A cycle is broken at its first path
+        let cycle = dependency_map(&[("b.rs", &["c.rs"]), ("c.rs", &["b.rs", "a.rs"])]);
+        assert_eq!(
+            ordered(FileOrder::Dependencies, Some(&cycle), 0),
+            vec!["a.rs", "d.rs", "b.rs", "c.rs"]
+        );
+        // Without a dependency map, path order
+        assert_eq!(
+            ordered(FileOrder::Dependencies, None, 0),
+            ordered(FileOrder::Path, None, 0)
+        );
+
+        assert_eq!(
+            ordered(FileOrder::Random, None, 7),
+            ordered(FileOrder::Random, None, 7)
+        );
+        let mut shuffled = ordered(FileOrder::Random, None, 7);
+        shuffled.sort();
+        assert_eq!(shuffled, ordered(FileOrder::Path, None, 0));
+    }
+
+    #[tokio::test]
+    async fn test_match_listed() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/lib.rs"), "pub fn f() {}\n").unwrap();
+        std::fs::write(root.join("src/util.h"), "int util(void);\n").unwrap();
+        std::fs::write(root.join("src/util.c"), "int util(void) { return 1; }\n").unwrap();
+        std::fs::write(root.join("README.md"), "# Docs\n").unwrap();
+        let mut inventory = discovery::discover(&root, None, &DiscoveryOptions::default())
+            .await
+            .unwrap();
+        inventory.source_files = pairing::pair(std::mem::take(&mut inventory.source_files));
+
+        let listed: Vec<String> = [
+            "./src/lib.rs",
+            "src/util.c",
+            "README.md",
+            "src/gone.rs",
+            &root.join("src/lib.rs").display().to_string(),
+        ]
+        .map(String::from)
+        .to_vec();
+        let (targets, unmatched) = match_listed(&root, &listed, &inventory);
+        // A paired file selects its module
+        assert_eq!(
+            targets,
+            HashSet::from(["src/lib.rs".to_string(), "src/util.h".to_string()])
+        );
+        assert_eq!(unmatched, ["README.md", "src/gone.rs"]);
+    }
+
+    /// Static analysis of `path` into `out`, with a provider that can't be
+    /// constructed
+    fn static_args(path: &Path, out: &Path) -> AnalyzeArgs {
+        AnalyzeArgs {
+            path: path.display().to_string(),
+            output: out.display().to_string(),
+            module: None,
+            provider: "no-such-provider".into(),
+            model: None,
+            parse_jobs: 1,
+            llm_concurrency: 1,
+            ownership: false,
+            owners: BTreeMap::new(),
+            parse_options: ParseOptions::default(),
+            mode: AnalysisMode::Static,
+            check_model: false,
+            skip_preflight: false,
+            resume: ResumeMode::Resume,
+            keep_stale_prompts: false,
+            import_state: None,
+            changed_only: None,
+            dependents: false,
+            fail_on_gaps: None,
+            baseline: None,
+            format: Format::Json,
+            discovery: DiscoveryOptions::default(),
+            max_files: None,
+            sample: None,
+            order: FileOrder::default(),
+            deep_select: DeepSelect::All,
+            max_llm_calls: None,
+            seed: None,
+            tag: None,
+            timings: false,
+            prune: false,
+            keep_stale: false,
+            no_pairing: false,
+            only_overview: false,
+            module_output: ModuleOutput::Overlay,
+            progress_json: None,
+            files_from: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_static_mode_needs_no_provider() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("lib.rs"), "pub fn f() {}\n").unwrap();
+        let out = dir.path().join("out");
+
+        // Constructing this provider would fail, as would any real one
+        // without an API key, so success means no LLM was touched
+        run(static_args(dir.path(), &out)).await.unwrap();
+
+        let (analysis, crossref) = output::load(&out.join("analysis.json")).unwrap();
+        assert_eq!(analysis.modules.len(), 1);
+        assert!(crossref.architecture_overview.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_only_overview_needs_an_analysis() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("out");
+        let only_overview = |mode| AnalyzeArgs {
+            mode,
+            only_overview: true,
+            ..static_args(dir.path(), &out)
+        };
+
+        let err = run(only_overview(AnalysisMode::Static)).await.unwrap_err();
+        assert!(err.downcast_ref::<UsageError>().is_some());
+        let err = run(only_overview(AnalysisMode::Standard))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("No analysis.json"), "{}", err);
+
+        // With one, it goes straight to the LLM, here a provider that fails
+        run(static_args(dir.path(), &out)).await.unwrap();
+        let err = run(only_overview(AnalysisMode::Standard))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("no-such-provider"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_module_run_merge_and_replace() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |path: &str, content: &str| {
+            let file = dir.path().join(path);
+            std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+            std::fs::write(file, content).unwrap();
+        };
+        write("src/api/handler.rs", "pub fn handle() {}\n");
+        write("src/core/engine.rs", "pub fn start() {}\n");
+        let out = dir.path().join("out");
+        let module_run = |module_output| AnalyzeArgs {
+            module: Some("src/api".into()),
+            module_output,
+            ..static_args(dir.path(), &out)
+        };
+
+        let err = run(module_run(ModuleOutput::Merge)).await.unwrap_err();
+        assert!(err.downcast_ref::<UsageError>().is_some());
+
+        run(static_args(dir.path(), &out)).await.unwrap();
+        write(
+            "src/api/handler.rs",
+            "pub fn handle() {}\npub fn route() {}\n",
+        );
+        run(module_run(ModuleOutput::Merge)).await.unwrap();
+        let (analysis, _) = output::load(&out.join("analysis.json")).unwrap();
+        let paths: Vec<_> = analysis.modules.iter().map(|m| m.path.as_str()).collect();
+        assert_eq!(paths, vec!["src/api/handler.rs", "src/core/engine.rs"]);
+        assert_eq!(analysis.total_exports(), 3);
+
+        // A markdown run over everything, then one that keeps src/api only
+        let markdown = |args| AnalyzeArgs {
+            format: Format::Markdown,
+            ..args
+        };
+        run(markdown(static_args(dir.path(), &out))).await.unwrap();
+        let core_page = out
+            .join("modules")
+            .join(analyzer::directory_page_name("src/core"));
+        assert!(core_page.is_file());
+        run(markdown(module_run(ModuleOutput::Replace)))
+            .await
+            .unwrap();
+        assert!(!core_page.is_file());
+        assert!(!out.join("analysis.json").exists());
+        assert!(out
+            .join("modules")
+            .join(analyzer::directory_page_name("src/api"))
+            .is_file());
+    }
+
+    #[tokio::test]
+    async fn test_module_run_paths_match_full_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |path: &str, content: &str| {
+            let file = dir.path().join(path);
+            std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+            std::fs::write(file, content).unwrap();
+        };
+        write("src/api/handler.rs", "pub fn handle() {}\n");
+        write("src/core/engine.rs", "pub fn start() {}\n");
+        let full_out = dir.path().join("full");
+        run(static_args(dir.path(), &full_out)).await.unwrap();
+
+        let api_paths = |out: &Path| {
+            let (analysis, crossref) = output::load(&out.join("analysis.json")).unwrap();
+            let mut paths: Vec<String> = analysis
+                .modules
+                .iter()
+                .map(|m| m.path.clone())
+                .chain(crossref.gaps.iter().filter_map(|g| g.location.clone()))
+                .filter(|p| p.starts_with("src/api/"))
+                .collect();
+            paths.sort();
+            paths
+        };
+        let expected = api_paths(&full_out);
+        assert!(expected.contains(&"src/api/handler.rs".to_string()));
+
+        let module_out = dir.path().join("module");
+        let absolute = dir.path().join("src/core/../api/");
+        for module in [
+            "src/api",
+            "./src/core/../api/",
+            &absolute.display().to_string(),
+        ] {
+            run(AnalyzeArgs {
+                module: Some(module.to_string()),
+                baseline: Some(full_out.join("analysis.json").display().to_string()),
+                fail_on_gaps: Some("missing_docs:0".parse().unwrap()),
+                ..static_args(dir.path(), &module_out)
+            })
+            .await
+            .unwrap();
+            assert_eq!(api_paths(&module_out), expected, "--module {}", module);
+        }
+
+        for module in ["src/nowhere", ".."] {
+            let err = run(AnalyzeArgs {
+                module: Some(module.to_string()),
+                ..static_args(dir.path(), &module_out)
+            })
+            .await
+            .unwrap_err();
+            assert!(err.downcast_ref::<UsageError>().is_some(), "{}", err);
+        }
+    }
+}
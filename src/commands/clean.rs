@@ -0,0 +1,477 @@
+use anyhow::Result;
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use super::config;
+use crate::core::analyzer::{self, PageNaming, DIRECTORY_PAGE};
+use crate::core::exit::UsageError;
+use crate::core::orphans::{self, Orphans};
+use crate::output;
+use crate::output::manifest::{self, ArtifactKind, Manifest, MANIFEST_FILE};
+
+/// Top-level files `analyze` writes into the output directory
+const INDEX_FILES: &[&str] = &[
+    "CODEBASE.md",
+    "analysis.json",
+    analyzer::OVERVIEW_PROMPT_FILE,
+    orphans::CHANGES_FILE,
+];
+
+pub struct CleanArgs {
+    pub path: String,
+    pub progress: bool,
+    pub cache: bool,
+    pub modules: bool,
+    /// Remove the output of source files that are gone from `root`
+    pub stale: bool,
+    /// The analyzed directory; the manifest's when not given
+    pub root: Option<String>,
+    pub all: bool,
+    pub yes: bool,
+}
+
+pub fn run(args: CleanArgs) -> Result<()> {
+    if !(args.progress || args.cache || args.modules || args.stale || args.all) {
+        return Err(UsageError::new(
+            "Nothing selected. Use --progress, --cache, --modules, --stale or --all",
+        )
+        .into());
+    }
+
+    let output_path = Path::new(&args.path);
+    // Artifacts the manifest lists, or for output written before there were
+    // manifests, the pages of the files in the progress and the names cda
+    // has always used. A directory with neither isn't touched.
+    let manifest = Manifest::load(output_path)?;
+    let has_progress = analyzer::has_progress(output_path);
+    let touches_output = args.progress || args.modules || args.stale || args.all;
+    if touches_output && output_path.is_dir() && manifest.is_none() && !has_progress {
+        return Err(UsageError::new(format!(
+            "{} has no {} or cda progress file, so nothing in it can be shown to be \
+             cda's output. Point clean at the output directory of `cda analyze`",
+            output_path.display(),
+            MANIFEST_FILE
+        ))
+        .into());
+    }
+    let listed = |select: fn(ArtifactKind) -> bool| -> Vec<PathBuf> {
+        let artifacts = manifest.iter().flat_map(|m| &m.artifacts);
+        existing(
+            artifacts
+                .filter(|a| select(a.kind))
+                .map(|a| output_path.join(&a.path))
+                .collect(),
+        )
+    };
+    let mut targets = Vec::new();
+
+    if args.progress || args.all {
+        let state = output_path.join(analyzer::STATE_DIR);
+        if state.is_dir() && !analyzer::is_state_dir(&state) {
+            warn!(
+                "Leaving {} alone: it holds no cda progress file",
+                state.display()
+            );
+        } else {
+            targets.extend(existing(vec![state]));
+        }
+        targets.extend(existing(vec![
+            output_path.join(analyzer::LEGACY_PROGRESS_FILE)
+        ]));
+        targets.extend(listed(|kind| kind == ArtifactKind::Progress));
+    }
+    if args.modules || args.all {
+        targets.extend(generated_pages(output_path, manifest.as_ref()));
+    }
+    if args.all {
+        if manifest.is_none() {
+            targets.extend(existing(
+                INDEX_FILES.iter().map(|f| output_path.join(f)).collect(),
+            ));
+        }
+        targets.extend(listed(|_| true));
+        targets.extend(existing(vec![output_path.join(MANIFEST_FILE)]));
+    }
+    let mut seen = HashSet::new();
+    targets.retain(|t| seen.insert(t.clone()));
+    if args.cache || args.all {
+        let cache_dir = config::project_dirs()?.cache_dir().to_path_buf();
+        targets.extend(existing(vec![cache_dir]));
+    }
+
+    // Pages other selections remove anyway are left to them
+    let mut orphans = if args.stale {
+        // Modules of the last analysis.json keep pages with made-safe names
+        let json = output_path.join("analysis.json");
+        let known = if json.is_file() {
+            output::load(&json)?.0.modules
+        } else {
+            Vec::new()
+        };
+        let root = match (&args.root, manifest.as_ref().and_then(|m| m.root.as_ref())) {
+            (Some(root), _) | (None, Some(root)) => PathBuf::from(root),
+            (None, None) => {
+                return Err(UsageError::new(format!(
+                    "{} doesn't record the directory that was analyzed; pass it with --root",
+                    output_path.display()
+                ))
+                .into())
+            }
+        };
+        let known: Vec<&str> = known.iter().map(|m| m.path.as_str()).collect();
+        let listed = manifest.as_ref().map(Manifest::pages).unwrap_or_default();
+        // With the wrong root every file would look gone
+        let mirrored = analyzer::page_naming() == PageNaming::MirrorTree;
+        let recorded: Vec<String> = known
+            .iter()
+            .map(|p| p.to_string())
+            .chain(analyzer::progress_files(output_path).into_keys())
+            .chain(
+                listed
+                    .iter()
+                    .filter(|page| mirrored && page.rsplit('/').next() != Some(DIRECTORY_PAGE))
+                    .map(|page| page.trim_end_matches(".md").to_string()),
+            )
+            .collect();
+        if !recorded.is_empty() && !recorded.iter().any(|p| root.join(p).is_file()) {
+            return Err(UsageError::new(format!(
+                "None of the {} files recorded in {} exist under {}; pass the directory \
+                 that was analyzed with --root",
+                recorded.len(),
+                output_path.display(),
+                root.display()
+            ))
+            .into());
+        }
+        orphans::find(output_path, &root, known, &listed)?
+    } else {
+        Orphans::default()
+    };
+    let modules_dir = output_path.join("modules");
+    orphans
+        .pages
+        .retain(|page| !targets.contains(&modules_dir.join(page)));
+
+    if targets.is_empty() && orphans.is_empty() {
+        println!("Nothing to clean.");
+        return Ok(());
+    }
+
+    println!("The following will be removed:");
+    for target in &targets {
+        println!("  {}", target.display());
+    }
+    for page in &orphans.pages {
+        println!("  {}", modules_dir.join(page).display());
+    }
+    if !orphans.files.is_empty() {
+        println!(
+            "  the output of {} source file(s) that no longer exist",
+            orphans.files.len()
+        );
+    }
+
+    if !args.yes && !confirm()? {
+        println!("Aborted.");
+        return Ok(());
+    }
+
+    for target in &targets {
+        if target.is_dir() {
+            fs::remove_dir_all(target)?;
+        } else {
+            fs::remove_file(target)?;
+        }
+    }
+    if !orphans.is_empty() {
+        orphans::remove(output_path, &orphans)?;
+        if !args.all {
+            orphans::record(output_path, &orphans)?;
+        }
+    }
+    if !args.all {
+        let removed: Vec<String> = targets
+            .iter()
+            .filter_map(|t| t.strip_prefix(output_path).ok())
+            .map(|rel| rel.to_string_lossy().replace('\\', "/"))
+            .chain(orphans.pages.iter().map(|page| format!("modules/{}", page)))
+            .collect();
+        manifest::forget(output_path, &removed)?;
+    }
+
+    // Drop directories of the modules tree only once they have nothing but
+    // our pages in them
+    if (args.modules || args.all) && modules_dir.is_dir() {
+        remove_empty_dirs(&modules_dir)?;
+    }
+
+    info!("Removed {} item(s)", targets.len() + orphans.pages.len());
+    Ok(())
+}
+
+fn existing(paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    paths.into_iter().filter(|p| p.exists()).collect()
+}
+
+/// Module and directory pages cda can be shown to have written: those
+/// `manifest` lists, and the pages of the files in a progress file with
+/// cda's header. Anything else in the `modules/` tree is left alone.
+pub fn generated_pages(output_path: &Path, manifest: Option<&Manifest>) -> Vec<PathBuf> {
+    let modules_dir = output_path.join("modules");
+    let listed = manifest
+        .into_iter()
+        .flat_map(|m| &m.artifacts)
+        .filter(|a| a.kind.is_page())
+        .map(|a| output_path.join(&a.path));
+    let recorded = if analyzer::has_progress(output_path) {
+        analyzer::progress_files(output_path)
+    } else {
+        Default::default()
+    };
+    let recorded = recorded
+        .into_keys()
+        .map(|path| modules_dir.join(analyzer::module_page_name(&path)));
+
+    let mut pages: Vec<PathBuf> = listed
+        .chain(recorded)
+        .filter(|page| page.symlink_metadata().is_ok_and(|m| m.is_file()))
+        .collect();
+    pages.sort();
+    pages.dedup();
+    pages
+}
+
+/// Remove `dir` and the directories below it that are empty once their
+/// empty subdirectories are gone. Returns whether `dir` was removed.
+pub fn remove_empty_dirs(dir: &Path) -> Result<bool> {
+    let mut empty = true;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if !(entry.file_type()?.is_dir() && remove_empty_dirs(&entry.path())?) {
+            empty = false;
+        }
+    }
+    if empty {
+        fs::remove_dir(dir)?;
+    }
+    Ok(empty)
+}
+
+fn confirm() -> Result<bool> {
+    if !io::stdin().is_terminal() {
+        anyhow::bail!(
+            "Refusing to delete without confirmation; pass --yes to run non-interactively"
+        );
+    }
+
+    print!("Proceed? [y/N] ");
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_only_touches_generated_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("modules/src/core")).unwrap();
+        fs::create_dir_all(root.join("modules/docs")).unwrap();
+        fs::write(root.join("modules/src/main.rs.md"), "").unwrap();
+        fs::write(root.join("modules/src/core/a.rs.md"), "").unwrap();
+        fs::write(root.join("modules/src/core/README.md"), "").unwrap();
+        fs::write(root.join("modules/docs/guide.md"), "").unwrap();
+        fs::write(root.join("modules/notes.txt"), "").unwrap();
+        fs::write(
+            root.join(".cda-progress"),
+            "# cda-progress ollama/llama3\nsrc/main.rs\tv3\nsrc/core/a.rs\tv3\n",
+        )
+        .unwrap();
+        fs::write(root.join("CODEBASE.md"), "").unwrap();
+        fs::write(root.join("Cargo.toml"), "").unwrap();
+
+        run(CleanArgs {
+            path: root.display().to_string(),
+            progress: true,
+            cache: false,
+            modules: true,
+            stale: false,
+            root: None,
+            all: false,
+            yes: true,
+        })
+        .unwrap();
+
+        // The pages of the files in the progress, and nothing else
+        assert!(!root.join(".cda-progress").exists());
+        assert!(!root.join("modules/src/main.rs.md").exists());
+        assert!(!root.join("modules/src/core/a.rs.md").exists());
+        assert!(root.join("modules/src/core/README.md").exists());
+        assert!(root.join("modules/docs/guide.md").exists());
+        assert!(root.join("modules/notes.txt").exists());
+        assert!(root.join("CODEBASE.md").exists());
+        assert!(root.join("Cargo.toml").exists());
+    }
+
+    #[test]
+    fn test_clean_refuses_a_directory_cda_did_not_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("modules/docs")).unwrap();
+        fs::write(root.join("modules/docs/guide.md"), "").unwrap();
+        // Older progress files without the header prove nothing either
+        fs::write(root.join(".cda-progress"), "docs/guide\n").unwrap();
+
+        let err = run(CleanArgs {
+            path: root.display().to_string(),
+            progress: true,
+            cache: false,
+            modules: true,
+            stale: false,
+            root: None,
+            all: false,
+            yes: true,
+        })
+        .unwrap_err();
+        assert!(err.downcast_ref::<UsageError>().is_some(), "{}", err);
+        assert!(err.to_string().contains("no manifest.json"), "{}", err);
+        assert!(root.join("modules/docs/guide.md").exists());
+        assert!(root.join(".cda-progress").exists());
+    }
+
+    #[test]
+    fn test_clean_follows_the_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("modules/src")).unwrap();
+        fs::write(root.join("modules/src/a.rs.md"), "").unwrap();
+        fs::write(root.join("CODEBASE.md"), "").unwrap();
+        manifest::write(
+            root,
+            None,
+            None,
+            &[
+                ("CODEBASE.md".to_string(), ArtifactKind::Index),
+                ("modules/src/a.rs.md".to_string(), ArtifactKind::ModulePage),
+            ],
+            false,
+        )
+        .unwrap();
+
+        run(CleanArgs {
+            path: root.display().to_string(),
+            progress: false,
+            cache: false,
+            modules: true,
+            stale: false,
+            root: None,
+            all: false,
+            yes: true,
+        })
+        .unwrap();
+        assert!(!root.join("modules").exists());
+        assert!(root.join("CODEBASE.md").exists());
+        let listed = Manifest::load(root).unwrap().unwrap();
+        let paths: Vec<_> = listed.artifacts.iter().map(|a| a.path.as_str()).collect();
+        assert_eq!(paths, ["CODEBASE.md"]);
+    }
+
+    #[test]
+    fn test_clean_stale() {
+        let source = tempfile::tempdir().unwrap();
+        fs::create_dir_all(source.path().join("src")).unwrap();
+        fs::write(source.path().join("src/a.rs"), "").unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("modules/src")).unwrap();
+        fs::write(root.join("modules/src/a.rs.md"), "").unwrap();
+        fs::write(root.join("modules/src/b.rs.md"), "").unwrap();
+        manifest::write(
+            root,
+            None,
+            None,
+            &[
+                ("modules/src/a.rs.md".to_string(), ArtifactKind::ModulePage),
+                ("modules/src/b.rs.md".to_string(), ArtifactKind::ModulePage),
+            ],
+            false,
+        )
+        .unwrap();
+
+        run(CleanArgs {
+            path: root.display().to_string(),
+            progress: false,
+            cache: false,
+            modules: false,
+            stale: true,
+            root: Some(source.path().display().to_string()),
+            all: false,
+            yes: true,
+        })
+        .unwrap();
+        assert!(root.join("modules/src/a.rs.md").exists());
+        assert!(!root.join("modules/src/b.rs.md").exists());
+        let changes = fs::read_to_string(root.join(orphans::CHANGES_FILE)).unwrap();
+        assert!(changes.contains("- `src/b.rs`"), "{}", changes);
+        let listed = Manifest::load(root).unwrap().unwrap();
+        let paths: Vec<_> = listed.artifacts.iter().map(|a| a.path.as_str()).collect();
+        assert_eq!(paths, ["modules/src/a.rs.md"]);
+    }
+
+    #[test]
+    fn test_clean_stale_root() {
+        let source = tempfile::tempdir().unwrap();
+        fs::create_dir_all(source.path().join("src")).unwrap();
+        fs::write(source.path().join("src/a.rs"), "").unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("modules/src")).unwrap();
+        fs::write(root.join("modules/src/a.rs.md"), "").unwrap();
+        fs::write(root.join("modules/src/b.rs.md"), "").unwrap();
+        let pages = [
+            ("modules/src/a.rs.md".to_string(), ArtifactKind::ModulePage),
+            ("modules/src/b.rs.md".to_string(), ArtifactKind::ModulePage),
+        ];
+        let clean = |root_arg: Option<&Path>| {
+            run(CleanArgs {
+                path: root.display().to_string(),
+                progress: false,
+                cache: false,
+                modules: false,
+                stale: true,
+                root: root_arg.map(|r| r.display().to_string()),
+                all: false,
+                yes: true,
+            })
+        };
+
+        // Without a recorded root, --root is needed
+        manifest::write(root, None, None, &pages, false).unwrap();
+        let error = clean(None).unwrap_err();
+        assert!(error.downcast_ref::<UsageError>().is_some());
+
+        // A root none of the files are under is refused
+        manifest::write(root, None, Some(source.path()), &pages, false).unwrap();
+        let elsewhere = tempfile::tempdir().unwrap();
+        let error = clean(Some(elsewhere.path())).unwrap_err();
+        assert!(
+            error.to_string().contains("None of the 2 files"),
+            "{}",
+            error
+        );
+        assert!(root.join("modules/src/a.rs.md").exists());
+
+        // The manifest's root is the default
+        clean(None).unwrap();
+        assert!(root.join("modules/src/a.rs.md").exists());
+        assert!(!root.join("modules/src/b.rs.md").exists());
+    }
+}
@@ -0,0 +1,67 @@
+//! Shell completion scripts generated by clap_complete from the clap command
+//! tree
+//!
+//! New subcommands, flags and value enums are picked up automatically.
+//! `--provider` and `--model` are free-form strings, so their candidates come
+//! from the llm module instead.
+
+use anyhow::Result;
+use clap::builder::PossibleValuesParser;
+use clap::{Command, CommandFactory};
+use clap_complete::Shell;
+
+use crate::llm;
+
+pub fn run(shell: Shell) -> Result<()> {
+    print!("{}", script(shell)?);
+    Ok(())
+}
+
+fn script(shell: Shell) -> Result<String> {
+    let mut cmd = with_candidates(crate::Cli::command());
+    let name = cmd.get_name().to_string();
+    let mut out = Vec::new();
+    clap_complete::generate(shell, &mut cmd, name, &mut out);
+    Ok(String::from_utf8(out)?)
+}
+
+/// `cmd` with the providers and their known models as the possible values
+/// of `--provider` and `--model`. Only the scripts see them: parsing goes
+/// through `Cli` itself, where any name is accepted.
+fn with_candidates(cmd: Command) -> Command {
+    let models: Vec<&'static str> = llm::PROVIDERS
+        .iter()
+        .flat_map(|p| llm::known_models(p))
+        .copied()
+        .collect();
+    cmd.mut_args(|arg| match arg.get_id().as_str() {
+        "provider" => arg.value_parser(PossibleValuesParser::new(llm::PROVIDERS)),
+        "model" => arg.value_parser(PossibleValuesParser::new(models.clone())),
+        _ => arg,
+    })
+    .mut_subcommands(with_candidates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Regenerate with `cargo run -- completions bash > src/commands/snapshots/cda.bash`
+    #[test]
+    fn test_bash_completions_snapshot() {
+        let expected = include_str!("snapshots/cda.bash");
+        let actual = script(Shell::Bash).unwrap();
+        assert_eq!(
+            actual, expected,
+            "CLI changed; regenerate src/commands/snapshots/cda.bash"
+        );
+    }
+
+    #[test]
+    fn test_completions_cover_value_enums_and_providers() {
+        let script = script(Shell::Bash).unwrap();
+        assert!(script.contains("markdown json"));
+        assert!(script.contains("anthropic openai ollama"));
+        assert!(script.contains("claude-sonnet-4-20250514"));
+    }
+}
@@ -1,9 +1,14 @@
 use anyhow::Result;
 use directories::ProjectDirs;
 use std::fs;
+use std::path::{Path, PathBuf};
+use toml_edit::{value, Array, DocumentMut};
 use tracing::info;
 
-const DEFAULT_CONFIG: &str = r#"# CDA Configuration
+use crate::config::{self, ConfigFile, KeyType};
+use crate::core::exit::{ConfigError, UsageError};
+
+pub const DEFAULT_CONFIG: &str = r#"# CDA Configuration
 # https://github.com/Bentlybro/codebase-deep-analyzer
 
 [llm]
@@ -16,9 +21,22 @@ provider = "anthropic"
 # ollama: llama3, codellama
 # model = "claude-sonnet-4-20250514"
 
+# Context window of the model in tokens, for models the built-in table
+# doesn't know or local models run with a different size
+# context_window = 8192
+
 [analysis]
-# Number of parallel workers for module analysis
-parallelism = 4
+# LLM requests in flight at once; keep within your provider's rate limit
+llm_concurrency = 4
+
+# Files parsed concurrently [default: number of CPU cores]
+# parse_jobs = 8
+
+# Run per-file LLM analysis by default (same as --deep)
+# deep = false
+
+# Record git contributors and CODEOWNERS owners per module (same as --ownership)
+# ownership = false
 
 # File patterns to ignore (in addition to .gitignore)
 ignore_patterns = [
@@ -34,7 +52,40 @@ ignore_patterns = [
 # Maximum file size to analyze (in bytes)
 max_file_size = 1048576  # 1MB
 
+# Seconds the LLM may spend on one file, over all its chunks and retries;
+# after that the chunks analyzed so far are kept and the run moves on
+# file_budget_secs = 600
+
+# Answers to a file's prompt shorter than this many characters (or half the
+# file, if less) are rejected as non-answers, as are refusals: the file is
+# asked for again once, then gets a static page. 0 accepts any length
+# min_response_chars = 80
+
+# Parses taking longer than this many milliseconds are logged and listed in
+# the timings; parses still running after parse_timeout_secs are abandoned and
+# the file is scanned for its items instead. 0 never abandons a parse
+# slow_parse_ms = 2000
+# parse_timeout_secs = 30
+
+# When this share of the last 10 LLM analyses failed (in percent), halve the
+# requests in flight and pause for backoff_cooldown_secs; after
+# backoff_max_cooldowns such pauses, finish the run without the LLM
+# backoff_failure_percent = 50
+# backoff_cooldown_secs = 30
+# backoff_max_cooldowns = 3
+
+# Leave type-only imports (TypeScript `import type`) out of circular
+# dependency gaps
+# ignore_type_only_cycles = false
+
+# Run the [plugins] of a repository's ./cda.toml (same as --allow-repo-plugins);
+# only read from this file, never from ./cda.toml itself
+# allow_repo_plugins = false
+
 [output]
+# Output directory for generated documentation
+# directory = "./cda-output"
+
 # Default output format: markdown, json
 format = "markdown"
 
@@ -43,31 +94,396 @@ include_snippets = true
 
 # Maximum snippet length (lines)
 max_snippet_lines = 20
+
+# Usages from elsewhere in the codebase shown with each export (0 for none)
+max_examples = 3
+
+# Language of the LLM-written prose (summaries, overview, descriptions),
+# e.g. "Japanese". Headings come from the prompt templates: translate them
+# with --templates for fully localized output
+# language = "English"
+
+# How pages under modules/ are named: mirror-tree (src/a.rs.md),
+# flat-mangled (src__a.rs.md) or hashed (3f9a1c0b7d2e-a.rs.md). Names
+# longer than 160 characters get a hash in place of their tail either way.
+# page_naming = "mirror-tree"
+
+# Raw HTML in the LLM's answers, which `cda serve` and other renderers would
+# otherwise run: escape (show it as text), strip (remove tags, and scripts
+# and styles with their content) or keep
+# raw_html = "escape"
+
+[gaps]
+# Severity of each kind of gap: info, warning or error. A circular
+# dependency through an entry point is one level more severe.
+# `--fail-on-gaps error` fails on any error.
+# missing_docs = "info"
+# circular_dependency = "warning"
+
+[doc_checks]
+# Style checks on the doc comments exported items have, each reported as
+# gaps of the same kind; all off by default
+# restated_docs = true        # the doc only repeats the item's name
+# undocumented_params = true  # a function's doc doesn't name all its parameters
+# short_docs = true           # the doc is shorter than min_length characters
+# min_params = 4              # parameters from which undocumented_params applies
+# min_length = 20
+
+# Named profiles override the settings above when selected with
+# --profile <name> or CDA_PROFILE, e.g.:
+#
+# [profile.frontend]
+# llm.provider = "ollama"
+# analysis.ignore_patterns = ["node_modules", "*.stories.tsx"]
+#
+# [profile.backend]
+# analysis.deep = true
+
+# External extractors for languages without a built-in parser. The command
+# prints the file's exports and imports as JSON (see the README), e.g.:
+#
+# [plugins.dsl]
+# command = ["python3", "tools/extract_dsl.py"]
+# input = "path"  # or "stdin"
+# timeout_secs = 10
+
+# Teams owning paths, for grouping gaps by owner ahead of CODEOWNERS. Patterns
+# are CODEOWNERS patterns; the longest matching one wins, e.g.:
+#
+# [owners]
+# "src/billing/" = "@org/billing"
+# "*.sql" = "@org/data"
 "#;
 
-pub fn run(init: bool) -> Result<()> {
-    let dirs = ProjectDirs::from("dev", "bentlybro", "cda")
-        .ok_or_else(|| anyhow::anyhow!("Could not determine config directory"))?;
+/// Platform-specific config and cache directories for cda
+pub fn project_dirs() -> Result<ProjectDirs> {
+    ProjectDirs::from("dev", "bentlybro", "cda")
+        .ok_or_else(|| anyhow::anyhow!("Could not determine config directory"))
+}
 
-    let config_path = dirs.config_dir().join("config.toml");
+pub struct ConfigArgs {
+    pub init: bool,
+    pub set: Option<(String, String)>,
+    pub get: Option<String>,
+    pub unset: Option<String>,
+    pub validate: bool,
+    /// Target the repo-local `cda.toml` instead of the user config
+    pub local: bool,
+    pub cli: ConfigFile,
+}
+
+pub fn run(args: ConfigArgs) -> Result<()> {
+    let explicit = config::explicit_path(&args.cli)?;
+    let config_path = match &explicit {
+        Some(path) if args.local => {
+            return Err(UsageError::new(format!(
+                "--local can't be combined with {}; it already names the file to use",
+                config::explicit_origin(path.source)
+            ))
+            .into())
+        }
+        Some(path) => path.value.clone(),
+        None if args.local => PathBuf::from(config::REPO_CONFIG_FILE),
+        None => config::user_config_path()?,
+    };
 
-    if init {
-        fs::create_dir_all(dirs.config_dir())?;
+    if args.init {
+        if let Some(dir) = config_path.parent() {
+            fs::create_dir_all(dir)?;
+        }
         fs::write(&config_path, DEFAULT_CONFIG)?;
         info!("Created config file at: {}", config_path.display());
-    } else if config_path.exists() {
-        let content = fs::read_to_string(&config_path)?;
-        println!("Config file: {}\n", config_path.display());
-        println!("{}", content);
-    } else {
-        println!("No config file found.");
-        println!("Run `cda config --init` to create one at:");
-        println!("  {}", config_path.display());
-        println!("\nOr use environment variables:");
-        println!("  CDA_PROVIDER=anthropic");
-        println!("  CDA_MODEL=claude-sonnet-4-20250514");
-        println!("  ANTHROPIC_API_KEY=sk-...");
+        return Ok(());
+    }
+
+    if args.validate {
+        return validate(&config_path, args.local || explicit.is_some());
+    }
+
+    if let Some((key, value)) = &args.set {
+        let content = read_or_empty(&config_path)?;
+        let updated = set_key(&content, key, value)?;
+        check_update(&config_path, &content, &updated)?;
+        if let Some(dir) = config_path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&config_path, updated)?;
+        info!("Set {} in {}", key, config_path.display());
+        return Ok(());
     }
 
+    if let Some(key) = &args.unset {
+        let content = read_or_empty(&config_path)?;
+        let updated = unset_key(&content, key)?;
+        check_update(&config_path, &content, &updated)?;
+        fs::write(&config_path, updated)?;
+        info!("Unset {} in {}", key, config_path.display());
+        return Ok(());
+    }
+
+    if let Some(key) = &args.get {
+        config::key_type(key)?;
+        if args.local {
+            let doc = read_or_empty(&config_path)?.parse::<DocumentMut>()?;
+            match lookup(&doc, key) {
+                Some(value) => println!("{}", value.to_string().trim()),
+                None => {
+                    return Err(ConfigError::wrap(anyhow::anyhow!(
+                        "{} is not set in {}",
+                        key,
+                        config_path.display()
+                    )))
+                }
+            }
+        } else {
+            let effective = config::load(args.cli)?;
+            // `analysis.parallelism` is the older name for LLM concurrency
+            let key = match key.as_str() {
+                "analysis.parallelism" => "analysis.llm_concurrency",
+                key => key,
+            };
+            match effective.entries().into_iter().find(|(k, _, _)| *k == key) {
+                Some((_, value, _)) => println!("{}", value),
+                None => return Err(ConfigError::wrap(anyhow::anyhow!("{} is not set", key))),
+            }
+        }
+        return Ok(());
+    }
+
+    let effective = config::load(args.cli)?;
+    if let Some(path) = &effective.config_path {
+        println!(
+            "Config file: {} (from {}; user and repo configs are not read)",
+            path.value.display(),
+            config::explicit_origin(path.source)
+        );
+        print_profiles(&effective);
+        println!("\nPrecedence: cli > env > profile > config file > default\n");
+        print_entries(&effective);
+        return Ok(());
+    }
+
+    match &effective.user_path {
+        Some(path) => println!("User config: {}", path.display()),
+        None => println!(
+            "User config: {} (not found, run `cda config --init`)",
+            config_path.display()
+        ),
+    }
+    match &effective.repo_path {
+        Some(path) => println!("Repo config: {}", path.display()),
+        None => println!("Repo config: ./{} (not found)", config::REPO_CONFIG_FILE),
+    }
+    print_profiles(&effective);
+    println!("\nPrecedence: cli > env > profile > repo config > user config > default\n");
+    print_entries(&effective);
+
     Ok(())
 }
+
+fn print_profiles(effective: &config::Config) {
+    match &effective.profile.value {
+        Some(name) => println!("Profile:     {} ({})", name, effective.profile.source),
+        None => println!("Profile:     none"),
+    }
+    if !effective.profiles.is_empty() {
+        println!(
+            "Profiles:    {} (select with --profile or CDA_PROFILE)",
+            effective.profiles.join(", ")
+        );
+    }
+}
+
+fn print_entries(effective: &config::Config) {
+    let entries = effective.entries();
+    let width = entries.iter().map(|(k, _, _)| k.len()).max().unwrap_or(0);
+    for (key, value, source) in entries {
+        println!("{:<width$}  {}  ({})", key, value, source, width = width);
+    }
+}
+
+/// Validate the target config, or both the user and repo configs when not
+/// restricted to `--local` or an explicit `--config` file
+fn validate(config_path: &Path, only_target: bool) -> Result<()> {
+    let mut paths = vec![config_path.to_path_buf()];
+    if !only_target {
+        paths.push(PathBuf::from(config::REPO_CONFIG_FILE));
+    }
+
+    let mut failed = false;
+    for path in paths.iter().filter(|p| p.exists()) {
+        let diagnostics = config::validate(&fs::read_to_string(path)?);
+        if diagnostics.is_empty() {
+            println!("{}: ok", path.display());
+        } else {
+            println!("{}", config::format_diagnostics(path, &diagnostics));
+            failed = true;
+        }
+    }
+
+    if !paths.iter().any(|p| p.exists()) {
+        println!("No config files found");
+    }
+    if failed {
+        return Err(ConfigError::wrap(anyhow::anyhow!(
+            "Config validation failed"
+        )));
+    }
+    Ok(())
+}
+
+/// Refuse an edit of the config file at `path` from `content` to `updated`
+/// that [`config::validate`] finds new problems in, so a file `--set` or
+/// `--unset` writes keeps loading. Problems the file already had don't
+/// block it, and an edit can fix them.
+fn check_update(path: &Path, content: &str, updated: &str) -> Result<()> {
+    let before: Vec<String> = config::validate(content)
+        .into_iter()
+        .map(|d| d.message)
+        .collect();
+    let added: Vec<_> = config::validate(updated)
+        .into_iter()
+        .filter(|d| !before.contains(&d.message))
+        .collect();
+    if added.is_empty() {
+        return Ok(());
+    }
+    Err(ConfigError::wrap(anyhow::anyhow!(
+        "{}\n{} was left unchanged",
+        config::format_diagnostics(path, &added),
+        path.display()
+    )))
+}
+
+pub fn read_or_empty(path: &Path) -> Result<String> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(content),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn split_key(key: &str) -> Result<(&str, &str)> {
+    key.split_once('.')
+        .ok_or_else(|| anyhow::anyhow!("Config keys look like `section.name`, got `{}`", key))
+}
+
+fn lookup<'a>(doc: &'a DocumentMut, key: &str) -> Option<&'a toml_edit::Item> {
+    let (section, name) = key.split_once('.')?;
+    doc.get(section)?.get(name)
+}
+
+/// Set a key in a TOML document, keeping comments and unrelated keys intact
+pub fn set_key(content: &str, key: &str, raw: &str) -> Result<String> {
+    if config::is_credential_key(key) {
+        anyhow::bail!(
+            "Refusing to store `{}` in a config file. Provide credentials through the \
+             provider's environment variable (e.g. ANTHROPIC_API_KEY) instead",
+            key
+        );
+    }
+
+    let value = match config::key_type(key)? {
+        KeyType::String => value(raw),
+        KeyType::Integer => value(
+            raw.parse::<i64>()
+                .map_err(|_| anyhow::anyhow!("`{}` expects a number, got `{}`", key, raw))?,
+        ),
+        KeyType::Bool => value(
+            raw.parse::<bool>()
+                .map_err(|_| anyhow::anyhow!("`{}` expects true or false, got `{}`", key, raw))?,
+        ),
+        KeyType::StringList => {
+            let items: Array = raw
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .collect();
+            value(items)
+        }
+    };
+
+    let mut doc = content.parse::<DocumentMut>()?;
+    let (section, name) = split_key(key)?;
+    if !doc.contains_table(section) {
+        doc[section] = toml_edit::table();
+    }
+    doc[section][name] = value;
+    Ok(doc.to_string())
+}
+
+fn unset_key(content: &str, key: &str) -> Result<String> {
+    config::key_type(key)?;
+    let (section, name) = split_key(key)?;
+
+    let mut doc = content.parse::<DocumentMut>()?;
+    if let Some(table) = doc.get_mut(section).and_then(|t| t.as_table_like_mut()) {
+        table.remove(name);
+    }
+    Ok(doc.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_preserves_comments_and_unknown_keys() {
+        let content =
+            "# my settings\n[llm]\n# which backend\nprovider = \"anthropic\"\ncustom = 1\n";
+        let updated = set_key(content, "llm.provider", "ollama").unwrap();
+        assert!(updated.contains("# my settings"));
+        assert!(updated.contains("# which backend"));
+        assert!(updated.contains("provider = \"ollama\""));
+        assert!(updated.contains("custom = 1"));
+
+        let updated = set_key(&updated, "analysis.parallelism", "8").unwrap();
+        assert!(updated.contains("[analysis]\nparallelism = 8"));
+
+        let updated = unset_key(&updated, "llm.provider").unwrap();
+        assert!(!updated.contains("provider"));
+        assert!(updated.contains("custom = 1"));
+    }
+
+    #[test]
+    fn test_set_validates_keys_and_values() {
+        let err = set_key("", "llm.provdier", "ollama")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Did you mean `llm.provider`?"));
+        assert!(set_key("", "analysis.parallelism", "many").is_err());
+        assert!(set_key("", "llm.api_key", "sk-123").is_err());
+
+        let updated = set_key("", "analysis.ignore_patterns", "target, dist").unwrap();
+        assert!(updated.contains(r#"ignore_patterns = ["target", "dist"]"#));
+    }
+
+    #[test]
+    fn test_updates_are_validated() {
+        let path = Path::new("config.toml");
+        for (key, value) in [
+            ("analysis.parallelism", "0"),
+            ("llm.provider", "nonsense"),
+            ("output.format", "yaml"),
+        ] {
+            let updated = set_key("", key, value).unwrap();
+            let err = check_update(path, "", &updated).unwrap_err();
+            assert!(err.downcast_ref::<ConfigError>().is_some(), "{}", key);
+            assert!(
+                err.to_string()
+                    .contains("Invalid config config.toml:\n  line "),
+                "{}",
+                err
+            );
+        }
+
+        // An existing problem doesn't block other edits, and can be fixed
+        let broken = "[analysis]\nparallelism = 0\n";
+        let updated = set_key(broken, "output.format", "json").unwrap();
+        check_update(path, broken, &updated).unwrap();
+        let fixed = set_key(broken, "analysis.parallelism", "4").unwrap();
+        check_update(path, broken, &fixed).unwrap();
+        let unset = unset_key(&updated, "output.format").unwrap();
+        check_update(path, &updated, &unset).unwrap();
+    }
+}
@@ -0,0 +1,338 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::config::{self, ConfigFile};
+use crate::llm;
+use crate::output::Format;
+
+pub struct DoctorArgs {
+    pub cli: ConfigFile,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug, Serialize)]
+struct Check {
+    name: String,
+    status: Status,
+    detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hint: Option<String>,
+}
+
+impl Check {
+    fn new(name: impl Into<String>, status: Status, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+}
+
+pub async fn run(args: DoctorArgs) -> Result<()> {
+    let mut checks = Vec::new();
+
+    if let Some(path) = config::explicit_path(&args.cli)? {
+        let origin = config::explicit_origin(path.source);
+        let mut check = config_file("config file", &path.value, "");
+        if check.status == Status::Warn {
+            check = Check::new(
+                "config file",
+                Status::Fail,
+                format!("{} (from {}) not found", path.value.display(), origin),
+            )
+            .hint(format!("Fix the {} path", origin));
+        }
+        checks.push(check);
+    } else {
+        let user_path = config::user_config_path()?;
+        checks.push(config_file("user config", &user_path, "Run `cda init`"));
+        checks.push(config_file(
+            "repo config",
+            Path::new(config::REPO_CONFIG_FILE),
+            "Optional; `cda init --local` creates one",
+        ));
+        if let Some(check) = misplaced_repo_config() {
+            checks.push(check);
+        }
+    }
+
+    let effective = match config::load(args.cli) {
+        Ok(config) => config,
+        Err(e) => {
+            checks.push(
+                Check::new("config", Status::Fail, e.to_string())
+                    .hint("Fix the config, then check it with `cda config --validate`"),
+            );
+            config::Layers::default().merge()
+        }
+    };
+
+    let default_provider = llm::canonical_provider(&effective.provider.value);
+    if default_provider.is_none() {
+        checks.push(
+            Check::new(
+                "llm.provider",
+                Status::Fail,
+                format!("unknown provider `{}`", effective.provider.value),
+            )
+            .hint(format!("Use one of {}", llm::PROVIDERS.join(", "))),
+        );
+    }
+    for name in llm::PROVIDERS {
+        let is_default = default_provider == Some(*name);
+        let model = effective.model.value.as_deref().filter(|_| is_default);
+        checks.push(provider(name, model, is_default).await);
+    }
+
+    checks.push(path_check("output", Path::new(&effective.output.value)));
+    checks.push(path_check(
+        "cache",
+        crate::commands::config::project_dirs()?.cache_dir(),
+    ));
+    checks.push(git());
+
+    match effective.format.value {
+        Format::Json => println!("{}", serde_json::to_string_pretty(&checks)?),
+        Format::Markdown => print_checks(&checks),
+    }
+
+    let failed = checks.iter().filter(|c| c.status == Status::Fail).count();
+    if failed > 0 {
+        anyhow::bail!("{} required check(s) failed", failed);
+    }
+    Ok(())
+}
+
+fn config_file(name: &str, path: &Path, missing_hint: &str) -> Check {
+    if !path.exists() {
+        return Check::new(
+            name,
+            Status::Warn,
+            format!("{} not found, using defaults", path.display()),
+        )
+        .hint(missing_hint);
+    }
+
+    let diagnostics = match std::fs::read_to_string(path) {
+        Ok(content) => config::validate(&content),
+        Err(e) => {
+            return Check::new(name, Status::Fail, format!("{}: {}", path.display(), e));
+        }
+    };
+    if diagnostics.is_empty() {
+        Check::new(name, Status::Pass, format!("{} parses", path.display()))
+    } else {
+        Check::new(
+            name,
+            Status::Fail,
+            config::format_diagnostics(path, &diagnostics),
+        )
+        .hint("Run `cda config --validate` after fixing")
+    }
+}
+
+/// `cda.toml` is only read from the current directory, so one at the git
+/// root is silently ignored when running from a subdirectory
+fn misplaced_repo_config() -> Option<Check> {
+    if Path::new(config::REPO_CONFIG_FILE).exists() {
+        return None;
+    }
+    let output = Command::new("git")
+        .args(["rev-parse", "--show-toplevel"])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())?;
+    let root = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+    let candidate = root.join(config::REPO_CONFIG_FILE);
+
+    candidate.exists().then(|| {
+        Check::new(
+            "repo config",
+            Status::Warn,
+            format!("{} is not read from this directory", candidate.display()),
+        )
+        .hint(format!("Run cda from {}", root.display()))
+    })
+}
+
+/// List the provider's models: a free request that proves the credentials
+/// and endpoint work, and shows whether the configured model is offered.
+/// Only the configured provider is required to pass.
+async fn provider(name: &str, model: Option<&str>, is_default: bool) -> Check {
+    let label = format!("provider {}", name);
+    let problem = if is_default {
+        Status::Fail
+    } else {
+        Status::Warn
+    };
+
+    let provider = match llm::get_provider(name, model) {
+        Ok(provider) => provider,
+        Err(e) if is_default => {
+            return Check::new(label, problem, e.to_string()).hint(llm::credential_hint(name))
+        }
+        Err(e) => return Check::new(label, Status::Warn, format!("not configured ({})", e)),
+    };
+
+    let models = match provider.list_models().await {
+        Ok(models) => models,
+        Err(e) => {
+            let message = e.to_string();
+            let hint = if message.contains("401") || message.contains("403") {
+                format!("The API key was rejected. {}", llm::credential_hint(name))
+            } else {
+                connectivity_hint(name)
+            };
+            return Check::new(label, problem, message).hint(hint);
+        }
+    };
+
+    if !is_default && name != "ollama" {
+        return Check::new(
+            label,
+            Status::Pass,
+            format!("credentials ok, {} models", models.len()),
+        );
+    }
+
+    let model = provider.model();
+    if models.iter().any(|m| llm::model_matches(model, &m.id)) {
+        Check::new(
+            label,
+            Status::Pass,
+            format!("reachable, model `{}` available", model),
+        )
+    } else {
+        let hint = if name == "ollama" {
+            format!("Run `ollama pull {}`", model)
+        } else {
+            format!("Pick one from `cda models --provider {}`", name)
+        };
+        Check::new(
+            label,
+            problem,
+            format!("reachable, but model `{}` is not available", model),
+        )
+        .hint(hint)
+    }
+}
+
+fn connectivity_hint(provider: &str) -> String {
+    if provider == "ollama" {
+        return llm::credential_hint(provider).to_string();
+    }
+
+    let proxy = ["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok().map(|v| (var, v)));
+    match proxy {
+        Some((var, value)) => format!(
+            "Could not reach the {} API through the proxy in {} ({}); check that it allows the connection",
+            provider, var, value
+        ),
+        None => format!(
+            "Could not reach the {} API; check your network, or set HTTPS_PROXY if you need a proxy",
+            provider
+        ),
+    }
+}
+
+/// A directory cda writes to: fine if it exists and is writable, or if it
+/// can be created
+fn path_check(name: &str, path: &Path) -> Check {
+    let existing = path.ancestors().find(|p| p.exists());
+    match existing {
+        Some(dir) if dir.metadata().is_ok_and(|m| m.permissions().readonly()) => Check::new(
+            name,
+            Status::Fail,
+            format!("{} is not writable", dir.display()),
+        )
+        .hint(format!("Fix permissions or choose another {} path", name)),
+        Some(dir) if dir == path => Check::new(name, Status::Pass, path.display().to_string()),
+        _ => Check::new(
+            name,
+            Status::Pass,
+            format!("{} (will be created)", path.display()),
+        ),
+    }
+}
+
+fn git() -> Check {
+    match Command::new("git").arg("--version").output() {
+        Ok(output) if output.status.success() => Check::new(
+            "git",
+            Status::Pass,
+            String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        ),
+        _ => Check::new("git", Status::Warn, "git not found on PATH")
+            .hint("Install git to use `cda analyze --changed-only`"),
+    }
+}
+
+fn print_checks(checks: &[Check]) {
+    let width = checks.iter().map(|c| c.name.len()).max().unwrap_or(0);
+    for check in checks {
+        let status = match check.status {
+            Status::Pass => "pass",
+            Status::Warn => "warn",
+            Status::Fail => "FAIL",
+        };
+        println!(
+            "[{}] {:<width$}  {}",
+            status,
+            check.name,
+            check.detail.replace('\n', "\n        "),
+            width = width
+        );
+        if let Some(hint) = &check.hint {
+            println!("       {:<width$}  → {}", "", hint, width = width);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_file_check() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cda.toml");
+
+        let check = config_file("repo config", &path, "hint");
+        assert_eq!(check.status, Status::Warn);
+
+        std::fs::write(&path, "[analysis]\nparallelism = 2\n").unwrap();
+        assert_eq!(config_file("repo config", &path, "").status, Status::Pass);
+
+        std::fs::write(&path, "[analysis]\nparallelism = 0\n").unwrap();
+        let check = config_file("repo config", &path, "");
+        assert_eq!(check.status, Status::Fail);
+        assert!(check.detail.contains("line 2"));
+    }
+
+    #[test]
+    fn test_path_check() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(path_check("output", dir.path()).status, Status::Pass);
+        let check = path_check("output", &dir.path().join("new/docs"));
+        assert_eq!(check.status, Status::Pass);
+        assert!(check.detail.ends_with("(will be created)"));
+    }
+}
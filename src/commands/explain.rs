@@ -0,0 +1,146 @@
+use anyhow::Result;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+use crate::core::analyzer::{self, Completeness};
+use crate::core::discovery::{self, Language};
+use crate::core::exit::UsageError;
+use crate::core::parser;
+use crate::core::plugins::{self, PluginInput};
+use crate::core::source::{self, SourceText};
+use crate::core::templates;
+
+pub struct ExplainArgs {
+    /// Source file to explain, or `-` for stdin
+    pub file: String,
+    pub language: Option<String>,
+    pub provider: String,
+    pub model: Option<String>,
+    pub static_only: bool,
+    /// Lines of source to show per export, if any
+    pub snippet_lines: Option<usize>,
+}
+
+pub async fn run(args: ExplainArgs) -> Result<()> {
+    let (display_path, bytes) = if args.file == "-" {
+        let mut bytes = Vec::new();
+        io::stdin().read_to_end(&mut bytes)?;
+        ("<stdin>".to_string(), bytes)
+    } else {
+        (args.file.clone(), std::fs::read(&args.file)?)
+    };
+    let content = match source::decode(&bytes) {
+        SourceText::Text {
+            content,
+            encoding_warning,
+        } => {
+            if let Some(warning) = encoding_warning {
+                warn!("{}: {}", display_path, warning);
+            }
+            content
+        }
+        SourceText::Binary => anyhow::bail!("{} looks like a binary file", display_path),
+    };
+
+    // `--language` may also name the extension of a configured plugin
+    let extension = match &args.language {
+        Some(name) => Some(name.trim_start_matches('.')),
+        None => Path::new(&args.file).extension().and_then(|e| e.to_str()),
+    };
+    let plugin = extension.and_then(plugins::lookup);
+    let language = match &args.language {
+        Some(name) => Language::from_name(name),
+        None => extension.map(Language::from_extension).unwrap_or_default(),
+    };
+    let language = match language {
+        Language::Unknown if plugin.is_some() => Language::Plugin,
+        language => language,
+    };
+
+    if language == Language::Unknown && args.file == "-" {
+        return Err(UsageError::new("Cannot detect language from stdin; pass --language").into());
+    }
+
+    let parse_path = match (&plugin, extension) {
+        (Some(plugin), Some(extension)) if args.file == "-" => {
+            if plugin.input != PluginInput::Stdin {
+                anyhow::bail!(
+                    "The plugin for .{} reads files by path; set `input = \"stdin\"` in \
+                     [plugins.{}] to explain from stdin",
+                    extension,
+                    extension
+                );
+            }
+            PathBuf::from(format!("<stdin>.{}", extension))
+        }
+        _ => PathBuf::from(&args.file),
+    };
+    let mut parse_result = parser::parse_file(&parse_path, &content, language, false)?;
+    if let Some(max_lines) = args.snippet_lines {
+        analyzer::attach_snippets(&mut parse_result.exports, &content, max_lines);
+    }
+
+    let mut rejected = false;
+    let deep = if args.static_only {
+        None
+    } else {
+        let provider = crate::llm::get_provider(&args.provider, args.model.as_deref())?;
+        let sent = if analyzer::deep_mode().sends_source() {
+            content.len()
+        } else {
+            0
+        };
+        let budget = analyzer::static_context_budget(provider.context_window(), sent);
+        let static_context =
+            analyzer::build_static_context_from_parse(&display_path, &parse_result, budget);
+        let file_name = Path::new(&args.file)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("");
+        let is_test = args.file != "-" && discovery::is_test_file(&args.file, file_name);
+        let template = templates::select(&display_path, language, is_test);
+        match analyzer::analyze_module_with_llm_retry(
+            provider.as_ref(),
+            &display_path,
+            template,
+            &content,
+            &static_context,
+            3,
+        )
+        .await
+        {
+            Ok((analysis, completeness)) => Some((analysis, completeness, template)),
+            Err(e) if e.is::<analyzer::LowQualityResponse>() => {
+                warn!(
+                    "Rejected the LLM's answer: {}; showing the static analysis",
+                    e
+                );
+                rejected = true;
+                None
+            }
+            Err(e) => return Err(e),
+        }
+    };
+
+    let page = analyzer::render_module_markdown(
+        &display_path,
+        &[],
+        language,
+        &parse_result,
+        deep.as_ref().map(|(analysis, _, _)| analysis.as_str()),
+        deep.as_ref().map_or(
+            if rejected {
+                Completeness::Rejected
+            } else {
+                Completeness::StaticOnly
+            },
+            |(_, completeness, _)| *completeness,
+        ),
+        deep.as_ref().map(|(_, _, template)| *template),
+        None,
+    )?;
+    print!("{}", page);
+
+    Ok(())
+}
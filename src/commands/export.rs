@@ -0,0 +1,89 @@
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::core::exit::UsageError;
+use crate::core::ownership;
+use crate::output::manifest::{self, ArtifactKind, Manifest};
+use crate::output::{self, Format};
+
+pub struct ExportArgs {
+    /// analysis.json written by `cda analyze --format json`, or an output
+    /// directory holding one
+    pub input: String,
+    /// Defaults to `input` when it is a directory, otherwise the directory
+    /// containing it
+    pub output: Option<String>,
+    pub formats: Vec<Format>,
+    /// Keep only the gaps this team owns
+    pub owner: Option<String>,
+}
+
+pub async fn run(args: ExportArgs) -> Result<()> {
+    let input = analysis_file(Path::new(&args.input))?;
+    let input = input.as_path();
+    let (analysis, mut crossref) = output::load(input)?;
+
+    let output_path = match &args.output {
+        Some(dir) => Path::new(dir).to_path_buf(),
+        None if Path::new(&args.input).is_dir() => PathBuf::from(&args.input),
+        None => input
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(Path::new("."))
+            .to_path_buf(),
+    };
+
+    info!(
+        "Loaded {} modules and {} gaps from {}",
+        analysis.modules.len(),
+        crossref.gaps.len(),
+        input.display()
+    );
+
+    if let Some(team) = &args.owner {
+        if crossref.gaps.iter().all(|g| g.owner.is_none()) {
+            return Err(UsageError::new(format!(
+                "The gaps in {} have no owners; add a CODEOWNERS file or an [owners] \
+                 config section and run `cda analyze` again",
+                input.display()
+            ))
+            .into());
+        }
+        let total = crossref.gaps.len();
+        crossref.gaps.retain(|g| {
+            g.owner
+                .as_deref()
+                .is_some_and(|o| ownership::owned_by(o, team))
+        });
+        info!(
+            "Kept the {} of {} gaps owned by {}",
+            crossref.gaps.len(),
+            total,
+            team
+        );
+    }
+
+    for format in &args.formats {
+        output::generate(&analysis, &crossref, &output_path, *format)?;
+        manifest::update(
+            &output_path,
+            &manifest::outputs(&analysis, &output_path, *format),
+        )?;
+        info!("Wrote {:?} output to {}", format, output_path.display());
+    }
+
+    Ok(())
+}
+
+/// `input` itself, or for an output directory the analysis.json its
+/// manifest lists
+fn analysis_file(input: &Path) -> Result<PathBuf> {
+    if !input.is_dir() {
+        return Ok(input.to_path_buf());
+    }
+    output::compat::check(input)?;
+    let listed = Manifest::load(input)?
+        .and_then(|m| m.find(ArtifactKind::Analysis).map(|a| input.join(&a.path)));
+    Ok(listed.unwrap_or_else(|| input.join("analysis.json")))
+}
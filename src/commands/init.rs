@@ -0,0 +1,385 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::commands::config::{self as config_cmd, DEFAULT_CONFIG};
+use crate::config::{self, DEFAULT_PARALLELISM};
+use crate::core::discovery::IGNORE_FILE;
+use crate::core::exit::UsageError;
+use crate::llm::{self, LlmConfig, Message, Role};
+use crate::output::Format;
+
+const REPO_CONFIG: &str = r#"# Repo-local CDA settings; these override the user config
+# See `cda config` for every key and where its effective value comes from
+
+# Profile to use when neither --profile nor CDA_PROFILE is given
+# default_profile = "docs"
+
+[analysis]
+# parse_jobs = 8
+# llm_concurrency = 4
+# deep = false
+
+[output]
+# directory = "./cda-output"
+
+# Named profiles override the settings above, e.g. `cda --profile docs analyze`
+# [profile.docs]
+# llm.provider = "ollama"
+# analysis.deep = true
+"#;
+
+const IGNORE_TEMPLATE: &str = r#"# Paths cda should skip, in addition to .gitignore (gitignore syntax)
+# vendor/
+# generated/
+# *.pb.rs
+"#;
+
+pub struct InitArgs {
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub llm_concurrency: Option<usize>,
+    pub format: Option<Format>,
+    /// Also create `./cda.toml` and `./.cdaignore`
+    pub local: bool,
+    /// Accept defaults without prompting
+    pub yes: bool,
+    pub skip_verify: bool,
+}
+
+/// Answers collected by the wizard
+#[derive(Debug, PartialEq)]
+struct Choices {
+    provider: String,
+    model: Option<String>,
+    llm_concurrency: usize,
+    format: Format,
+    local: bool,
+}
+
+pub async fn run(args: InitArgs) -> Result<()> {
+    let interactive = !args.yes;
+    if interactive && !io::stdin().is_terminal() {
+        return Err(UsageError::new(
+            "cda init is interactive; pass --provider <name> --yes to run from scripts",
+        )
+        .into());
+    }
+
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    let mut out = io::stdout();
+
+    let choices = if interactive {
+        ask_choices(&args, &mut input, &mut out)?
+    } else {
+        Choices {
+            provider: args.provider.clone().ok_or_else(|| {
+                UsageError::new(format!(
+                    "--yes requires --provider ({})",
+                    llm::PROVIDERS.join(", ")
+                ))
+            })?,
+            model: args.model.clone(),
+            llm_concurrency: args.llm_concurrency.unwrap_or(DEFAULT_PARALLELISM),
+            format: args.format.unwrap_or_default(),
+            local: args.local,
+        }
+    };
+
+    let provider = llm::canonical_provider(&choices.provider).ok_or_else(|| {
+        UsageError::new(format!(
+            "Unknown LLM provider: {}. Supported: {}",
+            choices.provider,
+            llm::PROVIDERS.join(", ")
+        ))
+    })?;
+
+    if !args.skip_verify {
+        writeln!(out, "Checking {} credentials...", provider)?;
+        match verify(provider, choices.model.as_deref()).await {
+            Ok(()) => writeln!(out, "  ok")?,
+            Err(e) => {
+                writeln!(out, "  failed: {}", e)?;
+                writeln!(out, "  {}", llm::credential_hint(provider))?;
+                let keep_going = interactive
+                    && ask_bool(&mut input, &mut out, "Save the config anyway?", false)?;
+                if !keep_going {
+                    anyhow::bail!(
+                        "Could not verify the {} provider; fix the above or pass --skip-verify",
+                        provider
+                    );
+                }
+            }
+        }
+    }
+
+    let user_path = config::user_config_path()?;
+    write_user_config(&user_path, provider, &choices)?;
+    info!("Wrote {}", user_path.display());
+
+    if choices.local {
+        create_if_missing(Path::new(config::REPO_CONFIG_FILE), REPO_CONFIG)?;
+        create_if_missing(Path::new(IGNORE_FILE), IGNORE_TEMPLATE)?;
+    }
+
+    writeln!(out, "\nSetup complete. Next, run:\n")?;
+    writeln!(out, "  {}", next_command(provider))?;
+    Ok(())
+}
+
+fn ask_choices(args: &InitArgs, input: &mut impl BufRead, out: &mut impl Write) -> Result<Choices> {
+    let provider = loop {
+        let answer = ask(
+            input,
+            out,
+            &format!("LLM provider ({})", llm::PROVIDERS.join("/")),
+            args.provider.as_deref().unwrap_or(config::DEFAULT_PROVIDER),
+        )?;
+        match llm::canonical_provider(&answer) {
+            Some(p) => break p.to_string(),
+            None => writeln!(out, "  Unknown provider `{}`", answer)?,
+        }
+    };
+
+    let default_model = args
+        .model
+        .clone()
+        .or_else(|| llm::known_models(&provider).first().map(|m| m.to_string()))
+        .unwrap_or_default();
+    let model = ask(input, out, "Model", &default_model)?;
+
+    let llm_concurrency = loop {
+        let answer = ask(
+            input,
+            out,
+            "Concurrent LLM requests",
+            &args
+                .llm_concurrency
+                .unwrap_or(DEFAULT_PARALLELISM)
+                .to_string(),
+        )?;
+        match answer.parse::<usize>() {
+            Ok(n) if n > 0 => break n,
+            _ => writeln!(out, "  Enter a number of at least 1")?,
+        }
+    };
+
+    let format = loop {
+        let default = args.format.unwrap_or_default();
+        let answer = ask(
+            input,
+            out,
+            "Output format (markdown/json)",
+            default.to_possible_value().unwrap().get_name(),
+        )?;
+        match Format::from_str(&answer, true) {
+            Ok(format) => break format,
+            Err(_) => writeln!(out, "  Enter markdown or json")?,
+        }
+    };
+
+    let local = ask_bool(
+        input,
+        out,
+        &format!(
+            "Create ./{} and ./{} here?",
+            config::REPO_CONFIG_FILE,
+            IGNORE_FILE
+        ),
+        args.local,
+    )?;
+
+    Ok(Choices {
+        provider,
+        model: Some(model).filter(|m| !m.is_empty()),
+        llm_concurrency,
+        format,
+        local,
+    })
+}
+
+/// Prompt for a value, returning `default` on an empty answer
+fn ask(
+    input: &mut impl BufRead,
+    out: &mut impl Write,
+    question: &str,
+    default: &str,
+) -> Result<String> {
+    if default.is_empty() {
+        write!(out, "{}: ", question)?;
+    } else {
+        write!(out, "{} [{}]: ", question, default)?;
+    }
+    out.flush()?;
+
+    let mut answer = String::new();
+    if input.read_line(&mut answer)? == 0 {
+        anyhow::bail!("Setup cancelled");
+    }
+    let answer = answer.trim();
+    Ok(if answer.is_empty() { default } else { answer }.to_string())
+}
+
+fn ask_bool(
+    input: &mut impl BufRead,
+    out: &mut impl Write,
+    question: &str,
+    default: bool,
+) -> Result<bool> {
+    let hint = if default { "Y/n" } else { "y/N" };
+    write!(out, "{} [{}] ", question, hint)?;
+    out.flush()?;
+
+    let mut answer = String::new();
+    if input.read_line(&mut answer)? == 0 {
+        anyhow::bail!("Setup cancelled");
+    }
+    Ok(match answer.trim().to_lowercase().as_str() {
+        "" => default,
+        a => matches!(a, "y" | "yes"),
+    })
+}
+
+/// Make the smallest possible completion call to check credentials and model
+async fn verify(provider: &str, model: Option<&str>) -> Result<()> {
+    let provider = llm::get_provider(provider, model)?;
+    provider
+        .complete(
+            vec![Message {
+                role: Role::User,
+                content: "Reply with OK".to_string(),
+            }],
+            LlmConfig {
+                max_tokens: 1,
+                ..Default::default()
+            },
+        )
+        .await?;
+    Ok(())
+}
+
+/// Update the user config in place, starting from the template on first run
+fn write_user_config(path: &Path, provider: &str, choices: &Choices) -> Result<()> {
+    let existing = config_cmd::read_or_empty(path)?;
+    let mut content = if existing.trim().is_empty() {
+        DEFAULT_CONFIG.to_string()
+    } else {
+        existing
+    };
+
+    content = config_cmd::set_key(&content, "llm.provider", provider)?;
+    if let Some(model) = &choices.model {
+        content = config_cmd::set_key(&content, "llm.model", model)?;
+    }
+    content = config_cmd::set_key(
+        &content,
+        "analysis.llm_concurrency",
+        &choices.llm_concurrency.to_string(),
+    )?;
+    content = config_cmd::set_key(
+        &content,
+        "output.format",
+        choices.format.to_possible_value().unwrap().get_name(),
+    )?;
+
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, content)?;
+    Ok(())
+}
+
+fn create_if_missing(path: &Path, content: &str) -> Result<()> {
+    if path.exists() {
+        info!("{} already exists, leaving it as is", path.display());
+    } else {
+        fs::write(path, content)?;
+        info!("Created {}", path.display());
+    }
+    Ok(())
+}
+
+fn next_command(provider: &str) -> String {
+    let output = PathBuf::from(config::DEFAULT_OUTPUT);
+    format!(
+        "cda analyze . --provider {} --output {}",
+        provider,
+        output.display()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args() -> InitArgs {
+        InitArgs {
+            provider: None,
+            model: None,
+            llm_concurrency: None,
+            format: None,
+            local: false,
+            yes: false,
+            skip_verify: true,
+        }
+    }
+
+    #[test]
+    fn test_wizard_defaults_and_retries() {
+        // Empty answers take defaults; invalid answers are asked again
+        let mut input = "gemini\nopenai\n\nzero\n8\nhtml\njson\ny\n".as_bytes();
+        let mut out = Vec::new();
+        let choices = ask_choices(&args(), &mut input, &mut out).unwrap();
+
+        assert_eq!(
+            choices,
+            Choices {
+                provider: "openai".to_string(),
+                model: Some("gpt-4o".to_string()),
+                llm_concurrency: 8,
+                format: Format::Json,
+                local: true,
+            }
+        );
+        let transcript = String::from_utf8(out).unwrap();
+        assert!(transcript.contains("Unknown provider `gemini`"));
+        assert!(transcript.contains("Enter markdown or json"));
+    }
+
+    #[test]
+    fn test_wizard_cancelled_on_eof() {
+        let mut input = "ollama\n".as_bytes();
+        assert!(ask_choices(&args(), &mut input, &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_write_user_config_keeps_existing_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cda/config.toml");
+        let choices = Choices {
+            provider: "ollama".to_string(),
+            model: Some("llama3".to_string()),
+            llm_concurrency: 2,
+            format: Format::Markdown,
+            local: false,
+        };
+
+        write_user_config(&path, "ollama", &choices).unwrap();
+        let written = fs::read_to_string(&path).unwrap();
+        assert!(written.contains("provider = \"ollama\""));
+        assert!(written.contains("model = \"llama3\""));
+        assert!(written.contains("llm_concurrency = 2"));
+        assert!(written.contains("# Include source code snippets in output"));
+        assert_eq!(config::validate(&written), vec![]);
+
+        fs::write(&path, "[output]\ndirectory = \"docs\"\n").unwrap();
+        write_user_config(&path, "ollama", &choices).unwrap();
+        let written = fs::read_to_string(&path).unwrap();
+        assert!(written.contains("directory = \"docs\""));
+        assert!(written.contains("provider = \"ollama\""));
+    }
+}
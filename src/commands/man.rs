@@ -0,0 +1,55 @@
+//! roff man pages generated by clap_mangen from the clap command tree
+
+use anyhow::Result;
+use clap::{Command, CommandFactory};
+use std::path::Path;
+
+/// Print the `cda(1)` page, or with `dir` write it and a `cda-<command>(1)`
+/// page for each subcommand it lists there
+pub fn run(dir: Option<&Path>) -> Result<()> {
+    match dir {
+        Some(dir) => {
+            std::fs::create_dir_all(dir)?;
+            clap_mangen::generate_to(crate::Cli::command(), dir)?;
+        }
+        None => print!("{}", render(crate::Cli::command())?),
+    }
+    Ok(())
+}
+
+fn render(cmd: Command) -> Result<String> {
+    let mut out = Vec::new();
+    clap_mangen::Man::new(cmd).render(&mut out)?;
+    Ok(String::from_utf8(out)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_man_page_lists_subcommands() {
+        let page = render(crate::Cli::command()).unwrap();
+        assert!(page.starts_with(".ie \\n(.g .ds Aq"), "{}", page);
+        assert!(page.contains(".TH cda 1"));
+        assert!(page.contains("cda\\-analyze(1)"));
+        assert!(page.contains("\\-\\-deep\\-mode"));
+    }
+
+    #[test]
+    fn test_man_page_guards_control_characters() {
+        let cmd = Command::new("cda").about(".so /etc/passwd\n'br");
+        let page = render(cmd).unwrap();
+        assert!(!page.lines().any(|l| l.starts_with(".so")), "{}", page);
+        assert!(!page.lines().any(|l| l.starts_with("'br")), "{}", page);
+    }
+
+    #[test]
+    fn test_man_pages_per_subcommand() {
+        let dir = tempfile::tempdir().unwrap();
+        run(Some(dir.path())).unwrap();
+        assert!(dir.path().join("cda.1").is_file());
+        let analyze = std::fs::read_to_string(dir.path().join("cda-analyze.1")).unwrap();
+        assert!(analyze.contains("\\-\\-output"));
+    }
+}
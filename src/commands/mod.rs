@@ -1,3 +1,15 @@
 pub mod analyze;
+pub mod clean;
+pub mod completions;
 pub mod config;
+pub mod doctor;
+pub mod explain;
+pub mod export;
+pub mod init;
+pub mod man;
+pub mod models;
+pub mod runs;
+pub mod serve;
+pub mod stats;
 pub mod verify;
+pub mod watch;
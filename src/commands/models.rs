@@ -0,0 +1,128 @@
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::core::exit::UsageError;
+use crate::llm::{self, ModelInfo};
+use crate::output::Format;
+
+pub struct ModelsArgs {
+    /// Only query this provider
+    pub provider: Option<String>,
+    /// Configured provider and model, marked as the default in the listing
+    pub default_provider: String,
+    pub default_model: Option<String>,
+    pub format: Format,
+}
+
+#[derive(Serialize)]
+struct ProviderModels {
+    provider: String,
+    models: Vec<ListedModel>,
+    /// Why the provider could not be queried
+    #[serde(skip_serializing_if = "Option::is_none")]
+    note: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ListedModel {
+    #[serde(flatten)]
+    info: ModelInfo,
+    default: bool,
+}
+
+pub async fn run(args: ModelsArgs) -> Result<()> {
+    let providers: Vec<&str> = match &args.provider {
+        Some(name) => vec![llm::canonical_provider(name).ok_or_else(|| {
+            UsageError::new(format!(
+                "Unknown LLM provider: {}. Supported: {}",
+                name,
+                llm::PROVIDERS.join(", ")
+            ))
+        })?],
+        None => llm::PROVIDERS.to_vec(),
+    };
+    let default_provider = llm::canonical_provider(&args.default_provider);
+
+    let mut listings = Vec::new();
+    for name in providers {
+        let is_default = default_provider == Some(name);
+        let model = args.default_model.as_deref().filter(|_| is_default);
+        listings.push(query(name, model, is_default).await);
+    }
+
+    match args.format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(&listings)?),
+        Format::Markdown => print_listings(&listings),
+    }
+
+    Ok(())
+}
+
+/// Query one provider, turning missing credentials or an unreachable API
+/// into a note so the other providers are still listed
+async fn query(name: &str, model: Option<&str>, is_default: bool) -> ProviderModels {
+    let result = match llm::get_provider(name, model) {
+        Ok(provider) => provider
+            .list_models()
+            .await
+            .map(|models| (provider.model().to_string(), models)),
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok((current, models)) => ProviderModels {
+            provider: name.to_string(),
+            models: models
+                .into_iter()
+                .map(|info| ListedModel {
+                    default: is_default && llm::model_matches(&current, &info.id),
+                    info,
+                })
+                .collect(),
+            note: None,
+        },
+        Err(e) => ProviderModels {
+            provider: name.to_string(),
+            models: Vec::new(),
+            note: Some(e.to_string()),
+        },
+    }
+}
+
+fn print_listings(listings: &[ProviderModels]) {
+    for (i, listing) in listings.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!("{}", listing.provider);
+
+        if let Some(note) = &listing.note {
+            println!("  (skipped: {})", note);
+            continue;
+        }
+        if listing.models.is_empty() {
+            println!("  (no models)");
+            continue;
+        }
+
+        let width = listing
+            .models
+            .iter()
+            .map(|m| m.info.id.len())
+            .max()
+            .unwrap_or(0);
+        for model in &listing.models {
+            let marker = if model.default { "*" } else { " " };
+            match model.info.context_window {
+                Some(tokens) => println!(
+                    "{} {:<width$}  {} tokens",
+                    marker,
+                    model.info.id,
+                    tokens,
+                    width = width
+                ),
+                None => println!("{} {}", marker, model.info.id),
+            }
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::path::Path;
+
+use crate::core::runs::{RunEntry, RunIndex, RunStats};
+use crate::output::Format;
+
+pub struct RunsArgs {
+    /// Output directory holding `runs/`
+    pub output: String,
+    pub format: Format,
+}
+
+#[derive(Serialize)]
+struct RunRow<'a> {
+    #[serde(flatten)]
+    run: &'a RunEntry,
+    /// Change from the previous run; absent for the first
+    delta: Option<StatsDelta>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+struct StatsDelta {
+    modules: i64,
+    exports: i64,
+    gaps: i64,
+    llm_modules: i64,
+}
+
+impl StatsDelta {
+    fn between(previous: &RunStats, current: &RunStats) -> Self {
+        let diff = |a: usize, b: usize| b as i64 - a as i64;
+        Self {
+            modules: diff(previous.modules, current.modules),
+            exports: diff(previous.exports, current.exports),
+            gaps: diff(previous.gaps, current.gaps),
+            llm_modules: diff(previous.llm_modules, current.llm_modules),
+        }
+    }
+}
+
+pub fn list(args: RunsArgs) -> Result<()> {
+    let index = RunIndex::load(Path::new(&args.output))?;
+    let rows = rows(&index);
+
+    match args.format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(&rows)?),
+        Format::Markdown if rows.is_empty() => println!(
+            "No runs recorded in {}; use `cda analyze --tag <name>` to keep one",
+            args.output
+        ),
+        Format::Markdown => print_table(&rows),
+    }
+    Ok(())
+}
+
+fn rows(index: &RunIndex) -> Vec<RunRow<'_>> {
+    index
+        .runs
+        .iter()
+        .enumerate()
+        .map(|(i, run)| RunRow {
+            run,
+            delta: i
+                .checked_sub(1)
+                .map(|prev| StatsDelta::between(&index.runs[prev].stats, &run.stats)),
+        })
+        .collect()
+}
+
+fn print_table(rows: &[RunRow]) {
+    let providers: Vec<_> = rows
+        .iter()
+        .map(|row| match (&row.run.model, row.run.deep) {
+            (Some(model), true) => format!("{}/{}", row.run.provider, model),
+            (None, true) => row.run.provider.clone(),
+            (_, false) => format!("{} (static)", row.run.provider),
+        })
+        .collect();
+    let id_width = rows.iter().map(|r| r.run.id.len()).max().unwrap_or(0);
+    let provider_width = providers.iter().map(String::len).max().unwrap_or(0);
+    println!(
+        "  {:<id_width$}  {:<7}  {:<provider_width$}  {:>12}  {:>12}  {:>10}",
+        "RUN",
+        "COMMIT",
+        "PROVIDER",
+        "MODULES",
+        "EXPORTS",
+        "GAPS",
+        id_width = id_width,
+        provider_width = provider_width
+    );
+
+    for (i, (row, provider)) in rows.iter().zip(&providers).enumerate() {
+        let run = row.run;
+        let marker = if i + 1 == rows.len() { "*" } else { " " };
+        let commit = run
+            .commit
+            .as_deref()
+            .map(|c| &c[..c.len().min(7)])
+            .unwrap_or("-");
+        let delta = |value: usize, change: Option<i64>| match change {
+            Some(change) if change != 0 => format!("{} ({:+})", value, change),
+            _ => value.to_string(),
+        };
+        let d = row.delta.as_ref();
+
+        println!(
+            "{} {:<id_width$}  {:<7}  {:<provider_width$}  {:>12}  {:>12}  {:>10}{}",
+            marker,
+            run.id,
+            commit,
+            provider,
+            delta(run.stats.modules, d.map(|d| d.modules)),
+            delta(run.stats.exports, d.map(|d| d.exports)),
+            delta(run.stats.gaps, d.map(|d| d.gaps)),
+            if run.partial { "  partial" } else { "" },
+            id_width = id_width,
+            provider_width = provider_width
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deltas() {
+        let run = |modules, gaps| RunEntry {
+            id: String::new(),
+            tag: "t".into(),
+            started_at: String::new(),
+            commit: None,
+            provider: "ollama".into(),
+            model: None,
+            deep: false,
+            partial: false,
+            order: None,
+            prepass: false,
+            doc_language: None,
+            stats: RunStats {
+                modules,
+                gaps,
+                ..Default::default()
+            },
+        };
+        let index = RunIndex {
+            runs: vec![run(10, 4), run(12, 1)],
+        };
+
+        let rows = rows(&index);
+        assert!(rows[0].delta.is_none());
+        assert_eq!(
+            rows[1].delta,
+            Some(StatsDelta {
+                modules: 2,
+                exports: 0,
+                gaps: -3,
+                llm_modules: 0,
+            })
+        );
+    }
+}
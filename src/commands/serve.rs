@@ -0,0 +1,578 @@
+//! Local web UI for browsing an existing analysis output directory
+//!
+//! Everything is served straight from the generated artifacts, so there is no
+//! build step: markdown pages are rendered client-side, and the dependency
+//! graph and export search are derived from `analysis.json` when present.
+//! Pages and `analysis.json` are found through `manifest.json`; output from
+//! before there were manifests is scanned instead. The graph can be narrowed
+//! to part of the codebase with `--focus`, or per request with `?focus=`.
+
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info, warn};
+
+use crate::core::focus::{self, FocusGlob, FocusNode};
+use crate::output::manifest::{ArtifactKind, Manifest};
+
+const INDEX_HTML: &str = include_str!("serve.html");
+
+/// Largest request line and headers accepted, in bytes
+const MAX_REQUEST_HEAD: usize = 64 * 1024;
+
+/// Maximum number of search hits returned by `/api/search`
+const MAX_SEARCH_RESULTS: usize = 100;
+
+pub struct ServeArgs {
+    pub path: String,
+    pub bind: String,
+    pub port: u16,
+    /// Glob the dependency graph is narrowed to by default
+    pub focus: Option<String>,
+}
+
+pub async fn run(args: ServeArgs) -> Result<()> {
+    let root = Path::new(&args.path)
+        .canonicalize()
+        .map_err(|e| anyhow::anyhow!("Output directory {} not found: {}", args.path, e))?;
+    crate::output::compat::check(&root)?;
+    let focus = args.focus.as_deref().map(FocusGlob::new).transpose()?;
+
+    let listener = TcpListener::bind((args.bind.as_str(), args.port)).await?;
+    info!(
+        "Serving {} at http://{}",
+        root.display(),
+        listener.local_addr()?
+    );
+
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let root = root.clone();
+        let focus = focus.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, &root, focus.as_ref()).await {
+                debug!("Connection from {} failed: {}", peer, e);
+            }
+        });
+    }
+}
+
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn ok(content_type: &'static str, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status: "200 OK",
+            content_type,
+            body: body.into(),
+        }
+    }
+
+    fn json(value: &Value) -> Self {
+        Self::ok("application/json", value.to_string())
+    }
+
+    fn not_found() -> Self {
+        Self {
+            status: "404 Not Found",
+            content_type: "text/plain; charset=utf-8",
+            body: b"Not found".to_vec(),
+        }
+    }
+
+    fn bad_request() -> Self {
+        Self {
+            status: "400 Bad Request",
+            content_type: "text/plain; charset=utf-8",
+            body: b"Bad request".to_vec(),
+        }
+    }
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    root: &Path,
+    focus: Option<&FocusGlob>,
+) -> Result<()> {
+    let response = match read_head(&mut stream).await? {
+        Some(head) => match head.lines().next().map(|l| l.split_whitespace()) {
+            Some(mut parts) => match (parts.next(), parts.next()) {
+                (Some("GET"), Some(target)) => route(target, root, focus),
+                _ => Response::bad_request(),
+            },
+            None => Response::bad_request(),
+        },
+        None => Response::bad_request(),
+    };
+
+    let header = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.content_type,
+        response.body.len()
+    );
+    stream.write_all(header.as_bytes()).await?;
+    stream.write_all(&response.body).await?;
+    stream.flush().await?;
+
+    Ok(())
+}
+
+/// The request line and headers, however many reads they take; `None` when
+/// they exceed [`MAX_REQUEST_HEAD`] or the connection closes before their end
+async fn read_head(stream: &mut (impl AsyncRead + Unpin)) -> Result<Option<String>> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 4096];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        if head.len() > MAX_REQUEST_HEAD {
+            return Ok(None);
+        }
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            return Ok(None);
+        }
+        head.extend_from_slice(&buf[..n]);
+    }
+    Ok(Some(String::from_utf8_lossy(&head).into_owned()))
+}
+
+fn route(target: &str, root: &Path, focus: Option<&FocusGlob>) -> Response {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let path = percent_decode(path);
+
+    match path.as_str() {
+        "/" | "/index.html" => Response::ok("text/html; charset=utf-8", INDEX_HTML),
+        "/api/pages" => Response::json(&list_pages(root)),
+        "/api/graph" => {
+            let focus = match query_param(query, "focus").filter(|f| !f.trim().is_empty()) {
+                Some(glob) => match FocusGlob::new(&glob) {
+                    Ok(glob) => Some(glob),
+                    Err(_) => return Response::bad_request(),
+                },
+                None => focus.cloned(),
+            };
+            match load_analysis(root) {
+                Some(analysis) => Response::ok(
+                    "text/plain; charset=utf-8",
+                    mermaid_graph(&analysis, focus.as_ref()),
+                ),
+                None => Response::not_found(),
+            }
+        }
+        "/api/search" => {
+            let q = query_param(query, "q").unwrap_or_default();
+            Response::json(&search_exports(root, &q))
+        }
+        "/api/version" => Response::ok(
+            "text/plain; charset=utf-8",
+            output_version(root).to_string(),
+        ),
+        _ => match path.strip_prefix("/raw/") {
+            Some(rel) => serve_raw(root, rel),
+            None => Response::not_found(),
+        },
+    }
+}
+
+/// Serve a file from the output directory, refusing anything that escapes it
+fn serve_raw(root: &Path, rel: &str) -> Response {
+    let rel = Path::new(rel);
+    if rel.components().any(|c| !matches!(c, Component::Normal(_))) {
+        return Response::bad_request();
+    }
+
+    let content_type = match rel.extension().and_then(|e| e.to_str()) {
+        Some("md") => "text/markdown; charset=utf-8",
+        Some("json") => "application/json",
+        _ => "text/plain; charset=utf-8",
+    };
+
+    match fs::read(root.join(rel)) {
+        Ok(body) => Response::ok(content_type, body),
+        Err(_) => Response::not_found(),
+    }
+}
+
+/// All markdown pages in the output directory, relative to its root
+fn list_pages(root: &Path) -> Value {
+    let mut pages: Vec<String> = match load_manifest(root) {
+        Some(manifest) => manifest
+            .current()
+            .filter(|a| a.format == "markdown" && a.kind != ArtifactKind::OverviewPrompt)
+            .map(|a| a.path.clone())
+            .collect(),
+        None => walk_files(root)
+            .into_iter()
+            .filter(|p| p.extension().is_some_and(|e| e == "md"))
+            .filter_map(|p| relative_url_path(root, &p))
+            .collect(),
+    };
+    pages.sort();
+
+    serde_json::json!({
+        "pages": pages,
+        "has_analysis": analysis_path(root).exists(),
+    })
+}
+
+fn load_manifest(root: &Path) -> Option<Manifest> {
+    Manifest::load(root).unwrap_or_else(|e| {
+        warn!("{:#}", e);
+        None
+    })
+}
+
+/// analysis.json as listed in the manifest, or where it has always been
+fn analysis_path(root: &Path) -> PathBuf {
+    load_manifest(root)
+        .and_then(|m| m.find(ArtifactKind::Analysis).map(|a| root.join(&a.path)))
+        .unwrap_or_else(|| root.join("analysis.json"))
+}
+
+fn walk_files(root: &Path) -> Vec<PathBuf> {
+    walkdir::WalkDir::new(root)
+        .into_iter()
+        .flatten()
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .collect()
+}
+
+fn relative_url_path(root: &Path, path: &Path) -> Option<String> {
+    let rel = path.strip_prefix(root).ok()?;
+    Some(
+        rel.components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+    )
+}
+
+fn load_analysis(root: &Path) -> Option<Value> {
+    let content = fs::read_to_string(analysis_path(root)).ok()?;
+    match serde_json::from_str(&content) {
+        Ok(v) => Some(v),
+        Err(e) => {
+            warn!("Failed to parse analysis.json: {}", e);
+            None
+        }
+    }
+}
+
+/// Render the internal dependency map from `analysis.json` as a mermaid
+/// flowchart, narrowed to `focus` when given
+fn mermaid_graph(analysis: &Value, focus: Option<&FocusGlob>) -> String {
+    let mut out = String::from("graph LR\n");
+    let deps = analysis["cross_reference"]["dependencies"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    if let Some(glob) = focus {
+        focused_mermaid_graph(&mut out, &deps, glob);
+        return out;
+    }
+
+    for dep in &deps {
+        let from = dep["module"].as_str().unwrap_or_default();
+        for to in dep["depends_on"].as_array().into_iter().flatten() {
+            let to = to.as_str().unwrap_or_default();
+            out.push_str(&format!(
+                "  {}[\"{}\"] --> {}[\"{}\"]\n",
+                mermaid_id(from),
+                mermaid_label(short_name(from)),
+                mermaid_id(to),
+                mermaid_label(short_name(to))
+            ));
+        }
+    }
+
+    out
+}
+
+/// Focus modules highlighted, their neighbors as usual and the rest dimmed
+/// into one node per directory, with dashed edges labelled by count
+fn focused_mermaid_graph(out: &mut String, deps: &[Value], glob: &FocusGlob) {
+    let dependencies: BTreeMap<String, Vec<String>> = deps
+        .iter()
+        .map(|dep| {
+            let to = dep["depends_on"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|to| to.as_str().map(String::from))
+                .collect();
+            (dep["module"].as_str().unwrap_or_default().to_string(), to)
+        })
+        .collect();
+    let graph = focus::select(&dependencies, glob);
+
+    out.push_str("  classDef focus fill:#fde68a,stroke:#b45309\n");
+    out.push_str("  classDef summary fill:#f3f4f6,stroke:#d1d5db,color:#6b7280\n");
+    let node_id = |node: &FocusNode| match node {
+        FocusNode::Summary(dir) => format!("summary_{}", mermaid_id(dir)),
+        node => mermaid_id(node.path()),
+    };
+    for node in &graph.nodes {
+        let id = node_id(node);
+        match node {
+            FocusNode::Focus(path) => out.push_str(&format!(
+                "  {}[\"{}\"]:::focus\n",
+                id,
+                mermaid_label(short_name(path))
+            )),
+            FocusNode::Neighbor(path) => out.push_str(&format!(
+                "  {}[\"{}\"]\n",
+                id,
+                mermaid_label(short_name(path))
+            )),
+            FocusNode::Summary(dir) => {
+                let modules = graph.collapsed.get(dir).copied().unwrap_or_default();
+                out.push_str(&format!(
+                    "  {}([\"{}/ · {} module{}\"]):::summary\n",
+                    id,
+                    mermaid_label(if dir.is_empty() { "." } else { dir }),
+                    modules,
+                    if modules == 1 { "" } else { "s" }
+                ));
+            }
+        }
+    }
+    for edge in &graph.edges {
+        if edge.is_cut() {
+            out.push_str(&format!(
+                "  {} -.->|{}| {}\n",
+                node_id(&edge.from),
+                edge.count,
+                node_id(&edge.to)
+            ));
+        } else {
+            out.push_str(&format!(
+                "  {} --> {}\n",
+                node_id(&edge.from),
+                node_id(&edge.to)
+            ));
+        }
+    }
+}
+
+fn mermaid_id(path: &str) -> String {
+    path.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+/// `text` for inside a quoted node label: characters that would end the
+/// label or be read as markup become mermaid entity codes
+fn mermaid_label(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '"' | '#' | '<' | '>' | '&' | '[' | ']' | '(' | ')' | '{' | '}' | '|' => {
+                format!("#{};", c as u32)
+            }
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+fn short_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+/// Case-insensitive substring search over export names and descriptions
+fn search_exports(root: &Path, query: &str) -> Value {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Value::Array(vec![]);
+    }
+
+    let Some(analysis) = load_analysis(root) else {
+        return Value::Array(vec![]);
+    };
+
+    let mut hits = Vec::new();
+    for module in analysis["modules"].as_array().into_iter().flatten() {
+        for export in module["exports"].as_array().into_iter().flatten() {
+            let name = export["name"].as_str().unwrap_or_default();
+            let description = export["description"].as_str().unwrap_or_default();
+
+            if name.to_lowercase().contains(&query) || description.to_lowercase().contains(&query) {
+                hits.push(serde_json::json!({
+                    "name": name,
+                    "kind": export["kind"],
+                    "signature": export["signature"],
+                    "description": description,
+                    "module": module["path"],
+                    "line": export["line"],
+                }));
+                if hits.len() >= MAX_SEARCH_RESULTS {
+                    return Value::Array(hits);
+                }
+            }
+        }
+    }
+
+    Value::Array(hits)
+}
+
+/// Latest modification time in the output directory, used for live reload
+fn output_version(root: &Path) -> u64 {
+    walk_files(root)
+        .iter()
+        .filter_map(|p| p.metadata().ok()?.modified().ok())
+        .filter_map(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as u64)
+        .max()
+        .unwrap_or(0)
+}
+
+fn query_param(query: &str, key: &str) -> Option<String> {
+    query.split('&').find_map(|pair| {
+        let (k, v) = pair.split_once('=').unwrap_or((pair, ""));
+        (k == key).then(|| percent_decode(&v.replace('+', " ")))
+    })
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let Some(b) = std::str::from_utf8(&bytes[i + 1..i + 3])
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                out.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("modules/foo%20bar.md"), "modules/foo bar.md");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(
+            query_param("q=parse+file&x=1", "q").as_deref(),
+            Some("parse file")
+        );
+    }
+
+    #[test]
+    fn test_scripts_are_pinned() {
+        let scripts: Vec<&str> = INDEX_HTML
+            .split("<script src=\"")
+            .skip(1)
+            .map(|s| s.split('"').next().unwrap())
+            .collect();
+        assert_eq!(scripts.len(), 2);
+        for src in scripts {
+            let package = src
+                .split("/npm/")
+                .nth(1)
+                .unwrap()
+                .split('/')
+                .next()
+                .unwrap();
+            assert!(package.contains('@'), "{} has no version", src);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_head_across_reads() {
+        let (mut client, mut server) = tokio::io::duplex(64);
+        let reader = tokio::spawn(async move { read_head(&mut server).await.unwrap() });
+        client
+            .write_all(b"GET /api/pages HTTP/1.1\r\nHost: x")
+            .await
+            .unwrap();
+        tokio::task::yield_now().await;
+        client.write_all(b"\r\nAccept: */*\r\n\r\n").await.unwrap();
+        let head = reader.await.unwrap().unwrap();
+        assert!(head.starts_with("GET /api/pages HTTP/1.1\r\n"));
+        assert!(head.ends_with("Accept: */*\r\n\r\n"));
+
+        // Headers that never end are cut off
+        let (mut client, mut server) = tokio::io::duplex(MAX_REQUEST_HEAD * 2);
+        let long = format!("GET / HTTP/1.1\r\nX: {}\r\n", "a".repeat(MAX_REQUEST_HEAD));
+        client.write_all(long.as_bytes()).await.unwrap();
+        drop(client);
+        assert_eq!(read_head(&mut server).await.unwrap(), None);
+    }
+
+    #[test]
+    fn test_serve_raw_rejects_traversal() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(
+            serve_raw(dir.path(), "../Cargo.toml").status,
+            "400 Bad Request"
+        );
+        assert_eq!(
+            serve_raw(dir.path(), "/etc/passwd").status,
+            "400 Bad Request"
+        );
+    }
+
+    #[test]
+    fn test_mermaid_graph() {
+        let analysis = serde_json::json!({
+            "cross_reference": {
+                "dependencies": [
+                    {"module": "src/main.rs", "depends_on": ["src/lib.rs"]}
+                ]
+            }
+        });
+        let graph = mermaid_graph(&analysis, None);
+        assert!(graph.contains("src_main_rs[\"main.rs\"] --> src_lib_rs[\"lib.rs\"]"));
+
+        let analysis = serde_json::json!({
+            "cross_reference": {
+                "dependencies": [
+                    {"module": "src/a\"].rs", "depends_on": ["src/<b>.rs"]}
+                ]
+            }
+        });
+        let graph = mermaid_graph(&analysis, None);
+        assert!(graph.contains("[\"a#34;#93;.rs\"] --> "));
+        assert!(graph.contains("[\"#60;b#62;.rs\"]"));
+    }
+
+    #[test]
+    fn test_mermaid_graph_focus() {
+        let analysis = serde_json::json!({
+            "cross_reference": {
+                "dependencies": [
+                    {"module": "src/main.rs", "depends_on": ["src/billing/invoice.rs"]},
+                    {"module": "src/billing/invoice.rs", "depends_on": ["src/db/pool.rs"]},
+                    {"module": "src/db/pool.rs", "depends_on": ["src/util/a.rs", "src/util/b.rs"]}
+                ]
+            }
+        });
+        let glob = FocusGlob::new("src/billing/**").unwrap();
+        let graph = mermaid_graph(&analysis, Some(&glob));
+        assert!(graph.contains("src_billing_invoice_rs[\"invoice.rs\"]:::focus"));
+        assert!(graph.contains("summary_src_util([\"src/util/ · 2 modules\"]):::summary"));
+        assert!(graph.contains("src_db_pool_rs -.->|2| summary_src_util"));
+        assert!(graph.contains("src_main_rs --> src_billing_invoice_rs"));
+    }
+}
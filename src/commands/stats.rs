@@ -0,0 +1,221 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use crate::core::discovery::{self, DiscoveryOptions, FileInventory};
+use crate::output::Format;
+
+/// Number of entries shown in the largest-files list
+const LARGEST_FILES: usize = 10;
+
+pub struct StatsArgs {
+    pub path: String,
+    pub module: Option<String>,
+    pub format: Format,
+    pub discovery: DiscoveryOptions,
+}
+
+#[derive(Serialize)]
+struct ProjectStats {
+    source_files: usize,
+    test_files: usize,
+    config_files: usize,
+    /// Kubernetes manifests, Helm chart files and API specs
+    manifest_files: usize,
+    doc_files: usize,
+    total_lines: usize,
+    total_bytes: u64,
+    /// Test files per source file
+    test_ratio: f64,
+    languages: Vec<LanguageStats>,
+    largest_files: Vec<FileStats>,
+    config: Vec<String>,
+    docs: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct LanguageStats {
+    language: String,
+    files: usize,
+    lines: usize,
+    bytes: u64,
+}
+
+#[derive(Serialize)]
+struct FileStats {
+    path: String,
+    lines: usize,
+    bytes: u64,
+}
+
+pub async fn run(args: StatsArgs) -> Result<()> {
+    let path = Path::new(&args.path).canonicalize()?;
+    let inventory = discovery::discover(&path, args.module.as_deref(), &args.discovery).await?;
+    let stats = collect(&inventory);
+
+    match args.format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(&stats)?),
+        Format::Markdown => print_table(&stats),
+    }
+
+    Ok(())
+}
+
+fn collect(inventory: &FileInventory) -> ProjectStats {
+    let mut by_language: BTreeMap<String, LanguageStats> = BTreeMap::new();
+    let mut files = Vec::new();
+
+    for file in &inventory.source_files {
+        let lines = count_lines(&file.abs_path).unwrap_or(0);
+        let language = format!("{:?}", file.language);
+
+        let entry = by_language
+            .entry(language.clone())
+            .or_insert_with(|| LanguageStats {
+                language,
+                files: 0,
+                lines: 0,
+                bytes: 0,
+            });
+        entry.files += 1;
+        entry.lines += lines;
+        entry.bytes += file.size;
+
+        files.push(FileStats {
+            path: file.path.clone(),
+            lines,
+            bytes: file.size,
+        });
+    }
+
+    let total_lines = files.iter().map(|f| f.lines).sum();
+    let total_bytes = files.iter().map(|f| f.bytes).sum();
+
+    files.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.path.cmp(&b.path)));
+    files.truncate(LARGEST_FILES);
+
+    let mut languages: Vec<LanguageStats> = by_language.into_values().collect();
+    languages.sort_by(|a, b| {
+        b.lines
+            .cmp(&a.lines)
+            .then_with(|| a.language.cmp(&b.language))
+    });
+
+    let test_ratio = if inventory.source_files.is_empty() {
+        0.0
+    } else {
+        inventory.test_files.len() as f64 / inventory.source_files.len() as f64
+    };
+
+    ProjectStats {
+        source_files: inventory.source_files.len(),
+        test_files: inventory.test_files.len(),
+        config_files: inventory.config_files.len(),
+        manifest_files: inventory.manifest_files(),
+        doc_files: inventory.doc_files.len(),
+        total_lines,
+        total_bytes,
+        test_ratio,
+        languages,
+        largest_files: files,
+        config: inventory.config_files.clone(),
+        docs: inventory.doc_files.clone(),
+    }
+}
+
+/// Count newline-terminated lines without holding the file in memory
+fn count_lines(path: impl AsRef<Path>) -> std::io::Result<usize> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut buf = [0u8; 64 * 1024];
+    let mut lines = 0;
+    let mut last = b'\n';
+
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        lines += buf[..n].iter().filter(|&&b| b == b'\n').count();
+        last = buf[n - 1];
+    }
+
+    // Count a trailing line that has no final newline
+    if last != b'\n' {
+        lines += 1;
+    }
+
+    Ok(lines)
+}
+
+fn print_table(stats: &ProjectStats) {
+    println!("Files");
+    println!("  {:<10} {:>8}", "source", stats.source_files);
+    println!("  {:<10} {:>8}", "test", stats.test_files);
+    println!("  {:<10} {:>8}", "config", stats.config_files);
+    println!("  {:<10} {:>8}", "manifests", stats.manifest_files);
+    println!("  {:<10} {:>8}", "docs", stats.doc_files);
+    println!("  {:<10} {:>8.2}", "test ratio", stats.test_ratio);
+    println!();
+
+    println!(
+        "{:<12} {:>8} {:>10} {:>12}",
+        "Language", "Files", "Lines", "Bytes"
+    );
+    for lang in &stats.languages {
+        println!(
+            "{:<12} {:>8} {:>10} {:>12}",
+            lang.language, lang.files, lang.lines, lang.bytes
+        );
+    }
+    println!(
+        "{:<12} {:>8} {:>10} {:>12}",
+        "Total", stats.source_files, stats.total_lines, stats.total_bytes
+    );
+    println!();
+
+    if !stats.largest_files.is_empty() {
+        println!("Largest files");
+        for file in &stats.largest_files {
+            println!(
+                "  {:>10} bytes {:>8} lines  {}",
+                file.bytes, file.lines, file.path
+            );
+        }
+        println!();
+    }
+
+    if !stats.config.is_empty() {
+        println!("Config files");
+        for path in &stats.config {
+            println!("  {}", path);
+        }
+        println!();
+    }
+
+    if !stats.docs.is_empty() {
+        println!("Doc files");
+        for path in &stats.docs {
+            println!("  {}", path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let with_newline = dir.path().join("a.rs");
+        let without_newline = dir.path().join("b.rs");
+        std::fs::write(&with_newline, "fn a() {}\nfn b() {}\n").unwrap();
+        std::fs::write(&without_newline, "fn a() {}\nfn b() {}").unwrap();
+
+        assert_eq!(count_lines(with_newline.to_str().unwrap()).unwrap(), 2);
+        assert_eq!(count_lines(without_newline.to_str().unwrap()).unwrap(), 2);
+    }
+}
@@ -1,5 +1,11 @@
 use anyhow::Result;
-use tracing::info;
+use std::path::Path;
+use tracing::{debug, info, warn};
+
+use crate::core::analyzer::Completeness;
+use crate::core::Analysis;
+use crate::output;
+use crate::output::manifest::{self, ArtifactKind, Manifest, MANIFEST_FILE};
 
 pub struct VerifyArgs {
     pub path: String,
@@ -9,16 +15,253 @@ pub struct VerifyArgs {
 pub async fn run(args: VerifyArgs) -> Result<()> {
     info!("Verifying analysis at: {}", args.path);
 
+    let output_path = Path::new(&args.path);
+    output::compat::check(output_path)?;
+    let manifest = Manifest::load(output_path)?;
+    match &manifest {
+        Some(manifest) => {
+            let problems = check_artifacts(output_path, manifest);
+            for problem in &problems {
+                warn!("{}", problem);
+            }
+            let stale = manifest.artifacts.iter().filter(|a| a.stale).count();
+            info!(
+                "{} of {} artifacts in {} are missing or changed, {} stale",
+                problems.len(),
+                manifest.artifacts.len(),
+                MANIFEST_FILE,
+                stale
+            );
+        }
+        None => info!(
+            "No {} in {}; it is written by `cda analyze`",
+            MANIFEST_FILE, args.path
+        ),
+    }
+
+    let json = manifest
+        .as_ref()
+        .and_then(|m| m.find(ArtifactKind::Analysis))
+        .map(|a| output_path.join(&a.path))
+        .unwrap_or_else(|| output_path.join("analysis.json"));
+    let mut commands = Vec::new();
+    if json.exists() {
+        let (analysis, _) = output::load(&json)?;
+        let stale = stale_modules(&analysis);
+        for path in &stale {
+            warn!("Stale: {} was modified while it was being analyzed", path);
+        }
+        info!(
+            "{} of {} modules are stale",
+            stale.len(),
+            analysis.modules.len()
+        );
+        let candidates = reanalysis_candidates(&analysis);
+        for (path, completeness) in &candidates {
+            if *completeness == Completeness::Stale {
+                info!("Re-analysis candidate: {} is stale", path);
+            } else {
+                debug!("Re-analysis candidate: {} is {}", path, completeness.name());
+            }
+        }
+        if !candidates.is_empty() {
+            let count = |c| candidates.iter().filter(|(_, kind)| *kind == c).count();
+            info!(
+                "{} of {} modules are candidates for re-analysis ({} stale, {} static-only, \
+                 {} rejected); `cda analyze --mode deep` analyzes them again",
+                candidates.len(),
+                analysis.modules.len(),
+                count(Completeness::Stale),
+                count(Completeness::StaticOnly),
+                count(Completeness::Rejected)
+            );
+        }
+        for rename in &analysis.renames {
+            info!(
+                "Renamed: {} → {} ({})",
+                rename.from,
+                rename.to,
+                if rename.exact {
+                    "analysis carried forward"
+                } else {
+                    "analyzed again"
+                }
+            );
+        }
+        commands = analysis.commands;
+    } else {
+        info!(
+            "No analysis.json in {}; run `cda analyze -f json` to check for stale modules",
+            args.path
+        );
+    }
+
     if args.run_commands {
         info!("Running command verification (--run-commands enabled)");
+        // What CI runs is known to work somewhere, so it goes first
+        commands.sort_by_key(|c| !c.in_ci);
+        for command in &commands {
+            info!(
+                "Command: `{}` in {}{}",
+                command.command,
+                command_dir(&command.source),
+                if command.in_ci { " (run in CI)" } else { "" }
+            );
+        }
+        info!(
+            "{} commands documented in analysis.json, {} of them run in CI",
+            commands.len(),
+            commands.iter().filter(|c| c.in_ci).count()
+        );
     }
 
-    // TODO: Implement verification logic
-    // 1. Load existing analysis
-    // 2. Re-scan codebase for changes
-    // 3. Optionally run documented commands to verify they work
-    // 4. Report discrepancies
+    // TODO: Implement the remaining verification
+    // 1. Re-scan codebase for changes
+    // 2. Run the documented commands above to verify they work
+    // 3. Report discrepancies
 
-    info!("⚠️  Verification not yet implemented");
+    info!("⚠️  Verification beyond artifacts and stale modules not yet implemented");
     Ok(())
 }
+
+/// Artifacts of the latest run that are gone or no longer match their hash
+fn check_artifacts(output_path: &Path, manifest: &Manifest) -> Vec<String> {
+    manifest
+        .current()
+        .filter_map(|artifact| {
+            let path = output_path.join(&artifact.path);
+            match manifest::hash_file(&path) {
+                Ok(hash) if hash == artifact.hash => None,
+                Ok(_) => Some(format!("Changed since it was generated: {}", artifact.path)),
+                Err(_) => Some(format!("Missing: {}", artifact.path)),
+            }
+        })
+        .collect()
+}
+
+/// Directory a command runs from: that of the config file defining it
+fn command_dir(source: &str) -> &str {
+    match source.rsplit_once('/') {
+        Some((dir, _)) => dir,
+        None => ".",
+    }
+}
+
+/// Modules whose docs can't be trusted. A file that changed while it was
+/// being analyzed is stale whatever any other check finds.
+fn stale_modules(analysis: &Analysis) -> Vec<&str> {
+    analysis
+        .modules
+        .iter()
+        .filter(|m| m.modified_during_analysis)
+        .map(|m| m.path.as_str())
+        .collect()
+}
+
+/// Modules whose docs come from an older prompt, a file that changed, or
+/// no LLM analysis at all
+fn reanalysis_candidates(analysis: &Analysis) -> Vec<(&str, Completeness)> {
+    analysis
+        .modules
+        .iter()
+        .filter(|m| m.completeness.needs_reanalysis())
+        .map(|m| (m.path.as_str(), m.completeness))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_modified_modules_are_stale() {
+        let module = |path: &str, modified: bool| {
+            serde_json::from_value(serde_json::json!({
+                "path": path,
+                "language": "rust",
+                "exports": [],
+                "imports": [],
+                "summary": "",
+                "has_deep_analysis": true,
+                "is_test_module": false,
+                "modified_during_analysis": modified,
+            }))
+            .unwrap()
+        };
+        let mut analysis = Analysis::default();
+        analysis.modules = vec![module("src/a.rs", false), module("src/b.rs", true)];
+        assert_eq!(stale_modules(&analysis), ["src/b.rs"]);
+    }
+
+    #[test]
+    fn test_reanalysis_candidates() {
+        let module = |path: &str, completeness: &str| {
+            serde_json::from_value(serde_json::json!({
+                "path": path,
+                "language": "rust",
+                "exports": [],
+                "imports": [],
+                "summary": "",
+                "has_deep_analysis": completeness != "static-only",
+                "is_test_module": false,
+                "completeness": completeness,
+            }))
+            .unwrap()
+        };
+        let mut analysis = Analysis::default();
+        analysis.modules = vec![
+            module("src/a.rs", "full"),
+            module("src/b.rs", "stale"),
+            module("src/c.rs", "truncated"),
+            module("src/d.rs", "static-only"),
+        ];
+        assert_eq!(
+            reanalysis_candidates(&analysis),
+            [
+                ("src/b.rs", Completeness::Stale),
+                ("src/d.rs", Completeness::StaticOnly)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_command_dir() {
+        assert_eq!(command_dir("package.json"), ".");
+        assert_eq!(command_dir("web/app/package.json"), "web/app");
+    }
+
+    #[test]
+    fn test_check_artifacts() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for name in ["CODEBASE.md", "analysis.json", "overview-prompt.md"] {
+            std::fs::write(root.join(name), name).unwrap();
+        }
+        let manifest = manifest::write(
+            root,
+            None,
+            None,
+            &[
+                ("CODEBASE.md".to_string(), ArtifactKind::Index),
+                ("analysis.json".to_string(), ArtifactKind::Analysis),
+                (
+                    "overview-prompt.md".to_string(),
+                    ArtifactKind::OverviewPrompt,
+                ),
+            ],
+            false,
+        )
+        .unwrap();
+        assert!(check_artifacts(root, &manifest).is_empty());
+
+        std::fs::write(root.join("CODEBASE.md"), "edited").unwrap();
+        std::fs::remove_file(root.join("analysis.json")).unwrap();
+        assert_eq!(
+            check_artifacts(root, &manifest),
+            [
+                "Changed since it was generated: CODEBASE.md",
+                "Missing: analysis.json"
+            ]
+        );
+    }
+}
@@ -0,0 +1,435 @@
+//! Keep an output directory current while the codebase is being edited
+//!
+//! File system events on the tree are debounced, then the paths they name
+//! are put through the same discovery rules as `analyze` (so .gitignore is
+//! respected). Only the source files that changed are re-parsed before the
+//! index is regenerated.
+
+use anyhow::Result;
+use futures::FutureExt;
+use notify_debouncer_mini::notify::RecursiveMode;
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tracing::{debug, info, warn};
+
+use crate::core::analyzer::{self, Analysis, ModuleAnalysis, ParseOptions, ProgressWriter};
+use crate::core::discovery::{self, DiscoveryOptions, Language, SourceFile};
+use crate::core::source::{self, SourceText};
+use crate::core::timings::Timings;
+use crate::llm::LlmProvider;
+use crate::output::{self, Format};
+
+pub struct WatchArgs {
+    pub path: String,
+    pub output: String,
+    pub provider: String,
+    pub model: Option<String>,
+    pub deep: bool,
+    /// Files parsed concurrently in the initial pass
+    pub parse_jobs: usize,
+    pub debounce_ms: u64,
+    /// Snippets and private items to attach to exports
+    pub parse_options: ParseOptions,
+    pub format: Format,
+    pub discovery: DiscoveryOptions,
+}
+
+/// Modification time and size of every source file, keyed by path
+type Snapshot = HashMap<String, (Option<SystemTime>, u64, Language)>;
+
+pub async fn run(args: WatchArgs) -> Result<()> {
+    let path = Path::new(&args.path).canonicalize()?;
+    let output_path = PathBuf::from(&args.output);
+    std::fs::create_dir_all(&output_path)?;
+    // Don't document the pages this command writes into the watched path
+    let discovery = args
+        .discovery
+        .clone()
+        .excluding_output(&output_path, &output_path);
+
+    info!("Watching {} (Ctrl-C to stop)", path.display());
+
+    // One Ctrl-C future for the whole run, polled once here so the handler
+    // is installed before any work starts and no signal is missed between
+    // batches
+    let mut interrupt = std::pin::pin!(tokio::signal::ctrl_c());
+    if interrupt.as_mut().now_or_never().is_some() {
+        return shutdown();
+    }
+
+    // Nothing is written before the initial pass completes, so stopping
+    // during it leaves the output directory as it was
+    let initial = async {
+        // Start from a static pass; --deep only applies to files edited from here on
+        let inventory = discovery::discover(&path, None, &discovery).await?;
+        let mut analysis = analyzer::analyze_static(
+            &inventory,
+            args.parse_jobs,
+            args.parse_options,
+            &Timings::default(),
+        )
+        .await?;
+        analysis.doc_language = crate::llm::doc_language().map(String::from);
+        let snapshot = snapshot_of(inventory.source_files);
+
+        let provider = match crate::llm::get_provider(&args.provider, args.model.as_deref()) {
+            Ok(provider) => Some(provider),
+            Err(e) if args.deep => return Err(e),
+            Err(e) => {
+                warn!("Skipping architecture overview: {}", e);
+                None
+            }
+        };
+
+        // The architecture overview is generated once up front; incremental
+        // updates only refresh the static cross-reference data around it.
+        let overview = match provider.as_deref() {
+            Some(provider) => {
+                analyzer::cross_reference_with_llm(&mut analysis, provider, &output_path)
+                    .await?
+                    .architecture_overview
+            }
+            None => None,
+        };
+        Ok((analysis, snapshot, provider, overview))
+    };
+    let (mut analysis, mut snapshot, provider, overview) = tokio::select! {
+        biased;
+        _ = interrupt.as_mut() => return shutdown(),
+        initial = initial => initial?,
+    };
+    // Only re-run the LLM on edits when --deep was asked for
+    let deep_provider = provider.as_deref().filter(|_| args.deep);
+
+    regenerate(&analysis, overview.clone(), &output_path, args.format).await?;
+    info!(
+        "Initial analysis complete ({} modules)",
+        analysis.modules.len()
+    );
+    if interrupt.as_mut().now_or_never().is_some() {
+        return shutdown();
+    }
+
+    // A burst of saves arrives as one batch once the tree has been quiet
+    // for the debounce time
+    let (sender, mut events) = tokio::sync::mpsc::unbounded_channel();
+    let mut debouncer = new_debouncer(
+        Duration::from_millis(args.debounce_ms),
+        move |result: DebounceEventResult| {
+            let _ = sender.send(result);
+        },
+    )?;
+    debouncer.watcher().watch(&path, RecursiveMode::Recursive)?;
+
+    loop {
+        let batch = tokio::select! {
+            biased;
+            _ = interrupt.as_mut() => break,
+            batch = events.recv() => batch,
+        };
+        let paths: Vec<PathBuf> = match batch {
+            Some(Ok(batch)) => batch.into_iter().map(|e| e.path).collect(),
+            Some(Err(e)) => {
+                warn!("Watch error: {}", e);
+                continue;
+            }
+            None => break,
+        };
+
+        let found = discovery::discover_sources(&path, &paths, &discovery)?;
+        let current = apply(&snapshot, &path, &paths, found);
+        if current == snapshot {
+            continue;
+        }
+
+        let changes = diff(&snapshot, &current);
+        info!(
+            "Detected {} changed, {} removed file(s)",
+            changes.changed.len(),
+            changes.removed.len()
+        );
+
+        for file in &changes.removed {
+            analysis.modules.retain(|m| &m.path != file);
+            if let Err(e) = analyzer::forget_module(&output_path, file) {
+                warn!("Failed to remove stale output for {}: {}", file, e);
+            }
+        }
+
+        // A Ctrl-C during the batch stops it after the file being updated;
+        // the output is still regenerated from what was updated so far
+        let mut stopping = false;
+        for file in &changes.changed {
+            if interrupt.as_mut().now_or_never().is_some() {
+                stopping = true;
+                break;
+            }
+            let language = current[file].2;
+            update_module(
+                &mut analysis,
+                &path,
+                file,
+                language,
+                args.parse_options,
+                deep_provider,
+                &output_path,
+            )
+            .await;
+        }
+
+        regenerate(&analysis, overview.clone(), &output_path, args.format).await?;
+        if stopping || interrupt.as_mut().now_or_never().is_some() {
+            break;
+        }
+        snapshot = current;
+    }
+
+    shutdown()
+}
+
+fn shutdown() -> Result<()> {
+    info!("Stopped watching");
+    Ok(())
+}
+
+fn snapshot_of(source_files: Vec<SourceFile>) -> Snapshot {
+    source_files
+        .into_iter()
+        .map(|f| {
+            let modified = std::fs::metadata(&f.abs_path)
+                .and_then(|m| m.modified())
+                .ok();
+            (f.path, (modified, f.size, f.language))
+        })
+        .collect()
+}
+
+/// `snapshot` after events on `paths`, files or directories under `root`:
+/// what it held at or below each path is replaced by the source files
+/// `found` there now
+fn apply(snapshot: &Snapshot, root: &Path, paths: &[PathBuf], found: Vec<SourceFile>) -> Snapshot {
+    let root = root.display().to_string();
+    let touched: Vec<String> = paths
+        .iter()
+        .map(|p| discovery::relative_path(&p.display().to_string(), &root))
+        .collect();
+    let mut current = snapshot.clone();
+    current.retain(|file, _| {
+        !touched.iter().any(|path| {
+            path.is_empty()
+                || file == path
+                || file
+                    .strip_prefix(path.as_str())
+                    .is_some_and(|rest| rest.starts_with('/'))
+        })
+    });
+    current.extend(snapshot_of(found));
+    current
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Changes {
+    changed: Vec<String>,
+    removed: Vec<String>,
+}
+
+/// Files that are new or modified, and files that disappeared. A rename shows
+/// up as one removal plus one new file.
+fn diff(before: &Snapshot, after: &Snapshot) -> Changes {
+    let mut changes = Changes::default();
+
+    for (path, state) in after {
+        if before.get(path) != Some(state) {
+            changes.changed.push(path.clone());
+        }
+    }
+
+    let current: HashSet<&String> = after.keys().collect();
+    changes.removed = before
+        .keys()
+        .filter(|p| !current.contains(p))
+        .cloned()
+        .collect();
+
+    changes.changed.sort();
+    changes.removed.sort();
+    changes
+}
+
+/// Re-analyze one file (relative to `root`) and replace its entry in the
+/// analysis
+async fn update_module(
+    analysis: &mut Analysis,
+    root: &Path,
+    file: &str,
+    language: Language,
+    options: ParseOptions,
+    deep_provider: Option<&dyn LlmProvider>,
+    output_path: &Path,
+) {
+    let io_path = root.join(file);
+    let module = if let Some(provider) = deep_provider {
+        let (content, encoding_warning) = match source::read_source(&io_path) {
+            Ok(SourceText::Text {
+                content,
+                encoding_warning,
+            }) => (content, encoding_warning),
+            Ok(SourceText::Binary) => {
+                debug!("Skipping binary file {}", file);
+                return;
+            }
+            Err(e) => {
+                warn!("Failed to read {}: {}", file, e);
+                return;
+            }
+        };
+        if let Some(mut module) = analyzer::summarize_generated(file, &io_path, language, &content)
+        {
+            module.encoding_warning = encoding_warning;
+            replace_module(analysis, module);
+            return;
+        }
+        let modules_dir = output_path.join("modules");
+        if let Err(e) = std::fs::create_dir_all(&modules_dir) {
+            warn!("Failed to create {}: {}", modules_dir.display(), e);
+            return;
+        }
+        let progress = match ProgressWriter::open(output_path) {
+            Ok(progress) => progress,
+            Err(e) => {
+                warn!("Failed to open progress file: {}", e);
+                return;
+            }
+        };
+        let timings = Timings::default();
+        let parse_result =
+            analyzer::parse_module(file, &io_path, &content, language, options, &timings);
+        let mut module = analyzer::analyze_module_deep(
+            provider,
+            file.to_string(),
+            &[],
+            language,
+            false,
+            &content,
+            source::content_hash(&io_path).ok(),
+            parse_result,
+            &modules_dir,
+            &progress.log(),
+            &timings,
+            None,
+            None,
+        )
+        .await;
+        if let Err(e) = progress.finish().await {
+            warn!("Failed to save progress: {}", e);
+        }
+        module.encoding_warning = encoding_warning;
+        module
+    } else {
+        match analyzer::analyze_file_static(file, &io_path, language, options, &Timings::default())
+        {
+            Ok(Some(m)) => m,
+            Ok(None) => {
+                debug!("Skipping binary file {}", file);
+                return;
+            }
+            Err(e) => {
+                warn!("Failed to read {}: {}", file, e);
+                return;
+            }
+        }
+    };
+
+    replace_module(analysis, module);
+}
+
+fn replace_module(analysis: &mut Analysis, module: ModuleAnalysis) {
+    match analysis.modules.iter_mut().find(|m| m.path == module.path) {
+        Some(existing) => *existing = module,
+        None => analysis.modules.push(module),
+    }
+}
+
+async fn regenerate(
+    analysis: &Analysis,
+    architecture_overview: Option<String>,
+    output_path: &Path,
+    format: Format,
+) -> Result<()> {
+    let mut crossref = analyzer::cross_reference(analysis).await?;
+    crossref.architecture_overview = architecture_overview;
+    output::generate(analysis, &crossref, output_path, format)?;
+    info!(
+        "Updated {} ({} modules, {} gaps)",
+        output_path.display(),
+        analysis.modules.len(),
+        crossref.gaps.len()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::discovery::Detection;
+
+    #[test]
+    fn test_diff_detects_changes_and_renames() {
+        let t0 = Some(SystemTime::UNIX_EPOCH);
+        let t1 = Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1));
+
+        let before: Snapshot = HashMap::from([
+            ("a.rs".to_string(), (t0, 10, Language::Rust)),
+            ("b.rs".to_string(), (t0, 10, Language::Rust)),
+            ("old.rs".to_string(), (t0, 10, Language::Rust)),
+        ]);
+        let after: Snapshot = HashMap::from([
+            ("a.rs".to_string(), (t0, 10, Language::Rust)),
+            ("b.rs".to_string(), (t1, 12, Language::Rust)),
+            ("new.rs".to_string(), (t0, 10, Language::Rust)),
+        ]);
+
+        assert_eq!(
+            diff(&before, &after),
+            Changes {
+                changed: vec!["b.rs".to_string(), "new.rs".to_string()],
+                removed: vec!["old.rs".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn test_apply_replaces_what_the_events_touched() {
+        let t0 = Some(SystemTime::UNIX_EPOCH);
+        let root = Path::new("/repo");
+        let snapshot: Snapshot = HashMap::from([
+            ("a.rs".to_string(), (t0, 10, Language::Rust)),
+            ("lib/b.rs".to_string(), (t0, 10, Language::Rust)),
+            ("lib/c.rs".to_string(), (t0, 10, Language::Rust)),
+            ("library.rs".to_string(), (t0, 10, Language::Rust)),
+        ]);
+        // `lib` was removed and `new.rs` created
+        let found = vec![SourceFile {
+            path: "new.rs".to_string(),
+            abs_path: root.join("new.rs"),
+            language: Language::Rust,
+            detection: Detection::Extension,
+            size: 5,
+            is_test: false,
+            modified: t0,
+            companions: Vec::new(),
+        }];
+        let current = apply(
+            &snapshot,
+            root,
+            &[root.join("lib"), root.join("new.rs")],
+            found,
+        );
+
+        let mut files: Vec<&str> = current.keys().map(String::as_str).collect();
+        files.sort();
+        assert_eq!(files, vec!["a.rs", "library.rs", "new.rs"]);
+    }
+}
@@ -0,0 +1,2118 @@
+//! Layered configuration
+//!
+//! Every setting is resolved from, in order of precedence:
+//! CLI flags > environment variables > selected profile > repo config
+//! (`./cda.toml`) > user config (`config.toml` in the platform config dir) >
+//! built-in defaults.
+//!
+//! Each layer is parsed into the same partial [`ConfigFile`] shape, so merging
+//! is a matter of taking the first layer that sets a value. A profile is a
+//! `[profile.<name>]` table of the same shape in either config file, selected
+//! with `--profile`, `CDA_PROFILE` or a `default_profile` key.
+//!
+//! `--config <FILE>` (or `CDA_CONFIG`) replaces both config files with the
+//! given one, so a CI run reads exactly that file and nothing else.
+//!
+//! `[plugins.<extension>]` tables and the `[owners]` table are the exception
+//! to first-wins merging: entries from the user and repo configs are
+//! combined, the repo's winning for the same extension or pattern. A plugin
+//! runs a command, so the repo config's plugins are ignored unless
+//! `--allow-repo-plugins` or `analysis.allow_repo_plugins` in the user (or
+//! `--config`) file opts in; the repo config can't opt itself in.
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::core::analyzer::{self, GapKind, PageNaming, Severity};
+use crate::core::backoff::{self, BackoffSettings};
+use crate::core::discovery::DiscoveryOptions;
+use crate::core::doc_checks::{self, DocChecks};
+use crate::core::exit::ConfigError;
+use crate::core::parser;
+use crate::core::plugins::{self, Plugin};
+use crate::core::sanitize::{self, RawHtml};
+use crate::output::Format;
+
+/// Name of the repo-local config file, looked up in the current directory
+pub const REPO_CONFIG_FILE: &str = "cda.toml";
+
+pub const DEFAULT_PROVIDER: &str = "anthropic";
+pub const DEFAULT_OUTPUT: &str = "./cda-output";
+/// Default LLM concurrency, the value `analysis.parallelism` used to default to
+pub const DEFAULT_PARALLELISM: usize = 4;
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 1024 * 1024;
+pub const DEFAULT_MAX_SNIPPET_LINES: usize = 20;
+pub const DEFAULT_MAX_EXAMPLES: usize = 3;
+pub const DEFAULT_IGNORE_PATTERNS: &[&str] = &[
+    "node_modules",
+    "target",
+    "dist",
+    ".git",
+    "__pycache__",
+    "*.min.js",
+    "*.map",
+];
+
+/// Value type of a config key, used to parse `cda config --set` input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyType {
+    String,
+    Integer,
+    Bool,
+    StringList,
+}
+
+/// Every key a config file may set, as `section.name`
+pub const KEYS: &[(&str, KeyType)] = &[
+    ("llm.provider", KeyType::String),
+    ("llm.model", KeyType::String),
+    ("llm.context_window", KeyType::Integer),
+    ("analysis.parse_jobs", KeyType::Integer),
+    ("analysis.llm_concurrency", KeyType::Integer),
+    ("analysis.parallelism", KeyType::Integer),
+    ("analysis.deep", KeyType::Bool),
+    ("analysis.ownership", KeyType::Bool),
+    ("analysis.allow_repo_plugins", KeyType::Bool),
+    ("analysis.ignore_patterns", KeyType::StringList),
+    ("analysis.max_file_size", KeyType::Integer),
+    ("analysis.file_budget_secs", KeyType::Integer),
+    ("analysis.min_response_chars", KeyType::Integer),
+    ("analysis.slow_parse_ms", KeyType::Integer),
+    ("analysis.parse_timeout_secs", KeyType::Integer),
+    ("analysis.backoff_failure_percent", KeyType::Integer),
+    ("analysis.backoff_cooldown_secs", KeyType::Integer),
+    ("analysis.backoff_max_cooldowns", KeyType::Integer),
+    ("analysis.ignore_type_only_cycles", KeyType::Bool),
+    ("output.directory", KeyType::String),
+    ("output.format", KeyType::String),
+    ("output.include_snippets", KeyType::Bool),
+    ("output.max_snippet_lines", KeyType::Integer),
+    ("output.max_examples", KeyType::Integer),
+    ("output.language", KeyType::String),
+    ("output.page_naming", KeyType::String),
+    ("output.raw_html", KeyType::String),
+    ("gaps.unused_export", KeyType::String),
+    ("gaps.missing_docs", KeyType::String),
+    ("gaps.dead_code", KeyType::String),
+    ("gaps.untested", KeyType::String),
+    ("gaps.undocumented_command", KeyType::String),
+    ("gaps.circular_dependency", KeyType::String),
+    ("gaps.restated_docs", KeyType::String),
+    ("gaps.undocumented_params", KeyType::String),
+    ("gaps.short_docs", KeyType::String),
+    ("gaps.undeclared_feature", KeyType::String),
+    ("gaps.unused_feature", KeyType::String),
+    ("doc_checks.restated_docs", KeyType::Bool),
+    ("doc_checks.undocumented_params", KeyType::Bool),
+    ("doc_checks.short_docs", KeyType::Bool),
+    ("doc_checks.min_params", KeyType::Integer),
+    ("doc_checks.min_length", KeyType::Integer),
+];
+
+/// Look up a key in the schema, suggesting the closest match for typos
+pub fn key_type(key: &str) -> Result<KeyType> {
+    if let Some((_, ty)) = KEYS.iter().find(|(k, _)| *k == key) {
+        return Ok(*ty);
+    }
+
+    let suggestion = KEYS
+        .iter()
+        .map(|(k, _)| (*k, strsim::levenshtein(key, k)))
+        .min_by_key(|(_, distance)| *distance)
+        .filter(|(_, distance)| *distance <= 4)
+        .map(|(k, _)| format!(". Did you mean `{}`?", k))
+        .unwrap_or_default();
+
+    anyhow::bail!("Unknown config key `{}`{}", key, suggestion)
+}
+
+/// Keys that look like credentials must never be written to a config file.
+/// Whole words of the key count, so `llm.auth_token` is one and
+/// `llm.max_tokens` isn't.
+pub fn is_credential_key(key: &str) -> bool {
+    let key = key.to_lowercase();
+    let words: Vec<&str> = key.split(['.', '_', '-']).collect();
+    words
+        .iter()
+        .any(|w| matches!(*w, "apikey" | "token" | "secret" | "password"))
+        || words.windows(2).any(|pair| pair == ["api", "key"])
+}
+
+/// One configuration layer, as written in a TOML file. Every field is
+/// optional so a layer only overrides what it sets.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct ConfigFile {
+    pub llm: LlmSection,
+    pub analysis: AnalysisSection,
+    pub output: OutputSection,
+    /// `[gaps]` severity of each kind of gap, by [`GapKind::name`]
+    pub gaps: BTreeMap<String, Severity>,
+    /// `[doc_checks]` opt-in style checks on doc comments
+    pub doc_checks: DocChecksSection,
+    /// Profile to apply; set by `--profile` and `CDA_PROFILE` in those layers
+    pub default_profile: Option<String>,
+    /// `[profile.<name>]` overrides
+    pub profile: BTreeMap<String, ConfigFile>,
+    /// `[plugins.<extension>]` external extractors; only read from the user,
+    /// repo and `--config` files
+    pub plugins: BTreeMap<String, Plugin>,
+    /// `[owners]` team owning each path pattern, for attributing gaps ahead
+    /// of CODEOWNERS; only read from the user, repo and `--config` files
+    pub owners: BTreeMap<String, String>,
+    /// Explicit config file; set by `--config` and `CDA_CONFIG` in those
+    /// layers, never read from TOML
+    #[serde(skip)]
+    pub config_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct LlmSection {
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    /// Context window of the model in tokens, replacing the built-in table
+    pub context_window: Option<usize>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct AnalysisSection {
+    /// Files parsed concurrently
+    pub parse_jobs: Option<usize>,
+    /// LLM requests in flight at once
+    pub llm_concurrency: Option<usize>,
+    /// Older name for `llm_concurrency`, which wins when both are set
+    pub parallelism: Option<usize>,
+    pub deep: Option<bool>,
+    /// Collect git and CODEOWNERS ownership (off by default)
+    pub ownership: Option<bool>,
+    pub ignore_patterns: Option<Vec<String>>,
+    pub max_file_size: Option<u64>,
+    /// Wall time the LLM analysis of one file may take, in seconds
+    pub file_budget_secs: Option<u64>,
+    /// Shorter answers to a module prompt are rejected; 0 accepts any
+    pub min_response_chars: Option<u64>,
+    /// Parses taking longer are logged and listed in the timings
+    pub slow_parse_ms: Option<u64>,
+    /// Parses still running after this many seconds are abandoned for a
+    /// pattern scan; 0 never abandons one
+    pub parse_timeout_secs: Option<u64>,
+    /// Share of failed LLM analyses, in percent, that makes the deep loop
+    /// back off
+    pub backoff_failure_percent: Option<u64>,
+    pub backoff_cooldown_secs: Option<u64>,
+    /// Cooldowns before deep analysis stops
+    pub backoff_max_cooldowns: Option<u64>,
+    /// Leave type-only imports out of circular dependency gaps
+    pub ignore_type_only_cycles: Option<bool>,
+    /// Run `[plugins.*]` from the repo config; ignored when the repo config
+    /// sets it
+    pub allow_repo_plugins: Option<bool>,
+}
+
+/// Each check turns on the gaps of the same name
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct DocChecksSection {
+    pub restated_docs: Option<bool>,
+    pub undocumented_params: Option<bool>,
+    pub short_docs: Option<bool>,
+    pub min_params: Option<usize>,
+    pub min_length: Option<usize>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct OutputSection {
+    pub directory: Option<String>,
+    pub format: Option<Format>,
+    pub include_snippets: Option<bool>,
+    pub max_snippet_lines: Option<usize>,
+    /// Usage examples attached to each export
+    pub max_examples: Option<usize>,
+    /// Language the LLM writes its prose in
+    pub language: Option<String>,
+    pub page_naming: Option<PageNaming>,
+    /// What happens to raw HTML in LLM answers
+    pub raw_html: Option<RawHtml>,
+}
+
+impl ConfigFile {
+    /// Read and validate a config file, failing with every diagnostic found
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config {}", path.display()))?;
+
+        let diagnostics = validate(&content);
+        if !diagnostics.is_empty() {
+            anyhow::bail!("{}", format_diagnostics(path, &diagnostics));
+        }
+
+        toml::from_str(&content).with_context(|| format!("Invalid config {}", path.display()))
+    }
+
+    /// Build a layer from `CDA_*` environment variables
+    pub fn from_env(vars: &HashMap<String, String>) -> Result<Self> {
+        let get = |key: &str| vars.get(key).filter(|v| !v.is_empty()).cloned();
+
+        let parse_bool = |key: &str| -> Result<Option<bool>> {
+            get(key)
+                .map(|v| match v.to_lowercase().as_str() {
+                    "1" | "true" | "yes" => Ok(true),
+                    "0" | "false" | "no" => Ok(false),
+                    _ => anyhow::bail!("{} must be true or false, got {:?}", key, v),
+                })
+                .transpose()
+        };
+
+        Ok(Self {
+            llm: LlmSection {
+                provider: get("CDA_PROVIDER"),
+                model: get("CDA_MODEL"),
+                context_window: get("CDA_CONTEXT_WINDOW")
+                    .map(|v| v.parse().context("CDA_CONTEXT_WINDOW must be a number"))
+                    .transpose()?,
+            },
+            analysis: AnalysisSection {
+                parse_jobs: get("CDA_PARSE_JOBS")
+                    .map(|v| v.parse().context("CDA_PARSE_JOBS must be a number"))
+                    .transpose()?,
+                llm_concurrency: get("CDA_LLM_CONCURRENCY")
+                    .map(|v| v.parse().context("CDA_LLM_CONCURRENCY must be a number"))
+                    .transpose()?,
+                parallelism: get("CDA_PARALLELISM")
+                    .map(|v| v.parse().context("CDA_PARALLELISM must be a number"))
+                    .transpose()?,
+                deep: parse_bool("CDA_DEEP")?,
+                ownership: parse_bool("CDA_OWNERSHIP")?,
+                ignore_patterns: None,
+                max_file_size: get("CDA_MAX_FILE_SIZE")
+                    .map(|v| v.parse().context("CDA_MAX_FILE_SIZE must be a number"))
+                    .transpose()?,
+                file_budget_secs: get("CDA_FILE_BUDGET_SECS")
+                    .map(|v| v.parse().context("CDA_FILE_BUDGET_SECS must be a number"))
+                    .transpose()?,
+                min_response_chars: get("CDA_MIN_RESPONSE_CHARS")
+                    .map(|v| v.parse().context("CDA_MIN_RESPONSE_CHARS must be a number"))
+                    .transpose()?,
+                slow_parse_ms: get("CDA_SLOW_PARSE_MS")
+                    .map(|v| v.parse().context("CDA_SLOW_PARSE_MS must be a number"))
+                    .transpose()?,
+                parse_timeout_secs: get("CDA_PARSE_TIMEOUT_SECS")
+                    .map(|v| v.parse().context("CDA_PARSE_TIMEOUT_SECS must be a number"))
+                    .transpose()?,
+                backoff_failure_percent: None,
+                backoff_cooldown_secs: None,
+                backoff_max_cooldowns: None,
+                ignore_type_only_cycles: parse_bool("CDA_IGNORE_TYPE_ONLY_CYCLES")?,
+                allow_repo_plugins: None,
+            },
+            output: OutputSection {
+                directory: get("CDA_OUTPUT"),
+                format: get("CDA_FORMAT")
+                    .map(|v| {
+                        Format::from_str(&v, true)
+                            .map_err(|_| anyhow::anyhow!("CDA_FORMAT must be markdown or json"))
+                    })
+                    .transpose()?,
+                include_snippets: None,
+                max_snippet_lines: None,
+                max_examples: None,
+                language: get("CDA_DOC_LANGUAGE"),
+                page_naming: get("CDA_PAGE_NAMING")
+                    .map(|v| {
+                        PageNaming::from_str(&v, true).map_err(|_| {
+                            anyhow::anyhow!(
+                                "CDA_PAGE_NAMING must be mirror-tree, flat-mangled or hashed"
+                            )
+                        })
+                    })
+                    .transpose()?,
+                raw_html: get("CDA_RAW_HTML")
+                    .map(|v| {
+                        RawHtml::from_str(&v, true).map_err(|_| {
+                            anyhow::anyhow!("CDA_RAW_HTML must be escape, strip or keep")
+                        })
+                    })
+                    .transpose()?,
+            },
+            gaps: BTreeMap::new(),
+            doc_checks: DocChecksSection::default(),
+            default_profile: get("CDA_PROFILE"),
+            profile: BTreeMap::new(),
+            plugins: BTreeMap::new(),
+            owners: BTreeMap::new(),
+            config_file: get("CDA_CONFIG").map(PathBuf::from),
+        })
+    }
+}
+
+/// Where an effective setting came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Default,
+    UserConfig,
+    RepoConfig,
+    /// The file given by `--config` or `CDA_CONFIG`
+    ConfigFile,
+    Profile,
+    Env,
+    Cli,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::UserConfig => write!(f, "user config"),
+            Source::RepoConfig => write!(f, "repo config"),
+            Source::ConfigFile => write!(f, "config file"),
+            Source::Profile => write!(f, "profile"),
+            Source::Env => write!(f, "env"),
+            Source::Cli => write!(f, "cli"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Setting<T> {
+    pub value: T,
+    pub source: Source,
+}
+
+/// Fully resolved configuration
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub provider: Setting<String>,
+    pub model: Setting<Option<String>>,
+    pub context_window: Setting<Option<usize>>,
+    pub parse_jobs: Setting<usize>,
+    pub llm_concurrency: Setting<usize>,
+    pub deep: Setting<bool>,
+    pub ownership: Setting<bool>,
+    pub ignore_patterns: Setting<Vec<String>>,
+    pub max_file_size: Setting<u64>,
+    pub file_budget_secs: Setting<u64>,
+    pub min_response_chars: Setting<u64>,
+    pub slow_parse_ms: Setting<u64>,
+    pub parse_timeout_secs: Setting<u64>,
+    pub backoff_failure_percent: Setting<u64>,
+    pub backoff_cooldown_secs: Setting<u64>,
+    pub backoff_max_cooldowns: Setting<u64>,
+    pub ignore_type_only_cycles: Setting<bool>,
+    pub output: Setting<String>,
+    pub format: Setting<Format>,
+    pub include_snippets: Setting<bool>,
+    pub max_snippet_lines: Setting<usize>,
+    /// Usage examples mined for each export; 0 turns them off
+    pub max_examples: Setting<usize>,
+    /// Language of generated prose; `None` leaves it to the prompts
+    pub doc_language: Setting<Option<String>>,
+    pub page_naming: Setting<PageNaming>,
+    pub raw_html: Setting<RawHtml>,
+    /// Severity of each kind of gap, in [`GapKind::ALL`] order
+    pub gap_severities: Vec<(GapKind, Setting<Severity>)>,
+    pub restated_docs: Setting<bool>,
+    pub undocumented_params: Setting<bool>,
+    pub short_docs: Setting<bool>,
+    pub doc_min_params: Setting<usize>,
+    pub doc_min_length: Setting<usize>,
+    /// Selected profile, if any
+    pub profile: Setting<Option<String>>,
+    /// Profiles defined in either config file
+    pub profiles: Vec<String>,
+    pub allow_repo_plugins: Setting<bool>,
+    /// Plugins by extension; the repo config's win over the user config's
+    /// when `allow_repo_plugins` is set
+    pub plugins: BTreeMap<String, Setting<Plugin>>,
+    /// Extensions of repo config plugins left out for lack of
+    /// `allow_repo_plugins`
+    pub ignored_repo_plugins: Vec<String>,
+    /// Team of each path pattern; the repo config's win over the user
+    /// config's
+    pub owners: BTreeMap<String, Setting<String>>,
+    pub user_path: Option<PathBuf>,
+    pub repo_path: Option<PathBuf>,
+    /// The `--config` file that replaced the user and repo configs
+    pub config_path: Option<Setting<PathBuf>>,
+}
+
+/// All layers that feed into a [`Config`], highest precedence first
+#[derive(Debug, Default)]
+pub struct Layers {
+    pub cli: ConfigFile,
+    pub env: ConfigFile,
+    /// The selected profile from the repo config, filled by [`Layers::apply_profile`]
+    pub repo_profile: ConfigFile,
+    /// The selected profile from the user config
+    pub user_profile: ConfigFile,
+    pub repo: ConfigFile,
+    pub user: ConfigFile,
+    /// `user` holds the `--config` file rather than the user config
+    pub explicit: bool,
+}
+
+impl Layers {
+    /// Pick the first layer that sets a value, falling back to the default
+    fn pick<T>(&self, get: impl Fn(&ConfigFile) -> Option<T>, default: T) -> Setting<T> {
+        [
+            (&self.cli, Source::Cli),
+            (&self.env, Source::Env),
+            (&self.repo_profile, Source::Profile),
+            (&self.user_profile, Source::Profile),
+            (&self.repo, Source::RepoConfig),
+            (&self.user, self.user_source()),
+        ]
+        .into_iter()
+        .find_map(|(layer, source)| get(layer).map(|value| Setting { value, source }))
+        .unwrap_or(Setting {
+            value: default,
+            source: Source::Default,
+        })
+    }
+
+    fn user_source(&self) -> Source {
+        if self.explicit {
+            Source::ConfigFile
+        } else {
+            Source::UserConfig
+        }
+    }
+
+    /// Like [`Layers::pick`], but skipping the repo config and its profile,
+    /// for settings a checkout mustn't be able to turn on for itself
+    fn pick_trusted<T>(&self, get: impl Fn(&ConfigFile) -> Option<T>, default: T) -> Setting<T> {
+        [
+            (&self.cli, Source::Cli),
+            (&self.env, Source::Env),
+            (&self.user_profile, Source::Profile),
+            (&self.user, self.user_source()),
+        ]
+        .into_iter()
+        .find_map(|(layer, source)| get(layer).map(|value| Setting { value, source }))
+        .unwrap_or(Setting {
+            value: default,
+            source: Source::Default,
+        })
+    }
+
+    fn allow_repo_plugins(&self) -> Setting<bool> {
+        self.pick_trusted(|l| l.analysis.allow_repo_plugins, false)
+    }
+
+    /// Plugins from the user config, overridden per extension by the repo
+    /// config's if those are allowed
+    fn plugins(&self) -> BTreeMap<String, Setting<Plugin>> {
+        let mut layers = vec![(&self.user, self.user_source())];
+        if self.allow_repo_plugins().value {
+            layers.push((&self.repo, Source::RepoConfig));
+        }
+
+        let mut plugins = BTreeMap::new();
+        for (layer, source) in layers {
+            for (extension, plugin) in &layer.plugins {
+                plugins.insert(
+                    extension.trim_start_matches('.').to_lowercase(),
+                    Setting {
+                        value: plugin.clone(),
+                        source,
+                    },
+                );
+            }
+        }
+        plugins
+    }
+
+    fn owners(&self) -> BTreeMap<String, Setting<String>> {
+        let mut owners = BTreeMap::new();
+        for (layer, source) in [
+            (&self.user, self.user_source()),
+            (&self.repo, Source::RepoConfig),
+        ] {
+            for (pattern, team) in &layer.owners {
+                owners.insert(
+                    pattern.clone(),
+                    Setting {
+                        value: team.clone(),
+                        source,
+                    },
+                );
+            }
+        }
+        owners
+    }
+
+    /// Profiles defined in the repo or user config
+    pub fn profiles(&self) -> Vec<String> {
+        let mut names: Vec<_> = self
+            .repo
+            .profile
+            .keys()
+            .chain(self.user.profile.keys())
+            .cloned()
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Fill the profile layers from the profile selected by the CLI, env or
+    /// `default_profile`, failing if no config file defines it
+    pub fn apply_profile(&mut self) -> Result<()> {
+        let selected = self.pick(|l| l.default_profile.clone().map(Some), None);
+        let Some(name) = selected.value else {
+            return Ok(());
+        };
+
+        let repo = self.repo.profile.get(&name).cloned();
+        let user = self.user.profile.get(&name).cloned();
+        if repo.is_none() && user.is_none() {
+            let profiles = self.profiles();
+            if profiles.is_empty() {
+                anyhow::bail!(
+                    "Unknown profile `{}` (from {}); no profiles are defined. \
+                     Add a [profile.{}] section to ./{} or the user config",
+                    name,
+                    selected.source,
+                    name,
+                    REPO_CONFIG_FILE
+                );
+            }
+            anyhow::bail!(
+                "Unknown profile `{}` (from {}); available profiles: {}",
+                name,
+                selected.source,
+                profiles.join(", ")
+            );
+        }
+
+        self.repo_profile = repo.unwrap_or_default();
+        self.user_profile = user.unwrap_or_default();
+        Ok(())
+    }
+
+    pub fn merge(&self) -> Config {
+        let model = self.pick(|l| l.llm.model.clone(), String::new());
+
+        Config {
+            provider: self.pick(|l| l.llm.provider.clone(), DEFAULT_PROVIDER.to_string()),
+            model: Setting {
+                value: (model.source != Source::Default).then_some(model.value),
+                source: model.source,
+            },
+            context_window: self.pick(|l| l.llm.context_window.map(Some), None),
+            parse_jobs: self.pick(|l| l.analysis.parse_jobs, default_parse_jobs()),
+            llm_concurrency: self.pick(
+                |l| l.analysis.llm_concurrency.or(l.analysis.parallelism),
+                DEFAULT_PARALLELISM,
+            ),
+            deep: self.pick(|l| l.analysis.deep, false),
+            ownership: self.pick(|l| l.analysis.ownership, false),
+            ignore_patterns: self.pick(
+                |l| l.analysis.ignore_patterns.clone(),
+                DEFAULT_IGNORE_PATTERNS
+                    .iter()
+                    .map(|p| p.to_string())
+                    .collect(),
+            ),
+            max_file_size: self.pick(|l| l.analysis.max_file_size, DEFAULT_MAX_FILE_SIZE),
+            file_budget_secs: self.pick(
+                |l| l.analysis.file_budget_secs,
+                analyzer::DEFAULT_FILE_BUDGET_SECS,
+            ),
+            min_response_chars: self.pick(
+                |l| l.analysis.min_response_chars,
+                analyzer::DEFAULT_MIN_RESPONSE_CHARS as u64,
+            ),
+            slow_parse_ms: self.pick(|l| l.analysis.slow_parse_ms, parser::DEFAULT_SLOW_PARSE_MS),
+            parse_timeout_secs: self.pick(
+                |l| l.analysis.parse_timeout_secs,
+                parser::DEFAULT_PARSE_TIMEOUT_SECS,
+            ),
+            backoff_failure_percent: self.pick(
+                |l| l.analysis.backoff_failure_percent,
+                backoff::DEFAULT_FAILURE_PERCENT,
+            ),
+            backoff_cooldown_secs: self.pick(
+                |l| l.analysis.backoff_cooldown_secs,
+                backoff::DEFAULT_COOLDOWN_SECS,
+            ),
+            backoff_max_cooldowns: self.pick(
+                |l| l.analysis.backoff_max_cooldowns,
+                backoff::DEFAULT_MAX_COOLDOWNS,
+            ),
+            ignore_type_only_cycles: self.pick(|l| l.analysis.ignore_type_only_cycles, false),
+            output: self.pick(|l| l.output.directory.clone(), DEFAULT_OUTPUT.to_string()),
+            format: self.pick(|l| l.output.format, Format::default()),
+            include_snippets: self.pick(|l| l.output.include_snippets, true),
+            max_snippet_lines: self.pick(|l| l.output.max_snippet_lines, DEFAULT_MAX_SNIPPET_LINES),
+            max_examples: self.pick(|l| l.output.max_examples, DEFAULT_MAX_EXAMPLES),
+            doc_language: self.pick(|l| l.output.language.clone().map(Some), None),
+            page_naming: self.pick(|l| l.output.page_naming, PageNaming::default()),
+            raw_html: self.pick(|l| l.output.raw_html, RawHtml::default()),
+            gap_severities: GapKind::ALL
+                .iter()
+                .map(|&kind| {
+                    let severity = self.pick(
+                        |l| l.gaps.get(kind.name()).copied(),
+                        kind.default_severity(),
+                    );
+                    (kind, severity)
+                })
+                .collect(),
+            restated_docs: self.pick(|l| l.doc_checks.restated_docs, false),
+            undocumented_params: self.pick(|l| l.doc_checks.undocumented_params, false),
+            short_docs: self.pick(|l| l.doc_checks.short_docs, false),
+            doc_min_params: self.pick(|l| l.doc_checks.min_params, doc_checks::DEFAULT_MIN_PARAMS),
+            doc_min_length: self.pick(|l| l.doc_checks.min_length, doc_checks::DEFAULT_MIN_LENGTH),
+            profile: self.pick(|l| l.default_profile.clone().map(Some), None),
+            profiles: self.profiles(),
+            allow_repo_plugins: self.allow_repo_plugins(),
+            plugins: self.plugins(),
+            ignored_repo_plugins: if self.allow_repo_plugins().value {
+                Vec::new()
+            } else {
+                self.repo.plugins.keys().cloned().collect()
+            },
+            owners: self.owners(),
+            user_path: None,
+            repo_path: None,
+            config_path: None,
+        }
+    }
+}
+
+/// One parse job per available core
+pub fn default_parse_jobs() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Path of the per-user config file
+pub fn user_config_path() -> Result<PathBuf> {
+    Ok(crate::commands::config::project_dirs()?
+        .config_dir()
+        .join("config.toml"))
+}
+
+/// The config file given by `--config` (in the CLI layer) or `CDA_CONFIG`
+pub fn explicit_path(cli: &ConfigFile) -> Result<Option<Setting<PathBuf>>> {
+    let env = ConfigFile::from_env(&std::env::vars().collect())?;
+    Ok(explicit(cli, &env))
+}
+
+fn explicit(cli: &ConfigFile, env: &ConfigFile) -> Option<Setting<PathBuf>> {
+    [(cli, Source::Cli), (env, Source::Env)]
+        .into_iter()
+        .find_map(|(layer, source)| {
+            layer
+                .config_file
+                .clone()
+                .map(|value| Setting { value, source })
+        })
+}
+
+/// How an explicit config file was given, for messages
+pub fn explicit_origin(source: Source) -> &'static str {
+    match source {
+        Source::Env => "CDA_CONFIG",
+        _ => "--config",
+    }
+}
+
+/// Merge the CLI, environment, repo and user layers. Errors are marked as
+/// configuration errors for the exit code.
+pub fn load(cli: ConfigFile) -> Result<Config> {
+    read_layers(cli).map_err(ConfigError::wrap)
+}
+
+/// Load every layer from disk and the environment and merge them with the
+/// given CLI overrides. An explicit `--config` file is read instead of the
+/// user and repo configs and must exist.
+fn read_layers(cli: ConfigFile) -> Result<Config> {
+    let env = ConfigFile::from_env(&std::env::vars().collect())?;
+
+    if let Some(path) = explicit(&cli, &env) {
+        if !path.value.is_file() {
+            anyhow::bail!(
+                "Config file {} (from {}) does not exist",
+                path.value.display(),
+                explicit_origin(path.source)
+            );
+        }
+        let mut layers = Layers {
+            user: ConfigFile::from_path(&path.value)?,
+            cli,
+            env,
+            explicit: true,
+            ..Default::default()
+        };
+        layers.apply_profile()?;
+
+        return Ok(Config {
+            config_path: Some(path),
+            ..layers.merge()
+        });
+    }
+
+    let user_path = user_config_path().ok().filter(|p| p.exists());
+    let repo_path = Some(PathBuf::from(REPO_CONFIG_FILE)).filter(|p| p.exists());
+
+    let mut layers = Layers {
+        cli,
+        env,
+        repo: match &repo_path {
+            Some(p) => ConfigFile::from_path(p)?,
+            None => ConfigFile::default(),
+        },
+        user: match &user_path {
+            Some(p) => ConfigFile::from_path(p)?,
+            None => ConfigFile::default(),
+        },
+        ..Default::default()
+    };
+    layers.apply_profile()?;
+
+    Ok(Config {
+        user_path,
+        repo_path,
+        ..layers.merge()
+    })
+}
+
+impl Config {
+    /// Discovery with the configured filters; cda's cache is left out when
+    /// it is inside the analyzed path
+    pub fn discovery_options(&self) -> DiscoveryOptions {
+        let mut options = DiscoveryOptions::default();
+        options.ignore_patterns = self.ignore_patterns.value.clone();
+        options.max_file_size = Some(self.max_file_size.value);
+        if let Ok(dirs) = crate::commands::config::project_dirs() {
+            options.exclude_dirs.push(dirs.cache_dir().to_path_buf());
+        }
+        options
+    }
+
+    /// Lines of source to attach to each export, or `None` when snippets
+    /// are off
+    pub fn snippet_lines(&self) -> Option<usize> {
+        Some(self.max_snippet_lines.value).filter(|&n| self.include_snippets.value && n > 0)
+    }
+
+    /// Apply every process-wide setting, for a command that parses or calls
+    /// the LLM, so all of them honour the same configuration
+    pub fn register_process_settings(&self) {
+        self.register_plugins();
+        self.register_context_window();
+        self.register_file_budget();
+        self.register_parse_limits();
+        self.register_min_response();
+        self.register_backoff();
+        self.register_gap_severities();
+        self.register_doc_checks();
+        self.register_type_only_cycles();
+        self.register_doc_language();
+        self.register_page_naming();
+        self.register_raw_html();
+    }
+
+    /// Use the configured context window, if any, for every model
+    fn register_context_window(&self) {
+        if let Some(tokens) = self.context_window.value {
+            info!(
+                "Context window: {} tokens (from {})",
+                tokens, self.context_window.source
+            );
+            crate::llm::set_context_window(tokens);
+        }
+    }
+
+    /// Use the configured per-file budget for LLM analysis
+    fn register_file_budget(&self) {
+        if self.file_budget_secs.source != Source::Default {
+            info!(
+                "Per-file analysis budget: {}s (from {})",
+                self.file_budget_secs.value, self.file_budget_secs.source
+            );
+        }
+        analyzer::set_file_budget(Duration::from_secs(self.file_budget_secs.value));
+    }
+
+    /// Use the configured slow-parse threshold and parse timeout
+    fn register_parse_limits(&self) {
+        for (key, setting, unit) in [
+            ("Slow parse threshold", &self.slow_parse_ms, "ms"),
+            ("Parse timeout", &self.parse_timeout_secs, "s"),
+        ] {
+            if setting.source != Source::Default {
+                info!(
+                    "{}: {}{} (from {})",
+                    key, setting.value, unit, setting.source
+                );
+            }
+        }
+        parser::set_parse_limits(
+            Duration::from_millis(self.slow_parse_ms.value),
+            Some(Duration::from_secs(self.parse_timeout_secs.value)).filter(|t| !t.is_zero()),
+        );
+    }
+
+    /// Use the configured minimum length of module answers
+    fn register_min_response(&self) {
+        if self.min_response_chars.source != Source::Default {
+            info!(
+                "Minimum module answer: {} characters (from {})",
+                self.min_response_chars.value, self.min_response_chars.source
+            );
+        }
+        analyzer::set_min_response_chars(self.min_response_chars.value as usize);
+    }
+
+    /// Back off a failing provider as configured
+    fn register_backoff(&self) {
+        for (key, setting) in [
+            (
+                "analysis.backoff_failure_percent",
+                &self.backoff_failure_percent,
+            ),
+            (
+                "analysis.backoff_cooldown_secs",
+                &self.backoff_cooldown_secs,
+            ),
+            (
+                "analysis.backoff_max_cooldowns",
+                &self.backoff_max_cooldowns,
+            ),
+        ] {
+            if setting.source != Source::Default {
+                info!("{} = {} (from {})", key, setting.value, setting.source);
+            }
+        }
+        backoff::set_backoff(BackoffSettings {
+            failure_percent: self.backoff_failure_percent.value,
+            cooldown: Duration::from_secs(self.backoff_cooldown_secs.value),
+            max_cooldowns: self.backoff_max_cooldowns.value,
+        });
+    }
+
+    /// Leave type-only imports out of cycle detection if configured
+    fn register_type_only_cycles(&self) {
+        if self.ignore_type_only_cycles.value {
+            info!(
+                "Ignoring type-only imports in cycles (from {})",
+                self.ignore_type_only_cycles.source
+            );
+        }
+        analyzer::set_ignore_type_only_cycles(self.ignore_type_only_cycles.value);
+    }
+
+    /// Run the doc checks turned on under `[doc_checks]`
+    fn register_doc_checks(&self) {
+        for (key, setting) in [
+            ("restated_docs", &self.restated_docs),
+            ("undocumented_params", &self.undocumented_params),
+            ("short_docs", &self.short_docs),
+        ] {
+            if setting.value {
+                info!("Checking docs for {} gaps (from {})", key, setting.source);
+            }
+        }
+        doc_checks::set_doc_checks(DocChecks {
+            restated_docs: self.restated_docs.value,
+            undocumented_params: self.undocumented_params.value,
+            short_docs: self.short_docs.value,
+            min_params: self.doc_min_params.value,
+            min_length: self.doc_min_length.value,
+        });
+    }
+
+    /// Name pages under `modules/` with the configured scheme
+    pub fn register_page_naming(&self) {
+        if self.page_naming.source != Source::Default {
+            let name = self.page_naming.value.to_possible_value();
+            info!(
+                "Page naming: {} (from {})",
+                name.as_ref().map_or("", |v| v.get_name()),
+                self.page_naming.source
+            );
+        }
+        analyzer::set_page_naming(self.page_naming.value);
+    }
+
+    /// Treat raw HTML in LLM answers as configured
+    fn register_raw_html(&self) {
+        if self.raw_html.source != Source::Default {
+            let name = self.raw_html.value.to_possible_value();
+            info!(
+                "Raw HTML in LLM answers: {} (from {})",
+                name.as_ref().map_or("", |v| v.get_name()),
+                self.raw_html.source
+            );
+        }
+        sanitize::set_raw_html(self.raw_html.value);
+    }
+
+    /// Have the LLM write its prose in the configured language, if any
+    fn register_doc_language(&self) {
+        let language = self.doc_language.value.as_deref();
+        if let Some(language) = language.filter(|l| !l.trim().is_empty()) {
+            info!(
+                "Writing generated docs in {} (from {})",
+                language, self.doc_language.source
+            );
+            crate::llm::set_doc_language(language);
+        }
+    }
+
+    /// Give gaps the configured severities
+    fn register_gap_severities(&self) {
+        for (kind, severity) in &self.gap_severities {
+            if severity.source != Source::Default {
+                info!(
+                    "Severity of {} gaps: {} (from {})",
+                    kind.name(),
+                    severity.value.name(),
+                    severity.source
+                );
+            }
+        }
+        analyzer::set_gap_severities(
+            self.gap_severities
+                .iter()
+                .map(|(kind, severity)| (*kind, severity.value)),
+        );
+    }
+
+    /// Make the configured plugins available to discovery and parsing
+    pub fn register_plugins(&self) {
+        if !self.ignored_repo_plugins.is_empty() {
+            warn!(
+                "Ignoring plugins for {} from {}; pass --allow-repo-plugins or set \
+                 analysis.allow_repo_plugins in the user config to run them",
+                self.ignored_repo_plugins
+                    .iter()
+                    .map(|e| format!(".{}", e))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                REPO_CONFIG_FILE
+            );
+        }
+        for (extension, plugin) in &self.plugins {
+            info!(
+                "Plugin for .{} files: {} (from {})",
+                extension,
+                plugin.value.display(),
+                plugin.source
+            );
+            plugins::register(extension, plugin.value.clone());
+        }
+    }
+
+    /// `(key, value, source)` rows for display
+    pub fn entries(&self) -> Vec<(&'static str, String, Source)> {
+        fn row<T: fmt::Debug>(key: &'static str, s: &Setting<T>) -> (&'static str, String, Source) {
+            (key, format!("{:?}", s.value), s.source)
+        }
+
+        let mut entries = vec![
+            (
+                "llm.provider",
+                self.provider.value.clone(),
+                self.provider.source,
+            ),
+            (
+                "llm.model",
+                self.model
+                    .value
+                    .clone()
+                    .unwrap_or_else(|| "(provider default)".to_string()),
+                self.model.source,
+            ),
+            (
+                "llm.context_window",
+                match self.context_window.value {
+                    Some(tokens) => tokens.to_string(),
+                    None => "(per model)".to_string(),
+                },
+                self.context_window.source,
+            ),
+            row("analysis.parse_jobs", &self.parse_jobs),
+            row("analysis.llm_concurrency", &self.llm_concurrency),
+            row("analysis.deep", &self.deep),
+            row("analysis.ownership", &self.ownership),
+            row("analysis.allow_repo_plugins", &self.allow_repo_plugins),
+            row("analysis.ignore_patterns", &self.ignore_patterns),
+            row("analysis.max_file_size", &self.max_file_size),
+            row("analysis.file_budget_secs", &self.file_budget_secs),
+            row("analysis.min_response_chars", &self.min_response_chars),
+            row("analysis.slow_parse_ms", &self.slow_parse_ms),
+            row("analysis.parse_timeout_secs", &self.parse_timeout_secs),
+            row(
+                "analysis.backoff_failure_percent",
+                &self.backoff_failure_percent,
+            ),
+            row(
+                "analysis.backoff_cooldown_secs",
+                &self.backoff_cooldown_secs,
+            ),
+            row(
+                "analysis.backoff_max_cooldowns",
+                &self.backoff_max_cooldowns,
+            ),
+            row(
+                "analysis.ignore_type_only_cycles",
+                &self.ignore_type_only_cycles,
+            ),
+            (
+                "output.directory",
+                self.output.value.clone(),
+                self.output.source,
+            ),
+            (
+                "output.format",
+                self.format
+                    .value
+                    .to_possible_value()
+                    .map(|v| v.get_name().to_string())
+                    .unwrap_or_default(),
+                self.format.source,
+            ),
+            row("output.include_snippets", &self.include_snippets),
+            row("output.max_snippet_lines", &self.max_snippet_lines),
+            row("output.max_examples", &self.max_examples),
+            (
+                "output.language",
+                self.doc_language
+                    .value
+                    .clone()
+                    .unwrap_or_else(|| "(prompt language)".to_string()),
+                self.doc_language.source,
+            ),
+            (
+                "output.page_naming",
+                self.page_naming
+                    .value
+                    .to_possible_value()
+                    .map(|v| v.get_name().to_string())
+                    .unwrap_or_default(),
+                self.page_naming.source,
+            ),
+            (
+                "output.raw_html",
+                self.raw_html
+                    .value
+                    .to_possible_value()
+                    .map(|v| v.get_name().to_string())
+                    .unwrap_or_default(),
+                self.raw_html.source,
+            ),
+        ];
+        for (key, _) in KEYS.iter().filter(|(k, _)| k.starts_with("gaps.")) {
+            let kind = key.strip_prefix("gaps.").and_then(GapKind::from_name);
+            if let Some((_, severity)) = self.gap_severities.iter().find(|(k, _)| Some(*k) == kind)
+            {
+                entries.push((*key, severity.value.name().to_string(), severity.source));
+            }
+        }
+        entries.extend([
+            row("doc_checks.restated_docs", &self.restated_docs),
+            row("doc_checks.undocumented_params", &self.undocumented_params),
+            row("doc_checks.short_docs", &self.short_docs),
+            row("doc_checks.min_params", &self.doc_min_params),
+            row("doc_checks.min_length", &self.doc_min_length),
+        ]);
+        entries
+    }
+}
+
+/// A problem found while validating a config file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// 1-based line number, when the location is known
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+pub fn format_diagnostics(path: &Path, diagnostics: &[Diagnostic]) -> String {
+    let mut out = format!("Invalid config {}:", path.display());
+    for diagnostic in diagnostics {
+        out.push_str(&format!("\n  {}", diagnostic));
+    }
+    out
+}
+
+/// Check a config file against the schema: unknown keys, type mismatches,
+/// unsupported provider/model/format values and out-of-range numbers
+pub fn validate(content: &str) -> Vec<Diagnostic> {
+    let mut validator = Validator {
+        content,
+        context: String::new(),
+        diagnostics: Vec::new(),
+    };
+
+    let doc = match toml_edit::ImDocument::parse(content) {
+        Ok(doc) => doc,
+        Err(e) => {
+            validator.push(e.span(), e.message().trim().to_string());
+            return validator.diagnostics;
+        }
+    };
+
+    validator.sections(doc.as_table(), true);
+    validator.diagnostics
+}
+
+struct Validator<'a> {
+    content: &'a str,
+    /// Prefix for messages about keys inside a profile
+    context: String,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Validator<'_> {
+    fn push(&mut self, span: Option<std::ops::Range<usize>>, message: String) {
+        let line = span.map(|s| {
+            self.content[..s.start.min(self.content.len())]
+                .matches('\n')
+                .count()
+                + 1
+        });
+        self.diagnostics.push(Diagnostic {
+            line,
+            message: format!("{}{}", self.context, message),
+        })
+    }
+
+    /// Check the `[llm]`, `[analysis]` and `[output]` sections of `table`.
+    /// `top_level` also allows `default_profile` and `[profile.*]`.
+    fn sections(&mut self, table: &dyn toml_edit::TableLike, top_level: bool) {
+        let mut provider = None;
+        let mut model = None;
+
+        for (section, item) in table.iter() {
+            let span = table.key(section).and_then(|k| k.span());
+
+            if top_level && section == "default_profile" {
+                if !item.is_str() {
+                    self.push(
+                        item.span().or(span),
+                        format!(
+                            "`default_profile` must be a string, got {}",
+                            item.type_name()
+                        ),
+                    );
+                }
+                continue;
+            }
+            if top_level && section == "profile" {
+                self.profiles(item, span);
+                continue;
+            }
+            if top_level && section == "plugins" {
+                self.plugins(item, span);
+                continue;
+            }
+            if top_level && section == "owners" {
+                self.owners(item, span);
+                continue;
+            }
+
+            let Some(table) = item.as_table_like() else {
+                let is_section = KEYS
+                    .iter()
+                    .any(|(k, _)| k.split_once('.').is_some_and(|(s, _)| s == section));
+                if is_section {
+                    self.push(span, format!("`{}` must be a table", section));
+                } else if let Err(e) = key_type(section) {
+                    self.push(span, e.to_string());
+                }
+                continue;
+            };
+
+            for (name, item) in table.iter() {
+                let key = format!("{}.{}", section, name);
+                let key_span = table.key(name).and_then(|k| k.span());
+                let span = item.span().or(key_span.clone());
+
+                let ty = match key_type(&key) {
+                    Ok(ty) => ty,
+                    Err(_) if is_credential_key(&key) => {
+                        self.push(
+                            key_span,
+                            format!(
+                                "`{}` looks like a credential; provide it through the \
+                                 provider's environment variable instead",
+                                key
+                            ),
+                        );
+                        continue;
+                    }
+                    Err(e) => {
+                        self.push(key_span, e.to_string());
+                        continue;
+                    }
+                };
+
+                let type_ok = match ty {
+                    KeyType::String => item.is_str(),
+                    KeyType::Integer => item.is_integer(),
+                    KeyType::Bool => item.is_bool(),
+                    KeyType::StringList => item
+                        .as_array()
+                        .is_some_and(|a| a.iter().all(|v| v.is_str())),
+                };
+                if !type_ok {
+                    let expected = match ty {
+                        KeyType::String => "a string",
+                        KeyType::Integer => "an integer",
+                        KeyType::Bool => "true or false",
+                        KeyType::StringList => "a list of strings",
+                    };
+                    self.push(
+                        span,
+                        format!("`{}` must be {}, got {}", key, expected, item.type_name()),
+                    );
+                    continue;
+                }
+
+                match key.as_str() {
+                    "llm.provider" => {
+                        let name = item.as_str().unwrap_or_default();
+                        match crate::llm::canonical_provider(name) {
+                            Some(p) => provider = Some(p),
+                            None => self.push(
+                                span,
+                                format!(
+                                    "unknown provider `{}`; expected one of {}",
+                                    name,
+                                    crate::llm::PROVIDERS.join(", ")
+                                ),
+                            ),
+                        }
+                    }
+                    "llm.model" => {
+                        let name = item.as_str().unwrap_or_default();
+                        if name.trim().is_empty() {
+                            self.push(span, "`llm.model` must not be empty".to_string());
+                        } else {
+                            model = Some((name.to_string(), span));
+                        }
+                    }
+                    "output.language" if item.as_str().is_some_and(|l| l.trim().is_empty()) => {
+                        self.push(span, "`output.language` must not be empty".to_string());
+                    }
+                    "output.format" => {
+                        let name = item.as_str().unwrap_or_default();
+                        if Format::from_str(name, true).is_err() {
+                            self.push(
+                                span,
+                                format!("unknown format `{}`; expected markdown or json", name),
+                            );
+                        }
+                    }
+                    "output.page_naming" => {
+                        let name = item.as_str().unwrap_or_default();
+                        if PageNaming::from_str(name, true).is_err() {
+                            self.push(
+                                span,
+                                format!(
+                                    "unknown page naming `{}`; expected mirror-tree, \
+                                     flat-mangled or hashed",
+                                    name
+                                ),
+                            );
+                        }
+                    }
+                    "output.raw_html" => {
+                        let name = item.as_str().unwrap_or_default();
+                        if RawHtml::from_str(name, true).is_err() {
+                            self.push(
+                                span,
+                                format!(
+                                    "unknown raw_html `{}`; expected escape, strip or keep",
+                                    name
+                                ),
+                            );
+                        }
+                    }
+                    "analysis.parse_jobs" | "analysis.llm_concurrency" | "analysis.parallelism"
+                        if item.as_integer().is_some_and(|n| n < 1) =>
+                    {
+                        self.push(span, format!("`{}` must be at least 1", key));
+                    }
+                    "llm.context_window" if item.as_integer().is_some_and(|n| n < 1) => {
+                        self.push(
+                            span,
+                            "`llm.context_window` must be a positive number of tokens".to_string(),
+                        );
+                    }
+                    "analysis.file_budget_secs" if item.as_integer().is_some_and(|n| n < 1) => {
+                        self.push(
+                            span,
+                            "`analysis.file_budget_secs` must be a positive number of seconds"
+                                .to_string(),
+                        );
+                    }
+                    "analysis.slow_parse_ms" | "analysis.parse_timeout_secs"
+                        if item.as_integer().is_some_and(|n| n < 0) =>
+                    {
+                        self.push(span, format!("`{}` can't be negative", key));
+                    }
+                    "analysis.min_response_chars" if item.as_integer().is_some_and(|n| n < 0) => {
+                        self.push(
+                            span,
+                            "`analysis.min_response_chars` can't be negative".to_string(),
+                        );
+                    }
+                    "analysis.backoff_failure_percent"
+                        if item.as_integer().is_some_and(|n| !(1..=100).contains(&n)) =>
+                    {
+                        self.push(
+                            span,
+                            "`analysis.backoff_failure_percent` must be between 1 and 100"
+                                .to_string(),
+                        );
+                    }
+                    "analysis.backoff_cooldown_secs"
+                        if item.as_integer().is_some_and(|n| n < 1) =>
+                    {
+                        self.push(
+                            span,
+                            "`analysis.backoff_cooldown_secs` must be a positive number of seconds"
+                                .to_string(),
+                        );
+                    }
+                    "analysis.backoff_max_cooldowns"
+                        if item.as_integer().is_some_and(|n| n < 0) =>
+                    {
+                        self.push(
+                            span,
+                            "`analysis.backoff_max_cooldowns` must not be negative".to_string(),
+                        );
+                    }
+                    "doc_checks.min_params" | "doc_checks.min_length"
+                        if item.as_integer().is_some_and(|n| n < 1) =>
+                    {
+                        self.push(span, format!("`{}` must be at least 1", key));
+                    }
+                    "analysis.max_file_size" if item.as_integer().is_some_and(|n| n < 1) => {
+                        self.push(
+                            span,
+                            "`analysis.max_file_size` must be a positive number of bytes"
+                                .to_string(),
+                        );
+                    }
+                    gap if gap.starts_with("gaps.")
+                        && item
+                            .as_str()
+                            .is_some_and(|name| Severity::from_name(name).is_none()) =>
+                    {
+                        self.push(span, format!("`{}` must be info, warning or error", key));
+                    }
+                    "output.max_snippet_lines" | "output.max_examples"
+                        if item.as_integer().is_some_and(|n| n < 0) =>
+                    {
+                        self.push(span, format!("`{}` must not be negative", key));
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        // A model that belongs to a different provider in the same table
+        if let (Some(provider), Some((model, span))) = (provider, model) {
+            let owner = crate::llm::PROVIDERS
+                .iter()
+                .find(|p| crate::llm::known_models(p).contains(&model.as_str()));
+            if let Some(owner) = owner.filter(|owner| **owner != provider) {
+                self.push(
+                    span,
+                    format!(
+                        "model `{}` is a {} model but the provider is {}",
+                        model, owner, provider
+                    ),
+                );
+            }
+        }
+    }
+
+    /// Check `[plugins.<extension>]` tables
+    fn plugins(&mut self, item: &toml_edit::Item, span: Option<std::ops::Range<usize>>) {
+        let Some(plugins) = item.as_table_like() else {
+            self.push(span, "`plugins` must be a table of plugins".to_string());
+            return;
+        };
+
+        for (extension, plugin) in plugins.iter() {
+            let span = plugins.key(extension).and_then(|k| k.span());
+            let Some(table) = plugin.as_table_like() else {
+                self.push(span, format!("`plugins.{}` must be a table", extension));
+                continue;
+            };
+
+            if table.get("command").is_none() {
+                self.push(
+                    span,
+                    format!("`plugins.{}` is missing `command`", extension),
+                );
+            }
+            for (name, item) in table.iter() {
+                let key = format!("plugins.{}.{}", extension, name);
+                let key_span = table.key(name).and_then(|k| k.span());
+                let span = item.span().or(key_span.clone());
+
+                match name {
+                    "command" => {
+                        let ok = item.as_array().is_some_and(|a| {
+                            !a.is_empty()
+                                && a.iter().all(|v| v.as_str().is_some_and(|s| !s.is_empty()))
+                        });
+                        if !ok {
+                            self.push(
+                                span,
+                                format!(
+                                    "`{}` must be a non-empty list of strings, such as \
+                                     [\"python3\", \"tools/extract.py\"]",
+                                    key
+                                ),
+                            );
+                        }
+                    }
+                    "input" => {
+                        if !matches!(item.as_str(), Some("path" | "stdin")) {
+                            self.push(span, format!("`{}` must be \"path\" or \"stdin\"", key));
+                        }
+                    }
+                    "timeout_secs" | "max_file_size" => {
+                        if item.as_integer().is_none_or(|n| n < 1) {
+                            self.push(span, format!("`{}` must be a positive integer", key));
+                        }
+                    }
+                    _ => self.push(
+                        key_span,
+                        format!(
+                            "Unknown plugin key `{}`; expected command, input, timeout_secs \
+                             or max_file_size",
+                            key
+                        ),
+                    ),
+                }
+            }
+        }
+    }
+
+    /// Check the `[owners]` table of path patterns and teams
+    fn owners(&mut self, item: &toml_edit::Item, span: Option<std::ops::Range<usize>>) {
+        let Some(owners) = item.as_table_like() else {
+            self.push(
+                span,
+                "`owners` must be a table of path patterns and teams".to_string(),
+            );
+            return;
+        };
+
+        for (pattern, team) in owners.iter() {
+            let key_span = owners.key(pattern).and_then(|k| k.span());
+            if team.as_str().is_none_or(|t| t.trim().is_empty()) {
+                self.push(
+                    team.span().or(key_span),
+                    format!(
+                        "`owners.\"{}\"` must be a team name, such as \"@org/billing\"",
+                        pattern
+                    ),
+                );
+            }
+        }
+    }
+
+    /// Check `[profile.<name>]` tables, each shaped like a config file
+    fn profiles(&mut self, item: &toml_edit::Item, span: Option<std::ops::Range<usize>>) {
+        let Some(profiles) = item.as_table_like() else {
+            self.push(span, "`profile` must be a table of profiles".to_string());
+            return;
+        };
+
+        for (name, profile) in profiles.iter() {
+            let span = profiles.key(name).and_then(|k| k.span());
+            match profile.as_table_like() {
+                Some(table) => {
+                    self.context = format!("profile `{}`: ", name);
+                    self.sections(table, false);
+                    self.context.clear();
+                }
+                None => self.push(span, format!("`profile.{}` must be a table", name)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(toml: &str) -> ConfigFile {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn test_defaults_when_no_layer_sets_a_value() {
+        let config = Layers::default().merge();
+        assert_eq!(config.provider.value, DEFAULT_PROVIDER);
+        assert_eq!(config.provider.source, Source::Default);
+        assert_eq!(config.model.value, None);
+        assert_eq!(config.llm_concurrency.value, DEFAULT_PARALLELISM);
+        assert!(config.include_snippets.value);
+    }
+
+    #[test]
+    fn test_parse_jobs_and_llm_concurrency() {
+        let config = Layers::default().merge();
+        assert_eq!(config.parse_jobs.value, default_parse_jobs());
+        assert_eq!(config.parse_jobs.source, Source::Default);
+
+        // The old `parallelism` key still sets LLM concurrency, but the new
+        // key wins when a layer sets both
+        let repo = layer("[analysis]\nparallelism = 2\nparse_jobs = 3");
+        let user = layer("[analysis]\nllm_concurrency = 6");
+        let config = Layers {
+            repo: repo.clone(),
+            user,
+            ..Default::default()
+        }
+        .merge();
+        assert_eq!(config.llm_concurrency.value, 2);
+        assert_eq!(config.llm_concurrency.source, Source::RepoConfig);
+        assert_eq!(config.parse_jobs.value, 3);
+
+        let both = layer("[analysis]\nparallelism = 2\nllm_concurrency = 5");
+        let config = Layers {
+            repo: both,
+            ..Default::default()
+        }
+        .merge();
+        assert_eq!(config.llm_concurrency.value, 5);
+
+        assert_eq!(
+            validate("[analysis]\nparse_jobs = 0")[0].message,
+            "`analysis.parse_jobs` must be at least 1"
+        );
+    }
+
+    #[test]
+    fn test_precedence_matrix() {
+        let user = layer("[analysis]\nparallelism = 1\n[llm]\nprovider = \"ollama\"\nmodel = \"llama3\"\n[output]\nformat = \"json\"");
+        let repo = layer("[analysis]\nparallelism = 2\n[llm]\nprovider = \"openai\"");
+        let env = layer("[analysis]\nparallelism = 3");
+        let cli = layer("[analysis]\nparallelism = 8");
+
+        let all = Layers {
+            cli: cli.clone(),
+            env: env.clone(),
+            repo: repo.clone(),
+            user: user.clone(),
+            ..Default::default()
+        }
+        .merge();
+        assert_eq!(all.llm_concurrency.value, 8);
+        assert_eq!(all.llm_concurrency.source, Source::Cli);
+        assert_eq!(all.provider.value, "openai");
+        assert_eq!(all.provider.source, Source::RepoConfig);
+        assert_eq!(all.model.value.as_deref(), Some("llama3"));
+        assert_eq!(all.model.source, Source::UserConfig);
+        assert!(matches!(all.format.value, Format::Json));
+
+        let no_cli = Layers {
+            env: env.clone(),
+            repo: repo.clone(),
+            user: user.clone(),
+            ..Default::default()
+        }
+        .merge();
+        assert_eq!(no_cli.llm_concurrency.value, 3);
+        assert_eq!(no_cli.llm_concurrency.source, Source::Env);
+
+        let no_env = Layers {
+            repo: repo.clone(),
+            user: user.clone(),
+            ..Default::default()
+        }
+        .merge();
+        assert_eq!(no_env.llm_concurrency.value, 2);
+        assert_eq!(no_env.llm_concurrency.source, Source::RepoConfig);
+
+        let user_only = Layers {
+            user,
+            ..Default::default()
+        }
+        .merge();
+        assert_eq!(user_only.llm_concurrency.value, 1);
+        assert_eq!(user_only.provider.value, "ollama");
+        assert_eq!(user_only.llm_concurrency.source, Source::UserConfig);
+    }
+
+    #[test]
+    fn test_plugins_merge_per_extension() {
+        let user = layer(
+            "[plugins.dsl]\ncommand = [\"user-dsl\"]\n[plugins.proto]\ncommand = [\"protoc-cda\"]",
+        );
+        let repo = layer(
+            "[plugins.dsl]\ncommand = [\"python3\", \"tools/dsl.py\"]\ninput = \"stdin\"\ntimeout_secs = 5",
+        );
+
+        let config = Layers {
+            cli: layer("[analysis]\nallow_repo_plugins = true"),
+            repo,
+            user,
+            ..Default::default()
+        }
+        .merge();
+        assert_eq!(config.plugins.keys().collect::<Vec<_>>(), ["dsl", "proto"]);
+        let dsl = &config.plugins["dsl"];
+        assert_eq!(dsl.source, Source::RepoConfig);
+        assert_eq!(dsl.value.command, ["python3", "tools/dsl.py"]);
+        assert_eq!(dsl.value.input, plugins::PluginInput::Stdin);
+        assert_eq!(dsl.value.timeout_secs, Some(5));
+        assert_eq!(config.plugins["proto"].source, Source::UserConfig);
+    }
+
+    #[test]
+    fn test_repo_plugins_need_opt_in() {
+        let repo = "[plugins.dsl]\ncommand = [\"sh\", \"tools/dsl.sh\"]";
+        let layers = |cli: &str, repo: &str, user: &str| {
+            Layers {
+                cli: layer(cli),
+                repo: layer(repo),
+                user: layer(user),
+                ..Default::default()
+            }
+            .merge()
+        };
+
+        let config = layers("", repo, "");
+        assert!(config.plugins.is_empty());
+        assert_eq!(config.ignored_repo_plugins, ["dsl"]);
+
+        // The repo config can't allow its own plugins
+        let config = layers(
+            "",
+            &format!("{}\n[analysis]\nallow_repo_plugins = true", repo),
+            "",
+        );
+        assert!(config.plugins.is_empty());
+        assert_eq!(config.allow_repo_plugins.source, Source::Default);
+
+        let config = layers("", repo, "[analysis]\nallow_repo_plugins = true");
+        assert_eq!(config.plugins["dsl"].source, Source::RepoConfig);
+        assert!(config.ignored_repo_plugins.is_empty());
+        let config = layers("[analysis]\nallow_repo_plugins = true", repo, "");
+        assert_eq!(config.plugins["dsl"].source, Source::RepoConfig);
+    }
+
+    #[test]
+    fn test_owners_merge_per_pattern() {
+        let user = layer(
+            "[owners]
+\"src/\" = \"@me\"
+\"web/\" = \"@org/web\"",
+        );
+        let repo = layer(
+            "[owners]
+\"src/\" = \"@org/core\"",
+        );
+
+        let config = Layers {
+            repo,
+            user,
+            ..Default::default()
+        }
+        .merge();
+        assert_eq!(config.owners["src/"].value, "@org/core");
+        assert_eq!(config.owners["src/"].source, Source::RepoConfig);
+        assert_eq!(config.owners["web/"].source, Source::UserConfig);
+    }
+
+    #[test]
+    fn test_explicit_config_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ci.toml");
+        std::fs::write(&path, "[analysis]\nparallelism = 7\n").unwrap();
+
+        let env = ConfigFile {
+            config_file: Some(PathBuf::from("env.toml")),
+            ..Default::default()
+        };
+        let cli = ConfigFile {
+            config_file: Some(path.clone()),
+            ..Default::default()
+        };
+        let chosen = explicit(&cli, &env).unwrap();
+        assert_eq!(chosen.value, path);
+        assert_eq!(explicit_origin(chosen.source), "--config");
+        let chosen = explicit(&ConfigFile::default(), &env).unwrap();
+        assert_eq!(explicit_origin(chosen.source), "CDA_CONFIG");
+
+        let config = load(cli).unwrap();
+        assert_eq!(config.llm_concurrency.value, 7);
+        assert_eq!(config.llm_concurrency.source, Source::ConfigFile);
+        assert!(config.user_path.is_none() && config.repo_path.is_none());
+
+        let missing = ConfigFile {
+            config_file: Some(dir.path().join("nope.toml")),
+            ..Default::default()
+        };
+        let err = load(missing).unwrap_err().to_string();
+        assert!(
+            err.contains("nope.toml (from --config) does not exist"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_profile_precedence() {
+        let user = layer(
+            "[llm]\nprovider = \"anthropic\"\n\
+             [profile.backend.analysis]\nparallelism = 2\ndeep = true\n\
+             [profile.backend.llm]\nmodel = \"claude-opus-4-20250514\"",
+        );
+        let repo = layer(
+            "default_profile = \"frontend\"\n\
+             [analysis]\nparallelism = 6\n\
+             [profile.frontend]\nllm.provider = \"ollama\"\nanalysis.ignore_patterns = [\"*.stories.tsx\"]\n\
+             [profile.backend.analysis]\nparallelism = 3",
+        );
+        let layers = |cli: &str, env: &str| {
+            let mut layers = Layers {
+                cli: layer(cli),
+                env: layer(env),
+                repo: repo.clone(),
+                user: user.clone(),
+                ..Default::default()
+            };
+            layers.apply_profile().map(|_| layers.merge())
+        };
+
+        // Repo default_profile applies over the plain repo and user settings
+        let config = layers("", "").unwrap();
+        assert_eq!(config.profile.value.as_deref(), Some("frontend"));
+        assert_eq!(config.profile.source, Source::RepoConfig);
+        assert_eq!(config.provider.value, "ollama");
+        assert_eq!(config.provider.source, Source::Profile);
+        assert_eq!(config.ignore_patterns.value, vec!["*.stories.tsx"]);
+        assert_eq!(config.llm_concurrency.value, 6);
+        assert_eq!(config.llm_concurrency.source, Source::RepoConfig);
+        assert_eq!(config.profiles, vec!["backend", "frontend"]);
+
+        // Env selects another profile; the repo's copy of it wins over the user's
+        let config = layers("", "default_profile = \"backend\"").unwrap();
+        assert_eq!(config.profile.source, Source::Env);
+        assert_eq!(config.llm_concurrency.value, 3);
+        assert_eq!(config.llm_concurrency.source, Source::Profile);
+        assert!(config.deep.value);
+        assert_eq!(
+            config.model.value.as_deref(),
+            Some("claude-opus-4-20250514")
+        );
+        assert_eq!(config.provider.value, "anthropic");
+        assert_eq!(config.provider.source, Source::UserConfig);
+
+        // Env settings beat the profile, CLI settings and selection beat env
+        let config = layers(
+            "default_profile = \"backend\"\n[analysis]\nparallelism = 9",
+            "default_profile = \"frontend\"\n[analysis]\nparallelism = 7\ndeep = false",
+        )
+        .unwrap();
+        assert_eq!(config.profile.value.as_deref(), Some("backend"));
+        assert_eq!(config.profile.source, Source::Cli);
+        assert_eq!(config.llm_concurrency.value, 9);
+        assert_eq!(config.llm_concurrency.source, Source::Cli);
+        assert!(!config.deep.value);
+        assert_eq!(config.deep.source, Source::Env);
+
+        let err = layers("default_profile = \"mobile\"", "")
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("Unknown profile `mobile` (from cli)"),
+            "{}",
+            err
+        );
+        assert!(
+            err.contains("available profiles: backend, frontend"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_validate_profiles() {
+        let content = "default_profile = \"web\"\n\
+                       [profile.web.llm]\nprovider = \"gemini\"\n\
+                       [profile.web.analysis]\nparalelism = 2\n\
+                       [profile.api]\nllm.provider = \"ollama\"\n";
+        let diagnostics = validate(content);
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "line 3: profile `web`: unknown provider `gemini`; expected one of anthropic, openai, ollama",
+                "line 5: profile `web`: Unknown config key `analysis.paralelism`. Did you mean `analysis.parallelism`?",
+            ]
+        );
+
+        assert_eq!(
+            validate("default_profile = 3")[0].message,
+            "`default_profile` must be a string, got integer"
+        );
+        assert_eq!(
+            validate("[profile.web]\ndefault_profile = \"x\"")[0].message,
+            "profile `web`: Unknown config key `default_profile`"
+        );
+    }
+
+    #[test]
+    fn test_env_layer() {
+        let vars = HashMap::from([
+            ("CDA_PROVIDER".to_string(), "openai".to_string()),
+            ("CDA_PARALLELISM".to_string(), "6".to_string()),
+            ("CDA_FORMAT".to_string(), "JSON".to_string()),
+            ("CDA_DEEP".to_string(), "true".to_string()),
+            ("CDA_PROFILE".to_string(), "backend".to_string()),
+        ]);
+        let env = ConfigFile::from_env(&vars).unwrap();
+        assert_eq!(env.llm.provider.as_deref(), Some("openai"));
+        assert_eq!(env.analysis.parallelism, Some(6));
+        assert_eq!(env.analysis.deep, Some(true));
+        assert_eq!(env.default_profile.as_deref(), Some("backend"));
+        assert!(matches!(env.output.format, Some(Format::Json)));
+
+        let bad = HashMap::from([("CDA_PARALLELISM".to_string(), "lots".to_string())]);
+        assert!(ConfigFile::from_env(&bad).is_err());
+    }
+
+    #[test]
+    fn test_key_suggestions() {
+        assert_eq!(key_type("analysis.parallelism").unwrap(), KeyType::Integer);
+        let err = key_type("analysis.paralelism").unwrap_err().to_string();
+        assert!(err.contains("Did you mean `analysis.parallelism`?"));
+        let err = key_type("nonsense").unwrap_err().to_string();
+        assert!(!err.contains("Did you mean"));
+        assert!(is_credential_key("llm.api_key"));
+        assert!(is_credential_key("llm.openai-api-key"));
+        assert!(is_credential_key("llm.apikey"));
+        assert!(is_credential_key("llm.auth_token"));
+        assert!(is_credential_key("llm.secret"));
+        assert!(is_credential_key("llm.PASSWORD"));
+        assert!(!is_credential_key("llm.model"));
+        assert!(!is_credential_key("llm.max_tokens"));
+        assert!(!is_credential_key("llm.key_file"));
+        assert!(!is_credential_key("output.tokenizer"));
+    }
+
+    #[test]
+    fn test_default_config_template_parses() {
+        let config = layer(crate::commands::config::DEFAULT_CONFIG);
+        assert_eq!(config.analysis.llm_concurrency, Some(DEFAULT_PARALLELISM));
+        assert_eq!(config.analysis.max_file_size, Some(DEFAULT_MAX_FILE_SIZE));
+    }
+
+    #[test]
+    fn test_validate_accepts_default_template() {
+        assert_eq!(validate(crate::commands::config::DEFAULT_CONFIG), vec![]);
+    }
+
+    #[test]
+    fn test_validate_broken_config_corpus() {
+        let corpus: &[(&str, usize, &str)] = &[
+            ("[llm\nprovider = \"openai\"", 1, "invalid table header"),
+            (
+                "[analysis]\nparalelism = 4",
+                2,
+                "Did you mean `analysis.parallelism`?",
+            ),
+            (
+                "[llms]\nprovider = \"openai\"",
+                2,
+                "Did you mean `llm.provider`?",
+            ),
+            ("llm = \"openai\"", 1, "`llm` must be a table"),
+            ("[llm]\napi_key = \"sk-123\"", 2, "looks like a credential"),
+            (
+                "[analysis]\n\nparallelism = \"4\"",
+                3,
+                "`analysis.parallelism` must be an integer, got string",
+            ),
+            (
+                "[analysis]\ndeep = \"yes\"",
+                2,
+                "`analysis.deep` must be true or false",
+            ),
+            (
+                "[analysis]\nignore_patterns = [\"target\", 3]",
+                2,
+                "must be a list of strings",
+            ),
+            (
+                "[llm]\nprovider = \"gemini\"",
+                2,
+                "unknown provider `gemini`; expected one of anthropic, openai, ollama",
+            ),
+            (
+                "[llm]\nprovider = \"openai\"\nmodel = \"claude-opus-4-20250514\"",
+                3,
+                "model `claude-opus-4-20250514` is a anthropic model but the provider is openai",
+            ),
+            ("[llm]\nmodel = \" \"", 2, "`llm.model` must not be empty"),
+            (
+                "[output]\nformat = \"html\"",
+                2,
+                "unknown format `html`; expected markdown or json",
+            ),
+            (
+                "[analysis]\nparallelism = 0",
+                2,
+                "`analysis.parallelism` must be at least 1",
+            ),
+            (
+                "[llm]\ncontext_window = 0",
+                2,
+                "`llm.context_window` must be a positive number of tokens",
+            ),
+            (
+                "[analysis]\nfile_budget_secs = 0",
+                2,
+                "`analysis.file_budget_secs` must be a positive number of seconds",
+            ),
+            (
+                "[analysis]\nparse_timeout_secs = -30",
+                2,
+                "`analysis.parse_timeout_secs` can't be negative",
+            ),
+            (
+                "[analysis]\nmin_response_chars = -1",
+                2,
+                "`analysis.min_response_chars` can't be negative",
+            ),
+            (
+                "[analysis]\nbackoff_failure_percent = 120",
+                2,
+                "`analysis.backoff_failure_percent` must be between 1 and 100",
+            ),
+            (
+                "[analysis]\nbackoff_cooldown_secs = 0",
+                2,
+                "`analysis.backoff_cooldown_secs` must be a positive number of seconds",
+            ),
+            (
+                "[doc_checks]\nmin_params = 0",
+                2,
+                "`doc_checks.min_params` must be at least 1",
+            ),
+            (
+                "[analysis]\nmax_file_size = -1",
+                2,
+                "`analysis.max_file_size` must be a positive number of bytes",
+            ),
+            (
+                "[gaps]\ncircular_dependency = \"fatal\"",
+                2,
+                "`gaps.circular_dependency` must be info, warning or error",
+            ),
+            (
+                "[output]\nlanguage = \"\"",
+                2,
+                "`output.language` must not be empty",
+            ),
+            (
+                "[output]\npage_naming = \"deep\"",
+                2,
+                "unknown page naming `deep`; expected mirror-tree, flat-mangled or hashed",
+            ),
+            (
+                "[output]\nraw_html = \"allow\"",
+                2,
+                "unknown raw_html `allow`; expected escape, strip or keep",
+            ),
+            (
+                "[output]\nmax_snippet_lines = -5",
+                2,
+                "`output.max_snippet_lines` must not be negative",
+            ),
+            (
+                "[plugins.dsl]\ncommand = \"extract.sh\"",
+                2,
+                "`plugins.dsl.command` must be a non-empty list of strings",
+            ),
+            (
+                "[owners]\n\"src/billing/\" = 3",
+                2,
+                "`owners.\"src/billing/\"` must be a team name, such as \"@org/billing\"",
+            ),
+            (
+                "[plugins.dsl]\ninput = \"stdin\"",
+                1,
+                "`plugins.dsl` is missing `command`",
+            ),
+            (
+                "[plugins.dsl]\ncommand = [\"x\"]\ninput = \"file\"",
+                3,
+                "`plugins.dsl.input` must be \"path\" or \"stdin\"",
+            ),
+            (
+                "[plugins.dsl]\ncommand = [\"x\"]\ntimeout_secs = 0",
+                3,
+                "`plugins.dsl.timeout_secs` must be a positive integer",
+            ),
+            (
+                "[plugins.dsl]\ncommand = [\"x\"]\ntimeout = 5",
+                3,
+                "Unknown plugin key `plugins.dsl.timeout`",
+            ),
+        ];
+
+        for (content, line, message) in corpus {
+            let diagnostics = validate(content);
+            assert_eq!(diagnostics.len(), 1, "{:?}: {:?}", content, diagnostics);
+            assert_eq!(diagnostics[0].line, Some(*line), "{:?}", content);
+            assert!(
+                diagnostics[0].message.contains(message),
+                "{:?}: expected {:?}, got {:?}",
+                content,
+                message,
+                diagnostics[0].message
+            );
+        }
+    }
+
+    #[test]
+    fn test_validate_reports_every_problem() {
+        let content =
+            "[llm]\nprovider = \"gemini\"\n[analysis]\nparallelism = 0\nmax_file_size = -1\n";
+        let lines: Vec<_> = validate(content).iter().map(|d| d.line).collect();
+        assert_eq!(lines, vec![Some(2), Some(4), Some(5)]);
+    }
+
+    #[test]
+    fn test_from_path_fails_fast_with_diagnostics() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cda.toml");
+        std::fs::write(&path, "[analysis]\nparallelism = 0\n").unwrap();
+        let err = ConfigFile::from_path(&path).unwrap_err().to_string();
+        assert!(err.contains("line 2: `analysis.parallelism` must be at least 1"));
+    }
+}
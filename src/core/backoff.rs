@@ -0,0 +1,293 @@
+//! Easing off a provider that keeps failing
+//!
+//! During an outage, or once a quota is exhausted, every request of a deep
+//! run fails after its retries. [`Backoff`] watches the outcomes of the last
+//! [`WINDOW`] LLM analyses. When the share of failures reaches
+//! `analysis.backoff_failure_percent`, it halves the requests in flight and
+//! pauses new ones for `analysis.backoff_cooldown_secs`. When the failures
+//! persist through `analysis.backoff_max_cooldowns` cooldowns, deep analysis
+//! stops: the remaining files get static pages and stay out of the progress
+//! file, so a later run with `--resume` analyzes them.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tokio::time::Instant;
+use tracing::warn;
+
+use super::timings::Timings;
+
+/// LLM analyses whose outcomes the failure rate is taken over
+pub const WINDOW: usize = 10;
+
+/// Default of `analysis.backoff_failure_percent`
+pub const DEFAULT_FAILURE_PERCENT: u64 = 50;
+/// Default of `analysis.backoff_cooldown_secs`
+pub const DEFAULT_COOLDOWN_SECS: u64 = 30;
+/// Default of `analysis.backoff_max_cooldowns`
+pub const DEFAULT_MAX_COOLDOWNS: u64 = 3;
+
+/// When to back off, and for how long
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackoffSettings {
+    /// Failures among the last [`WINDOW`] analyses, in percent, that start
+    /// a cooldown
+    pub failure_percent: u64,
+    pub cooldown: Duration,
+    /// Cooldowns before deep analysis stops
+    pub max_cooldowns: u64,
+}
+
+impl Default for BackoffSettings {
+    fn default() -> Self {
+        Self {
+            failure_percent: DEFAULT_FAILURE_PERCENT,
+            cooldown: Duration::from_secs(DEFAULT_COOLDOWN_SECS),
+            max_cooldowns: DEFAULT_MAX_COOLDOWNS,
+        }
+    }
+}
+
+/// Settings set by the `analysis.backoff_*` keys
+static SETTINGS: OnceLock<BackoffSettings> = OnceLock::new();
+
+/// Back off according to `settings` for the rest of the process. Only the
+/// first call has an effect.
+pub fn set_backoff(settings: BackoffSettings) {
+    let _ = SETTINGS.set(settings);
+}
+
+fn settings() -> BackoffSettings {
+    SETTINGS.get().copied().unwrap_or_default()
+}
+
+/// What [`Backoff`] did when failures spiked, as listed in the timings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackoffAction {
+    /// Paused new requests and lowered the concurrency
+    Cooldown,
+    /// Stopped deep analysis for the rest of the run
+    Stop,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackoffEvent {
+    pub action: BackoffAction,
+    /// Failed analyses among the last `window`
+    pub failed: usize,
+    pub window: usize,
+    /// Requests allowed in flight from then on
+    pub concurrency: usize,
+    /// Milliseconds since the deep loop started
+    pub at_ms: u64,
+}
+
+/// Shared by the deep loop's workers; see the module docs
+pub struct Backoff {
+    settings: BackoffSettings,
+    slots: Arc<Semaphore>,
+    timings: Timings,
+    started: Instant,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    /// Outcomes of the latest analyses, newest last; `true` for a failure
+    recent: VecDeque<bool>,
+    concurrency: usize,
+    /// Permits to take out of `slots` as workers give them back
+    surplus: usize,
+    cooldowns: u64,
+    paused_until: Option<Instant>,
+    stopped: bool,
+    /// Files left static because deep analysis had stopped
+    skipped: usize,
+}
+
+impl Backoff {
+    /// Back off the workers that take their permits from `slots`, which
+    /// holds `concurrency` of them, with the configured settings
+    pub fn new(slots: Arc<Semaphore>, concurrency: usize, timings: &Timings) -> Self {
+        Self::with_settings(settings(), slots, concurrency, timings)
+    }
+
+    fn with_settings(
+        settings: BackoffSettings,
+        slots: Arc<Semaphore>,
+        concurrency: usize,
+        timings: &Timings,
+    ) -> Self {
+        Self {
+            settings,
+            slots,
+            timings: timings.clone(),
+            started: Instant::now(),
+            state: Mutex::new(State {
+                concurrency,
+                ..State::default()
+            }),
+        }
+    }
+
+    /// Wait out a cooldown before an LLM request. `false` once deep analysis
+    /// has stopped: the file is left to a later run.
+    pub async fn ready(&self) -> bool {
+        loop {
+            let until = {
+                let mut state = self.lock();
+                if state.stopped {
+                    state.skipped += 1;
+                    return false;
+                }
+                state.paused_until
+            };
+            match until {
+                Some(until) if until > Instant::now() => tokio::time::sleep_until(until).await,
+                _ => return true,
+            }
+        }
+    }
+
+    /// Record the outcome of an LLM analysis, backing off when failures
+    /// have reached the configured share of the last [`WINDOW`]
+    pub fn record(&self, failed: bool) {
+        let mut state = self.lock();
+        if state.stopped {
+            return;
+        }
+        state.recent.push_back(failed);
+        if state.recent.len() > WINDOW {
+            state.recent.pop_front();
+        }
+        let failures = state.recent.iter().filter(|&&f| f).count();
+        if state.recent.len() < WINDOW
+            || (failures as u64) * 100 < self.settings.failure_percent * WINDOW as u64
+        {
+            return;
+        }
+        // Outcomes from before the backoff don't count towards the next one
+        state.recent.clear();
+
+        let action = if state.cooldowns >= self.settings.max_cooldowns {
+            state.stopped = true;
+            warn!(
+                "{} of the last {} LLM analyses failed after {} cooldown(s); stopping deep \
+                 analysis. The remaining files get static pages only; run again with --resume \
+                 once the provider recovers",
+                failures, WINDOW, state.cooldowns
+            );
+            BackoffAction::Stop
+        } else {
+            state.cooldowns += 1;
+            let reduced = (state.concurrency / 2).max(1);
+            state.surplus += state.concurrency - reduced;
+            state.concurrency = reduced;
+            let taken = self.slots.forget_permits(state.surplus);
+            state.surplus -= taken;
+            state.paused_until = Some(Instant::now() + self.settings.cooldown);
+            warn!(
+                "{} of the last {} LLM analyses failed; pausing for {}s and lowering LLM \
+                 concurrency to {}",
+                failures,
+                WINDOW,
+                self.settings.cooldown.as_secs(),
+                reduced
+            );
+            BackoffAction::Cooldown
+        };
+        self.timings.backoff(BackoffEvent {
+            action,
+            failed: failures,
+            window: WINDOW,
+            concurrency: state.concurrency,
+            at_ms: self.started.elapsed().as_millis() as u64,
+        });
+    }
+
+    /// Give back a worker's permit, unless the concurrency was lowered
+    /// while it was out
+    pub fn release(&self, permit: SemaphorePermit<'_>) {
+        let mut state = self.lock();
+        if state.surplus > 0 {
+            state.surplus -= 1;
+            permit.forget();
+        }
+    }
+
+    /// Files left static because deep analysis stopped
+    pub fn skipped(&self) -> Option<usize> {
+        let state = self.lock();
+        state.stopped.then_some(state.skipped)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_backoff_cools_down_then_stops() {
+        let slots = Arc::new(Semaphore::new(4));
+        let timings = Timings::default();
+        let backoff = Backoff::with_settings(
+            BackoffSettings {
+                failure_percent: 50,
+                cooldown: Duration::from_millis(50),
+                max_cooldowns: 1,
+            },
+            Arc::clone(&slots),
+            4,
+            &timings,
+        );
+
+        // Four failures in ten is under the threshold
+        for failed in [true, false].repeat(3).into_iter().chain([true; 1]) {
+            backoff.record(failed);
+        }
+        backoff.record(false);
+        backoff.record(false);
+        backoff.record(false);
+        assert!(timings.report().backoff.is_empty());
+
+        // Three permits are out, so only one of the two to drop is taken
+        // now, and the other when a worker gives its permit back
+        let mut permits = slots.acquire_many(3).await.unwrap();
+        for _ in 0..WINDOW {
+            backoff.record(true);
+        }
+        assert_eq!(slots.available_permits(), 0);
+        for _ in 0..2 {
+            backoff.release(permits.split(1).unwrap());
+        }
+        drop(permits);
+        assert_eq!(slots.available_permits(), 2);
+
+        let started = Instant::now();
+        assert!(backoff.ready().await);
+        assert!(started.elapsed() >= Duration::from_millis(50));
+        assert_eq!(backoff.skipped(), None);
+
+        for _ in 0..WINDOW {
+            backoff.record(true);
+        }
+        assert!(!backoff.ready().await);
+        assert_eq!(backoff.skipped(), Some(1));
+
+        let events = timings.report().backoff;
+        let actions: Vec<_> = events.iter().map(|e| (e.action, e.concurrency)).collect();
+        assert_eq!(
+            actions,
+            [(BackoffAction::Cooldown, 2), (BackoffAction::Stop, 2)]
+        );
+        // The spike trips as soon as half of the window has failed
+        assert_eq!(events[0].failed, 5);
+    }
+}
@@ -0,0 +1,628 @@
+//! Behavior documented by tests
+//!
+//! Test names are a spec: `#[test] fn rejects_empty_input`,
+//! `it("retries on 429")`. [`collect`] finds the tests of every module, Rust
+//! tests inline in a source file and, with `--include-tests`, test files, and
+//! files each one under the module it exercises: its own for inline tests,
+//! otherwise the modules whose imported exports the test's body names, or
+//! the file the test file is named after. With `--mode deep`, [`describe`]
+//! has the LLM restate them as plain-language behavior.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use tracing::{debug, info, warn};
+
+use super::analyzer::ModuleAnalysis;
+use super::discovery::{FileInventory, Language, SourceFile};
+use super::parser;
+use super::redact;
+use super::run_commands::parse_answer;
+use super::source::{self, SourceText};
+use crate::llm::{self, LlmConfig, LlmProvider, Message, Role};
+
+/// A test that exercises a module
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Behavior {
+    /// The test function, or the `describe > it` titles of a JS/TS test
+    pub test: String,
+    /// File the test is in
+    pub path: String,
+    pub line: usize,
+    /// Doc comment or docstring of the test
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc: Option<String>,
+    /// The behavior in plain language, written by the LLM with `--mode deep`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub statement: Option<String>,
+}
+
+/// A test found in a file, with the source up to the next test
+struct TestCase<'a> {
+    name: String,
+    line: usize,
+    doc: Option<String>,
+    body: &'a str,
+}
+
+/// Set the behaviors of every module from the tests in the files of
+/// `inventory`. Test modules get none of their own.
+pub fn collect(modules: &mut [ModuleAnalysis], inventory: &FileInventory) {
+    let index: HashMap<&str, usize> = modules
+        .iter()
+        .enumerate()
+        .map(|(i, m)| (m.path.as_str(), i))
+        .collect();
+    let mut exporters: HashMap<&str, usize> = HashMap::new();
+    for (i, module) in modules.iter().enumerate() {
+        if !module.is_test_module {
+            for export in module.public_exports() {
+                exporters.insert(export.name.as_str(), i);
+            }
+        }
+    }
+
+    let mut found: Vec<(usize, Behavior)> = Vec::new();
+    for file in &inventory.source_files {
+        let Some(&module_index) = index.get(file.path.as_str()) else {
+            continue;
+        };
+        let module = &modules[module_index];
+        let imported: HashSet<&str> = module
+            .imports
+            .iter()
+            .filter(|i| !i.is_external)
+            .flat_map(|i| i.items.iter().map(String::as_str))
+            .collect();
+        for part in std::iter::once(file).chain(&file.companions) {
+            let content = match read(part) {
+                Some(content) => content,
+                None => continue,
+            };
+            for test in find_tests(&content, part.language) {
+                let targets: Vec<usize> = if !module.is_test_module {
+                    vec![module_index]
+                } else {
+                    let mut targets: Vec<usize> = identifiers(test.body)
+                        .filter(|name| imported.contains(name))
+                        .filter_map(|name| exporters.get(name).copied())
+                        .filter(|&target| target != module_index)
+                        .collect();
+                    targets.sort_unstable();
+                    targets.dedup();
+                    if targets.is_empty() {
+                        targets.extend(named_after(modules, &part.path));
+                    }
+                    targets
+                };
+                for target in targets {
+                    found.push((
+                        target,
+                        Behavior {
+                            test: test.name.clone(),
+                            path: part.path.clone(),
+                            line: test.line,
+                            doc: test.doc.clone(),
+                            statement: None,
+                        },
+                    ));
+                }
+            }
+        }
+    }
+
+    for module in modules.iter_mut() {
+        module.behaviors.clear();
+    }
+    for (target, behavior) in found {
+        modules[target].behaviors.push(behavior);
+    }
+    let mut total = 0;
+    for module in modules.iter_mut() {
+        module
+            .behaviors
+            .sort_by(|a, b| (&a.path, a.line, &a.test).cmp(&(&b.path, b.line, &b.test)));
+        module
+            .behaviors
+            .dedup_by(|a, b| a.path == b.path && a.line == b.line);
+        total += module.behaviors.len();
+    }
+    if total > 0 {
+        debug!("Found {} tests documenting module behavior", total);
+    }
+}
+
+fn read(file: &SourceFile) -> Option<String> {
+    match source::read_source(&file.abs_path) {
+        Ok(SourceText::Text { content, .. }) => Some(content),
+        Ok(SourceText::Binary) => None,
+        Err(e) => {
+            debug!("Cannot read {} for its tests: {}", file.path, e);
+            None
+        }
+    }
+}
+
+/// The tests in `content`: `#[test]` functions in Rust, `it`/`test` calls
+/// under their `describe` titles in JavaScript and TypeScript, `test_`
+/// functions in Python and `Test` functions in Go
+fn find_tests(content: &str, language: Language) -> Vec<TestCase<'_>> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut tests: Vec<(String, usize, Option<String>)> = Vec::new();
+    match language {
+        Language::Rust => {
+            let mut attribute = false;
+            for (i, line) in lines.iter().enumerate() {
+                let trimmed = line.trim();
+                if is_test_attribute(trimmed) {
+                    attribute = true;
+                } else if attribute && !trimmed.starts_with("#[") && !trimmed.starts_with("//") {
+                    attribute = false;
+                    if let Some(name) = after_keyword(trimmed, "fn ") {
+                        tests.push((name, i + 1, parser::extract_doc_comment(content, i + 1)));
+                    }
+                }
+            }
+        }
+        Language::JavaScript | Language::TypeScript => {
+            let mut describes: Vec<(usize, String)> = Vec::new();
+            for (i, line) in lines.iter().enumerate() {
+                let Some((call, title)) = js_test_call(line.trim_start()) else {
+                    continue;
+                };
+                let indent = line.len() - line.trim_start().len();
+                while describes.last().is_some_and(|(d, _)| *d >= indent) {
+                    describes.pop();
+                }
+                if call == "describe" {
+                    describes.push((indent, title));
+                } else {
+                    let mut name: Vec<&str> = describes.iter().map(|(_, t)| t.as_str()).collect();
+                    name.push(&title);
+                    tests.push((name.join(" > "), i + 1, None));
+                }
+            }
+        }
+        Language::Python => {
+            for (i, line) in lines.iter().enumerate() {
+                let trimmed = line.trim_start().trim_start_matches("async ");
+                if let Some(name) = after_keyword(trimmed, "def ").filter(|n| n.starts_with("test"))
+                {
+                    tests.push((name, i + 1, python_docstring(&lines[i + 1..])));
+                }
+            }
+        }
+        Language::Go => {
+            for (i, line) in lines.iter().enumerate() {
+                if !line.contains("*testing.T") {
+                    continue;
+                }
+                if let Some(name) = after_keyword(line, "func ").filter(|n| n.starts_with("Test")) {
+                    tests.push((name, i + 1, go_doc_comment(&lines[..i])));
+                }
+            }
+        }
+        _ => {}
+    }
+
+    // Each test's body runs up to the next test
+    let starts: Vec<usize> = std::iter::once(0)
+        .chain(content.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+    let offset = |line: usize| starts.get(line - 1).copied().unwrap_or(content.len());
+    let ends: Vec<usize> = tests
+        .iter()
+        .skip(1)
+        .map(|(_, line, _)| offset(*line))
+        .chain(std::iter::once(content.len()))
+        .collect();
+    tests
+        .into_iter()
+        .zip(ends)
+        .map(|((name, line, doc), end)| TestCase {
+            name,
+            line,
+            doc,
+            body: &content[offset(line)..end],
+        })
+        .collect()
+}
+
+/// `#[test]`, `#[tokio::test]`, `#[tokio::test(flavor = ...)]`, `#[rstest]`
+fn is_test_attribute(line: &str) -> bool {
+    let Some(attribute) = line.strip_prefix("#[") else {
+        return false;
+    };
+    let path = attribute
+        .split(['(', ']'])
+        .next()
+        .unwrap_or_default()
+        .trim();
+    path == "test" || path == "rstest" || path.ends_with("::test")
+}
+
+/// The identifier after `keyword` in `line`
+fn after_keyword(line: &str, keyword: &str) -> Option<String> {
+    let (_, rest) = line.split_once(keyword)?;
+    let name: String = rest
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || *c == '_')
+        .collect();
+    (!name.is_empty()).then_some(name)
+}
+
+/// `describe`, `it` or `test` and the title of a call starting `line`, as in
+/// `it("parses", ...)` or `test.only('parses', ...)`
+fn js_test_call(line: &str) -> Option<(&str, String)> {
+    let call = ["describe", "it", "test"]
+        .into_iter()
+        .find(|call| line.starts_with(call))?;
+    let mut rest = &line[call.len()..];
+    for modifier in [".only", ".skip", ".concurrent"] {
+        rest = rest.strip_prefix(modifier).unwrap_or(rest);
+    }
+    let rest = rest.strip_prefix('(')?.trim_start();
+    let quote = rest
+        .chars()
+        .next()
+        .filter(|c| matches!(c, '\'' | '"' | '`'))?;
+    let title = &rest[1..];
+    let end = title.find(quote)?;
+    Some((call, title[..end].to_string()))
+}
+
+/// First line of the docstring starting `body`, if any
+fn python_docstring(body: &[&str]) -> Option<String> {
+    let mut lines = body.iter().map(|l| l.trim()).filter(|l| !l.is_empty());
+    let first = lines.next()?;
+    let quote = ["\"\"\"", "'''"]
+        .into_iter()
+        .find(|q| first.starts_with(q))?;
+    let text = first[3..].trim_end_matches(quote).trim();
+    let text = if text.is_empty() {
+        lines.next()?.trim_end_matches(quote).trim()
+    } else {
+        text
+    };
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// The `//` comment right above the last of `before`
+fn go_doc_comment(before: &[&str]) -> Option<String> {
+    let mut doc: Vec<&str> = before
+        .iter()
+        .rev()
+        .map(|l| l.trim())
+        .take_while(|l| l.starts_with("//"))
+        .map(|l| l.trim_start_matches('/').trim())
+        .collect();
+    doc.reverse();
+    (!doc.is_empty()).then(|| doc.join(" "))
+}
+
+fn identifiers(body: &str) -> impl Iterator<Item = &str> {
+    body.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|word| !word.is_empty())
+}
+
+/// The module a test file is named after: `parser.test.ts`, `test_parser.py`,
+/// `parser_test.go` and `tests/parser.rs` test `parser`. When several
+/// modules have that name, the one nearest the test file.
+fn named_after(modules: &[ModuleAnalysis], test_path: &str) -> Option<usize> {
+    let stem = file_stem(test_path);
+    let stem = stem
+        .strip_suffix(".test")
+        .or_else(|| stem.strip_suffix(".spec"))
+        .or_else(|| stem.strip_suffix("_test"))
+        .or_else(|| stem.strip_prefix("test_"))
+        .unwrap_or(stem);
+    let candidates: Vec<usize> = modules
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| !m.is_test_module && file_stem(&m.path) == stem)
+        .map(|(i, _)| i)
+        .collect();
+    if candidates.len() <= 1 {
+        return candidates.first().copied();
+    }
+    let mut dir = Path::new(test_path).parent();
+    while let Some(current) = dir {
+        if let Some(&nearest) = candidates
+            .iter()
+            .find(|&&i| Path::new(&modules[i].path).starts_with(current))
+        {
+            return Some(nearest);
+        }
+        dir = current.parent();
+    }
+    None
+}
+
+/// File name up to its last extension
+fn file_stem(path: &str) -> &str {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    name.rsplit_once('.').map_or(name, |(stem, _)| stem)
+}
+
+/// Tests restated per LLM call
+const DESCRIBE_BATCH: usize = 40;
+
+const DESCRIBE_PROMPT: &str = r#"You turn a project's tests into documentation. The user lists tests, numbered, each with the module it exercises, its name and any doc comment. For each, answer one line:
+
+number | the behavior the test checks, as a plain-language statement about the module
+
+Write what the module does, not what the test does: "Rejects an empty config file", not "Tests that empty configs fail". Answer only with these lines."#;
+
+/// Ask the LLM to restate the behaviors of every module in plain language,
+/// [`DESCRIBE_BATCH`] tests per call. A failed call leaves those tests with
+/// their names only.
+pub async fn describe(modules: &mut [ModuleAnalysis], provider: &dyn LlmProvider) {
+    let tests: Vec<(usize, usize)> = modules
+        .iter()
+        .enumerate()
+        .flat_map(|(m, module)| (0..module.behaviors.len()).map(move |b| (m, b)))
+        .collect();
+    if tests.is_empty() {
+        return;
+    }
+
+    let mut described = 0;
+    for batch in tests.chunks(DESCRIBE_BATCH) {
+        let prompt: String = batch
+            .iter()
+            .enumerate()
+            .map(|(i, &(m, b))| {
+                let behavior = &modules[m].behaviors[b];
+                format!(
+                    "{}. {}: `{}`{}\n",
+                    i + 1,
+                    modules[m].path,
+                    behavior.test,
+                    behavior
+                        .doc
+                        .as_ref()
+                        .map(|doc| format!(" ({})", doc))
+                        .unwrap_or_default()
+                )
+            })
+            .collect();
+        let [prompt] = redact::redact_prompt("the test descriptions", [prompt.as_str()]);
+        let messages = vec![
+            Message {
+                role: Role::System,
+                content: llm::localize(DESCRIBE_PROMPT).into_owned(),
+            },
+            Message {
+                role: Role::User,
+                content: prompt.into_owned(),
+            },
+        ];
+        let config = LlmConfig {
+            max_tokens: 4096,
+            ..Default::default()
+        };
+        let response = match provider.complete(messages, config).await {
+            Ok(response) => response,
+            Err(e) => {
+                warn!("Failed to describe {} tests: {}", batch.len(), e);
+                continue;
+            }
+        };
+        for (number, statement) in response.lines().filter_map(parse_answer) {
+            let Some(&(m, b)) = number.checked_sub(1).and_then(|i| batch.get(i)) else {
+                continue;
+            };
+            modules[m].behaviors[b].statement = Some(statement.to_string());
+            described += 1;
+        }
+    }
+    info!(
+        "Described the behavior of {} of {} tests",
+        described,
+        tests.len()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn names(content: &str, language: Language) -> Vec<(String, usize, Option<String>)> {
+        find_tests(content, language)
+            .into_iter()
+            .map(|t| (t.name, t.line, t.doc))
+            .collect()
+    }
+
+    #[test]
+    fn test_find_tests() {
+        let rust = "pub fn parse() {}\n\n#[cfg(test)]\nmod tests {\n    /// Empty input is an error\n    #[test]\n    fn rejects_empty() {}\n\n    #[tokio::test(flavor = \"multi_thread\")]\n    #[ignore]\n    async fn retries() {}\n\n    fn helper() {}\n}\n";
+        assert_eq!(
+            names(rust, Language::Rust),
+            [
+                (
+                    "rejects_empty".to_string(),
+                    7,
+                    Some("Empty input is an error".to_string())
+                ),
+                ("retries".to_string(), 11, None),
+            ]
+        );
+
+        let ts = "describe('parser', () => {\n  it(\"parses a file\", () => {});\n  describe.only(`errors`, () => {\n    test('reports the line', () => {});\n  });\n  it('handles CRLF', () => {});\n});\ntest('top level', () => {});\n";
+        assert_eq!(
+            names(ts, Language::TypeScript)
+                .into_iter()
+                .map(|(name, line, _)| (name, line))
+                .collect::<Vec<_>>(),
+            [
+                ("parser > parses a file".to_string(), 2),
+                ("parser > errors > reports the line".to_string(), 4),
+                ("parser > handles CRLF".to_string(), 6),
+                ("top level".to_string(), 8),
+            ]
+        );
+
+        let py = "def helper():\n    pass\n\nclass TestParser:\n    def test_empty(self):\n        \"\"\"Empty input is an error.\"\"\"\n\nasync def test_retry():\n    pass\n";
+        assert_eq!(
+            names(py, Language::Python),
+            [
+                (
+                    "test_empty".to_string(),
+                    5,
+                    Some("Empty input is an error.".to_string())
+                ),
+                ("test_retry".to_string(), 8, None),
+            ]
+        );
+
+        let go = "package parser\n\n// Empty input is an error\nfunc TestEmpty(t *testing.T) {}\n\nfunc helper() {}\n";
+        assert_eq!(
+            names(go, Language::Go),
+            [(
+                "TestEmpty".to_string(),
+                4,
+                Some("Empty input is an error".to_string())
+            )]
+        );
+    }
+
+    fn module(path: &str, is_test: bool, exports: &[&str], imports: &[&str]) -> ModuleAnalysis {
+        let exports: Vec<_> = exports
+            .iter()
+            .map(|name| {
+                serde_json::json!({
+                    "name": name,
+                    "kind": "function",
+                    "signature": null,
+                    "description": "",
+                    "line_number": 1,
+                })
+            })
+            .collect();
+        let imports: Vec<_> = imports
+            .iter()
+            .map(|item| serde_json::json!({"source": "./src", "items": [item], "is_external": false}))
+            .collect();
+        serde_json::from_value(serde_json::json!({
+            "path": path,
+            "language": "typescript",
+            "exports": exports,
+            "imports": imports,
+            "summary": "",
+            "has_deep_analysis": false,
+            "is_test_module": is_test,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_collect_files_tests_under_the_modules_they_exercise() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = [
+            ("src/parser.ts", "export function parse() {}\n"),
+            ("src/format.ts", "export function format() {}\n"),
+            (
+                "test/app.test.ts",
+                "it('parses', () => { parse(); });\nit('formats', () => { format(parse()); });\nit('is unrelated', () => {});\n",
+            ),
+            ("src/format.spec.ts", "it('pads numbers', () => {});\n"),
+        ];
+        let mut inventory = FileInventory::default();
+        for (path, content) in files {
+            let abs_path = dir.path().join(path);
+            fs::create_dir_all(abs_path.parent().unwrap()).unwrap();
+            fs::write(&abs_path, content).unwrap();
+            inventory.source_files.push(SourceFile {
+                path: path.to_string(),
+                abs_path,
+                language: Language::TypeScript,
+                size: content.len() as u64,
+                is_test: path.contains("test") || path.contains("spec"),
+                modified: None,
+                companions: Vec::new(),
+                detection: Default::default(),
+            });
+        }
+        let mut modules = vec![
+            module("src/parser.ts", false, &["parse"], &[]),
+            module("src/format.ts", false, &["format"], &[]),
+            module("test/app.test.ts", true, &[], &["parse", "format"]),
+            module("src/format.spec.ts", true, &[], &[]),
+        ];
+        collect(&mut modules, &inventory);
+
+        let tests = |m: &ModuleAnalysis| -> Vec<String> {
+            m.behaviors
+                .iter()
+                .map(|b| format!("{}:{} {}", b.path, b.line, b.test))
+                .collect()
+        };
+        assert_eq!(
+            tests(&modules[0]),
+            ["test/app.test.ts:1 parses", "test/app.test.ts:2 formats"]
+        );
+        assert_eq!(
+            tests(&modules[1]),
+            [
+                "src/format.spec.ts:1 pads numbers",
+                "test/app.test.ts:2 formats"
+            ]
+        );
+        assert!(modules[2].behaviors.is_empty());
+    }
+
+    struct Restater;
+
+    #[async_trait::async_trait]
+    impl LlmProvider for Restater {
+        fn name(&self) -> &str {
+            "mock"
+        }
+
+        fn model(&self) -> &str {
+            "mock"
+        }
+
+        async fn list_models(&self) -> anyhow::Result<Vec<crate::llm::ModelInfo>> {
+            Ok(vec![])
+        }
+
+        async fn complete(
+            &self,
+            messages: Vec<Message>,
+            _config: LlmConfig,
+        ) -> anyhow::Result<String> {
+            assert_eq!(
+                messages[1].content,
+                "1. src/parser.ts: `parses` (Reads a file)\n2. src/parser.ts: `fails`\n"
+            );
+            Ok("2 | Rejects invalid input\n7 | Unknown\n".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_describe_with_llm() {
+        let mut modules = vec![module("src/parser.ts", false, &[], &[])];
+        let behavior = |test: &str, doc: Option<&str>| Behavior {
+            test: test.to_string(),
+            path: "src/parser.test.ts".to_string(),
+            line: 1,
+            doc: doc.map(String::from),
+            statement: None,
+        };
+        modules[0].behaviors = vec![
+            behavior("parses", Some("Reads a file")),
+            behavior("fails", None),
+        ];
+        describe(&mut modules, &Restater).await;
+        let statements: Vec<_> = modules[0]
+            .behaviors
+            .iter()
+            .map(|b| b.statement.as_deref())
+            .collect();
+        assert_eq!(statements, [None, Some("Rejects invalid input")]);
+    }
+}
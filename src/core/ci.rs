@@ -0,0 +1,408 @@
+//! CI pipelines: when they run, their jobs and the commands each job runs
+//!
+//! GitHub Actions workflows, `.gitlab-ci.yml` and `.circleci/config.yml` are
+//! read with the small reader of `core::yaml`. The commands of
+//! [`run_commands`](super::run_commands) that a job runs are marked, so
+//! `verify --run-commands` can start with them.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use tracing::debug;
+
+use super::discovery::FileInventory;
+use super::run_commands::{CommandKind, RunCommand};
+use super::yaml::Yaml;
+
+/// The CI service a pipeline is for. Serialized in kebab case:
+/// `github-actions`, `gitlab-ci`, `circleci`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[non_exhaustive]
+pub enum CiSystem {
+    GithubActions,
+    GitlabCi,
+    #[serde(rename = "circleci")]
+    CircleCi,
+}
+
+impl CiSystem {
+    pub fn label(self) -> &'static str {
+        match self {
+            CiSystem::GithubActions => "GitHub Actions",
+            CiSystem::GitlabCi => "GitLab CI",
+            CiSystem::CircleCi => "CircleCI",
+        }
+    }
+
+    /// The system a config file at `path` is for, if it is read
+    fn of(path: &str) -> Option<Self> {
+        let lower = path.to_lowercase();
+        let (dir, name) = lower.rsplit_once('/').unwrap_or(("", &lower));
+        let yaml = name.ends_with(".yml") || name.ends_with(".yaml");
+        if yaml && (dir == ".github/workflows" || dir.ends_with("/.github/workflows")) {
+            Some(CiSystem::GithubActions)
+        } else if name == ".gitlab-ci.yml" {
+            Some(CiSystem::GitlabCi)
+        } else if name == "config.yml" && (dir == ".circleci" || dir.ends_with("/.circleci")) {
+            Some(CiSystem::CircleCi)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Pipeline {
+    /// The config file
+    pub path: String,
+    pub system: CiSystem,
+    /// The workflow's `name`, when it has one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Events that start it, e.g. `push` and `pull_request`; empty when the
+    /// file doesn't say
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub triggers: Vec<String>,
+    pub jobs: Vec<CiJob>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct CiJob {
+    /// The job's `name`, else its key
+    pub name: String,
+    /// Shell command lines, in the order they run
+    pub commands: Vec<String>,
+}
+
+/// The pipelines of the inventory's CI config files, in path order
+pub fn extract(inventory: &FileInventory) -> Vec<Pipeline> {
+    let root = Path::new(&inventory.root);
+    let mut sources: Vec<&String> = inventory.config_files.iter().collect();
+    sources.sort();
+
+    let mut pipelines = Vec::new();
+    for source in sources {
+        let Some(system) = CiSystem::of(source) else {
+            continue;
+        };
+        match fs::read_to_string(root.join(source)) {
+            Ok(content) => pipelines.push(parse(source, system, &content)),
+            Err(e) => debug!("Not reading CI config {}: {}", source, e),
+        }
+    }
+    pipelines
+}
+
+/// Read the pipeline of one config file
+fn parse(path: &str, system: CiSystem, content: &str) -> Pipeline {
+    let doc = Yaml::parse(content);
+    let (name, triggers, jobs) = match system {
+        CiSystem::GithubActions => {
+            let triggers = match doc.get("on") {
+                Some(Yaml::Map(events)) => events.iter().map(|(k, _)| k.clone()).collect(),
+                Some(on) => on.strings(),
+                None => vec![],
+            };
+            let jobs = doc
+                .get("jobs")
+                .map(Yaml::entries)
+                .unwrap_or_default()
+                .iter()
+                .map(|(id, job)| CiJob {
+                    name: job
+                        .get("name")
+                        .and_then(Yaml::as_str)
+                        .unwrap_or(id)
+                        .to_string(),
+                    commands: job
+                        .get("steps")
+                        .map(Yaml::items)
+                        .unwrap_or_default()
+                        .iter()
+                        .filter_map(|step| step.get("run"))
+                        .flat_map(|run| command_lines(&run.strings()))
+                        .collect(),
+                })
+                .collect();
+            (doc.get("name").and_then(Yaml::as_str), triggers, jobs)
+        }
+        CiSystem::GitlabCi => {
+            // Top-level keys that configure the pipeline rather than name a job
+            const RESERVED: &[&str] = &[
+                "default",
+                "include",
+                "stages",
+                "variables",
+                "workflow",
+                "image",
+                "services",
+                "cache",
+                "before_script",
+                "after_script",
+            ];
+            let jobs = doc
+                .entries()
+                .iter()
+                .filter(|(key, job)| {
+                    !key.starts_with('.')
+                        && !RESERVED.contains(&key.as_str())
+                        && job.get("script").is_some()
+                })
+                .map(|(key, job)| CiJob {
+                    name: key.clone(),
+                    commands: command_lines(
+                        &job.get("script").map(Yaml::strings).unwrap_or_default(),
+                    ),
+                })
+                .collect();
+            let name = doc
+                .get("workflow")
+                .and_then(|w| w.get("name"))
+                .and_then(Yaml::as_str);
+            (name, vec![], jobs)
+        }
+        CiSystem::CircleCi => {
+            let jobs = doc
+                .get("jobs")
+                .map(Yaml::entries)
+                .unwrap_or_default()
+                .iter()
+                .map(|(key, job)| CiJob {
+                    name: key.clone(),
+                    commands: job
+                        .get("steps")
+                        .map(Yaml::items)
+                        .unwrap_or_default()
+                        .iter()
+                        .filter_map(|step| step.get("run"))
+                        .flat_map(|run| {
+                            let command = run.get("command").unwrap_or(run);
+                            command_lines(&command.strings())
+                        })
+                        .collect(),
+                })
+                .collect();
+            (None, vec![], jobs)
+        }
+    };
+    Pipeline {
+        path: path.to_string(),
+        system,
+        name: name.map(String::from),
+        triggers,
+        jobs,
+    }
+}
+
+/// The command lines of `run` or `script` values: one per line, comments
+/// and blank lines left out, continued lines joined
+fn command_lines(scripts: &[String]) -> Vec<String> {
+    let mut lines = Vec::new();
+    for script in scripts {
+        let mut pending = String::new();
+        for line in script.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match line.strip_suffix('\\') {
+                Some(start) => {
+                    pending.push_str(start.trim_end());
+                    pending.push(' ');
+                }
+                None => {
+                    pending.push_str(line);
+                    lines.push(std::mem::take(&mut pending));
+                }
+            }
+        }
+        if !pending.trim().is_empty() {
+            lines.push(pending.trim().to_string());
+        }
+    }
+    lines
+}
+
+/// Mark the commands that a CI job runs
+pub fn mark_commands(commands: &mut [RunCommand], pipelines: &[Pipeline]) {
+    let lines: Vec<&str> = pipelines
+        .iter()
+        .flat_map(|p| &p.jobs)
+        .flat_map(|j| &j.commands)
+        .flat_map(|line| line.split("&&").flat_map(|part| part.split(';')))
+        .collect();
+    for command in commands {
+        let forms = invocations(command);
+        command.in_ci = lines.iter().any(|line| {
+            let line = normalize(line);
+            forms
+                .iter()
+                .any(|form| line == *form || line.starts_with(&format!("{} ", form)))
+        });
+    }
+}
+
+/// The ways a command can be spelled in a CI step
+fn invocations(command: &RunCommand) -> Vec<String> {
+    let command_line = normalize(&command.command);
+    let mut forms = vec![command_line.clone()];
+    if command.kind == CommandKind::NpmScript {
+        if let Some(script) = command_line.strip_prefix("npm run ") {
+            forms.push(format!("yarn {}", script));
+            forms.push(format!("yarn run {}", script));
+            forms.push(format!("pnpm {}", script));
+            forms.push(format!("pnpm run {}", script));
+            if matches!(script, "test" | "start" | "stop" | "restart") {
+                forms.push(format!("npm {}", script));
+            }
+        }
+    }
+    forms
+}
+
+fn normalize(line: &str) -> String {
+    line.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_github_workflow() {
+        let pipeline = parse(
+            ".github/workflows/ci.yml",
+            CiSystem::GithubActions,
+            r#"name: CI
+on:
+  push:
+    branches: [main]
+  pull_request:
+
+jobs:
+  test:
+    name: "Test (stable)"
+    runs-on: ubuntu-latest
+    steps:
+      - uses: actions/checkout@v4
+      - name: Build
+        run: cargo build --locked  # no network
+      - run: |
+          cargo test \
+            --workspace
+          # and lint
+          cargo clippy -- -D warnings
+
+  release:
+    steps:
+    - run: npm ci && npm test
+"#,
+        );
+        assert_eq!(pipeline.name.as_deref(), Some("CI"));
+        assert_eq!(pipeline.triggers, ["push", "pull_request"]);
+        let jobs: Vec<_> = pipeline
+            .jobs
+            .iter()
+            .map(|j| (j.name.as_str(), j.commands.clone()))
+            .collect();
+        assert_eq!(
+            jobs,
+            [
+                (
+                    "Test (stable)",
+                    vec![
+                        "cargo build --locked".to_string(),
+                        "cargo test --workspace".to_string(),
+                        "cargo clippy -- -D warnings".to_string(),
+                    ]
+                ),
+                ("release", vec!["npm ci && npm test".to_string()]),
+            ]
+        );
+
+        let flow = parse(
+            "w.yml",
+            CiSystem::GithubActions,
+            "on: [push, 'workflow_dispatch']\njobs: {}\n",
+        );
+        assert_eq!(flow.triggers, ["push", "workflow_dispatch"]);
+    }
+
+    #[test]
+    fn test_gitlab_and_circleci() {
+        let gitlab = parse(
+            ".gitlab-ci.yml",
+            CiSystem::GitlabCi,
+            "stages: [test]\n\n.template:\n  script: echo hidden\n\nunit:\n  stage: test\n  script:\n    - make test\n    - make lint\n\ndeploy:\n  script: ./deploy.sh\n",
+        );
+        let jobs: Vec<_> = gitlab
+            .jobs
+            .iter()
+            .map(|j| (j.name.as_str(), j.commands.clone()))
+            .collect();
+        assert_eq!(
+            jobs,
+            [
+                (
+                    "unit",
+                    vec!["make test".to_string(), "make lint".to_string()]
+                ),
+                ("deploy", vec!["./deploy.sh".to_string()]),
+            ]
+        );
+
+        let circle = parse(
+            ".circleci/config.yml",
+            CiSystem::CircleCi,
+            "version: 2.1\njobs:\n  build:\n    docker:\n      - image: cimg/node:20.0\n    steps:\n      - checkout\n      - run: yarn build\n      - run:\n          name: Test\n          command: yarn test\n",
+        );
+        assert_eq!(circle.jobs[0].commands, ["yarn build", "yarn test"]);
+    }
+
+    #[test]
+    fn test_mark_commands() {
+        let command = |line: &str, kind| RunCommand {
+            command: line.into(),
+            kind,
+            source: "package.json".into(),
+            description: None,
+            inferred: false,
+            in_ci: false,
+        };
+        let mut commands = vec![
+            command("npm run build", CommandKind::NpmScript),
+            command("npm run test", CommandKind::NpmScript),
+            command("make lint", CommandKind::MakeTarget),
+            command("make docs", CommandKind::MakeTarget),
+        ];
+        let pipeline = Pipeline {
+            path: ".github/workflows/ci.yml".into(),
+            system: CiSystem::GithubActions,
+            name: None,
+            triggers: vec![],
+            jobs: vec![CiJob {
+                name: "ci".into(),
+                commands: vec![
+                    "npm ci && npm test".into(),
+                    "npm  run build -- --prod".into(),
+                    "make lint-fix".into(),
+                ],
+            }],
+        };
+        mark_commands(&mut commands, &[pipeline]);
+        let marked: Vec<_> = commands.iter().map(|c| c.in_ci).collect();
+        assert_eq!(marked, [true, true, false, false]);
+        assert_eq!(
+            CiSystem::of(".circleci/config.yml"),
+            Some(CiSystem::CircleCi)
+        );
+        assert_eq!(
+            CiSystem::of(".github/workflows/release.yaml"),
+            Some(CiSystem::GithubActions)
+        );
+        assert_eq!(CiSystem::of(".travis.yml"), None);
+    }
+}
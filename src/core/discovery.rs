@@ -1,28 +1,127 @@
 use anyhow::Result;
+use ignore::overrides::OverrideBuilder;
 use ignore::WalkBuilder;
-use std::path::Path;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 use tracing::debug;
 
-/// Categorized inventory of files in a codebase
+use super::analyzer::{is_progress_file, LEGACY_PROGRESS_FILE, PROGRESS_FILE};
+use super::exit::UsageError;
+use super::gitattributes::{self, GitAttributes};
+use super::manifests::{self, ManifestKind};
+use super::plugins;
+use super::sniff;
+
+/// Project-specific ignore file, gitignore syntax, honoured alongside `.gitignore`
+pub const IGNORE_FILE: &str = ".cdaignore";
+
+/// Categorized inventory of files in a codebase. Every path in it is
+/// relative to `root` with `/` separators (see [`relative_path`]), except
+/// [`SourceFile::abs_path`], which is only used to read the file.
 #[derive(Debug, Default)]
 pub struct FileInventory {
-    #[allow(dead_code)]
     pub root: String,
     pub source_files: Vec<SourceFile>,
+    /// Source files left out by a language filter
+    pub filtered_files: usize,
     pub config_files: Vec<String>,
+    /// YAML files with top-level `apiVersion` and `kind` keys
+    pub kubernetes_files: Vec<String>,
+    /// `Chart.yaml`, values and templates of Helm charts
+    pub helm_files: Vec<String>,
+    /// OpenAPI and Swagger specs, YAML or JSON
+    pub api_spec_files: Vec<String>,
     pub doc_files: Vec<String>,
     pub test_files: Vec<String>,
+    /// Files `.gitattributes` marks `linguist-generated`, left out of analysis
+    pub generated_files: Vec<String>,
+    /// Files `.gitattributes` marks `linguist-vendored`, left out of analysis
+    pub vendored_files: Vec<String>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SourceFile {
+    /// Relative to the analysis root, `/`-separated: the name stored in
+    /// progress files, analysis.json and module page names
     pub path: String,
+    /// OS path for reading the file
+    pub abs_path: PathBuf,
     pub language: Language,
-    #[allow(dead_code)]
+    /// How `language` was found
+    pub detection: Detection,
     pub size: u64,
+    /// A test file promoted by `include_tests`
+    pub is_test: bool,
+    /// Modification time at discovery; `None` turns off
+    /// [`SourceFile::changed_since_discovery`]
+    pub modified: Option<SystemTime>,
+    /// Files analyzed with this one as a single module, set by
+    /// [`pairing::pair`](super::pairing::pair)
+    pub companions: Vec<SourceFile>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+impl SourceFile {
+    /// Whether the file's size or modification time differs from discovery,
+    /// or it is gone
+    pub fn changed_since_discovery(&self) -> bool {
+        if self.companions.iter().any(|c| c.changed_since_discovery()) {
+            return true;
+        }
+        let Some(modified) = self.modified else {
+            return false;
+        };
+        match self.abs_path.metadata() {
+            Ok(metadata) => {
+                metadata.len() != self.size || metadata.modified().ok() != Some(modified)
+            }
+            Err(_) => true,
+        }
+    }
+
+    /// Take the size and modification time again, as if just discovered
+    pub fn refresh(&mut self) {
+        if let Ok(metadata) = self.abs_path.metadata() {
+            self.size = metadata.len();
+            self.modified = metadata.modified().ok();
+        }
+        for companion in &mut self.companions {
+            companion.refresh();
+        }
+    }
+
+    /// Every file of a paired module, this one first; empty for a module
+    /// that is a single file
+    pub fn files(&self) -> Vec<String> {
+        if self.companions.is_empty() {
+            return Vec::new();
+        }
+        std::iter::once(self)
+            .chain(&self.companions)
+            .map(|f| f.path.clone())
+            .collect()
+    }
+}
+
+/// How the language of a source file was found
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Detection {
+    /// From the extension, or a plugin registered for it
+    #[default]
+    Extension,
+    /// From a `linguist-language` attribute in `.gitattributes`
+    Attributes,
+    /// From the interpreter the `#!` line names
+    Shebang,
+    /// From lines typical of the language; see [`sniff`]
+    Content,
+}
+
+/// Serialized as [`Language::name`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
 pub enum Language {
     Rust,
     TypeScript,
@@ -35,12 +134,14 @@ pub enum Language {
     C,
     Ruby,
     Shell,
+    /// Parsed by an external plugin registered for the file's extension
+    Plugin,
     #[default]
     Unknown,
 }
 
 impl Language {
-    fn from_extension(ext: &str) -> Self {
+    pub fn from_extension(ext: &str) -> Self {
         match ext.to_lowercase().as_str() {
             "rs" => Language::Rust,
             "ts" | "tsx" => Language::TypeScript,
@@ -56,45 +157,285 @@ impl Language {
             _ => Language::Unknown,
         }
     }
+
+    /// Languages that can be named on the command line
+    pub const KNOWN: &'static [Language] = &[
+        Language::Rust,
+        Language::TypeScript,
+        Language::JavaScript,
+        Language::Python,
+        Language::Go,
+        Language::Java,
+        Language::CSharp,
+        Language::Cpp,
+        Language::C,
+        Language::Ruby,
+        Language::Shell,
+    ];
+
+    /// Lowercase name used in filters and run metadata
+    pub fn name(self) -> &'static str {
+        match self {
+            Language::Rust => "rust",
+            Language::TypeScript => "typescript",
+            Language::JavaScript => "javascript",
+            Language::Python => "python",
+            Language::Go => "go",
+            Language::Java => "java",
+            Language::CSharp => "csharp",
+            Language::Cpp => "cpp",
+            Language::C => "c",
+            Language::Ruby => "ruby",
+            Language::Shell => "shell",
+            Language::Plugin => "plugin",
+            Language::Unknown => "unknown",
+        }
+    }
+
+    /// Parse a `--language` value, rejecting anything that isn't a known language
+    pub fn parse_filter(name: &str) -> Result<Self, String> {
+        match Language::from_name(name.trim()) {
+            Language::Unknown => {
+                let valid: Vec<_> = Language::KNOWN.iter().map(|l| l.name()).collect();
+                Err(format!(
+                    "unknown language `{}`; valid values: {} (or a file extension such as rs, ts, py)",
+                    name,
+                    valid.join(", ")
+                ))
+            }
+            language => Ok(language),
+        }
+    }
+
+    /// Resolve a user-supplied language name or file extension
+    pub fn from_name(name: &str) -> Self {
+        match name.to_lowercase().as_str() {
+            "rust" => Language::Rust,
+            "typescript" => Language::TypeScript,
+            "javascript" => Language::JavaScript,
+            "python" => Language::Python,
+            "golang" => Language::Go,
+            "java" => Language::Java,
+            "csharp" | "c#" => Language::CSharp,
+            "c++" => Language::Cpp,
+            "ruby" => Language::Ruby,
+            "shell" => Language::Shell,
+            "plugin" => Language::Plugin,
+            other => Language::from_extension(other),
+        }
+    }
 }
 
 impl FileInventory {
     pub fn total_files(&self) -> usize {
         self.source_files.len()
             + self.config_files.len()
+            + self.manifest_files()
             + self.doc_files.len()
             + self.test_files.len()
+            + self.generated_files.len()
+            + self.vendored_files.len()
     }
+
+    /// Kubernetes manifests, Helm chart files and API specs
+    pub fn manifest_files(&self) -> usize {
+        self.kubernetes_files.len() + self.helm_files.len() + self.api_spec_files.len()
+    }
+}
+
+/// Filters applied on top of .gitignore during discovery
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct DiscoveryOptions {
+    /// Extra gitignore-style patterns to skip
+    pub ignore_patterns: Vec<String>,
+    /// Files larger than this many bytes are skipped
+    pub max_file_size: Option<u64>,
+    /// Only keep source files in these languages; empty keeps all
+    pub languages: Vec<Language>,
+    /// Treat test files as source files (marked `is_test`) instead of
+    /// only listing them in `test_files`
+    pub include_tests: bool,
+    /// Classify files `.gitattributes` marks `linguist-generated` or
+    /// `linguist-vendored` like any other file instead of setting them aside
+    pub include_generated: bool,
+    /// cda's own output and cache directories; left out when they are
+    /// inside the analyzed path
+    pub exclude_dirs: Vec<PathBuf>,
+}
+
+impl DiscoveryOptions {
+    /// These options with an output directory left out: `output` as given
+    /// and `output_dir`, the directory a run actually writes to under it
+    pub fn excluding_output(mut self, output: &Path, output_dir: &Path) -> Self {
+        self.exclude_dirs
+            .extend([output.to_path_buf(), output_dir.to_path_buf()]);
+        self
+    }
+}
+
+/// `dirs` that are inside `root`, as `root`-joined paths the way the walker
+/// reports them
+fn excluded_dirs(root: &Path, dirs: &[PathBuf]) -> Vec<PathBuf> {
+    let Ok(canonical_root) = root.canonicalize() else {
+        return Vec::new();
+    };
+    dirs.iter()
+        .filter_map(|dir| dir.canonicalize().ok())
+        .filter_map(|dir| {
+            let relative = dir.strip_prefix(&canonical_root).ok()?;
+            if relative.as_os_str().is_empty() {
+                return None;
+            }
+            debug!(
+                "Excluding {} from discovery: it is cda output inside the analyzed path",
+                dir.display()
+            );
+            Some(root.join(relative))
+        })
+        .collect()
 }
 
 /// Discover all files in a codebase, respecting .gitignore
-pub async fn discover(path: &Path, module: Option<&str>) -> Result<FileInventory> {
-    let search_path = if let Some(m) = module {
-        path.join(m)
-    } else {
-        path.to_path_buf()
+pub async fn discover(
+    path: &Path,
+    module: Option<&str>,
+    options: &DiscoveryOptions,
+) -> Result<FileInventory> {
+    let search_path = match module.map(|m| resolve_module(path, m)).transpose()? {
+        Some(m) if !m.is_empty() => path.join(m),
+        _ => path.to_path_buf(),
     };
+    discover_in(path, &search_path, None, options)
+}
 
-    let mut inventory = FileInventory {
-        root: path.display().to_string(),
-        ..Default::default()
+/// Source files among `paths`, files and directories under `root` that
+/// changed, by the rules of [`discover`]. A path the walk of `root` doesn't
+/// reach, e.g. one in an ignored directory, is left out, and a directory
+/// stands for the files below it.
+pub fn discover_sources(
+    root: &Path,
+    paths: &[PathBuf],
+    options: &DiscoveryOptions,
+) -> Result<Vec<SourceFile>> {
+    let mut sources = Vec::new();
+    for path in paths {
+        if !path.starts_with(root) || !path.exists() || !reaches(root, path, options)? {
+            continue;
+        }
+        if path.is_dir() {
+            sources.extend(discover_in(root, path, None, options)?.source_files);
+        } else if let Some(dir) = path.parent() {
+            let inventory = discover_in(root, dir, Some(1), options)?;
+            sources.extend(
+                inventory
+                    .source_files
+                    .into_iter()
+                    .filter(|f| &f.abs_path == path),
+            );
+        }
+    }
+    sources.sort_by(|a, b| a.path.cmp(&b.path));
+    sources.dedup_by(|a, b| a.path == b.path);
+    Ok(sources)
+}
+
+/// Whether the walk of `root` gets to `path`: each directory on the way,
+/// and `path` itself, is listed by the walk of its parent
+fn reaches(root: &Path, path: &Path, options: &DiscoveryOptions) -> Result<bool> {
+    let Ok(relative) = path.strip_prefix(root) else {
+        return Ok(false);
     };
+    let mut dir = root.to_path_buf();
+    for component in relative.components() {
+        let next = dir.join(component);
+        let listed = walker(root, &dir, Some(1), options)?
+            .build()
+            .flatten()
+            .any(|e| e.path() == next);
+        if !listed {
+            return Ok(false);
+        }
+        dir = next;
+    }
+    Ok(true)
+}
+
+/// The walk of `search_path` in the analyzed `path`, with its ignore files,
+/// `options.ignore_patterns` (relative to `path`) and cda's output left out
+fn walker(
+    path: &Path,
+    search_path: &Path,
+    max_depth: Option<usize>,
+    options: &DiscoveryOptions,
+) -> Result<WalkBuilder> {
+    let mut overrides = OverrideBuilder::new(path);
+    for pattern in &options.ignore_patterns {
+        overrides.add(&format!("!{}", pattern))?;
+    }
+
+    let excluded = excluded_dirs(path, &options.exclude_dirs);
 
-    let walker = WalkBuilder::new(&search_path)
+    let mut walker = WalkBuilder::new(search_path);
+    walker
+        .filter_entry(move |e| !excluded.iter().any(|dir| e.path() == dir))
         .hidden(false)
         .git_ignore(true)
         .git_global(true)
         .git_exclude(true)
-        .build();
+        .add_custom_ignore_filename(IGNORE_FILE)
+        .overrides(overrides.build()?)
+        .max_filesize(options.max_file_size)
+        .max_depth(max_depth);
+    Ok(walker)
+}
 
-    for entry in walker.flatten() {
-        let path = entry.path();
+/// [`discover`] of `search_path`, down to `max_depth` below it
+fn discover_in(
+    path: &Path,
+    search_path: &Path,
+    max_depth: Option<usize>,
+    options: &DiscoveryOptions,
+) -> Result<FileInventory> {
+    let root = path.display().to_string();
 
-        if !path.is_file() {
-            continue;
-        }
+    let mut inventory = FileInventory {
+        root: root.clone(),
+        ..Default::default()
+    };
+
+    let walker = walker(path, search_path, max_depth, options)?.build();
+
+    let entries: Vec<_> = walker.flatten().filter(|e| e.path().is_file()).collect();
+
+    // Root .gitattributes and those between it and a --module, then nested ones
+    let mut attribute_files: Vec<PathBuf> = search_path
+        .ancestors()
+        .skip(1)
+        .take_while(|dir| dir.starts_with(path))
+        .map(|dir| dir.join(gitattributes::FILE_NAME))
+        .filter(|file| file.is_file())
+        .collect();
+    attribute_files.extend(
+        entries
+            .iter()
+            .filter(|e| e.file_name() == gitattributes::FILE_NAME)
+            .map(|e| e.path().to_path_buf()),
+    );
+    let attributes = GitAttributes::load(&attribute_files);
+
+    // Directories of Helm charts, whose files are set apart from config
+    let chart_dirs: Vec<String> = entries
+        .iter()
+        .filter(|e| e.file_name() == "Chart.yaml")
+        .filter_map(|e| e.path().parent())
+        .map(|dir| relative_path(&dir.display().to_string(), &root))
+        .collect();
+
+    for entry in &entries {
+        let path = entry.path();
 
-        let path_str = path.display().to_string();
+        let path_str = relative_path(&path.display().to_string(), &root);
         let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
         let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
 
@@ -102,24 +443,81 @@ pub async fn discover(path: &Path, module: Option<&str>) -> Result<FileInventory
         if is_binary_extension(extension) {
             continue;
         }
+        // Progress files of runs that wrote their output here
+        let unsuffixed = path
+            .to_str()
+            .and_then(|p| p.strip_suffix(".old"))
+            .map_or(path, Path::new);
+        if unsuffixed.ends_with(LEGACY_PROGRESS_FILE)
+            || (unsuffixed.ends_with(PROGRESS_FILE) && is_progress_file(path))
+        {
+            continue;
+        }
+
+        let linguist = attributes.linguist(path);
+        if linguist.generated && !options.include_generated {
+            debug!("Generated file (.gitattributes): {}", path_str);
+            inventory.generated_files.push(path_str);
+            continue;
+        }
+        if linguist.vendored && !options.include_generated {
+            debug!("Vendored file (.gitattributes): {}", path_str);
+            inventory.vendored_files.push(path_str);
+            continue;
+        }
+        let language_override = linguist
+            .language
+            .as_deref()
+            .map(Language::from_name)
+            .filter(|l| *l != Language::Unknown);
+
+        let is_source = is_source_file(extension)
+            || plugins::is_registered(extension)
+            || language_override.is_some();
 
         // Categorize the file
-        if is_config_file(file_name, extension) {
+        if linguist.documentation {
+            debug!("Doc file (.gitattributes): {}", path_str);
+            inventory.doc_files.push(path_str);
+        } else if let Some(kind) = (!is_test_file(&path_str, file_name))
+            .then(|| manifests::classify(&path_str, file_name, extension, path, &chart_dirs))
+            .flatten()
+        {
+            debug!("{:?} file: {}", kind, path_str);
+            match kind {
+                ManifestKind::Kubernetes => inventory.kubernetes_files.push(path_str),
+                ManifestKind::Helm => inventory.helm_files.push(path_str),
+                ManifestKind::ApiSpec => inventory.api_spec_files.push(path_str),
+            }
+        } else if is_config_file(file_name, extension) {
             debug!("Config file: {}", path_str);
             inventory.config_files.push(path_str);
         } else if is_doc_file(file_name, extension) {
             debug!("Doc file: {}", path_str);
             inventory.doc_files.push(path_str);
-        } else if is_test_file(&path_str, file_name) {
+        } else if is_test_file(&path_str, file_name) && !(options.include_tests && is_source) {
             debug!("Test file: {}", path_str);
             inventory.test_files.push(path_str);
-        } else if is_source_file(extension) {
+        } else if let Some((language, detection)) =
+            source_language(path, extension, language_override, is_source)
+        {
+            if !options.languages.is_empty() && !options.languages.contains(&language) {
+                debug!("Filtered by language: {}", path_str);
+                inventory.filtered_files += 1;
+                continue;
+            }
+
             let metadata = path.metadata()?;
             debug!("Source file: {} ({} bytes)", path_str, metadata.len());
             inventory.source_files.push(SourceFile {
+                is_test: is_test_file(&path_str, file_name),
                 path: path_str,
-                language: Language::from_extension(extension),
+                abs_path: path.to_path_buf(),
+                language,
+                detection,
                 size: metadata.len(),
+                modified: metadata.modified().ok(),
+                companions: Vec::new(),
             });
         }
     }
@@ -127,6 +525,159 @@ pub async fn discover(path: &Path, module: Option<&str>) -> Result<FileInventory
     Ok(inventory)
 }
 
+/// Inventory of the single source file `file`, without walking anything:
+/// the root is the file's directory, so its module path is its file name.
+/// Ignore files and filters don't apply, as the file was named explicitly.
+pub fn discover_file(file: &Path) -> Result<FileInventory> {
+    let root = file
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let file_name = file.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    let extension = file.extension().and_then(|e| e.to_str()).unwrap_or("");
+
+    let attribute_files: Vec<PathBuf> = Some(root.join(gitattributes::FILE_NAME))
+        .filter(|f| f.is_file())
+        .into_iter()
+        .collect();
+    let attributes = GitAttributes::load(&attribute_files);
+    let language_override = attributes
+        .linguist(file)
+        .language
+        .as_deref()
+        .map(Language::from_name)
+        .filter(|l| *l != Language::Unknown);
+    let is_source = is_source_file(extension) || plugins::is_registered(extension);
+    let (language, detection) = source_language(file, extension, language_override, is_source)
+        .filter(|_| !is_binary_extension(extension))
+        .unwrap_or_default();
+    if language == Language::Unknown {
+        anyhow::bail!(
+            "{} is not a source file cda can parse; pass its directory to analyze the project",
+            file.display()
+        );
+    }
+
+    let metadata = file.metadata()?;
+    debug!("Source file: {} ({} bytes)", file_name, metadata.len());
+    Ok(FileInventory {
+        root: root.display().to_string(),
+        source_files: vec![SourceFile {
+            is_test: is_test_file(file_name, file_name),
+            path: file_name.to_string(),
+            abs_path: file.to_path_buf(),
+            language,
+            detection,
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+            companions: Vec::new(),
+        }],
+        ..Default::default()
+    })
+}
+
+/// The language of a file that is neither config, docs nor a test and how it
+/// was found: from `.gitattributes`, the extension or a plugin, and for an
+/// extension no table knows, from the content. `None` when the file is not
+/// source; a source file in a language without a parser is
+/// [`Language::Unknown`].
+fn source_language(
+    path: &Path,
+    extension: &str,
+    language_override: Option<Language>,
+    is_source: bool,
+) -> Option<(Language, Detection)> {
+    if let Some(language) = language_override {
+        return Some((language, Detection::Attributes));
+    }
+    match Language::from_extension(extension) {
+        Language::Unknown if plugins::is_registered(extension) => {
+            Some((Language::Plugin, Detection::Extension))
+        }
+        // A language cda has no parser for, such as PHP
+        Language::Unknown if is_source => Some((Language::Unknown, Detection::Extension)),
+        Language::Unknown if is_data_extension(extension) => None,
+        Language::Unknown => {
+            let size = path.metadata().map_or(u64::MAX, |m| m.len());
+            let found = sniff::language(path, size)?;
+            debug!(
+                "{} looks like {} ({:?})",
+                path.display(),
+                found.0.name(),
+                found.1
+            );
+            Some(found)
+        }
+        language => Some((language, Detection::Extension)),
+    }
+}
+
+/// Markup and data formats, never sniffed for code
+fn is_data_extension(ext: &str) -> bool {
+    matches!(
+        ext.to_lowercase().as_str(),
+        "json" | "lock" | "css" | "scss" | "less" | "html" | "htm" | "xml" | "csv" | "tsv" | "map"
+    )
+}
+
+/// `path` relative to `root`, with `/` separators on every platform. Paths
+/// are stored and displayed in this form so output made on Windows matches
+/// output made elsewhere; works on plain strings so that is testable anywhere.
+pub fn relative_path(path: &str, root: &str) -> String {
+    let path = path.replace('\\', "/");
+    let root = root.replace('\\', "/");
+    let root = root.trim_end_matches('/');
+
+    match path.strip_prefix(root) {
+        Some(rest) if !root.is_empty() && (rest.is_empty() || rest.starts_with('/')) => {
+            rest.trim_start_matches('/').to_string()
+        }
+        _ => path.trim_start_matches("./").to_string(),
+    }
+}
+
+/// A `--module` argument as a `/`-separated path below the root, so
+/// `src\\core`, `./src/core/` and `src/core` select the same directory
+pub fn normalize_module(module: &str) -> String {
+    module
+        .replace('\\', "/")
+        .trim_start_matches("./")
+        .trim_matches('/')
+        .to_string()
+}
+
+/// A `--module` argument as a [`normalize_module`] path below `root`, with
+/// `..`, symlinks and absolute spellings resolved, so its files are stored
+/// under the same paths a run over the whole of `root` gives them
+pub fn resolve_module(root: &Path, module: &str) -> Result<String> {
+    let normalized = normalize_module(module);
+    if normalized.is_empty() {
+        return Ok(normalized);
+    }
+    let dir = match Path::new(module) {
+        absolute if absolute.is_absolute() => absolute.to_path_buf(),
+        _ => root.join(&normalized),
+    };
+    let dir = dir.canonicalize().map_err(|_| {
+        UsageError::new(format!(
+            "--module {} does not exist in {}",
+            module,
+            root.display()
+        ))
+    })?;
+    if !dir.is_dir() {
+        return Err(UsageError::new(format!(
+            "--module {} is not a directory; pass a file as the path to analyze it alone",
+            module
+        ))
+        .into());
+    }
+    let relative = dir.strip_prefix(root.canonicalize()?).map_err(|_| {
+        UsageError::new(format!("--module {} is outside {}", module, root.display()))
+    })?;
+    Ok(normalize_module(&relative.to_string_lossy()))
+}
+
 fn is_binary_extension(ext: &str) -> bool {
     matches!(
         ext.to_lowercase().as_str(),
@@ -198,14 +749,13 @@ fn is_doc_file(name: &str, ext: &str) -> bool {
         )
 }
 
-fn is_test_file(path: &str, name: &str) -> bool {
+/// Whether a path looks like a test file (tests/ dirs, `_test.`/`.spec.` names)
+pub fn is_test_file(path: &str, name: &str) -> bool {
     let path_lower = path.to_lowercase();
     let name_lower = name.to_lowercase();
+    let mut dirs = path_lower.split(['/', '\\']).rev().skip(1);
 
-    path_lower.contains("/test/")
-        || path_lower.contains("/tests/")
-        || path_lower.contains("/__tests__/")
-        || path_lower.contains("/spec/")
+    dirs.any(|dir| matches!(dir, "test" | "tests" | "__tests__" | "spec"))
         || name_lower.contains("_test.")
         || name_lower.contains(".test.")
         || name_lower.contains("_spec.")
@@ -257,6 +807,8 @@ mod tests {
         assert_eq!(Language::from_extension("ts"), Language::TypeScript);
         assert_eq!(Language::from_extension("py"), Language::Python);
         assert_eq!(Language::from_extension("unknown"), Language::Unknown);
+        assert_eq!(Language::from_name("TypeScript"), Language::TypeScript);
+        assert_eq!(Language::from_name("rs"), Language::Rust);
     }
 
     #[test]
@@ -265,5 +817,280 @@ mod tests {
         assert!(is_test_file("/src/foo_test.rs", "foo_test.rs"));
         assert!(is_test_file("/src/foo.test.ts", "foo.test.ts"));
         assert!(!is_test_file("/src/foo.rs", "foo.rs"));
+        assert!(is_test_file("tests/it.rs", "it.rs"));
+        assert!(is_test_file("C:\\repo\\tests\\it.rs", "it.rs"));
+        assert!(!is_test_file("src/tests.rs", "tests.rs"));
+    }
+
+    #[test]
+    fn test_relative_path() {
+        assert_eq!(relative_path("/repo/src/a.rs", "/repo"), "src/a.rs");
+        assert_eq!(relative_path("/repo/src/a.rs", "/repo/"), "src/a.rs");
+        assert_eq!(
+            relative_path("/repository/a.rs", "/repo"),
+            "/repository/a.rs"
+        );
+        assert_eq!(relative_path("./src/a.rs", ""), "src/a.rs");
+
+        // Windows paths, including verbatim ones from canonicalize()
+        assert_eq!(
+            relative_path("C:\\repo\\src\\core\\a.rs", "C:\\repo"),
+            "src/core/a.rs"
+        );
+        assert_eq!(
+            relative_path("\\\\?\\C:\\My Repo\\src\\a.rs", "\\\\?\\C:\\My Repo\\"),
+            "src/a.rs"
+        );
+        assert_eq!(
+            relative_path("D:\\other\\a.rs", "C:\\repo"),
+            "D:/other/a.rs"
+        );
+    }
+
+    #[test]
+    fn test_normalize_module() {
+        for module in ["src\\core", "./src/core/", "src/core"] {
+            assert_eq!(normalize_module(module), "src/core");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_paths_with_spaces() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("my project");
+        std::fs::create_dir_all(root.join("my module/tests")).unwrap();
+        std::fs::write(root.join("my module/lib file.rs"), "pub fn f() {}").unwrap();
+        std::fs::write(root.join("my module/tests/it.rs"), "#[test] fn t() {}").unwrap();
+        std::fs::write(root.join("READ ME.md"), "# Docs").unwrap();
+
+        let inventory = discover(&root, Some("./my module/"), &DiscoveryOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(inventory.source_files.len(), 1);
+        let file = &inventory.source_files[0];
+        assert_eq!(file.path, "my module/lib file.rs");
+        assert!(file.abs_path.is_file());
+        assert_eq!(inventory.test_files, vec!["my module/tests/it.rs"]);
+
+        let inventory = discover(&root, None, &DiscoveryOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(inventory.doc_files, vec!["READ ME.md"]);
+    }
+
+    #[tokio::test]
+    async fn test_cdaignore_is_honoured() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("generated")).unwrap();
+        std::fs::write(dir.path().join("main.rs"), "fn main() {}").unwrap();
+        std::fs::write(dir.path().join("generated/api.rs"), "fn api() {}").unwrap();
+        std::fs::write(dir.path().join(IGNORE_FILE), "generated/\n").unwrap();
+
+        let inventory = discover(dir.path(), None, &DiscoveryOptions::default())
+            .await
+            .unwrap();
+        let paths: Vec<_> = inventory.source_files.iter().map(|f| &f.path).collect();
+        assert_eq!(paths.len(), 1, "{:?}", paths);
+        assert!(paths[0].ends_with("main.rs"));
+    }
+
+    #[tokio::test]
+    async fn test_only_cda_progress_files_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("docs/.cda-state")).unwrap();
+        std::fs::create_dir_all(root.join("tools/.cda-state")).unwrap();
+        std::fs::write(
+            root.join("docs/.cda-state/progress"),
+            "# cda-progress ollama/llama3\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("tools/.cda-state/progress"),
+            "#!/bin/sh\necho progress\n",
+        )
+        .unwrap();
+
+        let inventory = discover(root, None, &DiscoveryOptions::default())
+            .await
+            .unwrap();
+        let paths: Vec<_> = inventory.source_files.iter().map(|f| &f.path).collect();
+        assert_eq!(paths, ["tools/.cda-state/progress"]);
+    }
+
+    #[test]
+    fn test_parse_language_filter() {
+        assert_eq!(Language::parse_filter("Rust"), Ok(Language::Rust));
+        assert_eq!(Language::parse_filter("tsx"), Ok(Language::TypeScript));
+        assert_eq!(Language::parse_filter("go"), Ok(Language::Go));
+        let err = Language::parse_filter("cobol").unwrap_err();
+        assert!(err.contains("valid values: rust, typescript"));
+    }
+
+    #[tokio::test]
+    async fn test_language_filter() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("main.rs"), "fn main() {}").unwrap();
+        std::fs::write(dir.path().join("app.ts"), "export const a = 1;").unwrap();
+        std::fs::write(dir.path().join("tool.py"), "print(1)").unwrap();
+
+        let options = DiscoveryOptions {
+            languages: vec![Language::Rust, Language::TypeScript],
+            ..Default::default()
+        };
+        let inventory = discover(dir.path(), None, &options).await.unwrap();
+        let mut languages: Vec<_> = inventory.source_files.iter().map(|f| f.language).collect();
+        languages.sort_by_key(|l| l.name());
+        assert_eq!(languages, vec![Language::Rust, Language::TypeScript]);
+        assert_eq!(inventory.filtered_files, 1);
+    }
+
+    #[tokio::test]
+    async fn test_include_tests() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("tests")).unwrap();
+        std::fs::write(dir.path().join("lib.rs"), "pub fn f() {}").unwrap();
+        std::fs::write(dir.path().join("tests/it.rs"), "#[test] fn t() {}").unwrap();
+        std::fs::write(dir.path().join("tests/fixture.json"), "{}").unwrap();
+
+        let inventory = discover(dir.path(), None, &DiscoveryOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(inventory.source_files.len(), 1);
+        assert_eq!(inventory.test_files.len(), 2);
+
+        let options = DiscoveryOptions {
+            include_tests: true,
+            ..Default::default()
+        };
+        let inventory = discover(dir.path(), None, &options).await.unwrap();
+        let tests: Vec<_> = inventory
+            .source_files
+            .iter()
+            .filter(|f| f.is_test)
+            .collect();
+        assert_eq!(inventory.source_files.len(), 2);
+        assert_eq!(tests.len(), 1);
+        assert!(tests[0].path.ends_with("it.rs"));
+        assert_eq!(inventory.test_files.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_gitattributes_linguist_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("third_party/zlib")).unwrap();
+        std::fs::create_dir_all(dir.path().join("examples")).unwrap();
+        std::fs::write(dir.path().join("main.rs"), "fn main() {}").unwrap();
+        std::fs::write(dir.path().join("schema.gen.rs"), "pub struct S;").unwrap();
+        std::fs::write(dir.path().join("third_party/zlib/lib.rs"), "pub fn z() {}").unwrap();
+        std::fs::write(dir.path().join("examples/demo.rs"), "fn main() {}").unwrap();
+        std::fs::write(dir.path().join("build.inc"), "pub fn b() {}").unwrap();
+        std::fs::write(
+            dir.path().join(gitattributes::FILE_NAME),
+            "*.gen.rs linguist-generated\n\
+             third_party/** linguist-vendored\n\
+             examples/** linguist-documentation\n\
+             *.inc linguist-language=Rust\n",
+        )
+        .unwrap();
+
+        let inventory = discover(dir.path(), None, &DiscoveryOptions::default())
+            .await
+            .unwrap();
+        let mut sources: Vec<_> = inventory
+            .source_files
+            .iter()
+            .map(|f| (f.path.as_str(), f.language))
+            .collect();
+        sources.sort_by_key(|(path, _)| *path);
+        assert_eq!(
+            sources,
+            vec![("build.inc", Language::Rust), ("main.rs", Language::Rust)]
+        );
+        assert_eq!(inventory.generated_files, vec!["schema.gen.rs"]);
+        assert_eq!(inventory.vendored_files, vec!["third_party/zlib/lib.rs"]);
+        assert!(inventory
+            .doc_files
+            .contains(&"examples/demo.rs".to_string()));
+
+        let options = DiscoveryOptions {
+            include_generated: true,
+            ..Default::default()
+        };
+        let inventory = discover(dir.path(), None, &options).await.unwrap();
+        assert_eq!(inventory.source_files.len(), 4);
+        assert!(inventory.generated_files.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_extensions_are_sniffed() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = [
+            ("deploy", "#!/usr/bin/env bash\necho deploying\n"),
+            (
+                "settings.inc",
+                "from os import environ\n\nclass Settings:\n    def get(self, key):\n        try:\n            return environ[key]\n        except KeyError:\n            return None\n",
+            ),
+            (
+                "widget.tpl",
+                "const $ = require('jquery');\n\nfunction mount(el) {\n  console.log(el);\n}\n\nmodule.exports = { mount };\n",
+            ),
+            ("page.tpl", "<h1>{{ title }}</h1>\n"),
+            ("data.json", "{\"def f():\": 1}"),
+            ("index.php", "<?php\nfunction render() {\n  console.log(1);\n}\n"),
+        ];
+        for (name, content) in files {
+            std::fs::write(dir.path().join(name), content).unwrap();
+        }
+
+        let inventory = discover(dir.path(), None, &DiscoveryOptions::default())
+            .await
+            .unwrap();
+        let mut found: Vec<_> = inventory
+            .source_files
+            .iter()
+            .map(|f| (f.path.as_str(), f.language, f.detection))
+            .collect();
+        found.sort_by_key(|f| f.0);
+        assert_eq!(
+            found,
+            [
+                ("deploy", Language::Shell, Detection::Shebang),
+                ("index.php", Language::Unknown, Detection::Extension),
+                ("settings.inc", Language::Python, Detection::Content),
+                ("widget.tpl", Language::JavaScript, Detection::Content),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_discover_sources_applies_the_ignore_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(root.join("generated/deep")).unwrap();
+        std::fs::write(root.join(IGNORE_FILE), "generated/\n*.tmp.rs\n").unwrap();
+        std::fs::write(root.join("src/a.rs"), "pub fn a() {}").unwrap();
+        std::fs::write(root.join("src/b.tmp.rs"), "pub fn b() {}").unwrap();
+        std::fs::write(root.join("src/README.md"), "# src").unwrap();
+        std::fs::write(root.join("generated/deep/c.rs"), "pub fn c() {}").unwrap();
+        let options = DiscoveryOptions::default();
+
+        let paths = [
+            "src/a.rs",
+            "src/b.tmp.rs",
+            "src/README.md",
+            "src/gone.rs",
+            "generated/deep/c.rs",
+            "generated",
+        ]
+        .map(|p| root.join(p));
+        let sources = discover_sources(root, &paths, &options).unwrap();
+        let found: Vec<&str> = sources.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(found, vec!["src/a.rs"]);
+
+        // A directory stands for what is below it
+        let sources = discover_sources(root, &[root.join("src")], &options).unwrap();
+        assert_eq!(sources.len(), 1);
     }
 }
@@ -0,0 +1,269 @@
+//! Style checks on the doc comments exported items do have
+//!
+//! Each check produces gaps of its own kind and is off unless turned on
+//! under `[doc_checks]`, so enabling one doesn't change the counts of the
+//! others:
+//!
+//! - `restated_docs`: the description only repeats the item's name, as in
+//!   "Gets the user" on `get_user`
+//! - `undocumented_params`: a function with at least `min_params`
+//!   parameters whose description doesn't name all of them
+//! - `short_docs`: the description is shorter than `min_length` characters
+
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+use super::analyzer::{Export, ExportKind, Gap, GapKind};
+
+/// Default of `doc_checks.min_params`
+pub const DEFAULT_MIN_PARAMS: usize = 4;
+/// Default of `doc_checks.min_length`
+pub const DEFAULT_MIN_LENGTH: usize = 20;
+
+/// Words that say nothing about an item, ignored when comparing its
+/// description with its name
+const FILLER: &[&str] = &[
+    "a", "an", "the", "this", "that", "of", "to", "for", "is", "it", "return", "returns", "get",
+    "gets", "set", "sets", "function", "method", "class", "struct", "type", "value", "given",
+];
+
+/// Which checks run, with their thresholds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DocChecks {
+    pub restated_docs: bool,
+    pub undocumented_params: bool,
+    pub short_docs: bool,
+    /// Parameters, receivers left out, from which `undocumented_params`
+    /// applies
+    pub min_params: usize,
+    /// Characters a description needs to pass `short_docs`
+    pub min_length: usize,
+}
+
+impl Default for DocChecks {
+    fn default() -> Self {
+        Self {
+            restated_docs: false,
+            undocumented_params: false,
+            short_docs: false,
+            min_params: DEFAULT_MIN_PARAMS,
+            min_length: DEFAULT_MIN_LENGTH,
+        }
+    }
+}
+
+/// Checks set by the `doc_checks.*` keys
+static DOC_CHECKS: OnceLock<DocChecks> = OnceLock::new();
+
+/// Run `checks` on the docs of exported items for the rest of the process.
+/// Only the first call has an effect.
+pub fn set_doc_checks(checks: DocChecks) {
+    let _ = DOC_CHECKS.set(checks);
+}
+
+/// The configured checks; none run by default
+pub fn doc_checks() -> DocChecks {
+    DOC_CHECKS.get().copied().unwrap_or_default()
+}
+
+impl DocChecks {
+    /// Gaps in the documentation of `export`, found at `location`. Items
+    /// without documentation are left to `missing_docs`.
+    pub fn check(&self, export: &Export, location: &str) -> Vec<Gap> {
+        let description = export.description.trim();
+        if description.is_empty() {
+            return vec![];
+        }
+        let gap = |kind: GapKind, description: String| Gap {
+            kind,
+            severity: kind.severity(),
+            description,
+            location: Some(location.to_string()),
+            owner: None,
+        };
+
+        let mut gaps = Vec::new();
+        if self.restated_docs && restates_name(description, &export.name) {
+            gaps.push(gap(
+                GapKind::RestatedDocs,
+                format!(
+                    "The documentation of {} `{}` only restates its name",
+                    export.kind, export.name
+                ),
+            ));
+        }
+        if self.undocumented_params && export.kind == ExportKind::Function {
+            let params = param_names(export);
+            if params.len() >= self.min_params {
+                let missing: Vec<String> = params
+                    .iter()
+                    .filter(|p| !mentions(description, p))
+                    .map(|p| format!("`{}`", p))
+                    .collect();
+                if !missing.is_empty() {
+                    gaps.push(gap(
+                        GapKind::UndocumentedParams,
+                        format!(
+                            "The documentation of {} `{}` doesn't describe {} of its {} \
+                             parameters: {}",
+                            export.kind,
+                            export.name,
+                            missing.len(),
+                            params.len(),
+                            missing.join(", ")
+                        ),
+                    ));
+                }
+            }
+        }
+        let length = description.chars().count();
+        if self.short_docs && length < self.min_length {
+            gaps.push(gap(
+                GapKind::ShortDocs,
+                format!(
+                    "The documentation of {} `{}` is {} characters, under {}",
+                    export.kind, export.name, length, self.min_length
+                ),
+            ));
+        }
+        gaps
+    }
+}
+
+/// Whether every word of `description` is a word of `name` or filler
+fn restates_name(description: &str, name: &str) -> bool {
+    let name: HashSet<String> = words(name).iter().map(|w| stem(w)).collect();
+    words(description)
+        .iter()
+        .all(|w| FILLER.contains(&w.as_str()) || name.contains(&stem(w)))
+}
+
+/// Lowercase words of an identifier or a sentence, split at punctuation and
+/// camelCase humps
+fn words(text: &str) -> Vec<String> {
+    let mut split = String::new();
+    let mut previous_lower = false;
+    for c in text.chars() {
+        if !c.is_alphanumeric() || (c.is_uppercase() && previous_lower) {
+            split.push(' ');
+        }
+        if c.is_alphanumeric() {
+            split.push(c);
+        }
+        previous_lower = c.is_lowercase() || c.is_ascii_digit();
+    }
+    split.split_whitespace().map(str::to_lowercase).collect()
+}
+
+/// `word` without a plural or third-person `s`
+fn stem(word: &str) -> String {
+    match word.strip_suffix('s') {
+        Some(stem) if stem.len() > 2 && !stem.ends_with('s') => stem.to_string(),
+        _ => word.to_string(),
+    }
+}
+
+/// Names of the parameters a description should mention: receivers,
+/// `_`-prefixed ones and destructuring patterns are left out
+fn param_names(export: &Export) -> Vec<&str> {
+    let Some(signature) = &export.function_signature else {
+        return vec![];
+    };
+    signature
+        .params
+        .iter()
+        .filter_map(|p| {
+            let name = p.name.trim_start_matches("mut ").trim();
+            let is_identifier = !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '_' || c == '$');
+            (is_identifier && name != "self" && !name.starts_with('_')).then_some(name)
+        })
+        .collect()
+}
+
+/// Whether `description` names `param` as a word of its own
+fn mentions(description: &str, param: &str) -> bool {
+    description
+        .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+        .any(|word| word == param)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::analyzer::{FunctionSignature, Param};
+
+    fn function(name: &str, description: &str, params: &[&str]) -> Export {
+        let signature = FunctionSignature {
+            params: params
+                .iter()
+                .map(|p| Param {
+                    name: p.to_string(),
+                    ty: Some("u32".into()),
+                })
+                .collect(),
+            ..Default::default()
+        };
+        Export {
+            name: name.into(),
+            kind: ExportKind::Function,
+            signature: None,
+            description: description.into(),
+            line_number: 3,
+            end_line: None,
+            snippet: None,
+            visibility: Default::default(),
+            file: None,
+            function_signature: Some(signature),
+            cfg: None,
+            examples: Vec::new(),
+        }
+    }
+
+    fn kinds(checks: &DocChecks, export: &Export) -> Vec<GapKind> {
+        checks
+            .check(export, "src/a.rs:3")
+            .iter()
+            .map(|g| g.kind)
+            .collect()
+    }
+
+    #[test]
+    fn test_doc_checks() {
+        let all = DocChecks {
+            restated_docs: true,
+            undocumented_params: true,
+            short_docs: true,
+            min_params: 3,
+            min_length: 20,
+        };
+
+        assert_eq!(
+            kinds(&all, &function("get_user", "Gets the user.", &[])),
+            [GapKind::RestatedDocs, GapKind::ShortDocs]
+        );
+        assert!(restates_name("Returns the parsed config", "parsedConfig"));
+        assert!(!restates_name("Reads the settings file", "load_config"));
+
+        let draw = function(
+            "draw",
+            "Draws `shape` at x on the canvas, in the given color",
+            &["&self", "shape", "mut x", "y", "_unused", "color"],
+        );
+        let gaps = all.check(&draw, "src/a.rs:3");
+        assert_eq!(gaps.len(), 1);
+        assert_eq!(gaps[0].kind, GapKind::UndocumentedParams);
+        assert!(gaps[0].description.ends_with("1 of its 4 parameters: `y`"));
+        assert_eq!(gaps[0].location.as_deref(), Some("src/a.rs:3"));
+
+        // Nothing runs by default, and undocumented items are missing_docs
+        assert!(kinds(
+            &DocChecks::default(),
+            &function("get_user", "Gets the user.", &[])
+        )
+        .is_empty());
+        assert!(kinds(&all, &function("f", "", &["a", "b", "c"])).is_empty());
+    }
+}
@@ -0,0 +1,402 @@
+//! Usage examples mined from the codebase
+//!
+//! A real call is often the best documentation of a function. [`collect`]
+//! follows every module's imports the way the cross-reference resolves them,
+//! finds the lines of the importing file that use each imported export and
+//! attaches the best few, with the lines around them, to the export. Test
+//! files and short calls come first, and every file gets one example in before
+//! any gets a second.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::debug;
+
+use super::analyzer::{self, EdgeKind, ModuleAnalysis};
+use super::discovery::{FileInventory, SourceFile};
+use super::source::{self, SourceText};
+
+/// Lines shown before the use, unless a blank line comes first
+const CONTEXT_BEFORE: usize = 1;
+/// Lines followed past the use to the end of a call split over several
+const MAX_CALL_LINES: usize = 20;
+/// Longest example; the rest of a long call is cut like an export's snippet
+const MAX_EXAMPLE_LINES: usize = 8;
+
+/// A use of an export in another file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Example {
+    /// File the use is in
+    pub path: String,
+    /// Line of the use
+    pub line: usize,
+    /// The use with the lines around it, dedented
+    pub snippet: String,
+}
+
+/// A use found, before the best ones of each export are picked
+struct Candidate {
+    module: usize,
+    name: String,
+    example: Example,
+    /// Better uses of the export in the same file
+    repeat: usize,
+    in_test: bool,
+    is_call: bool,
+    length: usize,
+}
+
+/// Set the examples of every export to up to `limit` of its uses in the
+/// files of `inventory`; `0` leaves every export without
+pub fn collect(modules: &mut [ModuleAnalysis], inventory: &FileInventory, limit: usize) {
+    for module in modules.iter_mut() {
+        for export in &mut module.exports {
+            export.examples.clear();
+        }
+    }
+    if limit == 0 {
+        return;
+    }
+
+    let mut candidates = find(modules, inventory, limit);
+    candidates.sort_by(|a, b| {
+        (
+            a.repeat,
+            !a.in_test,
+            !a.is_call,
+            a.length,
+            &a.example.path,
+            a.example.line,
+        )
+            .cmp(&(
+                b.repeat,
+                !b.in_test,
+                !b.is_call,
+                b.length,
+                &b.example.path,
+                b.example.line,
+            ))
+    });
+    let mut total = 0;
+    for candidate in candidates {
+        let Some(export) = modules[candidate.module]
+            .exports
+            .iter_mut()
+            .find(|e| e.visibility.is_public() && e.name == candidate.name)
+        else {
+            continue;
+        };
+        if export.examples.len() < limit {
+            export.examples.push(candidate.example);
+            total += 1;
+        }
+    }
+    if total > 0 {
+        debug!("Found {} usage examples of exports", total);
+    }
+}
+
+/// Up to `limit` uses of each export in each file importing it
+fn find(modules: &[ModuleAnalysis], inventory: &FileInventory, limit: usize) -> Vec<Candidate> {
+    let files: HashMap<&str, &SourceFile> = inventory
+        .source_files
+        .iter()
+        .map(|f| (f.path.as_str(), f))
+        .collect();
+    let exporters = analyzer::exporters(modules);
+    let paths: HashMap<&str, usize> = modules
+        .iter()
+        .enumerate()
+        .map(|(index, module)| (module.path.as_str(), index))
+        .collect();
+
+    let mut candidates = Vec::new();
+    for (index, module) in modules.iter().enumerate() {
+        // A re-export passes the name on rather than using it
+        let imported: Vec<(usize, &str)> =
+            analyzer::resolve_imports(index, module, &exporters, &paths)
+                .edges
+                .into_iter()
+                .filter_map(|(target, name, kind)| match (kind, name) {
+                    (EdgeKind::Import | EdgeKind::TypeOnly, Some(name)) => Some((target, name)),
+                    _ => None,
+                })
+                .collect();
+        if imported.is_empty() {
+            continue;
+        }
+        let Some(file) = files.get(module.path.as_str()) else {
+            continue;
+        };
+        for part in std::iter::once(*file).chain(&file.companions) {
+            let Some(content) = read(part) else {
+                continue;
+            };
+            let lines: Vec<&str> = content.lines().collect();
+            for &(target, name) in &imported {
+                let mut found = uses(&lines, name);
+                found.sort_by_key(|&(line, is_call)| (!is_call, lines[line].trim().len(), line));
+                for (repeat, (line, is_call)) in found.into_iter().take(limit).enumerate() {
+                    candidates.push(Candidate {
+                        module: target,
+                        name: name.to_string(),
+                        example: Example {
+                            path: part.path.clone(),
+                            line: line + 1,
+                            snippet: snippet(&lines, line),
+                        },
+                        repeat,
+                        in_test: module.is_test_module || part.is_test,
+                        is_call,
+                        length: lines[line].trim().len(),
+                    });
+                }
+            }
+        }
+    }
+    candidates
+}
+
+fn read(file: &SourceFile) -> Option<String> {
+    match source::read_source(&file.abs_path) {
+        Ok(SourceText::Text { content, .. }) => Some(content),
+        Ok(SourceText::Binary) => None,
+        Err(e) => {
+            debug!("Cannot read {} for usage examples: {}", file.path, e);
+            None
+        }
+    }
+}
+
+/// Indices of the lines that use `name` as a whole word, other than imports
+/// and comments, with whether the use is a call such as `name(..)`,
+/// `Name::new(..)` or `name.method(..)`
+fn uses(lines: &[&str], name: &str) -> Vec<(usize, bool)> {
+    let mut found = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        if is_import(trimmed) || is_comment(trimmed) {
+            continue;
+        }
+        let mut used = false;
+        let mut is_call = false;
+        for (start, _) in line.match_indices(name) {
+            let rest = &line[start + name.len()..];
+            if line[..start].chars().next_back().is_some_and(is_word)
+                || rest.chars().next().is_some_and(is_word)
+            {
+                continue;
+            }
+            used = true;
+            is_call |= rest
+                .trim_start_matches(|c: char| is_word(c) || c == ':' || c == '.')
+                .starts_with('(');
+        }
+        if used {
+            found.push((index, is_call));
+        }
+    }
+    found
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Whether a trimmed line is, or is part of, an import: `use`, `import`,
+/// `from .. import`, `export .. from`, `require(..)`, or a line of nothing
+/// but names such as `parse, format,` in a list split over several
+fn is_import(line: &str) -> bool {
+    let line = line.strip_prefix("pub ").unwrap_or(line);
+    line.starts_with("use ")
+        || line.starts_with("pub(crate) use ")
+        || line.starts_with("import ")
+        || line.starts_with("from ")
+        || (line.starts_with("export ") || line.starts_with('}')) && line.contains(" from ")
+        || line.contains("require(")
+        || line.chars().all(|c| is_word(c) || " ,:{}".contains(c))
+}
+
+fn is_comment(line: &str) -> bool {
+    ["//", "#", "*", "/*", "--"]
+        .iter()
+        .any(|prefix| line.starts_with(prefix))
+}
+
+/// The use at `index` with a line of context before it and the rest of a
+/// call that continues on the next lines, dedented and cut to
+/// [`MAX_EXAMPLE_LINES`]
+fn snippet(lines: &[&str], index: usize) -> String {
+    let mut start = index.saturating_sub(CONTEXT_BEFORE);
+    while start < index && (lines[start].trim().is_empty() || is_import(lines[start].trim())) {
+        start += 1;
+    }
+    let mut depth = 0i32;
+    let mut end = index;
+    loop {
+        for c in lines[end].chars() {
+            match c {
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => depth -= 1,
+                _ => {}
+            }
+        }
+        if depth <= 0 || end + 1 == lines.len() || end >= index + MAX_CALL_LINES {
+            break;
+        }
+        end += 1;
+    }
+
+    let lines = &lines[start..=end];
+    let indent = lines
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.len() - l.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let dedented: Vec<&str> = lines
+        .iter()
+        .map(|l| &l[indent.min(l.len() - l.trim_start().len())..])
+        .collect();
+    analyzer::truncate_lines(&dedented, MAX_EXAMPLE_LINES)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::discovery::Language;
+    use std::fs;
+
+    #[test]
+    fn test_uses() {
+        let lines = [
+            "use crate::parser::{parse, Parser};",
+            "// parse the input",
+            "let parsed = parse(input);",
+            "let parser = Parser::new();",
+            "let reparse = reparse(input);",
+            "let f = parse;",
+            "    parse,",
+        ];
+        assert_eq!(uses(&lines, "parse"), [(2, true), (5, false)]);
+        assert_eq!(uses(&lines, "Parser"), [(3, true)]);
+    }
+
+    #[test]
+    fn test_snippet_follows_the_call_and_dedents() {
+        let lines = [
+            "fn main() {",
+            "    let input = read();",
+            "    let parsed = parse(",
+            "        &input,",
+            "        Options::default(),",
+            "    );",
+            "    println!(\"{parsed}\");",
+            "}",
+        ];
+        assert_eq!(
+            snippet(&lines, 2),
+            "let input = read();\nlet parsed = parse(\n    &input,\n    Options::default(),\n);"
+        );
+
+        let long: Vec<String> = std::iter::once("run(".to_string())
+            .chain((0..12).map(|i| format!("    {},", i)))
+            .chain(std::iter::once(")".to_string()))
+            .collect();
+        let long: Vec<&str> = long.iter().map(String::as_str).collect();
+        let cut = snippet(&long, 0);
+        assert_eq!(cut.lines().count(), MAX_EXAMPLE_LINES + 1);
+        assert!(cut.ends_with("\n... (6 more lines)"), "{}", cut);
+    }
+
+    fn module(path: &str, is_test: bool, exports: &[&str], imports: &[&str]) -> ModuleAnalysis {
+        let exports: Vec<_> = exports
+            .iter()
+            .map(|name| {
+                serde_json::json!({
+                    "name": name,
+                    "kind": "function",
+                    "signature": null,
+                    "description": "",
+                    "line_number": 1,
+                })
+            })
+            .collect();
+        let imports: Vec<_> = imports
+            .iter()
+            .map(|item| serde_json::json!({"source": "./src", "items": [item], "is_external": false}))
+            .collect();
+        serde_json::from_value(serde_json::json!({
+            "path": path,
+            "language": "typescript",
+            "exports": exports,
+            "imports": imports,
+            "summary": "",
+            "has_deep_analysis": false,
+            "is_test_module": is_test,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_collect_prefers_tests_short_calls_and_other_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = [
+            ("src/parser.ts", "export function parse() {}\n"),
+            (
+                "src/app.ts",
+                "import { parse } from './parser';\n\nconst result = parse(readTheWholeInputFile());\nconst again = parse(x);\nexport const p = parse;\n",
+            ),
+            (
+                "test/parser.test.ts",
+                "import { parse } from '../src/parser';\nit('parses', () => { expect(parse('')).toBe(1); });\n",
+            ),
+        ];
+        let mut inventory = FileInventory::default();
+        for (path, content) in files {
+            let abs_path = dir.path().join(path);
+            fs::create_dir_all(abs_path.parent().unwrap()).unwrap();
+            fs::write(&abs_path, content).unwrap();
+            inventory.source_files.push(SourceFile {
+                path: path.to_string(),
+                abs_path,
+                language: Language::TypeScript,
+                size: content.len() as u64,
+                is_test: path.starts_with("test/"),
+                modified: None,
+                companions: Vec::new(),
+                detection: Default::default(),
+            });
+        }
+        let mut modules = vec![
+            module("src/parser.ts", false, &["parse"], &[]),
+            module("src/app.ts", false, &["p"], &["parse"]),
+            module("test/parser.test.ts", true, &[], &["parse"]),
+        ];
+
+        collect(&mut modules, &inventory, 2);
+        let examples: Vec<_> = modules[0].exports[0]
+            .examples
+            .iter()
+            .map(|e| (e.path.as_str(), e.line, e.snippet.as_str()))
+            .collect();
+        assert_eq!(
+            examples,
+            [
+                (
+                    "test/parser.test.ts",
+                    2,
+                    "it('parses', () => { expect(parse('')).toBe(1); });"
+                ),
+                (
+                    "src/app.ts",
+                    4,
+                    "const result = parse(readTheWholeInputFile());\nconst again = parse(x);"
+                ),
+            ]
+        );
+
+        collect(&mut modules, &inventory, 0);
+        assert!(modules[0].exports[0].examples.is_empty());
+    }
+}
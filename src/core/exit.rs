@@ -0,0 +1,137 @@
+//! Process exit codes, and the errors `main` maps to them
+//!
+//! Commands fail with `anyhow` errors like everywhere else; the ones that
+//! wrap a marker type from here get their own exit code, so wrapper scripts
+//! can tell a typo in a flag from a missing API key. The codes are stable.
+
+use std::error::Error;
+use std::fmt;
+
+pub use super::gate::{GapsExceeded, EXIT_GAPS_EXCEEDED};
+
+/// Any failure without a more specific code: I/O, provider errors, ...
+pub const EXIT_ERROR: i32 = 1;
+/// Invalid command-line usage; clap uses the same code for parse errors
+pub const EXIT_USAGE: i32 = 2;
+/// Bad configuration, or provider credentials that are missing or rejected
+pub const EXIT_CONFIG: i32 = 4;
+/// `analyze` wrote its output, but logged warnings along the way
+pub const EXIT_WARNINGS: i32 = 5;
+/// Stopped by Ctrl-C; 128 + SIGINT, as shells report it
+pub const EXIT_INTERRUPTED: i32 = 130;
+
+/// A combination of flags or arguments that clap cannot reject by itself
+#[derive(Debug)]
+pub struct UsageError(pub String);
+
+impl UsageError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self(message.into())
+    }
+}
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for UsageError {}
+
+/// Marks an error as caused by configuration or credentials. Displays as
+/// the wrapped error, so messages are unchanged.
+#[derive(Debug)]
+pub struct ConfigError(anyhow::Error);
+
+impl ConfigError {
+    /// For `map_err`: `config::load(layer).map_err(ConfigError::wrap)`
+    pub fn wrap(error: anyhow::Error) -> anyhow::Error {
+        if error.downcast_ref::<ConfigError>().is_some() {
+            return error;
+        }
+        Self(error).into()
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.0.source()
+    }
+}
+
+/// `analyze` finished and wrote its output, but `count` warnings were logged
+#[derive(Debug)]
+pub struct CompletedWithWarnings {
+    pub count: usize,
+}
+
+impl fmt::Display for CompletedWithWarnings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Analysis completed with {} warning(s); see the log above",
+            self.count
+        )
+    }
+}
+
+impl Error for CompletedWithWarnings {}
+
+/// Ctrl-C arrived before the command finished
+#[derive(Debug)]
+pub struct Interrupted;
+
+impl fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Interrupted")
+    }
+}
+
+impl Error for Interrupted {}
+
+/// Exit code for an error returned by a command
+pub fn code(error: &anyhow::Error) -> i32 {
+    if error.downcast_ref::<UsageError>().is_some() {
+        EXIT_USAGE
+    } else if error.downcast_ref::<GapsExceeded>().is_some() {
+        EXIT_GAPS_EXCEEDED
+    } else if error.downcast_ref::<ConfigError>().is_some() {
+        EXIT_CONFIG
+    } else if error.downcast_ref::<CompletedWithWarnings>().is_some() {
+        EXIT_WARNINGS
+    } else if error.downcast_ref::<Interrupted>().is_some() {
+        EXIT_INTERRUPTED
+    } else {
+        EXIT_ERROR
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_code_by_failure_class() {
+        assert_eq!(code(&anyhow::anyhow!("disk full")), EXIT_ERROR);
+        assert_eq!(code(&UsageError::new("bad flags").into()), EXIT_USAGE);
+        assert_eq!(code(&GapsExceeded::new(&[]).into()), EXIT_GAPS_EXCEEDED);
+        assert_eq!(code(&Interrupted.into()), EXIT_INTERRUPTED);
+        assert_eq!(
+            code(&CompletedWithWarnings { count: 2 }.into()),
+            EXIT_WARNINGS
+        );
+
+        // Context added on top keeps the class
+        let error = ConfigError::wrap(anyhow::anyhow!("ANTHROPIC_API_KEY not set"));
+        assert_eq!(error.to_string(), "ANTHROPIC_API_KEY not set");
+        let error = Err::<(), _>(error).context("Loading provider").unwrap_err();
+        assert_eq!(code(&error), EXIT_CONFIG);
+    }
+}
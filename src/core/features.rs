@@ -0,0 +1,687 @@
+//! Feature flags: the Cargo features and environment variables code is
+//! conditional on
+//!
+//! A Rust item under `#[cfg(...)]` (its own, its `impl`'s or module's, or
+//! the file's `#![cfg(...)]`) records the condition as its `cfg`, and each
+//! module lists every flag it checks, in `cfg` and `cfg_attr` attributes and
+//! `cfg!`. In TypeScript and JavaScript, a flag is a `process.env.NAME` or
+//! `import.meta.env.NAME` read in the condition of an `if` or a `?:`; the
+//! export containing the condition is gated by it. [`inventory`] joins the
+//! flags with the `[features]` each Cargo.toml declares, and [`gaps`]
+//! reports the features code checks without declaring, and the empty ones
+//! nothing checks.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+use tracing::debug;
+
+use super::analyzer::{Gap, GapKind, ModuleAnalysis};
+use super::discovery::{FileInventory, Language};
+use super::parser::ParseResult;
+use super::project_files::ConfigFile;
+
+/// Serialized in kebab case: `cargo`, `env`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[non_exhaustive]
+pub enum FlagKind {
+    /// A Cargo feature, checked with `feature = "name"`
+    Cargo,
+    /// An environment variable checked in a condition
+    Env,
+}
+
+/// A flag some code checks
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Flag {
+    pub kind: FlagKind,
+    pub name: String,
+}
+
+impl Flag {
+    pub fn new(kind: FlagKind, name: impl Into<String>) -> Self {
+        Flag {
+            kind,
+            name: name.into(),
+        }
+    }
+}
+
+/// A feature in the `[features]` of a Cargo.toml, or the implicit feature
+/// of an optional dependency
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct DeclaredFeature {
+    pub name: String,
+    /// The Cargo.toml, relative to the root
+    pub manifest: String,
+    /// Features and dependencies it turns on, as written
+    pub enables: Vec<String>,
+}
+
+/// A flag and the code it gates, across the project
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct FeatureFlag {
+    pub kind: FlagKind,
+    pub name: String,
+    /// The Cargo.toml declaring a Cargo feature; `None` for undeclared
+    /// features and environment variables
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub declared_in: Option<String>,
+    /// What a declared feature turns on
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub enables: Vec<String>,
+    /// Modules checking it, in path order
+    #[serde(default)]
+    pub modules: Vec<String>,
+    /// Exports only there when it is set
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exports: Vec<GatedExport>,
+}
+
+/// An export gated by a flag
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct GatedExport {
+    pub module: String,
+    pub export: String,
+    /// The whole condition, which may name other flags
+    pub cfg: String,
+}
+
+struct Patterns {
+    /// Start of a `cfg` or `cfg_attr` attribute or a `cfg!`
+    rust_cfg: Regex,
+    rust_feature: Regex,
+    env: Regex,
+    /// A line with a condition: an `if` or a `?:`
+    js_condition: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| Patterns {
+        rust_cfg: Regex::new(r"#!?\[\s*cfg(?:_attr)?\s*\(|\bcfg!\s*\(").unwrap(),
+        rust_feature: Regex::new(r#"\bfeature\s*=\s*"([^"]+)""#).unwrap(),
+        env: Regex::new(r"\b(?:process\.env|import\.meta\.env)\.([A-Za-z_][A-Za-z0-9_]*)").unwrap(),
+        js_condition: Regex::new(r"\bif\s*\(|\?[^?.:]").unwrap(),
+    })
+}
+
+/// The flags named in a condition: Cargo features in a Rust `cfg`,
+/// environment variables in a TypeScript or JavaScript one
+pub fn flags_in(condition: &str) -> Vec<Flag> {
+    let patterns = patterns();
+    let cargo = patterns
+        .rust_feature
+        .captures_iter(condition)
+        .map(|c| Flag::new(FlagKind::Cargo, &c[1]));
+    let env = patterns
+        .env
+        .captures_iter(condition)
+        .map(|c| Flag::new(FlagKind::Env, &c[1]));
+    let flags: BTreeSet<Flag> = cargo.chain(env).collect();
+    flags.into_iter().collect()
+}
+
+/// Fill in the flags `content` checks, and gate the TypeScript and
+/// JavaScript exports containing an environment flag condition. Rust
+/// exports get their `cfg` from the parser.
+pub(crate) fn mark(result: &mut ParseResult, content: &str, language: Language) {
+    let patterns = patterns();
+    let mut flags = BTreeSet::new();
+    match language {
+        Language::Rust => {
+            // Found in code only, so examples in strings and docs don't count
+            for start in patterns.rust_cfg.find_iter(&rust_code(content)) {
+                flags.extend(flags_in(balanced(&content[start.end()..])));
+            }
+        }
+        Language::TypeScript | Language::JavaScript => {
+            let conditions: Vec<(usize, Vec<&str>)> = content
+                .lines()
+                .enumerate()
+                .filter(|(_, line)| patterns.js_condition.is_match(line))
+                .map(|(i, line)| {
+                    let reads = patterns.env.find_iter(line).map(|m| m.as_str()).collect();
+                    (i + 1, reads)
+                })
+                .filter(|(_, reads): &(usize, Vec<&str>)| !reads.is_empty())
+                .collect();
+            for export in result.exports.iter_mut().filter(|e| e.cfg.is_none()) {
+                let end = export.end_line.unwrap_or(export.line_number);
+                let reads: BTreeSet<&str> = conditions
+                    .iter()
+                    .filter(|(line, _)| (export.line_number..=end).contains(line))
+                    .flat_map(|(_, reads)| reads.iter().copied())
+                    .collect();
+                if !reads.is_empty() {
+                    export.cfg = Some(reads.into_iter().collect::<Vec<_>>().join(", "));
+                }
+            }
+            for (_, reads) in &conditions {
+                flags.extend(reads.iter().flat_map(|read| flags_in(read)));
+            }
+        }
+        _ => {}
+    }
+    result.flags = flags.into_iter().collect();
+}
+
+/// Add the flags of a paired file to those of its module, keeping them
+/// sorted and unique
+pub(crate) fn merge_flags(flags: &mut Vec<Flag>, more: Vec<Flag>) {
+    let merged: BTreeSet<Flag> = flags.drain(..).chain(more).collect();
+    flags.extend(merged);
+}
+
+/// `content` with the insides of Rust comments and string literals blanked
+/// out, at the same offsets
+fn rust_code(content: &str) -> String {
+    let bytes = content.as_bytes();
+    let mut code = bytes.to_vec();
+    let blank = |code: &mut Vec<u8>, from: usize, to: usize| {
+        for byte in &mut code[from..to.min(bytes.len())] {
+            if *byte != b'\n' {
+                *byte = b' ';
+            }
+        }
+    };
+    let mut i = 0;
+    while i < bytes.len() {
+        let rest = &content[i..];
+        let literal = if rest.starts_with("//") {
+            Some(rest.find('\n').map_or(bytes.len(), |n| i + n))
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            Some(comment.find("*/").map_or(bytes.len(), |n| i + n + 4))
+        } else if bytes[i] == b'\'' && bytes.get(i + 2) == Some(&b'\'') {
+            // A character literal like '"', not a lifetime
+            Some(i + 3)
+        } else if bytes[i] == b'"' {
+            let mut j = i + 1;
+            while j < bytes.len() && bytes[j] != b'"' {
+                j += if bytes[j] == b'\\' { 2 } else { 1 };
+            }
+            Some(j + 1)
+        } else if bytes[i] == b'r' && (i == 0 || !bytes[i - 1].is_ascii_alphanumeric()) {
+            let hashes = rest[1..].len() - rest[1..].trim_start_matches('#').len();
+            rest[1 + hashes..].starts_with('"').then(|| {
+                let close = format!("\"{}", "#".repeat(hashes));
+                rest[2 + hashes..]
+                    .find(&close)
+                    .map_or(bytes.len(), |n| i + 2 + hashes + n + close.len())
+            })
+        } else {
+            None
+        };
+        match literal {
+            Some(end) => {
+                blank(&mut code, i, end);
+                i = end;
+            }
+            None => i += rest.chars().next().map_or(1, char::len_utf8),
+        }
+    }
+    // Whole characters were blanked, a space per byte, so it stays UTF-8
+    String::from_utf8(code).unwrap_or_default()
+}
+
+/// `text` up to the parenthesis closing the one before it
+fn balanced(text: &str) -> &str {
+    let mut depth = 1;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return &text[..i];
+                }
+            }
+            _ => {}
+        }
+    }
+    text
+}
+
+/// The features declared by the inventory's Cargo.toml files, in manifest
+/// order
+pub fn declared(inventory: &FileInventory) -> Vec<DeclaredFeature> {
+    let root = Path::new(&inventory.root);
+    let mut manifests: Vec<&String> = inventory
+        .config_files
+        .iter()
+        .filter(|path| is_manifest(path))
+        .collect();
+    manifests.sort();
+    manifests
+        .into_iter()
+        .flat_map(|manifest| match fs::read_to_string(root.join(manifest)) {
+            Ok(content) => cargo_features(&content, manifest),
+            Err(e) => {
+                debug!("Not reading features from {}: {}", manifest, e);
+                Vec::new()
+            }
+        })
+        .collect()
+}
+
+fn is_manifest(path: &str) -> bool {
+    path.rsplit('/').next() == Some("Cargo.toml")
+}
+
+/// `[features]` of a Cargo.toml, then the optional dependencies that no
+/// feature enables with `dep:`, as Cargo makes a feature of each
+fn cargo_features(content: &str, manifest: &str) -> Vec<DeclaredFeature> {
+    let table: toml::Table = match content.parse() {
+        Ok(table) => table,
+        Err(e) => {
+            debug!("Not a valid Cargo.toml: {}: {}", manifest, e);
+            return Vec::new();
+        }
+    };
+    let mut features: Vec<DeclaredFeature> = table
+        .get("features")
+        .and_then(|f| f.as_table())
+        .into_iter()
+        .flatten()
+        .map(|(name, enables)| DeclaredFeature {
+            name: name.clone(),
+            manifest: manifest.to_string(),
+            enables: enables
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|e| e.as_str().map(String::from))
+                .collect(),
+        })
+        .collect();
+
+    let explicit: BTreeSet<&str> = features
+        .iter()
+        .flat_map(|f| f.enables.iter())
+        .filter_map(|e| e.strip_prefix("dep:"))
+        .collect();
+    let optional: BTreeSet<&str> = ["dependencies", "build-dependencies"]
+        .into_iter()
+        .filter_map(|section| table.get(section)?.as_table())
+        .flatten()
+        .filter(|(_, spec)| spec.get("optional").and_then(|o| o.as_bool()) == Some(true))
+        .map(|(name, _)| name.as_str())
+        .filter(|name| !explicit.contains(name))
+        .collect();
+    let implicit: Vec<DeclaredFeature> = optional
+        .into_iter()
+        .filter(|name| !features.iter().any(|f| f.name == *name))
+        .map(|name| DeclaredFeature {
+            name: name.to_string(),
+            manifest: manifest.to_string(),
+            enables: vec![format!("dep:{}", name)],
+        })
+        .collect();
+    features.extend(implicit);
+    features
+}
+
+/// Every flag: the declared features, then those only code names, with the
+/// modules and exports each gates. A module's Cargo features are looked up
+/// in the nearest Cargo.toml among `config_files` above it. Sorted by kind
+/// and name.
+pub fn inventory(
+    modules: &[ModuleAnalysis],
+    declared: &[DeclaredFeature],
+    config_files: &[ConfigFile],
+) -> Vec<FeatureFlag> {
+    let manifests: Vec<&str> = config_files
+        .iter()
+        .map(|c| c.path.as_str())
+        .filter(|path| is_manifest(path))
+        .collect();
+    let mut flags: BTreeMap<(FlagKind, String, Option<String>), FeatureFlag> = declared
+        .iter()
+        .map(|feature| {
+            let key = (
+                FlagKind::Cargo,
+                feature.name.clone(),
+                Some(feature.manifest.clone()),
+            );
+            let flag = FeatureFlag {
+                kind: FlagKind::Cargo,
+                name: feature.name.clone(),
+                declared_in: Some(feature.manifest.clone()),
+                enables: feature.enables.clone(),
+                modules: Vec::new(),
+                exports: Vec::new(),
+            };
+            (key, flag)
+        })
+        .collect();
+
+    let key_of = |module: &str, flag: &Flag| {
+        let declared_in = match flag.kind {
+            FlagKind::Cargo => manifest_of(module, &manifests)
+                .filter(|manifest| {
+                    declared
+                        .iter()
+                        .any(|f| f.manifest == *manifest && f.name == flag.name)
+                })
+                .map(String::from),
+            FlagKind::Env => None,
+        };
+        (flag.kind, flag.name.clone(), declared_in)
+    };
+    fn entry(
+        flags: &mut BTreeMap<(FlagKind, String, Option<String>), FeatureFlag>,
+        key: (FlagKind, String, Option<String>),
+    ) -> &mut FeatureFlag {
+        flags.entry(key.clone()).or_insert_with(|| FeatureFlag {
+            kind: key.0,
+            name: key.1,
+            declared_in: key.2,
+            enables: Vec::new(),
+            modules: Vec::new(),
+            exports: Vec::new(),
+        })
+    }
+    for module in modules {
+        for flag in &module.flags {
+            entry(&mut flags, key_of(&module.path, flag))
+                .modules
+                .push(module.path.clone());
+        }
+        for export in &module.exports {
+            let Some(cfg) = &export.cfg else { continue };
+            for flag in flags_in(cfg) {
+                entry(&mut flags, key_of(&module.path, &flag))
+                    .exports
+                    .push(GatedExport {
+                        module: module.path.clone(),
+                        export: export.name.clone(),
+                        cfg: cfg.clone(),
+                    });
+            }
+        }
+    }
+
+    flags
+        .into_values()
+        .map(|mut flag| {
+            flag.modules.sort();
+            flag.modules.dedup();
+            flag.exports.sort();
+            flag.exports.dedup();
+            flag
+        })
+        .collect()
+}
+
+/// The Cargo.toml of the crate `path` is in: the one in its nearest
+/// directory
+fn manifest_of<'a>(path: &str, manifests: &[&'a str]) -> Option<&'a str> {
+    manifests
+        .iter()
+        .copied()
+        .filter(|manifest| {
+            let dir = manifest.strip_suffix("Cargo.toml").unwrap_or_default();
+            path.starts_with(dir)
+        })
+        .max_by_key(|manifest| manifest.len())
+}
+
+/// An [`GapKind::UndeclaredFeature`] for each module checking a Cargo
+/// feature its crate doesn't declare, and an [`GapKind::UnusedFeature`] for
+/// each declared feature that turns nothing on and that neither code nor
+/// another feature names. `default` is never unused.
+pub fn gaps(flags: &[FeatureFlag], config_files: &[ConfigFile]) -> Vec<Gap> {
+    let manifests: Vec<&str> = config_files
+        .iter()
+        .map(|c| c.path.as_str())
+        .filter(|path| is_manifest(path))
+        .collect();
+    let enabled: BTreeSet<(&str, &str)> = flags
+        .iter()
+        .filter_map(|flag| Some((flag.declared_in.as_deref()?, &flag.enables)))
+        .flat_map(|(manifest, enables)| enables.iter().map(move |e| (manifest, e.as_str())))
+        .collect();
+
+    let mut gaps = Vec::new();
+    for flag in flags.iter().filter(|f| f.kind == FlagKind::Cargo) {
+        match &flag.declared_in {
+            None => {
+                for module in &flag.modules {
+                    let Some(manifest) = manifest_of(module, &manifests) else {
+                        continue;
+                    };
+                    gaps.push(Gap {
+                        kind: GapKind::UndeclaredFeature,
+                        severity: GapKind::UndeclaredFeature.severity(),
+                        description: format!(
+                            "Checks feature `{}`, which {} doesn't declare, so the code \
+                             under it is never compiled",
+                            flag.name, manifest
+                        ),
+                        location: Some(module.clone()),
+                        owner: None,
+                    });
+                }
+            }
+            Some(manifest) => {
+                let unused = flag.name != "default"
+                    && flag.enables.is_empty()
+                    && flag.modules.is_empty()
+                    && !enabled.contains(&(manifest.as_str(), flag.name.as_str()));
+                if unused {
+                    gaps.push(Gap {
+                        kind: GapKind::UnusedFeature,
+                        severity: GapKind::UnusedFeature.severity(),
+                        description: format!(
+                            "Feature `{}` turns nothing on and no code checks it",
+                            flag.name
+                        ),
+                        location: Some(manifest.clone()),
+                        owner: None,
+                    });
+                }
+            }
+        }
+    }
+    gaps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::project_files::ConfigKind;
+    use crate::core::{analyzer, parser};
+
+    fn parse(path: &str, content: &str, language: Language) -> ParseResult {
+        parser::parse_file(Path::new(path), content, language, false).unwrap()
+    }
+
+    #[test]
+    fn test_rust_cfg() {
+        let content = r#"#[cfg(feature = "serde")]
+pub fn to_json() {}
+
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct Config;
+
+#[cfg(all(unix, feature = "tls"))]
+mod tls {
+    pub fn connect() {}
+}
+
+#[cfg(test)]
+pub fn fixture() {}
+
+pub fn always() {
+    if cfg!(feature = "trace") {}
+}
+"#;
+        let result = parse("src/lib.rs", content, Language::Rust);
+        let cfg = |name: &str| {
+            let export = result.exports.iter().find(|e| e.name == name).unwrap();
+            export.cfg.as_deref()
+        };
+        assert_eq!(cfg("to_json"), Some(r#"feature = "serde""#));
+        assert_eq!(cfg("Config"), None);
+        assert_eq!(cfg("connect"), Some(r#"all(unix, feature = "tls")"#));
+        assert_eq!(cfg("fixture"), Some("test"));
+        assert_eq!(cfg("always"), None);
+        let names: Vec<&str> = result.flags.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["schema", "serde", "tls", "trace"]);
+
+        // Conditions in comments and strings are examples, not checks
+        let result = parse(
+            "src/doc.rs",
+            "/// `#[cfg(feature = \"a\")]`\npub fn f() -> &'static str {\n    \
+             r#\"#[cfg(feature = \"b\")]\"#\n}\nconst Q: char = '\"';\n\
+             #[cfg(feature = \"c\")]\npub fn g() {}\n",
+            Language::Rust,
+        );
+        let names: Vec<&str> = result.flags.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["c"]);
+
+        // The file's own condition applies to everything in it
+        let result = parse(
+            "src/gated.rs",
+            "#![cfg(feature = \"gated\")]\n\n#[cfg(unix)]\npub fn f() {}\n",
+            Language::Rust,
+        );
+        assert_eq!(
+            result.exports[0].cfg.as_deref(),
+            Some(r#"all(feature = "gated", unix)"#)
+        );
+    }
+
+    #[test]
+    fn test_env_flags() {
+        let content = "export function render() {\n  if (process.env.FEATURE_NEW_UI) {\n    return 1;\n  }\n  return 0;\n}\n\nexport const port = process.env.PORT;\nexport const mode = import.meta.env.VITE_BETA ? 'beta' : 'stable';\n";
+        let result = parse("src/ui.ts", content, Language::TypeScript);
+        let cfg = |name: &str| {
+            let export = result.exports.iter().find(|e| e.name == name).unwrap();
+            export.cfg.as_deref()
+        };
+        assert_eq!(cfg("render"), Some("process.env.FEATURE_NEW_UI"));
+        assert_eq!(cfg("port"), None);
+        assert_eq!(cfg("mode"), Some("import.meta.env.VITE_BETA"));
+        assert_eq!(
+            result.flags,
+            [
+                Flag::new(FlagKind::Env, "FEATURE_NEW_UI"),
+                Flag::new(FlagKind::Env, "VITE_BETA")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cargo_features() {
+        let manifest = r#"
+[features]
+default = ["tls"]
+tls = ["dep:rustls"]
+trace = []
+experimental = []
+
+[dependencies]
+rustls = { version = "0.23", optional = true }
+serde = { version = "1", optional = true }
+"#;
+        let features = cargo_features(manifest, "Cargo.toml");
+        let names: Vec<(&str, &[String])> = features
+            .iter()
+            .map(|f| (f.name.as_str(), f.enables.as_slice()))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("default", &["tls".to_string()][..]),
+                ("experimental", &[][..]),
+                ("tls", &["dep:rustls".to_string()][..]),
+                ("trace", &[][..]),
+                ("serde", &["dep:serde".to_string()][..]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_inventory_and_gaps() {
+        let features = cargo_features(
+            "[features]\ntrace = []\nunused = []\nserde = [\"dep:serde\"]\n",
+            "Cargo.toml",
+        );
+        let config_files = vec![ConfigFile {
+            path: "Cargo.toml".into(),
+            kind: ConfigKind::Build,
+            detail: None,
+            summary: None,
+        }];
+        let dir = tempfile::tempdir().unwrap();
+        let io_path = dir.path().join("lib.rs");
+        fs::write(
+            &io_path,
+            "#[cfg(feature = \"trace\")]\npub fn trace() {}\n\
+             #[cfg(feature = \"missing\")]\npub fn gone() {}\n",
+        )
+        .unwrap();
+        let module = analyzer::analyze_file_static(
+            "src/lib.rs",
+            &io_path,
+            Language::Rust,
+            Default::default(),
+            &Default::default(),
+        )
+        .unwrap()
+        .unwrap();
+        let modules = vec![module];
+
+        let flags = inventory(&modules, &features, &config_files);
+        let summary: Vec<(&str, Option<&str>, &[String], usize)> = flags
+            .iter()
+            .map(|f| {
+                (
+                    f.name.as_str(),
+                    f.declared_in.as_deref(),
+                    f.modules.as_slice(),
+                    f.exports.len(),
+                )
+            })
+            .collect();
+        let lib = ["src/lib.rs".to_string()];
+        assert_eq!(
+            summary,
+            [
+                ("missing", None, &lib[..], 1),
+                ("serde", Some("Cargo.toml"), &[][..], 0),
+                ("trace", Some("Cargo.toml"), &lib[..], 1),
+                ("unused", Some("Cargo.toml"), &[][..], 0),
+            ]
+        );
+        assert_eq!(flags[2].exports[0].export, "trace");
+
+        let gaps = gaps(&flags, &config_files);
+        let found: Vec<(GapKind, Option<&str>)> = gaps
+            .iter()
+            .map(|g| (g.kind, g.location.as_deref()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (GapKind::UndeclaredFeature, Some("src/lib.rs")),
+                (GapKind::UnusedFeature, Some("Cargo.toml")),
+            ]
+        );
+        assert!(gaps[0].description.contains("`missing`"));
+
+        // Without a Cargo.toml, nothing can be undeclared
+        assert!(super::gaps(&flags, &[])
+            .iter()
+            .all(|g| g.kind != GapKind::UndeclaredFeature));
+    }
+}
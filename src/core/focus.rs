@@ -0,0 +1,329 @@
+//! Dependency graphs narrowed to one part of the codebase
+//!
+//! `--focus <glob>` keeps the modules the glob matches and their direct
+//! dependencies and dependents. Every other module is collapsed into a
+//! summary node for its directory, and the edges cut that way are merged
+//! into one per pair of nodes, with the number of imports they stand for.
+//! Graph outputs render the result; the selection itself is shared here.
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A glob over module paths: `*` and `?` stay within a path segment and
+/// `**` spans segments. A pattern that matches a directory matches
+/// everything under it, so `src/billing` and `src/billing/**` agree.
+#[derive(Debug, Clone)]
+pub struct FocusGlob {
+    pattern: String,
+    regex: Regex,
+}
+
+impl FocusGlob {
+    pub fn new(pattern: &str) -> Result<Self> {
+        let pattern = pattern
+            .trim()
+            .trim_start_matches("./")
+            .trim_end_matches('/');
+        anyhow::ensure!(!pattern.is_empty(), "--focus needs a non-empty glob");
+
+        let mut source = String::from("^");
+        let mut rest = pattern;
+        while let Some(c) = rest.chars().next() {
+            if let Some(after) = rest.strip_prefix("**/") {
+                source.push_str("(?:.*/)?");
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix("**") {
+                source.push_str(".*");
+                rest = after;
+            } else {
+                match c {
+                    '*' => source.push_str("[^/]*"),
+                    '?' => source.push_str("[^/]"),
+                    c => source.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+                }
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+        source.push('$');
+
+        let regex =
+            Regex::new(&source).with_context(|| format!("invalid --focus glob `{}`", pattern))?;
+        Ok(Self {
+            pattern: pattern.to_string(),
+            regex,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    pub fn is_match(&self, module: &str) -> bool {
+        let module = module.replace('\\', "/");
+        if self.regex.is_match(&module) {
+            return true;
+        }
+        module
+            .match_indices('/')
+            .any(|(i, _)| self.regex.is_match(&module[..i]))
+    }
+}
+
+/// A node of a focused graph
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FocusNode {
+    /// A module the glob matched
+    Focus(String),
+    /// A direct dependency or dependent of a focus module
+    Neighbor(String),
+    /// The other modules of a directory (`""` for the root)
+    Summary(String),
+}
+
+impl FocusNode {
+    /// Module path, or directory path for a summary
+    pub fn path(&self) -> &str {
+        match self {
+            FocusNode::Focus(path) | FocusNode::Neighbor(path) | FocusNode::Summary(path) => path,
+        }
+    }
+}
+
+/// An edge of a focused graph
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FocusEdge {
+    pub from: FocusNode,
+    pub to: FocusNode,
+    /// How many module-to-module dependencies the edge stands for; more
+    /// than one only for edges to or from a summary
+    pub count: usize,
+}
+
+impl FocusEdge {
+    /// Whether the edge replaces dependencies on collapsed modules
+    pub fn is_cut(&self) -> bool {
+        matches!(self.from, FocusNode::Summary(_)) || matches!(self.to, FocusNode::Summary(_))
+    }
+}
+
+/// `dependencies` narrowed to the modules `glob` matches and their
+/// neighbors
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FocusGraph {
+    pub nodes: Vec<FocusNode>,
+    pub edges: Vec<FocusEdge>,
+    /// Modules in each summary node, by directory
+    pub collapsed: BTreeMap<String, usize>,
+}
+
+/// Select the part of a `CrossReference::dependencies` map that `glob`
+/// is about. Dependencies between two collapsed modules are left out.
+pub fn select(dependencies: &BTreeMap<String, Vec<String>>, glob: &FocusGlob) -> FocusGraph {
+    let modules: BTreeSet<&str> = dependencies
+        .iter()
+        .flat_map(|(from, to)| std::iter::once(from).chain(to))
+        .map(String::as_str)
+        .collect();
+    let focused: BTreeSet<&str> = modules
+        .iter()
+        .copied()
+        .filter(|m| glob.is_match(m))
+        .collect();
+
+    let mut neighbors = BTreeSet::new();
+    for (from, to) in dependencies {
+        for to in to {
+            if focused.contains(from.as_str()) && !focused.contains(to.as_str()) {
+                neighbors.insert(to.as_str());
+            }
+            if focused.contains(to.as_str()) && !focused.contains(from.as_str()) {
+                neighbors.insert(from.as_str());
+            }
+        }
+    }
+
+    let node = |module: &str| {
+        if focused.contains(module) {
+            FocusNode::Focus(module.to_string())
+        } else if neighbors.contains(module) {
+            FocusNode::Neighbor(module.to_string())
+        } else {
+            FocusNode::Summary(directory(module).to_string())
+        }
+    };
+
+    let mut collapsed: BTreeMap<String, usize> = BTreeMap::new();
+    for module in &modules {
+        if let FocusNode::Summary(dir) = node(module) {
+            *collapsed.entry(dir).or_default() += 1;
+        }
+    }
+
+    let mut counts: BTreeMap<(FocusNode, FocusNode), usize> = BTreeMap::new();
+    for (from, to) in dependencies {
+        for to in to {
+            let (from, to) = (node(from), node(to));
+            if matches!((&from, &to), (FocusNode::Summary(_), FocusNode::Summary(_))) {
+                continue;
+            }
+            *counts.entry((from, to)).or_default() += 1;
+        }
+    }
+
+    let mut nodes: BTreeSet<FocusNode> = modules
+        .iter()
+        .map(|m| node(m))
+        .filter(|n| !matches!(n, FocusNode::Summary(_)))
+        .collect();
+    // Only the summaries something visible connects to
+    for (from, to) in counts.keys() {
+        nodes.insert(from.clone());
+        nodes.insert(to.clone());
+    }
+    collapsed.retain(|dir, _| nodes.contains(&FocusNode::Summary(dir.clone())));
+
+    FocusGraph {
+        nodes: nodes.into_iter().collect(),
+        edges: counts
+            .into_iter()
+            .map(|((from, to), count)| FocusEdge { from, to, count })
+            .collect(),
+        collapsed,
+    }
+}
+
+fn directory(module: &str) -> &str {
+    module.rfind(['/', '\\']).map_or("", |i| &module[..i])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_focus_glob() {
+        let glob = FocusGlob::new("src/billing/**").unwrap();
+        assert!(glob.is_match("src/billing/invoice.rs"));
+        assert!(glob.is_match("src/billing/tax/vat.rs"));
+        assert!(!glob.is_match("src/billing_old.rs"));
+
+        let glob = FocusGlob::new("./src/billing/").unwrap();
+        assert_eq!(glob.as_str(), "src/billing");
+        assert!(glob.is_match("src/billing/tax/vat.rs"));
+        assert!(!glob.is_match("src/billing.rs"));
+
+        let glob = FocusGlob::new("src/*.rs").unwrap();
+        assert!(glob.is_match("src/main.rs"));
+        assert!(!glob.is_match("src/core/main.rs"));
+
+        let glob = FocusGlob::new("**/mod.rs").unwrap();
+        assert!(glob.is_match("mod.rs"));
+        assert!(glob.is_match("src/core/mod.rs"));
+        assert!(glob.is_match(r"src\core\mod.rs"));
+
+        assert!(FocusGlob::new("web/api.?s").unwrap().is_match("web/api.ts"));
+        assert!(FocusGlob::new("src/a+b.rs").unwrap().is_match("src/a+b.rs"));
+        assert!(FocusGlob::new(" ").is_err());
+    }
+
+    #[test]
+    fn test_focus_neighbors() {
+        let dependencies = BTreeMap::from([
+            (
+                "src/main.rs".to_string(),
+                vec![
+                    "src/billing/invoice.rs".to_string(),
+                    "src/cli.rs".to_string(),
+                ],
+            ),
+            (
+                "src/billing/invoice.rs".to_string(),
+                vec![
+                    "src/db/pool.rs".to_string(),
+                    "src/billing/tax.rs".to_string(),
+                ],
+            ),
+            (
+                "src/db/pool.rs".to_string(),
+                vec!["src/util/log.rs".to_string()],
+            ),
+            (
+                "src/cli.rs".to_string(),
+                vec!["src/util/log.rs".to_string(), "src/util/fmt.rs".to_string()],
+            ),
+            (
+                "src/web/api.rs".to_string(),
+                vec!["src/util/log.rs".to_string()],
+            ),
+        ]);
+        let graph = select(&dependencies, &FocusGlob::new("src/billing").unwrap());
+
+        let focus_node = |p: &str| FocusNode::Focus(p.to_string());
+        let neighbor = |p: &str| FocusNode::Neighbor(p.to_string());
+        let summary = |p: &str| FocusNode::Summary(p.to_string());
+        assert_eq!(
+            graph.nodes,
+            vec![
+                focus_node("src/billing/invoice.rs"),
+                focus_node("src/billing/tax.rs"),
+                neighbor("src/db/pool.rs"),
+                neighbor("src/main.rs"),
+                summary("src"),
+                summary("src/util"),
+            ]
+        );
+        // src/web/api.rs only depends on collapsed modules
+        assert_eq!(
+            graph.collapsed,
+            BTreeMap::from([("src".to_string(), 1), ("src/util".to_string(), 2)])
+        );
+
+        let edge = |from: FocusNode, to: FocusNode| {
+            graph
+                .edges
+                .iter()
+                .find(|e| e.from == from && e.to == to)
+                .map(|e| e.count)
+        };
+        assert_eq!(
+            edge(
+                focus_node("src/billing/invoice.rs"),
+                neighbor("src/db/pool.rs")
+            ),
+            Some(1)
+        );
+        assert_eq!(edge(neighbor("src/main.rs"), summary("src")), Some(1));
+        assert_eq!(
+            edge(neighbor("src/db/pool.rs"), summary("src/util")),
+            Some(1)
+        );
+        // cli.rs -> util is between collapsed modules
+        assert!(graph
+            .edges
+            .iter()
+            .all(|e| !(e.from == summary("src") && e.to == summary("src/util"))));
+        assert_eq!(graph.edges.iter().filter(|e| e.is_cut()).count(), 2);
+    }
+
+    #[test]
+    fn test_focus_counts_cut_edges() {
+        let dependencies = BTreeMap::from([
+            (
+                "app/core.rs".to_string(),
+                vec!["lib/a.rs".to_string(), "lib/b.rs".to_string()],
+            ),
+            ("lib/a.rs".to_string(), vec!["vendor/x.rs".to_string()]),
+            (
+                "lib/b.rs".to_string(),
+                vec!["vendor/y.rs".to_string(), "vendor/z.rs".to_string()],
+            ),
+        ]);
+        let graph = select(&dependencies, &FocusGlob::new("app/*").unwrap());
+        let cut: Vec<_> = graph.edges.iter().filter(|e| e.is_cut()).collect();
+        assert_eq!(cut.len(), 2);
+        assert_eq!(cut[0].to, FocusNode::Summary("vendor".to_string()));
+        assert_eq!(cut.iter().map(|e| e.count).sum::<usize>(), 3);
+        assert_eq!(graph.collapsed["vendor"], 3);
+    }
+}
@@ -0,0 +1,420 @@
+//! CI gating on cross-reference gaps
+//!
+//! `--fail-on-gaps` compares gap counts per kind, or per severity, against
+//! limits after cross-referencing. `--fail-on-new-gaps` does the same but skips gaps that
+//! already exist in a baseline `analysis.json`, so legacy debt doesn't block
+//! every change.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use super::analyzer::{Gap, GapKind, Severity};
+
+/// Process exit code when gaps exceed their limits. Stable: CI scripts rely on it.
+pub const EXIT_GAPS_EXCEEDED: i32 = 3;
+
+/// Maximum allowed gaps per kind, and per severity counting the gaps at
+/// that severity or above. An empty spec allows zero gaps of any kind.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GapThresholds {
+    limits: BTreeMap<&'static str, usize>,
+}
+
+impl GapThresholds {
+    /// Limit for a kind, or `None` if the kind is not checked
+    fn limit(&self, kind: GapKind) -> Option<usize> {
+        if self.limits.is_empty() {
+            Some(0)
+        } else {
+            self.limits.get(kind.name()).copied()
+        }
+    }
+
+    /// Limit for gaps at `severity` or above, or `None` if not checked
+    fn severity_limit(&self, severity: Severity) -> Option<usize> {
+        self.limits.get(severity.name()).copied()
+    }
+}
+
+impl FromStr for GapThresholds {
+    type Err = String;
+
+    /// Parse `kind[:max],severity[:max]`; an entry without a count allows none
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let mut limits = BTreeMap::new();
+
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (name, max) = match entry.split_once(':') {
+                Some((name, max)) => (
+                    name.trim(),
+                    max.trim()
+                        .parse()
+                        .map_err(|_| format!("invalid limit `{}` for `{}`", max, name))?,
+                ),
+                None => (entry, 0),
+            };
+            let name = GapKind::from_name(name)
+                .map(GapKind::name)
+                .or_else(|| Severity::from_name(name).map(Severity::name))
+                .ok_or_else(|| {
+                    let known: Vec<_> = GapKind::ALL
+                        .iter()
+                        .map(|k| k.name())
+                        .chain(Severity::ALL.iter().map(|s| s.name()))
+                        .collect();
+                    format!(
+                        "unknown gap kind or severity `{}`; expected one of {}",
+                        name,
+                        known.join(", ")
+                    )
+                })?;
+            limits.insert(name, max);
+        }
+
+        Ok(Self { limits })
+    }
+}
+
+/// Gaps from a previous run, identified by kind, description and file with
+/// paths made relative so checkouts in different directories compare equal
+#[derive(Debug, Default)]
+pub struct Baseline {
+    gaps: HashSet<(String, String, String)>,
+}
+
+#[derive(Deserialize)]
+struct BaselineFile {
+    modules: Vec<BaselineModule>,
+    cross_reference: BaselineCrossRef,
+}
+
+#[derive(Deserialize)]
+struct BaselineModule {
+    path: String,
+}
+
+#[derive(Deserialize)]
+struct BaselineCrossRef {
+    gaps: Vec<BaselineGap>,
+}
+
+#[derive(Deserialize)]
+struct BaselineGap {
+    kind: String,
+    description: String,
+    location: Option<String>,
+}
+
+impl Baseline {
+    /// Load the gaps from an `analysis.json` written by `--format json`
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read baseline {}", path.display()))?;
+        let file: BaselineFile = serde_json::from_str(&content).with_context(|| {
+            format!(
+                "Baseline {} is not an analysis.json from `cda analyze --format json`",
+                path.display()
+            )
+        })?;
+
+        let root = legacy_root(file.modules.iter().map(|m| m.path.as_str()));
+        Ok(Self {
+            gaps: file
+                .cross_reference
+                .gaps
+                .into_iter()
+                .map(|g| key(&g.kind, &g.description, g.location.as_deref(), &root))
+                .collect(),
+        })
+    }
+
+    /// Gaps that are not in the baseline. `root` is the [`legacy_root`] of
+    /// the current run's module paths.
+    pub fn new_gaps<'a>(&self, gaps: &'a [Gap], root: &str) -> Vec<&'a Gap> {
+        gaps.iter()
+            .filter(|g| {
+                !self.gaps.contains(&key(
+                    g.kind.name(),
+                    &g.description,
+                    g.location.as_deref(),
+                    root,
+                ))
+            })
+            .collect()
+    }
+}
+
+/// Longest shared directory of the given paths, with a trailing separator
+pub fn common_root<'a>(paths: impl Iterator<Item = &'a str>) -> String {
+    let mut root: Option<&str> = None;
+    for path in paths {
+        let dir = path.rfind('/').map(|i| &path[..=i]).unwrap_or("");
+        root = Some(match root {
+            None => dir,
+            Some(root) => {
+                let shared = root
+                    .char_indices()
+                    .zip(dir.chars())
+                    .take_while(|((_, a), b)| a == b)
+                    .filter(|((_, a), _)| *a == '/')
+                    .last()
+                    .map(|((i, _), _)| i + 1)
+                    .unwrap_or(0);
+                &root[..shared]
+            }
+        });
+    }
+    root.unwrap_or_default().to_string()
+}
+
+/// What to strip from module paths to make them root-relative: nothing for
+/// the root-relative paths cda stores, the [`common_root`] for absolute
+/// paths older versions stored. Stripping a common root from relative paths
+/// would key a `--module` run differently from a run over the whole tree.
+pub fn legacy_root<'a>(paths: impl Iterator<Item = &'a str>) -> String {
+    let paths: Vec<&str> = paths.collect();
+    let absolute = |p: &&str| p.starts_with('/') || Path::new(p).is_absolute();
+    if !paths.is_empty() && paths.iter().all(absolute) {
+        common_root(paths.into_iter())
+    } else {
+        String::new()
+    }
+}
+
+/// Identity of a gap: root-relative paths, and no line number so gaps
+/// survive unrelated edits above them
+fn key(
+    kind: &str,
+    description: &str,
+    location: Option<&str>,
+    root: &str,
+) -> (String, String, String) {
+    let relative = |s: &str| {
+        if root.is_empty() {
+            s.to_string()
+        } else {
+            s.replace(root, "")
+        }
+    };
+
+    let location = location.unwrap_or_default();
+    let file = match location.rsplit_once(':') {
+        Some((file, line)) if line.chars().all(|c| c.is_ascii_digit()) => file,
+        _ => location,
+    };
+
+    (kind.to_string(), relative(description), relative(file))
+}
+
+/// A gap kind or severity over its limit, with the gaps that were counted
+#[derive(Debug)]
+pub struct Violation<'a> {
+    /// [`GapKind::name`] or [`Severity::name`]
+    pub name: &'static str,
+    pub max: usize,
+    pub gaps: Vec<&'a Gap>,
+}
+
+/// Count gaps per checked severity, most severe first, then per checked kind
+pub fn evaluate<'a>(gaps: &[&'a Gap], thresholds: &GapThresholds) -> Vec<Violation<'a>> {
+    let check = |name, max: Option<usize>, counts: &dyn Fn(&Gap) -> bool| {
+        let max = max?;
+        let counted: Vec<&Gap> = gaps.iter().copied().filter(|g| counts(g)).collect();
+        (counted.len() > max).then_some(Violation {
+            name,
+            max,
+            gaps: counted,
+        })
+    };
+    let severities = Severity::ALL.iter().filter_map(|&severity| {
+        check(severity.name(), thresholds.severity_limit(severity), &|g| {
+            g.severity >= severity
+        })
+    });
+    let kinds = GapKind::ALL
+        .iter()
+        .filter_map(|&kind| check(kind.name(), thresholds.limit(kind), &|g| g.kind == kind));
+    severities.chain(kinds).collect()
+}
+
+/// Print offending gaps grouped by kind or severity
+pub fn print_violations(violations: &[Violation], new_only: bool) {
+    let qualifier = if new_only { "new " } else { "" };
+    for violation in violations {
+        println!(
+            "\n{}: {} {}gaps (max {})",
+            violation.name,
+            violation.gaps.len(),
+            qualifier,
+            violation.max
+        );
+        for gap in &violation.gaps {
+            let severity = gap.severity.name();
+            match &gap.location {
+                Some(location) => println!("  {}  {}  {}", severity, location, gap.description),
+                None => println!("  {}  {}", severity, gap.description),
+            }
+        }
+    }
+}
+
+/// Returned when gaps exceed their limits; `main` exits with [`EXIT_GAPS_EXCEEDED`]
+#[derive(Debug)]
+pub struct GapsExceeded {
+    pub summary: String,
+}
+
+impl fmt::Display for GapsExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Gap check failed: {}", self.summary)
+    }
+}
+
+impl std::error::Error for GapsExceeded {}
+
+impl GapsExceeded {
+    pub fn new(violations: &[Violation]) -> Self {
+        Self {
+            summary: violations
+                .iter()
+                .map(|v| format!("{} {} (max {})", v.gaps.len(), v.name, v.max))
+                .collect::<Vec<_>>()
+                .join(", "),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gap(kind: GapKind, description: &str, location: &str) -> Gap {
+        Gap {
+            kind,
+            severity: kind.default_severity(),
+            description: description.to_string(),
+            location: Some(location.to_string()),
+            owner: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_thresholds() {
+        let t: GapThresholds = "missing_docs:5, circular_dependency".parse().unwrap();
+        assert_eq!(t.limit(GapKind::MissingDocumentation), Some(5));
+        assert_eq!(t.limit(GapKind::CircularDependency), Some(0));
+        assert_eq!(t.limit(GapKind::DeadCode), None);
+
+        let all: GapThresholds = "".parse().unwrap();
+        assert_eq!(all.limit(GapKind::DeadCode), Some(0));
+
+        assert!("missing_doc:1".parse::<GapThresholds>().is_err());
+        assert!("missing_docs:many".parse::<GapThresholds>().is_err());
+
+        let t: GapThresholds = "error, warning:3".parse().unwrap();
+        assert_eq!(t.severity_limit(Severity::Error), Some(0));
+        assert_eq!(t.severity_limit(Severity::Warning), Some(3));
+        assert_eq!(t.severity_limit(Severity::Info), None);
+        assert_eq!(t.limit(GapKind::CircularDependency), None);
+    }
+
+    #[test]
+    fn test_evaluate_severities() {
+        let mut cycle = gap(GapKind::CircularDependency, "Cycle", "src/main.rs");
+        cycle.severity = Severity::Error;
+        let gaps = [
+            cycle,
+            gap(GapKind::CircularDependency, "Cycle", "src/a.rs"),
+            gap(GapKind::MissingDocumentation, "Undocumented", "src/a.rs:1"),
+        ];
+        let all: Vec<&Gap> = gaps.iter().collect();
+
+        let violations = evaluate(&all, &"error".parse().unwrap());
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].name, "error");
+        assert_eq!(violations[0].gaps.len(), 1);
+
+        // A severity counts the gaps above it too
+        let violations = evaluate(&all, &"warning:1,info:3".parse().unwrap());
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].name, "warning");
+        assert_eq!(violations[0].gaps.len(), 2);
+        assert_eq!(GapsExceeded::new(&violations).summary, "2 warning (max 1)");
+    }
+
+    #[test]
+    fn test_common_root() {
+        let paths = ["/repo/src/a.rs", "/repo/src/core/b.rs", "/repo/build.rs"];
+        assert_eq!(common_root(paths.into_iter()), "/repo/");
+        assert_eq!(common_root(["/repo/src/a.rs"].into_iter()), "/repo/src/");
+        assert_eq!(common_root(["/ab/x.rs", "/ac/y.rs"].into_iter()), "/");
+        assert_eq!(common_root(std::iter::empty()), "");
+    }
+
+    #[test]
+    fn test_legacy_root() {
+        let absolute = ["/repo/src/api/a.rs", "/repo/src/api/b.rs"];
+        assert_eq!(legacy_root(absolute.into_iter()), "/repo/src/api/");
+        // A --module run keeps the paths a full run has
+        let relative = ["src/api/a.rs", "src/api/b.rs"];
+        assert_eq!(legacy_root(relative.into_iter()), "");
+        assert_eq!(legacy_root(std::iter::empty()), "");
+    }
+
+    #[test]
+    fn test_evaluate_thresholds_and_baseline() {
+        let gaps = vec![
+            gap(
+                GapKind::MissingDocumentation,
+                "Public fn `a` has no documentation",
+                "/ci/pr/src/a.rs:3",
+            ),
+            gap(
+                GapKind::MissingDocumentation,
+                "Public fn `b` has no documentation",
+                "/ci/pr/src/b.rs:9",
+            ),
+            gap(
+                GapKind::CircularDependency,
+                "Circular dependency: /ci/pr/src/a.rs -> /ci/pr/src/b.rs -> /ci/pr/src/a.rs",
+                "/ci/pr/src/a.rs",
+            ),
+        ];
+        let all: Vec<&Gap> = gaps.iter().collect();
+
+        let t: GapThresholds = "missing_docs:1".parse().unwrap();
+        let violations = evaluate(&all, &t);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].name, "missing_docs");
+        assert_eq!(violations[0].gaps.len(), 2);
+
+        assert_eq!(evaluate(&all, &"".parse().unwrap()).len(), 2);
+
+        // Baseline from another checkout: `a` and the cycle already existed,
+        // `a` on another line
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("analysis.json");
+        std::fs::write(
+            &path,
+            serde_json::json!({
+                "modules": [{ "path": "/ci/main/src/a.rs" }, { "path": "/ci/main/src/b.rs" }],
+                "cross_reference": { "gaps": [
+                    { "kind": "missing_docs", "description": "Public fn `a` has no documentation", "location": "/ci/main/src/a.rs:1" },
+                    { "kind": "circular_dependency", "description": "Circular dependency: /ci/main/src/a.rs -> /ci/main/src/b.rs -> /ci/main/src/a.rs", "location": "/ci/main/src/a.rs" },
+                ]}
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let baseline = Baseline::from_path(&path).unwrap();
+
+        let new = baseline.new_gaps(&gaps, "/ci/pr/src/");
+        assert_eq!(new.len(), 1);
+        assert!(new[0].description.contains("`b`"));
+        assert!(evaluate(&new, &t).is_empty());
+    }
+}
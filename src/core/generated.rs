@@ -0,0 +1,234 @@
+//! Spotting minified bundles and other machine-generated source files
+//!
+//! A checked-in bundle is usually one enormous line: tree-sitter takes
+//! seconds over it and it does not fit in an LLM prompt, while documenting it
+//! tells nobody anything. Such files get a synthesized summary instead of a
+//! full analysis. When the bundle has a `sourceMappingURL`, the source map's
+//! `sources` name what it was built from.
+
+use std::path::Path;
+
+use super::discovery::Language;
+
+/// Files smaller than this are never treated as minified
+const MIN_SIZE: usize = 1024;
+/// A line this long is pathological whatever it contains
+const MAX_LINE_LENGTH: usize = 20_000;
+/// Lines this long are minified when the file is also dense
+const LONG_LINE: usize = 500;
+/// Share of whitespace below which code counts as dense. Formatted code sits
+/// around 15-30%, minified code at a few percent.
+const DENSE_WHITESPACE: f64 = 0.08;
+
+/// A file that gets a synthesized summary instead of a full analysis
+#[derive(Debug, PartialEq)]
+pub struct Generated {
+    /// Stands in for the module summary
+    pub summary: String,
+    /// Why the file was not analyzed, for warnings
+    pub reason: String,
+}
+
+/// Check `content` for signs of minification. `io_path` is where the file
+/// lives, used to find a source map next to it.
+pub fn detect(io_path: &Path, content: &str, language: Language) -> Option<Generated> {
+    let reason = minified_reason(content)?;
+
+    let kind = match language {
+        Language::JavaScript | Language::TypeScript => "bundle",
+        _ => "file",
+    };
+    let mut summary = format!(
+        "Minified {:?} {}, {} KB",
+        language,
+        kind,
+        content.len().div_ceil(1024)
+    );
+    if let Some(url) = source_map_url(content) {
+        match source_map_origin(io_path, url) {
+            Some(origin) => summary.push_str(&format!(
+                ", likely generated from {} based on sourceMappingURL",
+                origin
+            )),
+            None if url.starts_with("data:") => summary.push_str(", with an inline source map"),
+            None => summary.push_str(&format!(", source map `{}`", url)),
+        }
+    }
+
+    Some(Generated { summary, reason })
+}
+
+fn minified_reason(content: &str) -> Option<String> {
+    if content.len() < MIN_SIZE {
+        return None;
+    }
+
+    let longest = content.lines().map(str::len).max().unwrap_or(0);
+    let whitespace =
+        content.bytes().filter(u8::is_ascii_whitespace).count() as f64 / content.len() as f64;
+
+    if longest >= MAX_LINE_LENGTH || (longest >= LONG_LINE && whitespace < DENSE_WHITESPACE) {
+        Some(format!(
+            "looks minified (longest line {} characters, {:.0}% whitespace); \
+             summarized instead of analyzed",
+            longest,
+            whitespace * 100.0
+        ))
+    } else {
+        None
+    }
+}
+
+/// The last `sourceMappingURL` comment, as emitted by bundlers
+fn source_map_url(content: &str) -> Option<&str> {
+    let start = content.rfind("sourceMappingURL=")? + "sourceMappingURL=".len();
+    let url = content[start..]
+        .split(|c: char| c.is_whitespace() || c == '*')
+        .next()?;
+    (!url.is_empty()).then_some(url)
+}
+
+/// What the source map at `url` (relative to the bundle) was built from:
+/// its one source, or how many sources share a directory. `None` for inline
+/// or unreadable maps.
+fn source_map_origin(io_path: &Path, url: &str) -> Option<String> {
+    if url.contains("://") || url.starts_with("data:") {
+        return None;
+    }
+    let map_path = io_path.parent()?.join(url);
+    let map: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(map_path).ok()?).ok()?;
+    let sources: Vec<String> = map["sources"]
+        .as_array()?
+        .iter()
+        .filter_map(|s| s.as_str())
+        .map(clean_source)
+        .collect();
+
+    // Bundled dependencies say little about where the bundle comes from
+    let own: Vec<&String> = sources
+        .iter()
+        .filter(|s| !s.contains("node_modules/"))
+        .collect();
+    let sources = if own.is_empty() {
+        sources.iter().collect()
+    } else {
+        own
+    };
+
+    match sources.as_slice() {
+        [] => None,
+        [only] => Some(format!("`{}`", only)),
+        [first, rest @ ..] => {
+            let mut dir = first.rsplit_once('/').map_or("", |(dir, _)| dir);
+            for source in rest {
+                while !dir.is_empty() && !source.starts_with(&format!("{}/", dir)) {
+                    dir = dir.rsplit_once('/').map_or("", |(parent, _)| parent);
+                }
+            }
+            if dir.is_empty() {
+                Some(format!("`{}` and {} other files", first, rest.len()))
+            } else {
+                Some(format!("{} files under `{}/`", sources.len(), dir))
+            }
+        }
+    }
+}
+
+/// Strip bundler prefixes such as `webpack://app/` and leading `./` or `../`
+fn clean_source(source: &str) -> String {
+    let mut source = source;
+    if let Some((_, rest)) = source.split_once("://") {
+        source = rest.trim_start_matches('/');
+        // webpack puts the project name first: webpack://my-app/./src/index.ts
+        if let Some((_, path)) = source.split_once("/./") {
+            source = path;
+        }
+    }
+    while let Some(rest) = source
+        .strip_prefix("./")
+        .or_else(|| source.strip_prefix("../"))
+    {
+        source = rest;
+    }
+    source.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bundle(trailer: &str) -> String {
+        let body: String = (0..200)
+            .map(|i| format!("function f{i}(a,b){{return a+b*{i}}};"))
+            .collect();
+        format!("{}\n{}", body, trailer)
+    }
+
+    #[test]
+    fn test_formatted_code_is_not_minified() {
+        let content: String = (0..200)
+            .map(|i| format!("export function f{i}(a, b) {{\n  return a + b * {i};\n}}\n\n"))
+            .collect();
+        assert_eq!(
+            detect(Path::new("f.js"), &content, Language::JavaScript),
+            None
+        );
+
+        // One long line in otherwise ordinary code is not enough
+        let content = format!("const TABLE = \"{}\";\n{}", "x ".repeat(400), content);
+        assert_eq!(
+            detect(Path::new("f.js"), &content, Language::JavaScript),
+            None
+        );
+    }
+
+    #[test]
+    fn test_minified_bundle_with_source_map() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("app.js.map"),
+            r#"{"version":3,"sources":["webpack://my-app/./src/index.ts","webpack://my-app/./src/ui/button.ts","webpack://my-app/./node_modules/react/index.js"],"mappings":""}"#,
+        )
+        .unwrap();
+        let content = bundle("//# sourceMappingURL=app.js.map");
+
+        let generated = detect(&dir.path().join("app.js"), &content, Language::JavaScript).unwrap();
+        assert_eq!(
+            generated.summary,
+            "Minified JavaScript bundle, 7 KB, likely generated from 2 files under `src/` \
+             based on sourceMappingURL"
+        );
+        assert!(
+            generated.reason.starts_with("looks minified (longest line"),
+            "{}",
+            generated.reason
+        );
+    }
+
+    #[test]
+    fn test_minified_bundle_without_readable_map() {
+        let content = bundle("//# sourceMappingURL=missing.js.map");
+        let generated = detect(Path::new("dist/app.js"), &content, Language::JavaScript).unwrap();
+        assert!(generated.summary.ends_with(", source map `missing.js.map`"));
+
+        let content =
+            bundle("//# sourceMappingURL=data:application/json;base64,eyJ2ZXJzaW9uIjozfQ==");
+        let generated = detect(Path::new("dist/app.js"), &content, Language::JavaScript).unwrap();
+        assert!(generated.summary.ends_with(", with an inline source map"));
+
+        let generated =
+            detect(Path::new("dist/app.js"), &bundle(""), Language::JavaScript).unwrap();
+        assert_eq!(generated.summary, "Minified JavaScript bundle, 7 KB");
+    }
+
+    #[test]
+    fn test_clean_source() {
+        assert_eq!(
+            clean_source("webpack://my-app/./src/index.ts"),
+            "src/index.ts"
+        );
+        assert_eq!(clean_source("../../src/main.ts"), "src/main.ts");
+        assert_eq!(clean_source("src/lib.ts"), "src/lib.ts");
+    }
+}
@@ -0,0 +1,141 @@
+//! Changed-file listing for `--changed-only` and rename detection, by
+//! shelling out to git
+
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+pub(crate) fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .context("Failed to run git; is it installed and on PATH?")?;
+
+    if !output.status.success() {
+        anyhow::bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Absolute paths of files that differ from `git_ref` in the working tree,
+/// including untracked files that aren't ignored
+pub fn changed_files(dir: &Path, git_ref: &str) -> Result<HashSet<PathBuf>> {
+    let toplevel = git(dir, &["rev-parse", "--show-toplevel"]).map_err(|_| {
+        anyhow::anyhow!(
+            "--changed-only needs a git repository, but {} is not inside one",
+            dir.display()
+        )
+    })?;
+    let toplevel = PathBuf::from(toplevel.trim());
+
+    git(
+        &toplevel,
+        &[
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("{}^{{commit}}", git_ref),
+        ],
+    )
+    .map_err(|_| anyhow::anyhow!("Unknown git ref `{}`", git_ref))?;
+
+    let diff = git(
+        &toplevel,
+        &["diff", "--name-only", "--no-renames", git_ref, "--"],
+    )?;
+    let untracked = git(&toplevel, &["ls-files", "--others", "--exclude-standard"])?;
+
+    Ok(diff
+        .lines()
+        .chain(untracked.lines())
+        .filter(|line| !line.is_empty())
+        .map(|line| toplevel.join(line))
+        .collect())
+}
+
+/// Commits searched for renames, newest first
+const RENAME_HISTORY: usize = 50;
+
+/// Renames git detects, as absolute `(from, to)` paths: uncommitted ones
+/// against HEAD, then those in the last [`RENAME_HISTORY`] commits
+pub fn renames(dir: &Path) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let toplevel = PathBuf::from(git(dir, &["rev-parse", "--show-toplevel"])?.trim());
+    let uncommitted = git(&toplevel, &["diff", "--name-status", "-M", "HEAD", "--"])?;
+    let committed = git(
+        &toplevel,
+        &[
+            "log",
+            "-M",
+            "--diff-filter=R",
+            "--name-status",
+            "--format=",
+            &format!("--max-count={}", RENAME_HISTORY),
+        ],
+    )?;
+
+    Ok(uncommitted
+        .lines()
+        .chain(committed.lines())
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            if !fields.next()?.starts_with('R') {
+                return None;
+            }
+            Some((toplevel.join(fields.next()?), toplevel.join(fields.next()?)))
+        })
+        .collect())
+}
+
+/// The commit checked out at `dir`, or `None` outside a git repository
+pub fn head_commit(dir: &Path) -> Option<String> {
+    git(dir, &["rev-parse", "HEAD"])
+        .ok()
+        .map(|s| s.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=t", "-c", "user.email=t@t"])
+            .args(args)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {:?}", args);
+    }
+
+    #[test]
+    fn test_changed_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        run(&root, &["init", "-q"]);
+        std::fs::write(root.join("a.rs"), "fn a() {}").unwrap();
+        std::fs::write(root.join("b.rs"), "fn b() {}").unwrap();
+        run(&root, &["add", "."]);
+        run(&root, &["commit", "-qm", "init"]);
+
+        std::fs::write(root.join("a.rs"), "fn a() { }").unwrap();
+        std::fs::write(root.join("c.rs"), "fn c() {}").unwrap();
+
+        let changed = changed_files(&root, "HEAD").unwrap();
+        assert_eq!(
+            changed,
+            HashSet::from([root.join("a.rs"), root.join("c.rs")])
+        );
+
+        let err = changed_files(&root, "nope").unwrap_err().to_string();
+        assert!(err.contains("Unknown git ref `nope`"));
+
+        let plain = tempfile::tempdir().unwrap();
+        let err = changed_files(plain.path(), "HEAD").unwrap_err().to_string();
+        assert!(err.contains("needs a git repository"));
+    }
+}
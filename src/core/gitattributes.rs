@@ -0,0 +1,224 @@
+//! Linguist overrides from `.gitattributes`
+//!
+//! GitHub's linguist reads `linguist-generated`, `linguist-vendored`,
+//! `linguist-documentation` and `linguist-language` from `.gitattributes`,
+//! and discovery honours the same attributes. Patterns follow gitattributes
+//! rules: gitignore syntax, except that negative patterns are ignored and a
+//! pattern matching a directory does not apply to the files inside it (write
+//! `dir/**`). Later lines override earlier ones, and files in deeper
+//! directories override those above them.
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+pub const FILE_NAME: &str = ".gitattributes";
+
+/// The linguist attributes in effect for one file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Linguist {
+    pub generated: bool,
+    pub vendored: bool,
+    pub documentation: bool,
+    /// The `linguist-language` value, as written
+    pub language: Option<String>,
+}
+
+/// One pattern line and the attributes it assigns. A `None` value is
+/// `!attr`, which puts the attribute back to unspecified.
+struct Rule {
+    /// Directory of the `.gitattributes`; the rule only covers files below it
+    base: PathBuf,
+    matcher: Gitignore,
+    assignments: Vec<(String, Option<String>)>,
+}
+
+/// Every linguist rule from a set of `.gitattributes` files
+#[derive(Default)]
+pub struct GitAttributes {
+    rules: Vec<Rule>,
+}
+
+impl GitAttributes {
+    /// Read `files`; they apply from the shallowest directory down, whatever
+    /// order they are given in. Unreadable files are skipped.
+    pub fn load(files: &[PathBuf]) -> Self {
+        let mut files: Vec<&PathBuf> = files.iter().collect();
+        files.sort_by_key(|f| f.components().count());
+
+        let mut attributes = Self::default();
+        for file in files {
+            match fs::read_to_string(file) {
+                Ok(text) => {
+                    let base = file.parent().unwrap_or(Path::new(""));
+                    attributes.add(base, &text);
+                }
+                Err(e) => debug!("Skipping {}: {}", file.display(), e),
+            }
+        }
+        attributes
+    }
+
+    /// Add the rules of a `.gitattributes` in `base`, after those already read
+    pub fn add(&mut self, base: &Path, text: &str) {
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with("[attr]") {
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let Some(pattern) = fields.next() else {
+                continue;
+            };
+            if pattern.starts_with('!') {
+                debug!("Negative patterns are ignored in {}: {}", FILE_NAME, line);
+                continue;
+            }
+
+            let assignments: Vec<_> = fields
+                .map(parse_assignment)
+                .filter(|(name, _)| name.starts_with("linguist-"))
+                .collect();
+            if assignments.is_empty() {
+                continue;
+            }
+
+            let mut builder = GitignoreBuilder::new(base);
+            if let Err(e) = builder.add_line(None, pattern) {
+                debug!("Bad pattern in {}: {}", FILE_NAME, e);
+                continue;
+            }
+            if let Ok(matcher) = builder.build() {
+                self.rules.push(Rule {
+                    base: base.to_path_buf(),
+                    matcher,
+                    assignments,
+                });
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The linguist attributes of the file at `path`
+    pub fn linguist(&self, path: &Path) -> Linguist {
+        let mut values: HashMap<&str, Option<&str>> = HashMap::new();
+        for rule in &self.rules {
+            if !path.starts_with(&rule.base) || !rule.matcher.matched(path, false).is_ignore() {
+                continue;
+            }
+            for (name, value) in &rule.assignments {
+                values.insert(name, value.as_deref());
+            }
+        }
+
+        let is_set = |name: &str| {
+            values
+                .get(name)
+                .copied()
+                .flatten()
+                .is_some_and(|v| v != "false")
+        };
+        Linguist {
+            generated: is_set("linguist-generated"),
+            vendored: is_set("linguist-vendored"),
+            documentation: is_set("linguist-documentation"),
+            language: values
+                .get("linguist-language")
+                .copied()
+                .flatten()
+                .map(String::from),
+        }
+    }
+}
+
+/// `attr` sets an attribute, `-attr` unsets it, `attr=value` gives it a
+/// value and `!attr` makes it unspecified again
+fn parse_assignment(field: &str) -> (String, Option<String>) {
+    if let Some(name) = field.strip_prefix('-') {
+        return (name.to_string(), Some("false".to_string()));
+    }
+    if let Some(name) = field.strip_prefix('!') {
+        return (name.to_string(), None);
+    }
+    match field.split_once('=') {
+        Some((name, value)) => (name.to_string(), Some(value.to_string())),
+        None => (field.to_string(), Some("true".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attributes(text: &str) -> GitAttributes {
+        let mut attributes = GitAttributes::default();
+        attributes.add(Path::new("/repo"), text);
+        attributes
+    }
+
+    fn linguist(attributes: &GitAttributes, path: &str) -> Linguist {
+        attributes.linguist(&Path::new("/repo").join(path))
+    }
+
+    #[test]
+    fn test_last_match_wins_and_negation() {
+        let attributes = attributes(
+            "# Vendored code, except our fork\n\
+             vendor/** linguist-vendored\n\
+             vendor/ours/** -linguist-vendored\n\
+             vendor/patched/** !linguist-vendored\n\
+             !vendor/keep.c linguist-vendored=false\n\
+             *.pb.go linguist-generated=true\n\
+             api.pb.go linguist-generated=false\n",
+        );
+        assert!(linguist(&attributes, "vendor/lib/a.c").vendored);
+        assert!(!linguist(&attributes, "vendor/ours/a.c").vendored);
+        assert!(!linguist(&attributes, "vendor/patched/a.c").vendored);
+        // The negative pattern line is ignored
+        assert!(linguist(&attributes, "vendor/keep.c").vendored);
+        assert!(linguist(&attributes, "src/types.pb.go").generated);
+        assert!(!linguist(&attributes, "src/api.pb.go").generated);
+        assert_eq!(linguist(&attributes, "src/main.go"), Linguist::default());
+    }
+
+    #[test]
+    fn test_directory_patterns() {
+        let attributes = attributes(
+            "docs/ linguist-documentation\n\
+             guide/** linguist-documentation\n\
+             /build/*.js linguist-generated\n",
+        );
+        // A directory pattern doesn't reach the files inside
+        assert!(!linguist(&attributes, "docs/intro.rs").documentation);
+        assert!(linguist(&attributes, "guide/intro.rs").documentation);
+        assert!(linguist(&attributes, "guide/deep/more.rs").documentation);
+        assert!(linguist(&attributes, "build/app.js").generated);
+        assert!(!linguist(&attributes, "build/sub/app.js").generated);
+        assert!(!linguist(&attributes, "web/build/app.js").generated);
+    }
+
+    #[test]
+    fn test_nested_files_override_and_language() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("scripts");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(
+            dir.path().join(FILE_NAME),
+            "*.inc linguist-language=Rust\n*.gen.ts linguist-generated\n",
+        )
+        .unwrap();
+        fs::write(nested.join(FILE_NAME), "*.inc linguist-language=Shell\n").unwrap();
+
+        // Deeper files apply last, even when listed first
+        let attributes = GitAttributes::load(&[nested.join(FILE_NAME), dir.path().join(FILE_NAME)]);
+        let language = |path: &str| attributes.linguist(&dir.path().join(path)).language;
+        assert_eq!(language("src/table.inc").as_deref(), Some("Rust"));
+        assert_eq!(language("scripts/setup.inc").as_deref(), Some("Shell"));
+        assert!(attributes.linguist(&nested.join("api.gen.ts")).generated);
+    }
+}
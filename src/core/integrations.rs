@@ -0,0 +1,663 @@
+//! Third-party services a codebase integrates with
+//!
+//! External dependencies are grouped by what they connect to (databases,
+//! HTTP clients, cloud SDKs, ...). A built-in table covers the common
+//! packages of each ecosystem, so static runs get most of them; `--mode deep`
+//! asks the LLM about the rest in one call.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use tracing::{debug, info, warn};
+
+use super::analyzer::{package_name, Analysis};
+use super::discovery::Language;
+use crate::llm::{LlmConfig, LlmProvider, Message, Role};
+
+/// Most packages sent to the LLM, the most used first
+const MAX_INFERRED: usize = 200;
+
+/// What kind of service a package talks to. Serialized as [`Category::name`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[non_exhaustive]
+pub enum Category {
+    Database,
+    Cache,
+    /// Message queues and brokers
+    Messaging,
+    Search,
+    /// Cloud provider SDKs and hosted storage
+    CloudSdk,
+    HttpClient,
+    Auth,
+    Payments,
+    Email,
+    /// Tracing, metrics and error reporting
+    Telemetry,
+}
+
+const CATEGORIES: &[(Category, &str, &str)] = &[
+    (Category::Database, "database", "Databases"),
+    (Category::Cache, "cache", "Caches"),
+    (Category::Messaging, "messaging", "Messaging"),
+    (Category::Search, "search", "Search"),
+    (Category::CloudSdk, "cloud-sdk", "Cloud SDKs"),
+    (Category::HttpClient, "http-client", "HTTP clients"),
+    (Category::Auth, "auth", "Authentication"),
+    (Category::Payments, "payments", "Payments"),
+    (Category::Email, "email", "Email"),
+    (Category::Telemetry, "telemetry", "Telemetry"),
+];
+
+impl Category {
+    pub fn name(self) -> &'static str {
+        CATEGORIES
+            .iter()
+            .find(|(c, _, _)| *c == self)
+            .map(|(_, name, _)| *name)
+            .unwrap_or("unknown")
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        CATEGORIES
+            .iter()
+            .find(|(_, n, _)| *n == name)
+            .map(|(c, _, _)| *c)
+    }
+
+    /// Heading for the CODEBASE.md section
+    pub fn label(self) -> &'static str {
+        CATEGORIES
+            .iter()
+            .find(|(c, _, _)| *c == self)
+            .map(|(_, _, label)| *label)
+            .unwrap_or("Other")
+    }
+}
+
+/// An external package that connects to a third-party service
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Integration {
+    pub package: String,
+    pub category: Category,
+    /// The service, e.g. `PostgreSQL`; `None` for generic clients
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service: Option<String>,
+    /// Non-test modules that import the package
+    pub modules: Vec<String>,
+    /// Classified by the LLM rather than the built-in table
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub inferred: bool,
+}
+
+type KnownEntry = (&'static str, Category, &'static str);
+
+/// Built-in classification: package (or package prefix) and the service it
+/// talks to, empty for generic clients. Rust crates are written as they are
+/// imported, with underscores.
+const KNOWN: &[KnownEntry] = &[
+    // Rust
+    ("sqlx", Category::Database, "SQL databases"),
+    ("diesel", Category::Database, "SQL databases"),
+    ("sea_orm", Category::Database, "SQL databases"),
+    ("tokio_postgres", Category::Database, "PostgreSQL"),
+    ("deadpool_postgres", Category::Database, "PostgreSQL"),
+    ("rusqlite", Category::Database, "SQLite"),
+    ("mysql_async", Category::Database, "MySQL"),
+    ("bb8_redis", Category::Cache, "Redis"),
+    ("deadpool_redis", Category::Cache, "Redis"),
+    ("meilisearch_sdk", Category::Search, "Meilisearch"),
+    ("lapin", Category::Messaging, "RabbitMQ"),
+    ("rdkafka", Category::Messaging, "Kafka"),
+    ("async_nats", Category::Messaging, "NATS"),
+    ("reqwest", Category::HttpClient, ""),
+    ("ureq", Category::HttpClient, ""),
+    ("surf", Category::HttpClient, ""),
+    ("isahc", Category::HttpClient, ""),
+    ("aws_config", Category::CloudSdk, "AWS"),
+    ("aws_sdk_s3", Category::CloudSdk, "AWS S3"),
+    ("aws_sdk_dynamodb", Category::CloudSdk, "AWS DynamoDB"),
+    ("aws_sdk_sqs", Category::CloudSdk, "AWS SQS"),
+    ("aws_sdk_sns", Category::CloudSdk, "AWS SNS"),
+    ("aws_sdk_lambda", Category::CloudSdk, "AWS Lambda"),
+    (
+        "aws_sdk_secretsmanager",
+        Category::CloudSdk,
+        "AWS Secrets Manager",
+    ),
+    ("rusoto_core", Category::CloudSdk, "AWS"),
+    (
+        "google_cloud_storage",
+        Category::CloudSdk,
+        "Google Cloud Storage",
+    ),
+    (
+        "azure_storage_blobs",
+        Category::CloudSdk,
+        "Azure Blob Storage",
+    ),
+    ("oauth2", Category::Auth, "OAuth 2"),
+    ("openidconnect", Category::Auth, "OpenID Connect"),
+    ("async_stripe", Category::Payments, "Stripe"),
+    ("lettre", Category::Email, "SMTP"),
+    (
+        "tracing_opentelemetry",
+        Category::Telemetry,
+        "OpenTelemetry",
+    ),
+    ("prometheus", Category::Telemetry, "Prometheus"),
+    // Rust, JavaScript and Python
+    ("postgres", Category::Database, "PostgreSQL"),
+    ("mongodb", Category::Database, "MongoDB"),
+    ("redis", Category::Cache, "Redis"),
+    ("elasticsearch", Category::Search, "Elasticsearch"),
+    ("jsonwebtoken", Category::Auth, "JWT"),
+    ("stripe", Category::Payments, "Stripe"),
+    ("sentry", Category::Telemetry, "Sentry"),
+    ("opentelemetry", Category::Telemetry, "OpenTelemetry"),
+    // JavaScript and TypeScript
+    ("pg", Category::Database, "PostgreSQL"),
+    ("mysql", Category::Database, "MySQL"),
+    ("mysql2", Category::Database, "MySQL"),
+    ("sqlite3", Category::Database, "SQLite"),
+    ("better-sqlite3", Category::Database, "SQLite"),
+    ("mongoose", Category::Database, "MongoDB"),
+    ("@prisma/client", Category::Database, "SQL databases"),
+    ("typeorm", Category::Database, "SQL databases"),
+    ("sequelize", Category::Database, "SQL databases"),
+    ("knex", Category::Database, "SQL databases"),
+    ("drizzle-orm", Category::Database, "SQL databases"),
+    ("@supabase/supabase-js", Category::Database, "Supabase"),
+    ("ioredis", Category::Cache, "Redis"),
+    ("@elastic/elasticsearch", Category::Search, "Elasticsearch"),
+    ("amqplib", Category::Messaging, "RabbitMQ"),
+    ("kafkajs", Category::Messaging, "Kafka"),
+    ("nats", Category::Messaging, "NATS"),
+    ("bullmq", Category::Messaging, "Redis"),
+    ("axios", Category::HttpClient, ""),
+    ("node-fetch", Category::HttpClient, ""),
+    ("got", Category::HttpClient, ""),
+    ("undici", Category::HttpClient, ""),
+    ("superagent", Category::HttpClient, ""),
+    ("ky", Category::HttpClient, ""),
+    ("aws-sdk", Category::CloudSdk, "AWS"),
+    ("@aws-sdk/client-s3", Category::CloudSdk, "AWS S3"),
+    (
+        "@aws-sdk/client-dynamodb",
+        Category::CloudSdk,
+        "AWS DynamoDB",
+    ),
+    ("@aws-sdk/client-sqs", Category::CloudSdk, "AWS SQS"),
+    ("@aws-sdk", Category::CloudSdk, "AWS"),
+    ("@google-cloud", Category::CloudSdk, "Google Cloud"),
+    ("firebase", Category::CloudSdk, "Firebase"),
+    ("firebase-admin", Category::CloudSdk, "Firebase"),
+    ("@azure", Category::CloudSdk, "Azure"),
+    ("passport", Category::Auth, "Passport"),
+    ("next-auth", Category::Auth, "NextAuth"),
+    ("@auth0", Category::Auth, "Auth0"),
+    ("@clerk", Category::Auth, "Clerk"),
+    ("@stripe/stripe-js", Category::Payments, "Stripe"),
+    ("nodemailer", Category::Email, "SMTP"),
+    ("@sendgrid/mail", Category::Email, "SendGrid"),
+    ("@sentry", Category::Telemetry, "Sentry"),
+    ("@opentelemetry", Category::Telemetry, "OpenTelemetry"),
+    ("dd-trace", Category::Telemetry, "Datadog"),
+    ("prom-client", Category::Telemetry, "Prometheus"),
+    // Python
+    ("psycopg", Category::Database, "PostgreSQL"),
+    ("psycopg2", Category::Database, "PostgreSQL"),
+    ("asyncpg", Category::Database, "PostgreSQL"),
+    ("sqlalchemy", Category::Database, "SQL databases"),
+    ("pymongo", Category::Database, "MongoDB"),
+    ("motor", Category::Database, "MongoDB"),
+    ("pika", Category::Messaging, "RabbitMQ"),
+    ("confluent_kafka", Category::Messaging, "Kafka"),
+    ("celery", Category::Messaging, "Celery"),
+    ("requests", Category::HttpClient, ""),
+    ("httpx", Category::HttpClient, ""),
+    ("aiohttp", Category::HttpClient, ""),
+    ("boto3", Category::CloudSdk, "AWS"),
+    ("botocore", Category::CloudSdk, "AWS"),
+    ("google.cloud", Category::CloudSdk, "Google Cloud"),
+    ("azure", Category::CloudSdk, "Azure"),
+    ("jwt", Category::Auth, "JWT"),
+    ("authlib", Category::Auth, "OAuth"),
+    ("sendgrid", Category::Email, "SendGrid"),
+    ("sentry_sdk", Category::Telemetry, "Sentry"),
+    ("prometheus_client", Category::Telemetry, "Prometheus"),
+    // Go
+    ("github.com/jackc/pgx", Category::Database, "PostgreSQL"),
+    ("github.com/lib/pq", Category::Database, "PostgreSQL"),
+    ("gorm.io/gorm", Category::Database, "SQL databases"),
+    ("go.mongodb.org/mongo-driver", Category::Database, "MongoDB"),
+    ("github.com/redis/go-redis", Category::Cache, "Redis"),
+    ("github.com/go-redis/redis", Category::Cache, "Redis"),
+    (
+        "github.com/segmentio/kafka-go",
+        Category::Messaging,
+        "Kafka",
+    ),
+    (
+        "github.com/rabbitmq/amqp091-go",
+        Category::Messaging,
+        "RabbitMQ",
+    ),
+    ("github.com/nats-io/nats.go", Category::Messaging, "NATS"),
+    ("github.com/aws/aws-sdk-go", Category::CloudSdk, "AWS"),
+    ("github.com/aws/aws-sdk-go-v2", Category::CloudSdk, "AWS"),
+    ("cloud.google.com/go", Category::CloudSdk, "Google Cloud"),
+    ("github.com/golang-jwt/jwt", Category::Auth, "JWT"),
+    ("golang.org/x/oauth2", Category::Auth, "OAuth 2"),
+    ("github.com/stripe/stripe-go", Category::Payments, "Stripe"),
+    (
+        "go.opentelemetry.io/otel",
+        Category::Telemetry,
+        "OpenTelemetry",
+    ),
+    (
+        "github.com/getsentry/sentry-go",
+        Category::Telemetry,
+        "Sentry",
+    ),
+    (
+        "github.com/prometheus/client_golang",
+        Category::Telemetry,
+        "Prometheus",
+    ),
+];
+
+/// Standard library modules imported like packages, never sent to the LLM
+const RUST_STD: &[&str] = &["std", "core", "alloc", "proc_macro", "test"];
+const NODE_BUILTINS: &[&str] = &[
+    "assert",
+    "buffer",
+    "child_process",
+    "crypto",
+    "events",
+    "fs",
+    "http",
+    "https",
+    "net",
+    "os",
+    "path",
+    "readline",
+    "stream",
+    "url",
+    "util",
+    "zlib",
+];
+const PYTHON_STDLIB: &[&str] = &[
+    "abc",
+    "asyncio",
+    "collections",
+    "dataclasses",
+    "datetime",
+    "functools",
+    "itertools",
+    "json",
+    "logging",
+    "os",
+    "pathlib",
+    "re",
+    "subprocess",
+    "sys",
+    "time",
+    "typing",
+];
+
+/// Whether the import `source` is `key` or inside it (`@sentry/node` is in
+/// `@sentry`, `google.cloud.storage` in `google.cloud`). `-` and `_` are the
+/// same, as crate names are imported with underscores.
+fn matches(source: &str, key: &str) -> bool {
+    let normalize = |c: char| if c == '-' { '_' } else { c };
+    let mut source = source.chars().map(normalize);
+    key.chars().map(normalize).all(|k| source.next() == Some(k))
+        && matches!(source.next(), None | Some('/') | Some('.'))
+}
+
+/// The longest built-in entry `source` falls under
+fn known(source: &str) -> Option<&'static KnownEntry> {
+    KNOWN
+        .iter()
+        .filter(|(key, _, _)| matches(source, key))
+        .max_by_key(|(key, _, _)| key.len())
+}
+
+/// The package an import belongs to. Go imports name their module by host
+/// and path, so the first three segments are kept.
+fn package_of(source: &str) -> String {
+    let is_module_path = source
+        .split('/')
+        .next()
+        .is_some_and(|host| host.contains('.'));
+    if is_module_path {
+        return source.split('/').take(3).collect::<Vec<_>>().join("/");
+    }
+    package_name(source).to_string()
+}
+
+fn is_standard_library(source: &str, language: Language) -> bool {
+    let root = source.split(['/', '.']).next().unwrap_or("");
+    match language {
+        Language::Rust => RUST_STD.contains(&root),
+        Language::TypeScript | Language::JavaScript => {
+            source.starts_with("node:") || NODE_BUILTINS.contains(&root)
+        }
+        Language::Python => PYTHON_STDLIB.contains(&root),
+        // Go's standard library paths have no host
+        Language::Go => !source.split('/').next().unwrap_or("").contains('.'),
+        _ => false,
+    }
+}
+
+/// Packages imported by non-test modules, with the modules importing each
+struct Usage<'a> {
+    /// With the built-in entry each falls under
+    known: BTreeMap<String, (&'static KnownEntry, BTreeSet<&'a str>)>,
+    unknown: BTreeMap<String, BTreeSet<&'a str>>,
+}
+
+fn usage(analysis: &Analysis) -> Usage<'_> {
+    let mut usage = Usage {
+        known: BTreeMap::new(),
+        unknown: BTreeMap::new(),
+    };
+    for module in analysis.modules.iter().filter(|m| !m.is_test_module) {
+        for import in module.imports.iter().filter(|i| i.is_external) {
+            let path = module.path.as_str();
+            match known(&import.source) {
+                Some(entry) => {
+                    usage
+                        .known
+                        .entry(package_of(&import.source))
+                        .or_insert_with(|| (entry, BTreeSet::new()))
+                        .1
+                        .insert(path);
+                }
+                None if is_standard_library(&import.source, module.language) => {}
+                None => {
+                    usage
+                        .unknown
+                        .entry(package_of(&import.source))
+                        .or_default()
+                        .insert(path);
+                }
+            }
+        }
+    }
+    usage
+}
+
+/// The integrations the built-in table knows of, sorted by category
+pub fn classify(analysis: &Analysis) -> Vec<Integration> {
+    let mut integrations: Vec<Integration> = usage(analysis)
+        .known
+        .into_iter()
+        .map(|(package, ((_, category, service), modules))| Integration {
+            package,
+            category: *category,
+            service: (!service.is_empty()).then(|| service.to_string()),
+            modules: modules.into_iter().map(String::from).collect(),
+            inferred: false,
+        })
+        .collect();
+    sort(&mut integrations);
+    integrations
+}
+
+fn sort(integrations: &mut [Integration]) {
+    integrations.sort_by(|a, b| {
+        (a.category, &a.service, &a.package).cmp(&(b.category, &b.service, &b.package))
+    });
+}
+
+const CLASSIFY_PROMPT: &str = r#"You classify software packages by the third-party service they connect to. For each package the user lists, answer one line:
+
+package | category | service
+
+category is one of: database, cache, messaging, search, cloud-sdk, http-client, auth, payments, email, telemetry, or none when the package doesn't connect to an external service (utilities, frameworks, parsers, ...). service names the product (PostgreSQL, Stripe, AWS S3), or is empty for generic clients. Answer only with these lines."#;
+
+/// Ask the LLM about the external packages the built-in table doesn't know,
+/// in one call, and add those that connect to a service to `integrations`.
+/// A failed call leaves `integrations` as it is.
+pub async fn add_inferred(
+    analysis: &Analysis,
+    integrations: &mut Vec<Integration>,
+    provider: &dyn LlmProvider,
+) {
+    let mut unknown: Vec<(String, BTreeSet<&str>)> = usage(analysis).unknown.into_iter().collect();
+    if unknown.is_empty() {
+        return;
+    }
+    // The most used first, should the list be cut short
+    unknown.sort_by_key(|(_, modules)| std::cmp::Reverse(modules.len()));
+    if unknown.len() > MAX_INFERRED {
+        debug!(
+            "Classifying the {} most used of {} unknown packages",
+            MAX_INFERRED,
+            unknown.len()
+        );
+        unknown.truncate(MAX_INFERRED);
+    }
+
+    let prompt: String = unknown
+        .iter()
+        .map(|(package, _)| format!("{}\n", package))
+        .collect();
+    let messages = vec![
+        Message {
+            role: Role::System,
+            content: CLASSIFY_PROMPT.to_string(),
+        },
+        Message {
+            role: Role::User,
+            content: prompt,
+        },
+    ];
+    let config = LlmConfig {
+        max_tokens: 2048,
+        ..Default::default()
+    };
+    let response = match provider.complete(messages, config).await {
+        Ok(response) => response,
+        Err(e) => {
+            warn!("Failed to classify external dependencies: {}", e);
+            return;
+        }
+    };
+
+    let mut unknown: BTreeMap<String, BTreeSet<&str>> = unknown.into_iter().collect();
+    let before = integrations.len();
+    for (package, category, service) in response.lines().filter_map(parse_answer) {
+        // Only packages that were asked about, each once
+        let Some(modules) = unknown.remove(package) else {
+            continue;
+        };
+        integrations.push(Integration {
+            package: package.to_string(),
+            category,
+            service: service.map(String::from),
+            modules: modules.into_iter().map(String::from).collect(),
+            inferred: true,
+        });
+    }
+    info!(
+        "Classified {} more external dependencies as integrations",
+        integrations.len() - before
+    );
+    sort(integrations);
+}
+
+/// One `package | category | service` line of the LLM's answer; `None` for
+/// other lines and packages that aren't integrations
+fn parse_answer(line: &str) -> Option<(&str, Category, Option<&str>)> {
+    let mut fields = line
+        .trim()
+        .trim_start_matches("- ")
+        .split('|')
+        .map(|f| f.trim().trim_matches('`').trim());
+    let package = fields.next().filter(|p| !p.is_empty())?;
+    let category = Category::from_name(&fields.next()?.to_lowercase())?;
+    let service = fields.next().filter(|s| !s.is_empty() && *s != "-");
+    Some((package, category, service))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::analyzer::{Completeness, Import, ImportKind, ModuleAnalysis};
+    use anyhow::Result;
+
+    fn module(path: &str, language: Language, sources: &[&str]) -> ModuleAnalysis {
+        ModuleAnalysis {
+            path: path.to_string(),
+            language,
+            exports: vec![],
+            imports: sources
+                .iter()
+                .map(|source| Import {
+                    source: source.to_string(),
+                    items: vec![],
+                    is_external: true,
+                    kind: ImportKind::Runtime,
+                })
+                .collect(),
+            summary: String::new(),
+            has_deep_analysis: false,
+            is_test_module: path.contains("test"),
+            encoding_warning: None,
+            ownership: None,
+            skip_reason: None,
+            prompt_version: None,
+            modified_during_analysis: false,
+            files: Vec::new(),
+            content_hash: None,
+            completeness: Completeness::StaticOnly,
+            prompt_template: None,
+            behaviors: Vec::new(),
+            neighbor_context: false,
+            flags: Vec::new(),
+            selection: None,
+            llm_usage: None,
+        }
+    }
+
+    fn analysis() -> Analysis {
+        Analysis {
+            modules: vec![
+                module("src/db.rs", Language::Rust, &["sqlx", "std", "serde"]),
+                module("src/billing.rs", Language::Rust, &["async_stripe", "sqlx"]),
+                module(
+                    "web/api.ts",
+                    Language::TypeScript,
+                    &["@sentry/node", "axios", "node:fs"],
+                ),
+                module(
+                    "web/upload.ts",
+                    Language::TypeScript,
+                    &["@aws-sdk/client-s3", "zod"],
+                ),
+                module(
+                    "svc/main.go",
+                    Language::Go,
+                    &["github.com/jackc/pgx/v5", "net/http"],
+                ),
+                module("tests/db_test.rs", Language::Rust, &["redis"]),
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_classify_from_the_table() {
+        let summary: Vec<(String, &str, Option<String>, Vec<String>)> = classify(&analysis())
+            .into_iter()
+            .map(|i| (i.package, i.category.name(), i.service, i.modules))
+            .collect();
+        let owned = |s: &str| s.to_string();
+        assert_eq!(
+            summary,
+            [
+                (
+                    owned("github.com/jackc/pgx"),
+                    "database",
+                    Some(owned("PostgreSQL")),
+                    vec![owned("svc/main.go")]
+                ),
+                (
+                    owned("sqlx"),
+                    "database",
+                    Some(owned("SQL databases")),
+                    vec![owned("src/billing.rs"), owned("src/db.rs")]
+                ),
+                (
+                    owned("@aws-sdk/client-s3"),
+                    "cloud-sdk",
+                    Some(owned("AWS S3")),
+                    vec![owned("web/upload.ts")]
+                ),
+                (
+                    owned("axios"),
+                    "http-client",
+                    None,
+                    vec![owned("web/api.ts")]
+                ),
+                (
+                    owned("async_stripe"),
+                    "payments",
+                    Some(owned("Stripe")),
+                    vec![owned("src/billing.rs")]
+                ),
+                (
+                    owned("@sentry/node"),
+                    "telemetry",
+                    Some(owned("Sentry")),
+                    vec![owned("web/api.ts")]
+                ),
+            ]
+        );
+    }
+
+    struct Classifier;
+
+    #[async_trait::async_trait]
+    impl LlmProvider for Classifier {
+        fn name(&self) -> &str {
+            "mock"
+        }
+
+        fn model(&self) -> &str {
+            "mock"
+        }
+
+        async fn list_models(&self) -> Result<Vec<crate::llm::ModelInfo>> {
+            Ok(vec![])
+        }
+
+        async fn complete(&self, messages: Vec<Message>, _config: LlmConfig) -> Result<String> {
+            assert_eq!(messages[1].content, "serde\nzod\n");
+            Ok("serde | none |\n\
+                Here you go:\n\
+                `zod` | messaging | Kafka\n\
+                left-pad | http-client |\n"
+                .to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unknown_packages_go_to_the_llm() {
+        let analysis = analysis();
+        let mut integrations = classify(&analysis);
+        let known = integrations.len();
+        add_inferred(&analysis, &mut integrations, &Classifier).await;
+
+        assert_eq!(integrations.len(), known + 1);
+        let zod = integrations.iter().find(|i| i.package == "zod").unwrap();
+        assert!(zod.inferred);
+        assert_eq!(zod.category, Category::Messaging);
+        assert_eq!(zod.service.as_deref(), Some("Kafka"));
+        assert_eq!(zod.modules, ["web/upload.ts"]);
+    }
+}
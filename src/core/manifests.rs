@@ -0,0 +1,588 @@
+//! Kubernetes manifests, Helm charts and OpenAPI specs
+//!
+//! Discovery sets these apart from other YAML by content rather than name:
+//! a manifest has top-level `apiVersion` and `kind` keys, a spec an `openapi`
+//! or `swagger` key, and a chart is the directory of a `Chart.yaml`. Each is
+//! read for its key facts: the resources a manifest defines, a chart's
+//! metadata and values, and the operations of a spec. Operations are matched
+//! to the functions handling them by `operationId`, or else by the path
+//! appearing as a string literal in the source.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use tracing::debug;
+
+use super::analyzer::{ExportKind, ModuleAnalysis};
+use super::discovery::FileInventory;
+use super::project_files::read_head;
+use super::yaml::{self, Yaml};
+
+/// How far into a YAML or JSON file to look for the keys that classify it
+const DETECT_SEARCH_LINES: usize = 100;
+/// Lines after a route's path literal in which the function it registers
+/// is looked for, as under a decorator or attribute
+const ROUTE_HANDLER_LINES: usize = 3;
+/// HTTP methods that are operations of an OpenAPI path item
+const METHODS: &[&str] = &[
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// What a file set apart by [`classify`] is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManifestKind {
+    Kubernetes,
+    /// A file of a Helm chart: `Chart.yaml`, values, templates
+    Helm,
+    /// An OpenAPI or Swagger spec
+    ApiSpec,
+}
+
+/// Classify a YAML or JSON file at `path`, relative to the root, whose
+/// content can be read at `abs_path`. `chart_dirs` are the directories
+/// holding a `Chart.yaml`.
+pub fn classify(
+    path: &str,
+    name: &str,
+    ext: &str,
+    abs_path: &Path,
+    chart_dirs: &[String],
+) -> Option<ManifestKind> {
+    let ext = ext.to_lowercase();
+    if is_chart_file(path, name, &ext, chart_dirs) {
+        return Some(ManifestKind::Helm);
+    }
+    if !matches!(ext.as_str(), "yaml" | "yml" | "json") {
+        return None;
+    }
+    let lines = read_head(abs_path, DETECT_SEARCH_LINES)?;
+    if ext == "json" {
+        let is_spec = lines.iter().any(|line| {
+            ["\"openapi\"", "\"swagger\""].iter().any(|key| {
+                line.split(key)
+                    .skip(1)
+                    .any(|rest| rest.trim_start().starts_with(':'))
+            })
+        });
+        return is_spec.then_some(ManifestKind::ApiSpec);
+    }
+    let has_key = |key: &str| {
+        lines
+            .iter()
+            .any(|line| line.strip_prefix(key).is_some_and(|r| r.starts_with(':')))
+    };
+    if has_key("openapi") || has_key("swagger") {
+        Some(ManifestKind::ApiSpec)
+    } else if has_key("apiVersion") && has_key("kind") {
+        Some(ManifestKind::Kubernetes)
+    } else {
+        None
+    }
+}
+
+/// Whether a file belongs to the chart in one of `chart_dirs`: its
+/// `Chart.yaml`, lock file, values and templates
+fn is_chart_file(path: &str, name: &str, ext: &str, chart_dirs: &[String]) -> bool {
+    let Some(relative) = chart_dirs
+        .iter()
+        .filter_map(|dir| match dir.as_str() {
+            "" => Some(path),
+            dir => path.strip_prefix(dir)?.strip_prefix('/'),
+        })
+        .min_by_key(|relative| relative.len())
+    else {
+        return false;
+    };
+    let name = name.to_lowercase();
+    matches!(
+        name.as_str(),
+        "chart.yaml" | "chart.lock" | ".helmignore" | "values.schema.json"
+    ) && !relative.contains('/')
+        || name.starts_with("values") && matches!(ext, "yaml" | "yml") && !relative.contains('/')
+        || relative.starts_with("templates/")
+        || relative.starts_with("crds/")
+}
+
+/// The Kubernetes resources of one manifest file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct KubernetesManifest {
+    pub path: String,
+    pub resources: Vec<KubernetesResource>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct KubernetesResource {
+    /// E.g. `Deployment`, `Service`
+    pub kind: String,
+    /// `metadata.name`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+}
+
+/// A Helm chart, from its `Chart.yaml` and `values.yaml`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct HelmChart {
+    /// The chart's `Chart.yaml`
+    pub path: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Top-level keys of `values.yaml`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub values: Vec<String>,
+    /// Files under the chart's `templates/`
+    pub templates: usize,
+}
+
+/// An OpenAPI or Swagger spec and its operations
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct ApiSpec {
+    pub path: String,
+    /// `openapi` or `swagger` with its version, e.g. `OpenAPI 3.0.3`
+    pub format: String,
+    /// `info.title`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// `info.version`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    pub operations: Vec<ApiOperation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct ApiOperation {
+    /// Uppercase, e.g. `GET`
+    pub method: String,
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operation_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    /// Where the operation is handled, when [`match_handlers`] found it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handler: Option<ApiHandler>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct ApiHandler {
+    pub file: String,
+    pub line: usize,
+    /// The handling function, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub matched_by: HandlerMatch,
+}
+
+/// How a handler was found. Serialized in snake case: `operation_id`,
+/// `path`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HandlerMatch {
+    /// An exported function has the operation's `operationId` for a name
+    OperationId,
+    /// The operation's path is a string literal in the source
+    Path,
+}
+
+/// The resources of the inventory's Kubernetes manifests, in path order
+pub fn kubernetes(inventory: &FileInventory) -> Vec<KubernetesManifest> {
+    read_sorted(inventory, &inventory.kubernetes_files)
+        .map(|(path, content)| KubernetesManifest {
+            resources: yaml::documents(&content)
+                .iter()
+                .filter_map(resource)
+                .collect(),
+            path,
+        })
+        .collect()
+}
+
+fn resource(doc: &Yaml) -> Option<KubernetesResource> {
+    let metadata = doc.get("metadata");
+    let field = |key: &str| {
+        metadata
+            .and_then(|m| m.get(key))
+            .and_then(Yaml::as_str)
+            .map(String::from)
+    };
+    Some(KubernetesResource {
+        kind: doc.get("kind").and_then(Yaml::as_str)?.to_string(),
+        name: field("name"),
+        namespace: field("namespace"),
+    })
+}
+
+/// The inventory's Helm charts, in path order
+pub fn helm_charts(inventory: &FileInventory) -> Vec<HelmChart> {
+    let root = Path::new(&inventory.root);
+    let charts: Vec<String> = inventory
+        .helm_files
+        .iter()
+        .filter(|path| path.rsplit('/').next() == Some("Chart.yaml"))
+        .cloned()
+        .collect();
+    read_sorted(inventory, &charts)
+        .map(|(path, content)| {
+            let chart = Yaml::parse(&content);
+            let dir = path.strip_suffix("Chart.yaml").unwrap_or("");
+            let field = |key: &str| chart.get(key).and_then(Yaml::as_str).map(String::from);
+            let values = fs::read_to_string(root.join(format!("{}values.yaml", dir)))
+                .map(|values| {
+                    Yaml::parse(&values)
+                        .entries()
+                        .into_iter()
+                        .map(|(key, _)| key)
+                        .collect()
+                })
+                .unwrap_or_default();
+            let templates = format!("{}templates/", dir);
+            HelmChart {
+                name: field("name").unwrap_or_else(|| {
+                    dir.trim_end_matches('/')
+                        .rsplit('/')
+                        .next()
+                        .unwrap_or_default()
+                        .to_string()
+                }),
+                version: field("version"),
+                app_version: field("appVersion"),
+                description: field("description"),
+                values,
+                templates: inventory
+                    .helm_files
+                    .iter()
+                    .filter(|f| f.starts_with(&templates))
+                    .count(),
+                path,
+            }
+        })
+        .collect()
+}
+
+/// The inventory's OpenAPI and Swagger specs, in path order
+pub fn api_specs(inventory: &FileInventory) -> Vec<ApiSpec> {
+    read_sorted(inventory, &inventory.api_spec_files)
+        .filter_map(|(path, content)| {
+            let doc = if path.to_lowercase().ends_with(".json") {
+                match serde_json::from_str(&content) {
+                    Ok(value) => yaml::from_json(&value),
+                    Err(e) => {
+                        debug!("Not reading API spec {}: {}", path, e);
+                        return None;
+                    }
+                }
+            } else {
+                Yaml::parse(&content)
+            };
+            Some(api_spec(path, &doc))
+        })
+        .collect()
+}
+
+fn api_spec(path: String, doc: &Yaml) -> ApiSpec {
+    let format = match (doc.get("openapi"), doc.get("swagger")) {
+        (Some(version), _) => format!("OpenAPI {}", version.as_str().unwrap_or("?")),
+        (None, Some(version)) => format!("Swagger {}", version.as_str().unwrap_or("?")),
+        (None, None) => "OpenAPI".to_string(),
+    };
+    let info = |key: &str| {
+        doc.get("info")
+            .and_then(|i| i.get(key))
+            .and_then(Yaml::as_str)
+            .map(String::from)
+    };
+    let mut operations = Vec::new();
+    for (route, item) in doc.get("paths").map(Yaml::entries).unwrap_or_default() {
+        for (method, operation) in item.entries() {
+            if !METHODS.contains(&method.to_lowercase().as_str()) {
+                continue;
+            }
+            let field = |key: &str| operation.get(key).and_then(Yaml::as_str).map(String::from);
+            operations.push(ApiOperation {
+                method: method.to_uppercase(),
+                path: route.clone(),
+                operation_id: field("operationId"),
+                summary: field("summary"),
+                handler: None,
+            });
+        }
+    }
+    ApiSpec {
+        path,
+        format,
+        title: info("title"),
+        version: info("version"),
+        operations,
+    }
+}
+
+/// `paths` sorted, with their content; files that can't be read are left out
+fn read_sorted<'a>(
+    inventory: &'a FileInventory,
+    paths: &'a [String],
+) -> impl Iterator<Item = (String, String)> + 'a {
+    let mut paths: Vec<&String> = paths.iter().collect();
+    paths.sort();
+    paths.into_iter().filter_map(|path| {
+        match fs::read_to_string(Path::new(&inventory.root).join(path)) {
+            Ok(content) => Some((path.clone(), content)),
+            Err(e) => {
+                debug!("Not reading {}: {}", path, e);
+                None
+            }
+        }
+    })
+}
+
+/// Find the function handling each operation: an exported function named
+/// like its `operationId`, ignoring case, `_` and `-`, or else the first
+/// string literal in the source that is its path, with `{param}` also
+/// spelled `:param` or `<param>`
+pub fn match_handlers(
+    specs: &mut [ApiSpec],
+    modules: &[ModuleAnalysis],
+    inventory: &FileInventory,
+) {
+    let mut pending = Vec::new();
+    for operation in specs.iter_mut().flat_map(|s| &mut s.operations) {
+        operation.handler = operation
+            .operation_id
+            .as_deref()
+            .and_then(|id| by_operation_id(id, modules));
+        if operation.handler.is_none() && operation.path != "/" {
+            pending.push(operation);
+        }
+    }
+    if pending.is_empty() {
+        return;
+    }
+
+    let literals: Vec<Vec<String>> = pending.iter().map(|o| path_literals(&o.path)).collect();
+    for file in &inventory.source_files {
+        let Ok(content) = fs::read_to_string(&file.abs_path) else {
+            continue;
+        };
+        for (i, line) in content.lines().enumerate() {
+            for (operation, literals) in pending.iter_mut().zip(&literals) {
+                if operation.handler.is_some() || !literals.iter().any(|l| line.contains(l)) {
+                    continue;
+                }
+                let line = i + 1;
+                let name = modules
+                    .iter()
+                    .find(|m| m.path == file.path)
+                    .and_then(|m| {
+                        m.exports.iter().find(|e| {
+                            e.kind == ExportKind::Function
+                                && (line..=line + ROUTE_HANDLER_LINES).contains(&e.line_number)
+                        })
+                    })
+                    .map(|e| e.name.clone());
+                operation.handler = Some(ApiHandler {
+                    file: file.path.clone(),
+                    line,
+                    name,
+                    matched_by: HandlerMatch::Path,
+                });
+            }
+        }
+    }
+}
+
+fn by_operation_id(id: &str, modules: &[ModuleAnalysis]) -> Option<ApiHandler> {
+    let id = identifier(id);
+    modules
+        .iter()
+        .filter(|m| !m.is_test_module)
+        .flat_map(|m| m.exports.iter().map(move |e| (m, e)))
+        .find(|(_, e)| e.kind == ExportKind::Function && identifier(&e.name) == id)
+        .map(|(module, export)| ApiHandler {
+            file: export.file.clone().unwrap_or_else(|| module.path.clone()),
+            line: export.line_number,
+            name: Some(export.name.clone()),
+            matched_by: HandlerMatch::OperationId,
+        })
+}
+
+/// A name with case, `_` and `-` ignored, so `getUser` matches `get_user`
+fn identifier(name: &str) -> String {
+    name.chars()
+        .filter(|c| *c != '_' && *c != '-')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// The quoted spellings of an OpenAPI path in route definitions
+fn path_literals(path: &str) -> Vec<String> {
+    let spell = |open: &str, close: &str| {
+        path.split('/')
+            .map(
+                |segment| match segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+                    Some(param) => format!("{}{}{}", open, param, close),
+                    None => segment.to_string(),
+                },
+            )
+            .collect::<Vec<_>>()
+            .join("/")
+    };
+    let mut spellings = vec![path.to_string(), spell(":", ""), spell("<", ">")];
+    spellings.dedup();
+    spellings
+        .iter()
+        .flat_map(|s| ['"', '\'', '`'].map(|q| format!("{}{}{}", q, s, q)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::analyzer::{analyze_static, ParseOptions};
+    use crate::core::discovery::{discover, DiscoveryOptions};
+    use crate::core::timings::Timings;
+
+    const SPEC: &str = "openapi: 3.0.3
+info:
+  title: Items
+  version: '2.1'
+paths:
+  /items:
+    get:
+      operationId: list-items
+      summary: List items
+    post:
+      summary: Create an item
+  /items/{id}:
+    parameters: []
+    delete:
+      summary: Delete an item
+  /health:
+    get: {}
+";
+
+    #[tokio::test]
+    async fn test_manifests() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |path: &str, content: &str| {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        };
+        write(
+            "deploy/api.yaml",
+            "apiVersion: apps/v1\nkind: Deployment\nmetadata:\n  name: api\n  namespace: prod\n---\napiVersion: v1\nkind: Service\nmetadata:\n  name: api\n",
+        );
+        write(
+            "charts/api/Chart.yaml",
+            "apiVersion: v2\nname: api\nversion: 1.2.0\nappVersion: \"2.1\"\n",
+        );
+        write(
+            "charts/api/values.yaml",
+            "image:\n  tag: latest\nreplicas: 2\n",
+        );
+        write(
+            "charts/api/templates/deployment.yaml",
+            "apiVersion: apps/v1\nkind: Deployment\nmetadata:\n  name: {{ .Release.Name }}\n",
+        );
+        write("api/openapi.yaml", SPEC);
+        write("config/settings.yaml", "apiVersion: 1\nlevel: debug\n");
+        write("tests/fixtures/pod.yaml", "apiVersion: v1\nkind: Pod\n");
+        write(
+            "src/items.rs",
+            "pub fn list_items() {}\n\n#[delete(\"/items/{id}\")]\npub async fn remove(id: u32) {}\n\nfn routes() -> Router { route(\"/items\", post(create)) }\n",
+        );
+
+        let inventory = discover(dir.path(), None, &DiscoveryOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(inventory.kubernetes_files, ["deploy/api.yaml"]);
+        assert_eq!(inventory.api_spec_files, ["api/openapi.yaml"]);
+        let mut helm = inventory.helm_files.clone();
+        helm.sort();
+        assert_eq!(
+            helm,
+            [
+                "charts/api/Chart.yaml",
+                "charts/api/templates/deployment.yaml",
+                "charts/api/values.yaml"
+            ]
+        );
+        let mut config = inventory.config_files.clone();
+        config.sort();
+        assert_eq!(config, ["config/settings.yaml", "tests/fixtures/pod.yaml"]);
+
+        let analysis = analyze_static(&inventory, 1, ParseOptions::default(), &Timings::default())
+            .await
+            .unwrap();
+        let resources = &analysis.kubernetes[0].resources;
+        assert_eq!(resources.len(), 2);
+        assert_eq!(resources[0].namespace.as_deref(), Some("prod"));
+        assert_eq!(resources[1].kind, "Service");
+
+        let chart = &analysis.helm_charts[0];
+        assert_eq!((chart.name.as_str(), chart.templates), ("api", 1));
+        assert_eq!(chart.app_version.as_deref(), Some("2.1"));
+        assert_eq!(chart.values, ["image", "replicas"]);
+
+        let spec = &analysis.api_specs[0];
+        assert_eq!(spec.format, "OpenAPI 3.0.3");
+        assert_eq!(spec.version.as_deref(), Some("2.1"));
+        let handlers: Vec<_> = spec
+            .operations
+            .iter()
+            .map(|o| {
+                let handler = o.handler.as_ref();
+                (
+                    format!("{} {}", o.method, o.path),
+                    handler.map(|h| h.line),
+                    handler.and_then(|h| h.name.clone()),
+                    handler.map(|h| h.matched_by),
+                )
+            })
+            .collect();
+        assert_eq!(
+            handlers,
+            [
+                (
+                    "GET /items".to_string(),
+                    Some(1),
+                    Some("list_items".to_string()),
+                    Some(HandlerMatch::OperationId)
+                ),
+                (
+                    "POST /items".to_string(),
+                    Some(6),
+                    None,
+                    Some(HandlerMatch::Path)
+                ),
+                (
+                    "DELETE /items/{id}".to_string(),
+                    Some(3),
+                    Some("remove".to_string()),
+                    Some(HandlerMatch::Path)
+                ),
+                ("GET /health".to_string(), None, None, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_json_spec() {
+        let json = r#"{"swagger": "2.0", "paths": {"/a": {"get": {"operationId": "a"}}}}"#;
+        let value: serde_json::Value = serde_json::from_str(json).unwrap();
+        let spec = api_spec("spec.json".into(), &yaml::from_json(&value));
+        assert_eq!(spec.format, "Swagger 2.0");
+        assert_eq!(spec.operations[0].operation_id.as_deref(), Some("a"));
+    }
+}
@@ -1,6 +1,36 @@
 pub mod analyzer;
+pub mod backoff;
+pub mod behaviors;
+pub mod ci;
 pub mod discovery;
+pub mod doc_checks;
+pub mod examples;
+pub mod exit;
+pub mod features;
+pub mod focus;
+pub mod gate;
+pub mod generated;
+pub mod git;
+pub mod gitattributes;
+pub mod integrations;
+pub mod manifests;
+pub mod orphans;
+pub mod ownership;
+pub mod pairing;
 pub mod parser;
+pub mod plugins;
+pub mod project_files;
+pub mod redact;
+pub mod renames;
+pub mod run_commands;
+pub mod runs;
+pub mod sanitize;
+pub mod selection;
+pub mod sniff;
+pub mod source;
+pub mod templates;
+pub mod timings;
+mod yaml;
 
 pub use analyzer::{Analysis, CrossReference};
 #[allow(unused_imports)]
@@ -0,0 +1,277 @@
+//! Output left behind by source files that no longer exist
+//!
+//! A deleted file keeps its module page in `modules/` and its line in the
+//! progress file until something removes them, and a renamed one that was
+//! edited as it moved does too. At the end of each `cda analyze` run, and
+//! with `cda clean --stale`, the pages and progress entries whose file is
+//! gone from the analyzed directory are removed and listed in `cda-changes.md`.
+//! A file counts as gone when it doesn't exist, whatever the run's filters,
+//! so files left out by `--languages` or `--max-files` keep their pages.
+
+use anyhow::Result;
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+use tracing::debug;
+
+use super::analyzer::{self, directory_page_name, module_page_name, PageNaming, DIRECTORY_PAGE};
+use super::runs;
+
+/// Report of the removals, newest run first, in the output directory
+pub const CHANGES_FILE: &str = "cda-changes.md";
+
+/// Source files that are gone, with what they left in the output directory
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Orphans {
+    /// Paths of the files, as in the progress file and `analysis.json`
+    pub files: Vec<String>,
+    /// Their module pages, and the pages of directories that are gone,
+    /// relative to `modules/`
+    pub pages: Vec<String>,
+}
+
+impl Orphans {
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty() && self.pages.is_empty()
+    }
+}
+
+/// What `output_path` holds for files that are gone from `root`. `known`
+/// are module paths of the run or of `analysis.json`: the pages of the ones
+/// that exist are kept even when their names had to be made safe. Only
+/// pages cda can be shown to have written are considered: the `listed`
+/// ones of the manifest, relative to `modules/`, and those of the files in
+/// a progress file with cda's header. Other files in `modules/` are never
+/// orphans.
+pub fn find<'a>(
+    output_path: &Path,
+    root: &Path,
+    known: impl IntoIterator<Item = &'a str>,
+    listed: &[String],
+) -> Result<Orphans> {
+    let exists = |path: &str| root.join(path).is_file();
+    let progress: BTreeSet<String> = analyzer::progress_files(output_path).into_keys().collect();
+    let live_files: Vec<String> = known
+        .into_iter()
+        .map(String::from)
+        .chain(progress.iter().cloned())
+        .filter(|path| exists(path))
+        .collect();
+    let live: HashSet<String> = live_files.iter().map(|p| module_page_name(p)).collect();
+    // Index pages of the directories holding them, the root's included
+    let live_dirs: HashSet<String> = live_files
+        .iter()
+        .flat_map(|path| {
+            path.match_indices(['/', '\\'])
+                .map(|(i, _)| &path[..i])
+                .chain([""])
+        })
+        .map(directory_page_name)
+        .collect();
+    // Only mirrored page names lead back to the source path
+    let mirrored = analyzer::page_naming() == PageNaming::MirrorTree;
+
+    let recorded: Vec<String> = if analyzer::has_progress(output_path) {
+        progress.iter().map(|p| module_page_name(p)).collect()
+    } else {
+        Vec::new()
+    };
+    let vouched: HashSet<&str> = listed.iter().chain(&recorded).map(String::as_str).collect();
+
+    let mut files: BTreeSet<String> = progress.into_iter().filter(|p| !exists(p)).collect();
+
+    let mut pages = Vec::new();
+    for page in pages_in(&output_path.join("modules"))? {
+        if !vouched.contains(page.as_str()) {
+            continue;
+        }
+        let (dir, name) = page.rsplit_once('/').unwrap_or(("", page.as_str()));
+        let gone = if live.contains(&page) || live_dirs.contains(&page) {
+            false
+        } else if !mirrored {
+            true
+        } else if name == DIRECTORY_PAGE {
+            !root.join(dir).is_dir()
+        } else {
+            !exists(page.trim_end_matches(".md"))
+        };
+        if gone {
+            if mirrored && name != DIRECTORY_PAGE {
+                files.insert(page.trim_end_matches(".md").to_string());
+            }
+            pages.push(page);
+        }
+    }
+
+    Ok(Orphans {
+        files: files.into_iter().collect(),
+        pages,
+    })
+}
+
+/// Delete the pages of `orphans`, and the directories they leave empty, and
+/// drop their files from the progress file
+pub fn remove(output_path: &Path, orphans: &Orphans) -> Result<()> {
+    let modules_dir = output_path.join("modules");
+    for page in &orphans.pages {
+        debug!("Removing the page of a file that is gone: {}", page);
+        let path = modules_dir.join(page);
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+        let mut dir = path.parent();
+        while let Some(current) = dir.filter(|d| *d != modules_dir) {
+            if fs::remove_dir(current).is_err() {
+                break;
+            }
+            dir = current.parent();
+        }
+    }
+    analyzer::remove_progress(output_path, &orphans.files.iter().cloned().collect())
+}
+
+/// Add a section for the removal of `orphans` at the top of
+/// [`CHANGES_FILE`], below its title
+pub fn record(output_path: &Path, orphans: &Orphans) -> Result<()> {
+    const TITLE: &str = "# Changes\n\n";
+    let path = output_path.join(CHANGES_FILE);
+    let previous = fs::read_to_string(&path).unwrap_or_default();
+    let previous = previous.strip_prefix(TITLE).unwrap_or(&previous);
+
+    let mut section = format!(
+        "## {}\n\nRemoved the output of {} file(s) that no longer exist:\n\n",
+        runs::iso_timestamp(SystemTime::now()),
+        orphans.files.len()
+    );
+    for file in &orphans.files {
+        section.push_str(&format!("- `{}`\n", file));
+    }
+    let directories: Vec<&String> = orphans
+        .pages
+        .iter()
+        .filter(|p| p.rsplit('/').next() == Some(DIRECTORY_PAGE))
+        .collect();
+    if !directories.is_empty() {
+        section.push_str("\nand the pages of directories that are gone:\n\n");
+        for page in directories {
+            section.push_str(&format!("- `modules/{}`\n", page));
+        }
+    }
+
+    let mut content = format!("{}{}", TITLE, section);
+    if !previous.is_empty() {
+        content.push('\n');
+        content.push_str(previous);
+    }
+    analyzer::write_atomic(&path, content)?;
+    Ok(())
+}
+
+/// Markdown files in the modules tree, relative to it and `/`-separated
+fn pages_in(modules_dir: &Path) -> Result<Vec<String>> {
+    if !modules_dir.is_dir() {
+        return Ok(vec![]);
+    }
+    let mut pages = Vec::new();
+    for entry in walkdir::WalkDir::new(modules_dir) {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type().is_file() && path.extension().is_some_and(|e| e == "md") {
+            if let Ok(relative) = path.strip_prefix(modules_dir) {
+                pages.push(relative.to_string_lossy().replace('\\', "/"));
+            }
+        }
+    }
+    pages.sort();
+    Ok(pages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_remove_and_record_orphans() {
+        let root = tempfile::tempdir().unwrap();
+        let out = tempfile::tempdir().unwrap();
+        let (root, out) = (root.path(), out.path());
+        fs::create_dir_all(root.join("src/core")).unwrap();
+        fs::write(root.join("src/core/a.rs"), "").unwrap();
+        fs::write(root.join("src/my file.rs"), "").unwrap();
+
+        let modules = out.join("modules");
+        fs::create_dir_all(modules.join("src/core")).unwrap();
+        fs::create_dir_all(modules.join("src/old")).unwrap();
+        fs::create_dir_all(modules.join("docs")).unwrap();
+        let listed: Vec<String> = [
+            "README.md",
+            "src/README.md",
+            "src/core/a.rs.md",
+            "src/my_file.rs.md",
+            "src/gone.rs.md",
+            "src/old/README.md",
+        ]
+        .map(String::from)
+        .to_vec();
+        for page in listed.iter().map(String::as_str).chain(["src/old/b.rs.md"]) {
+            fs::write(modules.join(page), "").unwrap();
+        }
+        // Neither listed nor in the progress, so not cda's
+        fs::write(modules.join("docs/guide.md"), "").unwrap();
+        fs::create_dir_all(out.join(analyzer::STATE_DIR)).unwrap();
+        fs::write(
+            out.join(analyzer::PROGRESS_FILE),
+            "# cda-progress test/v1\nsrc/core/a.rs\tv3\nsrc/old/b.rs\tv3\nsrc/deleted.rs\tv3\n",
+        )
+        .unwrap();
+
+        let orphans = find(out, root, ["src/my file.rs"], &listed).unwrap();
+        assert_eq!(
+            orphans.files,
+            ["src/deleted.rs", "src/gone.rs", "src/old/b.rs"]
+        );
+        assert_eq!(
+            orphans.pages,
+            ["src/gone.rs.md", "src/old/README.md", "src/old/b.rs.md"]
+        );
+
+        remove(out, &orphans).unwrap();
+        assert!(!modules.join("src/old").exists());
+        assert!(modules.join("src/my_file.rs.md").exists());
+        assert_eq!(
+            analyzer::progress_files(out)
+                .into_keys()
+                .collect::<Vec<_>>(),
+            ["src/core/a.rs"]
+        );
+        assert!(find(out, root, ["src/my file.rs"], &listed)
+            .unwrap()
+            .is_empty());
+        assert!(modules.join("docs/guide.md").exists());
+
+        record(out, &orphans).unwrap();
+        record(
+            out,
+            &Orphans {
+                files: vec!["src/later.rs".into()],
+                pages: vec![],
+            },
+        )
+        .unwrap();
+        let changes = fs::read_to_string(out.join(CHANGES_FILE)).unwrap();
+        assert!(changes.starts_with("# Changes\n\n## "), "{}", changes);
+        assert_eq!(changes.matches("# Changes").count(), 1, "{}", changes);
+        assert!(
+            changes.find("src/later.rs").unwrap() < changes.find("src/deleted.rs").unwrap(),
+            "{}",
+            changes
+        );
+        assert!(
+            changes.contains("- `modules/src/old/README.md`"),
+            "{}",
+            changes
+        );
+    }
+}
@@ -0,0 +1,509 @@
+//! Who to ask about a module, for `--ownership`: top git contributors, the
+//! last change, and the owners from CODEOWNERS
+//!
+//! History comes from a single `git log --name-only` pass over the analyzed
+//! directory rather than a `git shortlog` per file. Collection fails soft:
+//! outside a git repository only CODEOWNERS is used, and a shallow clone only
+//! reflects the commits that were fetched.
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+use super::analyzer::Gap;
+use super::git;
+
+/// Contributors recorded per module
+pub const MAX_CONTRIBUTORS: usize = 3;
+
+/// Where GitHub and GitLab look for CODEOWNERS, relative to the repository root
+const CODEOWNERS_PATHS: &[&str] = &[".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+/// Separates commits in the `git log` output
+const RECORD_SEPARATOR: char = '\u{1e}';
+/// Separates the author name, email and date of a commit
+const FIELD_SEPARATOR: char = '\u{1f}';
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Ownership {
+    /// Most frequent committers, most commits first
+    pub contributors: Vec<Contributor>,
+    /// Date of the last commit that touched the file, as YYYY-MM-DD
+    pub last_modified: Option<String>,
+    /// Owners from the last matching CODEOWNERS rule
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub owners: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Contributor {
+    pub name: String,
+    pub email: String,
+    pub commits: usize,
+}
+
+impl Ownership {
+    /// One line for a module page, e.g. `@org/core · Ada <ada@x> (4 commits) · last modified 2024-05-01`
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if !self.owners.is_empty() {
+            parts.push(self.owners.join(", "));
+        }
+        if !self.contributors.is_empty() {
+            parts.push(
+                self.contributors
+                    .iter()
+                    .map(|c| {
+                        format!(
+                            "{} <{}> ({} commit{})",
+                            c.name,
+                            c.email,
+                            c.commits,
+                            if c.commits == 1 { "" } else { "s" }
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            );
+        }
+        if let Some(date) = &self.last_modified {
+            parts.push(format!("last modified {}", date));
+        }
+        if parts.is_empty() {
+            "unknown (no history or CODEOWNERS entry)".to_string()
+        } else {
+            parts.join(" · ")
+        }
+    }
+
+    /// Who to ask first: the CODEOWNERS owners, else the top contributor
+    pub fn primary_owner(&self) -> Option<String> {
+        if !self.owners.is_empty() {
+            return Some(self.owners.join(", "));
+        }
+        self.contributors.first().map(|c| c.name.clone())
+    }
+}
+
+/// Ownership of each of `files` (root-relative, `/`-separated), keyed by path
+pub fn collect(root: &Path, files: &[&str]) -> HashMap<String, Ownership> {
+    let wanted: HashSet<&str> = files.iter().copied().collect();
+
+    let repo = match repository(root) {
+        Ok(repo) => Some(repo),
+        Err(e) => {
+            warn!(
+                "{} is not in a git repository ({}); ownership only uses CODEOWNERS",
+                root.display(),
+                e
+            );
+            None
+        }
+    };
+
+    let mut ownership: HashMap<String, Ownership> = match &repo {
+        Some(_) => history(root, &wanted),
+        None => HashMap::new(),
+    };
+
+    let (base, prefix) = match &repo {
+        Some((toplevel, prefix)) => (toplevel.clone(), prefix.clone()),
+        None => (root.to_path_buf(), String::new()),
+    };
+    if let Some(rules) = load_codeowners(&base) {
+        for file in files {
+            let owners = rules.owners(&format!("{}{}", prefix, file));
+            if !owners.is_empty() {
+                ownership.entry(file.to_string()).or_default().owners = owners;
+            }
+        }
+    }
+
+    ownership
+}
+
+/// Owner of gaps in files no CODEOWNERS rule or `[owners]` mapping covers
+pub const UNASSIGNED: &str = "unassigned";
+
+/// Who owns each path, for attributing gaps to teams: `[owners]` mappings
+/// from the config, then CODEOWNERS. Unlike [`collect`] this never reads
+/// git history, so it is cheap enough for every run.
+pub struct PathOwners {
+    /// `[owners]` patterns with their team, longest pattern first
+    mapping: Vec<(Gitignore, String)>,
+    codeowners: Option<CodeOwners>,
+    /// The analyzed root's path within the repository CODEOWNERS is for
+    prefix: String,
+}
+
+impl PathOwners {
+    /// Owners for files below `root`, or `None` when there is neither a
+    /// mapping nor a CODEOWNERS file to attribute anything with
+    pub fn load(root: &Path, mapping: &BTreeMap<String, String>) -> Option<Self> {
+        let (base, prefix) =
+            repository(root).unwrap_or_else(|_| (root.to_path_buf(), String::new()));
+        let codeowners = load_codeowners(&base);
+
+        let mut patterns: Vec<(&String, &String)> = mapping.iter().collect();
+        patterns.sort_by_key(|(pattern, _)| std::cmp::Reverse(pattern.len()));
+        let mapping: Vec<(Gitignore, String)> = patterns
+            .into_iter()
+            .filter_map(|(pattern, team)| Some((matcher(pattern)?, team.clone())))
+            .collect();
+
+        if mapping.is_empty() && codeowners.is_none() {
+            return None;
+        }
+        Some(Self {
+            mapping,
+            codeowners,
+            prefix,
+        })
+    }
+
+    /// Owner of a root-relative path, [`UNASSIGNED`] when nobody owns it.
+    /// Several CODEOWNERS owners of one rule are one owner, `@a, @b`.
+    pub fn owner(&self, path: &str) -> String {
+        if let Some((_, team)) = self
+            .mapping
+            .iter()
+            .find(|(m, _)| m.matched_path_or_any_parents(path, false).is_ignore())
+        {
+            return team.clone();
+        }
+        self.codeowners
+            .as_ref()
+            .map(|rules| rules.owners(&format!("{}{}", self.prefix, path)))
+            .filter(|owners| !owners.is_empty())
+            .map(|owners| owners.join(", "))
+            .unwrap_or_else(|| UNASSIGNED.to_string())
+    }
+
+    /// Set the owner of every gap from the file of its location
+    pub fn attribute(&self, gaps: &mut [Gap]) {
+        for gap in gaps {
+            let owner = match gap.file() {
+                Some(file) => self.owner(file),
+                None => UNASSIGNED.to_string(),
+            };
+            gap.owner = Some(owner);
+        }
+    }
+}
+
+/// Whether `owner`, as [`PathOwners::owner`] gives it, includes `team`
+pub fn owned_by(owner: &str, team: &str) -> bool {
+    owner
+        .split(", ")
+        .any(|o| o.eq_ignore_ascii_case(team.trim()))
+}
+
+/// Gaps per owner, [`UNASSIGNED`] first and then most gaps first; empty
+/// when the gaps were not attributed
+pub fn gaps_by_owner(gaps: &[Gap]) -> Vec<(&str, Vec<&Gap>)> {
+    let mut by_owner: BTreeMap<&str, Vec<&Gap>> = BTreeMap::new();
+    for gap in gaps {
+        if let Some(owner) = &gap.owner {
+            by_owner.entry(owner).or_default().push(gap);
+        }
+    }
+    let mut by_owner: Vec<_> = by_owner.into_iter().collect();
+    by_owner.sort_by_key(|(owner, gaps)| (*owner != UNASSIGNED, std::cmp::Reverse(gaps.len())));
+    by_owner
+}
+
+/// Top level of the git repository holding `root`, and `root`'s path in it
+fn repository(root: &Path) -> anyhow::Result<(PathBuf, String)> {
+    let output = git::git(root, &["rev-parse", "--show-toplevel", "--show-prefix"])?;
+    let mut lines = output.lines();
+    let toplevel = PathBuf::from(lines.next().unwrap_or_default().trim());
+    let prefix = lines.next().unwrap_or_default().trim().to_string();
+    Ok((toplevel, prefix))
+}
+
+fn matcher(pattern: &str) -> Option<Gitignore> {
+    let mut builder = GitignoreBuilder::new("");
+    builder.add_line(None, pattern).ok()?;
+    builder.build().ok()
+}
+
+/// Contributors and last-modified date per file from `git log`
+fn history(root: &Path, wanted: &HashSet<&str>) -> HashMap<String, Ownership> {
+    if git::git(root, &["rev-parse", "--is-shallow-repository"]).is_ok_and(|s| s.trim() == "true") {
+        warn!("Shallow clone: ownership only reflects the fetched history");
+    }
+
+    let format = format!(
+        "--format={}%aN{}%aE{}%ad",
+        RECORD_SEPARATOR, FIELD_SEPARATOR, FIELD_SEPARATOR
+    );
+    let log = match git::git(
+        root,
+        &[
+            "-c",
+            "core.quotePath=false",
+            "log",
+            &format,
+            "--date=short",
+            "--name-only",
+            "--relative",
+            "--no-renames",
+            "--",
+            ".",
+        ],
+    ) {
+        Ok(log) => log,
+        Err(e) => {
+            // e.g. a repository without commits
+            warn!("Could not read git history for ownership: {}", e);
+            return HashMap::new();
+        }
+    };
+
+    let ownership = parse_log(&log, wanted);
+    info!("Collected ownership for {} files", ownership.len());
+    ownership
+}
+
+/// Aggregate `git log` output (newest commit first) per file
+fn parse_log(log: &str, wanted: &HashSet<&str>) -> HashMap<String, Ownership> {
+    let mut commits: HashMap<&str, HashMap<(&str, &str), usize>> = HashMap::new();
+    let mut last_modified: HashMap<&str, &str> = HashMap::new();
+
+    for record in log.split(RECORD_SEPARATOR).filter(|r| !r.trim().is_empty()) {
+        let mut lines = record.lines();
+        let header = lines.next().unwrap_or_default();
+        let mut fields = header.split(FIELD_SEPARATOR);
+        let (Some(name), Some(email), Some(date)) = (fields.next(), fields.next(), fields.next())
+        else {
+            debug!("Unexpected git log record: {:?}", header);
+            continue;
+        };
+
+        for file in lines.map(str::trim).filter(|l| wanted.contains(l)) {
+            *commits
+                .entry(file)
+                .or_default()
+                .entry((name, email))
+                .or_default() += 1;
+            last_modified.entry(file).or_insert(date);
+        }
+    }
+
+    commits
+        .into_iter()
+        .map(|(file, authors)| {
+            let mut contributors: Vec<Contributor> = authors
+                .into_iter()
+                .map(|((name, email), commits)| Contributor {
+                    name: name.to_string(),
+                    email: email.to_string(),
+                    commits,
+                })
+                .collect();
+            contributors.sort_by(|a, b| b.commits.cmp(&a.commits).then(a.name.cmp(&b.name)));
+            contributors.truncate(MAX_CONTRIBUTORS);
+
+            let ownership = Ownership {
+                contributors,
+                last_modified: last_modified.get(file).map(|d| d.to_string()),
+                owners: vec![],
+            };
+            (file.to_string(), ownership)
+        })
+        .collect()
+}
+
+/// CODEOWNERS rules in file order; the last matching rule wins
+struct CodeOwners {
+    rules: Vec<(Gitignore, Vec<String>)>,
+}
+
+impl CodeOwners {
+    fn parse(content: &str) -> Self {
+        let rules = content
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or_default().trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('!'))
+            .filter_map(|line| {
+                let mut parts = line.split_whitespace();
+                let matcher = matcher(parts.next()?)?;
+                Some((matcher, parts.map(str::to_string).collect()))
+            })
+            .collect();
+        Self { rules }
+    }
+
+    /// Owners of a repository-relative path; empty when no rule matches or
+    /// the matching rule removes ownership
+    fn owners(&self, path: &str) -> Vec<String> {
+        self.rules
+            .iter()
+            .rev()
+            .find(|(matcher, _)| matcher.matched_path_or_any_parents(path, false).is_ignore())
+            .map(|(_, owners)| owners.clone())
+            .unwrap_or_default()
+    }
+}
+
+fn load_codeowners(base: &Path) -> Option<CodeOwners> {
+    let path = CODEOWNERS_PATHS
+        .iter()
+        .map(|p| base.join(p))
+        .find(|p| p.is_file())?;
+    match std::fs::read_to_string(&path) {
+        Ok(content) => {
+            debug!("Reading owners from {}", path.display());
+            Some(CodeOwners::parse(&content))
+        }
+        Err(e) => {
+            warn!("Failed to read {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    fn run(dir: &Path, author: &str, args: &[&str]) {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", &format!("user.name={}", author)])
+            .args([
+                "-c",
+                &format!("user.email={}@example.com", author.to_lowercase()),
+            ])
+            .args(args)
+            .env("GIT_AUTHOR_DATE", "2024-05-01T12:00:00Z")
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {:?}", args);
+    }
+
+    #[test]
+    fn test_codeowners_last_match_wins() {
+        let rules = CodeOwners::parse(
+            "# Default owners\n\
+             *        @org/everyone\n\
+             /web/    @org/frontend   # UI\n\
+             *.rs     @org/core @ada\n\
+             /src/generated.rs\n",
+        );
+
+        assert_eq!(rules.owners("README.md"), vec!["@org/everyone"]);
+        assert_eq!(rules.owners("web/api.ts"), vec!["@org/frontend"]);
+        assert_eq!(rules.owners("web/wasm.rs"), vec!["@org/core", "@ada"]);
+        assert!(rules.owners("src/generated.rs").is_empty());
+    }
+
+    #[test]
+    fn test_collect_from_git_and_codeowners() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().canonicalize().unwrap();
+        let root = repo.join("app");
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(repo.join(".github")).unwrap();
+        std::fs::write(repo.join(".github/CODEOWNERS"), "/app/src/ @org/core\n").unwrap();
+
+        run(&repo, "Ada", &["init", "-q"]);
+        std::fs::write(root.join("src/a.rs"), "fn a() {}").unwrap();
+        std::fs::write(root.join("b.rs"), "fn b() {}").unwrap();
+        run(&repo, "Ada", &["add", "."]);
+        run(&repo, "Ada", &["commit", "-qm", "init"]);
+        std::fs::write(root.join("src/a.rs"), "fn a() { }").unwrap();
+        run(&repo, "Grace", &["commit", "-qam", "tweak"]);
+        std::fs::write(root.join("src/a.rs"), "fn a() {  }").unwrap();
+        run(&repo, "Grace", &["commit", "-qam", "tweak again"]);
+
+        let ownership = collect(&root, &["src/a.rs", "b.rs", "new.rs"]);
+
+        let a = &ownership["src/a.rs"];
+        assert_eq!(a.owners, vec!["@org/core"]);
+        assert_eq!(a.last_modified.as_deref(), Some("2024-05-01"));
+        let names: Vec<_> = a
+            .contributors
+            .iter()
+            .map(|c| (c.name.as_str(), c.commits))
+            .collect();
+        assert_eq!(names, vec![("Grace", 2), ("Ada", 1)]);
+        assert_eq!(a.primary_owner().as_deref(), Some("@org/core"));
+
+        let b = &ownership["b.rs"];
+        assert!(b.owners.is_empty());
+        assert_eq!(b.primary_owner().as_deref(), Some("Ada"));
+        assert!(b.summary().contains("Ada <ada@example.com> (1 commit)"));
+
+        // Untracked files have no history
+        assert!(!ownership.contains_key("new.rs"));
+    }
+
+    #[test]
+    fn test_collect_outside_git() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("CODEOWNERS"), "*.rs @solo\n").unwrap();
+
+        let ownership = collect(dir.path(), &["a.rs", "b.ts"]);
+        assert_eq!(ownership["a.rs"].owners, vec!["@solo"]);
+        assert!(ownership["a.rs"].contributors.is_empty());
+        assert!(!ownership.contains_key("b.ts"));
+    }
+
+    #[test]
+    fn test_gaps_by_owner() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("CODEOWNERS"), "/src/ @org/core @ada\n").unwrap();
+        let mapping = BTreeMap::from([
+            ("src/billing/".to_string(), "@org/billing".to_string()),
+            ("*.rs".to_string(), "@org/rust".to_string()),
+        ]);
+        let owners = PathOwners::load(dir.path(), &mapping).unwrap();
+        // The longer mapping pattern wins, then CODEOWNERS applies
+        assert_eq!(owners.owner("src/billing/tax.rs"), "@org/billing");
+        assert_eq!(owners.owner("build.rs"), "@org/rust");
+        assert_eq!(owners.owner("src/api.ts"), "@org/core, @ada");
+        assert_eq!(owners.owner("README.md"), UNASSIGNED);
+
+        let gap = |location: Option<&str>| {
+            let mut gap: Gap = serde_json::from_value(serde_json::json!({
+                "kind": "missing_docs",
+                "severity": "info",
+                "description": "Public fn `f` has no documentation",
+                "location": location,
+            }))
+            .unwrap();
+            gap.owner = None;
+            gap
+        };
+        let mut gaps = vec![
+            gap(Some("src/api.ts:3")),
+            gap(Some("src/billing/tax.rs:1")),
+            gap(Some("src/web.ts:9")),
+            gap(Some("docs/x.md")),
+            gap(None),
+        ];
+        assert!(gaps_by_owner(&gaps).is_empty());
+        owners.attribute(&mut gaps);
+        assert!(owned_by(gaps[0].owner.as_deref().unwrap(), "@ADA"));
+        assert!(!owned_by(gaps[0].owner.as_deref().unwrap(), "@org"));
+
+        let by_owner: Vec<_> = gaps_by_owner(&gaps)
+            .into_iter()
+            .map(|(owner, gaps)| (owner, gaps.len()))
+            .collect();
+        assert_eq!(
+            by_owner,
+            vec![(UNASSIGNED, 2), ("@org/core, @ada", 2), ("@org/billing", 1)]
+        );
+
+        assert!(PathOwners::load(&dir.path().join("src"), &BTreeMap::new()).is_none());
+    }
+}
@@ -0,0 +1,229 @@
+//! Files documented together as one logical module
+//!
+//! A C/C++ header and the source files with the same stem in its directory
+//! (`foo.h` + `foo.c`) are one module, named after the header: the header
+//! has the API, the source the behavior. A Rust `mod.rs` is one module with
+//! the sibling files it declares as private submodules (`mod parse;` →
+//! `parse.rs`); public submodules are API of their own and keep their pages.
+//! Test files are never paired.
+
+use std::collections::HashMap;
+use std::fs;
+use tracing::debug;
+
+use super::discovery::SourceFile;
+
+const HEADER_EXTENSIONS: &[&str] = &["h", "hpp"];
+const C_SOURCE_EXTENSIONS: &[&str] = &["c", "cc", "cpp", "cxx"];
+
+/// Move files that belong to another file's module into its
+/// [`SourceFile::companions`], keeping the order of the rest
+pub fn pair(files: Vec<SourceFile>) -> Vec<SourceFile> {
+    let index: HashMap<&str, usize> = files
+        .iter()
+        .enumerate()
+        .map(|(i, f)| (f.path.as_str(), i))
+        .collect();
+
+    // Primary file of each companion
+    let mut primary_of: HashMap<usize, usize> = HashMap::new();
+    for (i, file) in files.iter().enumerate() {
+        if file.is_test || primary_of.contains_key(&i) {
+            continue;
+        }
+        for companion in companion_paths(file)
+            .iter()
+            .filter_map(|path| index.get(path.as_str()).copied())
+        {
+            if companion != i && !files[companion].is_test && !primary_of.contains_key(&companion) {
+                primary_of.insert(companion, i);
+            }
+        }
+    }
+    if primary_of.is_empty() {
+        return files;
+    }
+
+    let mut companions: HashMap<usize, Vec<SourceFile>> = HashMap::new();
+    let mut kept = Vec::new();
+    for (i, file) in files.into_iter().enumerate() {
+        match primary_of.get(&i) {
+            Some(&primary) => companions.entry(primary).or_default().push(file),
+            None => kept.push((i, file)),
+        }
+    }
+    kept.into_iter()
+        .map(|(i, mut file)| {
+            if let Some(mut companions) = companions.remove(&i) {
+                companions.sort_by(|a, b| a.path.cmp(&b.path));
+                debug!(
+                    "Pairing {} with {}",
+                    file.path,
+                    companions
+                        .iter()
+                        .map(|c| c.path.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+                // A header takes the language of its source: `.h` is also C++'s
+                if let Some(source) = companions.first().filter(|_| is_header(&file.path)) {
+                    file.language = source.language;
+                }
+                file.companions = companions;
+            }
+            file
+        })
+        .collect()
+}
+
+/// Paths of the files that would join `file`'s module, were they discovered
+fn companion_paths(file: &SourceFile) -> Vec<String> {
+    let (dir, name) = match file.path.rsplit_once('/') {
+        Some((dir, name)) => (format!("{}/", dir), name),
+        None => (String::new(), file.path.as_str()),
+    };
+    if name == "mod.rs" {
+        return match fs::read_to_string(&file.abs_path) {
+            Ok(content) => private_submodules(&content)
+                .into_iter()
+                .map(|module| format!("{}{}.rs", dir, module))
+                .collect(),
+            Err(e) => {
+                debug!("Not pairing {}: {}", file.path, e);
+                Vec::new()
+            }
+        };
+    }
+    match name.rsplit_once('.') {
+        Some((stem, extension)) if HEADER_EXTENSIONS.contains(&extension) => C_SOURCE_EXTENSIONS
+            .iter()
+            .map(|source| format!("{}{}.{}", dir, stem, source))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn is_header(path: &str) -> bool {
+    path.rsplit_once('.')
+        .is_some_and(|(_, extension)| HEADER_EXTENSIONS.contains(&extension))
+}
+
+/// Names of the `mod name;` declarations in a Rust file that are neither
+/// `pub` nor `#[cfg(test)]`
+fn private_submodules(content: &str) -> Vec<&str> {
+    let mut modules = Vec::new();
+    let mut test_only = false;
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with("//") {
+            continue;
+        }
+        if line.starts_with("#[") {
+            test_only |= line.replace(' ', "").starts_with("#[cfg(test)]");
+            continue;
+        }
+        let declared = line
+            .strip_prefix("mod ")
+            .and_then(|rest| rest.strip_suffix(';'))
+            .map(str::trim)
+            .filter(|name| name.chars().all(|c| c.is_alphanumeric() || c == '_'));
+        if let Some(name) = declared.filter(|_| !test_only) {
+            modules.push(name);
+        }
+        test_only = false;
+    }
+    modules
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Language;
+    use std::path::Path;
+
+    fn source_file(root: &Path, path: &str, content: &str) -> SourceFile {
+        let abs_path = root.join(path);
+        fs::create_dir_all(abs_path.parent().unwrap()).unwrap();
+        fs::write(&abs_path, content).unwrap();
+        let extension = path.rsplit_once('.').unwrap().1;
+        SourceFile {
+            path: path.to_string(),
+            abs_path,
+            language: Language::from_extension(extension),
+            size: content.len() as u64,
+            is_test: false,
+            modified: None,
+            companions: Vec::new(),
+            detection: Default::default(),
+        }
+    }
+
+    fn modules(files: Vec<SourceFile>) -> Vec<(String, Vec<String>)> {
+        pair(files)
+            .into_iter()
+            .map(|f| (f.path.clone(), f.files()))
+            .collect()
+    }
+
+    #[test]
+    fn test_header_and_source_pairs() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let files = vec![
+            source_file(root, "src/buffer.c", "int len;"),
+            source_file(root, "src/buffer.h", "int len(void);"),
+            source_file(root, "src/main.c", "int main() {}"),
+            source_file(root, "lib/buffer.c", "int other;"),
+            source_file(root, "src/shape.hpp", "class Shape;"),
+            source_file(root, "src/shape.cpp", "Shape::Shape() {}"),
+        ];
+        let paired = pair(files);
+        let shape = paired.iter().find(|f| f.path == "src/shape.hpp").unwrap();
+        assert_eq!(shape.language, Language::Cpp);
+        assert_eq!(
+            modules(paired),
+            [
+                (
+                    "src/buffer.h".to_string(),
+                    vec!["src/buffer.h".to_string(), "src/buffer.c".to_string()]
+                ),
+                ("src/main.c".to_string(), vec![]),
+                ("lib/buffer.c".to_string(), vec![]),
+                (
+                    "src/shape.hpp".to_string(),
+                    vec!["src/shape.hpp".to_string(), "src/shape.cpp".to_string()]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rust_directory_modules() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let files = vec![
+            source_file(
+                root,
+                "src/net/mod.rs",
+                "mod codec;\npub mod client;\n// mod old;\n#[cfg(test)]\nmod tests;\nmod inline { }\npub use codec::Codec;\n",
+            ),
+            source_file(root, "src/net/client.rs", "pub struct Client;"),
+            source_file(root, "src/net/codec.rs", "pub struct Codec;"),
+            source_file(root, "src/net/tests.rs", "#[test] fn t() {}"),
+            source_file(root, "src/net/old.rs", ""),
+            source_file(root, "src/lib.rs", "mod net;"),
+        ];
+        assert_eq!(
+            modules(files),
+            [
+                (
+                    "src/net/mod.rs".to_string(),
+                    vec!["src/net/mod.rs".to_string(), "src/net/codec.rs".to_string()]
+                ),
+                ("src/net/client.rs".to_string(), vec![]),
+                ("src/net/tests.rs".to_string(), vec![]),
+                ("src/net/old.rs".to_string(), vec![]),
+                ("src/lib.rs".to_string(), vec![]),
+            ]
+        );
+    }
+}
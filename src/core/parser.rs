@@ -4,37 +4,197 @@
 //! It extracts exports, imports, and other structural information from source files.
 
 use anyhow::Result;
+use regex::Regex;
+use std::cell::RefCell;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, OnceLock};
+use std::time::Duration;
 use streaming_iterator::StreamingIterator;
-use tree_sitter::{Node, Parser, Query, QueryCursor};
+use tracing::{debug, warn};
+use tree_sitter::{Node, ParseState, Parser, Query, QueryCursor, Tree};
 
-use super::analyzer::{Export, ExportKind, Import};
+use super::analyzer::{
+    Export, ExportKind, FunctionSignature, Import, ImportKind, Param, Visibility,
+};
 use super::discovery::Language;
+use super::features::{self, Flag};
+use super::plugins;
+
+/// Parses taking longer than this are logged and listed in the timings
+pub const DEFAULT_SLOW_PARSE_MS: u64 = 2000;
+/// Parses still running after this are abandoned for [`scan`]
+pub const DEFAULT_PARSE_TIMEOUT_SECS: u64 = 30;
+
+/// Limits set by `analysis.slow_parse_ms` and `analysis.parse_timeout_secs`
+static PARSE_LIMITS: OnceLock<(Duration, Option<Duration>)> = OnceLock::new();
+
+/// Report parses slower than `slow` and abandon those still running after
+/// `timeout` (never when `None`), for the rest of the process. Only the
+/// first call has an effect.
+pub fn set_parse_limits(slow: Duration, timeout: Option<Duration>) {
+    let _ = PARSE_LIMITS.set((slow, timeout));
+}
+
+/// Parses taking at least this long are slow
+pub fn slow_parse() -> Duration {
+    PARSE_LIMITS
+        .get()
+        .map(|(slow, _)| *slow)
+        .unwrap_or(Duration::from_millis(DEFAULT_SLOW_PARSE_MS))
+}
+
+fn parse_timeout() -> Option<Duration> {
+    match PARSE_LIMITS.get() {
+        Some((_, timeout)) => *timeout,
+        None => Some(Duration::from_secs(DEFAULT_PARSE_TIMEOUT_SECS)),
+    }
+}
+
+thread_local! {
+    /// Set on a parse thread that [`parse_file`] may abandon, so tree-sitter
+    /// stops instead of parsing on for nobody
+    static CANCELLED: RefCell<Option<Arc<AtomicBool>>> = const { RefCell::new(None) };
+}
+
+/// Parse a source file and extract structural information. A plugin
+/// registered for `path`'s extension takes precedence over the built-in
+/// parsers. `include_private` also extracts top-level items that are not
+/// exported, marked [`Visibility::Private`].
+///
+/// Parsing runs on its own thread. One that outlasts the parse timeout is
+/// abandoned, and the file is [`scan`]ned instead, with the reason in
+/// [`ParseResult::fallback`]. Either way, the feature flags the file checks
+/// are [`features::mark`]ed.
+pub fn parse_file(
+    path: &Path,
+    content: &str,
+    language: Language,
+    include_private: bool,
+) -> Result<ParseResult> {
+    let mut result = match parse_timeout() {
+        Some(timeout) => parse_within(path, content, language, include_private, timeout),
+        None => parse_now(path, content, language, include_private),
+    }?;
+    features::mark(&mut result, content, language);
+    Ok(result)
+}
+
+/// [`parse_file`] with a parse timeout
+fn parse_within(
+    path: &Path,
+    content: &str,
+    language: Language,
+    include_private: bool,
+    timeout: Duration,
+) -> Result<ParseResult> {
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let (tx, rx) = mpsc::channel();
+    let spawned = {
+        let (path, content, cancelled) =
+            (path.to_path_buf(), content.to_string(), cancelled.clone());
+        std::thread::Builder::new()
+            .name("parse".to_string())
+            .spawn(move || {
+                CANCELLED.with(|c| *c.borrow_mut() = Some(cancelled));
+                let _ = tx.send(parse_now(&path, &content, language, include_private));
+            })
+    };
+    if let Err(e) = spawned {
+        debug!(
+            "No thread to parse {} on ({}); parsing inline",
+            path.display(),
+            e
+        );
+        return parse_now(path, content, language, include_private);
+    }
+
+    match rx.recv_timeout(timeout) {
+        Ok(result) => result,
+        Err(mpsc::RecvTimeoutError::Timeout) => {
+            cancelled.store(true, Ordering::Relaxed);
+            let reason = format!(
+                "parsing did not finish within {}s; exports and imports were found by \
+                 pattern matching and may be incomplete",
+                timeout.as_secs_f64()
+            );
+            warn!("{}: {}", path.display(), reason);
+            let mut result = scan(content, language, include_private);
+            result.fallback = Some(reason);
+            Ok(result)
+        }
+        Err(mpsc::RecvTimeoutError::Disconnected) => {
+            anyhow::bail!("the parser stopped without a result")
+        }
+    }
+}
+
+fn parse_now(
+    path: &Path,
+    content: &str,
+    language: Language,
+    include_private: bool,
+) -> Result<ParseResult> {
+    if let Some(plugin) = plugins::for_path(path) {
+        let mut result = plugins::parse(&plugin, path, content)?;
+        if !include_private {
+            result.exports.retain(|e| e.visibility.is_public());
+        }
+        return Ok(result);
+    }
 
-/// Parse a source file and extract structural information
-pub fn parse_file(content: &str, language: Language) -> Result<ParseResult> {
     match language {
-        Language::Rust => parse_rust(content),
-        Language::TypeScript | Language::JavaScript => parse_js_ts(content, language),
-        _ => Ok(ParseResult {
-            exports: vec![],
-            imports: vec![],
-        }),
+        Language::Rust => parse_rust(content, include_private),
+        Language::TypeScript | Language::JavaScript => {
+            parse_js_ts(content, language, include_private)
+        }
+        _ => Ok(ParseResult::default()),
     }
 }
 
+/// Parse `content`, stopping early when the thread's parse was abandoned
+fn parse_tree(parser: &mut Parser, content: &str) -> Option<Tree> {
+    let cancelled = CANCELLED.with(|c| c.borrow().clone());
+    let mut progress = |_: &ParseState| {
+        cancelled
+            .as_ref()
+            .is_some_and(|c| c.load(Ordering::Relaxed))
+    };
+    let bytes = content.as_bytes();
+    parser.parse_with_options(
+        &mut |i, _| bytes.get(i..).unwrap_or_default(),
+        None,
+        Some(tree_sitter::ParseOptions::new().progress_callback(&mut progress)),
+    )
+}
+
+#[derive(Debug, Default)]
 pub struct ParseResult {
     pub exports: Vec<Export>,
     pub imports: Vec<Import>,
+    /// Why the exports and imports come from [`scan`] rather than the parser
+    pub fallback: Option<String>,
+    /// Feature flags the file checks anywhere, sorted
+    pub flags: Vec<Flag>,
+}
+
+impl ParseResult {
+    pub fn public_exports(&self) -> impl Iterator<Item = &Export> {
+        self.exports.iter().filter(|e| e.visibility.is_public())
+    }
+
+    pub fn internal_items(&self) -> impl Iterator<Item = &Export> {
+        self.exports.iter().filter(|e| !e.visibility.is_public())
+    }
 }
 
 /// Parse a Rust source file
-fn parse_rust(content: &str) -> Result<ParseResult> {
+fn parse_rust(content: &str, include_private: bool) -> Result<ParseResult> {
     let mut parser = Parser::new();
     let language = tree_sitter_rust::LANGUAGE;
     parser.set_language(&language.into())?;
 
-    let tree = parser
-        .parse(content, None)
+    let tree = parse_tree(&mut parser, content)
         .ok_or_else(|| anyhow::anyhow!("Failed to parse Rust file"))?;
 
     let mut exports = Vec::new();
@@ -44,37 +204,55 @@ fn parse_rust(content: &str) -> Result<ParseResult> {
         &language.into(),
         r#"
         (function_item
-          (visibility_modifier) @vis
+          (visibility_modifier)? @vis
           name: (identifier) @name
         ) @func
 
         (struct_item
-          (visibility_modifier) @vis
+          (visibility_modifier)? @vis
           name: (type_identifier) @name
         ) @struct
 
         (enum_item
-          (visibility_modifier) @vis
+          (visibility_modifier)? @vis
           name: (type_identifier) @name
         ) @enum
 
         (type_item
-          (visibility_modifier) @vis
+          (visibility_modifier)? @vis
           name: (type_identifier) @name
         ) @type
 
         (const_item
-          (visibility_modifier) @vis
+          (visibility_modifier)? @vis
           name: (identifier) @name
         ) @const
 
+        (static_item
+          (visibility_modifier)? @vis
+          name: (identifier) @name
+        ) @static
+
+        (union_item
+          (visibility_modifier)? @vis
+          name: (type_identifier) @name
+        ) @union
+
+        (foreign_mod_item
+          (extern_modifier) @abi
+          body: (declaration_list
+            (function_signature_item
+              (visibility_modifier)? @vis
+              name: (identifier) @name
+            ) @extern_fn))
+
         (trait_item
-          (visibility_modifier) @vis
+          (visibility_modifier)? @vis
           name: (type_identifier) @name
         ) @trait
 
         (mod_item
-          (visibility_modifier) @vis
+          (visibility_modifier)? @vis
           name: (identifier) @name
         ) @mod
         "#,
@@ -90,7 +268,7 @@ fn parse_rust(content: &str) -> Result<ParseResult> {
     )?;
 
     let mut cursor = QueryCursor::new();
-    let lines: Vec<&str> = content.lines().collect();
+    let lines = LineIndex::new(content);
 
     {
         let mut matches = cursor.matches(&export_query, tree.root_node(), content.as_bytes());
@@ -101,45 +279,85 @@ fn parse_rust(content: &str) -> Result<ParseResult> {
             let mut name = String::new();
             let mut kind = ExportKind::Function;
             let mut is_pub = false;
+            let mut is_top_level = false;
             let mut line_number = 0;
+            let mut end_line = None;
             let mut signature = None;
+            let mut function_signature = None;
+            let mut abi = None;
+            let mut cfg = None;
 
             for capture in match_.captures {
                 let capture_name = export_query.capture_names()[capture.index as usize];
                 let node = capture.node;
                 let text = node.utf8_text(content.as_bytes()).unwrap_or("");
 
+                if !matches!(capture_name, "vis" | "name" | "abi") {
+                    is_top_level = node.parent().is_some_and(|p| p.kind() == "source_file");
+                    end_line = Some(node.end_position().row + 1);
+                    cfg = rust_cfg(node, content);
+                }
+
                 match capture_name {
                     "vis" => is_pub = text.contains("pub"),
                     "name" => {
                         name = text.to_string();
                         line_number = node.start_position().row + 1;
                     }
-                    "func" => {
-                        kind = ExportKind::Function;
+                    "abi" => abi = Some(text),
+                    "func" | "extern_fn" | "static" => {
+                        kind = if capture_name == "static" {
+                            ExportKind::Static
+                        } else {
+                            ExportKind::Function
+                        };
                         let start = node.start_position().row;
-                        if let Some(line) = lines.get(start) {
-                            signature = Some(line.trim().to_string());
+                        signature = lines.line(start).map(|line| line.trim().to_string());
+                        if capture_name != "static" {
+                            function_signature = rust_function_signature(node, content);
+                        }
+                        // Functions in an extern block keep their ABI, as
+                        // `extern "C" { pub fn f(); }`
+                        if capture_name == "extern_fn" {
+                            signature = signature
+                                .map(|s| format!("{} {{ {} }}", abi.unwrap_or("extern"), s));
                         }
                     }
                     "struct" => kind = ExportKind::Struct,
                     "enum" => kind = ExportKind::Enum,
                     "type" => kind = ExportKind::Type,
                     "const" => kind = ExportKind::Const,
+                    "union" => kind = ExportKind::Union,
                     "trait" => kind = ExportKind::Trait,
                     "mod" => kind = ExportKind::Module,
                     _ => {}
                 }
             }
 
-            if is_pub && !name.is_empty() {
-                let description = extract_doc_comment(content, line_number).unwrap_or_default();
+            // Private items nested in impls, functions or inline modules
+            // are left out even with `include_private`
+            let visibility = if is_pub {
+                Visibility::Public
+            } else if include_private && is_top_level {
+                Visibility::Private
+            } else {
+                continue;
+            };
+            if !name.is_empty() {
+                let description = rust_doc_comment(&lines, line_number).unwrap_or_default();
                 exports.push(Export {
                     name,
                     kind,
                     signature,
                     description,
                     line_number,
+                    end_line,
+                    snippet: None,
+                    visibility,
+                    file: None,
+                    function_signature,
+                    cfg,
+                    examples: Vec::new(),
                 });
             }
         }
@@ -170,21 +388,99 @@ fn parse_rust(content: &str) -> Result<ParseResult> {
                         vec![]
                     };
 
+                    // `pub use` (or `pub(crate) use`) passes the items on
+                    let is_pub = node.parent().is_some_and(|p| {
+                        let mut cursor = p.walk();
+                        let is_pub = p
+                            .children(&mut cursor)
+                            .any(|c| c.kind() == "visibility_modifier");
+                        is_pub
+                    });
                     imports.push(Import {
                         source,
                         items,
                         is_external,
+                        kind: if is_pub {
+                            ImportKind::ReExport
+                        } else {
+                            ImportKind::Runtime
+                        },
                     });
                 }
             }
         }
     }
 
-    Ok(ParseResult { exports, imports })
+    Ok(ParseResult {
+        exports,
+        imports,
+        ..Default::default()
+    })
+}
+
+/// The `cfg` conditions a Rust item is under, as one condition: the file's
+/// `#![cfg(...)]`, then those of the items it is nested in, then its own.
+/// Several are joined into `all(...)`.
+fn rust_cfg(node: Node, content: &str) -> Option<String> {
+    let condition = |attribute: Node| {
+        let text = attribute.utf8_text(content.as_bytes()).ok()?;
+        let inner = text
+            .trim_start_matches('#')
+            .trim_start_matches('!')
+            .trim()
+            .strip_prefix('[')?
+            .strip_suffix(']')?
+            .trim();
+        let condition = inner.strip_prefix("cfg")?.trim_start().strip_prefix('(')?;
+        Some(condition.strip_suffix(')')?.trim().to_string())
+    };
+
+    let mut conditions = Vec::new();
+    let mut item = Some(node);
+    while let Some(current) = item {
+        let mut own = Vec::new();
+        let mut sibling = current.prev_sibling();
+        while let Some(attribute) = sibling {
+            match attribute.kind() {
+                "attribute_item" => own.extend(condition(attribute)),
+                "line_comment" | "block_comment" => {}
+                _ => break,
+            }
+            sibling = attribute.prev_sibling();
+        }
+        own.reverse();
+        conditions.splice(0..0, own);
+        item = current
+            .parent()
+            .filter(|p| p.kind() == "declaration_list")
+            .and_then(|p| p.parent());
+    }
+
+    let root = node.parent().map(|mut root| {
+        while let Some(parent) = root.parent() {
+            root = parent;
+        }
+        root
+    });
+    if let Some(root) = root {
+        let mut cursor = root.walk();
+        let file: Vec<String> = root
+            .children(&mut cursor)
+            .filter(|c| c.kind() == "inner_attribute_item")
+            .filter_map(condition)
+            .collect();
+        conditions.splice(0..0, file);
+    }
+
+    match conditions.len() {
+        0 => None,
+        1 => conditions.pop(),
+        _ => Some(format!("all({})", conditions.join(", "))),
+    }
 }
 
 /// Parse TypeScript/JavaScript using AST walking
-fn parse_js_ts(content: &str, lang: Language) -> Result<ParseResult> {
+fn parse_js_ts(content: &str, lang: Language, include_private: bool) -> Result<ParseResult> {
     let mut parser = Parser::new();
 
     let ts_lang: tree_sitter::Language = if lang == Language::TypeScript {
@@ -195,31 +491,153 @@ fn parse_js_ts(content: &str, lang: Language) -> Result<ParseResult> {
 
     parser.set_language(&ts_lang)?;
 
-    let tree = parser
-        .parse(content, None)
+    let tree = parse_tree(&mut parser, content)
         .ok_or_else(|| anyhow::anyhow!("Failed to parse JS/TS file"))?;
 
     let mut exports = Vec::new();
     let mut imports = Vec::new();
-    let lines: Vec<&str> = content.lines().collect();
+    let lines = LineIndex::new(content);
 
     // Walk the AST to find exports and imports
     walk_node(
         tree.root_node(),
         content,
         &lines,
+        include_private,
         &mut exports,
         &mut imports,
     );
 
-    Ok(ParseResult { exports, imports })
+    Ok(ParseResult {
+        exports,
+        imports,
+        ..Default::default()
+    })
+}
+
+/// Top-level items and imports found line by line with patterns, for files
+/// the parser gave up on. Items declared over several lines are still found
+/// by their first line, but nothing nested is, and there are no signatures
+/// or doc comments.
+pub fn scan(content: &str, language: Language, include_private: bool) -> ParseResult {
+    struct Patterns {
+        rust_item: Regex,
+        rust_use: Regex,
+        js_item: Regex,
+        js_import: Regex,
+    }
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    let patterns = PATTERNS.get_or_init(|| Patterns {
+        rust_item: Regex::new(
+            r#"^(pub(?:\([^)]*\))?\s+)?(?:(?:async|const|unsafe|extern\s+"[^"]*")\s+)*(fn|struct|enum|trait|type|const|static|mod|union)\s+([A-Za-z_][A-Za-z0-9_]*)"#,
+        )
+        .unwrap(),
+        rust_use: Regex::new(r"^(pub(?:\([^)]*\))?\s+)?use\s+([A-Za-z_][A-Za-z0-9_:]*)").unwrap(),
+        js_item: Regex::new(
+            r"^(export\s+(?:default\s+)?)?(?:declare\s+)?(?:abstract\s+)?(?:async\s+)?(function\*?|class|const|let|var|interface|type|enum)\s+([A-Za-z_$][A-Za-z0-9_$]*)",
+        )
+        .unwrap(),
+        js_import: Regex::new(r#"^(import|export)\b.*?\bfrom\s+['"]([^'"]+)['"]|^import\s+['"]([^'"]+)['"]"#)
+            .unwrap(),
+    });
+
+    let mut result = ParseResult::default();
+    for (i, line) in content.lines().enumerate() {
+        let line_number = i + 1;
+        let export = |name: &str, kind, public: bool| Export {
+            name: name.to_string(),
+            kind,
+            signature: None,
+            description: String::new(),
+            line_number,
+            end_line: None,
+            snippet: None,
+            visibility: if public {
+                Visibility::Public
+            } else {
+                Visibility::Private
+            },
+            file: None,
+            function_signature: None,
+            cfg: None,
+            examples: Vec::new(),
+        };
+
+        match language {
+            Language::Rust => {
+                if let Some(c) = patterns.rust_use.captures(line) {
+                    let mut parts = c[2].trim_end_matches("::").split("::");
+                    let source = parts.next().unwrap_or_default();
+                    result.imports.push(Import {
+                        source: source.to_string(),
+                        items: parts.map(str::to_string).collect(),
+                        is_external: !matches!(source, "crate" | "self" | "super"),
+                        kind: if c.get(1).is_some() {
+                            ImportKind::ReExport
+                        } else {
+                            ImportKind::Runtime
+                        },
+                    });
+                } else if let Some(c) = patterns.rust_item.captures(line) {
+                    let public = c.get(1).is_some();
+                    let kind = match &c[2] {
+                        "fn" => ExportKind::Function,
+                        "struct" => ExportKind::Struct,
+                        "enum" => ExportKind::Enum,
+                        "trait" => ExportKind::Trait,
+                        "type" => ExportKind::Type,
+                        "const" => ExportKind::Const,
+                        "static" => ExportKind::Static,
+                        "mod" => ExportKind::Module,
+                        _ => ExportKind::Union,
+                    };
+                    if public || include_private {
+                        result.exports.push(export(&c[3], kind, public));
+                    }
+                }
+            }
+            Language::TypeScript | Language::JavaScript => {
+                if let Some(c) = patterns.js_import.captures(line) {
+                    let (source, kind) = match (c.get(1), c.get(2), c.get(3)) {
+                        (Some(keyword), Some(source), _) if keyword.as_str() == "export" => {
+                            (source.as_str(), ImportKind::ReExport)
+                        }
+                        (_, Some(source), _) => (source.as_str(), ImportKind::Runtime),
+                        (_, _, Some(source)) => (source.as_str(), ImportKind::SideEffect),
+                        _ => continue,
+                    };
+                    result.imports.push(Import {
+                        source: source.to_string(),
+                        items: Vec::new(),
+                        is_external: is_external_source(source),
+                        kind,
+                    });
+                } else if let Some(c) = patterns.js_item.captures(line) {
+                    let public = c.get(1).is_some();
+                    let kind = match c[2].trim_end_matches('*') {
+                        "function" => ExportKind::Function,
+                        "class" => ExportKind::Class,
+                        "interface" | "type" => ExportKind::Type,
+                        "enum" => ExportKind::Enum,
+                        _ => ExportKind::Const,
+                    };
+                    if public || include_private {
+                        result.exports.push(export(&c[3], kind, public));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    result
 }
 
 /// Recursively walk AST nodes to extract exports/imports
 fn walk_node(
     node: Node,
     content: &str,
-    lines: &[&str],
+    lines: &LineIndex,
+    include_private: bool,
     exports: &mut Vec<Export>,
     imports: &mut Vec<Import>,
 ) {
@@ -230,160 +648,451 @@ fn walk_node(
             if let Some(export) = extract_export_from_node(node, content, lines) {
                 exports.push(export);
             }
-        }
-        "import_statement" => {
-            if let Some(import) = extract_import_from_node(node, content) {
+            if let Some(import) = extract_reexport_from_node(node, content) {
                 imports.push(import);
             }
         }
+        // Declarations directly in the program are module-private; exported
+        // ones sit inside an export_statement instead
+        _ if include_private && node.parent().is_some_and(|p| p.kind() == "program") => {
+            if let Some(mut item) = extract_declaration(node, node, content, lines) {
+                item.visibility = Visibility::Private;
+                exports.push(item);
+            }
+        }
+        "import_statement" => imports.extend(extract_imports_from_node(node, content)),
         _ => {}
     }
 
     // Recurse into children
     let mut cursor = node.walk();
     for child in node.children(&mut cursor) {
-        walk_node(child, content, lines, exports, imports);
+        walk_node(child, content, lines, include_private, exports, imports);
     }
 }
 
 /// Extract export info from an export_statement node
-fn extract_export_from_node(node: Node, content: &str, lines: &[&str]) -> Option<Export> {
+fn extract_export_from_node(node: Node, content: &str, lines: &LineIndex) -> Option<Export> {
     let mut cursor = node.walk();
 
     for child in node.children(&mut cursor) {
-        let child_kind = child.kind();
-
-        match child_kind {
-            "function_declaration" | "function" => {
-                if let Some(name_node) = child.child_by_field_name("name") {
-                    let name = name_node.utf8_text(content.as_bytes()).ok()?;
-                    let line = name_node.start_position().row + 1;
-                    let sig = lines
-                        .get(node.start_position().row)
-                        .map(|s| s.trim().to_string());
-                    let desc = extract_jsdoc_comment(content, line);
-
-                    return Some(Export {
-                        name: name.to_string(),
-                        kind: ExportKind::Function,
-                        signature: sig,
-                        description: desc.unwrap_or_default(),
-                        line_number: line,
-                    });
-                }
+        if let Some(export) = extract_declaration(child, node, content, lines) {
+            return Some(export);
+        }
+    }
+
+    None
+}
+
+/// A function, class, variable, type, interface or enum declaration.
+/// `statement` is the node whose first line is used as the signature: the
+/// export statement around `decl`, or `decl` itself.
+fn extract_declaration(
+    decl: Node,
+    statement: Node,
+    content: &str,
+    lines: &LineIndex,
+) -> Option<Export> {
+    match decl.kind() {
+        "function_declaration" | "function" => {
+            if let Some(name_node) = decl.child_by_field_name("name") {
+                let name = name_node.utf8_text(content.as_bytes()).ok()?;
+                let line = name_node.start_position().row + 1;
+                let sig = lines
+                    .line(statement.start_position().row)
+                    .map(|s| s.trim().to_string());
+                let desc = jsdoc_comment(lines, line);
+
+                return Some(Export {
+                    name: name.to_string(),
+                    kind: ExportKind::Function,
+                    signature: sig,
+                    description: desc.unwrap_or_default(),
+                    line_number: line,
+                    end_line: Some(statement.end_position().row + 1),
+                    snippet: None,
+                    visibility: Visibility::Public,
+                    file: None,
+                    function_signature: js_ts_function_signature(decl, content),
+                    cfg: None,
+                    examples: Vec::new(),
+                });
             }
-            "class_declaration" | "class" => {
-                if let Some(name_node) = child.child_by_field_name("name") {
-                    let name = name_node.utf8_text(content.as_bytes()).ok()?;
-                    let line = name_node.start_position().row + 1;
-                    let desc = extract_jsdoc_comment(content, line);
-
-                    return Some(Export {
-                        name: name.to_string(),
-                        kind: ExportKind::Class,
-                        signature: None,
-                        description: desc.unwrap_or_default(),
-                        line_number: line,
-                    });
-                }
+        }
+        "class_declaration" | "class" => {
+            if let Some(name_node) = decl.child_by_field_name("name") {
+                let name = name_node.utf8_text(content.as_bytes()).ok()?;
+                let line = name_node.start_position().row + 1;
+                let desc = jsdoc_comment(lines, line);
+
+                return Some(Export {
+                    name: name.to_string(),
+                    kind: ExportKind::Class,
+                    signature: None,
+                    description: desc.unwrap_or_default(),
+                    line_number: line,
+                    end_line: Some(statement.end_position().row + 1),
+                    snippet: None,
+                    visibility: Visibility::Public,
+                    file: None,
+                    function_signature: None,
+                    cfg: None,
+                    examples: Vec::new(),
+                });
             }
-            "lexical_declaration" => {
-                // const/let declarations
-                let mut decl_cursor = child.walk();
-                for decl_child in child.children(&mut decl_cursor) {
-                    if decl_child.kind() == "variable_declarator" {
-                        if let Some(name_node) = decl_child.child_by_field_name("name") {
-                            let name = name_node.utf8_text(content.as_bytes()).ok()?;
-                            let line = name_node.start_position().row + 1;
-                            let desc = extract_jsdoc_comment(content, line);
-
-                            return Some(Export {
-                                name: name.to_string(),
-                                kind: ExportKind::Const,
-                                signature: None,
-                                description: desc.unwrap_or_default(),
-                                line_number: line,
-                            });
-                        }
+        }
+        "lexical_declaration" => {
+            // const/let declarations
+            let mut decl_cursor = decl.walk();
+            for decl_child in decl.children(&mut decl_cursor) {
+                if decl_child.kind() == "variable_declarator" {
+                    if let Some(name_node) = decl_child.child_by_field_name("name") {
+                        let name = name_node.utf8_text(content.as_bytes()).ok()?;
+                        let line = name_node.start_position().row + 1;
+                        let desc = jsdoc_comment(lines, line);
+
+                        return Some(Export {
+                            name: name.to_string(),
+                            kind: ExportKind::Const,
+                            signature: None,
+                            description: desc.unwrap_or_default(),
+                            line_number: line,
+                            end_line: Some(statement.end_position().row + 1),
+                            snippet: None,
+                            visibility: Visibility::Public,
+                            file: None,
+                            function_signature: None,
+                            cfg: None,
+                            examples: Vec::new(),
+                        });
                     }
                 }
             }
-            "type_alias_declaration" => {
-                if let Some(name_node) = child.child_by_field_name("name") {
-                    let name = name_node.utf8_text(content.as_bytes()).ok()?;
-                    let line = name_node.start_position().row + 1;
-
-                    return Some(Export {
-                        name: name.to_string(),
-                        kind: ExportKind::Type,
-                        signature: None,
-                        description: String::new(),
-                        line_number: line,
-                    });
-                }
+        }
+        "type_alias_declaration" => {
+            if let Some(name_node) = decl.child_by_field_name("name") {
+                let name = name_node.utf8_text(content.as_bytes()).ok()?;
+                let line = name_node.start_position().row + 1;
+
+                return Some(Export {
+                    name: name.to_string(),
+                    kind: ExportKind::Type,
+                    signature: None,
+                    description: String::new(),
+                    line_number: line,
+                    end_line: Some(statement.end_position().row + 1),
+                    snippet: None,
+                    visibility: Visibility::Public,
+                    file: None,
+                    function_signature: None,
+                    cfg: None,
+                    examples: Vec::new(),
+                });
             }
-            "interface_declaration" => {
-                if let Some(name_node) = child.child_by_field_name("name") {
-                    let name = name_node.utf8_text(content.as_bytes()).ok()?;
-                    let line = name_node.start_position().row + 1;
-
-                    return Some(Export {
-                        name: name.to_string(),
-                        kind: ExportKind::Trait,
-                        signature: None,
-                        description: String::new(),
-                        line_number: line,
-                    });
-                }
+        }
+        "interface_declaration" => {
+            if let Some(name_node) = decl.child_by_field_name("name") {
+                let name = name_node.utf8_text(content.as_bytes()).ok()?;
+                let line = name_node.start_position().row + 1;
+
+                return Some(Export {
+                    name: name.to_string(),
+                    kind: ExportKind::Trait,
+                    signature: None,
+                    description: String::new(),
+                    line_number: line,
+                    end_line: Some(statement.end_position().row + 1),
+                    snippet: None,
+                    visibility: Visibility::Public,
+                    file: None,
+                    function_signature: None,
+                    cfg: None,
+                    examples: Vec::new(),
+                });
             }
-            "enum_declaration" => {
-                if let Some(name_node) = child.child_by_field_name("name") {
-                    let name = name_node.utf8_text(content.as_bytes()).ok()?;
-                    let line = name_node.start_position().row + 1;
-
-                    return Some(Export {
-                        name: name.to_string(),
-                        kind: ExportKind::Enum,
-                        signature: None,
-                        description: String::new(),
-                        line_number: line,
-                    });
-                }
+        }
+        "enum_declaration" => {
+            if let Some(name_node) = decl.child_by_field_name("name") {
+                let name = name_node.utf8_text(content.as_bytes()).ok()?;
+                let line = name_node.start_position().row + 1;
+
+                return Some(Export {
+                    name: name.to_string(),
+                    kind: ExportKind::Enum,
+                    signature: None,
+                    description: String::new(),
+                    line_number: line,
+                    end_line: Some(statement.end_position().row + 1),
+                    snippet: None,
+                    visibility: Visibility::Public,
+                    file: None,
+                    function_signature: None,
+                    cfg: None,
+                    examples: Vec::new(),
+                });
             }
-            _ => {}
         }
+        _ => {}
     }
 
     None
 }
 
-/// Extract import info from an import_statement node
-fn extract_import_from_node(node: Node, content: &str) -> Option<Import> {
+/// Parameters, return type and generics of a Rust `function_item` or
+/// `function_signature_item`; `None` where the tree has errors
+fn rust_function_signature(node: Node, content: &str) -> Option<FunctionSignature> {
+    if node.has_error() {
+        return None;
+    }
+    let text = |node: Node| node.utf8_text(content.as_bytes()).ok().map(str::to_string);
+
+    let mut params = Vec::new();
+    let parameters = node.child_by_field_name("parameters")?;
+    let mut cursor = parameters.walk();
+    for param in parameters.named_children(&mut cursor) {
+        match param.kind() {
+            // `&mut self` is a `&mut Self` named `self`
+            "self_parameter" => params.push(Param {
+                name: "self".to_string(),
+                ty: Some(
+                    text(param)?
+                        .trim_start_matches("mut ")
+                        .replace("self", "Self"),
+                ),
+            }),
+            "parameter" => params.push(Param {
+                name: text(param.child_by_field_name("pattern")?)?,
+                ty: Some(text(param.child_by_field_name("type")?)?),
+            }),
+            "variadic_parameter" => params.push(Param {
+                name: "...".to_string(),
+                ty: None,
+            }),
+            "attribute_item" | "line_comment" | "block_comment" => {}
+            _ => return None,
+        }
+    }
+
+    Some(FunctionSignature {
+        params,
+        return_type: node.child_by_field_name("return_type").and_then(text),
+        generics: generics(node, content),
+    })
+}
+
+/// Parameters, return type and generics of a TypeScript or JavaScript
+/// function declaration; JavaScript parameters have no type
+fn js_ts_function_signature(node: Node, content: &str) -> Option<FunctionSignature> {
+    if node.has_error() {
+        return None;
+    }
+    let text = |node: Node| node.utf8_text(content.as_bytes()).ok().map(str::to_string);
+    // A `type_annotation` is `: T`
+    let annotation =
+        |node: Node| text(node).map(|t| t.trim_start_matches(':').trim_start().to_string());
+
+    let mut params = Vec::new();
+    let parameters = node.child_by_field_name("parameters")?;
+    let mut cursor = parameters.walk();
+    for param in parameters.named_children(&mut cursor) {
+        match param.kind() {
+            "required_parameter" | "optional_parameter" => params.push(Param {
+                name: text(param.child_by_field_name("pattern")?)?,
+                ty: param.child_by_field_name("type").and_then(annotation),
+            }),
+            "identifier" | "object_pattern" | "array_pattern" | "rest_pattern" => {
+                params.push(Param {
+                    name: text(param)?,
+                    ty: None,
+                })
+            }
+            "assignment_pattern" => params.push(Param {
+                name: text(param.child_by_field_name("left")?)?,
+                ty: None,
+            }),
+            "comment" => {}
+            _ => return None,
+        }
+    }
+
+    Some(FunctionSignature {
+        params,
+        return_type: node.child_by_field_name("return_type").and_then(annotation),
+        generics: generics(node, content),
+    })
+}
+
+/// The `type_parameters` of a declaration, one per parameter
+fn generics(node: Node, content: &str) -> Vec<String> {
+    let Some(parameters) = node.child_by_field_name("type_parameters") else {
+        return Vec::new();
+    };
+    let mut cursor = parameters.walk();
+    let generics = parameters
+        .named_children(&mut cursor)
+        .filter(|p| !p.kind().ends_with("comment"))
+        .filter_map(|p| p.utf8_text(content.as_bytes()).ok())
+        .map(str::to_string)
+        .collect();
+    generics
+}
+
+/// Extract the imports of an import_statement node. The names marked
+/// `type` in an otherwise runtime import make a separate type-only import.
+fn extract_imports_from_node(node: Node, content: &str) -> Vec<Import> {
+    let Some(source) = module_source(node, content) else {
+        return Vec::new();
+    };
+    let text = |n: Node| n.utf8_text(content.as_bytes()).ok().map(str::to_string);
+
+    let mut type_only = false;
+    let mut has_clause = false;
+    let mut items = Vec::new();
+    let mut types = Vec::new();
     let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        match child.kind() {
+            "type" => type_only = true,
+            "import_clause" => {
+                has_clause = true;
+                let mut clause_cursor = child.walk();
+                for part in child.named_children(&mut clause_cursor) {
+                    match part.kind() {
+                        // The default export, under its local name
+                        "identifier" => items.extend(text(part)),
+                        "named_imports" => {
+                            let mut names_cursor = part.walk();
+                            for specifier in part.named_children(&mut names_cursor) {
+                                let Some(name) = specifier.child_by_field_name("name") else {
+                                    continue;
+                                };
+                                let mut specifier_cursor = specifier.walk();
+                                let is_type = specifier
+                                    .children(&mut specifier_cursor)
+                                    .any(|c| c.kind() == "type");
+                                if is_type { &mut types } else { &mut items }.extend(text(name));
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let import = |items, kind| Import {
+        source: source.to_string(),
+        items,
+        is_external: is_external_source(source),
+        kind,
+    };
+    if !has_clause {
+        return vec![import(Vec::new(), ImportKind::SideEffect)];
+    }
+    if type_only {
+        items.append(&mut types);
+        return vec![import(items, ImportKind::TypeOnly)];
+    }
+    let mut imports = Vec::new();
+    if !items.is_empty() || types.is_empty() {
+        imports.push(import(items, ImportKind::Runtime));
+    }
+    if !types.is_empty() {
+        imports.push(import(types, ImportKind::TypeOnly));
+    }
+    imports
+}
 
+/// The import made by `export { .. } from` or `export * from`, if the
+/// export_statement has a source
+fn extract_reexport_from_node(node: Node, content: &str) -> Option<Import> {
+    // `export default "x"` has a string child too, but no `from`
+    let mut from_cursor = node.walk();
+    if !node.children(&mut from_cursor).any(|c| c.kind() == "from") {
+        return None;
+    }
+    let source = module_source(node, content)?;
+    let mut type_only = false;
+    let mut items = Vec::new();
+    let mut cursor = node.walk();
     for child in node.children(&mut cursor) {
-        if child.kind() == "string" || child.kind().contains("string") {
-            let source_raw = child.utf8_text(content.as_bytes()).ok()?;
-            let source = source_raw.trim_matches(|c| c == '"' || c == '\'' || c == '`');
-
-            let is_external =
-                !source.starts_with('.') && !source.starts_with('/') && !source.starts_with("@/");
-
-            return Some(Import {
-                source: source.to_string(),
-                items: vec![],
-                is_external,
-            });
+        match child.kind() {
+            "type" => type_only = true,
+            "export_clause" => {
+                let mut clause_cursor = child.walk();
+                items.extend(
+                    child
+                        .named_children(&mut clause_cursor)
+                        .filter_map(|s| s.child_by_field_name("name"))
+                        .filter_map(|n| n.utf8_text(content.as_bytes()).ok())
+                        .map(str::to_string),
+                );
+            }
+            _ => {}
         }
     }
 
-    None
+    Some(Import {
+        source: source.to_string(),
+        items,
+        is_external: is_external_source(source),
+        kind: if type_only {
+            ImportKind::TypeOnly
+        } else {
+            ImportKind::ReExport
+        },
+    })
+}
+
+/// The unquoted module an import or export statement reads from
+fn module_source<'a>(node: Node, content: &'a str) -> Option<&'a str> {
+    let mut cursor = node.walk();
+    let string = node.child_by_field_name("source").or_else(|| {
+        node.children(&mut cursor)
+            .find(|c| c.kind().contains("string"))
+    })?;
+    let source_raw = string.utf8_text(content.as_bytes()).ok()?;
+    Some(source_raw.trim_matches(|c| c == '"' || c == '\'' || c == '`'))
+}
+
+fn is_external_source(source: &str) -> bool {
+    !source.starts_with('.') && !source.starts_with('/') && !source.starts_with("@/")
+}
+
+/// Byte offsets of line starts, so single lines can be looked up without
+/// splitting the whole file into a `Vec<&str>` for every export
+pub struct LineIndex<'a> {
+    content: &'a str,
+    starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(content: &'a str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(content.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self { content, starts }
+    }
+
+    /// Line `row` (0-based) without its line ending
+    pub fn line(&self, row: usize) -> Option<&'a str> {
+        let start = *self.starts.get(row)?;
+        let end = self
+            .starts
+            .get(row + 1)
+            .map_or(self.content.len(), |next| next - 1);
+        Some(self.content[start..end].trim_end_matches('\r'))
+    }
 }
 
 /// Extract doc comments (Rust style ///)
 pub fn extract_doc_comment(content: &str, line: usize) -> Option<String> {
-    let lines: Vec<&str> = content.lines().collect();
-    if line == 0 || line > lines.len() {
+    rust_doc_comment(&LineIndex::new(content), line)
+}
+
+fn rust_doc_comment(lines: &LineIndex, line: usize) -> Option<String> {
+    if line == 0 || lines.line(line - 1).is_none() {
         return None;
     }
 
@@ -392,7 +1101,7 @@ pub fn extract_doc_comment(content: &str, line: usize) -> Option<String> {
 
     while current > 0 {
         let prev_idx = current - 1;
-        let prev_line = lines.get(prev_idx)?;
+        let prev_line = lines.line(prev_idx)?;
         let trimmed = prev_line.trim();
 
         if trimmed.starts_with("///") {
@@ -417,9 +1126,8 @@ pub fn extract_doc_comment(content: &str, line: usize) -> Option<String> {
 }
 
 /// Extract JSDoc comments (JS/TS style /** */)
-fn extract_jsdoc_comment(content: &str, line: usize) -> Option<String> {
-    let lines: Vec<&str> = content.lines().collect();
-    if line == 0 || line > lines.len() {
+fn jsdoc_comment(lines: &LineIndex, line: usize) -> Option<String> {
+    if line == 0 || lines.line(line - 1).is_none() {
         return None;
     }
 
@@ -429,7 +1137,7 @@ fn extract_jsdoc_comment(content: &str, line: usize) -> Option<String> {
 
     while current > 0 {
         let prev_idx = current - 1;
-        let prev_line = lines.get(prev_idx)?;
+        let prev_line = lines.line(prev_idx)?;
         let trimmed = prev_line.trim();
 
         if trimmed.ends_with("*/") && !in_block {
@@ -484,6 +1192,79 @@ fn extract_jsdoc_comment(content: &str, line: usize) -> Option<String> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_scan() {
+        let rust = "use std::fmt;\npub use crate::core::Analysis;\n\n/// Docs\npub async fn run() {}\npub(crate) struct Config {\n    pub field: u8,\n}\nfn helper() {}\nimpl Config {\n    pub fn new() {}\n}\n";
+        let result = scan(rust, Language::Rust, false);
+        let names: Vec<_> = result
+            .exports
+            .iter()
+            .map(|e| (e.name.as_str(), e.kind, e.line_number))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("run", ExportKind::Function, 5),
+                ("Config", ExportKind::Struct, 6)
+            ]
+        );
+        assert_eq!(result.imports.len(), 2);
+        assert_eq!(result.imports[0].source, "std");
+        assert!(result.imports[0].is_external);
+        assert_eq!(result.imports[1].items, vec!["core", "Analysis"]);
+        assert_eq!(result.imports[1].kind, ImportKind::ReExport);
+        assert!(!result.imports[1].is_external);
+        assert_eq!(scan(rust, Language::Rust, true).internal_items().count(), 1);
+
+        let ts = "import { a } from './a';\nimport 'polyfill';\nexport * from \"../b\";\nexport default async function main() {}\nexport interface Props {}\nconst local = 1;\n";
+        let result = scan(ts, Language::TypeScript, false);
+        let names: Vec<_> = result.exports.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["main", "Props"]);
+        let imports: Vec<_> = result
+            .imports
+            .iter()
+            .map(|i| (i.source.as_str(), i.kind, i.is_external))
+            .collect();
+        assert_eq!(
+            imports,
+            vec![
+                ("./a", ImportKind::Runtime, false),
+                ("polyfill", ImportKind::SideEffect, true),
+                ("../b", ImportKind::ReExport, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_timeout_falls_back_to_scan() {
+        let mut content = String::from("use crate::util;\n");
+        for i in 0..20_000 {
+            content.push_str(&format!("pub fn f{}(x: u32) -> u32 {{ x + {} }}\n", i, i));
+        }
+        let result = parse_within(
+            Path::new("big.rs"),
+            &content,
+            Language::Rust,
+            false,
+            Duration::from_micros(1),
+        )
+        .unwrap();
+        assert!(result.fallback.unwrap().contains("pattern matching"));
+        assert_eq!(result.exports.len(), 20_000);
+        assert_eq!(result.imports[0].source, "crate");
+
+        let result = parse_within(
+            Path::new("small.rs"),
+            "pub fn f() {}\n",
+            Language::Rust,
+            false,
+            Duration::from_secs(30),
+        )
+        .unwrap();
+        assert!(result.fallback.is_none());
+        assert!(result.exports[0].signature.is_some());
+    }
+
     #[test]
     fn test_parse_rust_function() {
         let content = r#"
@@ -494,7 +1275,7 @@ pub fn hello_world(name: &str) -> String {
 
 fn private_func() {}
 "#;
-        let result = parse_rust(content).unwrap();
+        let result = parse_rust(content, false).unwrap();
         assert_eq!(result.exports.len(), 1);
         assert_eq!(result.exports[0].name, "hello_world");
         assert!(matches!(result.exports[0].kind, ExportKind::Function));
@@ -510,11 +1291,149 @@ pub struct TestStruct {
 
 struct PrivateStruct {}
 "#;
-        let result = parse_rust(content).unwrap();
+        let result = parse_rust(content, false).unwrap();
         assert_eq!(result.exports.len(), 1);
         assert_eq!(result.exports[0].name, "TestStruct");
     }
 
+    #[test]
+    fn test_parse_rust_ffi_surface() {
+        let content = r#"
+/// Calls made so far
+pub static CALLS: AtomicUsize = AtomicUsize::new(0);
+static PRIVATE: u8 = 0;
+
+/// Either view of a register
+#[repr(C)]
+pub union Register {
+    pub word: u32,
+    pub bytes: [u8; 4],
+}
+
+/// Entry point for C callers
+#[no_mangle]
+pub extern "C" fn cda_init(flags: u32) -> i32 {
+    0
+}
+
+extern "C" {
+    /// Provided by the host
+    pub fn host_log(message: *const c_char);
+    fn host_private();
+}
+"#;
+        let result = parse_rust(content, false).unwrap();
+        let exports: Vec<_> = result
+            .exports
+            .iter()
+            .map(|e| {
+                (
+                    e.name.as_str(),
+                    e.kind,
+                    e.signature.as_deref(),
+                    e.description.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            exports,
+            vec![
+                (
+                    "CALLS",
+                    ExportKind::Static,
+                    Some("pub static CALLS: AtomicUsize = AtomicUsize::new(0);"),
+                    "Calls made so far"
+                ),
+                (
+                    "Register",
+                    ExportKind::Union,
+                    None,
+                    "Either view of a register"
+                ),
+                (
+                    "cda_init",
+                    ExportKind::Function,
+                    Some("pub extern \"C\" fn cda_init(flags: u32) -> i32 {"),
+                    "Entry point for C callers"
+                ),
+                (
+                    "host_log",
+                    ExportKind::Function,
+                    Some("extern \"C\" { pub fn host_log(message: *const c_char); }"),
+                    "Provided by the host"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_export_end_lines() {
+        let rust = "pub struct Point {\n    x: i32,\n}\n\npub fn origin() -> Point {\n    Point { x: 0 }\n}\n";
+        let exports = parse_rust(rust, false).unwrap().exports;
+        let ends: Vec<_> = exports
+            .iter()
+            .map(|e| (e.line_number, e.end_line))
+            .collect();
+        assert_eq!(ends, vec![(1, Some(3)), (5, Some(7))]);
+
+        let ts = "export const LIMITS = {\n  max: 3,\n};\n\nexport function run() {}\n";
+        let exports = parse_js_ts(ts, Language::TypeScript, false)
+            .unwrap()
+            .exports;
+        let ends: Vec<_> = exports
+            .iter()
+            .map(|e| (e.line_number, e.end_line))
+            .collect();
+        assert_eq!(ends, vec![(1, Some(3)), (5, Some(5))]);
+    }
+
+    #[test]
+    fn test_parse_rust_include_private() {
+        let content = r#"
+pub fn run() {}
+
+/// Reads the settings file
+fn load_config() -> Config {
+    fn nested() {}
+    Config
+}
+
+struct Config;
+
+impl Config {
+    pub fn new() -> Self { Config }
+    fn validate(&self) {}
+}
+"#;
+        let public = parse_rust(content, false).unwrap();
+        let names: Vec<_> = public.exports.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["run", "new"]);
+
+        let all = parse_rust(content, true).unwrap();
+        let private: Vec<_> = all
+            .internal_items()
+            .map(|e| (e.name.as_str(), e.kind, e.line_number))
+            .collect();
+        assert_eq!(
+            private,
+            [
+                ("load_config", ExportKind::Function, 5),
+                ("Config", ExportKind::Struct, 10)
+            ]
+        );
+        assert_eq!(all.public_exports().count(), 2);
+        let load_config = all
+            .exports
+            .iter()
+            .find(|e| e.name == "load_config")
+            .unwrap();
+        assert_eq!(load_config.description, "Reads the settings file");
+        assert_eq!(
+            load_config.signature.as_deref(),
+            Some("fn load_config() -> Config {")
+        );
+    }
+
     #[test]
     fn test_parse_rust_imports() {
         let content = r#"
@@ -522,7 +1441,7 @@ use std::collections::HashMap;
 use crate::core::analyzer;
 use super::discovery::Language;
 "#;
-        let result = parse_rust(content).unwrap();
+        let result = parse_rust(content, false).unwrap();
         assert_eq!(result.imports.len(), 3);
         assert!(result.imports[0].is_external);
     }
@@ -540,21 +1459,201 @@ export class MyClass {
 
 export const MY_CONST = 42;
 "#;
-        let result = parse_js_ts(content, Language::TypeScript).unwrap();
+        let result = parse_js_ts(content, Language::TypeScript, false).unwrap();
         assert!(result.exports.len() >= 2); // At least function and class
 
         let names: Vec<&str> = result.exports.iter().map(|e| e.name.as_str()).collect();
         assert!(names.contains(&"greet") || names.contains(&"MyClass"));
     }
 
+    fn params(signature: &FunctionSignature) -> Vec<(&str, Option<&str>)> {
+        signature
+            .params
+            .iter()
+            .map(|p| (p.name.as_str(), p.ty.as_deref()))
+            .collect()
+    }
+
+    #[test]
+    fn test_rust_function_signatures() {
+        let content = r#"
+pub fn get<'a, T: Clone>(items: &'a [T], (x, y): (usize, usize)) -> Option<&'a T>
+where
+    T: Default,
+{
+    None
+}
+
+pub struct S;
+impl S {
+    pub fn take(&mut self, n: u32) {}
+}
+
+extern "C" {
+    pub fn host_log(message: *const c_char, ...) -> i32;
+}
+
+pub const MAX: usize = 3;
+"#;
+        let result = parse_rust(content, false).unwrap();
+        let signature = |name: &str| {
+            result
+                .exports
+                .iter()
+                .find(|e| e.name == name)
+                .unwrap()
+                .function_signature
+                .as_ref()
+        };
+
+        let get = signature("get").unwrap();
+        assert_eq!(
+            params(get),
+            [
+                ("items", Some("&'a [T]")),
+                ("(x, y)", Some("(usize, usize)"))
+            ]
+        );
+        assert_eq!(get.return_type.as_deref(), Some("Option<&'a T>"));
+        assert_eq!(get.generics, ["'a", "T: Clone"]);
+
+        let take = signature("take").unwrap();
+        assert_eq!(
+            params(take),
+            [("self", Some("&mut Self")), ("n", Some("u32"))]
+        );
+        assert_eq!(take.return_type, None);
+        assert!(take.generics.is_empty());
+
+        let host_log = signature("host_log").unwrap();
+        assert_eq!(
+            params(host_log),
+            [("message", Some("*const c_char")), ("...", None)]
+        );
+        assert_eq!(host_log.return_type.as_deref(), Some("i32"));
+
+        assert!(signature("S").is_none());
+        assert!(signature("MAX").is_none());
+    }
+
+    #[test]
+    fn test_js_ts_function_signatures() {
+        let content = r#"
+export function pick<T, K extends keyof T>(obj: T, keys?: K[], ...rest: string[]): Pick<T, K> {
+    return obj;
+}
+
+export function broken(a: number, b: ): void {}
+"#;
+        let result = parse_js_ts(content, Language::TypeScript, false).unwrap();
+        let pick = result.exports[0].function_signature.as_ref().unwrap();
+        assert_eq!(
+            params(pick),
+            [
+                ("obj", Some("T")),
+                ("keys", Some("K[]")),
+                ("...rest", Some("string[]"))
+            ]
+        );
+        assert_eq!(pick.return_type.as_deref(), Some("Pick<T, K>"));
+        assert_eq!(pick.generics, ["T", "K extends keyof T"]);
+        // The raw line stays when the parameters can't be taken apart
+        let broken = result.exports.iter().find(|e| e.name == "broken").unwrap();
+        assert!(broken.function_signature.is_none());
+        assert!(broken.signature.is_some());
+
+        let js = "export function add(a, b = 1, { c }) { return a + b; }\n";
+        let result = parse_js_ts(js, Language::JavaScript, false).unwrap();
+        let add = result.exports[0].function_signature.as_ref().unwrap();
+        assert_eq!(params(add), [("a", None), ("b", None), ("{ c }", None)]);
+        assert_eq!(add.return_type, None);
+    }
+
+    #[test]
+    fn test_parse_typescript_include_private() {
+        let content = r#"
+export function greet(name: string): string {
+    return format(name);
+}
+
+/**
+ * Wraps a name in a greeting
+ */
+function format(name: string): string {
+    const prefix = "Hello";
+    return `${prefix}, ${name}!`;
+}
+
+const DEFAULT_NAME = "world";
+interface Options { loud: boolean }
+"#;
+        let public = parse_js_ts(content, Language::TypeScript, false).unwrap();
+        assert_eq!(public.exports.len(), 1);
+
+        let all = parse_js_ts(content, Language::TypeScript, true).unwrap();
+        let exported: Vec<_> = all.public_exports().map(|e| e.name.as_str()).collect();
+        assert_eq!(exported, ["greet"]);
+        let private: Vec<_> = all
+            .internal_items()
+            .map(|e| (e.name.as_str(), e.kind))
+            .collect();
+        assert_eq!(
+            private,
+            [
+                ("format", ExportKind::Function),
+                ("DEFAULT_NAME", ExportKind::Const),
+                ("Options", ExportKind::Trait)
+            ]
+        );
+        let format = all.exports.iter().find(|e| e.name == "format").unwrap();
+        assert_eq!(format.description, "Wraps a name in a greeting");
+        assert_eq!(
+            format.signature.as_deref(),
+            Some("function format(name: string): string {")
+        );
+    }
+
     #[test]
     fn test_parse_typescript_imports() {
         let content = r#"
 import { foo } from './local';
 import bar from 'external-package';
 "#;
-        let result = parse_js_ts(content, Language::TypeScript).unwrap();
-        assert!(result.imports.len() >= 1);
+        let result = parse_js_ts(content, Language::TypeScript, false).unwrap();
+        assert!(!result.imports.is_empty());
+    }
+
+    #[test]
+    fn test_import_kinds() {
+        let content = r#"
+import { parse, type Options } from './parse';
+import type { Config } from './config';
+import './polyfill';
+export { render, type Theme } from './render';
+export * from './util';
+export default "not a re-export";
+"#;
+        let result = parse_js_ts(content, Language::TypeScript, false).unwrap();
+        let imports: Vec<_> = result
+            .imports
+            .iter()
+            .map(|i| (i.source.as_str(), i.items.join(","), i.kind))
+            .collect();
+        assert_eq!(
+            imports,
+            [
+                ("./parse", "parse".into(), ImportKind::Runtime),
+                ("./parse", "Options".into(), ImportKind::TypeOnly),
+                ("./config", "Config".into(), ImportKind::TypeOnly),
+                ("./polyfill", String::new(), ImportKind::SideEffect),
+                ("./render", "render,Theme".into(), ImportKind::ReExport),
+                ("./util", String::new(), ImportKind::ReExport),
+            ]
+        );
+
+        let rust = parse_rust("pub use crate::lex::Token;\nuse std::fmt;\n", false).unwrap();
+        let kinds: Vec<_> = rust.imports.iter().map(|i| i.kind).collect();
+        assert_eq!(kinds, [ImportKind::ReExport, ImportKind::Runtime]);
     }
 
     #[test]
@@ -568,4 +1667,14 @@ pub fn foo() {}
         assert!(doc.is_some());
         assert!(doc.unwrap().contains("doc comment"));
     }
+
+    #[test]
+    fn test_line_index() {
+        let lines = LineIndex::new("first\r\nsecond\n\nlast");
+        assert_eq!(lines.line(0), Some("first"));
+        assert_eq!(lines.line(1), Some("second"));
+        assert_eq!(lines.line(2), Some(""));
+        assert_eq!(lines.line(3), Some("last"));
+        assert_eq!(lines.line(4), None);
+    }
 }
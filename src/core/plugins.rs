@@ -0,0 +1,430 @@
+//! External-command extractors for languages without a tree-sitter parser
+//!
+//! A plugin is a command configured under `[plugins.<extension>]`. It gets a
+//! file's path as an argument (or its content on stdin) and prints the
+//! exports and imports as JSON:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "exports": [{ "name": "parse", "kind": "function", "line": 3,
+//!                 "signature": "parse(input)", "description": "..." }],
+//!   "imports": [{ "source": "./lexer", "items": ["Token"], "external": false }]
+//! }
+//! ```
+//!
+//! `kind` takes the names used in `analysis.json`. An export may also set
+//! `"visibility": "private"` for items only shown with `--include-private`,
+//! and `end_line` so that it gets a source snippet.
+//! Plugins are registered once
+//! at startup from the config; [`parse`] then runs the one registered for a
+//! file's extension, killing it after its timeout.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+use super::analyzer::{Export, ExportKind, Import, ImportKind, Visibility};
+use super::parser::ParseResult;
+
+/// Plugin output schema version this build understands
+pub const SCHEMA_VERSION: u32 = 1;
+
+pub const DEFAULT_TIMEOUT_SECS: u64 = 10;
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Replaced with the file path in a plugin's arguments
+const PATH_PLACEHOLDER: &str = "{path}";
+
+/// How much of unparseable plugin output is quoted in the error
+const OUTPUT_SNIPPET_LEN: usize = 200;
+
+/// One `[plugins.<extension>]` table
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[non_exhaustive]
+pub struct Plugin {
+    /// Program and arguments; `{path}` is replaced with the file path, which
+    /// is otherwise appended when `input` is `path`
+    pub command: Vec<String>,
+    #[serde(default)]
+    pub input: PluginInput,
+    pub timeout_secs: Option<u64>,
+    /// Larger files are not sent to the plugin
+    pub max_file_size: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum PluginInput {
+    /// The file path is passed as an argument
+    #[default]
+    Path,
+    /// The file content is written to the plugin's stdin
+    Stdin,
+}
+
+impl Plugin {
+    pub fn new(command: Vec<String>) -> Self {
+        Self {
+            command,
+            input: PluginInput::default(),
+            timeout_secs: None,
+            max_file_size: None,
+        }
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS))
+    }
+
+    /// The command as typed, for messages
+    pub fn display(&self) -> String {
+        self.command.join(" ")
+    }
+}
+
+fn registry() -> &'static RwLock<HashMap<String, Arc<Plugin>>> {
+    static REGISTRY: OnceLock<RwLock<HashMap<String, Arc<Plugin>>>> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+fn normalize_extension(extension: &str) -> String {
+    extension.trim_start_matches('.').to_lowercase()
+}
+
+/// Use `plugin` for files with `extension` (without the dot), replacing any
+/// plugin registered for it before
+pub fn register(extension: &str, plugin: Plugin) {
+    registry()
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(normalize_extension(extension), Arc::new(plugin));
+}
+
+/// The plugin registered for `extension`, if any
+pub fn lookup(extension: &str) -> Option<Arc<Plugin>> {
+    registry()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&normalize_extension(extension))
+        .cloned()
+}
+
+pub fn is_registered(extension: &str) -> bool {
+    lookup(extension).is_some()
+}
+
+/// The plugin for `path`'s extension, if any
+pub fn for_path(path: &Path) -> Option<Arc<Plugin>> {
+    path.extension().and_then(|e| e.to_str()).and_then(lookup)
+}
+
+/// Run `plugin` on a file and parse what it prints
+pub fn parse(plugin: &Plugin, path: &Path, content: &str) -> Result<ParseResult> {
+    let limit = plugin.max_file_size.unwrap_or(DEFAULT_MAX_FILE_SIZE);
+    if content.len() as u64 > limit {
+        anyhow::bail!(
+            "plugin `{}`: file is {} bytes, over its max_file_size of {}",
+            plugin.display(),
+            content.len(),
+            limit
+        );
+    }
+
+    let stdout = run(plugin, path, content)
+        .with_context(|| format!("plugin `{}` failed", plugin.display()))?;
+    parse_output(&stdout).with_context(|| {
+        format!(
+            "plugin `{}` printed invalid output for {}",
+            plugin.display(),
+            path.display()
+        )
+    })
+}
+
+/// Spawn the plugin and collect its stdout, killing it after the timeout
+fn run(plugin: &Plugin, path: &Path, content: &str) -> Result<String> {
+    let (program, args) = plugin
+        .command
+        .split_first()
+        .context("the command is empty")?;
+
+    let path_arg = path.display().to_string();
+    let mut args: Vec<String> = args
+        .iter()
+        .map(|a| a.replace(PATH_PLACEHOLDER, &path_arg))
+        .collect();
+    let has_placeholder = plugin.command.iter().any(|a| a.contains(PATH_PLACEHOLDER));
+    if plugin.input == PluginInput::Path && !has_placeholder {
+        args.push(path_arg);
+    }
+
+    let mut child = Command::new(program)
+        .args(&args)
+        .stdin(match plugin.input {
+            PluginInput::Stdin => Stdio::piped(),
+            PluginInput::Path => Stdio::null(),
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("could not start `{}`", program))?;
+
+    // Write and read on threads so a plugin that fills a pipe cannot
+    // deadlock us, and the timeout below still applies
+    let stdin = child.stdin.take().map(|mut stdin| {
+        let content = content.to_string();
+        std::thread::spawn(move || {
+            // A plugin may exit without reading everything
+            let _ = stdin.write_all(content.as_bytes());
+        })
+    });
+    let stdout = child.stdout.take().map(|mut out| {
+        std::thread::spawn(move || {
+            let mut buf = Vec::new();
+            let _ = out.read_to_end(&mut buf);
+            buf
+        })
+    });
+    let stderr = child.stderr.take().map(|mut err| {
+        std::thread::spawn(move || {
+            let mut buf = Vec::new();
+            let _ = err.read_to_end(&mut buf);
+            buf
+        })
+    });
+
+    let deadline = Instant::now() + plugin.timeout();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            anyhow::bail!("timed out after {}s", plugin.timeout().as_secs());
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    };
+
+    if let Some(handle) = stdin {
+        let _ = handle.join();
+    }
+    let stdout = stdout.and_then(|h| h.join().ok()).unwrap_or_default();
+    let stderr = stderr.and_then(|h| h.join().ok()).unwrap_or_default();
+
+    if !status.success() {
+        let stderr = String::from_utf8_lossy(&stderr);
+        anyhow::bail!("exited with {}: {}", status, stderr.trim());
+    }
+    String::from_utf8(stdout).context("output is not valid UTF-8")
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Output {
+    /// Checked before the rest of the output is parsed
+    #[allow(dead_code)]
+    version: u32,
+    #[serde(default)]
+    exports: Vec<OutputExport>,
+    #[serde(default)]
+    imports: Vec<OutputImport>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OutputExport {
+    name: String,
+    kind: ExportKind,
+    line: usize,
+    end_line: Option<usize>,
+    signature: Option<String>,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    visibility: Visibility,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OutputImport {
+    source: String,
+    #[serde(default)]
+    items: Vec<String>,
+    #[serde(default)]
+    external: bool,
+    #[serde(default)]
+    kind: ImportKind,
+}
+
+/// Check plugin output against the schema and convert it
+fn parse_output(stdout: &str) -> Result<ParseResult> {
+    // Read the version first so a newer schema gets a clear message rather
+    // than an unknown-field error
+    let version = serde_json::from_str::<serde_json::Value>(stdout)
+        .map_err(|e| anyhow::anyhow!("{} in output starting {:?}", e, snippet(stdout)))?
+        .get("version")
+        .and_then(|v| v.as_u64());
+    match version {
+        Some(v) if v == SCHEMA_VERSION as u64 => {}
+        Some(v) => anyhow::bail!(
+            "schema version {} is not supported; expected {}",
+            v,
+            SCHEMA_VERSION
+        ),
+        None => anyhow::bail!(
+            "missing `version`; expected {{\"version\": {}, \"exports\": [...], \"imports\": [...]}}",
+            SCHEMA_VERSION
+        ),
+    }
+
+    let output: Output = serde_json::from_str(stdout)?;
+
+    let mut exports = Vec::with_capacity(output.exports.len());
+    for (i, export) in output.exports.into_iter().enumerate() {
+        if export.name.trim().is_empty() {
+            anyhow::bail!("exports[{}] has an empty name", i);
+        }
+        if export.line == 0 {
+            anyhow::bail!("exports[{}] (`{}`): lines start at 1", i, export.name);
+        }
+        exports.push(Export {
+            name: export.name,
+            kind: export.kind,
+            signature: export.signature,
+            description: export.description,
+            line_number: export.line,
+            end_line: export.end_line,
+            snippet: None,
+            visibility: export.visibility,
+            file: None,
+            function_signature: None,
+            cfg: None,
+            examples: Vec::new(),
+        });
+    }
+
+    let mut imports = Vec::with_capacity(output.imports.len());
+    for (i, import) in output.imports.into_iter().enumerate() {
+        if import.source.trim().is_empty() {
+            anyhow::bail!("imports[{}] has an empty source", i);
+        }
+        imports.push(Import {
+            source: import.source,
+            items: import.items,
+            is_external: import.external,
+            kind: import.kind,
+        });
+    }
+
+    Ok(ParseResult {
+        exports,
+        imports,
+        ..Default::default()
+    })
+}
+
+fn snippet(output: &str) -> String {
+    let output = output.trim();
+    match output.char_indices().nth(OUTPUT_SNIPPET_LEN) {
+        Some((end, _)) => format!("{}...", &output[..end]),
+        None => output.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn sample_plugin() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/plugins/dsl-extractor.sh")
+    }
+
+    fn sh(script: &str) -> Plugin {
+        Plugin::new(vec!["sh".into(), "-c".into(), script.into()])
+    }
+
+    #[test]
+    fn test_sample_plugin_by_path_and_stdin() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/plugins/rules.dsl");
+        let content = std::fs::read_to_string(&path).unwrap();
+
+        let by_path = Plugin::new(vec!["sh".into(), sample_plugin().display().to_string()]);
+        let mut by_stdin = by_path.clone();
+        by_stdin.input = PluginInput::Stdin;
+
+        for plugin in [by_path, by_stdin] {
+            let result = parse(&plugin, &path, &content).unwrap();
+            let names: Vec<_> = result.exports.iter().map(|e| e.name.as_str()).collect();
+            assert_eq!(names, ["greet", "Shipping"]);
+            assert_eq!(result.exports[0].kind, ExportKind::Function);
+            assert_eq!(result.exports[0].line_number, 3);
+            assert_eq!(result.exports[1].kind, ExportKind::Type);
+            assert_eq!(result.imports[0].source, "./common");
+            assert!(!result.imports[0].is_external);
+        }
+    }
+
+    #[test]
+    fn test_invalid_output_is_reported() {
+        let path = Path::new("rules.dsl");
+
+        let err = parse(&sh("echo 'not json'"), path, "").unwrap_err();
+        let message = format!("{:#}", err);
+        assert!(
+            message.contains("invalid output for rules.dsl"),
+            "{}",
+            message
+        );
+        assert!(message.contains("\"not json\""), "{}", message);
+
+        let err = parse(&sh(r#"echo '{"version": 2}'"#), path, "").unwrap_err();
+        assert!(format!("{:#}", err).contains("schema version 2 is not supported"));
+
+        let err = parse(&sh(r#"echo '{"exports": []}'"#), path, "").unwrap_err();
+        assert!(format!("{:#}", err).contains("missing `version`"));
+
+        let bad_kind =
+            r#"echo '{"version": 1, "exports": [{"name": "x", "kind": "macro", "line": 1}]}'"#;
+        let err = parse(&sh(bad_kind), path, "").unwrap_err();
+        assert!(format!("{:#}", err).contains("unknown variant `macro`"));
+
+        let err = parse(&sh("echo oops >&2; exit 3"), path, "").unwrap_err();
+        let message = format!("{:#}", err);
+        assert!(message.contains("exit status: 3"), "{}", message);
+        assert!(message.contains("oops"), "{}", message);
+    }
+
+    #[test]
+    fn test_timeout_and_size_limit() {
+        let mut slow = sh("sleep 5");
+        slow.timeout_secs = Some(1);
+        let started = Instant::now();
+        let err = parse(&slow, Path::new("a.dsl"), "").unwrap_err();
+        assert!(format!("{:#}", err).contains("timed out after 1s"));
+        assert!(started.elapsed() < Duration::from_secs(4));
+
+        let mut small = sh(r#"echo '{"version": 1}'"#);
+        small.max_file_size = Some(4);
+        assert!(parse(&small, Path::new("a.dsl"), "abcd").is_ok());
+        let err = parse(&small, Path::new("a.dsl"), "abcde").unwrap_err();
+        assert!(err.to_string().contains("over its max_file_size of 4"));
+    }
+
+    #[test]
+    fn test_registry_normalizes_extensions() {
+        register(".RegTest", sh("true"));
+        assert!(is_registered("regtest"));
+        assert!(for_path(Path::new("dir/file.regtest")).is_some());
+        assert!(!is_registered("unregistered"));
+    }
+}
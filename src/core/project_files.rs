@@ -0,0 +1,389 @@
+//! Config and doc files: what each config file is for, and the title of
+//! each document
+//!
+//! Classification goes by file name and location, and titles come from the
+//! first heading, so no LLM call is needed. [`ConfigFile::summary`] is left
+//! for a later pass that analyzes selected config files in depth.
+
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use super::discovery::FileInventory;
+
+/// How far into a document to look for its title
+const TITLE_SEARCH_LINES: usize = 40;
+/// How far into a compose file to count services
+pub(crate) const COMPOSE_SEARCH_LINES: usize = 1000;
+
+/// What a config file configures. Serialized as [`ConfigKind::name`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum ConfigKind {
+    /// Package manifests, compiler and bundler settings, task runners
+    Build,
+    /// Continuous integration pipelines
+    Ci,
+    /// Container images and compose files
+    Container,
+    /// Linters and formatters
+    Lint,
+    Other,
+}
+
+impl ConfigKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            ConfigKind::Build => "build",
+            ConfigKind::Ci => "ci",
+            ConfigKind::Container => "container",
+            ConfigKind::Lint => "lint",
+            ConfigKind::Other => "other",
+        }
+    }
+
+    /// Label for the CODEBASE.md listing
+    pub fn label(self) -> &'static str {
+        match self {
+            ConfigKind::Build => "build",
+            ConfigKind::Ci => "CI",
+            ConfigKind::Container => "container",
+            ConfigKind::Lint => "lint",
+            ConfigKind::Other => "config",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct ConfigFile {
+    pub path: String,
+    pub kind: ConfigKind,
+    /// A detail read from the file, e.g. the services of a compose file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// One-line description from a deep pass; not produced yet
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct DocFile {
+    pub path: String,
+    /// The document's top-level heading, if it has one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+/// The inventory's config files, classified
+pub fn config_files(inventory: &FileInventory) -> Vec<ConfigFile> {
+    let root = Path::new(&inventory.root);
+    let mut files: Vec<ConfigFile> = inventory
+        .config_files
+        .iter()
+        .map(|path| {
+            let kind = classify_config(path);
+            let detail = match kind {
+                ConfigKind::Container if is_compose_file(path) => {
+                    read_head(&root.join(path), COMPOSE_SEARCH_LINES)
+                        .map(|lines| compose_services(&lines).len())
+                        .filter(|&n| n > 0)
+                        .map(|n| format!("{} services", n))
+                }
+                _ => None,
+            };
+            ConfigFile {
+                path: path.clone(),
+                kind,
+                detail,
+                summary: None,
+            }
+        })
+        .collect();
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    files
+}
+
+/// The inventory's doc files, with their titles
+pub fn doc_files(inventory: &FileInventory) -> Vec<DocFile> {
+    let root = Path::new(&inventory.root);
+    let mut files: Vec<DocFile> = inventory
+        .doc_files
+        .iter()
+        .map(|path| DocFile {
+            path: path.clone(),
+            title: read_head(&root.join(path), TITLE_SEARCH_LINES)
+                .and_then(|lines| doc_title(&lines)),
+        })
+        .collect();
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    files
+}
+
+/// What a config file is for, from its name and the directory it is in
+pub fn classify_config(path: &str) -> ConfigKind {
+    let lower = path.to_lowercase();
+    let name = lower.rsplit('/').next().unwrap_or(&lower);
+
+    let ci_dirs = [
+        ".github/workflows/",
+        ".circleci/",
+        ".buildkite/",
+        ".gitlab/",
+    ];
+    if ci_dirs
+        .iter()
+        .any(|dir| lower.starts_with(dir) || lower.contains(&format!("/{}", dir)))
+        || matches!(
+            name,
+            ".gitlab-ci.yml"
+                | ".travis.yml"
+                | "azure-pipelines.yml"
+                | "bitbucket-pipelines.yml"
+                | "jenkinsfile"
+                | "appveyor.yml"
+        )
+    {
+        return ConfigKind::Ci;
+    }
+
+    if name == "dockerfile"
+        || name.starts_with("dockerfile.")
+        || name.ends_with(".dockerfile")
+        || name == ".dockerignore"
+        || is_compose_file(path)
+    {
+        return ConfigKind::Container;
+    }
+
+    let lint_prefixes = [
+        ".eslintrc",
+        ".prettierrc",
+        ".stylelintrc",
+        ".markdownlint",
+        ".golangci",
+        ".pylintrc",
+        "eslint.config.",
+        "prettier.config.",
+    ];
+    if lint_prefixes.iter().any(|prefix| name.starts_with(prefix))
+        || matches!(
+            name,
+            "rustfmt.toml"
+                | ".rustfmt.toml"
+                | "clippy.toml"
+                | ".clippy.toml"
+                | "ruff.toml"
+                | ".ruff.toml"
+                | ".flake8"
+                | ".editorconfig"
+                | "biome.json"
+                | ".rubocop.yml"
+                | ".pre-commit-config.yaml"
+        )
+    {
+        return ConfigKind::Lint;
+    }
+
+    let build_prefixes = [
+        "webpack.config.",
+        "vite.config.",
+        "rollup.config.",
+        "babel.config.",
+        "tsconfig",
+    ];
+    if build_prefixes.iter().any(|prefix| name.starts_with(prefix))
+        || matches!(
+            name,
+            "package.json"
+                | "cargo.toml"
+                | "pyproject.toml"
+                | "setup.cfg"
+                | "go.mod"
+                | "makefile"
+                | "justfile"
+                | "build.gradle"
+                | "pom.xml"
+                | "gemfile"
+                | "rust-toolchain.toml"
+        )
+    {
+        return ConfigKind::Build;
+    }
+
+    ConfigKind::Other
+}
+
+pub(crate) fn is_compose_file(path: &str) -> bool {
+    let lower = path.to_lowercase();
+    let name = lower.rsplit('/').next().unwrap_or(&lower);
+    (name.starts_with("docker-compose") || name.starts_with("compose."))
+        && (name.ends_with(".yml") || name.ends_with(".yaml"))
+}
+
+/// Up to `limit` lines of a text file, or `None` if it can't be read
+pub(crate) fn read_head(path: &Path, limit: usize) -> Option<Vec<String>> {
+    let file = File::open(path).ok()?;
+    Some(
+        BufReader::new(file)
+            .lines()
+            .map_while(Result::ok)
+            .take(limit)
+            .collect(),
+    )
+}
+
+/// A service of a compose file, with the image it runs or the build context
+/// it is made from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ComposeService {
+    pub name: String,
+    pub image: Option<String>,
+    pub build: Option<String>,
+}
+
+/// Keys one level under the top-level `services:` of a compose file
+pub(crate) fn compose_services(lines: &[String]) -> Vec<ComposeService> {
+    let mut in_services = false;
+    let mut indent = None;
+    let mut services: Vec<ComposeService> = Vec::new();
+    for line in lines {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let depth = line.len() - trimmed.len();
+        if depth == 0 {
+            in_services = trimmed.trim_end() == "services:";
+            continue;
+        }
+        if !in_services {
+            continue;
+        }
+        let indent = *indent.get_or_insert(depth);
+        if depth == indent {
+            if let Some(name) = trimmed.trim_end().strip_suffix(':') {
+                services.push(ComposeService {
+                    name: name.trim_matches(|c| c == '"' || c == '\'').to_string(),
+                    image: None,
+                    build: None,
+                });
+            }
+            continue;
+        }
+        // `image: x` and `build: x` of the service above; a `build:` block
+        // without a value is left out
+        let Some(service) = services.last_mut() else {
+            continue;
+        };
+        let value = |key: &str| {
+            trimmed
+                .strip_prefix(key)
+                .map(|v| v.trim().trim_matches(|c| c == '"' || c == '\''))
+                .filter(|v| !v.is_empty())
+                .map(String::from)
+        };
+        if let Some(image) = value("image:") {
+            service.image.get_or_insert(image);
+        } else if let Some(build) = value("build:") {
+            service.build.get_or_insert(build);
+        }
+    }
+    services
+}
+
+/// The top-level heading: Markdown `# Title` (or a `===` underline, as in
+/// reStructuredText and Setext), or AsciiDoc `= Title`
+pub fn doc_title(lines: &[String]) -> Option<String> {
+    let mut previous: Option<&str> = None;
+    let mut in_front_matter = false;
+    for (i, line) in lines.iter().enumerate() {
+        let line = line.trim_end();
+        if i == 0 && line == "---" {
+            in_front_matter = true;
+            continue;
+        }
+        if in_front_matter {
+            in_front_matter = line != "---";
+            continue;
+        }
+        if let Some(title) = line.strip_prefix("# ").or_else(|| line.strip_prefix("= ")) {
+            return Some(title.trim().to_string()).filter(|t| !t.is_empty());
+        }
+        if let Some(text) = previous.filter(|p| !p.trim().is_empty()) {
+            if line.len() >= 3 && line.chars().all(|c| c == '=') {
+                return Some(text.trim().to_string());
+            }
+        }
+        previous = Some(line);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_classify_config() {
+        for (path, kind) in [
+            ("Cargo.toml", ConfigKind::Build),
+            ("web/package.json", ConfigKind::Build),
+            ("tsconfig.base.json", ConfigKind::Build),
+            (".github/workflows/ci.yml", ConfigKind::Ci),
+            (".gitlab-ci.yml", ConfigKind::Ci),
+            ("Dockerfile", ConfigKind::Container),
+            ("deploy/docker-compose.prod.yaml", ConfigKind::Container),
+            (".eslintrc", ConfigKind::Lint),
+            ("rustfmt.toml", ConfigKind::Lint),
+            ("config/settings.yaml", ConfigKind::Other),
+        ] {
+            assert_eq!(classify_config(path), kind, "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_compose_services() {
+        let compose = lines(
+            "version: '3'\nservices:\n  web:\n    image: nginx\n    ports:\n      - 80:80\n  db:\n    image: postgres\n\n  # cache:\n  redis:\n    image: redis\nvolumes:\n  data:\n",
+        );
+        let services = compose_services(&compose);
+        assert_eq!(services.len(), 3);
+        assert_eq!(
+            services[0],
+            ComposeService {
+                name: "web".to_string(),
+                image: Some("nginx".to_string()),
+                build: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_doc_title() {
+        assert_eq!(
+            doc_title(&lines("# Architecture\n\nText")).as_deref(),
+            Some("Architecture")
+        );
+        assert_eq!(
+            doc_title(&lines("---\ntitle: x\n---\n\n# 1. Record decisions\n")).as_deref(),
+            Some("1. Record decisions")
+        );
+        assert_eq!(
+            doc_title(&lines("Install\n=======\n")).as_deref(),
+            Some("Install")
+        );
+        assert_eq!(
+            doc_title(&lines("= User Guide\n")).as_deref(),
+            Some("User Guide")
+        );
+        assert_eq!(doc_title(&lines("## Only a subheading\ntext\n")), None);
+    }
+}
@@ -0,0 +1,218 @@
+//! Source files renamed since the previous run
+//!
+//! A file of the previous run that is gone is matched with a new file by
+//! content hash first, then by git's rename detection, which also finds
+//! files that were edited as they moved. Only exact renames keep their
+//! earlier analysis; the others are analyzed again, but reported as renames
+//! rather than as a removed and an added file. Only the primary file of a
+//! paired module is hashed, so a paired module is never an exact rename.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use tracing::debug;
+
+use super::discovery::{relative_path, FileInventory, SourceFile};
+use super::git;
+use super::source;
+
+/// A source file that moved from `from` to `to`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Rename {
+    pub from: String,
+    pub to: String,
+    /// The content didn't change, so the earlier analysis was carried forward
+    pub exact: bool,
+}
+
+/// Match files of the previous run that are no longer in `inventory` with
+/// the inventory's new files. `previous` maps each earlier path to its
+/// content hash, when one was recorded.
+pub fn detect(
+    previous: &HashMap<String, Option<String>>,
+    inventory: &FileInventory,
+) -> Vec<Rename> {
+    let current: HashSet<&str> = inventory
+        .source_files
+        .iter()
+        .map(|f| f.path.as_str())
+        .collect();
+    let mut removed: Vec<(&str, Option<&str>)> = previous
+        .iter()
+        .filter(|(path, _)| !current.contains(path.as_str()))
+        .map(|(path, hash)| (path.as_str(), hash.as_deref()))
+        .collect();
+    let mut added: Vec<&SourceFile> = inventory
+        .source_files
+        .iter()
+        .filter(|f| !previous.contains_key(&f.path))
+        .collect();
+    if removed.is_empty() || added.is_empty() {
+        return Vec::new();
+    }
+    removed.sort();
+
+    // By content: the first removed file with a hash takes it
+    let mut by_hash: HashMap<&str, &str> = HashMap::new();
+    for &(path, hash) in &removed {
+        if let Some(hash) = hash {
+            by_hash.entry(hash).or_insert(path);
+        }
+    }
+    let mut renames = Vec::new();
+    added.retain(|file| {
+        if !file.companions.is_empty() {
+            return true;
+        }
+        let Ok(hash) = source::content_hash(&file.abs_path) else {
+            return true;
+        };
+        match by_hash.remove(hash.as_str()) {
+            Some(from) => {
+                renames.push(Rename {
+                    from: from.to_string(),
+                    to: file.path.clone(),
+                    exact: true,
+                });
+                false
+            }
+            None => true,
+        }
+    });
+    removed.retain(|(path, _)| !renames.iter().any(|r| r.from == *path));
+
+    if !removed.is_empty() && !added.is_empty() {
+        renames.extend(detect_with_git(&inventory.root, &removed, &added));
+    }
+    renames.sort_by(|a, b| a.to.cmp(&b.to));
+    renames
+}
+
+/// Renames among `removed` and `added` that git saw, uncommitted or in
+/// recent commits; none outside a git repository
+fn detect_with_git(
+    root: &str,
+    removed: &[(&str, Option<&str>)],
+    added: &[&SourceFile],
+) -> Vec<Rename> {
+    let root = match Path::new(root).canonicalize() {
+        Ok(root) => root.display().to_string(),
+        Err(_) => return Vec::new(),
+    };
+    let renames = match git::renames(Path::new(&root)) {
+        Ok(renames) => renames,
+        Err(e) => {
+            debug!("No rename detection from git: {}", e);
+            return Vec::new();
+        }
+    };
+
+    let mut removed: HashSet<&str> = removed.iter().map(|(path, _)| *path).collect();
+    let mut added: HashSet<&str> = added.iter().map(|f| f.path.as_str()).collect();
+    let mut found = Vec::new();
+    for (from, to) in renames {
+        let from = relative_path(&from.display().to_string(), &root);
+        let to = relative_path(&to.display().to_string(), &root);
+        if removed.contains(from.as_str()) && added.contains(to.as_str()) {
+            removed.remove(from.as_str());
+            added.remove(to.as_str());
+            found.push(Rename {
+                from,
+                to,
+                exact: false,
+            });
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Language;
+    use std::fs;
+    use std::process::Command;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=t", "-c", "user.email=t@t"])
+            .args(args)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {:?}", args);
+    }
+
+    fn inventory(root: &Path, paths: &[&str]) -> FileInventory {
+        FileInventory {
+            root: root.display().to_string(),
+            source_files: paths
+                .iter()
+                .map(|path| SourceFile {
+                    path: path.to_string(),
+                    abs_path: root.join(path),
+                    language: Language::Rust,
+                    size: 0,
+                    is_test: false,
+                    modified: None,
+                    companions: Vec::new(),
+                    detection: Default::default(),
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_detect_renames() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        git(root, &["init", "-q"]);
+        fs::write(root.join("moved.rs"), "pub fn moved() {}\n").unwrap();
+        fs::write(root.join("edited.rs"), "pub fn edited() {}\n".repeat(20)).unwrap();
+        fs::write(root.join("gone.rs"), "pub fn gone() {}\n").unwrap();
+        git(root, &["add", "."]);
+        git(root, &["commit", "-qm", "init"]);
+        let hash = |path: &str| Some(source::content_hash(&root.join(path)).unwrap());
+        let previous = HashMap::from([
+            ("moved.rs".to_string(), hash("moved.rs")),
+            ("edited.rs".to_string(), hash("edited.rs")),
+            ("gone.rs".to_string(), hash("gone.rs")),
+            ("kept.rs".to_string(), None),
+        ]);
+
+        // Moved with CRLF line endings, which don't count as a change
+        fs::remove_file(root.join("moved.rs")).unwrap();
+        fs::write(root.join("src_moved.rs"), "pub fn moved() {}\r\n").unwrap();
+        git(root, &["mv", "edited.rs", "renamed.rs"]);
+        fs::write(
+            root.join("renamed.rs"),
+            "pub fn edited() {}\n".repeat(20) + "pub fn more() {}\n",
+        )
+        .unwrap();
+        git(root, &["add", "renamed.rs"]);
+        git(root, &["rm", "-q", "gone.rs"]);
+        fs::write(root.join("new.rs"), "pub fn new() {}\n").unwrap();
+        fs::write(root.join("kept.rs"), "").unwrap();
+
+        let inventory = inventory(root, &["kept.rs", "new.rs", "renamed.rs", "src_moved.rs"]);
+        assert_eq!(
+            detect(&previous, &inventory),
+            [
+                Rename {
+                    from: "edited.rs".to_string(),
+                    to: "renamed.rs".to_string(),
+                    exact: false,
+                },
+                Rename {
+                    from: "moved.rs".to_string(),
+                    to: "src_moved.rs".to_string(),
+                    exact: true,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,575 @@
+//! How to build and run the project, from its config files
+//!
+//! Commands come from Cargo binaries, package.json scripts, Makefile and
+//! justfile targets, compose services and Python entry points. Each gets a
+//! one-line description from the file itself; with `--mode deep`,
+//! [`describe`] replaces them with the LLM's in one call. A command is run
+//! from the directory of the file it comes from.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use tracing::{debug, info, warn};
+
+use super::discovery::FileInventory;
+use super::project_files::{self, COMPOSE_SEARCH_LINES};
+use super::redact;
+use crate::llm::{self, LlmConfig, LlmProvider, Message, Role};
+
+/// Longest description kept from a script or recipe line
+const MAX_DESCRIPTION: usize = 80;
+
+/// Where a command comes from. Serialized in kebab case: `cargo-bin`,
+/// `npm-script`, `make-target`, `just-recipe`, `compose-service`,
+/// `python-entry-point`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[non_exhaustive]
+pub enum CommandKind {
+    CargoBin,
+    NpmScript,
+    MakeTarget,
+    JustRecipe,
+    ComposeService,
+    PythonEntryPoint,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct RunCommand {
+    /// The command line, e.g. `cargo run --bin cda` or `npm run build`
+    pub command: String,
+    pub kind: CommandKind,
+    /// The config file it comes from
+    pub source: String,
+    /// What it does, in one line
+    pub description: Option<String>,
+    /// The description was written by the LLM
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub inferred: bool,
+    /// A CI job runs it; see [`super::ci`]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub in_ci: bool,
+}
+
+/// Commands found in one config file: command line, kind, description
+type Found = Vec<(String, CommandKind, Option<String>)>;
+
+/// The commands defined by the inventory's config files, in config file order
+pub fn extract(inventory: &FileInventory) -> Vec<RunCommand> {
+    let root = Path::new(&inventory.root);
+    let mut sources: Vec<&String> = inventory.config_files.iter().collect();
+    sources.sort();
+
+    let mut commands = Vec::new();
+    for source in sources {
+        let name = source.rsplit('/').next().unwrap_or(source).to_lowercase();
+        let path = root.join(source);
+        let found = if project_files::is_compose_file(source) {
+            project_files::read_head(&path, COMPOSE_SEARCH_LINES)
+                .map(|lines| compose_commands(&lines))
+                .unwrap_or_default()
+        } else {
+            let parse: fn(&str, &Path) -> Found = match name.as_str() {
+                "cargo.toml" => cargo_commands,
+                "package.json" => |content, _| npm_commands(content),
+                "makefile" => |content, _| make_commands(content),
+                "justfile" => |content, _| just_commands(content),
+                "pyproject.toml" => |content, _| python_commands(content),
+                _ => continue,
+            };
+            match fs::read_to_string(&path) {
+                Ok(content) => parse(&content, path.parent().unwrap_or(root)),
+                Err(e) => {
+                    debug!("Not reading commands from {}: {}", source, e);
+                    continue;
+                }
+            }
+        };
+        commands.extend(
+            found
+                .into_iter()
+                .map(|(command, kind, description)| RunCommand {
+                    command,
+                    kind,
+                    source: source.clone(),
+                    description,
+                    inferred: false,
+                    in_ci: false,
+                }),
+        );
+    }
+    commands
+}
+
+/// `cargo run --bin` for the `[[bin]]` targets, `src/main.rs` and
+/// `src/bin/*.rs` of a package manifest in `dir`
+fn cargo_commands(content: &str, dir: &Path) -> Found {
+    let manifest: toml::Table = match content.parse() {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            debug!("Not a valid Cargo.toml: {}", e);
+            return Vec::new();
+        }
+    };
+    let package = manifest.get("package").and_then(|p| p.as_table());
+    let package_name = package.and_then(|p| p.get("name")).and_then(|n| n.as_str());
+    let package_description = package
+        .and_then(|p| p.get("description"))
+        .and_then(|d| d.as_str());
+
+    let mut bins: Vec<String> = manifest
+        .get("bin")
+        .and_then(|b| b.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|bin| bin.get("name")?.as_str().map(String::from))
+        .collect();
+    if let Some(name) = package_name.filter(|_| dir.join("src/main.rs").is_file()) {
+        bins.push(name.to_string());
+    }
+    if let Ok(entries) = fs::read_dir(dir.join("src/bin")) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|e| e == "rs") {
+                if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+                    bins.push(stem.to_string());
+                }
+            }
+        }
+    }
+    bins.sort();
+    bins.dedup();
+
+    let single = bins.len() == 1;
+    bins.into_iter()
+        .map(|bin| {
+            let description = match package_description {
+                Some(description) if single => description.to_string(),
+                _ => format!("Builds and runs the `{}` binary", bin),
+            };
+            (
+                format!("cargo run --bin {}", bin),
+                CommandKind::CargoBin,
+                Some(description),
+            )
+        })
+        .collect()
+}
+
+/// `npm run` for each of package.json's scripts
+fn npm_commands(content: &str) -> Found {
+    let package: serde_json::Value = match serde_json::from_str(content) {
+        Ok(package) => package,
+        Err(e) => {
+            debug!("Not a valid package.json: {}", e);
+            return Vec::new();
+        }
+    };
+    let Some(scripts) = package.get("scripts").and_then(|s| s.as_object()) else {
+        return Vec::new();
+    };
+    scripts
+        .iter()
+        .map(|(name, script)| {
+            (
+                format!("npm run {}", name),
+                CommandKind::NpmScript,
+                script.as_str().map(runs),
+            )
+        })
+        .collect()
+}
+
+/// `make` for each target of a Makefile. A target's description is its
+/// `## comment`, the comment line above it, or its first recipe line.
+fn make_commands(content: &str) -> Found {
+    recipes(content, is_make_target)
+        .into_iter()
+        .map(|(name, description)| {
+            (
+                format!("make {}", name),
+                CommandKind::MakeTarget,
+                description,
+            )
+        })
+        .collect()
+}
+
+/// `just` for each recipe of a justfile, described like Makefile targets
+fn just_commands(content: &str) -> Found {
+    recipes(content, |line| {
+        let name = line.split([' ', ':']).next().unwrap_or("");
+        // `name param: …` or `name:`, but not the assignment `name := …`
+        (!line.contains(":=") && line.contains(':')).then_some(name)
+    })
+    .into_iter()
+    .map(|(name, description)| {
+        (
+            format!("just {}", name),
+            CommandKind::JustRecipe,
+            description,
+        )
+    })
+    .collect()
+}
+
+/// The target a Makefile line defines, if it defines one that isn't special
+/// (`.PHONY`) or a pattern rule (`%.o`)
+fn is_make_target(line: &str) -> Option<&str> {
+    let (target, rest) = line.split_once(':')?;
+    let target = target.trim();
+    let valid = !target.is_empty()
+        && !target.starts_with('.')
+        && target
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '/' | '.'));
+    // `x := y` and `x ::= y` are assignments
+    (valid && !rest.starts_with('=') && !rest.starts_with(":=")).then_some(target)
+}
+
+/// Recipes of a Makefile-like file, found by `target_of` on unindented
+/// lines, with their descriptions
+fn recipes<'a>(
+    content: &'a str,
+    target_of: impl Fn(&'a str) -> Option<&'a str>,
+) -> Vec<(String, Option<String>)> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut recipes: Vec<(String, Option<String>)> = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        if line.starts_with([' ', '\t', '#']) || line.trim().is_empty() {
+            continue;
+        }
+        let Some(name) = target_of(line) else {
+            continue;
+        };
+        if name.is_empty() || recipes.iter().any(|(known, _)| known == name) {
+            continue;
+        }
+        let inline = line
+            .split_once("##")
+            .map(|(_, comment)| comment.trim().to_string());
+        let above = i
+            .checked_sub(1)
+            .and_then(|above| lines[above].strip_prefix('#'))
+            .map(|comment| comment.trim_start_matches('#').trim().to_string());
+        let first_step = lines[i + 1..]
+            .iter()
+            .take_while(|l| l.starts_with([' ', '\t']))
+            .map(|l| l.trim().trim_start_matches('@'))
+            .find(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(runs);
+        let description = inline.or(above).filter(|d| !d.is_empty()).or(first_step);
+        recipes.push((name.to_string(), description));
+    }
+    recipes
+}
+
+/// `docker compose up` for each service of a compose file
+fn compose_commands(lines: &[String]) -> Found {
+    project_files::compose_services(lines)
+        .into_iter()
+        .map(|service| {
+            let description = match (&service.image, &service.build) {
+                (Some(image), _) => Some(format!("Starts the `{}` image", image)),
+                (None, Some(build)) => Some(format!("Builds and starts `{}`", build)),
+                (None, None) => None,
+            };
+            (
+                format!("docker compose up {}", service.name),
+                CommandKind::ComposeService,
+                description,
+            )
+        })
+        .collect()
+}
+
+/// The scripts of `[project.scripts]` and `[tool.poetry.scripts]`, which
+/// installing the package puts on the `PATH`
+fn python_commands(content: &str) -> Found {
+    let manifest: toml::Table = match content.parse() {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            debug!("Not a valid pyproject.toml: {}", e);
+            return Vec::new();
+        }
+    };
+    let tables = [
+        manifest.get("project").and_then(|p| p.get("scripts")),
+        manifest
+            .get("tool")
+            .and_then(|t| t.get("poetry"))
+            .and_then(|p| p.get("scripts")),
+    ];
+    let mut scripts: BTreeMap<&str, Option<&str>> = BTreeMap::new();
+    for table in tables.into_iter().flatten().filter_map(|t| t.as_table()) {
+        for (name, target) in table {
+            scripts.entry(name).or_insert(target.as_str());
+        }
+    }
+    scripts
+        .into_iter()
+        .map(|(name, target)| {
+            (
+                name.to_string(),
+                CommandKind::PythonEntryPoint,
+                target.map(|t| format!("Calls `{}`", t)),
+            )
+        })
+        .collect()
+}
+
+/// "Runs `script`", shortened to [`MAX_DESCRIPTION`]
+fn runs(script: &str) -> String {
+    let script = script.trim();
+    match script.char_indices().nth(MAX_DESCRIPTION) {
+        Some((end, _)) => format!("Runs `{}…`", &script[..end]),
+        None => format!("Runs `{}`", script),
+    }
+}
+
+const DESCRIBE_PROMPT: &str = r#"You explain how to work with a software project. The user lists commands defined by the project's config files, numbered, each with what it runs. For each, answer one line:
+
+number | what the command does, in at most 12 words
+
+Describe the purpose (start the dev server, run the tests, build a release), not the mechanics. Answer only with these lines."#;
+
+/// Ask the LLM for a one-line description of every command, in one call.
+/// A failed call leaves the descriptions read from the config files.
+pub async fn describe(commands: &mut [RunCommand], provider: &dyn LlmProvider) {
+    if commands.is_empty() {
+        return;
+    }
+    let prompt: String = commands
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            format!(
+                "{}. `{}` from {}: {}\n",
+                i + 1,
+                c.command,
+                c.source,
+                c.description.as_deref().unwrap_or("-")
+            )
+        })
+        .collect();
+    // Script bodies and recipes can carry credentials
+    let [prompt] = redact::redact_prompt("the project's commands", [prompt.as_str()]);
+    let messages = vec![
+        Message {
+            role: Role::System,
+            content: llm::localize(DESCRIBE_PROMPT).into_owned(),
+        },
+        Message {
+            role: Role::User,
+            content: prompt.into_owned(),
+        },
+    ];
+    let config = LlmConfig {
+        max_tokens: 2048,
+        ..Default::default()
+    };
+    let response = match provider.complete(messages, config).await {
+        Ok(response) => response,
+        Err(e) => {
+            warn!("Failed to describe the project's commands: {}", e);
+            return;
+        }
+    };
+
+    let mut described = 0;
+    for (number, description) in response.lines().filter_map(parse_answer) {
+        let Some(command) = number.checked_sub(1).and_then(|i| commands.get_mut(i)) else {
+            continue;
+        };
+        if !command.inferred {
+            command.description = Some(description.to_string());
+            command.inferred = true;
+            described += 1;
+        }
+    }
+    info!("Described {} of {} commands", described, commands.len());
+}
+
+/// One `number | description` line of the LLM's answer
+pub(crate) fn parse_answer(line: &str) -> Option<(usize, &str)> {
+    let (number, description) = line.split_once('|')?;
+    let number = number.trim().trim_end_matches('.').parse().ok()?;
+    let description = description.trim();
+    (!description.is_empty()).then_some((number, description))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    fn write(root: &Path, path: &str, content: &str) -> String {
+        let abs = root.join(path);
+        fs::create_dir_all(abs.parent().unwrap()).unwrap();
+        fs::write(abs, content).unwrap();
+        path.to_string()
+    }
+
+    fn commands(commands: &[RunCommand]) -> Vec<(&str, CommandKind, Option<&str>)> {
+        commands
+            .iter()
+            .map(|c| (c.command.as_str(), c.kind, c.description.as_deref()))
+            .collect()
+    }
+
+    #[test]
+    fn test_extract_commands() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        write(root, "src/main.rs", "fn main() {}");
+        write(root, "src/bin/migrate.rs", "fn main() {}");
+        let config_files = vec![
+            write(
+                root,
+                "Cargo.toml",
+                "[package]\nname = \"app\"\n\n[[bin]]\nname = \"worker\"\npath = \"src/worker.rs\"\n",
+            ),
+            write(
+                root,
+                "web/package.json",
+                r#"{"scripts": {"dev": "vite", "test": "vitest run"}}"#,
+            ),
+            write(
+                root,
+                "Makefile",
+                ".PHONY: build test\nVERSION := 1\n\nbuild: deps ## Build everything\n\tcargo build\n\n# Run the tests\ntest:\n\t@cargo test\n\n%.o: %.c\n\tcc $<\n",
+            ),
+            write(
+                root,
+                "justfile",
+                "set shell := [\"bash\", \"-c\"]\n\n# Format the code\nfmt:\n    cargo fmt\n\nserve port='8080':\n    python -m http.server {{port}}\n",
+            ),
+            write(
+                root,
+                "docker-compose.yml",
+                "services:\n  api:\n    build: ./api\n  db:\n    image: postgres:16\n",
+            ),
+            write(
+                root,
+                "pyproject.toml",
+                "[project.scripts]\ncli = \"pkg.cli:main\"\n",
+            ),
+        ];
+        let inventory = FileInventory {
+            root: root.display().to_string(),
+            config_files,
+            ..Default::default()
+        };
+
+        let found = extract(&inventory);
+        assert_eq!(
+            commands(&found),
+            [
+                (
+                    "cargo run --bin app",
+                    CommandKind::CargoBin,
+                    Some("Builds and runs the `app` binary")
+                ),
+                (
+                    "cargo run --bin migrate",
+                    CommandKind::CargoBin,
+                    Some("Builds and runs the `migrate` binary")
+                ),
+                (
+                    "cargo run --bin worker",
+                    CommandKind::CargoBin,
+                    Some("Builds and runs the `worker` binary")
+                ),
+                (
+                    "make build",
+                    CommandKind::MakeTarget,
+                    Some("Build everything")
+                ),
+                ("make test", CommandKind::MakeTarget, Some("Run the tests")),
+                (
+                    "docker compose up api",
+                    CommandKind::ComposeService,
+                    Some("Builds and starts `./api`")
+                ),
+                (
+                    "docker compose up db",
+                    CommandKind::ComposeService,
+                    Some("Starts the `postgres:16` image")
+                ),
+                ("just fmt", CommandKind::JustRecipe, Some("Format the code")),
+                (
+                    "just serve",
+                    CommandKind::JustRecipe,
+                    Some("Runs `python -m http.server {{port}}`")
+                ),
+                (
+                    "cli",
+                    CommandKind::PythonEntryPoint,
+                    Some("Calls `pkg.cli:main`")
+                ),
+                ("npm run dev", CommandKind::NpmScript, Some("Runs `vite`")),
+                (
+                    "npm run test",
+                    CommandKind::NpmScript,
+                    Some("Runs `vitest run`")
+                ),
+            ]
+        );
+        assert_eq!(found[10].source, "web/package.json");
+    }
+
+    struct Describer;
+
+    #[async_trait::async_trait]
+    impl LlmProvider for Describer {
+        fn name(&self) -> &str {
+            "mock"
+        }
+
+        fn model(&self) -> &str {
+            "mock"
+        }
+
+        async fn list_models(&self) -> Result<Vec<crate::llm::ModelInfo>> {
+            Ok(vec![])
+        }
+
+        async fn complete(&self, messages: Vec<Message>, _config: LlmConfig) -> Result<String> {
+            assert_eq!(
+                messages[1].content,
+                "1. `npm run dev` from package.json: Runs `vite`\n\
+                 2. `npm run deploy` from package.json: Runs \
+                 `AWS_SECRET_ACCESS_KEY=[REDACTED:secret] aws s3 sync`\n"
+            );
+            Ok("1 | Starts the development server\n9 | Unknown\nnot an answer\n".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_describe_with_llm() {
+        let command = |name: &str, script: &str| RunCommand {
+            command: name.to_string(),
+            kind: CommandKind::NpmScript,
+            source: "package.json".to_string(),
+            description: Some(runs(script)),
+            inferred: false,
+            in_ci: false,
+        };
+        // Credentials in a script never reach the prompt
+        let deploy = "AWS_SECRET_ACCESS_KEY=wJalrXUtnFEMI/K7MDENG/bPxRfiCYEXAMPLEKEY aws s3 sync";
+        let mut commands = vec![
+            command("npm run dev", "vite"),
+            command("npm run deploy", deploy),
+        ];
+        describe(&mut commands, &Describer).await;
+        assert_eq!(
+            commands[0].description.as_deref(),
+            Some("Starts the development server")
+        );
+        assert!(commands[0].inferred);
+        assert_eq!(commands[1].description, Some(runs(deploy)));
+        assert!(!commands[1].inferred);
+    }
+}
@@ -0,0 +1,286 @@
+//! Run history for `cda analyze --tag`
+//!
+//! Each tagged run writes into its own `<output>/runs/<timestamp>-<tag>/`
+//! directory, so progress and module pages are scoped to the run. Finished
+//! runs are listed in `runs/index.json` and `runs/latest` points at the most
+//! recent one.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const RUNS_DIR: &str = "runs";
+pub const INDEX_FILE: &str = "index.json";
+/// Symlink (or, where symlinks aren't available, a file holding the run id)
+pub const LATEST: &str = "latest";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunEntry {
+    /// Directory name under `runs/`
+    pub id: String,
+    pub tag: String,
+    /// UTC, e.g. `2026-10-15T07:57:14Z`
+    pub started_at: String,
+    /// `git rev-parse HEAD` of the analyzed repo, when it is one
+    pub commit: Option<String>,
+    pub provider: String,
+    pub model: Option<String>,
+    pub deep: bool,
+    pub partial: bool,
+    /// `--order` of the LLM loop; `None` when the run had none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<String>,
+    /// Whether a static pre-pass ranked the files before the LLM loop
+    #[serde(default)]
+    pub prepass: bool,
+    /// `output.language` of the run, when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc_language: Option<String>,
+    pub stats: RunStats,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunStats {
+    pub modules: usize,
+    pub exports: usize,
+    pub gaps: usize,
+    pub llm_modules: usize,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RunIndex {
+    /// Oldest first
+    pub runs: Vec<RunEntry>,
+}
+
+impl RunIndex {
+    /// The index under `output_root`, empty if there is none yet
+    pub fn load(output_root: &Path) -> Result<Self> {
+        let path = output_root.join(RUNS_DIR).join(INDEX_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path)?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    fn save(&self, output_root: &Path) -> Result<()> {
+        let path = output_root.join(RUNS_DIR).join(INDEX_FILE);
+        fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// `latest`, a run id, or a tag (its most recent run)
+    pub fn find(&self, spec: &str) -> Option<&RunEntry> {
+        if spec == LATEST {
+            return self.runs.last();
+        }
+        self.runs
+            .iter()
+            .rev()
+            .find(|r| r.id == spec)
+            .or_else(|| self.runs.iter().rev().find(|r| r.tag == spec))
+    }
+}
+
+pub fn validate_tag(tag: &str) -> Result<()> {
+    if tag.is_empty()
+        || tag == LATEST
+        || !tag
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        anyhow::bail!(
+            "Invalid tag `{}`: use letters, digits, `-`, `_` and `.` (and not `{}`)",
+            tag,
+            LATEST
+        );
+    }
+    Ok(())
+}
+
+/// Directory for a new run with `tag`. With `resume`, an interrupted run with
+/// the same tag (a directory that never made it into the index) is reused so
+/// its progress file can pick up where it stopped.
+pub fn run_dir(output_root: &Path, tag: &str, now: SystemTime, resume: bool) -> Result<PathBuf> {
+    validate_tag(tag)?;
+    let runs = output_root.join(RUNS_DIR);
+    fs::create_dir_all(&runs)?;
+
+    if resume {
+        let index = RunIndex::load(output_root)?;
+        let suffix = format!("-{}", tag);
+        let mut interrupted: Vec<_> = fs::read_dir(&runs)?
+            .flatten()
+            .filter(|e| e.path().is_dir() && !e.path().is_symlink())
+            .filter_map(|e| e.file_name().to_str().map(String::from))
+            .filter(|name| name.ends_with(&suffix) && !index.runs.iter().any(|r| &r.id == name))
+            .collect();
+        interrupted.sort();
+        if let Some(id) = interrupted.pop() {
+            return Ok(runs.join(id));
+        }
+    }
+
+    Ok(runs.join(format!("{}-{}", compact_timestamp(now), tag)))
+}
+
+/// Add a finished run to the index and point `latest` at it
+pub fn record(output_root: &Path, entry: RunEntry) -> Result<()> {
+    let mut index = RunIndex::load(output_root)?;
+    let id = entry.id.clone();
+    index.runs.retain(|r| r.id != id);
+    index.runs.push(entry);
+    index.save(output_root)?;
+
+    let latest = output_root.join(RUNS_DIR).join(LATEST);
+    if latest.is_symlink() || latest.is_file() {
+        fs::remove_file(&latest)?;
+    }
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(&id, &latest)?;
+    #[cfg(not(unix))]
+    fs::write(&latest, &id)?;
+    Ok(())
+}
+
+/// An existing output directory, or a run of `output_root` named by
+/// `latest`, its id or its tag
+pub fn resolve(spec: &str, output_root: &Path) -> Result<PathBuf> {
+    let path = Path::new(spec);
+    if path.exists() {
+        return Ok(path.to_path_buf());
+    }
+
+    let index = RunIndex::load(output_root)?;
+    match index.find(spec) {
+        Some(run) => Ok(output_root.join(RUNS_DIR).join(&run.id)),
+        None if index.runs.is_empty() => anyhow::bail!(
+            "{} is not a directory, and {} has no tagged runs (see `cda analyze --tag`)",
+            spec,
+            output_root.display()
+        ),
+        None => {
+            let mut tags: Vec<_> = index.runs.iter().map(|r| r.tag.as_str()).collect();
+            tags.sort();
+            tags.dedup();
+            anyhow::bail!(
+                "{} is not a directory or a run in {}; known tags: {}",
+                spec,
+                output_root.display(),
+                tags.join(", ")
+            )
+        }
+    }
+}
+
+/// `20261015-075714`
+pub fn compact_timestamp(time: SystemTime) -> String {
+    let (y, mo, d, h, mi, s) = civil(time);
+    format!("{:04}{:02}{:02}-{:02}{:02}{:02}", y, mo, d, h, mi, s)
+}
+
+/// `2026-10-15T07:57:14Z`
+pub fn iso_timestamp(time: SystemTime) -> String {
+    let (y, mo, d, h, mi, s) = civil(time);
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", y, mo, d, h, mi, s)
+}
+
+/// UTC calendar fields (days-to-civil from Howard Hinnant's date algorithms)
+fn civil(time: SystemTime) -> (i64, u64, u64, u64, u64, u64) {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u64;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u64;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (year, month, day, rem / 3_600, rem % 3_600 / 60, rem % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn entry(id: &str, tag: &str, modules: usize) -> RunEntry {
+        RunEntry {
+            id: id.into(),
+            tag: tag.into(),
+            started_at: String::new(),
+            commit: None,
+            provider: "anthropic".into(),
+            model: None,
+            doc_language: None,
+            deep: false,
+            partial: false,
+            order: None,
+            prepass: false,
+            stats: RunStats {
+                modules,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_timestamps() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_792_050_634);
+        assert_eq!(iso_timestamp(time), "2026-10-15T07:50:34Z");
+        assert_eq!(compact_timestamp(time), "20261015-075034");
+        assert_eq!(iso_timestamp(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        let leap = UNIX_EPOCH + Duration::from_secs(951_782_400);
+        assert_eq!(iso_timestamp(leap), "2000-02-29T00:00:00Z");
+    }
+
+    #[test]
+    fn test_record_and_resolve() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let now = UNIX_EPOCH + Duration::from_secs(1_792_050_634);
+
+        let first = run_dir(root, "nightly", now, true).unwrap();
+        assert!(first.ends_with("runs/20261015-075034-nightly"));
+        fs::create_dir_all(&first).unwrap();
+
+        // Unfinished, so a resumed run reuses it even at a later time
+        let later = now + Duration::from_secs(60);
+        assert_eq!(run_dir(root, "nightly", later, true).unwrap(), first);
+        assert_ne!(run_dir(root, "nightly", later, false).unwrap(), first);
+
+        record(root, entry("20261015-075034-nightly", "nightly", 10)).unwrap();
+        record(root, entry("20261016-075034-pr-12", "pr-12", 11)).unwrap();
+        assert_ne!(run_dir(root, "nightly", later, true).unwrap(), first);
+
+        let index = RunIndex::load(root).unwrap();
+        assert_eq!(index.find("latest").unwrap().tag, "pr-12");
+        assert_eq!(index.find("nightly").unwrap().stats.modules, 10);
+
+        assert_eq!(resolve("nightly", root).unwrap(), first);
+        let latest = resolve(LATEST, root).unwrap();
+        assert!(latest.ends_with("20261016-075034-pr-12"));
+        #[cfg(unix)]
+        assert_eq!(
+            fs::read_link(root.join(RUNS_DIR).join(LATEST)).unwrap(),
+            Path::new("20261016-075034-pr-12")
+        );
+
+        let err = resolve("weekly", root).unwrap_err().to_string();
+        assert!(err.contains("known tags: nightly, pr-12"), "{}", err);
+        assert!(validate_tag("latest").is_err());
+        assert!(validate_tag("../x").is_err());
+    }
+}
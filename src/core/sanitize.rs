@@ -0,0 +1,533 @@
+//! Cleaning up the LLM's markdown before it goes on a page
+//!
+//! A module's analysis is embedded under the "## Analysis" heading of its
+//! page, and `cda serve` renders pages as HTML. [`markdown`] keeps an answer
+//! within that section: it closes a code fence the model left open, demotes
+//! headings so the highest one is at the given level, escapes or strips raw
+//! HTML as `output.raw_html` says, disarms `javascript:` links and collapses
+//! runs of blank lines. Fenced code and inline code are left as they are.
+
+use serde::Deserialize;
+use std::sync::OnceLock;
+
+/// What happens to raw HTML in the LLM's markdown, set by `output.raw_html`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RawHtml {
+    /// Escape tags, so they show as text
+    #[default]
+    Escape,
+    /// Remove tags, and the content of `<script>` and `<style>` elements
+    Strip,
+    /// Leave HTML as the model wrote it
+    Keep,
+}
+
+/// Treatment set by `output.raw_html`
+static RAW_HTML: OnceLock<RawHtml> = OnceLock::new();
+
+/// Treat raw HTML with `mode` for the rest of the process. Only the first
+/// call has an effect.
+pub fn set_raw_html(mode: RawHtml) {
+    let _ = RAW_HTML.set(mode);
+}
+
+pub fn raw_html() -> RawHtml {
+    RAW_HTML.get().copied().unwrap_or_default()
+}
+
+/// Link schemes that run code when a rendered link is followed
+const UNSAFE_SCHEMES: [&str; 3] = ["javascript:", "vbscript:", "data:"];
+
+/// `text` made safe to embed in a page under a heading one level above
+/// `top_level`, with raw HTML treated as [`raw_html`] says
+pub fn markdown(text: &str, top_level: usize) -> String {
+    clean(text, top_level, raw_html())
+}
+
+/// A line of the answer, and whether it is inside a fenced code block
+struct Line {
+    text: String,
+    code: bool,
+}
+
+fn clean(text: &str, top_level: usize, html: RawHtml) -> String {
+    let mut lines: Vec<Line> = Vec::new();
+    let mut fence: Option<(char, usize)> = None;
+    let mut element: Option<&'static str> = None;
+    for raw in text.lines() {
+        if let Some((c, len)) = fence {
+            let closes = fence_marker(raw)
+                .is_some_and(|(mc, mlen, info)| mc == c && mlen >= len && info.trim().is_empty());
+            if closes {
+                fence = None;
+            }
+            lines.push(Line {
+                text: raw.to_string(),
+                code: true,
+            });
+            continue;
+        }
+        if element.is_none() {
+            if let Some((c, len, info)) = fence_marker(raw) {
+                if c == '~' || !info.contains('`') {
+                    fence = Some((c, len));
+                    lines.push(Line {
+                        text: raw.to_string(),
+                        code: true,
+                    });
+                    continue;
+                }
+            }
+        }
+        let was_open = element.is_some();
+        let text = match html {
+            RawHtml::Escape => inline(raw, escape_tags),
+            RawHtml::Strip => inline(&drop_elements(raw, &mut element), strip_tags),
+            RawHtml::Keep => inline(raw, |text| text.to_string()),
+        };
+        // Lines of a stripped element leave no blank line behind
+        if text.trim().is_empty() && (was_open || element.is_some()) {
+            continue;
+        }
+        lines.push(Line { text, code: false });
+    }
+    // The rest of the page would otherwise render as code
+    if let Some((c, len)) = fence {
+        lines.push(Line {
+            text: c.to_string().repeat(len),
+            code: true,
+        });
+    }
+
+    setext_to_atx(&mut lines);
+    demote_headings(&mut lines, top_level);
+
+    let mut out: Vec<&str> = Vec::new();
+    for line in &lines {
+        let blank = !line.code && line.text.trim().is_empty();
+        if blank && out.last().is_none_or(|l| l.trim().is_empty()) {
+            continue;
+        }
+        out.push(&line.text);
+    }
+    while out.last().is_some_and(|l| l.trim().is_empty()) {
+        out.pop();
+    }
+    out.join("\n")
+}
+
+/// The fence character, length and info string of a line opening or closing
+/// a fenced code block
+fn fence_marker(line: &str) -> Option<(char, usize, &str)> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let c = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = trimmed.len() - trimmed.trim_start_matches(c).len();
+    (len >= 3).then(|| (c, len, &trimmed[len..]))
+}
+
+/// The level of an ATX heading line, `## Title`
+fn heading_level(line: &str) -> Option<usize> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let level = trimmed.len() - trimmed.trim_start_matches('#').len();
+    let rest = &trimmed[level..];
+    ((1..=6).contains(&level) && (rest.is_empty() || rest.starts_with([' ', '\t'])))
+        .then_some(level)
+}
+
+/// Turn `Title` underlined with `===` or `---` into `# Title` or `## Title`,
+/// so [`demote_headings`] sees it
+fn setext_to_atx(lines: &mut Vec<Line>) {
+    let mut index = 1;
+    while index < lines.len() {
+        let underline = lines[index].text.trim();
+        let level = if underline.is_empty() || lines[index].code {
+            None
+        } else if underline.chars().all(|c| c == '=') {
+            Some(1)
+        } else if underline.chars().all(|c| c == '-') {
+            Some(2)
+        } else {
+            None
+        };
+        let previous = &lines[index - 1];
+        let paragraph = !previous.code
+            && !previous.text.trim().is_empty()
+            && heading_level(&previous.text).is_none()
+            && !is_block_start(previous.text.trim_start());
+        match level {
+            Some(level) if paragraph => {
+                let title = previous.text.trim().to_string();
+                lines[index - 1].text = format!("{} {}", "#".repeat(level), title);
+                lines.remove(index);
+            }
+            _ => index += 1,
+        }
+    }
+}
+
+/// Whether a line starts a list item, quote or table row rather than
+/// continuing a paragraph
+fn is_block_start(line: &str) -> bool {
+    let ordered = line.split_once(['.', ')']).is_some_and(|(n, rest)| {
+        !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()) && rest.starts_with(' ')
+    });
+    ordered || line.starts_with(['-', '*', '+', '>', '|'])
+}
+
+/// Shift every heading down so the highest is at `top_level`, keeping their
+/// nesting; headings below level 6 stay at 6
+fn demote_headings(lines: &mut [Line], top_level: usize) {
+    let levels = lines
+        .iter()
+        .filter(|l| !l.code)
+        .filter_map(|l| heading_level(&l.text));
+    let Some(highest) = levels.min() else {
+        return;
+    };
+    let shift = top_level.saturating_sub(highest);
+    if shift == 0 {
+        return;
+    }
+    for line in lines.iter_mut().filter(|l| !l.code) {
+        if let Some(level) = heading_level(&line.text) {
+            let text = line.text.trim_start_matches(' ');
+            line.text = format!("{}{}", "#".repeat((level + shift).min(6)), &text[level..]);
+        }
+    }
+}
+
+/// Apply `f` to the parts of `line` outside inline code spans, and disarm
+/// links to [`UNSAFE_SCHEMES`] in them
+fn inline(line: &str, f: impl Fn(&str) -> String) -> String {
+    let mut out = String::new();
+    let mut rest = line;
+    loop {
+        let (before, code, after) = split_code_span(rest);
+        out.push_str(&disarm_links(&f(before)));
+        out.push_str(code);
+        if after.is_empty() {
+            return out;
+        }
+        rest = after;
+    }
+}
+
+/// Whether `text` starts like an HTML tag, comment or declaration after `<`
+fn is_tag_start(text: &str) -> bool {
+    text.starts_with(|c: char| c.is_ascii_alphabetic() || c == '/' || c == '!' || c == '?')
+}
+
+/// An autolink such as `<https://example.com>` at the start of `text`,
+/// which renders as a link rather than HTML
+fn autolink(text: &str) -> Option<usize> {
+    let end = text.find('>')?;
+    let url = &text[1..end];
+    let safe = ["http://", "https://", "mailto:"]
+        .iter()
+        .any(|scheme| url.to_ascii_lowercase().starts_with(scheme));
+    (safe && !url.contains([' ', '<'])).then_some(end + 1)
+}
+
+fn escape_tags(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        out.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        if let Some(len) = autolink(tail) {
+            out.push_str(&tail[..len]);
+            rest = &tail[len..];
+        } else {
+            out.push_str(if is_tag_start(&tail[1..]) {
+                "&lt;"
+            } else {
+                "<"
+            });
+            rest = &tail[1..];
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// `line` without the `<script>` and `<style>` elements in it, content
+/// included; `open` is the one left open by an earlier line, and then by
+/// this one. Inline code is kept as it is.
+fn drop_elements(line: &str, open: &mut Option<&'static str>) -> String {
+    let mut out = String::new();
+    let mut rest = line;
+    loop {
+        if let Some(element) = *open {
+            let closing = format!("</{}", element);
+            let Some(start) = rest.to_ascii_lowercase().find(&closing) else {
+                return out;
+            };
+            let after = &rest[start..];
+            rest = after.find('>').map_or("", |end| &after[end + 1..]);
+            *open = None;
+        }
+        let (before, code, after) = split_code_span(rest);
+        match opening_element(before) {
+            Some((start, end, element)) => {
+                out.push_str(&before[..start]);
+                *open = Some(element);
+                rest = &rest[end..];
+            }
+            None => {
+                out.push_str(before);
+                out.push_str(code);
+                if after.is_empty() {
+                    return out;
+                }
+                rest = after;
+            }
+        }
+    }
+}
+
+/// Where the first `<script>` or `<style>` start tag in `text` starts and
+/// ends, and which it is
+fn opening_element(text: &str) -> Option<(usize, usize, &'static str)> {
+    let lower = text.to_ascii_lowercase();
+    lower.match_indices('<').find_map(|(start, _)| {
+        let name = &lower[start + 1..];
+        let element = ["script", "style"].into_iter().find(|e| {
+            name.starts_with(e) && !name[e.len()..].starts_with(|c: char| c.is_ascii_alphanumeric())
+        })?;
+        let end = start + lower[start..].find('>')?;
+        (!lower[..end].ends_with('/')).then_some((start, end + 1, element))
+    })
+}
+
+/// `text` without its tags; a `<` that doesn't end in `>` is escaped
+fn strip_tags(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        out.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        if let Some(len) = autolink(tail) {
+            out.push_str(&tail[..len]);
+            rest = &tail[len..];
+        } else if !is_tag_start(&tail[1..]) {
+            out.push('<');
+            rest = &tail[1..];
+        } else if let Some(end) = tail.find('>') {
+            rest = &tail[end + 1..];
+        } else {
+            out.push_str("&lt;");
+            rest = &tail[1..];
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// `text` split at its first inline code span: the text before it, the span
+/// and the text after it
+fn split_code_span(text: &str) -> (&str, &str, &str) {
+    let Some(start) = text.find('`') else {
+        return (text, "", "");
+    };
+    let run = text[start..].len() - text[start..].trim_start_matches('`').len();
+    let fence = "`".repeat(run);
+    let after = &text[start + run..];
+    match after.find(&fence) {
+        Some(close) => {
+            let end = start + run + close + run;
+            (&text[..start], &text[start..end], &text[end..])
+        }
+        None => (text, "", ""),
+    }
+}
+
+/// Point links and images with an [`UNSAFE_SCHEMES`] destination at `#`
+fn disarm_links(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("](") {
+        out.push_str(&rest[..start + 2]);
+        rest = &rest[start + 2..];
+        let destination = rest.trim_start().trim_start_matches('<');
+        let lower = destination.to_ascii_lowercase();
+        if UNSAFE_SCHEMES.iter().any(|s| lower.starts_with(s)) {
+            out.push('#');
+            rest = &destination[destination_len(destination)..];
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Length of the link destination at the start of `text`, up to the `)`
+/// closing the link, a title or the `>` of `<...>`
+fn destination_len(text: &str) -> usize {
+    let mut depth = 0;
+    for (index, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return index,
+            ')' => depth -= 1,
+            '>' => return index + 1,
+            c if c.is_whitespace() => return index,
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::analyzer::{render_module_markdown, Completeness};
+    use crate::core::discovery::Language;
+    use crate::core::parser::ParseResult;
+
+    /// The headings of a page as a renderer sees them: outside code blocks,
+    /// including setext ones. Panics on a code block left open.
+    fn outline(page: &str) -> Vec<String> {
+        let mut headings = Vec::new();
+        let mut fence: Option<(char, usize)> = None;
+        let mut previous = "";
+        for line in page.lines() {
+            match (fence, fence_marker(line)) {
+                (Some((c, len)), Some((mc, mlen, info)))
+                    if mc == c && mlen >= len && info.trim().is_empty() =>
+                {
+                    fence = None
+                }
+                (Some(_), _) => {}
+                (None, Some((c, len, _))) => fence = Some((c, len)),
+                (None, None) => {
+                    if let Some(level) = heading_level(line) {
+                        headings.push(format!("{} {}", level, line.trim()[level..].trim()));
+                    } else if !previous.trim().is_empty()
+                        && heading_level(previous).is_none()
+                        && !line.trim().is_empty()
+                        && line.trim().chars().all(|c| c == '=' || c == '-')
+                    {
+                        headings.push(format!("setext {}", previous.trim()));
+                    }
+                }
+            }
+            previous = line;
+        }
+        assert!(fence.is_none(), "code block left open:\n{}", page);
+        headings
+    }
+
+    fn page(analysis: &str) -> String {
+        render_module_markdown(
+            "src/lib.rs",
+            &[],
+            Language::Rust,
+            &ParseResult {
+                imports: vec![serde_json::from_value(serde_json::json!({
+                    "source": "serde",
+                    "items": [],
+                    "is_external": true,
+                }))
+                .unwrap()],
+                ..Default::default()
+            },
+            Some(&clean(analysis, 3, RawHtml::Escape)),
+            Completeness::Full,
+            Some("generic"),
+            None,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_adversarial_answers_stay_in_their_section() {
+        let expected = [
+            "1 lib",
+            "2 Analysis",
+            "3 Purpose",
+            "4 Details",
+            "2 Dependencies",
+            "3 External",
+        ];
+
+        // A fence left open would swallow the rest of the page
+        let unterminated = "Parses input.\n\n# Purpose\n\n## Details\n\n```rust\nfn parse() {}\n";
+        assert_eq!(outline(&page(unterminated)), expected);
+
+        // Top-level headings, setext ones included, would end the section
+        let headings = "Parses input.\n\nPurpose\n=======\n\nDetails\n-------\n\nText.";
+        assert_eq!(outline(&page(headings)), expected);
+
+        // A heading in a code block is code, not a heading
+        let code = "Parses input.\n\n## Purpose\n\n### Details\n\n~~~\n# not a heading\n~~~";
+        assert_eq!(outline(&page(code)), expected);
+        assert!(page(code).contains("\n# not a heading\n"));
+    }
+
+    #[test]
+    fn test_headings_keep_their_nesting() {
+        assert_eq!(
+            clean("# A\n\n## B\n\n###### C", 3, RawHtml::Escape),
+            "### A\n\n#### B\n\n###### C"
+        );
+        // Already low enough
+        assert_eq!(clean("### A\n#### B", 3, RawHtml::Escape), "### A\n#### B");
+        // `#tag` is not a heading
+        assert_eq!(
+            clean("#tag\n\n- a\n---", 3, RawHtml::Escape),
+            "#tag\n\n- a\n---"
+        );
+    }
+
+    #[test]
+    fn test_raw_html() {
+        let answer = "Renders <b>names</b>.\n\n<script>fetch('/x?' + document.cookie)</script>\n\n\
+                      <img src=x onerror=alert(1)>\n\nSee <https://example.com>, `Vec<String>` \
+                      and a < b.\n\n```html\n<script>kept()</script>\n```";
+
+        let escaped = clean(answer, 3, RawHtml::Escape);
+        assert_eq!(
+            escaped,
+            "Renders &lt;b>names&lt;/b>.\n\n&lt;script>fetch('/x?' + document.cookie)&lt;/script>\n\n\
+             &lt;img src=x onerror=alert(1)>\n\nSee <https://example.com>, `Vec<String>` \
+             and a < b.\n\n```html\n<script>kept()</script>\n```"
+        );
+
+        let stripped = clean(answer, 3, RawHtml::Strip);
+        assert_eq!(
+            stripped,
+            "Renders names.\n\nSee <https://example.com>, `Vec<String>` and a < b.\n\n\
+             ```html\n<script>kept()</script>\n```"
+        );
+        // A script element over several lines goes with its content
+        assert_eq!(
+            clean("a<script>\nsteal();\n</script>b", 3, RawHtml::Strip),
+            "a\nb"
+        );
+
+        assert_eq!(clean(answer, 3, RawHtml::Keep), answer);
+    }
+
+    #[test]
+    fn test_unsafe_links_and_blank_lines() {
+        assert_eq!(
+            clean(
+                "\n\n[docs](https://example.com) [x](javascript:alert(1)) ![i]( data:text/html,x)\n\n\n\n\nEnd.\n\n\n",
+                3,
+                RawHtml::Escape
+            ),
+            "[docs](https://example.com) [x](#) ![i](#)\n\nEnd."
+        );
+        // Blank lines in code are the code's
+        assert_eq!(
+            clean("```\na\n\n\n\nb\n```", 3, RawHtml::Escape),
+            "```\na\n\n\n\nb\n```"
+        );
+    }
+}
@@ -0,0 +1,428 @@
+//! Which modules get deep analysis when not all of them can
+//!
+//! With `--deep-select smart`, [`score`] ranks the modules of the static
+//! pass: entry points, modules many others import, large files with many
+//! exports and modules without a single doc comment come first, while barrel
+//! files, generated code and empty files are left out. [`select`] takes the
+//! modules in that order while the `--max-llm-calls` budget lasts and records
+//! the score and the decision on every module, for `analysis.json`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use super::analyzer::{self, ImportKind, ModuleAnalysis};
+use super::discovery::SourceFile;
+
+/// Points for an entry point
+const ENTRY_POINT_POINTS: u32 = 40;
+/// Points per module importing this one, and the most they add up to
+const FAN_IN_POINTS: u32 = 5;
+const MAX_FAN_IN_POINTS: u32 = 40;
+/// Points per KB of source, and the most they add up to
+const MAX_SIZE_POINTS: u32 = 20;
+/// Points per public export, and the most they add up to
+const EXPORT_POINTS: u32 = 2;
+const MAX_EXPORT_POINTS: u32 = 20;
+/// Points for public exports none of which has a doc comment
+const UNDOCUMENTED_POINTS: u32 = 15;
+/// Smaller files without exports or imports have nothing to explain
+const EMPTY_BYTES: u64 = 200;
+
+/// Which modules `--mode deep` sends to the LLM
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum DeepSelect {
+    /// Every module, in --order
+    #[default]
+    All,
+    /// The highest-scoring modules first, leaving out barrel files,
+    /// generated code and empty files
+    Smart,
+    /// None; every module gets static analysis only
+    None,
+}
+
+impl DeepSelect {
+    pub fn name(self) -> &'static str {
+        match self {
+            DeepSelect::All => "all",
+            DeepSelect::Smart => "smart",
+            DeepSelect::None => "none",
+        }
+    }
+}
+
+/// Why a module was or wasn't deep-analyzed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Selection {
+    /// Sum of the points of `factors`
+    pub score: u32,
+    /// What the score is made of, e.g. `entry point (+40)`
+    pub factors: Vec<String>,
+    pub selected: bool,
+    /// `selected`, `resumed`, `over budget`, `barrel file`, `generated`,
+    /// `empty` or `not selected`
+    pub reason: String,
+}
+
+/// The score of `module`, a file of `size` bytes that `fan_in` modules
+/// import, with the factors that make it up
+pub fn score(module: &ModuleAnalysis, size: u64, fan_in: usize) -> (u32, Vec<String>) {
+    let mut score = 0;
+    let mut factors = Vec::new();
+    let mut add = |points: u32, factor: String| {
+        if points > 0 {
+            score += points;
+            factors.push(format!("{} (+{})", factor, points));
+        }
+    };
+
+    if analyzer::is_entry_point(module) {
+        add(ENTRY_POINT_POINTS, "entry point".to_string());
+    }
+    add(
+        (fan_in as u32)
+            .saturating_mul(FAN_IN_POINTS)
+            .min(MAX_FAN_IN_POINTS),
+        format!("imported by {}", fan_in),
+    );
+    let kb = size / 1024;
+    add(
+        (kb.min(MAX_SIZE_POINTS as u64)) as u32,
+        format!("{} KB", kb),
+    );
+    let exports = module.public_exports().count();
+    add(
+        (exports as u32)
+            .saturating_mul(EXPORT_POINTS)
+            .min(MAX_EXPORT_POINTS),
+        format!("{} exports", exports),
+    );
+    if exports > 0 && module.public_exports().all(|e| e.description.is_empty()) {
+        add(UNDOCUMENTED_POINTS, "no doc comments".to_string());
+    }
+    (score, factors)
+}
+
+/// Why `--deep-select smart` leaves a module out, if it does: generated or
+/// minified code, a barrel file that only passes on what it imports, or a
+/// file too small to have anything to explain
+fn excluded(module: &ModuleAnalysis, size: u64) -> Option<&'static str> {
+    if module.skip_reason.is_some() {
+        return Some("generated");
+    }
+    let reexported: HashSet<&str> = module
+        .imports
+        .iter()
+        .filter(|i| i.kind == ImportKind::ReExport)
+        .flat_map(|i| i.items.iter().map(String::as_str))
+        .collect();
+    let reexports = module
+        .imports
+        .iter()
+        .any(|i| i.kind == ImportKind::ReExport);
+    if reexports
+        && module
+            .imports
+            .iter()
+            .all(|i| i.kind == ImportKind::ReExport)
+        && module
+            .public_exports()
+            .all(|e| reexported.contains(e.name.as_str()))
+    {
+        return Some("barrel file");
+    }
+    if size < EMPTY_BYTES && module.exports.is_empty() && module.imports.is_empty() {
+        return Some("empty");
+    }
+    None
+}
+
+/// Record the [`Selection`] of every module and return the paths to
+/// deep-analyze. `files` are in the order `all` takes them; each module
+/// costs the `calls` its size is expected to take (one per chunk) of the
+/// `max_calls` budget, except one in `resumed`, which an earlier run
+/// finished. A module costing more than what is left is skipped for the
+/// next ones, which may fit.
+pub fn select(
+    modules: &mut [ModuleAnalysis],
+    files: &[SourceFile],
+    dependencies: &BTreeMap<String, Vec<String>>,
+    strategy: DeepSelect,
+    max_calls: Option<usize>,
+    calls: impl Fn(u64) -> usize,
+    resumed: &HashSet<String>,
+) -> HashSet<String> {
+    let position: HashMap<&str, usize> = files
+        .iter()
+        .enumerate()
+        .map(|(i, f)| (f.path.as_str(), i))
+        .collect();
+    let sizes: HashMap<&str, u64> = files
+        .iter()
+        .map(|f| {
+            let size = f.size + f.companions.iter().map(|c| c.size).sum::<u64>();
+            (f.path.as_str(), size)
+        })
+        .collect();
+    let mut fan_in: HashMap<&str, usize> = HashMap::new();
+    for (module, deps) in dependencies {
+        for dep in deps.iter().filter(|d| *d != module) {
+            *fan_in.entry(dep.as_str()).or_default() += 1;
+        }
+    }
+
+    let mut ranked: Vec<(usize, u32)> = Vec::new();
+    for (index, module) in modules.iter_mut().enumerate() {
+        let size = sizes.get(module.path.as_str()).copied().unwrap_or(0);
+        let (score, factors) = score(
+            module,
+            size,
+            fan_in.get(module.path.as_str()).copied().unwrap_or(0),
+        );
+        let reason = match strategy {
+            DeepSelect::Smart => excluded(module, size),
+            DeepSelect::All => None,
+            DeepSelect::None => Some("not selected"),
+        };
+        if reason.is_none() {
+            ranked.push((index, score));
+        }
+        module.selection = Some(Selection {
+            score,
+            factors,
+            selected: false,
+            reason: reason.unwrap_or("over budget").to_string(),
+        });
+    }
+    let position_of = |index: usize| {
+        position
+            .get(modules[index].path.as_str())
+            .copied()
+            .unwrap_or(usize::MAX)
+    };
+    match strategy {
+        DeepSelect::Smart => {
+            ranked.sort_by_key(|&(index, score)| (std::cmp::Reverse(score), position_of(index)))
+        }
+        _ => ranked.sort_by_key(|&(index, _)| position_of(index)),
+    }
+
+    let mut budget = max_calls.unwrap_or(usize::MAX);
+    let mut targets = HashSet::new();
+    for (index, _) in ranked {
+        let module = &mut modules[index];
+        let cost = calls(sizes.get(module.path.as_str()).copied().unwrap_or(0)).max(1);
+        let reason = if resumed.contains(&module.path) {
+            "resumed"
+        } else if cost <= budget {
+            budget -= cost;
+            "selected"
+        } else {
+            continue;
+        };
+        if let Some(selection) = &mut module.selection {
+            selection.selected = true;
+            selection.reason = reason.to_string();
+        }
+        targets.insert(module.path.clone());
+    }
+    targets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::discovery::Language;
+
+    fn module(path: &str, exports: &[(&str, &str)], imports: &[(&str, &str)]) -> ModuleAnalysis {
+        let exports: Vec<_> = exports
+            .iter()
+            .map(|(name, description)| {
+                serde_json::json!({
+                    "name": name,
+                    "kind": "function",
+                    "signature": null,
+                    "description": description,
+                    "line_number": 1,
+                })
+            })
+            .collect();
+        let imports: Vec<_> = imports
+            .iter()
+            .map(|(item, kind)| {
+                serde_json::json!({"source": "./x", "items": [item], "is_external": false, "kind": kind})
+            })
+            .collect();
+        serde_json::from_value(serde_json::json!({
+            "path": path,
+            "language": "typescript",
+            "exports": exports,
+            "imports": imports,
+            "summary": "",
+            "has_deep_analysis": false,
+            "is_test_module": false,
+        }))
+        .unwrap()
+    }
+
+    fn file(path: &str, size: u64) -> SourceFile {
+        SourceFile {
+            path: path.to_string(),
+            abs_path: path.into(),
+            language: Language::TypeScript,
+            detection: Default::default(),
+            size,
+            is_test: false,
+            modified: None,
+            companions: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_score() {
+        let documented = module("src/util.ts", &[("pad", "Pads a string")], &[]);
+        assert_eq!(
+            score(&documented, 3 * 1024, 2),
+            (
+                15,
+                vec![
+                    "imported by 2 (+10)".to_string(),
+                    "3 KB (+3)".to_string(),
+                    "1 exports (+2)".to_string()
+                ]
+            )
+        );
+        let entry = module("src/index.ts", &[("run", "")], &[]);
+        assert_eq!(score(&entry, 100, 0).0, 40 + 2 + 15);
+    }
+
+    #[test]
+    fn test_select_smart_within_budget() {
+        let mut modules = vec![
+            module("src/a.ts", &[("a", "Documented")], &[]),
+            module("src/index.ts", &[("run", "")], &[]),
+            module("src/barrel.ts", &[("a", "")], &[("a", "re-export")]),
+            module("src/b.ts", &[("b", "")], &[]),
+            module("src/empty.ts", &[], &[]),
+            module("src/c.ts", &[("c", "Documented")], &[]),
+        ];
+        let files = vec![
+            file("src/a.ts", 2048),
+            file("src/index.ts", 500),
+            file("src/barrel.ts", 100),
+            file("src/b.ts", 500),
+            file("src/empty.ts", 10),
+            file("src/c.ts", 500),
+        ];
+        let dependencies = BTreeMap::from([
+            ("src/index.ts".to_string(), vec!["src/a.ts".to_string()]),
+            ("src/b.ts".to_string(), vec!["src/a.ts".to_string()]),
+        ]);
+        let resumed = HashSet::from(["src/c.ts".to_string()]);
+        let targets = select(
+            &mut modules,
+            &files,
+            &dependencies,
+            DeepSelect::Smart,
+            Some(2),
+            |_| 1,
+            &resumed,
+        );
+
+        let decisions: Vec<_> = modules
+            .iter()
+            .map(|m| {
+                let selection = m.selection.as_ref().unwrap();
+                (
+                    m.path.as_str(),
+                    selection.selected,
+                    selection.reason.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            decisions,
+            [
+                ("src/a.ts", false, "over budget"),
+                ("src/index.ts", true, "selected"),
+                ("src/barrel.ts", false, "barrel file"),
+                ("src/b.ts", true, "selected"),
+                ("src/empty.ts", false, "empty"),
+                ("src/c.ts", true, "resumed"),
+            ]
+        );
+        assert_eq!(targets.len(), 3);
+
+        let targets = select(
+            &mut modules,
+            &files,
+            &dependencies,
+            DeepSelect::All,
+            Some(2),
+            |_| 1,
+            &HashSet::new(),
+        );
+        assert_eq!(
+            targets,
+            HashSet::from(["src/a.ts".to_string(), "src/index.ts".to_string()])
+        );
+        let targets = select(
+            &mut modules,
+            &files,
+            &dependencies,
+            DeepSelect::None,
+            None,
+            |_| 1,
+            &resumed,
+        );
+        assert!(targets.is_empty());
+        assert_eq!(
+            modules[0].selection.as_ref().unwrap().reason,
+            "not selected"
+        );
+    }
+
+    #[test]
+    fn test_select_charges_chunks() {
+        let mut modules = vec![
+            module("src/big.ts", &[("big", "")], &[]),
+            module("src/small.ts", &[("small", "")], &[]),
+            module("src/other.ts", &[("other", "")], &[]),
+        ];
+        let files = vec![
+            file("src/big.ts", 5000),
+            file("src/small.ts", 500),
+            file("src/other.ts", 500),
+        ];
+        // One call per started KB
+        let calls = |size: u64| size.div_ceil(1024) as usize;
+
+        let targets = select(
+            &mut modules,
+            &files,
+            &BTreeMap::new(),
+            DeepSelect::All,
+            Some(5),
+            calls,
+            &HashSet::new(),
+        );
+        assert_eq!(targets, HashSet::from(["src/big.ts".to_string()]));
+        assert_eq!(modules[1].selection.as_ref().unwrap().reason, "over budget");
+
+        // Too big for what's left, so the smaller files after it get the calls
+        let targets = select(
+            &mut modules,
+            &files,
+            &BTreeMap::new(),
+            DeepSelect::All,
+            Some(4),
+            calls,
+            &HashSet::new(),
+        );
+        assert_eq!(
+            targets,
+            HashSet::from(["src/small.ts".to_string(), "src/other.ts".to_string()])
+        );
+    }
+}
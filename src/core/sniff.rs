@@ -0,0 +1,273 @@
+//! Language of a file from its content
+//!
+//! Files the extension tables don't know, such as `.inc`, `.tpl` or scripts
+//! without an extension, may still be code. [`language`] reads the start of
+//! one and names its language when a shebang says so, or when enough lines
+//! look like one language and few look like another. A wrong guess only
+//! means a parse that finds little, which analysis already handles.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use super::discovery::{Detection, Language};
+
+/// Files larger than this are not read
+pub const MAX_SNIFF_SIZE: u64 = 256 * 1024;
+/// Bytes read from the start of a file
+const SNIFF_BYTES: u64 = 16 * 1024;
+/// Lines that must look like the language
+const MIN_MATCHES: usize = 3;
+
+/// The language of the file at `path` and how it was recognized, when its
+/// start says so with confidence; `None` for a binary, unreadable or large file
+pub fn language(path: &Path, size: u64) -> Option<(Language, Detection)> {
+    if size > MAX_SNIFF_SIZE {
+        return None;
+    }
+    let mut bytes = Vec::new();
+    File::open(path)
+        .and_then(|file| file.take(SNIFF_BYTES).read_to_end(&mut bytes))
+        .ok()?;
+    if bytes.contains(&0) {
+        return None;
+    }
+    detect(&String::from_utf8_lossy(&bytes))
+}
+
+/// The language of `content`: from its shebang, else from the lines that
+/// look like a language's. The best language needs [`MIN_MATCHES`] lines and
+/// twice those of any other.
+pub fn detect(content: &str) -> Option<(Language, Detection)> {
+    if let Some(language) = content.lines().next().and_then(shebang) {
+        return Some((language, Detection::Shebang));
+    }
+
+    let mut scores: Vec<(Language, usize)> = Vec::new();
+    let mut typescript = 0;
+    let mut cpp = 0;
+    for line in content.lines().map(str::trim) {
+        for language in signatures(line) {
+            match scores.iter_mut().find(|(l, _)| *l == language) {
+                Some((_, score)) => *score += 1,
+                None => scores.push((language, 1)),
+            }
+        }
+        typescript += usize::from(is_typescript(line));
+        cpp += usize::from(is_cpp(line));
+    }
+    scores.sort_by_key(|&(_, score)| std::cmp::Reverse(score));
+    let (best, score) = *scores.first()?;
+    let runner_up = scores.get(1).map_or(0, |&(_, score)| score);
+    if score < MIN_MATCHES || score < runner_up * 2 {
+        return None;
+    }
+    // TypeScript and C++ files mostly look like the language they extend
+    let language = match best {
+        Language::JavaScript if typescript >= 2 => Language::TypeScript,
+        Language::C if cpp >= 2 => Language::Cpp,
+        language => language,
+    };
+    Some((language, Detection::Content))
+}
+
+/// The language of the interpreter a `#!` line runs, also through `env`
+fn shebang(line: &str) -> Option<Language> {
+    let mut words = line.strip_prefix("#!")?.split_whitespace();
+    let mut program = words.next()?.rsplit('/').next()?;
+    if program == "env" {
+        program = words.find(|word| !word.starts_with('-'))?;
+    }
+    match program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.') {
+        "python" | "pypy" => Some(Language::Python),
+        "node" | "nodejs" | "deno" | "bun" => Some(Language::JavaScript),
+        "ts-node" | "tsx" => Some(Language::TypeScript),
+        "sh" | "bash" | "zsh" | "dash" | "ksh" => Some(Language::Shell),
+        "ruby" => Some(Language::Ruby),
+        _ => None,
+    }
+}
+
+/// Languages a trimmed line is typical of
+fn signatures(line: &str) -> Vec<Language> {
+    let mut languages = Vec::new();
+    let mut check = |matches: bool, language: Language| {
+        if matches {
+            languages.push(language);
+        }
+    };
+
+    check(
+        (line.starts_with("def ") || line.starts_with("class ")) && line.ends_with(':')
+            || line.starts_with("from ") && line.contains(" import ")
+            || line.starts_with("if __name__ ==")
+            || line.starts_with("elif ") && line.ends_with(':')
+            || matches!(line, "try:" | "else:" | "finally:")
+            || line.starts_with("except") && line.ends_with(':'),
+        Language::Python,
+    );
+    check(
+        line.starts_with("function ") && line.contains('(')
+            || line.starts_with("module.exports")
+            || line.starts_with("exports.")
+            || line.contains("require(") && starts_with_any(line, &["const ", "let ", "var "])
+            || starts_with_any(
+                line,
+                &[
+                    "export default ",
+                    "export function ",
+                    "export const ",
+                    "export class ",
+                ],
+            )
+            || line.starts_with("import ")
+                && line.contains(" from ")
+                && line.ends_with(['\'', '"', ';'])
+            || line.contains("console.log(")
+            || line.ends_with("=> {"),
+        Language::JavaScript,
+    );
+    check(
+        line.starts_with("def ") && !line.ends_with(':')
+            || line == "end"
+            || starts_with_any(line, &["require '", "require \"", "require_relative "])
+            || starts_with_any(line, &["puts ", "attr_accessor ", "attr_reader "])
+            || line.contains(" do |"),
+        Language::Ruby,
+    );
+    check(
+        matches!(line, "fi" | "done" | "esac" | "then")
+            || line.ends_with("; then")
+            || line.ends_with("; do")
+            || line.starts_with("echo ")
+            || line.starts_with("set -e")
+            || line.starts_with("export ") && line.contains('=') && !line.contains(" = "),
+        Language::Shell,
+    );
+    check(
+        starts_with_any(line, &["fn ", "pub fn ", "pub(crate) fn ", "async fn "])
+            || line.starts_with("use ") && line.contains("::") && line.ends_with(';')
+            || starts_with_any(line, &["impl ", "impl<", "let mut ", "#[derive", "#[cfg"])
+            || starts_with_any(line, &["pub struct ", "pub enum ", "mod "]),
+        Language::Rust,
+    );
+    check(
+        line.starts_with("package ") && !line.ends_with(';')
+            || line.starts_with("func ")
+            || line == "import ("
+            || line.contains(" := ")
+            || line == "if err != nil {",
+        Language::Go,
+    );
+    check(
+        starts_with_any(line, &["#include ", "#include<", "#define ", "#ifndef "])
+            || line.starts_with("int main(")
+            || line.contains("printf(")
+            || line.contains("malloc("),
+        Language::C,
+    );
+    check(
+        line.starts_with("package ") && line.ends_with(';')
+            || line.starts_with("import java.")
+            || line.contains("System.out.print")
+            || line.contains("public static void main("),
+        Language::Java,
+    );
+    check(
+        line.starts_with("using System")
+            || line.contains("Console.Write")
+            || line.contains("static void Main("),
+        Language::CSharp,
+    );
+    languages
+}
+
+/// A line only TypeScript has among the JavaScript-like ones
+fn is_typescript(line: &str) -> bool {
+    starts_with_any(line, &["interface ", "export interface ", "import type "])
+        || starts_with_any(line, &["type ", "export type "]) && line.contains(" = ")
+        || [": string", ": number", ": boolean"]
+            .iter()
+            .any(|annotation| line.contains(annotation))
+}
+
+/// A line only C++ has among the C-like ones
+fn is_cpp(line: &str) -> bool {
+    line.contains("std::")
+        || starts_with_any(
+            line,
+            &["namespace ", "using namespace ", "template<", "template <"],
+        )
+        || line.starts_with("#include <") && !line.contains(".h>")
+}
+
+fn starts_with_any(line: &str, prefixes: &[&str]) -> bool {
+    prefixes.iter().any(|prefix| line.starts_with(prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shebang() {
+        for (line, language) in [
+            ("#!/usr/bin/env python3", Some(Language::Python)),
+            ("#!/usr/bin/python3.11 -u", Some(Language::Python)),
+            (
+                "#!/usr/bin/env -S node --no-warnings",
+                Some(Language::JavaScript),
+            ),
+            ("#!/bin/bash", Some(Language::Shell)),
+            ("#!/usr/bin/env ruby", Some(Language::Ruby)),
+            ("#!/usr/bin/perl", None),
+            ("# just a comment", None),
+        ] {
+            assert_eq!(shebang(line), language, "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_detect_disguised_files() {
+        let python = "import os\nfrom pathlib import Path\n\nclass Loader:\n    def load(self, path):\n        try:\n            return Path(path).read_text()\n        except OSError:\n            return None\n";
+        let javascript = "const path = require('path');\n\nfunction render(name) {\n  console.log(name);\n}\n\nmodule.exports = { render };\n";
+        let typescript = "import { render } from './render';\n\nexport interface Options {\n  name: string;\n  depth: number;\n}\n\nexport function run(options: Options) {\n  console.log(render(options.name));\n}\n";
+        let shell = "set -euo pipefail\nif [ -z \"$1\" ]; then\n  echo \"usage: deploy <env>\"\n  exit 1\nfi\n";
+        let php_template = "<div class=\"header\">\n  <?php echo $title; ?>\n</div>\n";
+        let prose = "Copyright (c) 2024\n\nPermission is hereby granted, free of charge, to any person obtaining a copy\nof this software, to deal in the Software without restriction.\n";
+
+        assert_eq!(detect(python), Some((Language::Python, Detection::Content)));
+        assert_eq!(
+            detect(javascript),
+            Some((Language::JavaScript, Detection::Content))
+        );
+        assert_eq!(
+            detect(typescript),
+            Some((Language::TypeScript, Detection::Content))
+        );
+        assert_eq!(detect(shell), Some((Language::Shell, Detection::Content)));
+        assert_eq!(
+            detect("#!/usr/bin/env node\nrun();\n"),
+            Some((Language::JavaScript, Detection::Shebang))
+        );
+        assert_eq!(detect(php_template), None);
+        assert_eq!(detect(prose), None);
+        assert_eq!(detect(""), None);
+    }
+
+    #[test]
+    fn test_language_skips_binary_and_large_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let binary = dir.path().join("blob");
+        std::fs::write(&binary, b"#!/bin/sh\n\0\0\0").unwrap();
+        assert_eq!(language(&binary, 12), None);
+
+        let script = dir.path().join("deploy");
+        std::fs::write(&script, "#!/bin/sh\necho hi\n").unwrap();
+        assert_eq!(
+            language(&script, 18),
+            Some((Language::Shell, Detection::Shebang))
+        );
+        assert_eq!(language(&script, MAX_SNIFF_SIZE + 1), None);
+    }
+}
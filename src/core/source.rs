@@ -0,0 +1,126 @@
+//! Reading source files for parsing
+//!
+//! Files are read as bytes rather than with `read_to_string`, so legacy
+//! encodings still get parsed instead of failing the whole module: a UTF-8
+//! BOM is stripped, invalid UTF-8 is converted lossily with a warning, and
+//! binary content is reported so the caller can skip the file.
+
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Bytes inspected for NUL when deciding whether content is binary, as git does
+const BINARY_SNIFF_LEN: usize = 8000;
+
+#[derive(Debug, PartialEq)]
+pub enum SourceText {
+    Text {
+        content: String,
+        /// Set when the file was not valid UTF-8 and was converted lossily
+        encoding_warning: Option<String>,
+    },
+    Binary,
+}
+
+/// Read a source file as text; see [`decode`]
+pub fn read_source(path: &Path) -> std::io::Result<SourceText> {
+    Ok(decode(&std::fs::read(path)?))
+}
+
+/// `sha256:<hex>` of a file's content with CRLF line endings read as LF, so
+/// Windows and Unix checkouts of the same file hash alike
+pub fn content_hash(path: &Path) -> std::io::Result<String> {
+    let bytes = std::fs::read(path)?;
+    let mut hasher = Sha256::new();
+    for chunk in bytes.split_inclusive(|&b| b == b'\n') {
+        match chunk.strip_suffix(b"\r\n") {
+            Some(line) => {
+                hasher.update(line);
+                hasher.update(b"\n");
+            }
+            None => hasher.update(chunk),
+        }
+    }
+    Ok(format!("sha256:{:x}", hasher.finalize()))
+}
+
+/// Decode file content: strip a UTF-8 BOM, then UTF-8, falling back to a
+/// lossy conversion. Content with a NUL byte near the start is binary.
+pub fn decode(bytes: &[u8]) -> SourceText {
+    let bytes = bytes.strip_prefix(BOM).unwrap_or(bytes);
+    if bytes[..bytes.len().min(BINARY_SNIFF_LEN)].contains(&0) {
+        return SourceText::Binary;
+    }
+
+    match std::str::from_utf8(bytes) {
+        Ok(content) => SourceText::Text {
+            content: content.to_string(),
+            encoding_warning: None,
+        },
+        Err(e) => SourceText::Text {
+            content: String::from_utf8_lossy(bytes).into_owned(),
+            encoding_warning: Some(format!(
+                "not valid UTF-8 (first invalid byte at offset {}); \
+                 invalid bytes were replaced with U+FFFD",
+                e.valid_up_to()
+            )),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::discovery::Language;
+    use crate::core::parser;
+
+    fn text(source: SourceText) -> (String, Option<String>) {
+        match source {
+            SourceText::Text {
+                content,
+                encoding_warning,
+            } => (content, encoding_warning),
+            SourceText::Binary => panic!("decoded as binary"),
+        }
+    }
+
+    #[test]
+    fn test_bom_is_stripped() {
+        let (content, warning) = text(decode(b"\xEF\xBB\xBFpub fn first() -> u8 {\n    1\n}\n"));
+        assert!(warning.is_none());
+        assert!(content.starts_with("pub fn first"));
+
+        let exports = parser::parse_file(Path::new("lib.rs"), &content, Language::Rust, false)
+            .unwrap()
+            .exports;
+        assert_eq!(exports.len(), 1);
+        assert_eq!(exports[0].name, "first");
+        let signature = exports[0].signature.as_deref().unwrap();
+        assert!(signature.starts_with("pub fn first()"), "{:?}", signature);
+    }
+
+    #[test]
+    fn test_latin1_is_decoded_lossily() {
+        // "/// Größe" in Latin-1, which is not valid UTF-8
+        let mut bytes = b"/// Gr\xF6\xDFe\npub fn size() -> u32 { 0 }\n".to_vec();
+        bytes.extend_from_slice(b"pub struct Caf\xE9Menu;\n");
+
+        let (content, warning) = text(decode(&bytes));
+        assert!(warning.unwrap().contains("offset 6"));
+        assert!(content.contains('\u{FFFD}'));
+
+        let exports = parser::parse_file(Path::new("lib.rs"), &content, Language::Rust, false)
+            .unwrap()
+            .exports;
+        let names: Vec<_> = exports.iter().map(|e| e.name.as_str()).collect();
+        assert!(names.contains(&"size"), "{:?}", names);
+        assert_eq!(exports.len(), 2);
+    }
+
+    #[test]
+    fn test_binary_content() {
+        assert_eq!(decode(b"\x7FELF\x02\x01\x01\x00\x00"), SourceText::Binary);
+        assert!(matches!(decode(b""), SourceText::Text { .. }));
+    }
+}
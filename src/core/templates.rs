@@ -0,0 +1,173 @@
+//! System prompts for `--deep` analysis, by kind of file
+//!
+//! Tests, shell scripts, React components, SQL files and Dockerfiles each
+//! get a template with questions of their own; every other file gets the
+//! generic one. The templates are embedded from `src/core/templates/`, and
+//! `--templates <DIR>` replaces any of them with `<DIR>/<id>.md`. The id of
+//! the template is recorded with each module.
+
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use super::discovery::Language;
+use super::exit::ConfigError;
+
+pub const GENERIC: &str = "generic";
+pub const TEST: &str = "test";
+
+/// The embedded templates by id
+const EMBEDDED: &[(&str, &str)] = &[
+    (GENERIC, include_str!("templates/generic.md")),
+    (TEST, include_str!("templates/test.md")),
+    ("shell", include_str!("templates/shell.md")),
+    ("react", include_str!("templates/react.md")),
+    ("sql", include_str!("templates/sql.md")),
+    ("dockerfile", include_str!("templates/dockerfile.md")),
+];
+
+/// Templates read from `--templates`, by id
+static OVERRIDES: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+/// Ids of the templates, the generic one first
+pub fn ids() -> impl Iterator<Item = &'static str> {
+    EMBEDDED.iter().map(|(id, _)| *id)
+}
+
+/// The template for a file: the test template for tests, otherwise the one
+/// for its language or kind, falling back to [`GENERIC`]
+pub fn select(path: &str, language: Language, is_test: bool) -> &'static str {
+    if is_test {
+        return TEST;
+    }
+    let name = path
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or(path)
+        .to_lowercase();
+    let extension = name.rsplit_once('.').map_or("", |(_, extension)| extension);
+    match (language, extension) {
+        (Language::Shell, _) => "shell",
+        (_, "tsx" | "jsx") => "react",
+        (_, "sql") => "sql",
+        _ if name == "dockerfile"
+            || name.starts_with("dockerfile.")
+            || extension == "dockerfile" =>
+        {
+            "dockerfile"
+        }
+        _ => GENERIC,
+    }
+}
+
+/// The system prompt of template `id`: its `--templates` override, else the
+/// embedded one. An unknown id gets the generic prompt.
+pub fn prompt(id: &str) -> &'static str {
+    if let Some(prompt) = OVERRIDES.get().and_then(|o| o.get(id)) {
+        return prompt.trim_end();
+    }
+    EMBEDDED
+        .iter()
+        .find(|(known, _)| *known == id)
+        .or_else(|| EMBEDDED.first())
+        .map_or("", |(_, prompt)| prompt.trim_end())
+}
+
+/// Use the templates in `dir` instead of the embedded ones with the same id,
+/// for the rest of the process. Returns the ids replaced; fails when they
+/// were already set.
+pub fn set_overrides(dir: &Path) -> Result<Vec<String>> {
+    let overrides = read_overrides(dir).map_err(ConfigError::wrap)?;
+    let mut ids: Vec<String> = overrides.keys().cloned().collect();
+    ids.sort();
+    OVERRIDES
+        .set(overrides)
+        .map_err(|_| anyhow::anyhow!("Prompt templates were already set"))?;
+    Ok(ids)
+}
+
+/// The `<id>.md` files in `dir`. Other files are ignored, but a Markdown
+/// file named after no template is an error, as it is likely a typo.
+fn read_overrides(dir: &Path) -> Result<HashMap<String, String>> {
+    let entries =
+        fs::read_dir(dir).with_context(|| format!("Cannot read --templates {}", dir.display()))?;
+    let mut overrides = HashMap::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().is_none_or(|e| e != "md") {
+            continue;
+        }
+        let id = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default()
+            .to_string();
+        if !ids().any(|known| known == id) {
+            anyhow::bail!(
+                "{} is not a prompt template; templates are {}",
+                path.display(),
+                ids()
+                    .map(|id| format!("{}.md", id))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+        let prompt =
+            fs::read_to_string(&path).with_context(|| format!("Cannot read {}", path.display()))?;
+        if prompt.trim().is_empty() {
+            anyhow::bail!("{} is empty", path.display());
+        }
+        overrides.insert(id, prompt);
+    }
+    Ok(overrides)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_template() {
+        let select = |path, language| select(path, language, false);
+        assert_eq!(select("src/lib.rs", Language::Rust), GENERIC);
+        assert_eq!(select("scripts/deploy.sh", Language::Shell), "shell");
+        assert_eq!(select("web/Button.tsx", Language::TypeScript), "react");
+        assert_eq!(select("web/app.jsx", Language::JavaScript), "react");
+        assert_eq!(select("web/api.ts", Language::TypeScript), GENERIC);
+        assert_eq!(select("db/001_init.sql", Language::Plugin), "sql");
+        assert_eq!(select("Dockerfile", Language::Unknown), "dockerfile");
+        assert_eq!(select("api/Dockerfile.dev", Language::Plugin), "dockerfile");
+        assert_eq!(
+            super::select("web/Button.test.tsx", Language::TypeScript, true),
+            TEST
+        );
+    }
+
+    #[test]
+    fn test_embedded_templates() {
+        for id in ids() {
+            assert!(prompt(id).starts_with("You are a"), "{}", id);
+            assert!(!prompt(id).ends_with('\n'), "{}", id);
+        }
+        assert_eq!(prompt("no-such-template"), prompt(GENERIC));
+    }
+
+    #[test]
+    fn test_read_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("shell.md"), "Explain the script.\n").unwrap();
+        fs::write(dir.path().join("notes.txt"), "ignored").unwrap();
+        let overrides = read_overrides(dir.path()).unwrap();
+        assert_eq!(
+            overrides,
+            HashMap::from([("shell".to_string(), "Explain the script.\n".to_string())])
+        );
+
+        fs::write(dir.path().join("shel.md"), "Typo").unwrap();
+        let err = read_overrides(dir.path()).unwrap_err().to_string();
+        assert!(err.contains("shel.md is not a prompt template"), "{}", err);
+        assert!(err.contains("generic.md, test.md, shell.md"), "{}", err);
+    }
+}
@@ -0,0 +1,337 @@
+//! Where an analyze run spends its time
+//!
+//! A [`Timings`] collector is handed through the pipeline: phases record their
+//! wall time, and the analyzer records parse and LLM durations per file,
+//! slow and abandoned parses, and the times it backed off a failing provider. The resulting [`TimingReport`]
+//! is embedded in analysis.json and printed with `--timings`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+use super::backoff::{BackoffAction, BackoffEvent};
+use super::parser;
+
+/// Files listed in [`TimingReport::slowest_files`]
+pub const SLOWEST_FILES: usize = 10;
+
+/// Cheap to clone; clones share the same measurements
+#[derive(Debug, Clone, Default)]
+pub struct Timings {
+    inner: Arc<Mutex<Measurements>>,
+}
+
+#[derive(Debug, Default)]
+struct Measurements {
+    phases: Vec<(String, Duration)>,
+    parse: HashMap<String, Duration>,
+    llm: HashMap<String, Duration>,
+    over_budget: HashMap<String, Duration>,
+    /// Files parsed slower than [`parser::slow_parse`], by their parse time
+    slow_parses: HashMap<String, Duration>,
+    /// Why files were scanned instead of parsed
+    parse_fallbacks: HashMap<String, String>,
+    backoff: Vec<BackoffEvent>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TimingReport {
+    pub phases: Vec<PhaseTiming>,
+    pub parse: Distribution,
+    pub llm: Distribution,
+    /// By parse + LLM time, slowest first
+    pub slowest_files: Vec<FileTiming>,
+    /// Files whose LLM analysis was stopped by the per-file budget, by the
+    /// time they took, slowest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub over_budget: Vec<OverBudgetFile>,
+    /// Files that took [`parser::slow_parse`] or longer to parse, slowest
+    /// first: candidates for `analysis.ignore_patterns`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub slow_parses: Vec<SlowParse>,
+    /// Times the deep loop eased off a failing provider, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub backoff: Vec<BackoffEvent>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PhaseTiming {
+    pub name: String,
+    pub ms: u64,
+}
+
+/// Per-file durations of one kind of work
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Distribution {
+    pub files: usize,
+    pub total_ms: u64,
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileTiming {
+    pub path: String,
+    pub parse_ms: u64,
+    pub llm_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OverBudgetFile {
+    pub path: String,
+    pub ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SlowParse {
+    pub path: String,
+    pub ms: u64,
+    /// Set when the parse was abandoned and the file scanned instead
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback: Option<String>,
+}
+
+impl Timings {
+    pub fn phase(&self, name: &str, elapsed: Duration) {
+        self.lock().phases.push((name.to_string(), elapsed));
+    }
+
+    /// Run `f` and record its duration as parsing `file`, warning when it
+    /// was slow
+    pub fn parse<T>(&self, file: &str, f: impl FnOnce() -> T) -> T {
+        let started = Instant::now();
+        let result = f();
+        let elapsed = started.elapsed();
+        let mut measurements = self.lock();
+        let total = measurements.parse.entry(file.to_string()).or_default();
+        *total += elapsed;
+        let total = *total;
+        if total >= parser::slow_parse() {
+            if !measurements.slow_parses.contains_key(file) {
+                warn!("{}: parsing took {:.1}s", file, total.as_secs_f64());
+            }
+            measurements.slow_parses.insert(file.to_string(), total);
+        }
+        result
+    }
+
+    /// Record that `file` was scanned because its parse was abandoned
+    pub fn parse_fallback(&self, file: &str, reason: &str) {
+        self.lock()
+            .parse_fallbacks
+            .insert(file.to_string(), reason.to_string());
+    }
+
+    /// Await `f` and record its duration as LLM time for `file`
+    pub async fn llm<T>(&self, file: &str, f: impl std::future::Future<Output = T>) -> T {
+        let started = Instant::now();
+        let result = f.await;
+        *self.lock().llm.entry(file.to_string()).or_default() += started.elapsed();
+        result
+    }
+
+    /// Record that the analysis of `file` was stopped by the per-file budget
+    /// after `elapsed`
+    pub fn over_budget(&self, file: &str, elapsed: Duration) {
+        self.lock().over_budget.insert(file.to_string(), elapsed);
+    }
+
+    /// Record that the deep loop backed off a failing provider
+    pub fn backoff(&self, event: BackoffEvent) {
+        self.lock().backoff.push(event);
+    }
+
+    pub fn report(&self) -> TimingReport {
+        let measurements = self.lock();
+
+        let mut files: Vec<FileTiming> = measurements
+            .parse
+            .keys()
+            .chain(measurements.llm.keys())
+            .collect::<std::collections::HashSet<_>>()
+            .into_iter()
+            .map(|path| FileTiming {
+                path: path.clone(),
+                parse_ms: ms(measurements.parse.get(path).copied().unwrap_or_default()),
+                llm_ms: ms(measurements.llm.get(path).copied().unwrap_or_default()),
+            })
+            .collect();
+        files.sort_by(|a, b| {
+            (b.parse_ms + b.llm_ms)
+                .cmp(&(a.parse_ms + a.llm_ms))
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        files.truncate(SLOWEST_FILES);
+
+        let mut over_budget: Vec<OverBudgetFile> = measurements
+            .over_budget
+            .iter()
+            .map(|(path, elapsed)| OverBudgetFile {
+                path: path.clone(),
+                ms: ms(*elapsed),
+            })
+            .collect();
+        over_budget.sort_by(|a, b| b.ms.cmp(&a.ms).then_with(|| a.path.cmp(&b.path)));
+
+        let mut slow_parses: Vec<SlowParse> = measurements
+            .slow_parses
+            .keys()
+            .chain(measurements.parse_fallbacks.keys())
+            .collect::<std::collections::HashSet<_>>()
+            .into_iter()
+            .map(|path| SlowParse {
+                path: path.clone(),
+                ms: ms(measurements.parse.get(path).copied().unwrap_or_default()),
+                fallback: measurements.parse_fallbacks.get(path).cloned(),
+            })
+            .collect();
+        slow_parses.sort_by(|a, b| b.ms.cmp(&a.ms).then_with(|| a.path.cmp(&b.path)));
+
+        TimingReport {
+            phases: measurements
+                .phases
+                .iter()
+                .map(|(name, elapsed)| PhaseTiming {
+                    name: name.clone(),
+                    ms: ms(*elapsed),
+                })
+                .collect(),
+            parse: Distribution::of(measurements.parse.values()),
+            llm: Distribution::of(measurements.llm.values()),
+            slowest_files: files,
+            over_budget,
+            slow_parses,
+            backoff: measurements.backoff.clone(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Measurements> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Distribution {
+    fn of<'a>(durations: impl Iterator<Item = &'a Duration>) -> Self {
+        let mut sorted: Vec<u64> = durations.map(|d| ms(*d)).collect();
+        sorted.sort_unstable();
+        Self {
+            files: sorted.len(),
+            total_ms: sorted.iter().sum(),
+            p50_ms: percentile(&sorted, 50),
+            p95_ms: percentile(&sorted, 95),
+        }
+    }
+}
+
+/// Nearest-rank percentile of sorted values
+fn percentile(sorted: &[u64], p: usize) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (p * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+fn ms(d: Duration) -> u64 {
+    d.as_millis() as u64
+}
+
+impl TimingReport {
+    /// Plain-text table for `--timings`
+    pub fn print(&self) {
+        println!("\nTimings");
+        let width = self.phases.iter().map(|p| p.name.len()).max().unwrap_or(0);
+        for phase in &self.phases {
+            println!(
+                "  {:<width$}  {:>8.1}s",
+                phase.name,
+                phase.ms as f64 / 1000.0,
+                width = width
+            );
+        }
+
+        for (label, dist) in [("parse", &self.parse), ("llm", &self.llm)] {
+            if dist.files > 0 {
+                println!(
+                    "\n  {} per file ({} files): p50 {}ms, p95 {}ms, total {}ms",
+                    label, dist.files, dist.p50_ms, dist.p95_ms, dist.total_ms
+                );
+            }
+        }
+
+        if !self.slowest_files.is_empty() {
+            println!("\n  Slowest files (parse + llm):");
+            for file in &self.slowest_files {
+                println!("  {:>8}ms  {}", file.parse_ms + file.llm_ms, file.path);
+            }
+        }
+
+        if !self.over_budget.is_empty() {
+            println!("\n  Stopped by the per-file budget:");
+            for file in &self.over_budget {
+                println!("  {:>8}ms  {}", file.ms, file.path);
+            }
+        }
+
+        if !self.slow_parses.is_empty() {
+            println!("\n  Slow parses (add them to analysis.ignore_patterns to skip them):");
+            for file in &self.slow_parses {
+                let note = if file.fallback.is_some() {
+                    "  (abandoned, scanned instead)"
+                } else {
+                    ""
+                };
+                println!("  {:>8}ms  {}{}", file.ms, file.path, note);
+            }
+        }
+
+        if !self.backoff.is_empty() {
+            println!("\n  Provider backoff:");
+            for event in &self.backoff {
+                let action = match event.action {
+                    BackoffAction::Cooldown => "cooldown",
+                    BackoffAction::Stop => "stopped deep analysis",
+                };
+                println!(
+                    "  {:>7.1}s  {}: {}/{} failed, LLM concurrency {}",
+                    event.at_ms as f64 / 1000.0,
+                    action,
+                    event.failed,
+                    event.window,
+                    event.concurrency
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentile() {
+        let values: Vec<u64> = (1..=20).collect();
+        assert_eq!(percentile(&values, 50), 10);
+        assert_eq!(percentile(&values, 95), 19);
+        assert_eq!(percentile(&[7], 95), 7);
+        assert_eq!(percentile(&[], 50), 0);
+    }
+
+    #[test]
+    fn test_parse_fallbacks_are_listed() {
+        let timings = Timings::default();
+        timings.parse("gen/parser.js", || {
+            timings.parse_fallback("gen/parser.js", "parsing did not finish within 30s")
+        });
+        timings.parse("src/a.rs", || ());
+
+        let report = timings.report();
+        assert_eq!(report.slow_parses.len(), 1);
+        assert_eq!(report.slow_parses[0].path, "gen/parser.js");
+        assert!(report.slow_parses[0].fallback.is_some());
+        assert_eq!(report.parse.files, 2);
+    }
+}
@@ -0,0 +1,336 @@
+//! A small YAML reader for the config files cda looks into
+//!
+//! It knows block mappings and sequences, flow sequences and mappings on
+//! one line, block scalars, quotes and comments, which is all CI configs,
+//! Kubernetes manifests, Helm charts and OpenAPI specs use in practice.
+//! Anchors, aliases, tags and multi-line flow collections aren't followed.
+
+/// A YAML value
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Yaml {
+    Null,
+    Scalar(String),
+    List(Vec<Yaml>),
+    Map(Vec<(String, Yaml)>),
+}
+
+/// A line of the document: its indent and its text, trimmed
+struct Line<'a> {
+    indent: usize,
+    text: &'a str,
+    raw: &'a str,
+}
+
+impl Yaml {
+    /// The first document of `content`
+    pub(crate) fn parse(content: &str) -> Yaml {
+        let lines: Vec<Line> = content
+            .lines()
+            .map(|raw| {
+                let text = raw.trim_start();
+                Line {
+                    indent: raw.len() - text.len(),
+                    text: text.trim_end(),
+                    raw,
+                }
+            })
+            .collect();
+        let mut parser = Parser { lines, at: 0 };
+        parser.skip_insignificant();
+        match parser.peek() {
+            Some((indent, _)) => parser.block(indent),
+            None => Yaml::Null,
+        }
+    }
+
+    pub(crate) fn get(&self, key: &str) -> Option<&Yaml> {
+        match self {
+            Yaml::Map(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match self {
+            Yaml::Scalar(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn entries(&self) -> Vec<(String, Yaml)> {
+        match self {
+            Yaml::Map(entries) => entries.clone(),
+            _ => vec![],
+        }
+    }
+
+    pub(crate) fn items(&self) -> Vec<Yaml> {
+        match self {
+            Yaml::List(items) => items.clone(),
+            _ => vec![],
+        }
+    }
+
+    /// A scalar, or the scalars of a list
+    pub(crate) fn strings(&self) -> Vec<String> {
+        match self {
+            Yaml::Scalar(s) => vec![s.clone()],
+            Yaml::List(items) => items
+                .iter()
+                .filter_map(|i| i.as_str().map(String::from))
+                .collect(),
+            _ => vec![],
+        }
+    }
+}
+
+/// Every document of a `---`-separated stream, empty ones left out
+pub(crate) fn documents(content: &str) -> Vec<Yaml> {
+    let mut documents = Vec::new();
+    let mut current = String::new();
+    for line in content.lines() {
+        if line == "---" || line.starts_with("--- ") || line == "..." {
+            documents.push(Yaml::parse(&current));
+            current.clear();
+        } else {
+            current.push_str(line);
+            current.push('\n');
+        }
+    }
+    documents.push(Yaml::parse(&current));
+    documents.retain(|d| *d != Yaml::Null);
+    documents
+}
+
+/// A JSON document as a [`Yaml`] value, of which JSON is a subset
+pub(crate) fn from_json(value: &serde_json::Value) -> Yaml {
+    use serde_json::Value;
+    match value {
+        Value::Null => Yaml::Null,
+        Value::String(s) => Yaml::Scalar(s.clone()),
+        Value::Bool(_) | Value::Number(_) => Yaml::Scalar(value.to_string()),
+        Value::Array(items) => Yaml::List(items.iter().map(from_json).collect()),
+        Value::Object(entries) => Yaml::Map(
+            entries
+                .iter()
+                .map(|(k, v)| (k.clone(), from_json(v)))
+                .collect(),
+        ),
+    }
+}
+
+struct Parser<'a> {
+    lines: Vec<Line<'a>>,
+    at: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn skip_insignificant(&mut self) {
+        while let Some(line) = self.lines.get(self.at) {
+            if line.text.is_empty() || line.text.starts_with('#') || line.text == "---" {
+                self.at += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Indent and text of the next significant line
+    fn peek(&mut self) -> Option<(usize, &'a str)> {
+        self.skip_insignificant();
+        self.lines.get(self.at).map(|l| (l.indent, l.text))
+    }
+
+    /// The mapping or sequence starting at the next line, at `indent`
+    fn block(&mut self, indent: usize) -> Yaml {
+        match self.peek() {
+            Some((_, text)) if is_item(text) => self.sequence(indent),
+            Some(_) => self.mapping(indent),
+            None => Yaml::Null,
+        }
+    }
+
+    fn sequence(&mut self, indent: usize) -> Yaml {
+        let mut items = Vec::new();
+        while let Some((depth, text)) = self.peek() {
+            if depth != indent || !is_item(text) {
+                break;
+            }
+            let rest = text[1..].trim_start();
+            if rest.is_empty() {
+                self.at += 1;
+                items.push(self.nested(indent));
+            } else if split_key(rest).is_some() {
+                // `- key: value` starts a mapping indented past the dash
+                let inner = indent + (text.len() - rest.len());
+                self.lines[self.at].indent = inner;
+                self.lines[self.at].text = rest;
+                items.push(self.mapping(inner));
+            } else {
+                self.at += 1;
+                items.push(self.value(rest, indent));
+            }
+        }
+        Yaml::List(items)
+    }
+
+    fn mapping(&mut self, indent: usize) -> Yaml {
+        let mut entries = Vec::new();
+        while let Some((depth, text)) = self.peek() {
+            if depth != indent || is_item(text) {
+                break;
+            }
+            self.at += 1;
+            let Some((key, rest)) = split_key(text) else {
+                continue;
+            };
+            let value = if rest.is_empty() {
+                match self.peek() {
+                    // A sequence may sit at the same indent as its key
+                    Some((depth, text)) if depth == indent && is_item(text) => {
+                        self.sequence(indent)
+                    }
+                    _ => self.nested(indent),
+                }
+            } else {
+                self.value(rest, indent)
+            };
+            entries.push((key, value));
+        }
+        Yaml::Map(entries)
+    }
+
+    /// The block under a line at `indent`, if the next line is indented more
+    fn nested(&mut self, indent: usize) -> Yaml {
+        match self.peek() {
+            Some((depth, _)) if depth > indent => self.block(depth),
+            _ => Yaml::Null,
+        }
+    }
+
+    /// An inline value, or the block scalar it introduces
+    fn value(&mut self, text: &str, indent: usize) -> Yaml {
+        let text = strip_comment(text);
+        if text.starts_with('|') || text.starts_with('>') {
+            let folded = text.starts_with('>');
+            let mut block: Vec<&str> = Vec::new();
+            while let Some(line) = self.lines.get(self.at) {
+                if !line.text.is_empty() && line.indent <= indent {
+                    break;
+                }
+                block.push(line.raw);
+                self.at += 1;
+            }
+            while block.last().is_some_and(|l| l.trim().is_empty()) {
+                block.pop();
+            }
+            let margin = block
+                .iter()
+                .filter(|l| !l.trim().is_empty())
+                .map(|l| l.len() - l.trim_start().len())
+                .min()
+                .unwrap_or(0);
+            let lines: Vec<&str> = block
+                .iter()
+                .map(|l| l.get(margin..).unwrap_or("").trim_end())
+                .collect();
+            return Yaml::Scalar(lines.join(if folded { " " } else { "\n" }));
+        }
+        if let Some(inner) = text.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+            return Yaml::List(
+                inner
+                    .split(',')
+                    .map(|item| unquote(item.trim()))
+                    .filter(|item| !item.is_empty())
+                    .map(Yaml::Scalar)
+                    .collect(),
+            );
+        }
+        if let Some(inner) = text.strip_prefix('{').and_then(|t| t.strip_suffix('}')) {
+            return Yaml::Map(
+                inner
+                    .split(',')
+                    .filter_map(|pair| {
+                        let (key, value) = split_key(pair.trim())?;
+                        Some((key, Yaml::Scalar(unquote(value))))
+                    })
+                    .collect(),
+            );
+        }
+        Yaml::Scalar(unquote(text))
+    }
+}
+
+fn is_item(text: &str) -> bool {
+    text == "-" || text.starts_with("- ")
+}
+
+/// `key: rest` or `key:`, with the key unquoted
+fn split_key(text: &str) -> Option<(String, &str)> {
+    let mut quote = None;
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') if i == 0 => quote = Some(c),
+            (None, ':') => {
+                let rest = &text[i + 1..];
+                if rest.is_empty() || rest.starts_with(' ') {
+                    return Some((unquote(&text[..i]), rest.trim()));
+                }
+            }
+            (None, _) => {}
+        }
+    }
+    None
+}
+
+/// `text` without a trailing ` # comment`, unless it is quoted
+fn strip_comment(text: &str) -> &str {
+    if text.starts_with('"') || text.starts_with('\'') {
+        return text;
+    }
+    match text.find(" #") {
+        Some(end) => text[..end].trim_end(),
+        None => text,
+    }
+}
+
+fn unquote(text: &str) -> String {
+    let text = strip_comment(text.trim());
+    for quote in ['"', '\''] {
+        if let Some(inner) = text.strip_prefix(quote).and_then(|t| t.strip_suffix(quote)) {
+            return inner.to_string();
+        }
+    }
+    text.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_documents() {
+        let docs = documents(
+            "---\napiVersion: v1\nkind: Service\nmetadata:\n  name: web # public\n---\n# empty\n---\nkind: Deployment\nspec:\n  ports: [80, \"443\"]\n",
+        );
+        assert_eq!(docs.len(), 2);
+        assert_eq!(
+            docs[0].get("metadata").and_then(|m| m.get("name")),
+            Some(&Yaml::Scalar("web".into()))
+        );
+        assert_eq!(
+            docs[1]
+                .get("spec")
+                .and_then(|s| s.get("ports"))
+                .map(Yaml::strings),
+            Some(vec!["80".to_string(), "443".to_string()])
+        );
+
+        let json = serde_json::json!({"openapi": "3.0.0", "paths": {"/a": {"get": {}}}});
+        let doc = from_json(&json);
+        assert_eq!(doc.get("openapi").and_then(Yaml::as_str), Some("3.0.0"));
+        assert_eq!(doc.get("paths").map(|p| p.entries().len()), Some(1));
+    }
+}
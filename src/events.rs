@@ -0,0 +1,184 @@
+//! Machine-readable progress events
+//!
+//! `cda analyze --progress-json <FILE|->` writes one JSON object per line for
+//! tools that wrap cda, next to the usual terminal output rather than instead
+//! of it. Every event has the [`EVENTS_SCHEMA`] version as `schema`, a `seq`
+//! number counting from 0, a `timestamp` and its kind as `event`; the other
+//! fields depend on the kind, see [`Event`]. Each line is flushed as it is
+//! written, so a reader sees it right away.
+//!
+//! A run emits `run_started` first and `summary` or `run_failed` last. Every
+//! `phase_started` is followed by its `phase_completed`, and a file of the
+//! deep loop goes `file_queued`, then `file_started`, then `file_completed` or
+//! `file_failed`, unless it ends with `file_skipped` or `file_failed` before it
+//! starts.
+
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fs::File;
+use std::io::{self, Write};
+use std::sync::{Mutex, OnceLock};
+
+use crate::llm::Usage;
+
+/// Version of the event format. Adding events or fields keeps it; renaming or
+/// removing them, or changing what they mean, bumps it.
+pub const EVENTS_SCHEMA: u32 = 1;
+
+static SINK: OnceLock<Mutex<Sink>> = OnceLock::new();
+
+struct Sink {
+    writer: Box<dyn Write + Send>,
+    seq: u64,
+}
+
+/// A progress event, serialized with its kind in snake case as `event`
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Event<'a> {
+    RunStarted {
+        command: &'a str,
+        version: &'a str,
+        path: &'a str,
+        output: &'a str,
+    },
+    PhaseStarted {
+        phase: &'a str,
+        message: &'a str,
+    },
+    PhaseCompleted {
+        phase: &'a str,
+        message: &'a str,
+        duration_ms: u64,
+    },
+    /// A file is waiting for a parse slot; `index` counts from 1 and
+    /// includes the files an earlier run finished
+    FileQueued {
+        file: &'a str,
+        index: usize,
+        total: usize,
+    },
+    /// A file got an LLM slot
+    FileStarted {
+        file: &'a str,
+    },
+    /// The LLM analyzed a file; `completeness` is as in `analysis.json`
+    FileCompleted {
+        file: &'a str,
+        duration_ms: u64,
+        completeness: &'a str,
+        #[serde(flatten)]
+        usage: Usage,
+    },
+    /// A file couldn't be read, or got no LLM analysis
+    FileFailed {
+        file: &'a str,
+        duration_ms: u64,
+        reason: &'a str,
+        #[serde(flatten)]
+        usage: Usage,
+    },
+    /// A file left out of the LLM loop: `binary` or `generated`
+    FileSkipped {
+        file: &'a str,
+        reason: &'a str,
+    },
+    Warning {
+        message: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        file: Option<&'a str>,
+    },
+    /// The end of a successful run, with the fields of its summary line
+    Summary {
+        message: &'a str,
+        #[serde(flatten)]
+        fields: &'a Map<String, Value>,
+    },
+    RunFailed {
+        error: &'a str,
+        exit_code: i32,
+    },
+}
+
+/// Write events to `target`, a file created or truncated, or stdout for `-`,
+/// for the rest of the process. Only the first call has an effect.
+pub fn open(target: &str) -> Result<()> {
+    let writer: Box<dyn Write + Send> = if target == "-" {
+        Box::new(io::stdout())
+    } else {
+        Box::new(
+            File::create(target).map_err(|e| anyhow::anyhow!("Cannot create {}: {}", target, e))?,
+        )
+    };
+    let _ = SINK.set(Mutex::new(Sink { writer, seq: 0 }));
+    Ok(())
+}
+
+/// Whether `--progress-json` was given
+pub fn enabled() -> bool {
+    SINK.get().is_some()
+}
+
+/// Write `event` as a line, if events are enabled. Failing to write never
+/// fails the run.
+pub fn emit(event: Event<'_>) {
+    let Some(sink) = SINK.get() else {
+        return;
+    };
+    let mut sink = sink.lock().unwrap_or_else(|e| e.into_inner());
+    let line = envelope(&event, sink.seq);
+    sink.seq += 1;
+    let _ = writeln!(sink.writer, "{}", line).and_then(|()| sink.writer.flush());
+}
+
+fn envelope(event: &Event<'_>, seq: u64) -> Value {
+    let mut object = match serde_json::to_value(event) {
+        Ok(Value::Object(object)) => object,
+        _ => Map::new(),
+    };
+    object.insert("schema".into(), EVENTS_SCHEMA.into());
+    object.insert("seq".into(), seq.into());
+    object.insert(
+        "timestamp".into(),
+        Value::String(crate::logging::timestamp()),
+    );
+    Value::Object(object)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_envelope() {
+        let event = Event::FileCompleted {
+            file: "src/main.rs",
+            duration_ms: 1200,
+            completeness: "full",
+            usage: Usage {
+                calls: 2,
+                input_tokens: Some(900),
+                output_tokens: None,
+            },
+        };
+        let mut line = envelope(&event, 7);
+        assert!(line["timestamp"].is_string());
+        line.as_object_mut().unwrap().remove("timestamp");
+        assert_eq!(
+            line,
+            serde_json::json!({
+                "schema": EVENTS_SCHEMA,
+                "seq": 7,
+                "event": "file_completed",
+                "file": "src/main.rs",
+                "duration_ms": 1200,
+                "completeness": "full",
+                "calls": 2,
+                "input_tokens": 900,
+                "output_tokens": null,
+            })
+        );
+    }
+}
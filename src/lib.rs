@@ -0,0 +1,247 @@
+//! Codebase Deep Analyzer as a library
+//!
+//! The `cda` binary is a thin CLI over this crate. Embedders get the same
+//! pipeline: [`discover`] the files, analyze them with [`analyze_static`]
+//! (no LLM) or [`analyze_deep`], [`cross_reference`] the result, and write it
+//! with [`generate`]. [`Analysis`], [`CrossReference`] and the types inside
+//! them implement `Serialize`/`Deserialize`.
+//!
+//! ```
+//! # #[cfg(not(feature = "analyzer"))]
+//! # fn main() {}
+//! # #[cfg(feature = "analyzer")]
+//! # #[tokio::main]
+//! # async fn main() -> anyhow::Result<()> {
+//! use codebase_deep_analyzer::{DiscoveryOptions, Format};
+//!
+//! # let dir = tempfile::tempdir()?;
+//! # std::fs::write(dir.path().join("lib.rs"), "pub fn parse() {}\n")?;
+//! # let project = dir.path();
+//! # let docs = dir.path().join("docs");
+//! let inventory = codebase_deep_analyzer::discover(project, &DiscoveryOptions::default()).await?;
+//! let analysis = codebase_deep_analyzer::analyze_static(&inventory).await?;
+//! assert_eq!(analysis.modules[0].exports[0].name, "parse");
+//!
+//! let crossref = codebase_deep_analyzer::cross_reference(&analysis).await?;
+//! codebase_deep_analyzer::generate(&analysis, &crossref, &docs, Format::Json)?;
+//! assert!(docs.join("analysis.json").exists());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The modules are public for advanced use, but only the items re-exported
+//! here are covered by semver: enums and result types are `#[non_exhaustive]`
+//! so variants and fields can be added in minor releases.
+//!
+//! Tools that only read `analysis.json` can depend on the [`cda_output`]
+//! types alone, without tree-sitter, reqwest or tokio:
+//!
+//! ```toml
+//! codebase-deep-analyzer = { version = "0.1", default-features = false, features = ["cda-output"] }
+//! ```
+//!
+//! Everything else needs the default `analyzer` feature.
+//!
+//! # Process-wide settings
+//!
+//! [`DeepOptions`] and [`DiscoveryOptions`] are per call, but the settings
+//! the CLI reads from its config are process-wide. Each is set once, before
+//! the first analysis, and the first call wins: a later call is ignored, so
+//! two analyses in one process can't use different values. Without a call,
+//! the CLI's default applies.
+//!
+//! - Page names: [`core::analyzer::set_page_naming`]
+//! - Gap severities and checks: [`core::analyzer::set_gap_severities`],
+//!   [`core::analyzer::set_ignore_type_only_cycles`],
+//!   [`core::doc_checks::set_doc_checks`]
+//! - What the LLM is sent and how long it may take:
+//!   [`core::analyzer::set_deep_mode`], [`core::analyzer::set_file_budget`],
+//!   [`core::analyzer::set_min_response_chars`],
+//!   [`core::backoff::set_backoff`], [`llm::set_context_window`],
+//!   [`llm::set_doc_language`], [`core::templates::set_overrides`]
+//! - Parsing: [`core::parser::set_parse_limits`]
+//! - Raw HTML in LLM answers: [`core::sanitize::set_raw_html`]
+//!
+//! Two are the exception. [`core::redact::set_enabled`] can be changed at
+//! any time, and [`register_plugin`] replaces the plugin for an extension;
+//! both apply to every analysis running in the process. An embedder that
+//! needs different settings per analysis has to run them in separate
+//! processes, e.g. by invoking `cda`.
+
+#[cfg(feature = "cda-output")]
+pub mod cda_output;
+#[cfg(feature = "analyzer")]
+pub mod core;
+#[cfg(feature = "analyzer")]
+#[doc(hidden)]
+pub mod events;
+#[cfg(feature = "analyzer")]
+pub mod llm;
+#[cfg(feature = "analyzer")]
+#[doc(hidden)]
+pub mod logging;
+#[cfg(feature = "analyzer")]
+pub mod output;
+
+#[cfg(feature = "analyzer")]
+use anyhow::Result;
+#[cfg(feature = "analyzer")]
+use std::path::{Path, PathBuf};
+#[cfg(feature = "analyzer")]
+use std::sync::Arc;
+
+#[cfg(feature = "analyzer")]
+pub use crate::core::analyzer::{
+    Analysis, CrossReference, Export, ExportKind, Gap, GapKind, Import, ModuleAnalysis, ResumeMode,
+    SampleInfo, Visibility,
+};
+#[cfg(feature = "analyzer")]
+pub use crate::core::discovery::{DiscoveryOptions, FileInventory, Language, SourceFile};
+#[cfg(feature = "analyzer")]
+pub use crate::core::ownership::{Contributor, Ownership};
+#[cfg(feature = "analyzer")]
+pub use crate::core::plugins::{Plugin, PluginInput};
+#[cfg(feature = "analyzer")]
+pub use crate::core::timings::TimingReport;
+#[cfg(feature = "analyzer")]
+pub use crate::llm::{get_provider, LlmProvider};
+#[cfg(feature = "analyzer")]
+pub use crate::output::Format;
+
+/// Settings for [`analyze_deep`]. The rest are process-wide; see
+/// [Process-wide settings](crate#process-wide-settings).
+#[cfg(feature = "analyzer")]
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct DeepOptions {
+    /// Where module pages and resume progress are written
+    pub output_dir: PathBuf,
+    /// Files read and parsed concurrently; defaults to the number of cores
+    pub parse_jobs: usize,
+    /// LLM requests in flight at once
+    pub llm_concurrency: usize,
+    /// Also document top-level items that are not exported, as
+    /// [`Visibility::Private`]
+    pub include_private: bool,
+    /// Attach up to this many lines of source to each export, shown on the
+    /// module pages
+    pub snippet_lines: Option<usize>,
+    /// Attach up to this many uses of each export from elsewhere in the
+    /// codebase, shown on the module pages
+    pub max_examples: usize,
+    pub resume: ResumeMode,
+    /// When resuming, keep modules analyzed with an older
+    /// [`PROMPT_VERSION`](core::analyzer::PROMPT_VERSION) instead of redoing them
+    pub keep_stale_prompts: bool,
+}
+
+#[cfg(feature = "analyzer")]
+impl DeepOptions {
+    pub fn new(output_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            output_dir: output_dir.into(),
+            parse_jobs: default_parse_jobs(),
+            llm_concurrency: 4,
+            include_private: false,
+            snippet_lines: None,
+            max_examples: 0,
+            resume: ResumeMode::default(),
+            keep_stale_prompts: false,
+        }
+    }
+}
+
+/// Find the source, test, config and doc files under `path`, respecting
+/// `.gitignore` and `.cdaignore`
+#[cfg(feature = "analyzer")]
+pub async fn discover(path: impl AsRef<Path>, options: &DiscoveryOptions) -> Result<FileInventory> {
+    core::discovery::discover(path.as_ref(), None, options).await
+}
+
+/// Parse every source file with tree-sitter, one job per core; no LLM calls.
+/// Only exported items are collected.
+#[cfg(feature = "analyzer")]
+pub async fn analyze_static(inventory: &FileInventory) -> Result<Analysis> {
+    core::analyzer::analyze_static(
+        inventory,
+        default_parse_jobs(),
+        Default::default(),
+        &Default::default(),
+    )
+    .await
+}
+
+#[cfg(feature = "analyzer")]
+fn default_parse_jobs() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Parse files with `extension` (without the dot) by running `plugin`, for
+/// every later [`discover`] and analysis in this process, replacing the
+/// plugin registered for it before
+#[cfg(feature = "analyzer")]
+pub fn register_plugin(extension: &str, plugin: Plugin) {
+    core::plugins::register(extension, plugin)
+}
+
+/// Analyze every source file with the LLM, writing a page per module to
+/// `options.output_dir` as it goes. Interrupted runs resume from there.
+///
+/// ```no_run
+/// # #[tokio::main]
+/// # async fn main() -> anyhow::Result<()> {
+/// use codebase_deep_analyzer::{DeepOptions, DiscoveryOptions};
+///
+/// let inventory = codebase_deep_analyzer::discover(".", &DiscoveryOptions::default()).await?;
+/// let provider = codebase_deep_analyzer::get_provider("ollama", None)?;
+/// let mut options = DeepOptions::new("./docs");
+/// options.llm_concurrency = 8;
+///
+/// let analysis = codebase_deep_analyzer::analyze_deep(&inventory, provider.into(), &options).await?;
+/// for module in &analysis.modules {
+///     println!("{}: {}", module.path, module.summary);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "analyzer")]
+pub async fn analyze_deep(
+    inventory: &FileInventory,
+    provider: Arc<dyn LlmProvider>,
+    options: &DeepOptions,
+) -> Result<Analysis> {
+    core::analyzer::analyze_streaming(
+        inventory,
+        provider,
+        &options.output_dir,
+        options.parse_jobs,
+        options.llm_concurrency,
+        core::analyzer::ParseOptions {
+            include_private: options.include_private,
+            snippet_lines: options.snippet_lines,
+            max_examples: options.max_examples,
+        },
+        options.resume,
+        options.keep_stale_prompts,
+        &Default::default(),
+        None,
+    )
+    .await
+}
+
+/// Dependency graph, external dependencies and documentation gaps
+#[cfg(feature = "analyzer")]
+pub async fn cross_reference(analysis: &Analysis) -> Result<CrossReference> {
+    core::analyzer::cross_reference(analysis).await
+}
+
+/// Write `CODEBASE.md` or `analysis.json` into `output_dir`
+#[cfg(feature = "analyzer")]
+pub fn generate(
+    analysis: &Analysis,
+    crossref: &CrossReference,
+    output_dir: impl AsRef<Path>,
+    format: Format,
+) -> Result<()> {
+    output::generate(analysis, crossref, output_dir.as_ref(), format)
+}
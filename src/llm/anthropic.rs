@@ -4,11 +4,12 @@ use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::env;
 
-use super::{LlmConfig, LlmProvider, Message, Role};
+use super::{LlmConfig, LlmProvider, Message, ModelInfo, Role};
 
 const DEFAULT_MODEL: &str = "claude-sonnet-4-20250514";
 #[allow(dead_code)]
 const API_URL: &str = "https://api.anthropic.com/v1/messages";
+const MODELS_URL: &str = "https://api.anthropic.com/v1/models?limit=1000";
 
 #[allow(dead_code)]
 pub struct AnthropicProvider {
@@ -52,6 +53,14 @@ struct ApiMessage {
 #[derive(Deserialize)]
 struct ApiResponse {
     content: Vec<ContentBlock>,
+    #[serde(default)]
+    usage: Option<ApiUsage>,
+}
+
+#[derive(Deserialize)]
+struct ApiUsage {
+    input_tokens: Option<u64>,
+    output_tokens: Option<u64>,
 }
 
 #[allow(dead_code)]
@@ -60,36 +69,58 @@ struct ContentBlock {
     text: String,
 }
 
+#[derive(Deserialize)]
+struct ModelsResponse {
+    data: Vec<ApiModel>,
+}
+
+#[derive(Deserialize)]
+struct ApiModel {
+    id: String,
+    #[serde(default)]
+    max_input_tokens: Option<u64>,
+}
+
 #[async_trait]
 impl LlmProvider for AnthropicProvider {
     fn name(&self) -> &str {
         "anthropic"
     }
 
-    async fn complete(&self, messages: Vec<Message>, config: LlmConfig) -> Result<String> {
-        let mut system_prompt = None;
-        let mut api_messages = Vec::new();
-
-        for msg in messages {
-            match msg.role {
-                Role::System => {
-                    system_prompt = Some(msg.content);
-                }
-                Role::User => {
-                    api_messages.push(ApiMessage {
-                        role: "user".to_string(),
-                        content: msg.content,
-                    });
-                }
-                Role::Assistant => {
-                    api_messages.push(ApiMessage {
-                        role: "assistant".to_string(),
-                        content: msg.content,
-                    });
-                }
-            }
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        let response = self
+            .client
+            .get(MODELS_URL)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", "2023-06-01")
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await?;
+            anyhow::bail!("Anthropic API error {}: {}", status, body);
         }
 
+        let models: ModelsResponse = response.json().await?;
+
+        Ok(models
+            .data
+            .into_iter()
+            .map(|m| ModelInfo {
+                id: m.id,
+                context_window: m.max_input_tokens,
+            })
+            .collect())
+    }
+
+    async fn complete(&self, messages: Vec<Message>, config: LlmConfig) -> Result<String> {
+        let (system_prompt, api_messages) = split_system(messages);
+
         let request = ApiRequest {
             model: self.model.clone(),
             max_tokens: config.max_tokens,
@@ -115,6 +146,11 @@ impl LlmProvider for AnthropicProvider {
         }
 
         let api_response: ApiResponse = response.json().await?;
+        let usage = api_response.usage.as_ref();
+        super::record_usage(
+            usage.and_then(|u| u.input_tokens),
+            usage.and_then(|u| u.output_tokens),
+        );
 
         Ok(api_response
             .content
@@ -124,3 +160,52 @@ impl LlmProvider for AnthropicProvider {
             .join(""))
     }
 }
+
+/// The API takes system prompts in a separate `system` field rather than as
+/// messages; several are joined with blank lines, in order
+fn split_system(messages: Vec<Message>) -> (Option<String>, Vec<ApiMessage>) {
+    let mut system: Vec<String> = Vec::new();
+    let mut api_messages = Vec::new();
+
+    for msg in messages {
+        match msg.role {
+            Role::System => system.push(msg.content),
+            Role::User | Role::Assistant => api_messages.push(ApiMessage {
+                role: msg.role.name().to_string(),
+                content: msg.content,
+            }),
+        }
+    }
+
+    let system = (!system.is_empty()).then(|| system.join("\n\n"));
+    (system, api_messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_system_messages_go_to_the_system_field() {
+        let message = |role, content: &str| Message {
+            role,
+            content: content.to_string(),
+        };
+        let (system, messages) = split_system(vec![
+            message(Role::System, "You are terse."),
+            message(Role::User, "Analyze this"),
+            message(Role::System, "Answer in markdown."),
+            message(Role::Assistant, "Sure"),
+        ]);
+
+        assert_eq!(
+            system.as_deref(),
+            Some("You are terse.\n\nAnswer in markdown.")
+        );
+        let roles: Vec<_> = messages.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(roles, vec!["user", "assistant"]);
+
+        let (system, _) = split_system(vec![message(Role::User, "Hi")]);
+        assert!(system.is_none());
+    }
+}
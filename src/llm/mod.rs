@@ -4,6 +4,14 @@ mod openai;
 
 use anyhow::Result;
 pub use async_trait::async_trait;
+use serde::Serialize;
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::env;
+use std::future::Future;
+use std::sync::OnceLock;
+
+use crate::core::exit::ConfigError;
 
 pub use anthropic::AnthropicProvider;
 pub use ollama::OllamaProvider;
@@ -17,7 +25,7 @@ pub struct Message {
     pub content: String,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[allow(dead_code)]
 pub enum Role {
     System,
@@ -25,6 +33,17 @@ pub enum Role {
     Assistant,
 }
 
+impl Role {
+    /// The role as the chat APIs spell it
+    pub fn name(self) -> &'static str {
+        match self {
+            Role::System => "system",
+            Role::User => "user",
+            Role::Assistant => "assistant",
+        }
+    }
+}
+
 /// Configuration for LLM request
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
@@ -42,6 +61,95 @@ impl Default for LlmConfig {
     }
 }
 
+/// Context window assumed for models whose size isn't known, in tokens
+pub const DEFAULT_CONTEXT_WINDOW: usize = 8192;
+
+/// Context windows of well-known models in tokens, by model name prefix.
+/// The longest matching prefix wins.
+const CONTEXT_WINDOWS: &[(&str, usize)] = &[
+    ("claude-", 200_000),
+    ("gpt-4.1", 1_047_576),
+    ("gpt-4o", 128_000),
+    ("gpt-4-turbo", 128_000),
+    ("gpt-4", 8192),
+    ("gpt-3.5-turbo", 16_385),
+    ("o1", 200_000),
+    ("o3", 200_000),
+    ("o4", 200_000),
+    ("llama3.1", 131_072),
+    ("llama3.2", 131_072),
+    ("llama3", 8192),
+    ("codellama", 16_384),
+    ("qwen2.5-coder", 32_768),
+    ("mistral", 32_768),
+    ("deepseek-coder", 16_384),
+];
+
+/// Context window set by `llm.context_window`, replacing the table's
+static CONTEXT_WINDOW: OnceLock<usize> = OnceLock::new();
+
+/// Use `tokens` as the context window of the configured model, for the rest
+/// of the process. Only the first call has an effect.
+pub fn set_context_window(tokens: usize) {
+    let _ = CONTEXT_WINDOW.set(tokens);
+}
+
+/// Context window of `model` in tokens: `llm.context_window` when set,
+/// else the table's entry for it, else [`DEFAULT_CONTEXT_WINDOW`]
+pub fn context_window_for(model: &str) -> usize {
+    if let Some(&tokens) = CONTEXT_WINDOW.get() {
+        return tokens;
+    }
+    CONTEXT_WINDOWS
+        .iter()
+        .filter(|(prefix, _)| model.starts_with(prefix))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map_or(DEFAULT_CONTEXT_WINDOW, |(_, tokens)| *tokens)
+}
+
+/// Language of generated prose set by `output.language`
+static DOC_LANGUAGE: OnceLock<String> = OnceLock::new();
+
+/// Have the LLM write its prose in `language` for the rest of the process.
+/// Only the first call has an effect.
+pub fn set_doc_language(language: &str) {
+    let _ = DOC_LANGUAGE.set(language.to_string());
+}
+
+/// The configured language of generated prose; `None` leaves it to the
+/// prompts, which are in English
+pub fn doc_language() -> Option<&'static str> {
+    DOC_LANGUAGE.get().map(String::as_str)
+}
+
+/// `system_prompt` with the instruction to write in the configured
+/// language, if there is one
+pub fn localize(system_prompt: &str) -> Cow<'_, str> {
+    with_language(system_prompt, doc_language())
+}
+
+fn with_language<'a>(system_prompt: &'a str, language: Option<&str>) -> Cow<'a, str> {
+    match language {
+        // Headings stay as the prompt writes them, so a template translated
+        // with `--templates` gives localized headings and the default one
+        // keeps the English ones
+        Some(language) => Cow::Owned(format!(
+            "{}\n\nRespond in {}. Keep headings and labels exactly as written above, and \
+             code, identifiers, file paths, commands and JSON keys as they are.",
+            system_prompt, language
+        )),
+        None => Cow::Borrowed(system_prompt),
+    }
+}
+
+/// A model reported by a provider's listing endpoint
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelInfo {
+    pub id: String,
+    /// Context window in tokens, when the API reports it
+    pub context_window: Option<u64>,
+}
+
 /// Trait for LLM providers
 #[async_trait]
 #[allow(dead_code)]
@@ -49,21 +157,337 @@ pub trait LlmProvider: Send + Sync {
     /// Get the provider name
     fn name(&self) -> &str;
 
+    /// Model used for completions
+    fn model(&self) -> &str;
+
+    /// Context window of the configured model in tokens, for budgeting
+    /// prompts; see [`context_window_for`]
+    fn context_window(&self) -> usize {
+        context_window_for(self.model())
+    }
+
+    /// Models available to the configured credentials
+    async fn list_models(&self) -> Result<Vec<ModelInfo>>;
+
     /// Send a message and get a response
     async fn complete(&self, messages: Vec<Message>, config: LlmConfig) -> Result<String>;
+
+    /// Whether `analyze` should run [`LlmProvider::preflight`] before the
+    /// per-file loop without being asked to
+    fn wants_preflight(&self) -> bool {
+        false
+    }
+
+    /// Cheap check that a long run can start: a one-token completion with the
+    /// configured model. Providers override it to turn their failures into
+    /// actionable errors.
+    async fn preflight(&self) -> Result<()> {
+        let messages = vec![Message {
+            role: Role::User,
+            content: PREFLIGHT_PROMPT.to_string(),
+        }];
+        let config = LlmConfig {
+            max_tokens: 1,
+            temperature: 0.0,
+        };
+        self.complete(messages, config).await.map(drop)
+    }
 }
 
-/// Get an LLM provider by name
-pub fn get_provider(name: &str, model: Option<&str>) -> Result<Box<dyn LlmProvider>> {
+/// Tokens and requests spent on LLM calls, as the providers report them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Usage {
+    /// Requests sent, failed ones included
+    pub calls: usize,
+    /// Prompt tokens; `None` when no response reported them
+    pub input_tokens: Option<u64>,
+    /// Response tokens; `None` when no response reported them
+    pub output_tokens: Option<u64>,
+}
+
+tokio::task_local! {
+    static USAGE: Cell<Usage>;
+}
+
+/// Run `future` and add up the requests [`record_call`] counts and the tokens
+/// providers [`record_usage`] while it runs, such as all the calls for one file
+pub async fn track_usage<F: Future>(future: F) -> (F::Output, Usage) {
+    USAGE
+        .scope(Cell::new(Usage::default()), async {
+            let output = future.await;
+            (output, USAGE.with(Cell::get))
+        })
+        .await
+}
+
+/// Count a request toward the enclosing [`track_usage`], if any, before it is
+/// sent
+pub fn record_call() {
+    let _ = USAGE.try_with(|usage| {
+        let total = usage.get();
+        usage.set(Usage {
+            calls: total.calls + 1,
+            ..total
+        });
+    });
+}
+
+/// Add the tokens a response reported to the enclosing [`track_usage`], if
+/// any
+pub fn record_usage(input_tokens: Option<u64>, output_tokens: Option<u64>) {
+    let add = |total: Option<u64>, tokens: Option<u64>| match (total, tokens) {
+        (Some(total), Some(tokens)) => Some(total + tokens),
+        (total, tokens) => total.or(tokens),
+    };
+    let _ = USAGE.try_with(|usage| {
+        let total = usage.get();
+        usage.set(Usage {
+            input_tokens: add(total.input_tokens, input_tokens),
+            output_tokens: add(total.output_tokens, output_tokens),
+            ..total
+        });
+    });
+}
+
+/// Prompt of the [`LlmProvider::preflight`] request
+pub const PREFLIGHT_PROMPT: &str = "Reply with OK.";
+
+/// Provider names accepted by `get_provider`, without aliases
+pub const PROVIDERS: &[&str] = &["anthropic", "openai", "ollama"];
+
+/// Resolve a provider name or alias to its entry in [`PROVIDERS`]
+pub fn canonical_provider(name: &str) -> Option<&'static str> {
     match name.to_lowercase().as_str() {
-        "anthropic" | "claude" => Ok(Box::new(AnthropicProvider::new(model)?)),
-        "openai" | "gpt" => Ok(Box::new(OpenAiProvider::new(model)?)),
-        "ollama" | "local" => Ok(Box::new(OllamaProvider::new(model)?)),
-        _ => {
-            anyhow::bail!(
-                "Unknown LLM provider: {}. Supported: anthropic, openai, ollama",
-                name
-            )
+        "anthropic" | "claude" => Some("anthropic"),
+        "openai" | "gpt" => Some("openai"),
+        "ollama" | "local" => Some("ollama"),
+        _ => None,
+    }
+}
+
+/// Well-known models for a provider, used for shell completion
+pub fn known_models(provider: &str) -> &'static [&'static str] {
+    match provider.to_lowercase().as_str() {
+        "anthropic" | "claude" => &["claude-sonnet-4-20250514", "claude-opus-4-20250514"],
+        "openai" | "gpt" => &["gpt-4o", "gpt-4o-mini", "gpt-4-turbo"],
+        "ollama" | "local" => &["llama3", "codellama", "qwen2.5-coder"],
+        _ => &[],
+    }
+}
+
+/// How to supply credentials (or a server) for a provider
+pub fn credential_hint(provider: &str) -> &'static str {
+    match canonical_provider(provider) {
+        Some("anthropic") => {
+            "Set ANTHROPIC_API_KEY in your shell profile, e.g. export ANTHROPIC_API_KEY=sk-ant-..."
         }
+        Some("openai") => {
+            "Set OPENAI_API_KEY in your shell profile, e.g. export OPENAI_API_KEY=sk-..."
+        }
+        _ => "Start Ollama (`ollama serve`) or point OLLAMA_URL at a running server",
+    }
+}
+
+/// Environment variable holding a provider's API key; `None` for providers
+/// without one
+pub fn api_key_var(provider: &str) -> Option<&'static str> {
+    match canonical_provider(provider) {
+        Some("anthropic") => Some("ANTHROPIC_API_KEY"),
+        Some("openai") => Some("OPENAI_API_KEY"),
+        _ => None,
+    }
+}
+
+/// Fail with how to set them when the provider's credentials are missing, so
+/// a run that needs the LLM stops before discovery instead of failing file by
+/// file. Only the environment is checked; [`preflight`] proves they work.
+pub fn check_credentials(provider: &str) -> Result<()> {
+    let Some(name) = canonical_provider(provider) else {
+        return get_provider(provider, None).map(drop);
+    };
+    let Some(var) = api_key_var(name) else {
+        return Ok(());
+    };
+    if env::var(var).is_ok_and(|key| !key.trim().is_empty()) {
+        return Ok(());
+    }
+    Err(ConfigError::wrap(anyhow::anyhow!(
+        "{} not set, and --provider {} needs it for the LLM analysis. {}",
+        var,
+        name,
+        credential_hint(name)
+    )))
+}
+
+/// Get an LLM provider by name. Unknown names and missing credentials are
+/// [`ConfigError`]s.
+pub fn get_provider(name: &str, model: Option<&str>) -> Result<Box<dyn LlmProvider>> {
+    let provider: Result<Box<dyn LlmProvider>> = match name.to_lowercase().as_str() {
+        "anthropic" | "claude" => AnthropicProvider::new(model).map(|p| Box::new(p) as _),
+        "openai" | "gpt" => OpenAiProvider::new(model).map(|p| Box::new(p) as _),
+        "ollama" | "local" => OllamaProvider::new(model).map(|p| Box::new(p) as _),
+        _ => Err(anyhow::anyhow!(
+            "Unknown LLM provider: {}. Supported: anthropic, openai, ollama",
+            name
+        )),
+    };
+    provider.map_err(ConfigError::wrap)
+}
+
+/// Whether a configured model name matches a listed model. Ollama lists
+/// models with a tag, and `llama3` refers to `llama3:latest`.
+pub fn model_matches(configured: &str, listed: &str) -> bool {
+    configured == listed || format!("{}:latest", configured) == listed
+}
+
+/// Fail with a clear error when the provider does not offer its configured model
+pub async fn check_model(provider: &dyn LlmProvider) -> Result<()> {
+    let models = provider.list_models().await.map_err(|e| {
+        anyhow::anyhow!(
+            "Could not list {} models to check --model: {}",
+            provider.name(),
+            e
+        )
+    })?;
+
+    if models
+        .iter()
+        .any(|m| model_matches(provider.model(), &m.id))
+    {
+        return Ok(());
+    }
+
+    let suggestion = models
+        .iter()
+        .map(|m| (m.id.as_str(), strsim::levenshtein(provider.model(), &m.id)))
+        .min_by_key(|(_, distance)| *distance)
+        .filter(|(_, distance)| *distance <= 4)
+        .map(|(id, _)| format!(". Did you mean `{}`?", id))
+        .unwrap_or_default();
+
+    Err(ConfigError::wrap(anyhow::anyhow!(
+        "Model `{}` is not available from {}{} Run `cda models --provider {}` to list models",
+        provider.model(),
+        provider.name(),
+        if suggestion.is_empty() {
+            ".".to_string()
+        } else {
+            suggestion
+        },
+        provider.name()
+    )))
+}
+
+/// Run the provider's [`LlmProvider::preflight`], so a missing model or
+/// rejected credentials fail the run before any file is analyzed
+pub async fn preflight(provider: &dyn LlmProvider) -> Result<()> {
+    provider.preflight().await.map_err(|e| {
+        ConfigError::wrap(anyhow::anyhow!(
+            "Pre-flight request to {} with model `{}` failed: {:#} (--skip-preflight skips this check)",
+            provider.name(),
+            provider.model(),
+            e
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_matches_ollama_tags() {
+        assert!(model_matches("gpt-4o", "gpt-4o"));
+        assert!(model_matches("llama3", "llama3:latest"));
+        assert!(!model_matches("llama3", "llama3:70b"));
+        assert!(!model_matches("gpt-4", "gpt-4o"));
+    }
+
+    #[test]
+    fn test_context_window_for() {
+        assert_eq!(context_window_for("claude-sonnet-4-20250514"), 200_000);
+        assert_eq!(context_window_for("gpt-4o-mini"), 128_000);
+        assert_eq!(context_window_for("gpt-4"), 8192);
+        assert_eq!(context_window_for("llama3.1:70b"), 131_072);
+        assert_eq!(context_window_for("llama3"), 8192);
+        assert_eq!(context_window_for("my-finetune"), DEFAULT_CONTEXT_WINDOW);
+    }
+
+    #[test]
+    fn test_with_language() {
+        assert_eq!(with_language("Be brief.", None), "Be brief.");
+        let localized = with_language("Be brief.", Some("Japanese"));
+        assert!(localized.starts_with("Be brief.\n\nRespond in Japanese."));
+        assert!(localized.contains("Keep headings and labels exactly as written above"));
+    }
+
+    struct Listed(&'static str);
+
+    #[async_trait]
+    impl LlmProvider for Listed {
+        fn name(&self) -> &str {
+            "test"
+        }
+
+        fn model(&self) -> &str {
+            self.0
+        }
+
+        async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+            Ok(["gpt-4o", "gpt-4o-mini"]
+                .iter()
+                .map(|id| ModelInfo {
+                    id: id.to_string(),
+                    context_window: None,
+                })
+                .collect())
+        }
+
+        async fn complete(&self, _messages: Vec<Message>, _config: LlmConfig) -> Result<String> {
+            unreachable!()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_model() {
+        assert!(check_model(&Listed("gpt-4o")).await.is_ok());
+        let err = check_model(&Listed("gpt4o")).await.unwrap_err().to_string();
+        assert!(err.contains("Did you mean `gpt-4o`?"), "{}", err);
+    }
+
+    struct Refusing;
+
+    #[async_trait]
+    impl LlmProvider for Refusing {
+        fn name(&self) -> &str {
+            "test"
+        }
+
+        fn model(&self) -> &str {
+            "gpt-4o"
+        }
+
+        async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+            unreachable!()
+        }
+
+        async fn complete(&self, messages: Vec<Message>, config: LlmConfig) -> Result<String> {
+            assert_eq!(messages[0].content, PREFLIGHT_PROMPT);
+            assert_eq!(config.max_tokens, 1);
+            anyhow::bail!("invalid x-api-key")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_preflight() {
+        assert!(!Refusing.wants_preflight());
+        let err = preflight(&Refusing).await.unwrap_err();
+        assert_eq!(
+            crate::core::exit::code(&err),
+            crate::core::exit::EXIT_CONFIG
+        );
+        let err = err.to_string();
+        assert!(err.contains("invalid x-api-key"), "{}", err);
+        assert!(err.contains("--skip-preflight"), "{}", err);
     }
 }
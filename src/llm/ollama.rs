@@ -1,10 +1,10 @@
 use anyhow::Result;
 use async_trait::async_trait;
-use reqwest::Client;
+use reqwest::{Client, StatusCode};
 use serde::{Deserialize, Serialize};
 use std::env;
 
-use super::{LlmConfig, LlmProvider, Message, Role};
+use super::{LlmConfig, LlmProvider, Message, ModelInfo};
 
 #[allow(dead_code)]
 const DEFAULT_MODEL: &str = "llama3";
@@ -44,6 +44,9 @@ struct ApiRequest {
 struct Options {
     num_predict: usize,
     temperature: f32,
+    /// Context size the server loads the model with; its own default is
+    /// smaller than most prompts are budgeted for
+    num_ctx: usize,
 }
 
 #[allow(dead_code)]
@@ -57,6 +60,10 @@ struct ApiMessage {
 #[derive(Deserialize)]
 struct ApiResponse {
     message: ResponseMessage,
+    #[serde(default)]
+    prompt_eval_count: Option<u64>,
+    #[serde(default)]
+    eval_count: Option<u64>,
 }
 
 #[allow(dead_code)]
@@ -65,24 +72,145 @@ struct ResponseMessage {
     content: String,
 }
 
+#[derive(Deserialize)]
+struct TagsResponse {
+    models: Vec<Tag>,
+}
+
+#[derive(Deserialize)]
+struct Tag {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct ShowResponse {
+    #[serde(default)]
+    model_info: serde_json::Map<String, serde_json::Value>,
+}
+
+impl OllamaProvider {
+    /// Context length from `/api/show`, stored under `<architecture>.context_length`
+    async fn show_context_window(&self, model: &str) -> Option<u64> {
+        let url = format!("{}/api/show", self.base_url);
+        let response = self
+            .client
+            .post(&url)
+            .json(&serde_json::json!({ "model": model }))
+            .send()
+            .await
+            .ok()?;
+        let show: ShowResponse = response.error_for_status().ok()?.json().await.ok()?;
+
+        show.model_info
+            .iter()
+            .find(|(key, _)| key.ends_with(".context_length"))
+            .and_then(|(_, value)| value.as_u64())
+    }
+}
+
+/// Turn a failed pre-flight request into an error that says what to do
+fn preflight_error(model: &str, num_ctx: usize, status: StatusCode, body: &str) -> anyhow::Error {
+    let message = serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|v| v.get("error")?.as_str().map(String::from))
+        .unwrap_or_else(|| body.trim().to_string());
+    if status == StatusCode::NOT_FOUND || message.contains("not found") {
+        anyhow::anyhow!("model {} not found — run `ollama pull {}`", model, model)
+    } else {
+        anyhow::anyhow!(
+            "server refused num_ctx={} ({}: {}). Set llm.context_window lower, \
+             or give the server more memory",
+            num_ctx,
+            status,
+            message
+        )
+    }
+}
+
 #[async_trait]
 impl LlmProvider for OllamaProvider {
     fn name(&self) -> &str {
         "ollama"
     }
 
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        let url = format!("{}/api/tags", self.base_url);
+        let response = self.client.get(&url).send().await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await?;
+            anyhow::bail!("Ollama API error {}: {}", status, body);
+        }
+
+        let tags: TagsResponse = response.json().await?;
+
+        let mut models = Vec::new();
+        for tag in tags.models {
+            models.push(ModelInfo {
+                context_window: self.show_context_window(&tag.name).await,
+                id: tag.name,
+            });
+        }
+        Ok(models)
+    }
+
+    fn wants_preflight(&self) -> bool {
+        true
+    }
+
+    /// One-token chat with the configured model and `num_ctx`, which makes
+    /// the server pull the model into memory at the size the run needs
+    async fn preflight(&self) -> Result<()> {
+        let request = ApiRequest {
+            model: self.model.clone(),
+            messages: vec![ApiMessage {
+                role: "user".to_string(),
+                content: super::PREFLIGHT_PROMPT.to_string(),
+            }],
+            stream: false,
+            options: Options {
+                num_predict: 1,
+                temperature: 0.0,
+                num_ctx: self.context_window(),
+            },
+        };
+
+        let url = format!("{}/api/chat", self.base_url);
+        let response = self
+            .client
+            .post(&url)
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| {
+                anyhow::anyhow!(
+                    "could not reach Ollama at {}: {}. {}",
+                    self.base_url,
+                    e,
+                    super::credential_hint("ollama")
+                )
+            })?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await?;
+            return Err(preflight_error(
+                &self.model,
+                self.context_window(),
+                status,
+                &body,
+            ));
+        }
+        Ok(())
+    }
+
     async fn complete(&self, messages: Vec<Message>, config: LlmConfig) -> Result<String> {
-        let api_messages: Vec<ApiMessage> = messages
-            .into_iter()
-            .map(|msg| ApiMessage {
-                role: match msg.role {
-                    Role::System => "system".to_string(),
-                    Role::User => "user".to_string(),
-                    Role::Assistant => "assistant".to_string(),
-                },
-                content: msg.content,
-            })
-            .collect();
+        let api_messages = api_messages(messages);
 
         let request = ApiRequest {
             model: self.model.clone(),
@@ -91,6 +219,7 @@ impl LlmProvider for OllamaProvider {
             options: Options {
                 num_predict: config.max_tokens,
                 temperature: config.temperature,
+                num_ctx: self.context_window(),
             },
         };
 
@@ -105,7 +234,71 @@ impl LlmProvider for OllamaProvider {
         }
 
         let api_response: ApiResponse = response.json().await?;
+        super::record_usage(api_response.prompt_eval_count, api_response.eval_count);
 
         Ok(api_response.message.content)
     }
 }
+
+/// System prompts stay messages of their own, with the `system` role
+fn api_messages(messages: Vec<Message>) -> Vec<ApiMessage> {
+    messages
+        .into_iter()
+        .map(|msg| ApiMessage {
+            role: msg.role.name().to_string(),
+            content: msg.content,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::Role;
+
+    #[test]
+    fn test_roles_are_kept() {
+        let messages = api_messages(vec![
+            Message {
+                role: Role::System,
+                content: "You are terse.".into(),
+            },
+            Message {
+                role: Role::User,
+                content: "Analyze this".into(),
+            },
+        ]);
+        let roles: Vec<_> = messages
+            .iter()
+            .map(|m| (m.role.as_str(), m.content.as_str()))
+            .collect();
+        assert_eq!(
+            roles,
+            vec![("system", "You are terse."), ("user", "Analyze this")]
+        );
+    }
+
+    #[test]
+    fn test_preflight_errors() {
+        let err = preflight_error(
+            "llama3",
+            8192,
+            StatusCode::NOT_FOUND,
+            r#"{"error":"model \"llama3\" not found, try pulling it first"}"#,
+        );
+        assert_eq!(
+            err.to_string(),
+            "model llama3 not found — run `ollama pull llama3`"
+        );
+
+        let err = preflight_error(
+            "llama3",
+            16384,
+            StatusCode::INTERNAL_SERVER_ERROR,
+            r#"{"error":"model requires more system memory (12.0 GiB) than is available (7.6 GiB)"}"#,
+        )
+        .to_string();
+        assert!(err.starts_with("server refused num_ctx=16384 ("), "{}", err);
+        assert!(err.contains("more system memory"), "{}", err);
+    }
+}
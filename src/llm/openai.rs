@@ -4,17 +4,18 @@ use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::env;
 
-use super::{LlmConfig, LlmProvider, Message, Role};
+use super::{LlmConfig, LlmProvider, Message, ModelInfo};
 
 #[allow(dead_code)]
 const DEFAULT_MODEL: &str = "gpt-4o";
-#[allow(dead_code)]
-const API_URL: &str = "https://api.openai.com/v1/chat/completions";
+/// Overridden by `OPENAI_BASE_URL`, for proxies and compatible servers
+const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
 
 #[allow(dead_code)]
 pub struct OpenAiProvider {
     client: Client,
     api_key: String,
+    base_url: String,
     model: String,
 }
 
@@ -22,10 +23,14 @@ impl OpenAiProvider {
     pub fn new(model: Option<&str>) -> Result<Self> {
         let api_key =
             env::var("OPENAI_API_KEY").map_err(|_| anyhow::anyhow!("OPENAI_API_KEY not set"))?;
+        let base_url = env::var("OPENAI_BASE_URL")
+            .map(|url| url.trim_end_matches('/').to_string())
+            .unwrap_or_else(|_| DEFAULT_BASE_URL.to_string());
 
         Ok(Self {
             client: Client::new(),
             api_key,
+            base_url,
             model: model.unwrap_or(DEFAULT_MODEL).to_string(),
         })
     }
@@ -51,6 +56,14 @@ struct ApiMessage {
 #[derive(Deserialize)]
 struct ApiResponse {
     choices: Vec<Choice>,
+    #[serde(default)]
+    usage: Option<ApiUsage>,
+}
+
+#[derive(Deserialize)]
+struct ApiUsage {
+    prompt_tokens: Option<u64>,
+    completion_tokens: Option<u64>,
 }
 
 #[allow(dead_code)]
@@ -65,24 +78,55 @@ struct ChoiceMessage {
     content: String,
 }
 
+#[derive(Deserialize)]
+struct ModelsResponse {
+    data: Vec<ApiModel>,
+}
+
+#[derive(Deserialize)]
+struct ApiModel {
+    id: String,
+}
+
 #[async_trait]
 impl LlmProvider for OpenAiProvider {
     fn name(&self) -> &str {
         "openai"
     }
 
-    async fn complete(&self, messages: Vec<Message>, config: LlmConfig) -> Result<String> {
-        let api_messages: Vec<ApiMessage> = messages
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        let response = self
+            .client
+            .get(format!("{}/models", self.base_url))
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await?;
+            anyhow::bail!("OpenAI API error {}: {}", status, body);
+        }
+
+        let models: ModelsResponse = response.json().await?;
+
+        // The listing endpoint does not report context windows
+        Ok(models
+            .data
             .into_iter()
-            .map(|msg| ApiMessage {
-                role: match msg.role {
-                    Role::System => "system".to_string(),
-                    Role::User => "user".to_string(),
-                    Role::Assistant => "assistant".to_string(),
-                },
-                content: msg.content,
+            .map(|m| ModelInfo {
+                id: m.id,
+                context_window: None,
             })
-            .collect();
+            .collect())
+    }
+
+    async fn complete(&self, messages: Vec<Message>, config: LlmConfig) -> Result<String> {
+        let api_messages = api_messages(messages);
 
         let request = ApiRequest {
             model: self.model.clone(),
@@ -93,7 +137,7 @@ impl LlmProvider for OpenAiProvider {
 
         let response = self
             .client
-            .post(API_URL)
+            .post(format!("{}/chat/completions", self.base_url))
             .header("Authorization", format!("Bearer {}", self.api_key))
             .header("Content-Type", "application/json")
             .json(&request)
@@ -107,6 +151,11 @@ impl LlmProvider for OpenAiProvider {
         }
 
         let api_response: ApiResponse = response.json().await?;
+        let usage = api_response.usage.as_ref();
+        super::record_usage(
+            usage.and_then(|u| u.prompt_tokens),
+            usage.and_then(|u| u.completion_tokens),
+        );
 
         api_response
             .choices
@@ -115,3 +164,42 @@ impl LlmProvider for OpenAiProvider {
             .ok_or_else(|| anyhow::anyhow!("No response from OpenAI"))
     }
 }
+
+/// System prompts stay messages of their own, with the `system` role
+fn api_messages(messages: Vec<Message>) -> Vec<ApiMessage> {
+    messages
+        .into_iter()
+        .map(|msg| ApiMessage {
+            role: msg.role.name().to_string(),
+            content: msg.content,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::Role;
+
+    #[test]
+    fn test_roles_are_kept() {
+        let messages = api_messages(vec![
+            Message {
+                role: Role::System,
+                content: "You are terse.".into(),
+            },
+            Message {
+                role: Role::User,
+                content: "Analyze this".into(),
+            },
+        ]);
+        let roles: Vec<_> = messages
+            .iter()
+            .map(|m| (m.role.as_str(), m.content.as_str()))
+            .collect();
+        assert_eq!(
+            roles,
+            vec![("system", "You are terse."), ("user", "Analyze this")]
+        );
+    }
+}
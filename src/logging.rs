@@ -0,0 +1,547 @@
+//! Logging and progress display
+//!
+//! Logs go to stderr so stdout stays clean for command output. `--log-format
+//! json` writes one JSON object per event; spinners are only drawn on an
+//! interactive stderr with text logs and without `--quiet`. All bars share one
+//! [`MultiProgress`], and text log lines are printed through it so they don't
+//! tear the bars.
+//!
+//! Colors follow `--color`: `auto` (the default) colors only an interactive
+//! stderr and honors `NO_COLOR`, `CLICOLOR=0` and `CLICOLOR_FORCE`.
+
+use clap::ValueEnum;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use serde_json::{Map, Value};
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::field::{Field, Visit};
+use tracing::{info, Event, Subscriber};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::time::{FormatTime, SystemTime};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+
+use crate::events::{self, Event as ProgressEvent};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// When to color log lines, spinners and other styled output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Color when stderr is a terminal and the environment doesn't say otherwise
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// Whether to use colors, given `--color`, whether stderr is a terminal and
+/// a lookup for environment variables. An explicit `always` or `never` wins;
+/// under `auto`, a non-empty `NO_COLOR` turns colors off, then a non-zero
+/// `CLICOLOR_FORCE` turns them on, then `CLICOLOR=0` turns them off.
+pub fn resolve_color(
+    choice: ColorChoice,
+    is_tty: bool,
+    env: impl Fn(&str) -> Option<String>,
+) -> bool {
+    let set = |name| env(name).filter(|v| !v.is_empty());
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto if set("NO_COLOR").is_some() => false,
+        ColorChoice::Auto if set("CLICOLOR_FORCE").is_some_and(|v| v != "0") => true,
+        ColorChoice::Auto if set("CLICOLOR").as_deref() == Some("0") => false,
+        ColorChoice::Auto => is_tty,
+    }
+}
+
+static SPINNERS: AtomicBool = AtomicBool::new(false);
+static COLOR: AtomicBool = AtomicBool::new(false);
+static WARNINGS: AtomicUsize = AtomicUsize::new(0);
+static FORMAT: OnceLock<LogFormat> = OnceLock::new();
+static MULTI: OnceLock<MultiProgress> = OnceLock::new();
+
+/// Number of recent files the ETA is averaged over
+const LATENCY_WINDOW: usize = 20;
+/// How often plain-text progress lines are logged without a terminal
+const REPORT_INTERVAL: Duration = Duration::from_secs(10);
+
+fn multi() -> &'static MultiProgress {
+    MULTI.get_or_init(MultiProgress::new)
+}
+
+/// Writes to stderr with the progress bars cleared for the duration
+struct SuspendingStderr;
+
+impl Write for SuspendingStderr {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        multi().suspend(|| io::stderr().write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
+
+pub fn init(verbose: bool, quiet: bool, format: LogFormat, color: ColorChoice) {
+    let color = resolve_color(color, io::stderr().is_terminal(), |name| {
+        std::env::var(name).ok()
+    });
+    COLOR.store(color, Ordering::Relaxed);
+    // indicatif styles bars through `console`, which otherwise decides per stream
+    console::set_colors_enabled(color);
+    console::set_colors_enabled_stderr(color);
+
+    let level = if verbose {
+        "debug"
+    } else if quiet {
+        "warn"
+    } else {
+        "info"
+    };
+    let filter =
+        tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| level.into());
+
+    // Warnings are counted even when RUST_LOG or --quiet hide them
+    let registry =
+        tracing_subscriber::registry().with(WarningCounter.with_filter(LevelFilter::WARN));
+    match format {
+        LogFormat::Text => registry
+            .with(
+                tracing_subscriber::fmt::layer()
+                    .with_target(false)
+                    .with_ansi(color)
+                    .with_writer(|| SuspendingStderr)
+                    .with_filter(filter),
+            )
+            .init(),
+        LogFormat::Json => registry.with(JsonLayer.with_filter(filter)).init(),
+    }
+
+    let _ = FORMAT.set(format);
+    SPINNERS.store(
+        !quiet && format == LogFormat::Text && std::io::stderr().is_terminal(),
+        Ordering::Relaxed,
+    );
+}
+
+pub fn spinners_enabled() -> bool {
+    SPINNERS.load(Ordering::Relaxed)
+}
+
+/// Warnings logged so far, for the "completed with warnings" exit code
+pub fn warnings() -> usize {
+    WARNINGS.load(Ordering::Relaxed)
+}
+
+/// Counts the events let through its filter, which is WARN and above, and
+/// passes them on as progress events
+struct WarningCounter;
+
+impl<S: Subscriber> Layer<S> for WarningCounter {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        WARNINGS.fetch_add(1, Ordering::Relaxed);
+        if events::enabled() {
+            let mut fields = JsonVisitor(Map::new());
+            event.record(&mut fields);
+            events::emit(ProgressEvent::Warning {
+                message: fields
+                    .0
+                    .get("message")
+                    .and_then(Value::as_str)
+                    .unwrap_or(""),
+                file: fields.0.get("file").and_then(Value::as_str),
+            });
+        }
+    }
+}
+
+/// Whether styled console output (log levels, bars, tables) may use colors
+pub fn colors_enabled() -> bool {
+    COLOR.load(Ordering::Relaxed)
+}
+
+/// Print the end-of-run summary. Shown even with `--quiet`, since it's the
+/// line CI logs care about.
+pub fn summary(message: &str, fields: Value) {
+    if let Value::Object(fields) = &fields {
+        events::emit(ProgressEvent::Summary { message, fields });
+    }
+    let line = match FORMAT.get().copied().unwrap_or_default() {
+        LogFormat::Text => message.to_string(),
+        LogFormat::Json => {
+            let mut object = match fields {
+                Value::Object(object) => object,
+                _ => Map::new(),
+            };
+            object.insert("timestamp".into(), Value::String(timestamp()));
+            object.insert("level".into(), Value::String("INFO".into()));
+            object.insert("message".into(), Value::String(message.into()));
+            object.insert("summary".into(), Value::Bool(true));
+            Value::Object(object).to_string()
+        }
+    };
+    let _ = writeln!(SuspendingStderr, "{}", line);
+}
+
+/// A numbered step of a command: a spinner on interactive terminals, or a
+/// log line with the phase name and duration when it finishes
+pub struct Phase {
+    name: &'static str,
+    bar: ProgressBar,
+    started: Instant,
+}
+
+impl Phase {
+    pub fn start(name: &'static str, prefix: &str, message: impl Into<String>) -> Self {
+        let message = message.into();
+        let bar = if spinners_enabled() {
+            let bar = multi().add(ProgressBar::new_spinner());
+            bar.set_style(
+                ProgressStyle::default_spinner()
+                    .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈ ")
+                    .template("{prefix:.bold.dim} {spinner} {wide_msg}")
+                    .expect("valid spinner template"),
+            );
+            bar.set_prefix(prefix.to_string());
+            bar.enable_steady_tick(Duration::from_millis(100));
+            bar
+        } else {
+            ProgressBar::hidden()
+        };
+        events::emit(ProgressEvent::PhaseStarted {
+            phase: name,
+            message: &message,
+        });
+        bar.set_message(message);
+
+        Self {
+            name,
+            bar,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn finish(self, message: impl Into<String>) -> Duration {
+        let message = message.into();
+        let elapsed = self.started.elapsed();
+        events::emit(ProgressEvent::PhaseCompleted {
+            phase: self.name,
+            message: &message,
+            duration_ms: elapsed.as_millis() as u64,
+        });
+        if spinners_enabled() {
+            self.bar.finish_with_message(message);
+        } else {
+            info!(
+                phase = self.name,
+                duration_ms = elapsed.as_millis() as u64,
+                "{}",
+                message
+            );
+        }
+        elapsed
+    }
+}
+
+/// Progress through a list of files processed by parallel workers: an
+/// overall bar plus a line per in-flight file on interactive terminals, or a
+/// log line every few seconds otherwise
+pub struct FileProgress {
+    bar: ProgressBar,
+    total: usize,
+    workers: usize,
+    state: Mutex<ProgressState>,
+}
+
+struct ProgressState {
+    done: usize,
+    latencies: VecDeque<Duration>,
+    last_report: Instant,
+}
+
+/// A file being worked on; pass it back to [`FileProgress::finish`]
+pub struct FileLine {
+    bar: ProgressBar,
+    started: Instant,
+}
+
+impl FileProgress {
+    /// `done` files were finished earlier (e.g. by a resumed run)
+    pub fn new(total: usize, done: usize, workers: usize) -> Self {
+        let bar = if spinners_enabled() {
+            let bar = multi().add(ProgressBar::new(total as u64));
+            bar.set_style(
+                ProgressStyle::default_bar()
+                    .template("      [{bar:30.cyan/blue}] {pos}/{len} files {msg}")
+                    .expect("valid bar template")
+                    .progress_chars("=> "),
+            );
+            bar.set_position(done as u64);
+            bar
+        } else {
+            ProgressBar::hidden()
+        };
+
+        Self {
+            bar,
+            total,
+            workers: workers.max(1),
+            state: Mutex::new(ProgressState {
+                done,
+                latencies: VecDeque::with_capacity(LATENCY_WINDOW),
+                last_report: Instant::now(),
+            }),
+        }
+    }
+
+    /// Show a line for a file a worker has started on
+    pub fn start(&self, file: &str) -> FileLine {
+        let bar = if spinners_enabled() {
+            let bar = multi().insert_after(&self.bar, ProgressBar::new_spinner());
+            bar.set_style(
+                ProgressStyle::default_spinner()
+                    .template("      {spinner:.dim} {elapsed:>3} {wide_msg}")
+                    .expect("valid spinner template"),
+            );
+            bar.set_message(file.to_string());
+            bar.enable_steady_tick(Duration::from_millis(200));
+            bar
+        } else {
+            ProgressBar::hidden()
+        };
+
+        FileLine {
+            bar,
+            started: Instant::now(),
+        }
+    }
+
+    /// Count a file as done, timing it when it had a line
+    pub fn finish(&self, line: Option<FileLine>) {
+        let mut state = self.state.lock().unwrap();
+        state.done += 1;
+        if let Some(line) = line {
+            line.bar.finish_and_clear();
+            multi().remove(&line.bar);
+            if state.latencies.len() == LATENCY_WINDOW {
+                state.latencies.pop_front();
+            }
+            state.latencies.push_back(line.started.elapsed());
+        }
+
+        let eta = eta(&state.latencies, self.total - state.done, self.workers);
+        let eta_text = eta
+            .map(|d| format!("ETA {}", format_duration(d)))
+            .unwrap_or_default();
+
+        if spinners_enabled() {
+            self.bar.set_position(state.done as u64);
+            self.bar.set_message(eta_text);
+        } else if state.last_report.elapsed() >= REPORT_INTERVAL || state.done == self.total {
+            state.last_report = Instant::now();
+            info!(
+                completed = state.done,
+                total = self.total,
+                eta_s = eta.map(|d| d.as_secs()),
+                "Progress: {}/{} files ({}%) {}",
+                state.done,
+                self.total,
+                state.done * 100 / self.total.max(1),
+                eta_text
+            );
+        }
+    }
+
+    pub fn clear(&self) {
+        self.bar.finish_and_clear();
+        multi().remove(&self.bar);
+    }
+}
+
+/// Remaining time from the average of recent per-file latencies, with
+/// `workers` files in flight at once
+fn eta(latencies: &VecDeque<Duration>, remaining: usize, workers: usize) -> Option<Duration> {
+    if latencies.is_empty() {
+        return None;
+    }
+    let average = latencies.iter().sum::<Duration>() / latencies.len() as u32;
+    Some(average * remaining.div_ceil(workers) as u32)
+}
+
+fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m{:02}s", secs / 60, secs % 60),
+        _ => format!("{}h{:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+pub(crate) fn timestamp() -> String {
+    let mut buf = String::new();
+    let _ = SystemTime.format_time(&mut Writer::new(&mut buf));
+    buf
+}
+
+/// Writes each event as a JSON object with its fields flattened in
+struct JsonLayer;
+
+impl<S: Subscriber> Layer<S> for JsonLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut fields = JsonVisitor(Map::new());
+        event.record(&mut fields);
+
+        let mut object = Map::new();
+        object.insert("timestamp".into(), Value::String(timestamp()));
+        object.insert(
+            "level".into(),
+            Value::String(event.metadata().level().to_string()),
+        );
+        object.append(&mut fields.0);
+
+        let _ = writeln!(std::io::stderr(), "{}", Value::Object(object));
+    }
+}
+
+struct JsonVisitor(Map<String, Value>);
+
+impl Visit for JsonVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0
+            .insert(field.name().into(), Value::String(value.into()));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0
+            .insert(field.name().into(), Value::String(format!("{:?}", value)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eta() {
+        assert_eq!(eta(&VecDeque::new(), 10, 2), None);
+
+        let latencies: VecDeque<_> = [2, 4, 6].into_iter().map(Duration::from_secs).collect();
+        // 4s average, 9 files over 4 workers is 3 rounds
+        assert_eq!(eta(&latencies, 9, 4), Some(Duration::from_secs(12)));
+        assert_eq!(eta(&latencies, 0, 4), Some(Duration::ZERO));
+
+        assert_eq!(format_duration(Duration::from_secs(42)), "42s");
+        assert_eq!(format_duration(Duration::from_secs(754)), "12m34s");
+        assert_eq!(format_duration(Duration::from_secs(7500)), "2h05m");
+    }
+
+    #[test]
+    fn test_resolve_color() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(k, _)| *k == name)
+                    .map(|(_, v)| v.to_string())
+            }
+        };
+
+        assert!(resolve_color(ColorChoice::Auto, true, env(&[])));
+        assert!(!resolve_color(ColorChoice::Auto, false, env(&[])));
+        assert!(!resolve_color(
+            ColorChoice::Auto,
+            true,
+            env(&[("NO_COLOR", "1")])
+        ));
+        // An empty NO_COLOR is treated as unset
+        assert!(resolve_color(
+            ColorChoice::Auto,
+            true,
+            env(&[("NO_COLOR", "")])
+        ));
+        assert!(!resolve_color(
+            ColorChoice::Auto,
+            true,
+            env(&[("CLICOLOR", "0")])
+        ));
+        assert!(resolve_color(
+            ColorChoice::Auto,
+            false,
+            env(&[("CLICOLOR_FORCE", "1")])
+        ));
+        assert!(!resolve_color(
+            ColorChoice::Auto,
+            false,
+            env(&[("CLICOLOR_FORCE", "1"), ("NO_COLOR", "1")])
+        ));
+
+        // The flag wins over the environment
+        assert!(resolve_color(
+            ColorChoice::Always,
+            false,
+            env(&[("NO_COLOR", "1")])
+        ));
+        assert!(!resolve_color(
+            ColorChoice::Never,
+            true,
+            env(&[("CLICOLOR_FORCE", "1")])
+        ));
+    }
+
+    #[test]
+    fn test_json_layer_flattens_fields() {
+        let captured = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+
+        struct Capture(std::sync::Arc<std::sync::Mutex<Vec<Map<String, Value>>>>);
+        impl<S: Subscriber> Layer<S> for Capture {
+            fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+                let mut fields = JsonVisitor(Map::new());
+                event.record(&mut fields);
+                self.0.lock().unwrap().push(fields.0);
+            }
+        }
+
+        let subscriber = tracing_subscriber::registry().with(Capture(captured.clone()));
+        tracing::subscriber::with_default(subscriber, || {
+            info!(phase = "discovery", file = %"src/main.rs", duration_ms = 12u64, "Found {} files", 3);
+        });
+
+        let events = captured.lock().unwrap();
+        assert_eq!(
+            Value::Object(events[0].clone()),
+            serde_json::json!({
+                "message": "Found 3 files",
+                "phase": "discovery",
+                "file": "src/main.rs",
+                "duration_ms": 12,
+            })
+        );
+    }
+}
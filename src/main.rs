@@ -1,11 +1,11 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use clap::{CommandFactory, Parser, Subcommand};
+use std::process::ExitCode;
 
 mod commands;
-mod core;
-mod llm;
-mod output;
+mod config;
+
+use codebase_deep_analyzer::{core, events, llm, logging, output};
 
 #[derive(Parser)]
 #[command(name = "cda")]
@@ -17,53 +17,440 @@ mod output;
 #[command(propagate_version = true)]
 struct Cli {
     /// Enable verbose logging
-    #[arg(short, long, global = true)]
+    #[arg(short, long, global = true, conflicts_with = "quiet")]
     verbose: bool,
 
-    /// Output format
-    #[arg(short, long, global = true, default_value = "markdown")]
-    format: output::Format,
+    /// Hide progress spinners and only log warnings and errors
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// Log output format
+    #[arg(long, global = true, value_enum, default_value_t = logging::LogFormat::Text)]
+    log_format: logging::LogFormat,
+
+    /// When to use colors in logs and progress bars; `auto` also honors
+    /// NO_COLOR and CLICOLOR
+    #[arg(long, global = true, value_enum, default_value_t = logging::ColorChoice::Auto)]
+    color: logging::ColorChoice,
+
+    /// Output format [default: markdown]
+    #[arg(short, long, global = true)]
+    format: Option<output::Format>,
+
+    /// Read only this config file, instead of the user and repo configs
+    #[arg(long = "config", global = true, value_name = "FILE")]
+    config_file: Option<std::path::PathBuf>,
+
+    /// Config profile to apply, from a [profile.<name>] section
+    #[arg(long, global = true, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// Run the [plugins] commands of ./cda.toml, which are ignored otherwise;
+    /// only use in repositories you trust
+    #[arg(long, global = true)]
+    allow_repo_plugins: bool,
+
+    /// Send source to the LLM as is, without replacing likely secrets (keys,
+    /// tokens, passwords) with placeholders
+    #[arg(long, global = true)]
+    no_redact: bool,
+
+    /// What per-file LLM analysis sends: `full` sends the source, `signatures`
+    /// only the parsed exports, signatures, doc comments and imports
+    #[arg(long, global = true, value_enum, default_value_t)]
+    deep_mode: core::analyzer::DeepMode,
+
+    /// Directory of prompt templates (`generic.md`, `test.md`, `shell.md`,
+    /// `react.md`, `sql.md`, `dockerfile.md`) replacing the built-in ones
+    #[arg(long, global = true, value_name = "DIR")]
+    templates: Option<std::path::PathBuf>,
+
+    /// Print the man page to stdout, or write it and a page per command to DIR
+    #[arg(long, hide = true, value_name = "DIR", num_args = 0..=1)]
+    generate_man: Option<Option<std::path::PathBuf>>,
 
     #[command(subcommand)]
-    command: Commands,
+    command: Option<Commands>,
 }
 
+// Parsed once at startup, so the size of the Analyze variant doesn't matter
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 enum Commands {
     /// Analyze a codebase and generate documentation
     Analyze {
-        /// Path to the codebase to analyze
+        /// Path to the codebase to analyze, or a single source file
         #[arg(default_value = ".")]
         path: String,
 
-        /// Output directory for generated documentation
-        #[arg(short, long, default_value = "./cda-output")]
-        output: String,
+        /// Output directory for generated documentation [default: ./cda-output]
+        #[arg(short, long)]
+        output: Option<String>,
 
         /// Specific module or directory to analyze (for targeted analysis)
         #[arg(short, long)]
         module: Option<String>,
 
-        /// LLM provider to use
-        #[arg(long, env = "CDA_PROVIDER", default_value = "anthropic")]
-        provider: String,
+        /// With --module, update that module within the output's earlier
+        /// analysis.json and regenerate the output for the whole project
+        #[arg(long, requires = "module")]
+        merge: bool,
+
+        /// With --module, remove the earlier output for the rest of the
+        /// project, so the output covers the module alone
+        #[arg(long, requires = "module", conflicts_with = "merge")]
+        replace: bool,
+
+        /// Only analyze source files in these languages, e.g. `rust,typescript`
+        #[arg(long = "language", value_name = "LANG", value_delimiter = ',', value_parser = core::Language::parse_filter)]
+        languages: Vec<core::Language>,
+
+        /// Also analyze test files; they are listed in a separate Tests
+        /// section and left out of export counts and gaps
+        #[arg(long)]
+        include_tests: bool,
+
+        /// Also analyze files `.gitattributes` marks `linguist-generated`
+        /// or `linguist-vendored`
+        #[arg(long)]
+        include_generated: bool,
+
+        /// Document each file as its own module, instead of pairing a C/C++
+        /// header with its source and a Rust mod.rs with its private
+        /// submodule files
+        #[arg(long)]
+        no_pairing: bool,
+
+        /// Also document top-level items that are not exported (private
+        /// functions, types, ...) in an "Internal items" section; they are
+        /// left out of export counts and gaps
+        #[arg(long)]
+        include_private: bool,
+
+        /// Analyze at most this many source files, e.g. to try --deep on a
+        /// large repo before a full run; the output is marked as partial
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..), conflicts_with_all = ["changed_only", "files_from"])]
+        max_files: Option<u64>,
+
+        /// How --max-files picks files [default: fan-in if a previous
+        /// analysis.json exists, otherwise largest]
+        #[arg(long, value_enum, requires = "max_files")]
+        sample: Option<commands::analyze::SampleStrategy>,
+
+        /// Order --mode deep, --changed-only and --files-from work through the
+        /// files in, so a capped or interrupted run covers the most useful
+        /// ones first
+        #[arg(long, value_enum, default_value_t)]
+        order: commands::analyze::FileOrder,
+
+        /// Which files --mode deep sends to the LLM: `smart` ranks them by
+        /// entry points, fan-in, size and missing docs and leaves out barrel
+        /// files and generated code; the choice is recorded in analysis.json
+        #[arg(long, value_enum, default_value_t, conflicts_with_all = ["changed_only", "files_from"])]
+        deep_select: core::selection::DeepSelect,
+
+        /// Make at most this many LLM calls in --mode deep, spent on files in
+        /// --deep-select order (a file too long for one prompt costs one call
+        /// per chunk); the rest get static analysis only. This is a call
+        /// budget: there is no token or cost limit
+        #[arg(long, value_name = "N", conflicts_with_all = ["changed_only", "files_from"])]
+        max_llm_calls: Option<usize>,
+
+        /// Seed for --sample random and --order random, to repeat a run
+        #[arg(long)]
+        seed: Option<u64>,
+
+        /// Keep this run in the history: write it to
+        /// <output>/runs/<timestamp>-<tag>/ and record it in runs/index.json
+        #[arg(long, value_name = "NAME")]
+        tag: Option<String>,
+
+        /// Print per-phase wall time, per-file parse and LLM percentiles
+        /// and the slowest files at the end (always saved in analysis.json)
+        #[arg(long)]
+        timings: bool,
+
+        /// Also write progress events as newline-delimited JSON to this file,
+        /// or to stdout for `-`, for tools that wrap cda
+        #[arg(long, value_name = "FILE")]
+        progress_json: Option<String>,
+
+        /// Remove artifacts an earlier run listed in manifest.json that this
+        /// run didn't regenerate, instead of marking them stale
+        #[arg(long)]
+        prune: bool,
+
+        /// Keep the module pages and progress entries of source files that
+        /// no longer exist, instead of removing them and listing them in
+        /// cda-changes.md
+        #[arg(long)]
+        keep_stale: bool,
+
+        /// LLM provider to use [default: anthropic]
+        #[arg(long)]
+        provider: Option<String>,
 
         /// Model to use for analysis
-        #[arg(long, env = "CDA_MODEL")]
+        #[arg(long)]
         model: Option<String>,
 
-        /// Number of parallel analysis workers
-        #[arg(short, long, default_value = "4")]
-        parallelism: usize,
+        /// Files parsed concurrently [default: number of CPU cores]
+        #[arg(long, value_name = "N")]
+        parse_jobs: Option<usize>,
 
-        /// Enable per-file LLM analysis (slow, use for small codebases)
+        /// LLM requests in flight at once; keep within the provider's rate
+        /// limit [default: 4]
+        #[arg(short = 'p', long, alias = "parallelism", value_name = "N")]
+        llm_concurrency: Option<usize>,
+
+        /// How much to use the LLM: `static` makes no calls, `standard` adds
+        /// an architecture overview, `deep` analyzes every file
+        /// [default: standard, or deep with analysis.deep = true]
+        #[arg(long, value_enum, conflicts_with = "deep")]
+        mode: Option<commands::analyze::AnalysisMode>,
+
+        /// Same as --mode deep: per-file LLM analysis (slow, use for small codebases)
         #[arg(long)]
         deep: bool,
+
+        /// Record top git contributors, last change and CODEOWNERS owners
+        /// per module [default: analysis.ownership, off]
+        #[arg(long, conflicts_with = "no_ownership")]
+        ownership: bool,
+
+        /// Don't collect ownership, even if the config enables it
+        #[arg(long)]
+        no_ownership: bool,
+
+        /// Show each export's source, up to output.max_snippet_lines lines,
+        /// on module pages and in JSON [default: output.include_snippets, on]
+        #[arg(long, conflicts_with = "no_snippets")]
+        snippets: bool,
+
+        /// Leave source snippets out, even if the config enables them
+        #[arg(long)]
+        no_snippets: bool,
+
+        /// Language the LLM writes summaries, the overview and descriptions
+        /// in, e.g. Japanese [default: output.language]
+        #[arg(long, value_name = "LANGUAGE")]
+        doc_language: Option<String>,
+
+        /// How pages under modules/ are named [default: output.page_naming,
+        /// mirror-tree]
+        #[arg(long, value_enum, value_name = "SCHEME")]
+        page_naming: Option<core::analyzer::PageNaming>,
+
+        /// Check that the provider offers --model before analyzing any files
+        #[arg(long)]
+        check_model: bool,
+
+        /// Start --deep runs without the small request that checks an Ollama
+        /// model is pulled and fits in memory
+        #[arg(long)]
+        skip_preflight: bool,
+
+        /// Skip files finished by a previous --deep run (default when progress exists)
+        #[arg(long, conflicts_with_all = ["no_resume", "force"])]
+        resume: bool,
+
+        /// Re-analyze every file; the old progress file is kept as .cda-state/progress.old
+        #[arg(long, conflicts_with = "force")]
+        no_resume: bool,
+
+        /// Delete progress and generated module pages before starting
+        #[arg(long)]
+        force: bool,
+
+        /// When resuming, keep files analyzed with an older prompt version
+        /// instead of re-analyzing them
+        #[arg(long, conflicts_with_all = ["no_resume", "force"])]
+        keep_stale_prompts: bool,
+
+        /// Adopt resume state restored from elsewhere, such as a CI cache: a
+        /// .cda-state/ directory, or an output directory holding one
+        #[arg(long, value_name = "DIR", conflicts_with_all = ["no_resume", "force"])]
+        import_state: Option<String>,
+
+        /// Deep-analyze only files changed since this git ref, reusing the
+        /// previous output for everything else
+        #[arg(long, value_name = "REF", conflicts_with = "force")]
+        changed_only: Option<String>,
+
+        /// Deep-analyze only the files listed in FILE (`-` for stdin), one
+        /// path relative to the analyzed directory per line, reusing the
+        /// previous output for everything else
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["changed_only", "force"])]
+        files_from: Option<String>,
+
+        /// With --changed-only or --files-from, also refresh modules that
+        /// import the files analyzed
+        #[arg(long)]
+        dependents: bool,
+
+        /// Exit with code 3 when gaps exceed per-kind or per-severity limits,
+        /// e.g. `missing_docs:0,circular_dependency:0` or `error` (no value:
+        /// any gap fails)
+        #[arg(long, value_name = "KIND:MAX,...", num_args = 0..=1, default_missing_value = "")]
+        fail_on_gaps: Option<core::gate::GapThresholds>,
+
+        /// Like --fail-on-gaps, but only count gaps not present in --baseline
+        #[arg(long, value_name = "KIND:MAX,...", num_args = 0..=1, default_missing_value = "", conflicts_with = "fail_on_gaps", requires = "baseline")]
+        fail_on_new_gaps: Option<core::gate::GapThresholds>,
+
+        /// analysis.json from a previous `--format json` run to compare against
+        #[arg(long, value_name = "FILE", requires = "fail_on_new_gaps")]
+        baseline: Option<String>,
+
+        /// Only generate the architecture overview again, for the
+        /// analysis.json already in the output directory
+        #[arg(long, conflicts_with_all = ["changed_only", "files_from", "max_files", "tag", "force", "fail_on_gaps", "fail_on_new_gaps"])]
+        only_overview: bool,
+    },
+
+    /// Explain a single source file and print the result to stdout
+    Explain {
+        /// File to explain, or `-` to read from stdin
+        file: String,
+
+        /// Source language (required when reading from stdin)
+        #[arg(short, long)]
+        language: Option<String>,
+
+        /// LLM provider to use [default: anthropic]
+        #[arg(long)]
+        provider: Option<String>,
+
+        /// Model to use for analysis
+        #[arg(long)]
+        model: Option<String>,
+
+        /// Skip the LLM and print only the static analysis
+        #[arg(long)]
+        static_only: bool,
+
+        /// Language the LLM writes summaries, the overview and descriptions
+        /// in, e.g. Japanese [default: output.language]
+        #[arg(long, value_name = "LANGUAGE")]
+        doc_language: Option<String>,
+    },
+
+    /// Watch a codebase and keep its documentation up to date on save
+    Watch {
+        /// Path to the codebase to watch
+        #[arg(default_value = ".")]
+        path: String,
+
+        /// Output directory for generated documentation [default: ./cda-output]
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// LLM provider to use [default: anthropic]
+        #[arg(long)]
+        provider: Option<String>,
+
+        /// Model to use for analysis
+        #[arg(long)]
+        model: Option<String>,
+
+        /// Re-run per-file LLM analysis on changed files
+        #[arg(long)]
+        deep: bool,
+
+        /// Also document top-level items that are not exported, as with
+        /// `cda analyze --include-private`
+        #[arg(long)]
+        include_private: bool,
+
+        /// Language the LLM writes summaries, the overview and descriptions
+        /// in, e.g. Japanese [default: output.language]
+        #[arg(long, value_name = "LANGUAGE")]
+        doc_language: Option<String>,
+
+        /// How long the tree must be quiet before re-analyzing, in milliseconds
+        #[arg(long, default_value = "500")]
+        debounce: u64,
+    },
+
+    /// Print project statistics without calling an LLM or writing output
+    Stats {
+        /// Path to the codebase to inspect
+        #[arg(default_value = ".")]
+        path: String,
+
+        /// Specific module or directory to inspect
+        #[arg(short, long)]
+        module: Option<String>,
+    },
+
+    /// Write other output formats from an existing analysis.json, without re-analyzing
+    Export {
+        /// analysis.json from `cda analyze --format json`, an output
+        /// directory holding one, or `latest`, a tag or a run id from the
+        /// runs index
+        input: String,
+
+        /// Output directory (default: INPUT's directory)
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Formats to write, e.g. `markdown,json` (default: --format)
+        #[arg(long, value_enum, value_delimiter = ',')]
+        to: Vec<output::Format>,
+
+        /// Keep only the gaps of this owner, e.g. `@org/billing` or
+        /// `unassigned`
+        #[arg(long, value_name = "TEAM", requires = "output")]
+        owner: Option<String>,
+    },
+
+    /// Interactive first-run setup: provider, credentials and defaults
+    Init {
+        /// LLM provider to configure
+        #[arg(long)]
+        provider: Option<String>,
+
+        /// Default model for the provider
+        #[arg(long)]
+        model: Option<String>,
+
+        /// Default number of concurrent LLM requests
+        #[arg(short = 'p', long, alias = "parallelism", value_name = "N")]
+        llm_concurrency: Option<usize>,
+
+        /// Also create ./cda.toml and ./.cdaignore
+        #[arg(long)]
+        local: bool,
+
+        /// Don't prompt; use flags and defaults (requires --provider)
+        #[arg(short, long)]
+        yes: bool,
+
+        /// Don't make a test call to check credentials
+        #[arg(long)]
+        skip_verify: bool,
+    },
+
+    /// List the models available from each LLM provider
+    Models {
+        /// Only query this provider
+        #[arg(long)]
+        provider: Option<String>,
+    },
+
+    /// Check credentials, provider connectivity, config files and paths
+    Doctor,
+
+    /// List runs recorded with `cda analyze --tag`
+    Runs {
+        #[command(subcommand)]
+        action: RunsAction,
     },
 
     /// Verify that documentation matches actual codebase behavior
     Verify {
-        /// Path to the analysis output to verify
+        /// Analysis output to verify: a directory, or `latest`, a tag or a
+        /// run id from the runs index
         #[arg(default_value = "./cda-output")]
         path: String,
 
@@ -72,55 +459,540 @@ enum Commands {
         run_commands: bool,
     },
 
-    /// Show current configuration
+    /// Serve an analysis output directory as a local web UI
+    Serve {
+        /// Path to the analysis output to serve
+        #[arg(default_value = "./cda-output")]
+        path: String,
+
+        /// Address to bind the server to
+        #[arg(long, default_value = "127.0.0.1")]
+        bind: String,
+
+        /// Port to listen on
+        #[arg(long, default_value = "8080")]
+        port: u16,
+
+        /// Narrow the dependency graph to modules matching GLOB, e.g.
+        /// `src/billing/**`, plus their direct dependencies and dependents
+        #[arg(long, value_name = "GLOB")]
+        focus: Option<String>,
+    },
+
+    /// Remove generated state from an output directory
+    Clean {
+        /// Path to the analysis output to clean
+        #[arg(default_value = "./cda-output")]
+        path: String,
+
+        /// Remove the resume state (progress and page copies)
+        #[arg(long)]
+        progress: bool,
+
+        /// Remove the LLM response cache
+        #[arg(long)]
+        cache: bool,
+
+        /// Remove generated module pages
+        #[arg(long)]
+        modules: bool,
+
+        /// Remove the module pages and progress entries of source files
+        /// that no longer exist under --root, and list them in cda-changes.md
+        #[arg(long)]
+        stale: bool,
+
+        /// Directory that was analyzed, for --stale [default: the one the
+        /// manifest records]
+        #[arg(long, value_name = "DIR", requires = "stale")]
+        root: Option<String>,
+
+        /// Remove everything cda generated
+        #[arg(long)]
+        all: bool,
+
+        /// Delete without asking for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Print a shell completion script to stdout
+    Completions {
+        /// Shell to generate completions for
+        shell: clap_complete::Shell,
+    },
+
+    /// Show or edit configuration
     Config {
         /// Initialize a new config file
-        #[arg(long)]
+        #[arg(long, conflicts_with_all = ["set", "get", "unset", "validate"])]
         init: bool,
+
+        /// Set a key, e.g. `--set llm.provider ollama`; invalid values are refused
+        #[arg(long, num_args = 2, value_names = ["KEY", "VALUE"], conflicts_with_all = ["get", "unset", "validate"])]
+        set: Option<Vec<String>>,
+
+        /// Print the effective value of a key
+        #[arg(long, value_name = "KEY", conflicts_with_all = ["unset", "validate"])]
+        get: Option<String>,
+
+        /// Remove a key from the config file
+        #[arg(long, value_name = "KEY", conflicts_with = "validate")]
+        unset: Option<String>,
+
+        /// Check config files for unknown keys and invalid values
+        #[arg(long)]
+        validate: bool,
+
+        /// Use the repo-local ./cda.toml instead of the user config
+        #[arg(long)]
+        local: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum RunsAction {
+    /// Show the run history with changes from the previous run
+    List {
+        /// Output directory the runs were written under [default: ./cda-output]
+        #[arg(short, long)]
+        output: Option<String>,
     },
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
+/// Exit codes are listed in [`core::exit`]; clap exits with 2 on parse errors
+fn main() -> ExitCode {
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("Error: {:?}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match runtime.block_on(run()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            events::emit(events::Event::RunFailed {
+                error: &format!("{:#}", e),
+                exit_code: core::exit::code(&e),
+            });
+            // Not a failure of the command itself, so not an "Error:"
+            if e.downcast_ref::<core::exit::CompletedWithWarnings>()
+                .is_some()
+            {
+                eprintln!("Warning: {}", e);
+            } else {
+                eprintln!("Error: {:?}", e);
+            }
+            ExitCode::from(core::exit::code(&e) as u8)
+        }
+    }
+}
+
+async fn run() -> Result<()> {
     let cli = Cli::parse();
 
-    // Set up logging
-    let filter = if cli.verbose { "debug" } else { "info" };
+    logging::init(cli.verbose, cli.quiet, cli.log_format, cli.color);
+    core::redact::set_enabled(!cli.no_redact);
+    core::analyzer::set_deep_mode(cli.deep_mode);
+    if let Some(dir) = &cli.templates {
+        let ids = core::templates::set_overrides(dir)?;
+        tracing::info!(
+            "Prompt templates from {}: {}",
+            dir.display(),
+            if ids.is_empty() {
+                "none".to_string()
+            } else {
+                ids.join(", ")
+            }
+        );
+    }
 
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| filter.into()),
-        )
-        .with(tracing_subscriber::fmt::layer().with_target(false))
-        .init();
+    if let Some(dir) = &cli.generate_man {
+        commands::man::run(dir.as_deref())?;
+        return Ok(());
+    }
 
-    match cli.command {
+    let Some(command) = cli.command else {
+        Cli::command().print_help()?;
+        return Ok(());
+    };
+
+    let mut cli_layer = config::ConfigFile::default();
+    cli_layer.output.format = cli.format;
+    cli_layer.default_profile = cli.profile;
+    cli_layer.config_file = cli.config_file;
+    cli_layer.analysis.allow_repo_plugins = cli.allow_repo_plugins.then_some(true);
+
+    // Watch stops cleanly on Ctrl-C by itself
+    if matches!(command, Commands::Watch { .. }) {
+        return dispatch(command, cli_layer, cli.format).await;
+    }
+    // Polled first, so the handler is installed before the command starts
+    tokio::select! {
+        biased;
+        _ = tokio::signal::ctrl_c() => Err(core::exit::Interrupted.into()),
+        result = dispatch(command, cli_layer, cli.format) => result,
+    }
+}
+
+async fn dispatch(
+    command: Commands,
+    mut cli_layer: config::ConfigFile,
+    format: Option<output::Format>,
+) -> Result<()> {
+    match command {
         Commands::Analyze {
             path,
             output,
             module,
+            merge,
+            replace,
+            languages,
+            include_tests,
+            include_generated,
+            no_pairing,
+            include_private,
+            max_files,
+            sample,
+            order,
+            deep_select,
+            max_llm_calls,
+            seed,
+            tag,
+            timings,
+            progress_json,
+            prune,
+            keep_stale,
             provider,
             model,
-            parallelism,
+            parse_jobs,
+            llm_concurrency,
+            mode,
             deep,
+            ownership,
+            no_ownership,
+            snippets,
+            no_snippets,
+            doc_language,
+            page_naming,
+            check_model,
+            skip_preflight,
+            resume: _,
+            no_resume,
+            force,
+            keep_stale_prompts,
+            import_state,
+            changed_only,
+            files_from,
+            dependents,
+            fail_on_gaps,
+            fail_on_new_gaps,
+            baseline,
+            only_overview,
         } => {
+            cli_layer.llm.provider = provider;
+            cli_layer.llm.model = model;
+            cli_layer.analysis.parse_jobs = parse_jobs;
+            cli_layer.analysis.llm_concurrency = llm_concurrency;
+            cli_layer.analysis.deep = deep.then_some(true);
+            cli_layer.analysis.ownership = if no_ownership {
+                Some(false)
+            } else {
+                ownership.then_some(true)
+            };
+            cli_layer.output.include_snippets = if no_snippets {
+                Some(false)
+            } else {
+                snippets.then_some(true)
+            };
+            cli_layer.output.directory = output;
+            cli_layer.output.language = doc_language;
+            cli_layer.output.page_naming = page_naming;
+            let config = config::load(cli_layer)?;
+            config.register_process_settings();
+
             commands::analyze::run(commands::analyze::AnalyzeArgs {
                 path,
-                output,
+                output: config.output.value.clone(),
+                module,
+                provider: config.provider.value.clone(),
+                model: config.model.value.clone(),
+                parse_jobs: config.parse_jobs.value,
+                llm_concurrency: config.llm_concurrency.value,
+                ownership: config.ownership.value,
+                owners: config
+                    .owners
+                    .iter()
+                    .map(|(pattern, team)| (pattern.clone(), team.value.clone()))
+                    .collect(),
+                parse_options: core::analyzer::ParseOptions {
+                    include_private,
+                    snippet_lines: config.snippet_lines(),
+                    max_examples: config.max_examples.value,
+                },
+                mode: match mode {
+                    Some(mode) => mode,
+                    None if config.deep.value => commands::analyze::AnalysisMode::Deep,
+                    None => commands::analyze::AnalysisMode::Standard,
+                },
+                check_model,
+                skip_preflight,
+                resume: if force {
+                    core::analyzer::ResumeMode::Force
+                } else if no_resume {
+                    core::analyzer::ResumeMode::NoResume
+                } else {
+                    core::analyzer::ResumeMode::Resume
+                },
+                keep_stale_prompts,
+                import_state,
+                changed_only,
+                files_from,
+                dependents,
+                fail_on_gaps: fail_on_gaps.or(fail_on_new_gaps),
+                baseline,
+                format: config.format.value,
+                discovery: {
+                    let mut options = config.discovery_options();
+                    options.languages = languages;
+                    options.include_tests = include_tests;
+                    options.include_generated = include_generated;
+                    options
+                },
+                max_files: max_files.map(|n| n as usize),
+                sample,
+                order,
+                deep_select,
+                max_llm_calls,
+                seed,
+                tag,
+                timings,
+                progress_json,
+                prune,
+                keep_stale,
+                no_pairing,
+                only_overview,
+                module_output: if merge {
+                    commands::analyze::ModuleOutput::Merge
+                } else if replace {
+                    commands::analyze::ModuleOutput::Replace
+                } else {
+                    commands::analyze::ModuleOutput::Overlay
+                },
+            })
+            .await?;
+        }
+        Commands::Explain {
+            file,
+            language,
+            provider,
+            model,
+            static_only,
+            doc_language,
+        } => {
+            cli_layer.llm.provider = provider;
+            cli_layer.llm.model = model;
+            cli_layer.output.language = doc_language;
+            let config = config::load(cli_layer)?;
+            config.register_process_settings();
+
+            commands::explain::run(commands::explain::ExplainArgs {
+                file,
+                language,
+                snippet_lines: config.snippet_lines(),
+                provider: config.provider.value,
+                model: config.model.value,
+                static_only,
+            })
+            .await?;
+        }
+        Commands::Watch {
+            path,
+            output,
+            provider,
+            model,
+            deep,
+            include_private,
+            doc_language,
+            debounce,
+        } => {
+            cli_layer.llm.provider = provider;
+            cli_layer.llm.model = model;
+            cli_layer.analysis.deep = deep.then_some(true);
+            cli_layer.output.directory = output;
+            cli_layer.output.language = doc_language;
+            let config = config::load(cli_layer)?;
+            config.register_process_settings();
+
+            commands::watch::run(commands::watch::WatchArgs {
+                path,
+                output: config.output.value.clone(),
+                provider: config.provider.value.clone(),
+                model: config.model.value.clone(),
+                deep: config.deep.value,
+                parse_jobs: config.parse_jobs.value,
+                debounce_ms: debounce,
+                parse_options: core::analyzer::ParseOptions {
+                    include_private,
+                    snippet_lines: config.snippet_lines(),
+                    max_examples: config.max_examples.value,
+                },
+                format: config.format.value,
+                discovery: config.discovery_options(),
+            })
+            .await?;
+        }
+        Commands::Stats { path, module } => {
+            let config = config::load(cli_layer)?;
+            config.register_plugins();
+
+            commands::stats::run(commands::stats::StatsArgs {
+                path,
                 module,
+                format: config.format.value,
+                discovery: config.discovery_options(),
+            })
+            .await?;
+        }
+        Commands::Export {
+            input,
+            output,
+            to,
+            owner,
+        } => {
+            let config = config::load(cli_layer)?;
+            config.register_page_naming();
+            let input = if std::path::Path::new(&input).exists() {
+                input
+            } else {
+                let run = core::runs::resolve(&input, std::path::Path::new(&config.output.value))?;
+                run.display().to_string()
+            };
+
+            commands::export::run(commands::export::ExportArgs {
+                input,
+                output,
+                formats: if to.is_empty() {
+                    vec![config.format.value]
+                } else {
+                    to
+                },
+                owner,
+            })
+            .await?;
+        }
+        Commands::Init {
+            provider,
+            model,
+            llm_concurrency,
+            local,
+            yes,
+            skip_verify,
+        } => {
+            commands::init::run(commands::init::InitArgs {
                 provider,
                 model,
-                parallelism,
-                deep,
-                format: cli.format,
+                llm_concurrency,
+                format,
+                local,
+                yes,
+                skip_verify,
+            })
+            .await?;
+        }
+        Commands::Models { provider } => {
+            let config = config::load(cli_layer)?;
+
+            commands::models::run(commands::models::ModelsArgs {
+                provider,
+                default_provider: config.provider.value,
+                default_model: config.model.value,
+                format: config.format.value,
             })
             .await?;
         }
+        Commands::Doctor => {
+            commands::doctor::run(commands::doctor::DoctorArgs { cli: cli_layer }).await?;
+        }
+        Commands::Runs {
+            action: RunsAction::List { output },
+        } => {
+            cli_layer.output.directory = output;
+            let config = config::load(cli_layer)?;
+
+            commands::runs::list(commands::runs::RunsArgs {
+                output: config.output.value,
+                format: config.format.value,
+            })?;
+        }
         Commands::Verify { path, run_commands } => {
-            commands::verify::run(commands::verify::VerifyArgs { path, run_commands }).await?;
+            let config = config::load(cli_layer)?;
+            let path = core::runs::resolve(&path, std::path::Path::new(&config.output.value))?;
+
+            commands::verify::run(commands::verify::VerifyArgs {
+                path: path.display().to_string(),
+                run_commands,
+            })
+            .await?;
         }
-        Commands::Config { init } => {
-            commands::config::run(init)?;
+        Commands::Serve {
+            path,
+            bind,
+            port,
+            focus,
+        } => {
+            commands::serve::run(commands::serve::ServeArgs {
+                path,
+                bind,
+                port,
+                focus,
+            })
+            .await?;
+        }
+        Commands::Clean {
+            path,
+            progress,
+            cache,
+            modules,
+            stale,
+            root,
+            all,
+            yes,
+        } => {
+            config::load(cli_layer)?.register_page_naming();
+            commands::clean::run(commands::clean::CleanArgs {
+                path,
+                progress,
+                cache,
+                modules,
+                stale,
+                root,
+                all,
+                yes,
+            })?;
+        }
+        Commands::Completions { shell } => {
+            commands::completions::run(shell)?;
+        }
+        Commands::Config {
+            init,
+            set,
+            get,
+            unset,
+            validate,
+            local,
+        } => {
+            commands::config::run(commands::config::ConfigArgs {
+                init,
+                set: set.map(|kv| (kv[0].clone(), kv[1].clone())),
+                get,
+                unset,
+                validate,
+                local,
+                cli: cli_layer,
+            })?;
         }
     }
 
@@ -0,0 +1,333 @@
+//! Output directories written by other versions of cda
+//!
+//! `manifest.json` records the cda that wrote an output directory and the
+//! analysis.json schema of the run. Commands that read an output directory
+//! call [`check`] before parsing anything, so a directory this cda can't
+//! read fails with both versions named instead of a parse error. Within a
+//! major version, an older analysis.json is brought up to date by
+//! [`migrate`] as it is read, one documented step per minor version that
+//! needs one; the other minor versions only added optional fields.
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+use super::json::SCHEMA_VERSION;
+use super::manifest::{MANIFEST_FILE, MANIFEST_VERSION};
+use crate::core::analyzer::package_name;
+
+/// A change to analysis.json that older files are rewritten for when read
+pub struct Migration {
+    /// Schema version that introduced the change
+    pub version: &'static str,
+    pub description: &'static str,
+    apply: fn(&mut Value),
+}
+
+/// In version order
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: "1.14",
+        description: "`completeness` of each module, derived from `has_deep_analysis`",
+        apply: add_completeness,
+    },
+    Migration {
+        version: "1.32",
+        description: "`external_dependencies` with the modules using each, derived from imports",
+        apply: add_external_dependencies,
+    },
+];
+
+/// Fail unless this cda can read the output in `output_path`: its
+/// `manifest.json` and its analysis.json must have this cda's major
+/// versions. A directory with neither passes, as there is nothing to read.
+pub fn check(output_path: &Path) -> Result<()> {
+    let manifest = read_json(&output_path.join(MANIFEST_FILE))?;
+    let generator = manifest
+        .as_ref()
+        .and_then(|m| m.get("generator"))
+        .and_then(Value::as_str)
+        .unwrap_or("an unknown version of cda");
+
+    if let Some(version) = manifest
+        .as_ref()
+        .and_then(|m| m.get("version"))
+        .and_then(Value::as_str)
+    {
+        if major(version) != major(MANIFEST_VERSION) {
+            anyhow::bail!(
+                "{} was written by {} with {} version {}, but this cda ({}) reads version {}. {}",
+                output_path.display(),
+                generator,
+                MANIFEST_FILE,
+                version,
+                env!("CARGO_PKG_VERSION"),
+                MANIFEST_VERSION,
+                ADVICE
+            );
+        }
+    }
+
+    let schema = match manifest.as_ref().and_then(|m| m.get("schema")) {
+        Some(schema) => schema.as_str().map(String::from),
+        None => read_json(&analysis_path(output_path, manifest.as_ref()))?
+            .and_then(|a| a.get("version")?.as_str().map(String::from)),
+    };
+    let Some(schema) = schema else {
+        return Ok(());
+    };
+    if major(&schema) != major(SCHEMA_VERSION) {
+        anyhow::bail!(
+            "{} was written by {} with analysis.json schema {}, but this cda ({}) reads \
+             schema {}.x. {}",
+            output_path.display(),
+            generator,
+            schema,
+            env!("CARGO_PKG_VERSION"),
+            major(SCHEMA_VERSION),
+            ADVICE
+        );
+    }
+    if minor(&schema) < minor(SCHEMA_VERSION) {
+        debug!(
+            "{} has analysis.json schema {}, which is migrated to {} as it is read",
+            output_path.display(),
+            schema,
+            SCHEMA_VERSION
+        );
+    } else if minor(&schema) > minor(SCHEMA_VERSION) {
+        debug!(
+            "{} has analysis.json schema {} from {}; fields added after {} are ignored",
+            output_path.display(),
+            schema,
+            generator,
+            SCHEMA_VERSION
+        );
+    }
+    Ok(())
+}
+
+const ADVICE: &str = "Run `cda analyze` with this cda to regenerate it (`--force` starts \
+                      over), or `cda export` with the cda that wrote it for its Markdown";
+
+/// Apply the [`MIGRATIONS`] newer than `value`'s schema version to an
+/// analysis.json of this major version. Returns the versions applied.
+pub fn migrate(value: &mut Value) -> Vec<&'static str> {
+    let version = value
+        .get("version")
+        .and_then(Value::as_str)
+        .unwrap_or(SCHEMA_VERSION)
+        .to_string();
+    MIGRATIONS
+        .iter()
+        .filter(|m| minor(&version) < minor(m.version))
+        .map(|m| {
+            (m.apply)(value);
+            m.version
+        })
+        .collect()
+}
+
+/// 1.14: modules record their completeness. Older LLM-analyzed modules are
+/// taken as full, the others as static-only.
+fn add_completeness(value: &mut Value) {
+    let modules = value.get_mut("modules").and_then(Value::as_array_mut);
+    for module in modules.into_iter().flatten() {
+        let Some(module) = module.as_object_mut() else {
+            continue;
+        };
+        if module.contains_key("completeness") {
+            continue;
+        }
+        let deep = module
+            .get("has_deep_analysis")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        module.insert(
+            "completeness".to_string(),
+            Value::from(if deep { "full" } else { "static-only" }),
+        );
+    }
+}
+
+/// 1.32: each external dependency lists the modules importing it, which
+/// older files have in the imports of each module
+fn add_external_dependencies(value: &mut Value) {
+    let mut used_by: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for module in value
+        .get("modules")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        let Some(path) = module.get("path").and_then(Value::as_str) else {
+            continue;
+        };
+        let imports = module.get("imports").and_then(Value::as_array);
+        for import in imports.into_iter().flatten() {
+            if import.get("external").and_then(Value::as_bool) != Some(true) {
+                continue;
+            }
+            // Files from before 1.3 list import sources rather than packages
+            let Some(source) = import.get("source").and_then(Value::as_str) else {
+                continue;
+            };
+            for package in [source, package_name(source)] {
+                used_by
+                    .entry(package.to_string())
+                    .or_default()
+                    .insert(path.to_string());
+            }
+        }
+    }
+
+    let Some(crossref) = value
+        .get_mut("cross_reference")
+        .and_then(Value::as_object_mut)
+    else {
+        return;
+    };
+    let packages: Vec<String> = crossref
+        .get("external_deps")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|p| p.as_str().map(String::from))
+        .collect();
+    let dependencies: Vec<Value> = packages
+        .into_iter()
+        .map(|package| {
+            let users = used_by.get(&package).cloned().unwrap_or_default();
+            json!({"package": package, "used_by": users})
+        })
+        .collect();
+    crossref
+        .entry("external_dependencies")
+        .or_insert(Value::Array(dependencies));
+}
+
+/// The analysis.json the manifest lists, or the default one
+fn analysis_path(output_path: &Path, manifest: Option<&Value>) -> PathBuf {
+    manifest
+        .and_then(|m| m.get("artifacts")?.as_array())
+        .into_iter()
+        .flatten()
+        .find(|a| {
+            a.get("type").and_then(Value::as_str) == Some("analysis")
+                && a.get("stale").and_then(Value::as_bool) != Some(true)
+        })
+        .and_then(|a| a.get("path")?.as_str())
+        .map(|path| output_path.join(path))
+        .unwrap_or_else(|| output_path.join("analysis.json"))
+}
+
+fn read_json(path: &Path) -> Result<Option<Value>> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let value = serde_json::from_str(&content)
+        .with_context(|| format!("{} is not valid JSON", path.display()))?;
+    Ok(Some(value))
+}
+
+fn major(version: &str) -> &str {
+    version.split('.').next().unwrap_or("")
+}
+
+fn minor(version: &str) -> u32 {
+    version
+        .split('.')
+        .nth(1)
+        .and_then(|m| m.parse().ok())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_adds_completeness() {
+        let mut value = json!({
+            "version": "1.13",
+            "modules": [
+                {"path": "a.rs", "has_deep_analysis": true},
+                {"path": "b.rs", "has_deep_analysis": false},
+            ],
+        });
+        assert_eq!(migrate(&mut value), ["1.14", "1.32"]);
+        assert_eq!(value["modules"][0]["completeness"], "full");
+        assert_eq!(value["modules"][1]["completeness"], "static-only");
+
+        let mut current = json!({"version": SCHEMA_VERSION, "modules": [{"path": "a.rs"}]});
+        assert!(migrate(&mut current).is_empty());
+        assert!(current["modules"][0].get("completeness").is_none());
+    }
+
+    #[test]
+    fn test_migrate_adds_external_dependencies() {
+        let mut value = json!({
+            "version": "1.31",
+            "modules": [
+                {"path": "a.ts", "imports": [
+                    {"source": "lodash/fp", "items": [], "external": true},
+                    {"source": "./b", "items": [], "external": false},
+                ]},
+                {"path": "b.ts", "imports": [
+                    {"source": "lodash", "items": ["map"], "external": true},
+                ]},
+            ],
+            "cross_reference": {"external_deps": ["lodash", "react"]},
+        });
+        assert_eq!(migrate(&mut value), ["1.32"]);
+        assert_eq!(
+            value["cross_reference"]["external_dependencies"],
+            json!([
+                {"package": "lodash", "used_by": ["a.ts", "b.ts"]},
+                {"package": "react", "used_by": []},
+            ])
+        );
+    }
+
+    #[test]
+    fn test_check_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        assert!(check(root).is_ok());
+
+        fs::write(root.join("analysis.json"), r#"{"version": "1.2"}"#).unwrap();
+        assert!(check(root).is_ok());
+
+        fs::write(root.join("analysis.json"), r#"{"version": "2.0"}"#).unwrap();
+        let err = check(root).unwrap_err().to_string();
+        assert!(err.contains("an unknown version of cda"), "{}", err);
+        assert!(err.contains("schema 2.0"), "{}", err);
+        assert!(err.contains("reads schema 1.x"), "{}", err);
+
+        // The manifest's schema is trusted over reading analysis.json
+        fs::write(
+            root.join(MANIFEST_FILE),
+            r#"{"version": "1", "generator": "cda 9.0.0", "schema": "1.30", "artifacts": []}"#,
+        )
+        .unwrap();
+        assert!(check(root).is_ok());
+
+        fs::write(
+            root.join(MANIFEST_FILE),
+            r#"{"version": "2", "generator": "cda 9.0.0", "artifacts": []}"#,
+        )
+        .unwrap();
+        let err = check(root).unwrap_err().to_string();
+        assert!(
+            err.contains("written by cda 9.0.0 with manifest.json version 2"),
+            "{}",
+            err
+        );
+        assert!(err.contains("cda export"), "{}", err);
+    }
+}
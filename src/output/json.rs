@@ -1,84 +1,619 @@
-use anyhow::Result;
-use serde::Serialize;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::Path;
+use tracing::debug;
 
-use crate::core::analyzer::{ExportKind, GapKind};
-use crate::core::{Analysis, CrossReference};
+use super::compat;
+use crate::core::analyzer::{
+    Completeness, Edge, Export, ExportKind, FunctionSignature, Gap, GapKind, Import, ImportKind,
+    ModuleAnalysis, ModuleUsage, PriorModule, RunWarning, SampleInfo, Severity, Visibility,
+};
+use crate::core::behaviors::Behavior;
+use crate::core::ci::Pipeline;
+use crate::core::examples::Example;
+use crate::core::features::{DeclaredFeature, FeatureFlag, Flag};
+use crate::core::integrations::Integration;
+use crate::core::manifests::{ApiSpec, HelmChart, KubernetesManifest};
+use crate::core::ownership::{self, Ownership};
+use crate::core::project_files::{ConfigFile, DocFile};
+use crate::core::renames::Rename;
+use crate::core::run_commands::RunCommand;
+use crate::core::selection::Selection;
+use crate::core::timings::TimingReport;
+use crate::core::{Analysis, CrossReference, Language};
 
-#[derive(Serialize)]
+/// Version of the analysis.json layout, kept with the types that describe it.
+/// Bump the major version when a change would stop older readers (`cda
+/// export`, `--baseline`) from loading it.
+pub use crate::cda_output::SCHEMA_VERSION;
+
+#[derive(Serialize, Deserialize)]
 struct JsonOutput {
-    version: &'static str,
+    version: String,
+    #[serde(default)]
+    run: JsonRun,
     architecture_overview: Option<String>,
     modules: Vec<JsonModule>,
+    /// Config files with what they configure (`build`, `ci`, ...)
+    #[serde(default)]
+    config_files: Vec<ConfigFile>,
+    /// Documentation files with their titles
+    #[serde(default)]
+    doc_files: Vec<DocFile>,
+    /// Commands that build and run the project, for `verify --run-commands`
+    #[serde(default)]
+    commands: Vec<RunCommand>,
+    /// CI pipelines, their triggers, jobs and commands
+    #[serde(default)]
+    ci: Vec<Pipeline>,
+    /// Kubernetes manifests and the resources each defines
+    #[serde(default)]
+    kubernetes: Vec<KubernetesManifest>,
+    /// Helm charts with their metadata and top-level values
+    #[serde(default)]
+    helm_charts: Vec<HelmChart>,
+    /// OpenAPI and Swagger specs, their operations and handlers
+    #[serde(default)]
+    api_specs: Vec<ApiSpec>,
     cross_reference: JsonCrossRef,
+    /// Derived from the rest, so recomputed rather than read back
+    #[serde(skip_deserializing)]
     statistics: JsonStats,
 }
 
-#[derive(Serialize)]
+/// How the analysis was scoped, so the output can be interpreted on its own
+#[derive(Serialize, Deserialize, Default)]
+struct JsonRun {
+    /// Languages analyzed; empty means all
+    language_filter: Vec<String>,
+    /// Language of the LLM-written prose, when one was configured
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    doc_language: Option<String>,
+    /// Only a sample of the source files was analyzed
+    #[serde(default)]
+    partial: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sample: Option<JsonSample>,
+    /// Per-phase and per-file durations, up to writing the output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timings: Option<TimingReport>,
+    /// Source files left out because their content is binary
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    skipped_files: Vec<String>,
+    /// Source files renamed since the previous run
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    renames: Vec<Rename>,
+    /// Problems that cost part of the output, e.g. the architecture overview
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<RunWarning>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonSample {
+    strategy: String,
+    sampled_files: usize,
+    total_files: usize,
+}
+
+#[derive(Serialize, Deserialize)]
 struct JsonModule {
     path: String,
     language: String,
     summary: String,
     has_deep_analysis: bool,
+    #[serde(default)]
+    is_test_module: bool,
+    /// Set when the file was not valid UTF-8 and was decoded lossily
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    encoding_warning: Option<String>,
+    /// Contributors and owners, with `--ownership`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ownership: Option<Ownership>,
+    /// Set when the file looked minified and only got a synthesized summary
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    skip_reason: Option<String>,
+    /// Version of the prompt behind the LLM analysis
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    prompt_version: Option<u32>,
+    /// Id of the prompt template behind the LLM analysis, e.g. `shell`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    prompt_template: Option<String>,
+    /// The file changed while it was being analyzed; `verify` reports it stale
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    modified_during_analysis: bool,
+    /// Every file of a module made of several, `path` first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    files: Vec<String>,
+    /// `sha256:` hash of the file's content, with CRLF read as LF
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    content_hash: Option<String>,
+    /// How much of the module the docs are based on; always written, and
+    /// added to older files by [`compat::migrate`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    completeness: Option<Completeness>,
+    /// Tests that exercise the module, with the LLM's plain-language
+    /// statement of each in deep runs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    behaviors: Vec<Behavior>,
+    /// The LLM prompt had the summaries of neighboring modules
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    neighbor_context: bool,
+    /// Cargo features (`cargo`) and environment variables (`env`) the
+    /// module's conditions check
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    feature_flags: Vec<Flag>,
+    /// `score`, `factors`, `selected` and `reason` of a deep run whose
+    /// modules `--deep-select` or `--max-llm-calls` chose
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    selection: Option<Selection>,
+    /// `duration_ms`, `calls`, `input_tokens`, `output_tokens` and `model`
+    /// of the LLM analysis
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    llm_usage: Option<ModuleUsage>,
     exports: Vec<JsonExport>,
     imports: Vec<JsonImport>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 struct JsonExport {
     name: String,
     kind: String,
     signature: Option<String>,
     description: String,
     line: usize,
+    /// Only written for items collected with `--include-private`
+    #[serde(default, skip_serializing_if = "Visibility::is_public")]
+    visibility: Visibility,
+    /// Source of the item, with `output.include_snippets`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    snippet: Option<String>,
+    /// File of a multi-file module the item is in, when it isn't `path`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    file: Option<String>,
+    /// `params` (`name`, `type`), `return_type` and `generics` of a Rust or
+    /// TypeScript function; null where only the raw `signature` is known
+    #[serde(default)]
+    function_signature: Option<FunctionSignature>,
+    /// Condition the item is compiled or run under, e.g. `feature = "x"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cfg: Option<String>,
+    /// Uses of the item elsewhere in the codebase: `path`, `line` and
+    /// `snippet`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    examples: Vec<Example>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 struct JsonImport {
     source: String,
     items: Vec<String>,
     external: bool,
+    /// `type-only`, `re-export` or `side-effect`; left out for ordinary imports
+    #[serde(default, skip_serializing_if = "ImportKind::is_runtime")]
+    kind: ImportKind,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 struct JsonCrossRef {
     dependencies: Vec<JsonDependency>,
+    /// Export-level dependencies: `from_module`, `from_export` (optional),
+    /// `to_module`, `to_export` (optional) and `kind`, one of `import`,
+    /// `call`, `re-export`, `type-only` or `side-effect`
+    #[serde(default)]
+    edges: Vec<Edge>,
     external_deps: Vec<String>,
+    /// `external_deps` with the modules importing each (`used_by`)
+    #[serde(default)]
+    external_dependencies: Vec<JsonExternalDependency>,
+    /// External dependencies grouped by the service they connect to:
+    /// `package`, `category`, `service` (optional), the `modules` using it
+    /// and `inferred` when the LLM classified it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    integrations: Vec<Integration>,
+    /// Feature flags: `kind`, `name`, the Cargo.toml declaring it
+    /// (`declared_in`), what it `enables`, and the `modules` and `exports`
+    /// it gates
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    features: Vec<FeatureFlag>,
     gaps: Vec<JsonGap>,
+    /// Gap counts per owner, `unassigned` first; only when gaps were
+    /// attributed to owners
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    gaps_by_owner: Vec<JsonOwnerGaps>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 struct JsonDependency {
     module: String,
     depends_on: Vec<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
+struct JsonExternalDependency {
+    package: String,
+    used_by: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
 struct JsonGap {
     kind: String,
+    /// Missing before 1.21: the kind's severity then
+    #[serde(default)]
+    severity: Option<Severity>,
     description: String,
     location: Option<String>,
+    /// Team owning the gap's file, from CODEOWNERS or `[owners]`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    owner: Option<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
+struct JsonOwnerGaps {
+    owner: String,
+    gaps: usize,
+    /// Gaps per severity name
+    by_severity: BTreeMap<String, usize>,
+    /// Gaps per kind name
+    by_kind: BTreeMap<String, usize>,
+}
+
+#[derive(Serialize, Default)]
 struct JsonStats {
     total_modules: usize,
+    /// Exports of non-test modules
     total_exports: usize,
+    test_modules: usize,
     external_dependencies: usize,
     potential_gaps: usize,
     llm_analyzed_modules: usize,
+    /// The modules whose LLM analysis took the most tokens, then calls and
+    /// time, at most [`EXPENSIVE_MODULES`]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    most_expensive_modules: Vec<JsonModuleCost>,
 }
 
-pub fn generate(analysis: &Analysis, crossref: &CrossReference, output_path: &Path) -> Result<()> {
-    let llm_analyzed = analysis
-        .modules
+#[derive(Serialize)]
+struct JsonModuleCost {
+    path: String,
+    #[serde(flatten)]
+    usage: ModuleUsage,
+}
+
+/// Length of `statistics.most_expensive_modules`
+const EXPENSIVE_MODULES: usize = 10;
+
+const EXPORT_KINDS: &[(ExportKind, &str)] = &[
+    (ExportKind::Function, "function"),
+    (ExportKind::Class, "class"),
+    (ExportKind::Type, "type"),
+    (ExportKind::Const, "const"),
+    (ExportKind::Enum, "enum"),
+    (ExportKind::Trait, "trait"),
+    (ExportKind::Struct, "struct"),
+    (ExportKind::Module, "module"),
+    (ExportKind::Static, "static"),
+    (ExportKind::Union, "union"),
+];
+
+fn export_kind_name(kind: ExportKind) -> &'static str {
+    EXPORT_KINDS
         .iter()
-        .filter(|m| m.has_deep_analysis)
-        .count();
+        .find(|(k, _)| *k == kind)
+        .map(|(_, name)| *name)
+        .unwrap_or("unknown")
+}
+
+/// The JSON of the analysis.json at `path`, after checking its schema
+/// version and migrating it to the current one
+fn read_versioned(path: &Path) -> Result<serde_json::Value> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut value: serde_json::Value = serde_json::from_str(&content)
+        .with_context(|| format!("{} is not valid JSON", path.display()))?;
+
+    // Check the version before the layout, so a mismatch gets a useful error
+    let version = value
+        .get("version")
+        .and_then(|v| v.as_str())
+        .with_context(|| {
+            format!(
+            "{} has no schema version; is it an analysis.json from `cda analyze --format json`?",
+            path.display()
+        )
+        })?;
+    let major = |v: &str| v.split('.').next().unwrap_or("").to_string();
+    if major(version) != major(SCHEMA_VERSION) {
+        anyhow::bail!(
+            "{} uses analysis.json schema version {}, but this cda reads version {}.x. \
+             Re-run `cda analyze --format json` with this version of cda to regenerate it",
+            path.display(),
+            version,
+            major(SCHEMA_VERSION)
+        );
+    }
+    for version in compat::migrate(&mut value) {
+        debug!("Migrated {} to schema {}", path.display(), version);
+    }
+    Ok(value)
+}
+
+/// Read an analysis.json written by [`generate`] back into the in-memory model
+pub fn load(path: &Path) -> Result<(Analysis, CrossReference)> {
+    let value = read_versioned(path)?;
+    let output: JsonOutput = serde_json::from_value(value)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+    let modules = output
+        .modules
+        .into_iter()
+        .map(|m| {
+            let exports = m
+                .exports
+                .into_iter()
+                .map(|e| {
+                    let kind = EXPORT_KINDS
+                        .iter()
+                        .find(|(_, name)| *name == e.kind)
+                        .map(|(k, _)| *k)
+                        .with_context(|| {
+                            format!("Unknown export kind `{}` in {}", e.kind, m.path)
+                        })?;
+                    Ok(Export {
+                        name: e.name,
+                        kind,
+                        signature: e.signature,
+                        description: e.description,
+                        line_number: e.line,
+                        end_line: None,
+                        snippet: e.snippet,
+                        visibility: e.visibility,
+                        file: e.file,
+                        function_signature: e.function_signature,
+                        cfg: e.cfg,
+                        examples: e.examples,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok(ModuleAnalysis {
+                language: Language::from_name(&m.language),
+                exports,
+                imports: m
+                    .imports
+                    .into_iter()
+                    .map(|i| Import {
+                        source: i.source,
+                        items: i.items,
+                        is_external: i.external,
+                        kind: i.kind,
+                    })
+                    .collect(),
+                summary: m.summary,
+                has_deep_analysis: m.has_deep_analysis,
+                is_test_module: m.is_test_module,
+                encoding_warning: m.encoding_warning,
+                ownership: m.ownership,
+                skip_reason: m.skip_reason,
+                prompt_version: m.prompt_version,
+                prompt_template: m.prompt_template,
+                modified_during_analysis: m.modified_during_analysis,
+                path: m.path,
+                files: m.files,
+                content_hash: m.content_hash,
+                completeness: m.completeness.unwrap_or_default(),
+                behaviors: m.behaviors,
+                neighbor_context: m.neighbor_context,
+                flags: m.feature_flags,
+                selection: m.selection,
+                llm_usage: m.llm_usage,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let analysis = Analysis {
+        modules,
+        language_filter: output
+            .run
+            .language_filter
+            .iter()
+            .map(|name| Language::from_name(name))
+            .collect(),
+        doc_language: output.run.doc_language,
+        sample: output.run.sample.map(|s| SampleInfo {
+            strategy: s.strategy,
+            sampled: s.sampled_files,
+            total: s.total_files,
+        }),
+        timings: output.run.timings,
+        skipped_files: output.run.skipped_files,
+        config_files: output.config_files,
+        doc_files: output.doc_files,
+        renames: output.run.renames,
+        commands: output.commands,
+        declared_features: output
+            .cross_reference
+            .features
+            .iter()
+            .filter_map(|flag| {
+                Some(DeclaredFeature {
+                    name: flag.name.clone(),
+                    manifest: flag.declared_in.clone()?,
+                    enables: flag.enables.clone(),
+                })
+            })
+            .collect(),
+        ci: output.ci,
+        kubernetes: output.kubernetes,
+        helm_charts: output.helm_charts,
+        api_specs: output.api_specs,
+        warnings: output.run.warnings,
+    };
+
+    let gaps = output
+        .cross_reference
+        .gaps
+        .into_iter()
+        .map(|g| {
+            let kind = GapKind::from_name(&g.kind)
+                .with_context(|| format!("Unknown gap kind `{}`", g.kind))?;
+            Ok(Gap {
+                kind,
+                severity: g.severity.unwrap_or_else(|| kind.default_severity()),
+                description: g.description,
+                location: g.location,
+                owner: g.owner,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let crossref = CrossReference {
+        dependencies: output
+            .cross_reference
+            .dependencies
+            .into_iter()
+            .map(|d| (d.module, d.depends_on))
+            .collect(),
+        edges: output.cross_reference.edges,
+        gaps,
+        external_deps: output.cross_reference.external_deps,
+        external_users: output
+            .cross_reference
+            .external_dependencies
+            .into_iter()
+            .map(|d| (d.package, d.used_by))
+            .collect(),
+        integrations: output.cross_reference.integrations,
+        features: output.cross_reference.features,
+        architecture_overview: output.architecture_overview,
+    };
+
+    Ok((analysis, crossref))
+}
+
+#[derive(Deserialize)]
+struct PriorFile {
+    modules: Vec<PriorEntry>,
+}
+
+#[derive(Deserialize)]
+struct PriorCrossRef {
+    cross_reference: PriorDependencies,
+}
+
+#[derive(Deserialize)]
+struct PriorDependencies {
+    dependencies: Vec<PriorDependency>,
+}
+
+#[derive(Deserialize)]
+struct PriorDependency {
+    module: String,
+    depends_on: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct PriorEntry {
+    path: String,
+    summary: String,
+    has_deep_analysis: bool,
+    #[serde(default)]
+    prompt_version: Option<u32>,
+    #[serde(default)]
+    prompt_template: Option<String>,
+    #[serde(default)]
+    content_hash: Option<String>,
+    #[serde(default)]
+    completeness: Option<Completeness>,
+}
+
+/// Module summaries from an earlier `analysis.json` in `output_path`, keyed
+/// by path. Empty when there is no earlier JSON output.
+pub fn load_prior(output_path: &Path) -> Result<HashMap<String, PriorModule>> {
+    let path = output_path.join("analysis.json");
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+
+    let content = fs::read_to_string(&path)?;
+    let mut value: serde_json::Value = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    compat::migrate(&mut value);
+    let file: PriorFile = serde_json::from_value(value)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+    Ok(file
+        .modules
+        .into_iter()
+        .map(|m| {
+            (
+                m.path,
+                PriorModule {
+                    summary: m.summary,
+                    has_deep_analysis: m.has_deep_analysis,
+                    prompt_version: m.prompt_version,
+                    prompt_template: m.prompt_template,
+                    content_hash: m.content_hash,
+                    completeness: m.completeness.unwrap_or_default(),
+                },
+            )
+        })
+        .collect())
+}
+
+/// Dependency graph from an earlier `analysis.json` in `output_path`, if any
+pub fn load_prior_dependencies(
+    output_path: &Path,
+) -> Result<Option<BTreeMap<String, Vec<String>>>> {
+    let path = output_path.join("analysis.json");
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let file: PriorCrossRef = serde_json::from_value(read_versioned(&path)?)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+    Ok(Some(
+        file.cross_reference
+            .dependencies
+            .into_iter()
+            .map(|d| (d.module, d.depends_on))
+            .collect(),
+    ))
+}
+
+/// `items` sorted, without duplicates
+fn sorted_unique(items: &[String]) -> Vec<String> {
+    let mut items = items.to_vec();
+    items.sort();
+    items.dedup();
+    items
+}
+
+pub fn generate(analysis: &Analysis, crossref: &CrossReference, output_path: &Path) -> Result<()> {
+    let llm_analyzed = analysis.llm_modules();
+    let external_deps = sorted_unique(&crossref.external_deps);
 
     let output = JsonOutput {
-        version: "1.0",
+        version: SCHEMA_VERSION.to_string(),
+        run: JsonRun {
+            language_filter: analysis
+                .language_filter
+                .iter()
+                .map(|l| l.name().to_string())
+                .collect(),
+            doc_language: analysis.doc_language.clone(),
+            partial: analysis.sample.is_some(),
+            sample: analysis.sample.as_ref().map(|s| JsonSample {
+                strategy: s.strategy.clone(),
+                sampled_files: s.sampled,
+                total_files: s.total,
+            }),
+            timings: analysis.timings.clone(),
+            skipped_files: analysis.skipped_files.clone(),
+            renames: analysis.renames.clone(),
+            warnings: analysis.warnings.clone(),
+        },
         architecture_overview: crossref.architecture_overview.clone(),
         modules: analysis
             .modules
@@ -88,25 +623,36 @@ pub fn generate(analysis: &Analysis, crossref: &CrossReference, output_path: &Pa
                 language: format!("{:?}", m.language),
                 summary: m.summary.clone(),
                 has_deep_analysis: m.has_deep_analysis,
+                is_test_module: m.is_test_module,
+                encoding_warning: m.encoding_warning.clone(),
+                ownership: m.ownership.clone(),
+                skip_reason: m.skip_reason.clone(),
+                prompt_version: m.prompt_version,
+                prompt_template: m.prompt_template.clone(),
+                modified_during_analysis: m.modified_during_analysis,
+                files: m.files.clone(),
+                content_hash: m.content_hash.clone(),
+                completeness: Some(m.completeness),
+                behaviors: m.behaviors.clone(),
+                neighbor_context: m.neighbor_context,
+                feature_flags: m.flags.clone(),
+                selection: m.selection.clone(),
+                llm_usage: m.llm_usage.clone(),
                 exports: m
                     .exports
                     .iter()
                     .map(|e| JsonExport {
                         name: e.name.clone(),
-                        kind: match e.kind {
-                            ExportKind::Function => "function",
-                            ExportKind::Class => "class",
-                            ExportKind::Type => "type",
-                            ExportKind::Const => "const",
-                            ExportKind::Enum => "enum",
-                            ExportKind::Trait => "trait",
-                            ExportKind::Struct => "struct",
-                            ExportKind::Module => "module",
-                        }
-                        .to_string(),
+                        kind: export_kind_name(e.kind).to_string(),
                         signature: e.signature.clone(),
                         description: e.description.clone(),
                         line: e.line_number,
+                        visibility: e.visibility,
+                        snippet: e.snippet.clone(),
+                        file: e.file.clone(),
+                        function_signature: e.function_signature.clone(),
+                        cfg: e.cfg.clone(),
+                        examples: e.examples.clone(),
                     })
                     .collect(),
                 imports: m
@@ -116,43 +662,90 @@ pub fn generate(analysis: &Analysis, crossref: &CrossReference, output_path: &Pa
                         source: i.source.clone(),
                         items: i.items.clone(),
                         external: i.is_external,
+                        kind: i.kind,
                     })
                     .collect(),
             })
             .collect(),
+        config_files: analysis.config_files.clone(),
+        doc_files: analysis.doc_files.clone(),
+        commands: analysis.commands.clone(),
+        ci: analysis.ci.clone(),
+        kubernetes: analysis.kubernetes.clone(),
+        helm_charts: analysis.helm_charts.clone(),
+        api_specs: analysis.api_specs.clone(),
         cross_reference: JsonCrossRef {
+            // Sorted and deduplicated so the same analysis always writes the
+            // same file, whoever built the cross-reference
             dependencies: crossref
                 .dependencies
                 .iter()
                 .map(|(k, v)| JsonDependency {
                     module: k.clone(),
-                    depends_on: v.clone(),
+                    depends_on: sorted_unique(v),
                 })
                 .collect(),
-            external_deps: crossref.external_deps.clone(),
+            edges: crossref.edges.clone(),
+            external_deps: external_deps.clone(),
+            external_dependencies: external_deps
+                .iter()
+                .map(|package| JsonExternalDependency {
+                    package: package.clone(),
+                    used_by: crossref
+                        .external_users
+                        .get(package)
+                        .map(|users| sorted_unique(users))
+                        .unwrap_or_default(),
+                })
+                .collect(),
+            integrations: crossref.integrations.clone(),
+            features: crossref.features.clone(),
             gaps: crossref
                 .gaps
                 .iter()
                 .map(|g| JsonGap {
-                    kind: match g.kind {
-                        GapKind::UnusedExport => "unused_export",
-                        GapKind::MissingDocumentation => "missing_docs",
-                        GapKind::DeadCode => "dead_code",
-                        GapKind::UntestedFunction => "untested",
-                        GapKind::UndocumentedCommand => "undocumented_command",
-                    }
-                    .to_string(),
+                    kind: g.kind.name().to_string(),
+                    severity: Some(g.severity),
                     description: g.description.clone(),
                     location: g.location.clone(),
+                    owner: g.owner.clone(),
+                })
+                .collect(),
+            gaps_by_owner: ownership::gaps_by_owner(&crossref.gaps)
+                .into_iter()
+                .map(|(owner, gaps)| {
+                    let mut by_severity = BTreeMap::new();
+                    let mut by_kind = BTreeMap::new();
+                    for gap in &gaps {
+                        *by_severity
+                            .entry(gap.severity.name().to_string())
+                            .or_default() += 1;
+                        *by_kind.entry(gap.kind.name().to_string()).or_default() += 1;
+                    }
+                    JsonOwnerGaps {
+                        owner: owner.to_string(),
+                        gaps: gaps.len(),
+                        by_severity,
+                        by_kind,
+                    }
                 })
                 .collect(),
         },
         statistics: JsonStats {
             total_modules: analysis.modules.len(),
             total_exports: analysis.total_exports(),
-            external_dependencies: crossref.external_deps.len(),
+            test_modules: analysis.test_modules(),
+            external_dependencies: external_deps.len(),
             potential_gaps: crossref.gaps.len(),
             llm_analyzed_modules: llm_analyzed,
+            most_expensive_modules: analysis
+                .most_expensive_modules(EXPENSIVE_MODULES)
+                .into_iter()
+                .map(|(m, usage)| JsonModuleCost {
+                    path: m.path.clone(),
+                    usage: usage.clone(),
+                })
+                .collect(),
         },
     };
 
@@ -162,3 +755,337 @@ pub fn generate(analysis: &Analysis, crossref: &CrossReference, output_path: &Pa
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cda_output::OutputDocument;
+    use crate::core::analyzer::{EdgeKind, Param, WarningKind};
+    use crate::core::features::{FlagKind, GatedExport};
+    use crate::core::integrations::Category;
+    use crate::core::project_files::ConfigKind;
+    use crate::core::run_commands::CommandKind;
+
+    fn read(path: &Path) -> serde_json::Value {
+        serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let analysis = Analysis {
+            modules: vec![ModuleAnalysis {
+                path: "/repo/src/lib.rs".into(),
+                language: Language::Rust,
+                exports: vec![
+                    Export {
+                        name: "parse".into(),
+                        kind: ExportKind::Function,
+                        signature: Some("pub fn parse(s: &str)".into()),
+                        description: "".into(),
+                        line_number: 3,
+                        end_line: None,
+                        snippet: Some("pub fn parse(s: &str) {}".into()),
+                        visibility: Visibility::Public,
+                        file: None,
+                        function_signature: Some(FunctionSignature {
+                            params: vec![Param {
+                                name: "s".into(),
+                                ty: Some("&str".into()),
+                            }],
+                            return_type: None,
+                            generics: vec![],
+                        }),
+                        cfg: None,
+                        examples: Vec::new(),
+                    },
+                    Export {
+                        name: "tokenize".into(),
+                        kind: ExportKind::Function,
+                        signature: Some("fn tokenize(s: &str)".into()),
+                        description: "".into(),
+                        line_number: 9,
+                        end_line: None,
+                        snippet: None,
+                        visibility: Visibility::Private,
+                        file: Some("/repo/src/lex.rs".into()),
+                        function_signature: None,
+                        cfg: Some("feature = \"lex\"".into()),
+                        examples: Vec::new(),
+                    },
+                ],
+                imports: vec![Import {
+                    source: "serde".into(),
+                    items: vec!["Deserialize".into()],
+                    is_external: true,
+                    kind: ImportKind::Runtime,
+                }],
+                summary: "**Purpose**: Parses things — deeply.".into(),
+                has_deep_analysis: true,
+                is_test_module: false,
+                encoding_warning: Some("not valid UTF-8".into()),
+                ownership: Some(Ownership {
+                    contributors: vec![crate::core::ownership::Contributor {
+                        name: "Ada".into(),
+                        email: "ada@example.com".into(),
+                        commits: 3,
+                    }],
+                    last_modified: Some("2024-05-01".into()),
+                    owners: vec!["@org/core".into()],
+                }),
+                skip_reason: Some("looks minified".into()),
+                prompt_version: Some(2),
+                prompt_template: Some("generic".into()),
+                modified_during_analysis: false,
+                files: vec!["/repo/src/lib.rs".into(), "/repo/src/lex.rs".into()],
+                content_hash: Some("sha256:00ff".into()),
+                completeness: Completeness::Truncated,
+                behaviors: vec![serde_json::from_value(serde_json::json!({
+                    "test": "rejects_empty",
+                    "path": "/repo/src/lib.rs",
+                    "line": 40,
+                    "statement": "Rejects empty input",
+                }))
+                .unwrap()],
+                neighbor_context: false,
+                flags: vec![Flag::new(FlagKind::Cargo, "lex")],
+                selection: None,
+                llm_usage: None,
+            }],
+            language_filter: vec![Language::Rust],
+            doc_language: Some("Japanese".into()),
+            sample: Some(SampleInfo {
+                strategy: "largest".into(),
+                sampled: 1,
+                total: 9,
+            }),
+            timings: Some(TimingReport::default()),
+            skipped_files: vec!["assets/blob.ts".into()],
+            config_files: vec![ConfigFile {
+                path: "docker-compose.yml".into(),
+                kind: ConfigKind::Container,
+                detail: Some("2 services".into()),
+                summary: None,
+            }],
+            doc_files: vec![DocFile {
+                path: "docs/design.md".into(),
+                title: Some("Design".into()),
+            }],
+            renames: vec![Rename {
+                from: "/repo/src/old.rs".into(),
+                to: "/repo/src/lib.rs".into(),
+                exact: true,
+            }],
+            declared_features: vec![DeclaredFeature {
+                name: "lex".into(),
+                manifest: "Cargo.toml".into(),
+                enables: vec![],
+            }],
+            commands: vec![RunCommand {
+                command: "npm run dev".into(),
+                kind: CommandKind::NpmScript,
+                source: "web/package.json".into(),
+                description: Some("Runs `vite`".into()),
+                inferred: false,
+                in_ci: true,
+            }],
+            ci: vec![serde_json::from_value(serde_json::json!({
+                "path": ".github/workflows/ci.yml",
+                "system": "github-actions",
+                "triggers": ["push"],
+                "jobs": [{"name": "test", "commands": ["npm run dev"]}],
+            }))
+            .unwrap()],
+            kubernetes: vec![],
+            helm_charts: vec![],
+            api_specs: vec![serde_json::from_value(serde_json::json!({
+                "path": "api/openapi.yaml",
+                "format": "OpenAPI 3.0.3",
+                "operations": [{
+                    "method": "GET",
+                    "path": "/items/{id}",
+                    "operation_id": "parse",
+                    "handler": {
+                        "file": "/repo/src/lib.rs",
+                        "line": 3,
+                        "name": "parse",
+                        "matched_by": "operation_id",
+                    },
+                }],
+            }))
+            .unwrap()],
+            warnings: vec![RunWarning::new(
+                WarningKind::OverviewMissing,
+                "The architecture overview could not be generated: overloaded",
+            )],
+        };
+        let crossref = CrossReference {
+            dependencies: BTreeMap::from([
+                (
+                    "/repo/src/main.rs".to_string(),
+                    vec![
+                        "/repo/src/util.rs".to_string(),
+                        "/repo/src/lib.rs".to_string(),
+                        "/repo/src/util.rs".to_string(),
+                    ],
+                ),
+                ("/repo/src/lib.rs".to_string(), vec![]),
+            ]),
+            edges: vec![Edge {
+                from_module: "/repo/src/main.rs".into(),
+                from_export: None,
+                to_module: "/repo/src/lib.rs".into(),
+                to_export: Some("parse".into()),
+                kind: EdgeKind::ReExport,
+            }],
+            gaps: vec![Gap {
+                kind: GapKind::MissingDocumentation,
+                severity: Severity::Info,
+                description: "Public fn `parse` has no documentation".into(),
+                location: Some("/repo/src/lib.rs:3".into()),
+                owner: None,
+            }],
+            external_deps: vec!["serde".into()],
+            external_users: BTreeMap::from([(
+                "serde".to_string(),
+                vec!["/repo/src/lib.rs".to_string()],
+            )]),
+            integrations: vec![Integration {
+                package: "reqwest".into(),
+                category: Category::HttpClient,
+                service: None,
+                modules: vec!["/repo/src/lib.rs".into()],
+                inferred: true,
+            }],
+            features: vec![FeatureFlag {
+                kind: FlagKind::Cargo,
+                name: "lex".into(),
+                declared_in: Some("Cargo.toml".into()),
+                enables: vec![],
+                modules: vec!["/repo/src/lib.rs".into()],
+                exports: vec![GatedExport {
+                    module: "/repo/src/lib.rs".into(),
+                    export: "tokenize".into(),
+                    cfg: "feature = \"lex\"".into(),
+                }],
+            }],
+            architecture_overview: Some("Overview".into()),
+        };
+
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        generate(&analysis, &crossref, first.path()).unwrap();
+        let (loaded, loaded_crossref) = load(&first.path().join("analysis.json")).unwrap();
+        generate(&loaded, &loaded_crossref, second.path()).unwrap();
+
+        assert_eq!(loaded.modules[0].summary, analysis.modules[0].summary);
+        assert_eq!(loaded.modules[0].exports[1].visibility, Visibility::Private);
+        let json = read(&first.path().join("analysis.json"));
+        // The consumer types read back every field the generator writes
+        let typed = OutputDocument::from_path(first.path().join("analysis.json")).unwrap();
+        assert_eq!(serde_json::to_value(&typed).unwrap(), json);
+        let exports = &json["modules"][0]["exports"];
+        assert!(exports[0].get("visibility").is_none());
+        assert_eq!(exports[0]["snippet"], "pub fn parse(s: &str) {}");
+        assert!(exports[1].get("snippet").is_none());
+        assert_eq!(exports[1]["visibility"], "private");
+        assert!(exports[0].get("file").is_none());
+        assert_eq!(
+            exports[0]["function_signature"]["params"][0]["type"],
+            "&str"
+        );
+        assert!(exports[1]["function_signature"].is_null());
+        assert_eq!(
+            loaded.modules[0].exports[0].function_signature,
+            analysis.modules[0].exports[0].function_signature
+        );
+        assert_eq!(exports[1]["file"], "/repo/src/lex.rs");
+        assert_eq!(loaded.modules[0].files, analysis.modules[0].files);
+        assert_eq!(json["modules"][0]["content_hash"], "sha256:00ff");
+        assert_eq!(json["modules"][0]["completeness"], "truncated");
+        assert_eq!(json["modules"][0]["prompt_template"], "generic");
+        assert_eq!(
+            loaded.modules[0].prompt_template,
+            analysis.modules[0].prompt_template
+        );
+        assert_eq!(loaded.modules[0].completeness, Completeness::Truncated);
+        let behavior = &json["modules"][0]["behaviors"][0];
+        assert_eq!(behavior["statement"], "Rejects empty input");
+        assert!(behavior.get("doc").is_none());
+        assert_eq!(loaded.modules[0].behaviors, analysis.modules[0].behaviors);
+        assert_eq!(loaded.renames, analysis.renames);
+        assert_eq!(json["config_files"][0]["kind"], "container");
+        assert!(json["config_files"][0].get("summary").is_none());
+        assert_eq!(json["doc_files"][0]["title"], "Design");
+        assert_eq!(json["commands"][0]["kind"], "npm-script");
+        assert!(json["commands"][0].get("inferred").is_none());
+        assert_eq!(json["commands"][0]["in_ci"], true);
+        assert_eq!(json["ci"][0]["system"], "github-actions");
+        assert_eq!(loaded.ci, analysis.ci);
+        assert_eq!(json["run"]["doc_language"], "Japanese");
+        assert_eq!(loaded.doc_language, analysis.doc_language);
+        let operation = &json["api_specs"][0]["operations"][0];
+        assert_eq!(operation["handler"]["matched_by"], "operation_id");
+        assert!(operation.get("summary").is_none());
+        assert_eq!(loaded.api_specs, analysis.api_specs);
+        assert_eq!(loaded.commands, analysis.commands);
+        assert_eq!(json["run"]["warnings"][0]["kind"], "overview-missing");
+        assert_eq!(loaded.warnings, analysis.warnings);
+        let edge = &json["cross_reference"]["edges"][0];
+        assert_eq!(edge["kind"], "re-export");
+        assert!(edge.get("from_export").is_none());
+        let integration = &json["cross_reference"]["integrations"][0];
+        assert_eq!(integration["category"], "http-client");
+        assert!(integration.get("service").is_none());
+        assert_eq!(loaded_crossref.integrations, crossref.integrations);
+        assert_eq!(loaded_crossref.external_users, crossref.external_users);
+        assert_eq!(exports[1]["cfg"], "feature = \"lex\"");
+        assert!(exports[0].get("cfg").is_none());
+        assert_eq!(json["modules"][0]["feature_flags"][0]["kind"], "cargo");
+        assert_eq!(loaded.modules[0].flags, analysis.modules[0].flags);
+        let feature = &json["cross_reference"]["features"][0];
+        assert_eq!(feature["declared_in"], "Cargo.toml");
+        assert!(feature.get("enables").is_none());
+        assert_eq!(loaded_crossref.features, crossref.features);
+        assert_eq!(loaded.declared_features, analysis.declared_features);
+        assert_eq!(
+            json["cross_reference"]["external_dependencies"][0]["used_by"][0],
+            "/repo/src/lib.rs"
+        );
+        assert_eq!(
+            json["cross_reference"]["dependencies"],
+            serde_json::json!([
+                {"module": "/repo/src/lib.rs", "depends_on": []},
+                {"module": "/repo/src/main.rs", "depends_on": ["/repo/src/lib.rs", "/repo/src/util.rs"]}
+            ])
+        );
+        assert_eq!(json["cross_reference"]["gaps"][0]["severity"], "info");
+        assert_eq!(loaded_crossref.gaps[0].severity, Severity::Info);
+        assert_eq!(
+            read(&first.path().join("analysis.json")),
+            read(&second.path().join("analysis.json"))
+        );
+    }
+
+    #[test]
+    fn test_load_checks_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("analysis.json");
+
+        fs::write(&path, r#"{"version": "2.0", "modules": []}"#).unwrap();
+        let err = load(&path).unwrap_err().to_string();
+        assert!(err.contains("schema version 2.0"), "{}", err);
+        assert!(err.contains("reads version 1.x"), "{}", err);
+
+        fs::write(&path, r#"{"modules": []}"#).unwrap();
+        let err = load(&path).unwrap_err().to_string();
+        assert!(err.contains("has no schema version"), "{}", err);
+
+        // The previous run's dependency map is read the same way
+        fs::write(&path, r#"{"version": "2.0", "modules": []}"#).unwrap();
+        let err = load_prior_dependencies(dir.path()).unwrap_err().to_string();
+        assert!(err.contains("schema version 2.0"), "{}", err);
+        let older = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/outputs/1.0");
+        let dependencies = load_prior_dependencies(&older).unwrap().unwrap();
+        assert_eq!(dependencies.len(), 4);
+    }
+}
@@ -0,0 +1,483 @@
+//! `manifest.json`: every artifact a run wrote into the output directory
+//!
+//! `cda analyze` writes it last and `cda export` adds to it, so `clean`,
+//! `export`, `serve` and `verify` find artifacts through it rather than by
+//! file name. Artifacts an earlier run listed that the latest one didn't
+//! regenerate stay in it marked `stale` until `cda analyze --prune` removes
+//! them.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Component, Path};
+use std::time::SystemTime;
+use tracing::{debug, info, warn};
+
+use super::json::SCHEMA_VERSION;
+use super::Format;
+use crate::core::analyzer::{self, directory_page_name, module_page_name};
+use crate::core::{orphans, runs, Analysis};
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+pub const MANIFEST_VERSION: &str = "1";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: String,
+    /// Version of cda that wrote it
+    pub generator: String,
+    /// Schema version of the run's analysis.json, when it wrote one; absent
+    /// from manifests of earlier versions of cda
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+    /// UTC, e.g. `2026-10-15T07:57:14Z`
+    pub generated_at: String,
+    /// Id of the run in `runs/index.json`, for runs made with `--tag`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run: Option<String>,
+    /// Absolute path of the analyzed directory; absent from manifests of
+    /// earlier versions of cda
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub root: Option<String>,
+    /// Sorted by path
+    pub artifacts: Vec<Artifact>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Artifact {
+    /// Relative to the output directory, `/`-separated
+    pub path: String,
+    #[serde(rename = "type")]
+    pub kind: ArtifactKind,
+    /// `markdown`, `json` or `text`
+    pub format: String,
+    /// Phase of `cda analyze` that writes it
+    pub phase: String,
+    /// `sha256:<hex>` of the content as written
+    pub hash: String,
+    /// Written by an earlier run and not regenerated by the latest one
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub stale: bool,
+}
+
+/// Serialized in kebab case, e.g. `module-page`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ArtifactKind {
+    /// `CODEBASE.md`
+    Index,
+    /// `analysis.json`
+    Analysis,
+    ModulePage,
+    DirectoryPage,
+    /// Prompt of the architecture overview call
+    OverviewPrompt,
+    /// `.cda-state/progress`
+    Progress,
+    /// `cda-changes.md`, the output removed for source files that are gone
+    Changes,
+    /// `llm-log.md`, the LLM answers that were rejected
+    LlmLog,
+}
+
+impl ArtifactKind {
+    pub fn format(self) -> &'static str {
+        match self {
+            ArtifactKind::Analysis => "json",
+            ArtifactKind::Progress => "text",
+            _ => "markdown",
+        }
+    }
+
+    pub fn phase(self) -> &'static str {
+        match self {
+            ArtifactKind::ModulePage | ArtifactKind::Progress | ArtifactKind::LlmLog => "analysis",
+            ArtifactKind::OverviewPrompt => "cross_reference",
+            _ => "output",
+        }
+    }
+
+    /// Module and directory pages
+    pub fn is_page(self) -> bool {
+        matches!(self, ArtifactKind::ModulePage | ArtifactKind::DirectoryPage)
+    }
+}
+
+impl Manifest {
+    /// The manifest in `output_path`, `None` for output written before there
+    /// were manifests
+    pub fn load(output_path: &Path) -> Result<Option<Self>> {
+        let path = output_path.join(MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path)?;
+        let mut manifest: Self = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        // Artifacts are removed by path, so one outside the output directory
+        // is never trusted
+        manifest.artifacts.retain(|a| {
+            let inside = is_inside(&a.path);
+            if !inside {
+                warn!(
+                    "Ignoring {} in {}: not a path inside the output directory",
+                    a.path,
+                    path.display()
+                );
+            }
+            inside
+        });
+        Ok(Some(manifest))
+    }
+
+    fn save(&self, output_path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        analyzer::write_atomic(&output_path.join(MANIFEST_FILE), json)?;
+        Ok(())
+    }
+
+    /// Artifacts the latest run wrote
+    pub fn current(&self) -> impl Iterator<Item = &Artifact> {
+        self.artifacts.iter().filter(|a| !a.stale)
+    }
+
+    /// The latest run's artifact of `kind`, for the kinds there is one of
+    pub fn find(&self, kind: ArtifactKind) -> Option<&Artifact> {
+        self.current().find(|a| a.kind == kind)
+    }
+
+    /// Module and directory pages it lists, relative to `modules/`
+    pub fn pages(&self) -> Vec<String> {
+        self.artifacts
+            .iter()
+            .filter(|a| a.kind.is_page())
+            .filter_map(|a| a.path.strip_prefix("modules/"))
+            .map(String::from)
+            .collect()
+    }
+}
+
+/// What writing `format` for `analysis` leaves in `output_path`: the index
+/// or analysis.json, directory pages for markdown, the module pages that
+/// exist (deep analysis writes them whatever the format) and `cda-changes.md`
+pub fn outputs(
+    analysis: &Analysis,
+    output_path: &Path,
+    format: Format,
+) -> Vec<(String, ArtifactKind)> {
+    let mut produced = Vec::new();
+    match format {
+        Format::Markdown => {
+            produced.push(("CODEBASE.md".to_string(), ArtifactKind::Index));
+            let mut dirs = BTreeSet::from([""]);
+            for module in &analysis.modules {
+                let mut path = module.path.as_str();
+                while let Some(i) = path.rfind(['/', '\\']) {
+                    path = &path[..i];
+                    dirs.insert(path);
+                }
+            }
+            produced.extend(dirs.into_iter().map(|dir| {
+                let page = format!("modules/{}", directory_page_name(dir));
+                (page, ArtifactKind::DirectoryPage)
+            }));
+        }
+        Format::Json => produced.push(("analysis.json".to_string(), ArtifactKind::Analysis)),
+    }
+    for module in &analysis.modules {
+        let page = format!("modules/{}", module_page_name(&module.path));
+        if output_path.join(&page).is_file() {
+            produced.push((page, ArtifactKind::ModulePage));
+        }
+    }
+    if output_path.join(orphans::CHANGES_FILE).is_file() {
+        produced.push((orphans::CHANGES_FILE.to_string(), ArtifactKind::Changes));
+    }
+    produced
+}
+
+/// Write the manifest of a finished run of `root` that wrote `produced`
+/// (missing files are left out). Artifacts of the previous manifest that are
+/// still there but weren't produced again are kept as stale, or removed with
+/// `prune`.
+pub fn write(
+    output_path: &Path,
+    run: Option<String>,
+    root: Option<&Path>,
+    produced: &[(String, ArtifactKind)],
+    prune: bool,
+) -> Result<Manifest> {
+    let previous = Manifest::load(output_path)?;
+    let mut artifacts = hash_all(output_path, produced)?;
+    let schema = schema_of(&artifacts, None);
+
+    let stale: Vec<Artifact> = previous
+        .into_iter()
+        .flat_map(|m| m.artifacts)
+        .filter(|a| !artifacts.contains_key(&a.path) && output_path.join(&a.path).is_file())
+        .collect();
+    if prune {
+        for artifact in &stale {
+            debug!("Pruning stale {}", artifact.path);
+            remove_artifact(output_path, &artifact.path)?;
+        }
+        if !stale.is_empty() {
+            info!("Pruned {} stale artifacts", stale.len());
+        }
+    } else {
+        if !stale.is_empty() {
+            info!(
+                "{} artifacts from an earlier run were not regenerated and are marked stale in {}; pass --prune to remove them",
+                stale.len(),
+                MANIFEST_FILE
+            );
+        }
+        for artifact in stale {
+            artifacts.insert(
+                artifact.path.clone(),
+                Artifact {
+                    stale: true,
+                    ..artifact
+                },
+            );
+        }
+    }
+
+    let manifest = Manifest {
+        version: MANIFEST_VERSION.to_string(),
+        generator: format!("cda {}", env!("CARGO_PKG_VERSION")),
+        schema,
+        generated_at: runs::iso_timestamp(SystemTime::now()),
+        run,
+        root: root.map(|root| root.display().to_string()),
+        artifacts: artifacts.into_values().collect(),
+    };
+    manifest.save(output_path)?;
+    Ok(manifest)
+}
+
+/// This cda's analysis.json schema if an analysis.json was `written`, else
+/// `previous`
+fn schema_of(written: &BTreeMap<String, Artifact>, previous: Option<String>) -> Option<String> {
+    if written.values().any(|a| a.kind == ArtifactKind::Analysis) {
+        Some(SCHEMA_VERSION.to_string())
+    } else {
+        previous
+    }
+}
+
+/// Add `produced` to the manifest in `output_path`, or replace the entries
+/// for the same paths, and keep the rest as is. Starts a manifest if there
+/// is none.
+pub fn update(output_path: &Path, produced: &[(String, ArtifactKind)]) -> Result<Manifest> {
+    let previous = Manifest::load(output_path)?;
+    let run = previous.as_ref().and_then(|m| m.run.clone());
+    let root = previous.as_ref().and_then(|m| m.root.clone());
+    let written = hash_all(output_path, produced)?;
+    let schema = schema_of(&written, previous.as_ref().and_then(|m| m.schema.clone()));
+    let mut artifacts: BTreeMap<String, Artifact> = previous
+        .into_iter()
+        .flat_map(|m| m.artifacts)
+        .map(|a| (a.path.clone(), a))
+        .collect();
+    artifacts.extend(written);
+
+    let manifest = Manifest {
+        version: MANIFEST_VERSION.to_string(),
+        generator: format!("cda {}", env!("CARGO_PKG_VERSION")),
+        schema,
+        generated_at: runs::iso_timestamp(SystemTime::now()),
+        run,
+        root,
+        artifacts: artifacts.into_values().collect(),
+    };
+    manifest.save(output_path)?;
+    Ok(manifest)
+}
+
+/// Drop `removed` paths (relative to `output_path`) from its manifest
+pub fn forget(output_path: &Path, removed: &[String]) -> Result<()> {
+    let Some(mut manifest) = Manifest::load(output_path)? else {
+        return Ok(());
+    };
+    manifest.artifacts.retain(|a| !removed.contains(&a.path));
+    manifest.save(output_path)
+}
+
+/// `sha256:<hex>` of the file at `path`
+pub fn hash_file(path: &Path) -> Result<String> {
+    let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(format!("sha256:{:x}", Sha256::digest(bytes)))
+}
+
+fn hash_all(
+    output_path: &Path,
+    produced: &[(String, ArtifactKind)],
+) -> Result<BTreeMap<String, Artifact>> {
+    let mut artifacts = BTreeMap::new();
+    for (path, kind) in produced {
+        let file = output_path.join(path);
+        if !file.is_file() {
+            continue;
+        }
+        artifacts.insert(
+            path.clone(),
+            Artifact {
+                path: path.clone(),
+                kind: *kind,
+                format: kind.format().to_string(),
+                phase: kind.phase().to_string(),
+                hash: hash_file(&file)?,
+                stale: false,
+            },
+        );
+    }
+    Ok(artifacts)
+}
+
+/// Whether `path` is relative and names something below the directory it is
+/// joined to: no root, prefix, `.` or `..`
+fn is_inside(path: &str) -> bool {
+    !path.is_empty()
+        && Path::new(path)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+}
+
+/// Remove an artifact, then the directories it leaves empty up to
+/// `output_path`
+fn remove_artifact(output_path: &Path, path: &str) -> Result<()> {
+    let file = output_path.join(path);
+    fs::remove_file(&file)?;
+    let mut dir = file.parent();
+    while let Some(d) = dir.filter(|d| *d != output_path) {
+        if fs::remove_dir(d).is_err() {
+            break;
+        }
+        dir = d.parent();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn produce(root: &Path, files: &[(&str, ArtifactKind)]) -> Vec<(String, ArtifactKind)> {
+        for (path, _) in files {
+            let file = root.join(path);
+            fs::create_dir_all(file.parent().unwrap()).unwrap();
+            fs::write(file, path).unwrap();
+        }
+        files.iter().map(|(p, k)| (p.to_string(), *k)).collect()
+    }
+
+    #[test]
+    fn test_manifest_lists_hashes_and_flags_stale_artifacts() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let first = produce(
+            root,
+            &[
+                ("CODEBASE.md", ArtifactKind::Index),
+                ("modules/src/a.rs.md", ArtifactKind::ModulePage),
+                ("modules/src/gone.rs.md", ArtifactKind::ModulePage),
+                ("missing.json", ArtifactKind::Analysis),
+            ],
+        );
+        fs::remove_file(root.join("missing.json")).unwrap();
+        let manifest = write(root, Some("run-1".into()), None, &first, false).unwrap();
+        let paths: Vec<_> = manifest.artifacts.iter().map(|a| a.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "CODEBASE.md",
+                "modules/src/a.rs.md",
+                "modules/src/gone.rs.md"
+            ]
+        );
+        assert_eq!(manifest.schema, None);
+        let index = manifest.find(ArtifactKind::Index).unwrap();
+        assert_eq!(index.format, "markdown");
+        assert_eq!(index.phase, "output");
+        assert_eq!(index.hash, hash_file(&root.join("CODEBASE.md")).unwrap());
+
+        // The next run no longer writes gone.rs.md
+        let second = &first[..2];
+        let manifest = write(root, None, None, second, false).unwrap();
+        let stale: Vec<_> = manifest.artifacts.iter().filter(|a| a.stale).collect();
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].path, "modules/src/gone.rs.md");
+        assert!(manifest.current().all(|a| a.path != stale[0].path));
+
+        // Stale until pruned, which also removes the file
+        let loaded = Manifest::load(root).unwrap().unwrap();
+        assert_eq!(loaded.artifacts, manifest.artifacts);
+        let manifest = write(root, None, None, second, true).unwrap();
+        assert!(manifest.artifacts.iter().all(|a| !a.stale));
+        assert!(!root.join("modules/src/gone.rs.md").exists());
+        assert!(root.join("modules/src/a.rs.md").exists());
+    }
+
+    #[test]
+    fn test_artifacts_outside_the_output_are_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("out");
+        let victim = dir.path().join("victim");
+        fs::write(&victim, "keep me").unwrap();
+        produce(&root, &[("CODEBASE.md", ArtifactKind::Index)]);
+        let artifact = |path: &str| {
+            serde_json::json!({
+                "path": path,
+                "type": "index",
+                "format": "markdown",
+                "phase": "output",
+                "hash": "sha256:00",
+                "stale": true,
+            })
+        };
+        let manifest = serde_json::json!({
+            "version": MANIFEST_VERSION,
+            "generator": "cda",
+            "generated_at": "2026-10-15T00:00:00Z",
+            "artifacts": [
+                artifact("../victim"),
+                artifact(&victim.display().to_string()),
+                artifact("CODEBASE.md"),
+            ],
+        });
+        fs::write(root.join(MANIFEST_FILE), manifest.to_string()).unwrap();
+
+        let loaded = Manifest::load(&root).unwrap().unwrap();
+        let paths: Vec<_> = loaded.artifacts.iter().map(|a| a.path.as_str()).collect();
+        assert_eq!(paths, ["CODEBASE.md"]);
+
+        // Pruning stale artifacts leaves the file outside alone
+        write(&root, None, None, &[], true).unwrap();
+        assert!(victim.exists());
+    }
+
+    #[test]
+    fn test_update_keeps_other_artifacts() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let markdown = produce(root, &[("CODEBASE.md", ArtifactKind::Index)]);
+        write(root, Some("run-1".into()), None, &markdown, false).unwrap();
+
+        let json = produce(root, &[("analysis.json", ArtifactKind::Analysis)]);
+        let manifest = update(root, &json).unwrap();
+        assert_eq!(manifest.run.as_deref(), Some("run-1"));
+        assert_eq!(manifest.schema.as_deref(), Some(SCHEMA_VERSION));
+        assert!(manifest.find(ArtifactKind::Index).is_some());
+        assert_eq!(
+            manifest.find(ArtifactKind::Analysis).unwrap().format,
+            "json"
+        );
+
+        forget(root, &["CODEBASE.md".to_string()]).unwrap();
+        let manifest = Manifest::load(root).unwrap().unwrap();
+        assert!(manifest.find(ArtifactKind::Index).is_none());
+    }
+}
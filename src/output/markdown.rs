@@ -1,15 +1,27 @@
 use anyhow::Result;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs::File;
 use std::io::Write;
 use std::path::Path;
+use tracing::warn;
 
-use crate::core::analyzer::GapKind;
-use crate::core::{Analysis, CrossReference};
+use crate::core::analyzer::{
+    self, directory_page_name, module_page_name, Completeness, Edge, EdgeKind, GapKind,
+    ModuleAnalysis, Severity, WarningKind,
+};
+use crate::core::features::FlagKind;
+use crate::core::{ownership, Analysis, CrossReference};
 
-/// Generate a single CODEBASE.md optimized for LLM consumption
+/// Modules listed for each external dependency in CODEBASE.md
+const EXTERNAL_USERS_SHOWN: usize = 5;
+
+/// Generate CODEBASE.md, a single index optimized for LLM consumption, and a
+/// README.md for every directory of the `modules/` tree
 pub fn generate(analysis: &Analysis, crossref: &CrossReference, output_path: &Path) -> Result<()> {
     std::fs::create_dir_all(output_path)?;
+    write_directory_pages(analysis, output_path)?;
+    write_module_relationships(analysis, crossref, output_path)?;
+    write_module_completeness(analysis, output_path)?;
 
     let codebase_path = output_path.join("CODEBASE.md");
     let mut f = File::create(&codebase_path)?;
@@ -21,69 +33,225 @@ pub fn generate(analysis: &Analysis, crossref: &CrossReference, output_path: &Pa
         "_Generated by [CDA](https://github.com/Bentlybro/codebase-deep-analyzer)_\n"
     )?;
 
-    // Architecture Overview (LLM-generated)
+    if !analysis.language_filter.is_empty() {
+        let names: Vec<_> = analysis.language_filter.iter().map(|l| l.name()).collect();
+        writeln!(
+            f,
+            "_Only {} files were analyzed (`--language {}`)._\n",
+            names.join(", "),
+            names.join(",")
+        )?;
+    }
+
+    if let Some(sample) = &analysis.sample {
+        writeln!(
+            f,
+            "_Partial run: {} of {} source files were analyzed (`--sample {}`)._\n",
+            sample.sampled, sample.total, sample.strategy
+        )?;
+    }
+
+    if !analysis.skipped_files.is_empty() {
+        writeln!(
+            f,
+            "_Skipped {} file(s) with binary content: {}._\n",
+            analysis.skipped_files.len(),
+            analysis
+                .skipped_files
+                .iter()
+                .map(|p| format!("`{}`", p))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )?;
+    }
+
+    let lossy: Vec<_> = analysis
+        .modules
+        .iter()
+        .filter(|m| m.encoding_warning.is_some())
+        .map(|m| format!("`{}`", m.path))
+        .collect();
+    if !lossy.is_empty() {
+        writeln!(
+            f,
+            "_Not valid UTF-8, decoded with replacement characters: {}._\n",
+            lossy.join(", ")
+        )?;
+    }
+
+    let minified: Vec<_> = analysis
+        .modules
+        .iter()
+        .filter(|m| m.skip_reason.is_some())
+        .map(|m| format!("`{}`", m.path))
+        .collect();
+    if !minified.is_empty() {
+        writeln!(
+            f,
+            "_Looked minified, summarized instead of analyzed: {}._\n",
+            minified.join(", ")
+        )?;
+    }
+
+    let modified: Vec<_> = analysis
+        .modules
+        .iter()
+        .filter(|m| m.modified_during_analysis)
+        .map(|m| format!("`{}`", m.path))
+        .collect();
+    if !modified.is_empty() {
+        writeln!(
+            f,
+            "_Modified while being analyzed, so the docs may not match the source: {}._\n",
+            modified.join(", ")
+        )?;
+    }
+
+    if !analysis.renames.is_empty() {
+        let renames: Vec<_> = analysis
+            .renames
+            .iter()
+            .map(|r| format!("`{}` → `{}`", r.from, r.to))
+            .collect();
+        writeln!(
+            f,
+            "_Renamed since the previous run: {}._\n",
+            renames.join(", ")
+        )?;
+    }
+
+    // Architecture Overview (LLM-generated), or why it is missing
     if let Some(overview) = &crossref.architecture_overview {
         writeln!(f, "## Architecture\n")?;
         writeln!(f, "{}\n", overview)?;
+    } else if let Some(warning) = analysis
+        .warnings
+        .iter()
+        .find(|w| w.kind == WarningKind::OverviewMissing)
+    {
+        writeln!(f, "## Architecture\n")?;
+        writeln!(
+            f,
+            "_The architecture overview is missing. {}. `cda analyze --only-overview` \
+             generates it again from the analysis.json of this output; without one, \
+             run `cda analyze` again._\n",
+            warning.message.trim_end_matches('.')
+        )?;
+    }
+
+    // Commands from the config files, grouped by the file they come from
+    if !analysis.commands.is_empty() {
+        writeln!(f, "## Getting Started\n")?;
+        let mut source = None;
+        for command in &analysis.commands {
+            if source != Some(command.source.as_str()) {
+                if source.is_some() {
+                    writeln!(f)?;
+                }
+                source = Some(command.source.as_str());
+                writeln!(f, "### `{}`\n", command.source)?;
+            }
+            let ci = if command.in_ci { " _(run in CI)_" } else { "" };
+            match &command.description {
+                Some(description) => {
+                    writeln!(f, "- `{}` — {}{}", command.command, description, ci)?
+                }
+                None => writeln!(f, "- `{}`{}", command.command, ci)?,
+            }
+        }
+        writeln!(f)?;
+    }
+
+    // CI pipelines, with the commands of each job
+    if !analysis.ci.is_empty() {
+        writeln!(f, "## CI/CD\n")?;
+        for pipeline in &analysis.ci {
+            match &pipeline.name {
+                Some(name) => writeln!(
+                    f,
+                    "### {} (`{}`, {})\n",
+                    name,
+                    pipeline.path,
+                    pipeline.system.label()
+                )?,
+                None => writeln!(f, "### `{}` ({})\n", pipeline.path, pipeline.system.label())?,
+            }
+            if !pipeline.triggers.is_empty() {
+                let triggers: Vec<_> = pipeline
+                    .triggers
+                    .iter()
+                    .map(|t| format!("`{}`", t))
+                    .collect();
+                writeln!(f, "Runs on {}.\n", triggers.join(", "))?;
+            }
+            for job in &pipeline.jobs {
+                let commands: Vec<_> = job.commands.iter().map(|c| format!("`{}`", c)).collect();
+                if commands.is_empty() {
+                    writeln!(f, "- **{}**", job.name)?;
+                } else {
+                    writeln!(f, "- **{}**: {}", job.name, commands.join(", "))?;
+                }
+            }
+            writeln!(f)?;
+        }
     }
 
     // Quick Stats
     writeln!(f, "## Overview\n")?;
     writeln!(f, "- **Modules:** {}", analysis.modules.len())?;
     writeln!(f, "- **Exports:** {}", analysis.total_exports())?;
+    if analysis.test_modules() > 0 {
+        writeln!(f, "- **Test Modules:** {}", analysis.test_modules())?;
+    }
     writeln!(
         f,
         "- **External Dependencies:** {}",
         crossref.external_deps.len()
     )?;
+    let completeness: Vec<String> = Completeness::ALL
+        .iter()
+        .filter_map(|c| {
+            let count = analysis
+                .modules
+                .iter()
+                .filter(|m| m.completeness == *c)
+                .count();
+            (count > 0).then(|| format!("{} {}", count, c.name()))
+        })
+        .collect();
+    if !completeness.is_empty() {
+        writeln!(f, "- **Completeness:** {}", completeness.join(", "))?;
+    }
     writeln!(f)?;
 
-    // Group modules by directory for structure
-    let mut by_dir: HashMap<String, Vec<&crate::core::analyzer::ModuleAnalysis>> = HashMap::new();
-    for module in &analysis.modules {
-        // Get directory path relative to root
-        let dir = Path::new(&module.path)
-            .parent()
-            .map(|p| {
-                // Try to get a reasonable relative path
-                let s = p.to_string_lossy();
-                // Find common prefixes and strip them
-                if let Some(idx) = s.find("/src/") {
-                    s[idx..].to_string()
-                } else if let Some(idx) = s.find("/lib/") {
-                    s[idx..].to_string()
-                } else {
-                    // Just use last 2-3 components
-                    let components: Vec<_> = p.components().collect();
-                    let start = if components.len() > 3 {
-                        components.len() - 3
-                    } else {
-                        0
-                    };
-                    components[start..]
-                        .iter()
-                        .map(|c| c.as_os_str().to_string_lossy())
-                        .collect::<Vec<_>>()
-                        .join("/")
-                }
-            })
-            .unwrap_or_else(|| "root".to_string());
-        by_dir.entry(dir).or_default().push(module);
+    // Group modules by directory for structure; tests get their own section
+    let mut by_dir: HashMap<String, Vec<&ModuleAnalysis>> = HashMap::new();
+    for module in analysis.modules.iter().filter(|m| !m.is_test_module) {
+        by_dir
+            .entry(display_dir(&module.path))
+            .or_default()
+            .push(module);
     }
 
     // Directory Structure
     writeln!(f, "## Directory Structure\n")?;
+    writeln!(
+        f,
+        "Each directory has a page under [modules/]({}).\n",
+        page_link(&directory_page_name(""))
+    )?;
     let mut dirs: Vec<_> = by_dir.keys().collect();
     dirs.sort();
 
     for dir in &dirs {
         let modules = &by_dir[*dir];
-        let export_count: usize = modules.iter().map(|m| m.exports.len()).sum();
+        let export_count: usize = modules.iter().map(|m| m.public_exports().count()).sum();
         if export_count > 0 {
             writeln!(
                 f,
-                "- `{}` — {} files, {} exports",
+                "- [`{}`]({}) — {} files, {} exports",
                 dir,
+                page_link(&directory_page_name(parent_dir(&modules[0].path))),
                 modules.len(),
                 export_count
             )?;
@@ -94,9 +262,233 @@ pub fn generate(analysis: &Analysis, crossref: &CrossReference, output_path: &Pa
     // External Dependencies
     if !crossref.external_deps.is_empty() {
         writeln!(f, "## Dependencies\n")?;
-        writeln!(f, "External packages used:\n")?;
+        writeln!(
+            f,
+            "External packages used, and the modules that import them:\n"
+        )?;
         for dep in &crossref.external_deps {
-            writeln!(f, "- `{}`", dep)?;
+            let users = crossref
+                .external_users
+                .get(dep)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            if users.is_empty() {
+                writeln!(f, "- `{}`", dep)?;
+                continue;
+            }
+            let shown: Vec<_> = users
+                .iter()
+                .take(EXTERNAL_USERS_SHOWN)
+                .map(|p| format!("`{}`", p))
+                .collect();
+            let more = match users.len() - shown.len() {
+                0 => String::new(),
+                n => format!(" +{} more", n),
+            };
+            writeln!(f, "- `{}` — {}{}", dep, shown.join(", "), more)?;
+        }
+        writeln!(f)?;
+    }
+
+    // External dependencies by the service they connect to; sorted by category
+    if !crossref.integrations.is_empty() {
+        writeln!(
+            f,
+            "## Third-party Integrations
+"
+        )?;
+        let mut category = None;
+        for integration in &crossref.integrations {
+            if category != Some(integration.category) {
+                if category.is_some() {
+                    writeln!(f)?;
+                }
+                category = Some(integration.category);
+                writeln!(
+                    f,
+                    "### {}
+",
+                    integration.category.label()
+                )?;
+            }
+            match &integration.service {
+                Some(service) => write!(f, "- **{}** via `{}`", service, integration.package)?,
+                None => write!(f, "- `{}`", integration.package)?,
+            }
+            if integration.inferred {
+                write!(f, " (classified by the LLM)")?;
+            }
+            let shown: Vec<_> = integration
+                .modules
+                .iter()
+                .take(5)
+                .map(|p| format!("`{}`", p))
+                .collect();
+            let more = match integration.modules.len().saturating_sub(shown.len()) {
+                0 => String::new(),
+                n => format!(" and {} more", n),
+            };
+            writeln!(f, " — used by {}{}", shown.join(", "), more)?;
+        }
+        writeln!(f)?;
+    }
+
+    // Config and doc files, classified without the LLM
+    if !analysis.config_files.is_empty() {
+        writeln!(f, "## Configuration\n")?;
+        for file in &analysis.config_files {
+            write!(f, "- `{}` — {}", file.path, file.kind.label())?;
+            if let Some(detail) = &file.detail {
+                write!(f, " ({})", detail)?;
+            }
+            if let Some(summary) = &file.summary {
+                write!(f, ": {}", summary)?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(f)?;
+    }
+
+    // Cargo features and environment flags, with the code each gates
+    if !crossref.features.is_empty() {
+        writeln!(f, "## Feature Flags\n")?;
+        for flag in &crossref.features {
+            match flag.kind {
+                FlagKind::Cargo => write!(f, "- Cargo feature `{}`", flag.name)?,
+                FlagKind::Env => write!(f, "- Environment variable `{}`", flag.name)?,
+            }
+            if let Some(manifest) = &flag.declared_in {
+                write!(f, " (`{}`", manifest)?;
+                if !flag.enables.is_empty() {
+                    let enables: Vec<_> = flag
+                        .enables
+                        .iter()
+                        .take(5)
+                        .map(|e| format!("`{}`", e))
+                        .collect();
+                    write!(f, ", enables {}", enables.join(", "))?;
+                    if flag.enables.len() > enables.len() {
+                        write!(f, " and {} more", flag.enables.len() - enables.len())?;
+                    }
+                }
+                write!(f, ")")?;
+            } else if flag.kind == FlagKind::Cargo {
+                write!(f, " (not declared)")?;
+            }
+            let shown: Vec<_> = flag
+                .modules
+                .iter()
+                .take(5)
+                .map(|p| format!("`{}`", p))
+                .collect();
+            if shown.is_empty() {
+                write!(f, " — checked nowhere")?;
+            } else {
+                write!(f, " — checked in {}", shown.join(", "))?;
+                if flag.modules.len() > shown.len() {
+                    write!(f, " and {} more", flag.modules.len() - shown.len())?;
+                }
+            }
+            if !flag.exports.is_empty() {
+                let gated: Vec<_> = flag
+                    .exports
+                    .iter()
+                    .take(5)
+                    .map(|e| format!("`{}`", e.export))
+                    .collect();
+                write!(f, "; gates {}", gated.join(", "))?;
+                if flag.exports.len() > gated.len() {
+                    write!(f, " and {} more", flag.exports.len() - gated.len())?;
+                }
+            }
+            writeln!(f)?;
+        }
+        writeln!(f)?;
+    }
+
+    if !analysis.kubernetes.is_empty() {
+        writeln!(f, "## Kubernetes\n")?;
+        for manifest in &analysis.kubernetes {
+            let resources: Vec<_> = manifest
+                .resources
+                .iter()
+                .map(|r| {
+                    let mut resource = r.kind.clone();
+                    if let Some(name) = &r.name {
+                        resource.push_str(&format!(" `{}`", name));
+                    }
+                    if let Some(namespace) = &r.namespace {
+                        resource.push_str(&format!(" in `{}`", namespace));
+                    }
+                    resource
+                })
+                .collect();
+            writeln!(f, "- `{}` — {}", manifest.path, resources.join(", "))?;
+        }
+        writeln!(f)?;
+    }
+
+    if !analysis.helm_charts.is_empty() {
+        writeln!(f, "## Helm Charts\n")?;
+        for chart in &analysis.helm_charts {
+            write!(f, "- **{}**", chart.name)?;
+            if let Some(version) = &chart.version {
+                write!(f, " {}", version)?;
+            }
+            if let Some(app_version) = &chart.app_version {
+                write!(f, " (app {})", app_version)?;
+            }
+            write!(f, " — `{}`", chart.path)?;
+            if let Some(description) = &chart.description {
+                write!(f, ": {}", description)?;
+            }
+            write!(f, ". {} templates", chart.templates)?;
+            if !chart.values.is_empty() {
+                let values: Vec<_> = chart.values.iter().map(|v| format!("`{}`", v)).collect();
+                write!(f, "; values: {}", values.join(", "))?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(f)?;
+    }
+
+    // API specs, with the handler found for each operation
+    if !analysis.api_specs.is_empty() {
+        writeln!(f, "## API\n")?;
+        for spec in &analysis.api_specs {
+            let title = match (&spec.title, &spec.version) {
+                (Some(title), Some(version)) => format!("{} {} (", title, version),
+                (Some(title), None) => format!("{} (", title),
+                (None, _) => "(".to_string(),
+            };
+            writeln!(f, "### {}`{}`, {})\n", title, spec.path, spec.format)?;
+            for operation in &spec.operations {
+                write!(f, "- `{} {}`", operation.method, operation.path)?;
+                if let Some(summary) = &operation.summary {
+                    write!(f, " — {}", summary)?;
+                }
+                match &operation.handler {
+                    Some(handler) => {
+                        write!(f, " → `{}:{}`", handler.file, handler.line)?;
+                        if let Some(name) = &handler.name {
+                            write!(f, " (`{}`)", name)?;
+                        }
+                        writeln!(f)?;
+                    }
+                    None => writeln!(f, " — no handler found")?,
+                }
+            }
+            writeln!(f)?;
+        }
+    }
+
+    if !analysis.doc_files.is_empty() {
+        writeln!(f, "## Documentation\n")?;
+        for file in &analysis.doc_files {
+            match &file.title {
+                Some(title) => writeln!(f, "- `{}` — {}", file.path, title)?,
+                None => writeln!(f, "- `{}`", file.path)?,
+            }
         }
         writeln!(f)?;
     }
@@ -113,45 +505,116 @@ pub fn generate(analysis: &Analysis, crossref: &CrossReference, output_path: &Pa
             continue;
         }
 
-        writeln!(f, "### `{}`\n", dir)?;
+        writeln!(
+            f,
+            "### [`{}`]({})\n",
+            dir,
+            page_link(&directory_page_name(parent_dir(&modules[0].path)))
+        )?;
 
         for module in modules {
             if module.exports.is_empty() {
                 continue;
             }
 
-            let filename = Path::new(&module.path)
-                .file_name()
-                .and_then(|s| s.to_str())
-                .unwrap_or("unknown");
-
-            writeln!(f, "#### {}\n", filename)?;
+            let filename = file_name(&module.path);
+            if module.has_deep_analysis {
+                writeln!(
+                    f,
+                    "#### [{}]({})\n",
+                    filename,
+                    page_link(&module_page_name(&module.path))
+                )?;
+            } else {
+                writeln!(f, "#### {}\n", filename)?;
+            }
 
             // Compact export list
-            for export in &module.exports {
+            for export in module.public_exports() {
                 let sig = export.signature.as_deref().unwrap_or("");
                 let desc = if !export.description.is_empty() {
                     format!(" — {}", truncate(&export.description, 80))
                 } else {
                     String::new()
                 };
+                let gate = match &export.cfg {
+                    Some(cfg) => format!(" _(only with `{}`)_", truncate(cfg, 60)),
+                    None => String::new(),
+                };
 
                 if !sig.is_empty() {
-                    writeln!(f, "- `{}`{}", truncate(sig, 60), desc)?;
+                    writeln!(f, "- `{}`{}{}", truncate(sig, 60), gate, desc)?;
                 } else {
-                    writeln!(f, "- `{}` ({}){}", export.name, export.kind, desc)?;
+                    writeln!(f, "- `{}` ({}){}{}", export.name, export.kind, gate, desc)?;
                 }
             }
+
+            // Only present with --include-private
+            let internal: Vec<_> = module
+                .internal_items()
+                .map(|item| format!("`{}`", item.name))
+                .collect();
+            if !internal.is_empty() {
+                writeln!(f, "- Internal: {}", internal.join(", "))?;
+            }
             writeln!(f)?;
         }
     }
 
+    // Test modules (only present with --include-tests)
+    let mut tests: Vec<_> = analysis
+        .modules
+        .iter()
+        .filter(|m| m.is_test_module)
+        .collect();
+    if !tests.is_empty() {
+        tests.sort_by(|a, b| a.path.cmp(&b.path));
+        writeln!(f, "## Tests\n")?;
+
+        for module in tests {
+            let filename = file_name(&module.path);
+            writeln!(
+                f,
+                "- `{}/{}` — {}",
+                display_dir(&module.path),
+                filename,
+                truncate(module.summary.trim_start_matches('#').trim(), 120)
+            )?;
+        }
+        writeln!(f)?;
+    }
+
+    // Owners index (only with --ownership)
+    let mut by_owner: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+    for module in analysis.modules.iter().filter(|m| !m.is_test_module) {
+        if let Some(owner) = module.ownership.as_ref().and_then(|o| o.primary_owner()) {
+            by_owner.entry(owner).or_default().push(&module.path);
+        }
+    }
+    if !by_owner.is_empty() {
+        writeln!(f, "## Owners\n")?;
+        writeln!(
+            f,
+            "From CODEOWNERS, or the top git contributor when no rule matches:\n"
+        )?;
+        for (owner, mut paths) in by_owner {
+            paths.sort();
+            let shown: Vec<_> = paths.iter().take(10).map(|p| format!("`{}`", p)).collect();
+            let more = match paths.len().saturating_sub(shown.len()) {
+                0 => String::new(),
+                n => format!(" and {} more", n),
+            };
+            writeln!(f, "- **{}** — {}{}", owner, shown.join(", "), more)?;
+        }
+        writeln!(f)?;
+    }
+
     // Internal dependency map (simplified)
     writeln!(f, "## Internal Dependencies\n")?;
     writeln!(f, "Key module connections:\n")?;
 
     let mut dep_count: HashMap<&str, usize> = HashMap::new();
-    for module in &analysis.modules {
+    for module in analysis.modules.iter().filter(|m| !m.is_test_module) {
         for import in &module.imports {
             if !import.is_external {
                 *dep_count.entry(&import.source).or_default() += 1;
@@ -161,7 +624,7 @@ pub fn generate(analysis: &Analysis, crossref: &CrossReference, output_path: &Pa
 
     // Show most-imported internal modules
     let mut deps: Vec<_> = dep_count.iter().collect();
-    deps.sort_by(|a, b| b.1.cmp(a.1));
+    deps.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
 
     for (source, count) in deps.iter().take(20) {
         writeln!(f, "- `{}` — imported {} times", source, count)?;
@@ -179,17 +642,97 @@ pub fn generate(analysis: &Analysis, crossref: &CrossReference, output_path: &Pa
             .count();
 
         writeln!(f, "- {} exports missing documentation", missing_docs)?;
-        writeln!(f)?;
 
-        // Show first 10 as examples
-        writeln!(f, "Examples:\n")?;
-        for gap in crossref.gaps.iter().take(10) {
-            if let Some(loc) = &gap.location {
-                writeln!(f, "- `{}`", loc)?;
+        let cycles = crossref
+            .gaps
+            .iter()
+            .filter(|g| matches!(g.kind, GapKind::CircularDependency))
+            .count();
+        if cycles > 0 {
+            writeln!(f, "- {} circular dependencies", cycles)?;
+        }
+
+        let style = crossref
+            .gaps
+            .iter()
+            .filter(|g| {
+                matches!(
+                    g.kind,
+                    GapKind::RestatedDocs | GapKind::UndocumentedParams | GapKind::ShortDocs
+                )
+            })
+            .count();
+        if style > 0 {
+            writeln!(f, "- {} doc comments that fail the `[doc_checks]`", style)?;
+        }
+
+        // Most severe first, up to 10 examples of each
+        for &severity in Severity::ALL {
+            let gaps: Vec<_> = crossref
+                .gaps
+                .iter()
+                .filter(|g| g.severity == severity)
+                .collect();
+            if gaps.is_empty() {
+                continue;
+            }
+            let heading = match severity {
+                Severity::Error => "Errors",
+                Severity::Warning => "Warnings",
+                Severity::Info => "Info",
+            };
+            writeln!(f, "\n### {} ({})\n", heading, gaps.len())?;
+            for gap in gaps.iter().take(10) {
+                if let Some(loc) = &gap.location {
+                    writeln!(f, "- `{}` ({})", loc, gap.kind.name())?;
+                }
+            }
+            if gaps.len() > 10 {
+                writeln!(f, "- ... and {} more", gaps.len() - 10)?;
             }
         }
-        if crossref.gaps.len() > 10 {
-            writeln!(f, "- ... and {} more", crossref.gaps.len() - 10)?;
+    }
+
+    let gaps_by_owner = ownership::gaps_by_owner(&crossref.gaps);
+    if !gaps_by_owner.is_empty() {
+        writeln!(f, "\n## Gaps by Owner\n")?;
+        if let Some((_, unassigned)) = gaps_by_owner
+            .iter()
+            .find(|(o, _)| *o == ownership::UNASSIGNED)
+        {
+            writeln!(
+                f,
+                "**{} gaps are in code nobody owns.** Add CODEOWNERS entries or `[owners]` mappings for them.\n",
+                unassigned.len()
+            )?;
+        }
+        writeln!(f, "| Owner | Gaps | Errors | Warnings | Info |")?;
+        writeln!(f, "|-------|------|--------|----------|------|")?;
+        for (owner, gaps) in &gaps_by_owner {
+            let count = |severity| gaps.iter().filter(|g| g.severity == severity).count();
+            writeln!(
+                f,
+                "| {} | {} | {} | {} | {} |",
+                owner,
+                gaps.len(),
+                count(Severity::Error),
+                count(Severity::Warning),
+                count(Severity::Info)
+            )?;
+        }
+
+        // Most severe first, up to 10 per owner
+        for (owner, gaps) in &gaps_by_owner {
+            writeln!(f, "\n### {} ({})\n", owner, gaps.len())?;
+            for gap in gaps.iter().take(10) {
+                match &gap.location {
+                    Some(loc) => writeln!(f, "- `{}` ({})", loc, gap.kind.name())?,
+                    None => writeln!(f, "- {} ({})", gap.description, gap.kind.name())?,
+                }
+            }
+            if gaps.len() > 10 {
+                writeln!(f, "- ... and {} more", gaps.len() - 10)?;
+            }
         }
     }
 
@@ -202,6 +745,328 @@ pub fn generate(analysis: &Analysis, crossref: &CrossReference, output_path: &Pa
     Ok(())
 }
 
+/// What a directory of the modules tree holds: its files, and the modules
+/// and exports in it and below
+#[derive(Default)]
+struct Listing<'a> {
+    files: Vec<&'a ModuleAnalysis>,
+    subdirectories: BTreeSet<String>,
+    modules: usize,
+    exports: usize,
+}
+
+/// Add "Uses", "Used by", "External dependencies", "Documented behavior" and
+/// "Examples from the codebase" to the module pages `--deep` wrote
+fn write_module_relationships(
+    analysis: &Analysis,
+    crossref: &CrossReference,
+    output_path: &Path,
+) -> Result<()> {
+    let modules_dir = output_path.join("modules");
+    for module in analysis.modules.iter().filter(|m| m.has_deep_analysis) {
+        if !analyzer::module_page_path(&modules_dir, &module.path).exists() {
+            continue;
+        }
+        let mut sections = relationship_sections(&module.path, crossref);
+        sections.push_str(&behavior_section(module));
+        sections.push_str(&example_section(module));
+        analyzer::write_page_relationships(&modules_dir, &module.path, &sections)?;
+    }
+    Ok(())
+}
+
+/// Bring the completeness badge of the module pages `--deep` wrote up to
+/// date: a page can't tell at write time that it was resumed or is stale
+fn write_module_completeness(analysis: &Analysis, output_path: &Path) -> Result<()> {
+    let modules_dir = output_path.join("modules");
+    for module in analysis.modules.iter().filter(|m| m.has_deep_analysis) {
+        if !analyzer::module_page_path(&modules_dir, &module.path).exists() {
+            continue;
+        }
+        analyzer::write_page_completeness(&modules_dir, &module.path, module.completeness)?;
+    }
+    Ok(())
+}
+
+/// What `path` uses and what uses it, by module: the exports involved where
+/// the cross-reference has edges, otherwise just the module
+fn relationship_sections(path: &str, crossref: &CrossReference) -> String {
+    let mut uses: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    let mut used_by: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for edge in &crossref.edges {
+        // Type-only names are in italics
+        let name = |edge: &Edge| match (edge.kind, &edge.to_export) {
+            (EdgeKind::ReExport, Some(export)) => Some(format!("`{}` (re-exported)", export)),
+            (EdgeKind::TypeOnly, Some(export)) => Some(format!("_`{}`_", export)),
+            (_, Some(export)) => Some(format!("`{}`", export)),
+            (EdgeKind::ReExport, None) => Some("everything (re-exported)".to_string()),
+            (EdgeKind::TypeOnly, None) => Some("_types_".to_string()),
+            (EdgeKind::SideEffect, None) => Some("side effects only".to_string()),
+            (_, None) => None,
+        };
+        if edge.from_module == path {
+            let names = uses.entry(edge.to_module.as_str()).or_default();
+            names.extend(name(edge));
+        }
+        if edge.to_module == path {
+            let names = used_by.entry(edge.from_module.as_str()).or_default();
+            names.extend(name(edge));
+        }
+    }
+    // Module-level dependencies without edges
+    for dep in crossref.dependencies.get(path).into_iter().flatten() {
+        if dep != path {
+            uses.entry(dep.as_str()).or_default();
+        }
+    }
+    for (module, deps) in &crossref.dependencies {
+        if module != path && deps.iter().any(|d| d == path) {
+            used_by.entry(module.as_str()).or_default();
+        }
+    }
+
+    let mut sections = String::new();
+    for (heading, modules) in [("## Uses", uses), ("## Used by", used_by)] {
+        if modules.is_empty() {
+            continue;
+        }
+        sections.push_str(heading);
+        sections.push_str("\n\n");
+        for (module, mut names) in modules {
+            names.dedup();
+            if names.is_empty() {
+                sections.push_str(&format!("- `{}`\n", module));
+            } else {
+                sections.push_str(&format!("- `{}`: {}\n", module, names.join(", ")));
+            }
+        }
+        sections.push('\n');
+    }
+
+    let external: Vec<_> = crossref
+        .external_users
+        .iter()
+        .filter(|(_, users)| users.iter().any(|u| u == path))
+        .map(|(package, _)| format!("- `{}`\n", package))
+        .collect();
+    if !external.is_empty() {
+        sections.push_str("## External dependencies\n\n");
+        sections.push_str(&external.concat());
+        sections.push('\n');
+    }
+    sections
+}
+
+/// The tests that exercise a module, as the LLM restated them in deep runs,
+/// else by name
+fn behavior_section(module: &ModuleAnalysis) -> String {
+    if module.behaviors.is_empty() {
+        return String::new();
+    }
+    let mut section = String::from("## Documented behavior (from tests)\n\n");
+    for behavior in &module.behaviors {
+        let location = format!("{}:{}", behavior.path, behavior.line);
+        match (&behavior.statement, &behavior.doc) {
+            (Some(statement), _) => section.push_str(&format!(
+                "- {} (`{}`, {})\n",
+                statement, behavior.test, location
+            )),
+            (None, Some(doc)) => {
+                section.push_str(&format!("- `{}`: {} ({})\n", behavior.test, doc, location))
+            }
+            (None, None) => section.push_str(&format!("- `{}` ({})\n", behavior.test, location)),
+        }
+    }
+    section.push('\n');
+    section
+}
+
+/// Uses of each export elsewhere in the codebase, with where they are
+fn example_section(module: &ModuleAnalysis) -> String {
+    let mut section = String::new();
+    for export in module.public_exports().filter(|e| !e.examples.is_empty()) {
+        section.push_str(&format!("### `{}`\n\n", export.name));
+        for example in &export.examples {
+            section.push_str(&format!(
+                "From `{}:{}`:\n\n{}\n",
+                example.path,
+                example.line,
+                analyzer::fenced_code(&example.snippet, module.language)
+            ));
+        }
+    }
+    if section.is_empty() {
+        return section;
+    }
+    format!("## Examples from the codebase\n\n{}", section)
+}
+
+/// Write `README.md` into every directory of `modules/`, from the root down:
+/// a rollup of the directory, its subdirectories and its files, linked to
+/// the module pages that `--deep` wrote
+fn write_directory_pages(analysis: &Analysis, output_path: &Path) -> Result<()> {
+    let mut listings: BTreeMap<String, Listing> = BTreeMap::new();
+    listings.entry(String::new()).or_default();
+    for module in &analysis.modules {
+        let mut dir = parent_dir(&module.path);
+        listings
+            .entry(dir.to_string())
+            .or_default()
+            .files
+            .push(module);
+        loop {
+            let listing = listings.entry(dir.to_string()).or_default();
+            listing.modules += 1;
+            listing.exports += module.public_exports().count();
+            if dir.is_empty() {
+                break;
+            }
+            let parent = parent_dir(dir);
+            listings
+                .entry(parent.to_string())
+                .or_default()
+                .subdirectories
+                .insert(dir.to_string());
+            dir = parent;
+        }
+    }
+
+    let modules_dir = output_path.join("modules");
+    for (dir, listing) in &listings {
+        let page_name = directory_page_name(dir);
+        let page = render_directory_page(dir, listing, &listings, &page_name);
+        // One page that can't be created doesn't cost the others
+        let written = analyzer::page_write_path(&modules_dir, &page_name)
+            .and_then(|path| Ok(analyzer::write_atomic(&path, page)?));
+        if let Err(e) = written {
+            warn!("Failed to write the directory page {}: {}", page_name, e);
+        }
+    }
+    Ok(())
+}
+
+fn render_directory_page(
+    dir: &str,
+    listing: &Listing,
+    listings: &BTreeMap<String, Listing>,
+    page_name: &str,
+) -> String {
+    // Links are relative to the page, wherever the naming scheme put it
+    let relative = |target: String| analyzer::page_link_between(page_name, &target);
+    let depth = page_name.matches('/').count();
+
+    let mut page = String::new();
+    if dir.is_empty() {
+        page.push_str("# Source Tree\n\n");
+    } else {
+        page.push_str(&format!("# `{}`\n\n", dir));
+    }
+    page.push_str(&format!("[Index]({}CODEBASE.md)", "../".repeat(depth + 1)));
+    if !dir.is_empty() {
+        page.push_str(&format!(
+            " · [Up]({})",
+            relative(directory_page_name(parent_dir(dir)))
+        ));
+    }
+    page.push_str(&format!(
+        "\n\n{} modules and {} exports in this directory and below.\n\n",
+        listing.modules, listing.exports
+    ));
+
+    if !listing.subdirectories.is_empty() {
+        page.push_str("## Directories\n\n");
+        for subdirectory in &listing.subdirectories {
+            let child = &listings[subdirectory];
+            page.push_str(&format!(
+                "- [`{}/`]({}) — {} modules, {} exports\n",
+                file_name(subdirectory),
+                relative(directory_page_name(subdirectory)),
+                child.modules,
+                child.exports
+            ));
+        }
+        page.push('\n');
+    }
+
+    if !listing.files.is_empty() {
+        let mut files = listing.files.clone();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        page.push_str("## Files\n\n");
+        for module in files {
+            let name = file_name(&module.path);
+            let entry = if module.has_deep_analysis {
+                format!("[`{}`]({})", name, relative(module_page_name(&module.path)))
+            } else {
+                format!("`{}`", name)
+            };
+            let summary = module.summary.trim_start_matches('#').trim();
+            if summary.is_empty() {
+                page.push_str(&format!("- {}\n", entry));
+            } else {
+                page.push_str(&format!("- {} — {}\n", entry, truncate(summary, 120)));
+            }
+        }
+        page.push('\n');
+    }
+
+    page
+}
+
+/// Link from CODEBASE.md to a page in the modules tree
+fn page_link(page_name: &str) -> String {
+    format!("modules/{}", page_name)
+}
+
+/// Directory part of a module path, `""` at the root
+fn parent_dir(path: &str) -> &str {
+    path.rfind(['/', '\\']).map_or("", |i| &path[..i])
+}
+
+/// Last component of a module path, whichever separator it uses
+fn file_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+/// Directory of a module. Paths are root-relative since analysis.json 1.1;
+/// absolute paths from older files are shortened to something readable.
+fn display_dir(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let Some((dir, _)) = path.rsplit_once('/') else {
+        return "root".to_string();
+    };
+    let is_absolute = path.starts_with('/') || path.get(1..2) == Some(":");
+    if !is_absolute {
+        return dir.to_string();
+    }
+
+    Path::new(&path)
+        .parent()
+        .map(|p| {
+            // Try to get a reasonable relative path
+            let s = p.to_string_lossy();
+            // Find common prefixes and strip them
+            if let Some(idx) = s.find("/src/") {
+                s[idx..].to_string()
+            } else if let Some(idx) = s.find("/lib/") {
+                s[idx..].to_string()
+            } else {
+                // Just use last 2-3 components
+                let components: Vec<_> = p.components().collect();
+                let start = if components.len() > 3 {
+                    components.len() - 3
+                } else {
+                    0
+                };
+                components[start..]
+                    .iter()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/")
+            }
+        })
+        .unwrap_or_else(|| "root".to_string())
+}
+
 fn truncate(s: &str, max: usize) -> &str {
     if s.len() <= max {
         s
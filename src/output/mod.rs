@@ -1,13 +1,19 @@
+pub mod compat;
 mod json;
+pub mod manifest;
 mod markdown;
 
 use anyhow::Result;
 use clap::ValueEnum;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
 use std::path::Path;
 
+use crate::core::analyzer::PriorModule;
 use crate::core::{Analysis, CrossReference};
 
-#[derive(Debug, Clone, Copy, ValueEnum, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Format {
     #[default]
     Markdown,
@@ -28,3 +34,20 @@ pub fn generate(
         Format::Json => json::generate(analysis, crossref, output_path),
     }
 }
+
+/// Module summaries from a previous JSON run in `output_path`, if any
+pub fn load_prior(output_path: &Path) -> Result<HashMap<String, PriorModule>> {
+    json::load_prior(output_path)
+}
+
+/// Dependency graph from a previous JSON run in `output_path`, if any
+pub fn load_prior_dependencies(
+    output_path: &Path,
+) -> Result<Option<BTreeMap<String, Vec<String>>>> {
+    json::load_prior_dependencies(output_path)
+}
+
+/// Load an analysis.json back into the in-memory model
+pub fn load(path: &Path) -> Result<(Analysis, CrossReference)> {
+    json::load(path)
+}
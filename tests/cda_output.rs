@@ -0,0 +1,33 @@
+//! Reading analysis.json with the `cda_output` types alone
+//!
+//! This is the one integration test that builds without the analyzer:
+//! `cargo test --no-default-features --features cda-output` runs it the way
+//! a tool depending on `cda-output` would.
+
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use codebase_deep_analyzer::cda_output::{OutputDocument, SCHEMA_VERSION};
+
+fn golden(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(name)
+        .join("analysis.json")
+}
+
+#[test]
+fn test_golden_outputs_round_trip() {
+    for name in ["static", "deep"] {
+        let path = golden(name);
+        let doc = OutputDocument::from_path(&path).unwrap();
+        assert_eq!(doc.version, SCHEMA_VERSION);
+
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(serde_json::to_value(&doc).unwrap(), json, "{}", name);
+
+        let written = serde_json::to_string_pretty(&doc).unwrap();
+        assert_eq!(OutputDocument::from_str(&written).unwrap(), doc, "{}", name);
+    }
+}
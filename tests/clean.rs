@@ -0,0 +1,78 @@
+//! `cda clean` removes what cda wrote and nothing else
+
+use std::fs;
+
+mod common;
+use common::cda;
+
+#[test]
+fn test_clean_all_stays_inside_the_output() {
+    let home = tempfile::tempdir().unwrap();
+    let home = home.path();
+    let fixture = common::fixture("mixed");
+    let out = home.join("out");
+    cda(home)
+        .arg("analyze")
+        .arg(&fixture)
+        .args(["--mode", "static", "--output"])
+        .arg(&out)
+        .assert()
+        .code(0);
+
+    // A changelog of the project's own, next to cda's
+    fs::write(out.join("CHANGES.md"), "# Changes\n").unwrap();
+    // a hand-written page among cda's
+    fs::create_dir_all(out.join("modules/docs")).unwrap();
+    fs::write(out.join("modules/docs/guide.md"), "# Guide\n").unwrap();
+    // and a state directory of its own
+    fs::create_dir(out.join("state")).unwrap();
+    fs::write(out.join("state/store.ts"), "export const store = {};\n").unwrap();
+
+    // A manifest edited to point outside the output directory
+    let victim = home.join("victim");
+    fs::write(&victim, "not cda's").unwrap();
+    let manifest_path = out.join("manifest.json");
+    let mut manifest: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&manifest_path).unwrap()).unwrap();
+    let artifacts = manifest["artifacts"].as_array_mut().unwrap();
+    let mut escape = artifacts[0].clone();
+    escape["path"] = "../victim".into();
+    let mut absolute = artifacts[0].clone();
+    absolute["path"] = victim.display().to_string().into();
+    artifacts.extend([escape, absolute]);
+    fs::write(&manifest_path, manifest.to_string()).unwrap();
+
+    cda(home)
+        .arg("clean")
+        .arg(&out)
+        .args(["--all", "--yes"])
+        .assert()
+        .code(0);
+    assert!(victim.exists());
+    assert!(out.join("CHANGES.md").exists());
+    assert!(out.join("state/store.ts").exists());
+    assert!(out.join("modules/docs/guide.md").exists());
+    assert!(!out.join("CODEBASE.md").exists());
+}
+
+#[test]
+fn test_clean_refuses_a_directory_without_cda_output() {
+    let home = tempfile::tempdir().unwrap();
+    let home = home.path();
+    let repo = home.join("repo");
+    fs::create_dir_all(repo.join("modules/docs")).unwrap();
+    fs::write(repo.join("modules/docs/guide.md"), "# Guide\n").unwrap();
+    fs::write(repo.join("CODEBASE.md"), "# Ours\n").unwrap();
+
+    cda(home)
+        .arg("clean")
+        .arg(&repo)
+        .args(["--all", "--yes"])
+        .assert()
+        .code(2)
+        .stderr(predicates::str::contains(
+            "no manifest.json or cda progress file",
+        ));
+    assert!(repo.join("modules/docs/guide.md").exists());
+    assert!(repo.join("CODEBASE.md").exists());
+}
@@ -0,0 +1,50 @@
+//! Escape codes in stderr under `--color`, `NO_COLOR` and a piped stderr
+//!
+//! `assert_cmd` captures stderr through a pipe, so `auto` must never color.
+
+mod common;
+
+/// stderr of a static `cda analyze` over the fixture repository
+fn analyze_stderr(args: &[&str], env: &[(&str, &str)]) -> String {
+    let home = tempfile::tempdir().unwrap();
+
+    let mut cmd = common::cda(home.path());
+    cmd.arg("analyze")
+        .arg(common::fixture("mixed"))
+        .arg("--output")
+        .arg(home.path().join("out"))
+        .args(["--mode", "static"])
+        .args(args);
+    for (name, value) in env {
+        cmd.env(name, value);
+    }
+
+    let output = cmd.assert().success().get_output().clone();
+    String::from_utf8(output.stderr).unwrap()
+}
+
+fn has_escapes(text: &str) -> bool {
+    text.contains("\x1b[")
+}
+
+#[test]
+fn test_no_colors_when_stderr_is_not_a_terminal() {
+    let stderr = analyze_stderr(&[], &[]);
+    assert!(stderr.contains("INFO"), "{}", stderr);
+    assert!(!has_escapes(&stderr), "{:?}", stderr);
+
+    let stderr = analyze_stderr(&["--color", "never"], &[("CLICOLOR_FORCE", "1")]);
+    assert!(!has_escapes(&stderr), "{:?}", stderr);
+}
+
+#[test]
+fn test_color_always_and_environment() {
+    let stderr = analyze_stderr(&["--color", "always"], &[("NO_COLOR", "1")]);
+    assert!(has_escapes(&stderr), "{:?}", stderr);
+
+    let stderr = analyze_stderr(&[], &[("CLICOLOR_FORCE", "1")]);
+    assert!(has_escapes(&stderr), "{:?}", stderr);
+
+    let stderr = analyze_stderr(&[], &[("CLICOLOR_FORCE", "1"), ("NO_COLOR", "1")]);
+    assert!(!has_escapes(&stderr), "{:?}", stderr);
+}
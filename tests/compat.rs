@@ -0,0 +1,87 @@
+//! Output directories written by earlier versions of cda
+//!
+//! `tests/fixtures/outputs/<schema>` holds output captured from the cda that
+//! wrote that analysis.json schema: 1.0 from the first release (a static
+//! run, absolute paths and no manifest), 1.13 from the last version before
+//! modules recorded their completeness (a deep run with a manifest).
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use codebase_deep_analyzer::core::analyzer::Completeness;
+use codebase_deep_analyzer::output;
+
+mod common;
+use common::cda;
+
+fn fixture(schema: &str) -> PathBuf {
+    common::fixture("outputs").join(schema)
+}
+
+/// A copy of the fixture output, as commands may write into it
+fn copy_fixture(schema: &str, to: &Path) {
+    common::copy_tree(&fixture(schema), to);
+}
+
+#[test]
+fn test_older_schemas_are_migrated() {
+    let (analysis, _) = output::load(&fixture("1.13").join("analysis.json")).unwrap();
+    assert_eq!(analysis.modules.len(), 4);
+    assert!(analysis
+        .modules
+        .iter()
+        .all(|m| m.completeness == Completeness::Full));
+
+    let (analysis, crossref) = output::load(&fixture("1.0").join("analysis.json")).unwrap();
+    assert_eq!(analysis.modules.len(), 4);
+    assert!(analysis
+        .modules
+        .iter()
+        .all(|m| m.completeness == Completeness::StaticOnly && !m.is_test_module));
+    assert!(!crossref.gaps.is_empty());
+    assert_eq!(
+        crossref.external_users.keys().collect::<Vec<_>>(),
+        crossref.external_deps.iter().collect::<Vec<_>>()
+    );
+    assert!(crossref.external_users.values().all(|m| !m.is_empty()));
+}
+
+#[test]
+fn test_commands_read_older_output() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path().join("out");
+    copy_fixture("1.13", &out);
+
+    cda(dir.path()).arg("verify").arg(&out).assert().success();
+    cda(dir.path())
+        .args(["export", "out", "--to", "markdown"])
+        .assert()
+        .success();
+    let index = fs::read_to_string(out.join("CODEBASE.md")).unwrap();
+    assert!(index.contains("full"), "{}", index);
+
+    let manifest = fs::read_to_string(out.join("manifest.json")).unwrap();
+    assert!(!manifest.contains("\"schema\""), "{}", manifest);
+}
+
+#[test]
+fn test_newer_major_version_is_refused() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path().join("out");
+    copy_fixture("1.13", &out);
+    let json = out.join("analysis.json");
+    let content = fs::read_to_string(&json).unwrap();
+    fs::write(&json, content.replacen("\"1.13\"", "\"2.0\"", 1)).unwrap();
+
+    for args in [vec!["verify", "out"], vec!["export", "out"]] {
+        let output = cda(dir.path()).args(&args).output().unwrap();
+        assert_eq!(output.status.code(), Some(1), "{:?}", args);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("was written by cda 0.1.0 with analysis.json schema 2.0"),
+            "{}",
+            stderr
+        );
+        assert!(stderr.contains("reads schema 1.x"), "{}", stderr);
+    }
+}
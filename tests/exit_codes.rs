@@ -0,0 +1,261 @@
+//! Exit codes for each class of failure, from the `cda` binary
+//!
+//! Every run gets an empty home and working directory, so user and repo
+//! configs on the machine running the tests are not picked up.
+
+use assert_cmd::Command;
+use std::path::Path;
+
+mod common;
+
+struct Sandbox {
+    dir: tempfile::TempDir,
+}
+
+impl Sandbox {
+    fn new() -> Self {
+        Self {
+            dir: tempfile::tempdir().unwrap(),
+        }
+    }
+
+    fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    fn cda(&self) -> Command {
+        common::cda(self.path())
+    }
+
+    /// `cda analyze <project> --output <sandbox>/out`
+    fn analyze(&self, project: &Path) -> Command {
+        let mut cmd = self.cda();
+        cmd.arg("analyze")
+            .arg(project)
+            .arg("--output")
+            .arg(self.path().join("out"));
+        cmd
+    }
+}
+
+#[test]
+fn test_success() {
+    let sandbox = Sandbox::new();
+    sandbox
+        .analyze(&common::fixture("mixed"))
+        .args(["--mode", "static"])
+        .assert()
+        .code(0);
+}
+
+#[test]
+fn test_usage_errors() {
+    let sandbox = Sandbox::new();
+    // Rejected by clap
+    sandbox
+        .cda()
+        .args(["analyze", "--no-such-flag"])
+        .assert()
+        .code(2);
+    // Rejected by the command
+    sandbox
+        .analyze(&common::fixture("mixed"))
+        .args(["--mode", "static", "--check-model"])
+        .assert()
+        .code(2);
+    sandbox
+        .analyze(&common::fixture("mixed"))
+        .args(["--mode", "static", "--order", "fan-in"])
+        .assert()
+        .code(2);
+    sandbox
+        .analyze(&common::fixture("mixed"))
+        .args(["--mode", "static", "--max-llm-calls", "10"])
+        .assert()
+        .code(2);
+    sandbox
+        .analyze(&common::fixture("mixed"))
+        .args(["--mode", "static", "--files-from", "-"])
+        .assert()
+        .code(2);
+    sandbox
+        .analyze(&common::fixture("mixed"))
+        .args(["--mode", "static", "--dependents"])
+        .assert()
+        .code(2);
+    sandbox.cda().arg("clean").assert().code(2);
+    sandbox
+        .cda()
+        .args([
+            "config",
+            "--local",
+            "--config",
+            "cda.toml",
+            "--get",
+            "llm.model",
+        ])
+        .assert()
+        .code(2);
+    sandbox
+        .cda()
+        .env("CDA_CONFIG", "cda.toml")
+        .args(["config", "--local", "--get", "llm.model"])
+        .assert()
+        .code(2);
+    sandbox
+        .cda()
+        .args(["models", "--provider", "no-such-provider"])
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn test_gaps_exceeded() {
+    let sandbox = Sandbox::new();
+    sandbox
+        .analyze(&common::fixture("mixed"))
+        .args(["--mode", "static", "--fail-on-gaps"])
+        .assert()
+        .code(3);
+}
+
+#[test]
+fn test_configuration_errors() {
+    let sandbox = Sandbox::new();
+    // Standard mode needs credentials for the overview
+    sandbox
+        .analyze(&common::fixture("mixed"))
+        .args(["--provider", "anthropic"])
+        .assert()
+        .code(4)
+        .stderr(predicates::str::contains("ANTHROPIC_API_KEY not set"));
+    sandbox
+        .analyze(&common::fixture("mixed"))
+        .args(["--provider", "nonsense"])
+        .assert()
+        .code(4);
+    sandbox
+        .analyze(&common::fixture("mixed"))
+        .args(["--mode", "static", "--config", "missing.toml"])
+        .assert()
+        .code(4);
+    // A value the config couldn't be loaded with is never written
+    let config = sandbox.path().join("cda.toml");
+    std::fs::write(&config, "[analysis]\nparallelism = 4\n").unwrap();
+    for (key, value) in [
+        ("analysis.parallelism", "0"),
+        ("llm.provider", "nonsense"),
+        ("output.format", "yaml"),
+    ] {
+        sandbox
+            .cda()
+            .args(["config", "--local", "--set", key, value])
+            .assert()
+            .code(4)
+            .stderr(predicates::str::contains("line "));
+    }
+    assert_eq!(
+        std::fs::read_to_string(&config).unwrap(),
+        "[analysis]\nparallelism = 4\n"
+    );
+    // An unset key is an error, not an empty value
+    sandbox
+        .cda()
+        .args(["config", "--local", "--get", "llm.model"])
+        .assert()
+        .code(4)
+        .stderr(predicates::str::contains("llm.model is not set"));
+}
+
+#[test]
+fn test_completed_with_warnings() {
+    let sandbox = Sandbox::new();
+    let project = sandbox.path().join("project");
+    std::fs::create_dir_all(&project).unwrap();
+    // Minified files are summarized with a warning
+    let bundle: String = (0..200)
+        .map(|i| format!("function f{i}(a,b){{return a+b*{i}}};"))
+        .collect();
+    std::fs::write(project.join("bundle.js"), bundle).unwrap();
+
+    sandbox
+        .analyze(&project)
+        .args(["--mode", "static"])
+        .assert()
+        .code(5)
+        .stderr(predicates::str::contains(
+            "Warning: Analysis completed with 1 warning(s)",
+        ));
+    assert!(sandbox.path().join("out/CODEBASE.md").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_interrupted() {
+    use std::io::{BufRead, BufReader};
+    use std::process::Stdio;
+
+    let sandbox = Sandbox::new();
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin("cda"))
+        .current_dir(sandbox.path())
+        .env("HOME", sandbox.path())
+        .env_remove("RUST_LOG")
+        .args(["serve", ".", "--port", "0"])
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // Wait until the server is up, so the Ctrl-C handler is installed
+    let mut stderr = BufReader::new(child.stderr.take().unwrap());
+    let mut line = String::new();
+    while stderr.read_line(&mut line).unwrap() > 0 && !line.contains("Serving") {
+        line.clear();
+    }
+    assert!(line.contains("Serving"), "server did not start");
+
+    unsafe {
+        libc::kill(child.id() as libc::pid_t, libc::SIGINT);
+    }
+    assert_eq!(child.wait().unwrap().code(), Some(130));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_watch_interrupted_during_update() {
+    use std::io::{BufRead, BufReader};
+    use std::process::Stdio;
+
+    let sandbox = Sandbox::new();
+    let project = sandbox.path().join("project");
+    common::copy_tree(&common::fixture("mixed"), &project);
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin("cda"))
+        .current_dir(sandbox.path())
+        .env("HOME", sandbox.path())
+        .env_remove("RUST_LOG")
+        .env_remove("ANTHROPIC_API_KEY")
+        .args(["watch", "project", "--output", "out", "--debounce", "50"])
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let mut stderr = BufReader::new(child.stderr.take().unwrap());
+    let mut wait_for = |text: &str| {
+        let mut line = String::new();
+        while stderr.read_line(&mut line).unwrap() > 0 && !line.contains(text) {
+            line.clear();
+        }
+        assert!(line.contains(text), "never logged {text:?}");
+    };
+    wait_for("Initial analysis complete");
+
+    std::fs::write(project.join("added.rs"), "pub fn added() {}\n").unwrap();
+    wait_for("Detected 1 changed");
+    unsafe {
+        libc::kill(child.id() as libc::pid_t, libc::SIGINT);
+    }
+    // The output is regenerated from the batch so far before stopping
+    wait_for("Updated");
+    wait_for("Stopped watching");
+    assert_eq!(child.wait().unwrap().code(), Some(0));
+    assert!(sandbox.path().join("out/CODEBASE.md").exists());
+}
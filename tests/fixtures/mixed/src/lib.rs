@@ -0,0 +1,19 @@
+//! Tokenizer and evaluator for a small expression language
+
+pub mod parser;
+
+use crate::parser::{parse, Token};
+
+/// Evaluate an expression such as `1 + 2`
+pub fn eval(input: &str) -> Result<i64, String> {
+    let tokens = parse(input)?;
+    let mut total = 0;
+    for token in tokens {
+        if let Token::Number(n) = token {
+            total += n;
+        }
+    }
+    Ok(total)
+}
+
+pub const MAX_DEPTH: usize = 32;
@@ -0,0 +1,36 @@
+use std::collections::HashMap;
+
+/// A lexical token
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Number(i64),
+    Plus,
+}
+
+/// Split an expression into tokens
+pub fn parse(input: &str) -> Result<Vec<Token>, String> {
+    input
+        .split_whitespace()
+        .map(|word| match word {
+            "+" => Ok(Token::Plus),
+            n => n.parse().map(Token::Number).map_err(|e| format!("{}: {}", n, e)),
+        })
+        .collect()
+}
+
+pub struct SymbolTable {
+    pub names: HashMap<String, i64>,
+}
+
+pub trait Visitor {
+    fn visit(&mut self, token: &Token);
+}
+
+#[cfg(test)]
+mod tests {
+    /// Words that are neither numbers nor `+` are errors
+    #[test]
+    fn rejects_unknown_words() {
+        assert!(super::parse("1 + x").is_err());
+    }
+}
@@ -0,0 +1,33 @@
+import { Client, DEFAULT_URL } from "./client";
+import { retry } from "@evaluator/http/retry";
+import { DEFAULT_TIMEOUT } from "@evaluator/http";
+import { z } from "zod";
+import * as Sentry from "@sentry/browser";
+
+/** Options accepted by {@link evaluateAll} */
+export interface EvalOptions {
+  url?: string;
+}
+
+export type Result = { expression: string; value: number };
+
+/**
+ * Evaluate several expressions against the service
+ */
+export async function evaluateAll(expressions: string[], options: EvalOptions = {}): Promise<Result[]> {
+  const client = new Client(options.url ?? DEFAULT_URL);
+  const schema = z.number();
+  return Promise.all(
+    expressions.map(async (expression) => ({
+      expression,
+      value: schema.parse(await retry(() => client.evaluate(expression), { timeout: DEFAULT_TIMEOUT })),
+    })),
+  ).catch((error) => {
+    Sentry.captureException(error);
+    throw error;
+  });
+}
+
+export function formatResult(result: Result): string {
+  return `${result.expression} = ${result.value}`;
+}
@@ -0,0 +1,14 @@
+/** HTTP client for the evaluation service */
+export class Client {
+  constructor(private baseUrl: string) {}
+
+  async evaluate(expression: string): Promise<number> {
+    const response = await fetch(`${this.baseUrl}/eval`, {
+      method: "POST",
+      body: expression,
+    });
+    return Number(await response.text());
+  }
+}
+
+export const DEFAULT_URL = "http://localhost:8080";
@@ -0,0 +1,29 @@
+#!/bin/sh
+# Sample cda plugin for a toy rules language:
+#
+#   import <source>
+#   fn <name>(<params>)
+#   type <name>
+#
+# Reads the file named by the first argument, or stdin when there is none, and
+# prints its exports and imports in the cda plugin schema (version 1).
+
+awk '
+function quote(s) { gsub(/\\/, "\\\\", s); gsub(/"/, "\\\"", s); return "\"" s "\"" }
+$1 == "fn" {
+    name = $2; sub(/\(.*/, "", name)
+    sig = $0; sub(/^fn[ \t]+/, "", sig)
+    exports = exports sep_e "{\"name\": " quote(name) ", \"kind\": \"function\", \"line\": " NR ", \"signature\": " quote(sig) "}"
+    sep_e = ", "
+}
+$1 == "type" {
+    exports = exports sep_e "{\"name\": " quote($2) ", \"kind\": \"type\", \"line\": " NR "}"
+    sep_e = ", "
+}
+$1 == "import" {
+    external = ($2 ~ /^\./) ? "false" : "true"
+    imports = imports sep_i "{\"source\": " quote($2) ", \"external\": " external "}"
+    sep_i = ", "
+}
+END { printf "{\"version\": 1, \"exports\": [%s], \"imports\": [%s]}\n", exports, imports }
+' "$@"
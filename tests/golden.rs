@@ -0,0 +1,270 @@
+//! Golden-file tests for generated output
+//!
+//! The fixture repository in `tests/fixtures/mixed` (Rust + TypeScript) is run
+//! through the static pipeline, and through the deep pipeline with a canned
+//! LLM provider. `CODEBASE.md`, the module pages and `analysis.json` are
+//! compared with the files under `tests/golden`, after replacing the temp
+//! output directory with `<OUT>`.
+//!
+//! After an intentional output change, regenerate the golden files and review
+//! the diff before committing it:
+//!
+//! ```text
+//! BLESS=1 cargo test --test golden
+//! git diff tests/golden
+//! ```
+
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use codebase_deep_analyzer::llm::{self, LlmConfig, Message, ModelInfo, Role};
+use codebase_deep_analyzer::{
+    Analysis, DeepOptions, DiscoveryOptions, FileInventory, Format, LlmProvider,
+};
+
+/// Answers every module prompt with a summary naming the file, so pages are
+/// deterministic
+struct Canned;
+
+#[async_trait::async_trait]
+impl LlmProvider for Canned {
+    fn name(&self) -> &str {
+        "canned"
+    }
+
+    fn model(&self) -> &str {
+        "golden"
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        Ok(vec![])
+    }
+
+    async fn complete(&self, messages: Vec<Message>, _config: LlmConfig) -> Result<String> {
+        let prompt = messages
+            .iter()
+            .find(|m| matches!(m.role, Role::User))
+            .map(|m| m.content.as_str())
+            .unwrap_or_default();
+        let file = prompt
+            .lines()
+            .next()
+            .and_then(|line| line.split('`').nth(1))
+            .unwrap_or("unknown");
+
+        let answer = format!(
+            "{} is part of the expression evaluator.\n\n\
+             ## Responsibilities\n\n\
+             - Canned analysis of `{}` for the golden tests\n",
+            file, file
+        );
+        // Token counts that differ between files but, unlike the prompt, not
+        // between runs
+        let input_tokens = file.bytes().map(u64::from).sum();
+        llm::record_usage(Some(input_tokens), Some(answer.len() as u64 / 4));
+        Ok(answer)
+    }
+}
+
+fn fixture() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/mixed")
+}
+
+fn golden_dir(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(name)
+}
+
+async fn inventory() -> FileInventory {
+    codebase_deep_analyzer::discover(fixture(), &DiscoveryOptions::default())
+        .await
+        .unwrap()
+}
+
+/// Write both output formats for `analysis` into `out`
+async fn generate(analysis: &Analysis, out: &Path) {
+    let crossref = codebase_deep_analyzer::cross_reference(analysis)
+        .await
+        .unwrap();
+    codebase_deep_analyzer::generate(analysis, &crossref, out, Format::Markdown).unwrap();
+    codebase_deep_analyzer::generate(analysis, &crossref, out, Format::Json).unwrap();
+}
+
+/// Replace values that differ between runs
+fn normalize(content: &str, out: &Path) -> String {
+    let content = content
+        .replace("\r\n", "\n")
+        .replace(&out.display().to_string(), "<OUT>");
+    let content = replace_number(&content, "\"duration_ms\": ", "", "0");
+    replace_number(&content, "*Took ", "s over", "0.0")
+}
+
+/// Replace the number between each `prefix` and `suffix` in `content` with
+/// `with`
+fn replace_number(content: &str, prefix: &str, suffix: &str, with: &str) -> String {
+    let mut result = String::new();
+    let mut rest = content;
+    while let Some(start) = rest.find(prefix) {
+        let (before, after) = rest.split_at(start + prefix.len());
+        result.push_str(before);
+        let end = after
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(after.len());
+        if after[end..].starts_with(suffix) {
+            result.push_str(with);
+            rest = &after[end..];
+        } else {
+            rest = after;
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Compare `files` (relative to `out`) with `tests/golden/<name>`, or
+/// overwrite the golden files when `BLESS` is set. Every mismatch is
+/// reported at once.
+fn check(name: &str, out: &Path, files: &[&str]) {
+    let bless = std::env::var_os("BLESS").is_some();
+    let golden = golden_dir(name);
+    let mut failures = Vec::new();
+
+    for file in files {
+        let actual = std::fs::read_to_string(out.join(file))
+            .unwrap_or_else(|e| panic!("{} was not generated: {}", file, e));
+        let actual = normalize(&actual, out);
+        let golden_path = golden.join(file);
+
+        if bless {
+            std::fs::create_dir_all(golden_path.parent().unwrap()).unwrap();
+            std::fs::write(&golden_path, actual).unwrap();
+            continue;
+        }
+
+        match std::fs::read_to_string(&golden_path) {
+            Ok(expected) if expected == actual => {}
+            Ok(expected) => failures.push(format!(
+                "{} differs from {}:\n{}",
+                file,
+                golden_path.display(),
+                first_difference(&expected, &actual)
+            )),
+            Err(_) => failures.push(format!("{} is missing", golden_path.display())),
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{}\n\nIf the change is intended, run `BLESS=1 cargo test --test golden` and review the diff",
+        failures.join("\n\n")
+    );
+}
+
+/// The first line that differs, with its line number
+fn first_difference(expected: &str, actual: &str) -> String {
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+    for line in 1.. {
+        match (expected_lines.next(), actual_lines.next()) {
+            (None, None) => break,
+            (e, a) if e == a => continue,
+            (e, a) => {
+                return format!(
+                    "  line {}\n  expected: {}\n  actual:   {}",
+                    line,
+                    e.unwrap_or("<end of file>"),
+                    a.unwrap_or("<end of file>")
+                )
+            }
+        }
+    }
+    "  (line endings differ)".to_string()
+}
+
+#[tokio::test]
+async fn test_static_output() {
+    let out = tempfile::tempdir().unwrap();
+    let analysis = codebase_deep_analyzer::analyze_static(&inventory().await)
+        .await
+        .unwrap();
+    generate(&analysis, out.path()).await;
+
+    check(
+        "static",
+        out.path(),
+        &[
+            "CODEBASE.md",
+            "analysis.json",
+            "modules/README.md",
+            "modules/src/README.md",
+        ],
+    );
+}
+
+#[tokio::test]
+async fn test_deep_output() {
+    let out = tempfile::tempdir().unwrap();
+    let mut options = DeepOptions::new(out.path());
+    // Short enough that some snippets are cut off
+    options.snippet_lines = Some(5);
+    options.max_examples = 2;
+    let analysis =
+        codebase_deep_analyzer::analyze_deep(&inventory().await, Arc::new(Canned), &options)
+            .await
+            .unwrap();
+    generate(&analysis, out.path()).await;
+
+    check(
+        "deep",
+        out.path(),
+        &[
+            "CODEBASE.md",
+            "analysis.json",
+            "modules/README.md",
+            "modules/web/README.md",
+            "modules/src/parser.rs.md",
+            "modules/web/api.ts.md",
+            "modules/web/client.ts.md",
+        ],
+    );
+}
+
+/// An interrupted and resumed deep run reports what a clean run does: the
+/// resumed modules are parsed again instead of counted as empty placeholders
+#[tokio::test]
+async fn test_resumed_deep_run_matches_clean_run() {
+    let clean = tempfile::tempdir().unwrap();
+    let resumed = tempfile::tempdir().unwrap();
+    let inventory = inventory().await;
+    let run = |out: PathBuf| {
+        let inventory = &inventory;
+        async move {
+            let options = DeepOptions::new(&out);
+            let analysis =
+                codebase_deep_analyzer::analyze_deep(inventory, Arc::new(Canned), &options)
+                    .await
+                    .unwrap();
+            generate(&analysis, &out).await;
+        }
+    };
+    run(clean.path().to_path_buf()).await;
+    run(resumed.path().to_path_buf()).await;
+
+    // Interrupted after two files, then resumed
+    let progress_path = resumed.path().join(".cda-state/progress");
+    let progress = std::fs::read_to_string(&progress_path).unwrap();
+    let finished: Vec<&str> = progress.lines().take(3).collect();
+    assert_eq!(finished.len(), 3, "{}", progress);
+    std::fs::write(&progress_path, finished.join("\n") + "\n").unwrap();
+    run(resumed.path().to_path_buf()).await;
+
+    let json = |out: &Path| -> serde_json::Value {
+        let content = std::fs::read_to_string(out.join("analysis.json")).unwrap();
+        serde_json::from_str(&normalize(&content, out)).unwrap()
+    };
+    let (clean, resumed) = (json(clean.path()), json(resumed.path()));
+    assert_eq!(clean["statistics"], resumed["statistics"]);
+    assert_eq!(clean["modules"], resumed["modules"]);
+}
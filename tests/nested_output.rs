@@ -0,0 +1,120 @@
+//! Output written inside the analyzed path is not analyzed on the next run
+
+use std::fs;
+use std::path::Path;
+
+mod common;
+
+fn analyze(home: &Path, project: &Path, format: &str) {
+    common::cda(home)
+        .current_dir(project)
+        // The cache lives inside the project too
+        .env("XDG_CACHE_HOME", project.join(".cache"))
+        .args(["analyze", ".", "--mode", "static", "--format", format])
+        .args(["--output", "./cda-output"])
+        .assert()
+        .code(0);
+}
+
+fn analysis(project: &Path) -> serde_json::Value {
+    let json = fs::read_to_string(project.join("cda-output/analysis.json")).unwrap();
+    serde_json::from_str(&json).unwrap()
+}
+
+fn listed_paths(analysis: &serde_json::Value) -> Vec<String> {
+    let modules = analysis["modules"].as_array().unwrap().iter();
+    let config = analysis["config_files"].as_array().unwrap().iter();
+    let docs = analysis["doc_files"].as_array().unwrap().iter();
+    modules
+        .chain(config)
+        .chain(docs)
+        .map(|entry| entry["path"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn test_nested_output_is_not_analyzed() {
+    let home = tempfile::tempdir().unwrap();
+    let project = tempfile::tempdir().unwrap();
+    common::copy_tree(&common::fixture("mixed"), project.path());
+    fs::create_dir_all(project.path().join(".cache/cda")).unwrap();
+    fs::write(project.path().join(".cache/cda/entry.json"), "{}").unwrap();
+
+    analyze(home.path(), project.path(), "json");
+    let first = analysis(project.path());
+    // Module pages, CODEBASE.md and progress files now sit in the project
+    analyze(home.path(), project.path(), "markdown");
+    analyze(home.path(), project.path(), "json");
+    let second = analysis(project.path());
+
+    assert_eq!(
+        second["modules"].as_array().unwrap().len(),
+        first["modules"].as_array().unwrap().len()
+    );
+    let paths = listed_paths(&second);
+    assert_eq!(paths, listed_paths(&first));
+    assert!(
+        paths
+            .iter()
+            .all(|p| !p.starts_with("cda-output") && !p.starts_with(".cache")),
+        "{:?}",
+        paths
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_watch_does_not_analyze_nested_output() {
+    use std::io::{BufRead, BufReader};
+    use std::process::Stdio;
+
+    let home = tempfile::tempdir().unwrap();
+    let project = tempfile::tempdir().unwrap();
+    common::copy_tree(&common::fixture("mixed"), project.path());
+    // Pages from an earlier run sit in the project
+    analyze(home.path(), project.path(), "markdown");
+
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin("cda"))
+        .current_dir(project.path())
+        .env("HOME", home.path())
+        .env("XDG_CONFIG_HOME", home.path().join(".config"))
+        .env("XDG_CACHE_HOME", home.path().join(".cache"))
+        .env_remove("RUST_LOG")
+        .env_remove("ANTHROPIC_API_KEY")
+        .args(["--format", "json", "watch", ".", "--output", "./cda-output"])
+        .args(["--debounce", "50"])
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stderr = BufReader::new(child.stderr.take().unwrap());
+    let mut wait_for = |text: &str| {
+        let mut line = String::new();
+        while stderr.read_line(&mut line).unwrap() > 0 && !line.contains(text) {
+            line.clear();
+        }
+        assert!(line.contains(text), "never logged {text:?}");
+    };
+    wait_for("Initial analysis complete");
+
+    // A source file in the output is never picked up by an update either
+    fs::write(
+        project.path().join("cda-output/stray.rs"),
+        "pub fn stray() {}\n",
+    )
+    .unwrap();
+    fs::write(project.path().join("added.rs"), "pub fn added() {}\n").unwrap();
+    wait_for("Detected");
+    wait_for("Updated");
+    unsafe {
+        libc::kill(child.id() as libc::pid_t, libc::SIGINT);
+    }
+    assert_eq!(child.wait().unwrap().code(), Some(0));
+
+    let paths = listed_paths(&analysis(project.path()));
+    assert!(paths.iter().any(|p| p == "added.rs"), "{:?}", paths);
+    assert!(
+        paths.iter().all(|p| !p.starts_with("cda-output")),
+        "{:?}",
+        paths
+    );
+}
@@ -0,0 +1,58 @@
+//! `[plugins]` in a checkout's own `./cda.toml` only run when allowed
+
+use std::fs;
+
+mod common;
+use common::cda;
+
+#[test]
+fn test_repo_plugins_are_ignored_by_default() {
+    let home = tempfile::tempdir().unwrap();
+    let home = home.path();
+    let fixtures = common::fixture("plugins");
+    let repo = home.join("repo");
+    fs::create_dir(&repo).unwrap();
+    fs::copy(fixtures.join("rules.dsl"), repo.join("rules.dsl")).unwrap();
+    fs::write(
+        repo.join("main.py"),
+        "\"\"\"Entry point\"\"\"\n\n\ndef main():\n    \"\"\"Run the rules\"\"\"\n",
+    )
+    .unwrap();
+    fs::write(
+        repo.join("cda.toml"),
+        format!(
+            "[analysis]\nallow_repo_plugins = true\n\n[plugins.dsl]\ncommand = [\"sh\", {:?}]\n",
+            fixtures.join("dsl-extractor.sh").display().to_string()
+        ),
+    )
+    .unwrap();
+    let documented = |format: &str| {
+        let out = repo.join(format!("out-{}", format));
+        fs::read_to_string(out.join("CODEBASE.md"))
+            .unwrap()
+            .contains("rules.dsl")
+    };
+
+    cda(home)
+        .current_dir(&repo)
+        .args([
+            "analyze",
+            ".",
+            "--mode",
+            "static",
+            "--output",
+            "out-default",
+        ])
+        .assert()
+        .code(5)
+        .stderr(predicates::str::contains("Ignoring plugins for .dsl"));
+    assert!(!documented("default"));
+
+    cda(home)
+        .current_dir(&repo)
+        .args(["--allow-repo-plugins", "analyze", ".", "--mode", "static"])
+        .args(["--output", "out-allowed"])
+        .assert()
+        .code(0);
+    assert!(documented("allowed"));
+}
@@ -0,0 +1,353 @@
+//! Runs against a provider other than Anthropic, from the `cda` binary
+//!
+//! A local server stands in for the OpenAI API (`OPENAI_BASE_URL`) and
+//! answers every chat completion with the same canned analysis, whose first
+//! line is the summary the test picks.
+
+use predicates::prelude::*;
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+mod common;
+use common::{cda, copy_tree};
+
+const SUMMARY: &str = "This module is part of the expression evaluator.";
+
+/// The rest of every answer, after the summary
+const DETAILS: &str = "\\n\\n## Responsibilities\\n\\n\
+                       - Canned analysis for the provider tests, long enough to be accepted\\n";
+
+/// Serve chat completions on a free port until the test ends; returns the
+/// base URL and the number of completions served
+fn mock_openai(summary: &'static str) -> (String, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/v1", listener.local_addr().unwrap());
+    let served = Arc::new(AtomicUsize::new(0));
+    let counter = served.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let counter = counter.clone();
+            std::thread::spawn(move || serve(stream, summary, &counter));
+        }
+    });
+    (url, served)
+}
+
+/// Answer the requests of one connection, which reqwest keeps alive
+fn serve(stream: TcpStream, summary: &str, served: &AtomicUsize) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut stream = stream;
+    loop {
+        let mut request_line = String::new();
+        if reader.read_line(&mut request_line).unwrap_or(0) == 0 {
+            return;
+        }
+        let mut length = 0;
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header).unwrap();
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    length = value.trim().parse().unwrap();
+                }
+            }
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+
+        let response = if request_line.contains("/chat/completions") {
+            served.fetch_add(1, Ordering::SeqCst);
+            format!(
+                r#"{{"choices": [{{"message": {{"content": "{}{}"}}}}], "usage": {{"prompt_tokens": 120, "completion_tokens": 30}}}}"#,
+                summary, DETAILS
+            )
+        } else {
+            r#"{"data": [{"id": "gpt-4o"}]}"#.to_string()
+        };
+        let written = write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            response.len(),
+            response
+        );
+        if written.is_err() {
+            return;
+        }
+    }
+}
+
+#[test]
+fn test_openai_only_deep_run() {
+    let home = tempfile::tempdir().unwrap();
+    let out = home.path().join("out");
+    let (url, served) = mock_openai(SUMMARY);
+
+    let output = cda(home.path())
+        .env("OPENAI_API_KEY", "sk-test")
+        .env("OPENAI_BASE_URL", &url)
+        .arg("analyze")
+        .arg(common::fixture("mixed"))
+        .args(["--mode", "deep", "--provider", "openai", "--format", "json"])
+        .arg("--output")
+        .arg(&out)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stderr.contains("ANTHROPIC_API_KEY"), "{}", stderr);
+    assert!(output.status.success(), "{}", stderr);
+
+    let analysis: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(out.join("analysis.json")).unwrap()).unwrap();
+    let modules = analysis["modules"].as_array().unwrap();
+    assert!(modules
+        .iter()
+        .filter(|m| m["is_test_module"] == false)
+        .all(|m| m["has_deep_analysis"] == true));
+    assert!(served.load(Ordering::SeqCst) >= modules.len());
+}
+
+#[test]
+fn test_missing_credentials_fail_before_discovery() {
+    let home = tempfile::tempdir().unwrap();
+    cda(home.path())
+        .env("ANTHROPIC_API_KEY", "sk-ant-test")
+        .arg("analyze")
+        .arg(common::fixture("mixed"))
+        .args(["--mode", "deep", "--provider", "openai", "--output"])
+        .arg(home.path().join("out"))
+        .assert()
+        .code(4)
+        .stderr(predicates::str::contains("OPENAI_API_KEY not set"))
+        .stderr(predicates::str::contains("export OPENAI_API_KEY="))
+        .stderr(predicates::str::contains("[1/4]").not());
+    assert!(!home.path().join("out").exists());
+}
+
+/// A deep run over `project` into `out`, answering with `summary`; returns
+/// the summaries of the modules by path
+fn deep_run(
+    home: &Path,
+    project: &Path,
+    out: &Path,
+    summary: &'static str,
+    args: &[&str],
+) -> BTreeMap<String, String> {
+    let (url, _) = mock_openai(summary);
+    let output = cda(home)
+        .env("OPENAI_API_KEY", "sk-test")
+        .env("OPENAI_BASE_URL", &url)
+        .arg("analyze")
+        .arg(project)
+        .args(["--mode", "deep", "--provider", "openai", "--format", "json"])
+        .arg("--output")
+        .arg(out)
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let analysis: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(out.join("analysis.json")).unwrap()).unwrap();
+    analysis["modules"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| {
+            let path = m["path"].as_str().unwrap().to_string();
+            (path, m["summary"].as_str().unwrap().to_string())
+        })
+        .collect()
+}
+
+#[test]
+fn test_resume_after_moving_the_output() {
+    let home = tempfile::tempdir().unwrap();
+
+    // Half the project is analyzed on one machine...
+    let first = home.path().join("first");
+    copy_tree(
+        &common::fixture("mixed").join("src"),
+        &first.join("project/src"),
+    );
+    let summaries = deep_run(
+        home.path(),
+        &first.join("project"),
+        &first.join("out"),
+        "Analyzed on the first runner.",
+        &[],
+    );
+    assert_eq!(summaries.len(), 2);
+
+    // ...and finished on another, where everything lives elsewhere
+    let second = home.path().join("second");
+    std::fs::rename(&first, &second).unwrap();
+    copy_tree(
+        &common::fixture("mixed").join("web"),
+        &second.join("project/web"),
+    );
+    let summaries = deep_run(
+        home.path(),
+        &second.join("project"),
+        &second.join("out"),
+        "Analyzed on the second runner.",
+        &[],
+    );
+    assert_eq!(summaries["src/lib.rs"], "Analyzed on the first runner.");
+    assert_eq!(summaries["src/parser.rs"], "Analyzed on the first runner.");
+    assert_eq!(summaries["web/api.ts"], "Analyzed on the second runner.");
+
+    // The state alone, restored from a cache, is enough to resume
+    let cache = home.path().join("cache");
+    copy_tree(&second.join("out/.cda-state"), &cache);
+    let fresh = home.path().join("fresh");
+    let summaries = deep_run(
+        home.path(),
+        &second.join("project"),
+        &fresh,
+        "Analyzed again.",
+        &["--import-state", cache.to_str().unwrap()],
+    );
+    assert!(
+        summaries.values().all(|s| s != "Analyzed again."),
+        "{:?}",
+        summaries
+    );
+    let page = std::fs::read_to_string(fresh.join("modules/src/lib.rs.md")).unwrap();
+    assert!(page.contains("Analyzed on the first runner."), "{}", page);
+}
+
+#[test]
+fn test_progress_events() {
+    let home = tempfile::tempdir().unwrap();
+    let out = home.path().join("out");
+    let events_file = home.path().join("events.ndjson");
+    let (url, _) = mock_openai(SUMMARY);
+
+    let output = cda(home.path())
+        .env("OPENAI_API_KEY", "sk-test")
+        .env("OPENAI_BASE_URL", &url)
+        .arg("analyze")
+        .arg(common::fixture("mixed"))
+        .args(["--mode", "deep", "--provider", "openai", "--format", "json"])
+        .arg("--output")
+        .arg(&out)
+        .arg("--progress-json")
+        .arg(&events_file)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+    // The terminal output is still there
+    assert!(stderr.contains("Analyzed "), "{}", stderr);
+
+    let events: Vec<serde_json::Value> = std::fs::read_to_string(&events_file)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    for (seq, event) in events.iter().enumerate() {
+        assert_eq!(event["schema"], 1);
+        assert_eq!(event["seq"], seq);
+    }
+    let kinds: Vec<&str> = events
+        .iter()
+        .map(|e| e["event"].as_str().unwrap())
+        .collect();
+    assert_eq!(kinds.first(), Some(&"run_started"));
+    assert_eq!(kinds.last(), Some(&"summary"));
+    let analysis: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(out.join("analysis.json")).unwrap()).unwrap();
+    assert_eq!(
+        events.last().unwrap()["modules"],
+        analysis["modules"].as_array().unwrap().len()
+    );
+
+    let position = |kind: &str, field: &str, value: &str| {
+        events
+            .iter()
+            .position(|e| e["event"] == kind && e[field] == value)
+            .unwrap_or_else(|| panic!("no {} for {}", kind, value))
+    };
+    let analysis_started = position("phase_started", "phase", "analysis");
+    let analysis_completed = position("phase_completed", "phase", "analysis");
+    assert!(analysis_started < analysis_completed);
+
+    let files: Vec<&str> = events
+        .iter()
+        .filter(|e| e["event"] == "file_queued")
+        .map(|e| e["file"].as_str().unwrap())
+        .collect();
+    assert!(files.contains(&"src/lib.rs"), "{:?}", files);
+    for file in files {
+        let queued = position("file_queued", "file", file);
+        let started = position("file_started", "file", file);
+        let completed = position("file_completed", "file", file);
+        assert!(analysis_started < queued, "{}", file);
+        assert!(queued < started && started < completed, "{}", file);
+        assert!(completed < analysis_completed, "{}", file);
+
+        let completed = &events[completed];
+        assert_eq!(completed["completeness"], "full");
+        assert!(completed["duration_ms"].is_u64());
+        let calls = completed["calls"].as_u64().unwrap();
+        assert!(calls >= 1);
+        assert_eq!(completed["input_tokens"], 120 * calls);
+        assert_eq!(completed["output_tokens"], 30 * calls);
+    }
+}
+
+#[test]
+fn test_files_from_reanalyzes_only_listed_files() {
+    let home = tempfile::tempdir().unwrap();
+    let out = home.path().join("out");
+    deep_run(
+        home.path(),
+        &common::fixture("mixed"),
+        &out,
+        "From the full run.",
+        &[],
+    );
+
+    let list = home.path().join("files.txt");
+    std::fs::write(&list, "src/parser.rs\n\nREADME.md\nsrc/gone.rs\n").unwrap();
+    let summaries = deep_run(
+        home.path(),
+        &common::fixture("mixed"),
+        &out,
+        "From the listed run.",
+        &["--files-from", list.to_str().unwrap()],
+    );
+    assert_eq!(summaries["src/parser.rs"], "From the listed run.");
+    assert_eq!(summaries["src/lib.rs"], "From the full run.");
+    assert_eq!(summaries["web/api.ts"], "From the full run.");
+
+    // The same list on stdin, where unmatched paths are reported
+    let (url, _) = mock_openai(SUMMARY);
+    cda(home.path())
+        .env("OPENAI_API_KEY", "sk-test")
+        .env("OPENAI_BASE_URL", &url)
+        .arg("analyze")
+        .arg(common::fixture("mixed"))
+        .args(["--provider", "openai", "--format", "json"])
+        .args(["--files-from", "-"])
+        .arg("--output")
+        .arg(&out)
+        .write_stdin("src/parser.rs\nREADME.md\nsrc/gone.rs\n")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("3 paths read from stdin, 1 of"))
+        .stderr(predicate::str::contains(
+            "README.md, src/gone.rs (not found)",
+        ));
+}
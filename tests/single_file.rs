@@ -0,0 +1,58 @@
+//! `cda analyze` on a single file writes the output of that file alone
+
+use std::fs;
+
+mod common;
+use common::cda;
+
+#[test]
+fn test_analyze_single_file() {
+    let home = tempfile::tempdir().unwrap();
+    let out = tempfile::tempdir().unwrap();
+    let fixture = common::fixture("mixed");
+    // A page from an earlier run of the whole project
+    fs::create_dir_all(out.path().join("modules/web")).unwrap();
+    fs::write(out.path().join("modules/web/api.ts.md"), "# api\n").unwrap();
+
+    cda(home.path())
+        .args(["analyze", "--mode", "static", "--output"])
+        .arg(out.path())
+        .arg(fixture.join("src/parser.rs"))
+        .assert()
+        .code(0);
+
+    let json = fs::read_to_string(out.path().join("analysis.json")).unwrap();
+    let analysis: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let modules = analysis["modules"].as_array().unwrap();
+    assert_eq!(modules.len(), 1);
+    assert_eq!(modules[0]["path"], "parser.rs");
+    assert!(!modules[0]["exports"].as_array().unwrap().is_empty());
+    // Nothing else was analyzed, so nothing internal resolves
+    let dependencies = analysis["cross_reference"]["dependencies"]
+        .as_array()
+        .unwrap();
+    assert!(dependencies
+        .iter()
+        .all(|d| d["depends_on"].as_array().unwrap().is_empty()));
+
+    let page = fs::read_to_string(out.path().join("modules/parser.rs.md")).unwrap();
+    assert!(page.starts_with("# parser"));
+    assert!(out.path().join("CODEBASE.md").is_file());
+    assert!(out.path().join("modules/web/api.ts.md").is_file());
+}
+
+#[test]
+fn test_analyze_single_file_rejects_other_files() {
+    let home = tempfile::tempdir().unwrap();
+    let out = tempfile::tempdir().unwrap();
+    let fixture = common::fixture("mixed");
+
+    let assert = cda(home.path())
+        .args(["analyze", "--mode", "static", "--output"])
+        .arg(out.path())
+        .arg(fixture.join("README.md"))
+        .assert()
+        .failure();
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr).to_string();
+    assert!(stderr.contains("is not a source file"), "{}", stderr);
+}